@@ -0,0 +1,42 @@
+//! How much is left in a single-cell LiPo on VSYS.
+
+use rp_hal_boards_common::filters::{Exponential, Filter};
+
+/// Resting voltage of a LiPo cell at 0 %, 10 %, ... 100 % charge, in
+/// millivolts. Under the logger's load it reads a little lower.
+const CURVE_MV: [u32; 11] = [
+    3300, 3600, 3690, 3740, 3770, 3790, 3820, 3870, 3920, 4000, 4200,
+];
+
+/// Smooths the VSYS readings, which jump as the SD card and the GPS draw
+/// current.
+pub struct Battery {
+    filter: Exponential,
+}
+
+impl Battery {
+    pub const fn new() -> Self {
+        Battery {
+            filter: Exponential::new(3),
+        }
+    }
+
+    /// Add a reading, returning the smoothed voltage in millivolts.
+    pub fn update(&mut self, vsys_mv: u32) -> u32 {
+        self.filter.update(vsys_mv as i32) as u32
+    }
+}
+
+/// Charge left at `mv`, from 0 to 100 %.
+pub fn percent(mv: u32) -> u8 {
+    if mv <= CURVE_MV[0] {
+        return 0;
+    }
+    for (step, pair) in CURVE_MV.windows(2).enumerate() {
+        let (low, high) = (pair[0], pair[1]);
+        if mv < high {
+            return (step as u32 * 10 + (mv - low) * 10 / (high - low)) as u8;
+        }
+    }
+    100
+}
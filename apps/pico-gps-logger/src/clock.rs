@@ -0,0 +1,43 @@
+//! The time from the GPS, for stamping the files on the SD card.
+
+use core::cell::Cell;
+
+use critical_section::Mutex;
+use embedded_sdmmc::{TimeSource, Timestamp};
+use rp_hal_boards_common::nmea::{Date, Time};
+
+/// The last date and time the receiver sent.
+static NOW: Mutex<Cell<Option<(Date, Time)>>> = Mutex::new(Cell::new(None));
+
+/// Record the time from a sentence.
+pub fn set(date: Date, time: Time) {
+    critical_section::with(|cs| NOW.borrow(cs).set(Some((date, time))));
+}
+
+/// The last time the GPS reported, or midnight on 1 January 2000 before it
+/// has.
+#[derive(Default)]
+pub struct GpsClock;
+
+impl TimeSource for GpsClock {
+    fn get_timestamp(&self) -> Timestamp {
+        match critical_section::with(|cs| NOW.borrow(cs).get()) {
+            Some((date, time)) => Timestamp {
+                year_since_1970: date.year.saturating_sub(1970) as u8,
+                zero_indexed_month: date.month.saturating_sub(1),
+                zero_indexed_day: date.day.saturating_sub(1),
+                hours: time.hour,
+                minutes: time.minute,
+                seconds: time.second,
+            },
+            None => Timestamp {
+                year_since_1970: 30,
+                zero_indexed_month: 0,
+                zero_indexed_day: 0,
+                hours: 0,
+                minutes: 0,
+                seconds: 0,
+            },
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! # Pico GPS Logger
+//!
+//! A handheld track logger: a Pico with a Pimoroni Pico Display Pack, a GPS
+//! receiver and an SD card, running from a LiPo cell.
+//!
+//! The screen shows the time, the position, speed and altitude, whether a
+//! track is being recorded, and the battery. Button A starts and stops
+//! recording, each time to a new `TRKnnnn.CSV` on the card (see [`track`]),
+//! and button B turns the backlight off and on. The LED is red while
+//! recording, green while there's a fix to record, and yellow after an SD
+//! card error.
+//!
+//! The receiver's NMEA sentences are parsed with
+//! `rp_hal_boards_common::nmea`. Its bytes are collected under interrupt, so
+//! none are lost while the card or the screen is busy.
+//!
+//! Wiring, besides the Display Pack:
+//!
+//! | Pico   | To                                 |
+//! |--------|------------------------------------|
+//! | GPIO0  | GPS RX, though nothing is sent     |
+//! | GPIO1  | GPS TX                             |
+//! | GPIO22 | SD card CS                         |
+//! | GPIO26 | SD card SCK                        |
+//! | GPIO27 | SD card MOSI                       |
+//! | GPIO28 | SD card MISO                       |
+//! | VSYS   | LiPo, through a charger or a diode |
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+mod battery;
+mod clock;
+mod screen;
+mod track;
+
+use core::cell::RefCell;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO and serial traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_hal::serial::Read;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// Our interrupt macro
+use pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use fugit::{HertzU32, RateExtU32};
+use hal::Clock;
+
+use critical_section::Mutex;
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+use embedded_sdmmc::{SdCard, SdCardError, VolumeManager};
+use hal::gpio::{
+    bank0::{Gpio0, Gpio1, Gpio22},
+    FunctionSpi, FunctionUart, Pin, PushPullOutput,
+};
+use hal::spi;
+use hal::uart::{DataBits, StopBits, UartConfig, UartPeripheral};
+use heapless::Deque;
+use st7789::{Orientation, ST7789};
+
+use rp_hal_boards_common::energy_budget::read_vsys_mv;
+use rp_hal_boards_common::nmea::{Gga, Parser, Rmc, Sentence};
+use rp_pico::display_pack::{DisplayPack, DisplayPackPins, LCD_OFFSET};
+
+use battery::Battery;
+use clock::GpsClock;
+use screen::{Logging, Status};
+use track::Tracks;
+
+/// The receiver's baud rate. Most modules, such as the u-blox NEO-6M, start
+/// at 9600.
+const GPS_BAUD: u32 = 9600;
+
+/// Time between screen updates, in microseconds.
+const REDRAW_US: u64 = 1_000_000;
+
+/// How long a button must stay released before another press counts, in
+/// microseconds.
+const DEBOUNCE_US: u64 = 50_000;
+
+type Card = SdCard<
+    spi::Spi<spi::Enabled, pac::SPI1, 8>,
+    Pin<Gpio22, PushPullOutput>,
+    cortex_m::delay::Delay,
+>;
+
+type GpsPins = (Pin<Gpio0, FunctionUart>, Pin<Gpio1, FunctionUart>);
+
+type GpsUart = UartPeripheral<hal::uart::Enabled, pac::UART0, GpsPins>;
+
+/// The UART, until the interrupt takes it.
+static GPS_UART: Mutex<RefCell<Option<GpsUart>>> = Mutex::new(RefCell::new(None));
+
+/// Bytes from the receiver, waiting to be parsed. A second of sentences is
+/// usually under 500 bytes.
+static GPS_BYTES: Mutex<RefCell<Deque<u8, 512>>> = Mutex::new(RefCell::new(Deque::new()));
+
+/// The Display Pack doesn't bring out the LCD's reset line.
+struct NoResetPin;
+
+impl OutputPin for NoResetPin {
+    type Error = ();
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// A fixed-point number, `.0` in units of ten to the minus `.1`.
+pub struct Fixed(pub i32, pub u32);
+
+impl core::fmt::Display for Fixed {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let value = self.0.unsigned_abs();
+        if self.1 == 0 {
+            return write!(f, "{}{}", sign, value);
+        }
+        let scale = 10u32.pow(self.1);
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            value / scale,
+            value % scale,
+            width = self.1 as usize
+        )
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The LCD needs this to start, and then the SD card keeps it
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The timer paces everything else
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let DisplayPack {
+        a, b, mut led, lcd, ..
+    } = DisplayPack::new(DisplayPackPins {
+        gpio6: pins.gpio6,
+        gpio7: pins.gpio7,
+        gpio8: pins.gpio8,
+        gpio12: pins.gpio12,
+        gpio13: pins.gpio13,
+        gpio14: pins.gpio14,
+        gpio15: pins.gpio15,
+        gpio16: pins.gpio16,
+        gpio17: pins.gpio17,
+        gpio18: pins.gpio18,
+        gpio19: pins.gpio19,
+        gpio20: pins.gpio20,
+    });
+
+    let (interface, mut backlight) = DisplayPack::spi_interface(
+        lcd,
+        pac.SPI0,
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+    );
+    let mut lcd = ST7789::new(interface, NoResetPin, 240, 320);
+    lcd.init(&mut delay).unwrap();
+    lcd.set_orientation(Orientation::Landscape).unwrap();
+    lcd.clear(Rgb565::BLACK).unwrap();
+    // Draw in panel coordinates rather than controller coordinates
+    let mut screen = lcd.translated(Point::new(LCD_OFFSET.0, LCD_OFFSET.1));
+
+    // The SD card on SPI1, starting slow for its initialisation
+    let _sd_sclk = pins.gpio26.into_mode::<FunctionSpi>();
+    let _sd_mosi = pins.gpio27.into_mode::<FunctionSpi>();
+    let _sd_miso = pins.gpio28.into_mode::<FunctionSpi>();
+    let sd_cs = pins.gpio22.into_push_pull_output();
+    let sd_spi = spi::Spi::<_, _, 8>::new(pac.SPI1).init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        400.kHz(),
+        &embedded_hal::spi::MODE_0,
+    );
+    let card = SdCard::new(sd_spi, sd_cs, delay);
+    let mut tracks = Tracks::new(VolumeManager::new(card, GpsClock));
+
+    // The receiver on UART0, read under interrupt
+    let gps_pins = (
+        pins.gpio0.into_mode::<FunctionUart>(),
+        pins.gpio1.into_mode::<FunctionUart>(),
+    );
+    let mut gps = UartPeripheral::new(pac.UART0, gps_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(GPS_BAUD.Hz(), DataBits::Eight, None, StopBits::One),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+    gps.enable_rx_interrupt();
+    critical_section::with(|cs| {
+        GPS_UART.borrow(cs).replace(Some(gps));
+    });
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::UART0_IRQ);
+    }
+
+    // VSYS, and whether USB is powering it
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut vsys_pin = pins.voltage_monitor.into_floating_input();
+    let vbus = pins.vbus_detect.into_floating_input();
+    let mut battery = Battery::new();
+    let mut vsys_mv = battery.update(read_vsys_mv(&mut adc, &mut vsys_pin));
+
+    let mut parser = Parser::new();
+    let mut rmc: Option<Rmc> = None;
+    let mut gga: Option<Gga> = None;
+    let mut failed = false;
+    let mut backlight_on = true;
+
+    // When each button was last seen pressed
+    let mut a_pressed: Option<u64> = None;
+    let mut b_pressed: Option<u64> = None;
+    let mut next_redraw = 0;
+
+    loop {
+        let now = timer.get_counter().ticks();
+        let mut redraw = false;
+
+        let bytes = critical_section::with(|cs| GPS_BYTES.borrow(cs).replace(Deque::new()));
+        for &byte in bytes.iter() {
+            match parser.push(byte) {
+                Some(Sentence::Rmc(sentence)) => {
+                    if let (Some(date), Some(time)) = (sentence.date, sentence.time) {
+                        clock::set(date, time);
+                    }
+                    if let Some(point) = point(&sentence, gga.as_ref()) {
+                        if tracks.log(&point).is_err() {
+                            failed = true;
+                            redraw = true;
+                        }
+                    }
+                    rmc = Some(sentence);
+                }
+                Some(Sentence::Gga(sentence)) => gga = Some(sentence),
+                _ => {}
+            }
+        }
+
+        if pressed(a.is_low().unwrap(), &mut a_pressed, now) {
+            let result = if tracks.recording().is_some() {
+                tracks.stop()
+            } else {
+                start(&mut tracks, clocks.peripheral_clock.freq())
+            };
+            failed = result.is_err();
+            redraw = true;
+        }
+        if pressed(b.is_low().unwrap(), &mut b_pressed, now) {
+            backlight_on = !backlight_on;
+            backlight.set_state(backlight_on.into()).unwrap();
+        }
+
+        if now >= next_redraw {
+            next_redraw = now + REDRAW_US;
+            vsys_mv = battery.update(read_vsys_mv(&mut adc, &mut vsys_pin));
+            redraw = true;
+        }
+        if !redraw {
+            continue;
+        }
+
+        let has_fix = matches!(rmc, Some(Rmc { valid: true, .. }));
+        let logging = match tracks.recording() {
+            Some((name, points)) => Logging::Recording { name, points },
+            None if failed => Logging::Failed,
+            None => Logging::Idle,
+        };
+        match logging {
+            Logging::Recording { .. } => led.set(true, false, false),
+            Logging::Failed => led.set(true, true, false),
+            Logging::Idle => led.set(false, has_fix, false),
+        }
+        let status = Status {
+            rmc: rmc.as_ref(),
+            gga: gga.as_ref(),
+            logging,
+            vsys_mv,
+            on_usb: vbus.is_high().unwrap(),
+        };
+        screen::draw(&mut screen, &status).unwrap();
+    }
+}
+
+/// Whether a button has just been pressed, given whether it is down now and
+/// when it was last seen down.
+fn pressed(down: bool, last_down: &mut Option<u64>, now: u64) -> bool {
+    if !down {
+        return false;
+    }
+    let new_press = match *last_down {
+        Some(then) => now - then > DEBOUNCE_US,
+        None => true,
+    };
+    *last_down = Some(now);
+    new_press
+}
+
+/// Bring the card up, if it isn't already, and start a new track.
+fn start(
+    tracks: &mut Tracks<Card, GpsClock>,
+    peripheral_clock: HertzU32,
+) -> Result<(), embedded_sdmmc::Error<SdCardError>> {
+    tracks
+        .device()
+        .spi(|spi| spi.set_baudrate(peripheral_clock, 400.kHz()));
+    tracks
+        .device()
+        .num_bytes()
+        .map_err(embedded_sdmmc::Error::DeviceError)?;
+    // Now that the card is initialised, the clock can go faster
+    tracks
+        .device()
+        .spi(|spi| spi.set_baudrate(peripheral_clock, 16.MHz()));
+    tracks.start()
+}
+
+/// The point an RMC sentence gives, with the satellites and altitude from
+/// the latest GGA, if there is a fix.
+fn point(rmc: &Rmc, gga: Option<&Gga>) -> Option<track::Point> {
+    if !rmc.valid {
+        return None;
+    }
+    Some(track::Point {
+        date: rmc.date?,
+        time: rmc.time?,
+        latitude: rmc.latitude?,
+        longitude: rmc.longitude?,
+        altitude: gga.and_then(|gga| gga.altitude),
+        speed: rmc.speed,
+        course: rmc.course,
+        satellites: gga.map_or(0, |gga| gga.satellites),
+        hdop: gga.and_then(|gga| gga.hdop),
+    })
+}
+
+#[interrupt]
+fn UART0_IRQ() {
+    // Taken from `GPS_UART` on the first interrupt, and only used here after
+    static mut GPS: Option<GpsUart> = None;
+
+    if GPS.is_none() {
+        critical_section::with(|cs| {
+            *GPS = GPS_UART.borrow(cs).take();
+        });
+    }
+
+    if let Some(gps) = GPS {
+        critical_section::with(|cs| {
+            let mut bytes = GPS_BYTES.borrow(cs).borrow_mut();
+            // Reading empties the FIFO, which clears the interrupt. If the
+            // main loop has fallen this far behind, drop the bytes.
+            while let Ok(byte) = gps.read() {
+                let _ = bytes.push_back(byte);
+            }
+        });
+    }
+}
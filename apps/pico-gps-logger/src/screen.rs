@@ -0,0 +1,144 @@
+//! The status screen, six lines of text on the Display Pack:
+//!
+//! ```text
+//! 09:41:07 UTC    9 sats
+//! Lat 51.50074
+//! Lon -0.12784
+//! 4.6 km/h  35 m
+//! REC TRK0003.CSV   128
+//! Battery 3.91 V  76%
+//! ```
+
+use core::fmt::Write;
+
+use embedded_graphics::{
+    mono_font::{ascii::FONT_10X20, MonoTextStyle, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use heapless::String;
+use rp_hal_boards_common::nmea::{Gga, Rmc};
+
+use crate::battery;
+use crate::track::kmh_tenths;
+use crate::Fixed;
+
+/// Characters across the screen.
+const COLUMNS: usize = 24;
+
+/// Pixels from the top of one line to the next.
+const LINE_HEIGHT: i32 = 22;
+
+/// What happens to the fixes.
+pub enum Logging<'a> {
+    /// Not recording; button A starts.
+    Idle,
+    /// Writing to the file `name`.
+    Recording { name: &'a str, points: u32 },
+    /// The last start or write failed, usually for want of a card.
+    Failed,
+}
+
+/// Everything shown.
+pub struct Status<'a> {
+    pub rmc: Option<&'a Rmc>,
+    pub gga: Option<&'a Gga>,
+    pub logging: Logging<'a>,
+    /// Smoothed VSYS.
+    pub vsys_mv: u32,
+    /// Whether USB power is present, in which case VSYS says nothing about
+    /// the battery.
+    pub on_usb: bool,
+}
+
+/// Draw every line, padded to the full width so old text is covered.
+pub fn draw<D>(target: &mut D, status: &Status) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let fix = status
+        .rmc
+        .filter(|rmc| rmc.valid)
+        .and_then(|rmc| Some((rmc.latitude?, rmc.longitude?)));
+
+    let mut lines: [String<COLUMNS>; 6] = Default::default();
+    // The lines are all written to fit, and a longer one is just cut short
+    match status.rmc.and_then(|rmc| rmc.time) {
+        Some(time) => {
+            let _ = write!(
+                lines[0],
+                "{:02}:{:02}:{:02} UTC",
+                time.hour, time.minute, time.second
+            );
+        }
+        None => {
+            let _ = write!(lines[0], "--:--:-- UTC");
+        }
+    }
+    let satellites = status.gga.map_or(0, |gga| gga.satellites);
+    let _ = write!(lines[0], "   {:>2} sats", satellites);
+
+    match fix {
+        Some((latitude, longitude)) => {
+            // Five places is about a metre
+            let _ = write!(lines[1], "Lat {}", Fixed(latitude / 100, 5));
+            let _ = write!(lines[2], "Lon {}", Fixed(longitude / 100, 5));
+            let speed = status.rmc.and_then(|rmc| rmc.speed).unwrap_or(0);
+            let _ = write!(lines[3], "{} km/h", Fixed(kmh_tenths(speed), 1));
+            if let Some(altitude) = status.gga.and_then(|gga| gga.altitude) {
+                let _ = write!(lines[3], "  {} m", altitude / 10);
+            }
+        }
+        None => {
+            let _ = write!(lines[1], "Waiting for a fix");
+        }
+    }
+
+    let log_colour = match status.logging {
+        Logging::Idle => {
+            let _ = write!(lines[4], "A: start logging");
+            Rgb565::WHITE
+        }
+        Logging::Recording { name, points } => {
+            let _ = write!(lines[4], "REC {} {:>5}", name, points);
+            Rgb565::RED
+        }
+        Logging::Failed => {
+            let _ = write!(lines[4], "SD card error");
+            Rgb565::YELLOW
+        }
+    };
+
+    if status.on_usb {
+        let _ = write!(lines[5], "USB {} V", Fixed(status.vsys_mv as i32 / 10, 2));
+    } else {
+        let _ = write!(
+            lines[5],
+            "Battery {} V {:>3}%",
+            Fixed(status.vsys_mv as i32 / 10, 2),
+            battery::percent(status.vsys_mv)
+        );
+    }
+
+    for (row, line) in lines.iter_mut().enumerate() {
+        while line.push(' ').is_ok() {}
+        let colour = if row == 4 { log_colour } else { Rgb565::WHITE };
+        Text::with_baseline(
+            line.as_str(),
+            Point::new(0, 2 + LINE_HEIGHT * row as i32),
+            style(colour),
+            Baseline::Top,
+        )
+        .draw(target)?;
+    }
+    Ok(())
+}
+
+fn style(colour: Rgb565) -> MonoTextStyle<'static, Rgb565> {
+    MonoTextStyleBuilder::new()
+        .font(&FONT_10X20)
+        .text_color(colour)
+        .background_color(Rgb565::BLACK)
+        .build()
+}
@@ -0,0 +1,265 @@
+//! Track files on the SD card.
+//!
+//! Each recording goes to a new `TRKnnnn.CSV` in the root directory, numbered
+//! on from the highest one already there, with a row per fix:
+//!
+//! ```text
+//! time,latitude,longitude,altitude_m,speed_kmh,course,satellites,hdop
+//! 2026-10-16T09:41:07Z,51.5007400,-0.1278400,35.2,4.6,271.50,9,0.9
+//! ```
+//!
+//! Fields the receiver left out are empty. The file is closed and reopened
+//! every [`SYNC_EVERY`] points, which writes its length to the directory, so
+//! pulling the power loses at most that many.
+
+use core::fmt::Write;
+
+use embedded_sdmmc::{
+    BlockDevice, Directory, Error, File, Mode, TimeSource, Volume, VolumeIdx, VolumeManager,
+};
+use heapless::String;
+use rp_hal_boards_common::nmea::{Date, Time};
+
+use crate::Fixed;
+
+/// Points written between closing and reopening the file.
+pub const SYNC_EVERY: u32 = 30;
+
+const HEADER: &str = "time,latitude,longitude,altitude_m,speed_kmh,course,satellites,hdop\n";
+
+/// One fix, in the units of [`nmea`](rp_hal_boards_common::nmea).
+pub struct Point {
+    pub date: Date,
+    pub time: Time,
+    pub latitude: i32,
+    pub longitude: i32,
+    /// Decimetres above sea level.
+    pub altitude: Option<i32>,
+    /// Hundredths of a knot.
+    pub speed: Option<u32>,
+    /// Hundredths of a degree.
+    pub course: Option<u32>,
+    pub satellites: u8,
+    /// Tenths.
+    pub hdop: Option<u16>,
+}
+
+impl Point {
+    fn to_csv(&self) -> String<96> {
+        let mut line = String::new();
+        // The line is sized for the longest values, so can't overflow
+        let _ = write!(
+            line,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z,{},{},",
+            self.date.year,
+            self.date.month,
+            self.date.day,
+            self.time.hour,
+            self.time.minute,
+            self.time.second,
+            Fixed(self.latitude, 7),
+            Fixed(self.longitude, 7),
+        );
+        if let Some(altitude) = self.altitude {
+            let _ = write!(line, "{}", Fixed(altitude, 1));
+        }
+        let _ = line.push(',');
+        if let Some(speed) = self.speed {
+            let _ = write!(line, "{}", Fixed(kmh_tenths(speed), 1));
+        }
+        let _ = line.push(',');
+        if let Some(course) = self.course {
+            let _ = write!(line, "{}", Fixed(course as i32, 2));
+        }
+        let _ = write!(line, ",{},", self.satellites);
+        if let Some(hdop) = self.hdop {
+            let _ = write!(line, "{}", Fixed(i32::from(hdop), 1));
+        }
+        let _ = line.push('\n');
+        line
+    }
+}
+
+/// Hundredths of a knot in tenths of a km/h.
+pub fn kmh_tenths(speed: u32) -> i32 {
+    (speed * 1852 / 10_000) as i32
+}
+
+/// The file a recording goes to, and where it is.
+struct Recording {
+    volume: Volume,
+    root: Directory,
+    file: File,
+    name: String<12>,
+    points: u32,
+    unsynced: u32,
+}
+
+/// Starts, writes and stops recordings on an SD card.
+pub struct Tracks<D, T>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    volumes: VolumeManager<D, T>,
+    recording: Option<Recording>,
+}
+
+impl<D, T> Tracks<D, T>
+where
+    D: BlockDevice,
+    T: TimeSource,
+{
+    pub fn new(volumes: VolumeManager<D, T>) -> Self {
+        Tracks {
+            volumes,
+            recording: None,
+        }
+    }
+
+    /// The card, to change its SPI speed.
+    pub fn device(&mut self) -> &mut D {
+        self.volumes.device()
+    }
+
+    /// The name of the file being written, and the points in it so far.
+    pub fn recording(&self) -> Option<(&str, u32)> {
+        self.recording
+            .as_ref()
+            .map(|recording| (recording.name.as_str(), recording.points))
+    }
+
+    /// Start a new track file on the first partition.
+    pub fn start(&mut self) -> Result<(), Error<D::Error>> {
+        self.stop()?;
+        let mut volume = self.volumes.get_volume(VolumeIdx(0))?;
+        let root = self.volumes.open_root_dir(&volume)?;
+        match self.create(&mut volume, &root) {
+            Ok((file, name)) => {
+                self.recording = Some(Recording {
+                    volume,
+                    root,
+                    file,
+                    name,
+                    points: 0,
+                    unsynced: 0,
+                });
+                Ok(())
+            }
+            Err(error) => {
+                self.volumes.close_dir(&volume, root);
+                Err(error)
+            }
+        }
+    }
+
+    /// Add `point` to the track, if one is being recorded.
+    ///
+    /// After an error the recording is over, and the file holds the points
+    /// up to the last time it was closed.
+    pub fn log(&mut self, point: &Point) -> Result<(), Error<D::Error>> {
+        let mut recording = match self.recording.take() {
+            Some(recording) => recording,
+            None => return Ok(()),
+        };
+        let line = point.to_csv();
+        if let Err(error) =
+            self.volumes
+                .write(&mut recording.volume, &mut recording.file, line.as_bytes())
+        {
+            self.abandon(recording);
+            return Err(error);
+        }
+        recording.points += 1;
+        recording.unsynced += 1;
+        if recording.unsynced >= SYNC_EVERY {
+            let Recording {
+                mut volume,
+                root,
+                file,
+                name,
+                points,
+                ..
+            } = recording;
+            if let Err(error) = self.volumes.close_file(&volume, file) {
+                self.volumes.close_dir(&volume, root);
+                return Err(error);
+            }
+            match self.volumes.open_file_in_dir(
+                &mut volume,
+                &root,
+                name.as_str(),
+                Mode::ReadWriteAppend,
+            ) {
+                Ok(file) => {
+                    recording = Recording {
+                        volume,
+                        root,
+                        file,
+                        name,
+                        points,
+                        unsynced: 0,
+                    };
+                }
+                Err(error) => {
+                    self.volumes.close_dir(&volume, root);
+                    return Err(error);
+                }
+            }
+        }
+        self.recording = Some(recording);
+        Ok(())
+    }
+
+    /// Close the track file, if one is open.
+    pub fn stop(&mut self) -> Result<(), Error<D::Error>> {
+        if let Some(recording) = self.recording.take() {
+            let result = self.volumes.close_file(&recording.volume, recording.file);
+            self.volumes.close_dir(&recording.volume, recording.root);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Create the next numbered file, with its header row.
+    fn create(
+        &mut self,
+        volume: &mut Volume,
+        root: &Directory,
+    ) -> Result<(File, String<12>), Error<D::Error>> {
+        let mut last = 0;
+        self.volumes.iterate_dir(volume, root, |entry| {
+            if let Some(number) = track_number(entry.name.base_name(), entry.name.extension()) {
+                last = last.max(number);
+            }
+        })?;
+        let mut name = String::new();
+        // Kept to four digits; once TRK9999 exists, creating it again fails
+        let _ = write!(name, "TRK{:04}.CSV", (last + 1).min(9999));
+        let mut file =
+            self.volumes
+                .open_file_in_dir(volume, root, name.as_str(), Mode::ReadWriteCreate)?;
+        if let Err(error) = self.volumes.write(volume, &mut file, HEADER.as_bytes()) {
+            let _ = self.volumes.close_file(volume, file);
+            return Err(error);
+        }
+        Ok((file, name))
+    }
+
+    /// Close what's left of a recording after an error.
+    fn abandon(&mut self, recording: Recording) {
+        let _ = self.volumes.close_file(&recording.volume, recording.file);
+        self.volumes.close_dir(&recording.volume, recording.root);
+    }
+}
+
+/// The number of a `TRKnnnn.CSV` file.
+fn track_number(base_name: &[u8], extension: &[u8]) -> Option<u16> {
+    let base_name = core::str::from_utf8(base_name).ok()?.trim_end();
+    let extension = core::str::from_utf8(extension).ok()?.trim_end();
+    let digits = base_name.strip_prefix("TRK")?;
+    if extension != "CSV" || digits.len() != 4 {
+        return None;
+    }
+    digits.parse().ok()
+}
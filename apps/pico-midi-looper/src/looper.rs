@@ -0,0 +1,302 @@
+//! The loop: recording, overdubbing and playing back timestamped messages.
+//!
+//! Time is counted in ticks of [`PPQN`] to the quarter note. The first
+//! recording sets the loop's length, rounded to whole bars, up to
+//! [`MAX_BARS`]. Notes are quantized to [`GRID`] as they are recorded, each
+//! Note Off moving as far as its Note On so the note keeps its length.
+//! Other messages, such as controllers and pitch bend, keep their timing.
+//!
+//! ```text
+//!            REC            REC           REC            REC
+//! Empty ---------> Recording ---> Playing ---> Overdubbing ---> Playing
+//!                                  |   ^
+//!                             PLAY |   | PLAY
+//!                                  v   |
+//!                                 Stopped
+//! ```
+//!
+//! PLAY also stops a recording or an overdub, and REC while stopped starts
+//! an overdub from the top of the loop.
+
+use heapless::Vec;
+
+use crate::midi::Message;
+
+/// Ticks to the quarter note.
+pub const PPQN: u16 = 96;
+
+/// Ticks to the bar, in 4/4.
+pub const TICKS_PER_BAR: u16 = 4 * PPQN;
+
+/// The longest loop, in bars.
+pub const MAX_BARS: u16 = 8;
+
+/// Notes are moved to the nearest sixteenth.
+pub const GRID: u16 = PPQN / 4;
+
+/// Most messages in a loop.
+pub const MAX_EVENTS: usize = 384;
+
+/// A message and when in the loop it plays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub tick: u16,
+    pub message: Message,
+}
+
+/// What the looper is doing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// No loop yet.
+    Empty,
+    /// Recording the first pass, which sets the length.
+    Recording,
+    Playing,
+    /// Playing and recording over the top.
+    Overdubbing,
+    Stopped,
+}
+
+/// Notes, one bit each, on each channel.
+type Notes = [u128; 16];
+
+/// The loop and the playhead.
+pub struct Looper {
+    /// Sorted by tick.
+    events: Vec<Event, MAX_EVENTS>,
+    /// The loop's length in ticks, or 0 before the first recording ends.
+    length: u16,
+    /// The next tick to play.
+    position: u16,
+    mode: Mode,
+    /// Notes recorded as started and not yet stopped; each needs room
+    /// saved for its Note Off.
+    held: Notes,
+    /// How far each held note's Note On was moved, in ticks.
+    shifts: [[i8; 128]; 16],
+    /// Notes the loop has started and not yet stopped.
+    sounding: Notes,
+}
+
+impl Looper {
+    /// An empty looper.
+    pub fn new() -> Self {
+        Looper {
+            events: Vec::new(),
+            length: 0,
+            position: 0,
+            mode: Mode::Empty,
+            held: [0; 16],
+            shifts: [[0; 128]; 16],
+            sounding: [0; 16],
+        }
+    }
+
+    /// A stopped looper with a saved loop of `length` ticks, whose `events`
+    /// are sorted by tick.
+    pub fn with_loop(events: Vec<Event, MAX_EVENTS>, length: u16) -> Self {
+        let mut looper = Looper::new();
+        if length != 0 && length <= MAX_BARS * TICKS_PER_BAR {
+            looper.events = events;
+            looper.length = length;
+            looper.mode = Mode::Stopped;
+        }
+        looper
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The next tick to play.
+    pub fn position(&self) -> u16 {
+        self.position
+    }
+
+    /// The loop's length in ticks, 0 while there is none.
+    pub fn length(&self) -> u16 {
+        self.length
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// The REC button.
+    pub fn record(&mut self) {
+        match self.mode {
+            Mode::Empty => {
+                self.position = 0;
+                self.mode = Mode::Recording;
+            }
+            Mode::Recording => {
+                self.finish_recording();
+                self.mode = Mode::Playing;
+            }
+            Mode::Playing => self.mode = Mode::Overdubbing,
+            Mode::Overdubbing => {
+                self.release_held();
+                self.mode = Mode::Playing;
+            }
+            Mode::Stopped => {
+                self.position = 0;
+                self.mode = Mode::Overdubbing;
+            }
+        }
+    }
+
+    /// The PLAY button.
+    pub fn play(&mut self, out: impl FnMut(Message)) {
+        match self.mode {
+            Mode::Empty => {}
+            Mode::Recording | Mode::Playing | Mode::Overdubbing => self.stop(out),
+            Mode::Stopped => {
+                self.position = 0;
+                self.mode = Mode::Playing;
+            }
+        }
+    }
+
+    /// Stop, silence the loop's notes, and forget it.
+    pub fn clear(&mut self, out: impl FnMut(Message)) {
+        self.silence(out);
+        *self = Looper::new();
+    }
+
+    /// Stop, silencing the loop's notes, ready to play from the top. A
+    /// recording or an overdub ends as if by REC.
+    pub fn stop(&mut self, out: impl FnMut(Message)) {
+        match self.mode {
+            Mode::Recording => self.finish_recording(),
+            Mode::Overdubbing => self.release_held(),
+            _ => {}
+        }
+        self.silence(out);
+        self.position = 0;
+        if self.mode != Mode::Empty {
+            self.mode = Mode::Stopped;
+        }
+    }
+
+    /// Take a message played live, recording it if recording.
+    pub fn input(&mut self, message: Message) {
+        if !matches!(self.mode, Mode::Recording | Mode::Overdubbing) {
+            return;
+        }
+        let channel = usize::from(message.channel());
+        let mut tick = self.position;
+        if let Some(note) = message.started_note() {
+            // Leave room for every held note's Note Off, this one's included
+            if self.events.len() + self.held_count() + 2 > MAX_EVENTS {
+                return;
+            }
+            let quantized = (tick + GRID / 2) / GRID * GRID;
+            self.shifts[channel][usize::from(note)] =
+                (i32::from(quantized) - i32::from(tick)) as i8;
+            self.held[channel] |= 1 << note;
+            tick = quantized;
+        } else if let Some(note) = message.stopped_note() {
+            // Only notes whose start was recorded
+            if self.held[channel] & (1 << note) == 0 {
+                return;
+            }
+            self.held[channel] &= !(1 << note);
+            let shift = self.shifts[channel][usize::from(note)];
+            tick = (i32::from(tick) + i32::from(shift)).max(0) as u16;
+        } else if self.events.len() + self.held_count() + 1 > MAX_EVENTS {
+            return;
+        }
+        if self.length != 0 {
+            tick %= self.length;
+        }
+        self.insert(Event { tick, message });
+    }
+
+    /// Advance one tick, playing what falls on it.
+    pub fn tick(&mut self, mut out: impl FnMut(Message)) {
+        match self.mode {
+            Mode::Empty | Mode::Stopped => return,
+            Mode::Recording => {
+                self.position += 1;
+                if self.position == MAX_BARS * TICKS_PER_BAR {
+                    self.finish_recording();
+                    self.mode = Mode::Playing;
+                }
+                return;
+            }
+            Mode::Playing | Mode::Overdubbing => {}
+        }
+
+        let position = self.position;
+        let start = self.events.partition_point(|e| e.tick < position);
+        for event in self.events[start..]
+            .iter()
+            .take_while(|e| e.tick == position)
+        {
+            let message = event.message;
+            let channel = usize::from(message.channel());
+            if let Some(note) = message.started_note() {
+                self.sounding[channel] |= 1 << note;
+            } else if let Some(note) = message.stopped_note() {
+                self.sounding[channel] &= !(1 << note);
+            }
+            out(message);
+        }
+        self.position = (self.position + 1) % self.length;
+    }
+
+    /// End the first recording, setting the length to the nearest whole
+    /// bar, at least one, and wrapping anything recorded after the end
+    /// round to the start.
+    fn finish_recording(&mut self) {
+        let bars = ((self.position + TICKS_PER_BAR / 2) / TICKS_PER_BAR).clamp(1, MAX_BARS);
+        self.release_held();
+        self.length = bars * TICKS_PER_BAR;
+        self.position %= self.length;
+        // Insert them again in order, so messages on the same tick keep
+        // theirs
+        let recorded = core::mem::take(&mut self.events);
+        for mut event in recorded {
+            event.tick %= self.length;
+            self.insert(event);
+        }
+    }
+
+    /// Record a Note Off for every note still held, so none is left
+    /// sounding when the loop plays back.
+    fn release_held(&mut self) {
+        for channel in 0..16u8 {
+            let index = usize::from(channel);
+            while self.held[index] != 0 {
+                let note = self.held[index].trailing_zeros() as u8;
+                self.input(Message::note_off(channel, note));
+            }
+        }
+    }
+
+    /// Send a Note Off for every note the loop has started.
+    fn silence(&mut self, mut out: impl FnMut(Message)) {
+        for (channel, notes) in self.sounding.iter_mut().enumerate() {
+            while *notes != 0 {
+                let note = notes.trailing_zeros() as u8;
+                *notes &= !(1 << note);
+                out(Message::note_off(channel as u8, note));
+            }
+        }
+    }
+
+    fn held_count(&self) -> usize {
+        self.held
+            .iter()
+            .map(|notes| notes.count_ones() as usize)
+            .sum()
+    }
+
+    /// Add `event` after any others on the same tick.
+    fn insert(&mut self, event: Event) {
+        let at = self.events.partition_point(|e| e.tick <= event.tick);
+        if self.events.push(event).is_ok() {
+            self.events[at..].rotate_right(1);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! # Pico MIDI Looper
+//!
+//! A MIDI phrase looper: play into it from a keyboard on USB or DIN MIDI,
+//! and it plays the phrase back round and round, quantized to sixteenths,
+//! while you play over the top or record more layers.
+//!
+//! * REC starts the first recording; pressing it again sets the loop's
+//!   length to the nearest whole bar and starts playing it. After that, REC
+//!   starts and ends overdubs.
+//! * PLAY stops and starts playback, from the top of the loop.
+//! * CLEAR forgets the loop.
+//! * SAVE stops playback and keeps the loop in flash, to be loaded at the
+//!   next power-up.
+//!
+//! Everything played in goes straight out again, on both USB and DIN, along
+//! with the loop. The LED flashes on each beat, and the red LED is lit while
+//! recording. The looper keeps its own tempo, [`BPM`]; it doesn't follow
+//! MIDI clock.
+//!
+//! The loop is kept in [`looper`], saved with [`storage`] through
+//! `rp_hal_boards_common::datalog`, and clocked by a
+//! `rp_hal_boards_common::schedule::Scheduler`.
+//!
+//! Wiring:
+//!
+//! | Pico   | To                                                        |
+//! |--------|-----------------------------------------------------------|
+//! | GPIO0  | DIN MIDI OUT pin 5, through 10 Ω; pin 4 to 3V3 via 33 Ω   |
+//! | GPIO1  | DIN MIDI IN, from a 6N138 optocoupler's output            |
+//! | GPIO10 | REC button to ground                                      |
+//! | GPIO11 | PLAY button to ground                                     |
+//! | GPIO12 | CLEAR button to ground                                    |
+//! | GPIO13 | SAVE button to ground                                     |
+//! | GPIO15 | Red LED, through 330 Ω to ground                          |
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+mod looper;
+mod midi;
+mod storage;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use fugit::RateExtU32;
+use hal::Clock;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use hal::gpio::{
+    bank0::{Gpio0, Gpio1, Gpio15, Gpio25},
+    DynPin, FunctionUart, Pin, PushPullOutput,
+};
+use hal::uart::{DataBits, StopBits, UartConfig, UartPeripheral};
+
+use rp_hal_boards_common::datalog::Log;
+use rp_hal_boards_common::keyboard::Debouncer;
+use rp_hal_boards_common::schedule::{Duration, Instant, Monotonic, Scheduler};
+use rp_hal_boards_common::usb_midi::MidiClass;
+
+use looper::{Looper, Mode, PPQN};
+use midi::{Message, Parser};
+
+/// The tempo, in beats per minute.
+const BPM: u32 = 120;
+
+/// Length of a tick, in microseconds.
+const TICK_US: u64 = 60_000_000 / (BPM as u64 * PPQN as u64);
+
+/// The DIN MIDI baud rate.
+const MIDI_BAUD: u32 = 31_250;
+
+const BUTTONS: usize = 4;
+const REC: usize = 0;
+const PLAY: usize = 1;
+const CLEAR: usize = 2;
+const SAVE: usize = 3;
+
+/// The DIN MIDI UART's pins.
+type UartPins = (Pin<Gpio0, FunctionUart>, Pin<Gpio1, FunctionUart>);
+
+/// The DIN MIDI UART.
+type Uart = UartPeripheral<hal::uart::Enabled, pac::UART0, UartPins>;
+
+/// Where messages go: USB and DIN both.
+struct Output<'a> {
+    midi: MidiClass<'a, hal::usb::UsbBus>,
+    uart: Uart,
+}
+
+impl Output<'_> {
+    /// Send `message` on USB cable 0 and on DIN.
+    ///
+    /// USB messages are dropped if the host isn't reading, rather than
+    /// holding up the loop.
+    fn send(&mut self, message: Message) {
+        let _ = self.midi.write(&message.to_usb_packet());
+        self.uart.write_full_blocking(message.as_bytes());
+    }
+}
+
+/// Everything the scheduled callbacks work on.
+struct App<'a> {
+    looper: Looper,
+    output: Output<'a>,
+    parser: Parser,
+    log: Log,
+    buttons: [DynPin; BUTTONS],
+    debouncer: Debouncer<BUTTONS, 1>,
+    beat_led: Pin<Gpio25, PushPullOutput>,
+    record_led: Pin<Gpio15, PushPullOutput>,
+}
+
+impl App<'_> {
+    /// Pass on what has come in from USB and DIN, and record it.
+    fn read_input(&mut self) {
+        let mut packets = [0; 64];
+        if let Ok(count) = self.output.midi.read(&mut packets) {
+            for packet in packets[..count].chunks_exact(4) {
+                if let Some(message) = Message::from_usb_packet(packet) {
+                    self.play_live(message);
+                }
+            }
+        }
+
+        let mut bytes = [0; 32];
+        if let Ok(count) = self.output.uart.read_raw(&mut bytes) {
+            for &byte in &bytes[..count] {
+                if let Some(message) = self.parser.push(byte) {
+                    self.play_live(message);
+                }
+            }
+        }
+    }
+
+    fn play_live(&mut self, message: Message) {
+        self.output.send(message);
+        self.looper.input(message);
+    }
+
+    /// Show the beat and whether the looper is recording.
+    fn update_leds(&mut self) {
+        let mode = self.looper.mode();
+        let running = !matches!(mode, Mode::Empty | Mode::Stopped);
+        let on_beat = running && self.looper.position() % PPQN < PPQN / 8;
+        let recording = matches!(mode, Mode::Recording | Mode::Overdubbing);
+        self.beat_led.set_state(on_beat.into()).unwrap();
+        self.record_led.set_state(recording.into()).unwrap();
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // The buttons pull their GPIO low when pressed
+    let buttons: [DynPin; BUTTONS] = [
+        pins.gpio10.into_pull_up_input().into(),
+        pins.gpio11.into_pull_up_input().into(),
+        pins.gpio12.into_pull_up_input().into(),
+        pins.gpio13.into_pull_up_input().into(),
+    ];
+
+    let uart_pins = (
+        pins.gpio0.into_mode::<FunctionUart>(),
+        pins.gpio1.into_mode::<FunctionUart>(),
+    );
+    let uart = UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(MIDI_BAUD.Hz(), DataBits::Eight, None, StopBits::One),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // One MIDI IN jack and one MIDI OUT jack
+    let midi = MidiClass::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x5e4))
+        .manufacturer("Fake company")
+        .product("MIDI looper")
+        .serial_number("TEST")
+        .device_class(0)
+        .build();
+
+    // Pick up the loop saved last time, if there is one
+    let log = storage::open();
+    let looper = match storage::load(&log) {
+        Some((events, length)) => Looper::with_loop(events, length),
+        None => Looper::new(),
+    };
+
+    let mut app = App {
+        looper,
+        output: Output { midi, uart },
+        parser: Parser::new(),
+        log,
+        buttons,
+        debouncer: Debouncer::default(),
+        beat_led: pins.led.into_push_pull_output(),
+        record_led: pins.gpio15.into_push_pull_output(),
+    };
+
+    let mut scheduler: Scheduler<App, 2> = Scheduler::new(Monotonic::new(timer));
+    scheduler
+        .periodic(Duration::micros(TICK_US), on_tick)
+        .unwrap();
+    scheduler.periodic(Duration::millis(1), on_scan).unwrap();
+
+    loop {
+        usb_dev.poll(&mut [&mut app.output.midi]);
+        app.read_input();
+        scheduler.run_pending(&mut app);
+    }
+}
+
+/// Move the loop on a tick.
+fn on_tick(app: &mut App, _now: Instant) {
+    let output = &mut app.output;
+    app.looper.tick(|message| output.send(message));
+    app.update_leds();
+}
+
+/// Scan and debounce the buttons, and act on presses.
+fn on_scan(app: &mut App, _now: Instant) {
+    let mut scan = [[false; BUTTONS]; 1];
+    for (button, pressed) in app.buttons.iter().zip(scan[0].iter_mut()) {
+        *pressed = button.is_low().unwrap();
+    }
+    let mut pressed = [false; BUTTONS];
+    app.debouncer.update(&scan, |event| {
+        if event.is_press() {
+            let (_, button) = event.coord();
+            pressed[usize::from(button)] = true;
+        }
+    });
+
+    let output = &mut app.output;
+    let looper = &mut app.looper;
+    if pressed[REC] {
+        looper.record();
+    }
+    if pressed[PLAY] {
+        looper.play(|message| output.send(message));
+    }
+    if pressed[CLEAR] {
+        looper.clear(|message| output.send(message));
+    }
+    if pressed[SAVE] && looper.mode() != Mode::Empty {
+        looper.stop(|message| output.send(message));
+        storage::save(&mut app.log, looper.events(), looper.length());
+    }
+    app.update_leds();
+}
+
+// End of file
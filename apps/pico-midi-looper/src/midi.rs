@@ -0,0 +1,146 @@
+//! MIDI channel messages, from a DIN byte stream or USB MIDI packets.
+//!
+//! Only channel voice messages, status 0x80 to 0xEF, are looped. System
+//! messages are dropped: the looper runs from its own clock, and SysEx
+//! dumps are no use replayed.
+
+/// A channel voice message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Message {
+    bytes: [u8; 3],
+}
+
+impl Message {
+    /// A message from its status byte and data bytes, if the status is a
+    /// channel voice message. Messages with one data byte ignore `data2`.
+    pub fn new(status: u8, data1: u8, data2: u8) -> Option<Self> {
+        if !(0x80..0xF0).contains(&status) {
+            return None;
+        }
+        let mut message = Message {
+            bytes: [status, data1 & 0x7F, data2 & 0x7F],
+        };
+        if data_len(status) == 1 {
+            message.bytes[2] = 0;
+        }
+        Some(message)
+    }
+
+    /// Note Off for `note` on `channel`, 0 to 15.
+    pub fn note_off(channel: u8, note: u8) -> Self {
+        Message {
+            bytes: [0x80 | (channel & 0x0F), note & 0x7F, 0],
+        }
+    }
+
+    /// The message as sent over DIN MIDI.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..1 + data_len(self.bytes[0])]
+    }
+
+    /// The channel, 0 to 15.
+    pub fn channel(&self) -> u8 {
+        self.bytes[0] & 0x0F
+    }
+
+    /// The note this message starts, if it is a Note On.
+    pub fn started_note(&self) -> Option<u8> {
+        match self.bytes {
+            [status, note, velocity] if status & 0xF0 == 0x90 && velocity != 0 => Some(note),
+            _ => None,
+        }
+    }
+
+    /// The note this message ends, if it is a Note Off, or a Note On with
+    /// velocity 0.
+    pub fn stopped_note(&self) -> Option<u8> {
+        match self.bytes {
+            [status, note, _] if status & 0xF0 == 0x80 => Some(note),
+            [status, note, 0] if status & 0xF0 == 0x90 => Some(note),
+            _ => None,
+        }
+    }
+
+    /// The message in a four-byte USB MIDI event packet, if it holds a
+    /// channel voice message.
+    pub fn from_usb_packet(packet: &[u8]) -> Option<Self> {
+        match *packet {
+            [header, status, data1, data2] if (0x8..=0xE).contains(&(header & 0x0F)) => {
+                Message::new(status, data1, data2)
+            }
+            _ => None,
+        }
+    }
+
+    /// The message as a USB MIDI event packet on cable 0.
+    pub fn to_usb_packet(self) -> [u8; 4] {
+        let [status, data1, data2] = self.bytes;
+        // The code index number of a channel message is its status nibble
+        [status >> 4, status, data1, data2]
+    }
+
+    /// The message as stored in flash.
+    pub fn to_bytes(self) -> [u8; 3] {
+        self.bytes
+    }
+
+    /// A message stored with [`Message::to_bytes`].
+    pub fn from_bytes(bytes: [u8; 3]) -> Option<Self> {
+        let [status, data1, data2] = bytes;
+        Message::new(status, data1, data2)
+    }
+}
+
+/// Data bytes after a channel message's status byte.
+fn data_len(status: u8) -> usize {
+    match status & 0xF0 {
+        // Program Change and Channel Pressure
+        0xC0 | 0xD0 => 1,
+        _ => 2,
+    }
+}
+
+/// Reassembles messages from DIN MIDI bytes, including ones sent with
+/// running status.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Parser {
+    /// The running status, or 0 if there is none.
+    status: u8,
+    data: [u8; 2],
+    count: usize,
+}
+
+impl Parser {
+    /// A parser waiting for a status byte.
+    pub fn new() -> Self {
+        Parser::default()
+    }
+
+    /// Take the next byte, returning the message it completes.
+    pub fn push(&mut self, byte: u8) -> Option<Message> {
+        match byte {
+            // Real-time messages can come between any two bytes
+            0xF8..=0xFF => None,
+            // Other system messages cancel the running status
+            0xF0..=0xF7 => {
+                self.status = 0;
+                None
+            }
+            0x80..=0xEF => {
+                self.status = byte;
+                self.count = 0;
+                None
+            }
+            _ if self.status == 0 => None,
+            _ => {
+                self.data[self.count] = byte;
+                self.count += 1;
+                if self.count < data_len(self.status) {
+                    return None;
+                }
+                self.count = 0;
+                Message::new(self.status, self.data[0], self.data[1])
+            }
+        }
+    }
+}
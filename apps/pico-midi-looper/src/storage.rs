@@ -0,0 +1,116 @@
+//! Keeping the loop in flash, in a `rp_hal_boards_common::datalog` ring.
+//!
+//! Each save appends the whole loop as 8-byte records:
+//!
+//! ```text
+//! start  | 0x01 | 0 ...                                  |
+//! event  | 0x02 | tick (u16) | status | data 1 | data 2 | 0 0 |
+//! end    | 0x03 | length (u16) | events (u16) | 0 0 0     |
+//! ```
+//!
+//! [`load`] takes the newest loop whose end record is there and whose
+//! event count matches, so one cut short by a power failure, or half
+//! overwritten when the ring wraps, is passed over for the one before.
+//! The ring holds several full loops, so the newest complete one survives
+//! the wrap.
+
+use heapless::Vec;
+use rp_hal_boards_common::datalog::{Log, RECORD_LEN};
+use rp_hal_boards_common::flash_storage::{self, SECTOR_SIZE};
+
+use crate::looper::{Event, MAX_EVENTS};
+use crate::midi::Message;
+
+/// Sectors in the ring: 1984 records, five of the longest loops, so
+/// erasing the oldest sector never reaches the newest loop.
+const SECTORS: u32 = 4;
+
+/// Where the ring starts, in bytes from the start of the `flash_storage`
+/// region.
+const START: u32 = 0;
+
+const START_RECORD: u8 = 0x01;
+const EVENT_RECORD: u8 = 0x02;
+const END_RECORD: u8 = 0x03;
+
+/// The ring in flash.
+///
+/// Panics if the ring doesn't fit in the `flash_storage` region, which
+/// `FLASH_STORAGE_SIZE` can shrink below the default 64 KiB.
+pub fn open() -> Log {
+    assert!(
+        START + SECTORS * SECTOR_SIZE <= flash_storage::capacity(),
+        "the loop ring doesn't fit in the flash storage region"
+    );
+    // Can't fail: the ring is sector aligned and inside the region
+    Log::new(START, SECTORS).unwrap()
+}
+
+/// Append a loop of `length` ticks to the ring.
+///
+/// Writing flash stops the CPU, interrupts and all, for several
+/// milliseconds a page, so nothing else runs meanwhile.
+pub fn save(log: &mut Log, events: &[Event], length: u16) {
+    let mut record = [0; RECORD_LEN];
+    record[0] = START_RECORD;
+    log.append(&record);
+    for event in events {
+        let [status, data1, data2] = event.message.to_bytes();
+        let [tick_low, tick_high] = event.tick.to_le_bytes();
+        log.append(&[
+            EVENT_RECORD,
+            tick_low,
+            tick_high,
+            status,
+            data1,
+            data2,
+            0,
+            0,
+        ]);
+    }
+    let [length_low, length_high] = length.to_le_bytes();
+    let [count_low, count_high] = (events.len() as u16).to_le_bytes();
+    log.append(&[
+        END_RECORD,
+        length_low,
+        length_high,
+        count_low,
+        count_high,
+        0,
+        0,
+        0,
+    ]);
+    log.flush();
+}
+
+/// The newest complete loop in the ring, and its length in ticks.
+pub fn load(log: &Log) -> Option<(Vec<Event, MAX_EVENTS>, u16)> {
+    let mut newest = None;
+    let mut pending: Vec<Event, MAX_EVENTS> = Vec::new();
+    // Whether `pending` follows a start record and has lost nothing
+    let mut intact = false;
+    log.for_each(|record| match record[0] {
+        START_RECORD => {
+            pending.clear();
+            intact = true;
+        }
+        EVENT_RECORD => {
+            let tick = u16::from_le_bytes([record[1], record[2]]);
+            let message = Message::from_bytes([record[3], record[4], record[5]]);
+            match message {
+                Some(message) if intact => intact = pending.push(Event { tick, message }).is_ok(),
+                _ => intact = false,
+            }
+        }
+        END_RECORD => {
+            let length = u16::from_le_bytes([record[1], record[2]]);
+            let count = u16::from_le_bytes([record[3], record[4]]);
+            if intact && usize::from(count) == pending.len() {
+                newest = Some((pending.clone(), length));
+            }
+            intact = false;
+        }
+        _ => intact = false,
+    });
+    newest
+}
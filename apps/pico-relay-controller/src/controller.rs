@@ -0,0 +1,216 @@
+//! The relays and inputs, as the Modbus master sees them.
+//!
+//! | Table             | Address | Meaning                                        |
+//! |-------------------|---------|------------------------------------------------|
+//! | Coils             | 0-7     | Relays 1 to 8, on when set                     |
+//! | Discrete inputs   | 0-7     | Inputs 1 to 8, set when closed                 |
+//! | Holding registers | 0       | Failsafe timeout in seconds, 0 for none        |
+//! | Input registers   | 0       | Status: bit 0 failsafe tripped, bit 1 watchdog |
+//! |                   |         | reset, bit 2 relays restored at start-up       |
+//! |                   | 1       | Requests handled                               |
+//! |                   | 2       | Corrupt frames seen                            |
+//! |                   | 3       | Minutes since start-up                         |
+//!
+//! The counters wrap at 65535. When the master has been silent for the
+//! failsafe timeout, every relay drops. They stay off until the master sets
+//! them again; the failsafe bit clears at its next request.
+
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use rp_pico::hal::gpio::DynPin;
+
+use crate::health::Health;
+use crate::modbus::{Device, Exception, Outcome};
+
+/// Relays and inputs on the board.
+pub const CHANNELS: usize = 8;
+
+/// The failsafe timeout for a board with nothing saved, in seconds.
+pub const DEFAULT_TIMEOUT_S: u16 = 10;
+
+/// The longest failsafe timeout, in seconds: an hour.
+const MAX_TIMEOUT_S: u16 = 3600;
+
+/// Whether a relay turns on with its pin low, as on many relay modules with
+/// opto-isolated inputs.
+const RELAYS_ACTIVE_LOW: bool = false;
+
+const STATUS_FAILSAFE: u16 = 1 << 0;
+const STATUS_WATCHDOG_RESET: u16 = 1 << 1;
+const STATUS_RESTORED: u16 = 1 << 2;
+
+/// What is kept in flash, so the relays come back as they were after a
+/// power cut.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Saved {
+    /// One bit per relay, relay 1 in bit 0.
+    pub relays: u8,
+    pub timeout_s: u16,
+}
+
+impl Saved {
+    /// Length of the flash record.
+    pub const LEN: usize = 3;
+
+    pub fn to_bytes(self) -> [u8; Self::LEN] {
+        let [low, high] = self.timeout_s.to_le_bytes();
+        [self.relays, low, high]
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match *bytes {
+            [relays, low, high] => Some(Saved {
+                relays,
+                timeout_s: u16::from_le_bytes([low, high]).min(MAX_TIMEOUT_S),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The board.
+pub struct Controller {
+    relays: [DynPin; CHANNELS],
+    inputs: [DynPin; CHANNELS],
+    state: Saved,
+    pub health: Health,
+    restored: bool,
+    failsafe: bool,
+    /// Whether `state` has changed since [`Controller::take_changed`].
+    changed: bool,
+    now_us: u64,
+}
+
+impl Controller {
+    /// Drive `relays`, already outputs, to `saved`, or all off if there's
+    /// nothing saved, and read `inputs`, already inputs with pull-ups.
+    pub fn new(
+        relays: [DynPin; CHANNELS],
+        inputs: [DynPin; CHANNELS],
+        saved: Option<Saved>,
+        health: Health,
+    ) -> Self {
+        let mut controller = Controller {
+            relays,
+            inputs,
+            state: saved.unwrap_or(Saved {
+                relays: 0,
+                timeout_s: DEFAULT_TIMEOUT_S,
+            }),
+            health,
+            restored: saved.is_some(),
+            failsafe: false,
+            changed: false,
+            now_us: 0,
+        };
+        controller.set_relays(controller.state.relays);
+        controller.changed = false;
+        controller
+    }
+
+    /// Check on the master at `now_us`, dropping the relays if it has gone.
+    pub fn poll(&mut self, now_us: u64) {
+        self.now_us = now_us;
+        let timeout_s = self.state.timeout_s;
+        if timeout_s != 0 && !self.failsafe && self.health.master_lost(now_us, timeout_s) {
+            self.failsafe = true;
+            self.set_relays(0);
+        }
+    }
+
+    /// Count a frame, handled by [`crate::modbus::respond`] at `now_us`.
+    pub fn record(&mut self, outcome: Outcome, now_us: u64) {
+        self.health.record(outcome, now_us);
+        if outcome == Outcome::Handled {
+            self.failsafe = false;
+        }
+    }
+
+    /// Whether the failsafe has dropped the relays.
+    pub fn failsafe(&self) -> bool {
+        self.failsafe
+    }
+
+    /// The state to save, if it has changed since the last call.
+    pub fn take_changed(&mut self) -> Option<Saved> {
+        if core::mem::take(&mut self.changed) {
+            Some(self.state)
+        } else {
+            None
+        }
+    }
+
+    fn set_relays(&mut self, relays: u8) {
+        for (i, pin) in self.relays.iter_mut().enumerate() {
+            let on = relays & (1 << i) != 0;
+            let _ = pin.set_state((on != RELAYS_ACTIVE_LOW).into());
+        }
+        if relays != self.state.relays {
+            self.state.relays = relays;
+            self.changed = true;
+        }
+    }
+}
+
+impl Device for Controller {
+    fn coil(&self, address: u16) -> Option<bool> {
+        let i = usize::from(address);
+        (i < CHANNELS).then(|| self.state.relays & (1 << i) != 0)
+    }
+
+    fn set_coil(&mut self, address: u16, on: bool) {
+        let bit = 1 << address;
+        let relays = if on {
+            self.state.relays | bit
+        } else {
+            self.state.relays & !bit
+        };
+        self.set_relays(relays);
+    }
+
+    fn discrete_input(&self, address: u16) -> Option<bool> {
+        let pin = self.inputs.get(usize::from(address))?;
+        // Closed pulls the input low
+        Some(pin.is_low().unwrap_or(false))
+    }
+
+    fn holding_register(&self, address: u16) -> Option<u16> {
+        match address {
+            0 => Some(self.state.timeout_s),
+            _ => None,
+        }
+    }
+
+    fn set_holding_register(&mut self, _address: u16, value: u16) -> Result<(), Exception> {
+        // Only the timeout exists
+        if value > MAX_TIMEOUT_S {
+            return Err(Exception::IllegalDataValue);
+        }
+        if value != self.state.timeout_s {
+            self.state.timeout_s = value;
+            self.changed = true;
+        }
+        Ok(())
+    }
+
+    fn input_register(&self, address: u16) -> Option<u16> {
+        match address {
+            0 => {
+                let mut status = 0;
+                if self.failsafe {
+                    status |= STATUS_FAILSAFE;
+                }
+                if self.health.watchdog_reset() {
+                    status |= STATUS_WATCHDOG_RESET;
+                }
+                if self.restored {
+                    status |= STATUS_RESTORED;
+                }
+                Some(status)
+            }
+            1 => Some(self.health.requests),
+            2 => Some(self.health.corrupt),
+            3 => Some((self.now_us / 60_000_000) as u16),
+            _ => None,
+        }
+    }
+}
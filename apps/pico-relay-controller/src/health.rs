@@ -0,0 +1,84 @@
+//! Supervision: the hardware watchdog, and counters the master can read to
+//! see how the board and the bus are doing.
+//!
+//! The watchdog resets the board if the main loop stops going round for
+//! [`WATCHDOG_MS`]. After such a reset, [`Health::watchdog_reset`] says so
+//! until the next power cycle or deliberate reset, so the master can tell a
+//! board that crashed from one that lost power.
+
+use embedded_hal::watchdog::{Watchdog as _, WatchdogEnable};
+use fugit::ExtU32;
+use rp_pico::hal::{pac, Watchdog};
+
+use crate::modbus::Outcome;
+
+/// How long the main loop may stall before the board resets, in
+/// milliseconds. A flash write, the longest thing it does, takes about
+/// 50 ms.
+pub const WATCHDOG_MS: u32 = 500;
+
+/// The state of the board and the bus since start-up.
+pub struct Health {
+    watchdog: Watchdog,
+    watchdog_reset: bool,
+    /// Good frames addressed to this board.
+    pub requests: u16,
+    /// Frames with a bad CRC, addressed to anyone.
+    pub corrupt: u16,
+    /// When the last good request arrived, in microseconds since start-up.
+    last_request_us: u64,
+}
+
+impl Health {
+    /// Start `watchdog`. Call this before anything that might hang.
+    ///
+    /// Whether the watchdog caused the last reset has to be read with
+    /// [`reset_by_watchdog`] before the clocks are set up, which needs the
+    /// watchdog too.
+    pub fn new(mut watchdog: Watchdog, watchdog_reset: bool) -> Self {
+        watchdog.pause_on_debug(true);
+        watchdog.start((WATCHDOG_MS * 1000).micros());
+        Health {
+            watchdog,
+            watchdog_reset,
+            requests: 0,
+            corrupt: 0,
+            last_request_us: 0,
+        }
+    }
+
+    /// Tell the watchdog the main loop is still going round.
+    pub fn feed(&mut self) {
+        self.watchdog.feed();
+    }
+
+    /// Whether the last reset was the watchdog's.
+    pub fn watchdog_reset(&self) -> bool {
+        self.watchdog_reset
+    }
+
+    /// Count a received frame, at `now_us`.
+    pub fn record(&mut self, outcome: Outcome, now_us: u64) {
+        match outcome {
+            Outcome::Corrupt => self.corrupt = self.corrupt.wrapping_add(1),
+            Outcome::NotForUs => {}
+            Outcome::Handled => {
+                self.requests = self.requests.wrapping_add(1);
+                self.last_request_us = now_us;
+            }
+        }
+    }
+
+    /// Whether the master has been silent for over `timeout_s` at
+    /// `now_us`. Start-up counts as a request, so a board that comes up
+    /// without a master gets the full timeout before it gives up.
+    pub fn master_lost(&self, now_us: u64, timeout_s: u16) -> bool {
+        now_us.saturating_sub(self.last_request_us) > u64::from(timeout_s) * 1_000_000
+    }
+}
+
+/// Whether the watchdog timed out to cause the last reset, rather than a
+/// power-on, the RUN pin or a deliberate `trigger_reset`.
+pub fn reset_by_watchdog(watchdog: &pac::WATCHDOG) -> bool {
+    watchdog.reason.read().timer().bit_is_set()
+}
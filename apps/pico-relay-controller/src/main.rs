@@ -0,0 +1,242 @@
+//! # Pico Relay Controller
+//!
+//! An eight-channel relay board with eight inputs, for home automation,
+//! controlled over Modbus RTU on an RS-485 bus.
+//!
+//! * The relays are Modbus coils and the inputs discrete inputs; the
+//!   register map is in [`controller`].
+//! * The relays' state is kept in flash, so after a power cut they come back
+//!   as the master last set them.
+//! * If the master goes quiet for longer than the failsafe timeout, ten
+//!   seconds unless the master changes it, every relay drops. The LED shows
+//!   when that has happened.
+//! * The hardware watchdog resets the board if the firmware hangs, and the
+//!   master can read whether it did, along with counts of requests and
+//!   corrupt frames (see [`health`]).
+//!
+//! The bus runs at 19200 baud, 8 data bits, even parity and one stop bit,
+//! Modbus's default, and the board answers at address [`UNIT`].
+//!
+//! Wiring:
+//!
+//! | Pico        | To                                                   |
+//! |-------------|------------------------------------------------------|
+//! | GPIO0       | MAX485 DI                                            |
+//! | GPIO1       | MAX485 RO                                            |
+//! | GPIO2       | MAX485 DE and /RE, tied together                     |
+//! | GPIO6-13    | Relay drivers 1 to 8, e.g. a ULN2803                 |
+//! | GPIO14-21   | Inputs 1 to 8, closing to ground                     |
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+mod controller;
+mod health;
+mod modbus;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use fugit::RateExtU32;
+use hal::Clock;
+
+use hal::gpio::{DynPin, FunctionUart};
+use hal::uart::{DataBits, Parity, StopBits, UartConfig, UartPeripheral};
+
+use rp_hal_boards_common::settings::{default_offset, Store};
+
+use controller::{Controller, Saved};
+use health::Health;
+use modbus::{Frame, Outcome};
+
+/// This board's Modbus address, 1 to 247.
+const UNIT: u8 = 1;
+
+/// The bus's baud rate.
+const BAUD: u32 = 19200;
+
+/// Silence that ends a frame, in microseconds: 3.5 characters of 11 bits,
+/// or 1750 µs above 19200 baud as the spec sets.
+const FRAME_GAP_US: u64 = if BAUD > 19200 {
+    1750
+} else {
+    38_500_000 / BAUD as u64
+};
+
+/// Version of the flash record, in case its layout changes.
+const SAVED_VERSION: u16 = 1;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Read why we reset before the watchdog driver takes the registers
+    let watchdog_reset = health::reset_by_watchdog(&pac.WATCHDOG);
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let health = Health::new(watchdog, watchdog_reset);
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led = pins.led.into_push_pull_output();
+
+    let relays: [DynPin; controller::CHANNELS] = [
+        pins.gpio6.into_push_pull_output().into(),
+        pins.gpio7.into_push_pull_output().into(),
+        pins.gpio8.into_push_pull_output().into(),
+        pins.gpio9.into_push_pull_output().into(),
+        pins.gpio10.into_push_pull_output().into(),
+        pins.gpio11.into_push_pull_output().into(),
+        pins.gpio12.into_push_pull_output().into(),
+        pins.gpio13.into_push_pull_output().into(),
+    ];
+    let inputs: [DynPin; controller::CHANNELS] = [
+        pins.gpio14.into_pull_up_input().into(),
+        pins.gpio15.into_pull_up_input().into(),
+        pins.gpio16.into_pull_up_input().into(),
+        pins.gpio17.into_pull_up_input().into(),
+        pins.gpio18.into_pull_up_input().into(),
+        pins.gpio19.into_pull_up_input().into(),
+        pins.gpio20.into_pull_up_input().into(),
+        pins.gpio21.into_pull_up_input().into(),
+    ];
+
+    // Put the relays back as they were
+    let mut store = Store::new(default_offset());
+    let mut buf = [0u8; Saved::LEN];
+    let saved = match store.load_version(&mut buf) {
+        Some((SAVED_VERSION, len)) => Saved::from_bytes(&buf[..len]),
+        _ => None,
+    };
+    let mut controller = Controller::new(relays, inputs, saved, health);
+
+    // The RS-485 transceiver, receiving until there's a reply to send
+    let mut driver_enable = pins.gpio2.into_push_pull_output();
+    driver_enable.set_low().unwrap();
+    let uart_pins = (
+        pins.gpio0.into_mode::<FunctionUart>(),
+        pins.gpio1.into_mode::<FunctionUart>(),
+    );
+    let uart = UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(
+                BAUD.Hz(),
+                DataBits::Eight,
+                Some(Parity::Even),
+                StopBits::One,
+            ),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    let mut frame = Frame::new();
+    // Whether the frame has lost a byte, to a UART error or for want of room
+    let mut garbled = false;
+    let mut last_byte_us = 0;
+    let mut reply = Frame::new();
+
+    loop {
+        controller.health.feed();
+        let now = timer.get_counter().ticks();
+
+        let mut bytes = [0u8; 32];
+        match uart.read_raw(&mut bytes) {
+            Ok(count) => {
+                garbled |= frame.extend_from_slice(&bytes[..count]).is_err();
+                last_byte_us = now;
+            }
+            Err(nb::Error::Other(_)) => {
+                garbled = true;
+                last_byte_us = now;
+            }
+            Err(nb::Error::WouldBlock) => {}
+        }
+
+        if (!frame.is_empty() || garbled) && now - last_byte_us >= FRAME_GAP_US {
+            let outcome = if garbled {
+                Outcome::Corrupt
+            } else {
+                modbus::respond(&mut controller, UNIT, &frame, &mut reply)
+            };
+            controller.record(outcome, now);
+            frame.clear();
+            garbled = false;
+
+            if !reply.is_empty() {
+                driver_enable.set_high().unwrap();
+                uart.write_full_blocking(&reply);
+                // Hold the bus until the last stop bit is out
+                while uart_busy() {}
+                driver_enable.set_low().unwrap();
+                reply.clear();
+            }
+        }
+
+        controller.poll(now);
+        led.set_state(controller.failsafe().into()).unwrap();
+
+        if let Some(saved) = controller.take_changed() {
+            // Frames arriving while flash is written are lost, and the master
+            // tries again
+            let _ = store.save_version(SAVED_VERSION, &saved.to_bytes());
+        }
+    }
+}
+
+/// Whether UART0 is still sending, including the byte in its shift register.
+fn uart_busy() -> bool {
+    // Note (safety): only the flag register is read
+    let uart = unsafe { &*pac::UART0::ptr() };
+    uart.uartfr.read().busy().bit_is_set()
+}
+
+// End of file
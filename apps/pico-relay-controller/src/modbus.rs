@@ -0,0 +1,231 @@
+//! A Modbus RTU slave: checks each request frame, hands it to a [`Device`]
+//! and builds the reply.
+//!
+//! Supported functions:
+//!
+//! | Code | Function                 |
+//! |------|--------------------------|
+//! | 0x01 | Read coils               |
+//! | 0x02 | Read discrete inputs     |
+//! | 0x03 | Read holding registers   |
+//! | 0x04 | Read input registers     |
+//! | 0x05 | Write single coil        |
+//! | 0x06 | Write single register    |
+//! | 0x0F | Write multiple coils     |
+//! | 0x10 | Write multiple registers |
+//!
+//! Anything else gets an "illegal function" exception. Requests to address
+//! 0 are broadcasts: writes are carried out, and never answered.
+
+use heapless::Vec;
+
+/// The longest frame, request or reply.
+pub const MAX_FRAME: usize = 256;
+
+/// The broadcast address.
+const BROADCAST: u8 = 0;
+
+/// Most coils or inputs read at once, as the spec allows.
+const MAX_READ_BITS: u16 = 2000;
+
+/// Most registers read at once, as the spec allows.
+const MAX_READ_REGISTERS: u16 = 125;
+
+/// A reply frame.
+pub type Frame = Vec<u8, MAX_FRAME>;
+
+/// Why a request was refused, named as in the Modbus specification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Exception {
+    IllegalFunction = 0x01,
+    IllegalDataAddress = 0x02,
+    IllegalDataValue = 0x03,
+}
+
+/// What the slave holds, addressed from 0.
+pub trait Device {
+    /// A coil's state, or `None` if there is no coil `address`.
+    fn coil(&self, address: u16) -> Option<bool>;
+    /// Set a coil that [`Device::coil`] says exists.
+    fn set_coil(&mut self, address: u16, on: bool);
+    /// A discrete input's state, or `None` if there is no input `address`.
+    fn discrete_input(&self, address: u16) -> Option<bool>;
+    /// A holding register, or `None` if there is no register `address`.
+    fn holding_register(&self, address: u16) -> Option<u16>;
+    /// Set a holding register that [`Device::holding_register`] says exists.
+    fn set_holding_register(&mut self, address: u16, value: u16) -> Result<(), Exception>;
+    /// An input register, or `None` if there is no register `address`.
+    fn input_register(&self, address: u16) -> Option<u16>;
+}
+
+/// What became of a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The CRC didn't match, or the frame was too short to have one.
+    Corrupt,
+    /// A good frame for another slave.
+    NotForUs,
+    /// Handled; send the reply if there is one.
+    Handled,
+}
+
+/// Handle the request `frame` for the slave at `unit`, leaving any reply in
+/// `reply`.
+pub fn respond<D: Device>(device: &mut D, unit: u8, frame: &[u8], reply: &mut Frame) -> Outcome {
+    reply.clear();
+    if frame.len() < 4 {
+        return Outcome::Corrupt;
+    }
+    let (body, crc) = frame.split_at(frame.len() - 2);
+    if crc16(body).to_le_bytes() != crc {
+        return Outcome::Corrupt;
+    }
+    let (address, function, data) = (body[0], body[1], &body[2..]);
+    if address != unit && address != BROADCAST {
+        return Outcome::NotForUs;
+    }
+
+    let _ = reply.push(unit);
+    let _ = reply.push(function);
+    if let Err(exception) = execute(device, function, data, reply) {
+        reply.truncate(1);
+        let _ = reply.push(function | 0x80);
+        let _ = reply.push(exception as u8);
+    }
+    if address == BROADCAST {
+        reply.clear();
+    } else {
+        let crc = crc16(reply);
+        let _ = reply.extend_from_slice(&crc.to_le_bytes());
+    }
+    Outcome::Handled
+}
+
+/// Carry out one request, adding the reply's data after its function code.
+fn execute<D: Device>(
+    device: &mut D,
+    function: u8,
+    data: &[u8],
+    reply: &mut Frame,
+) -> Result<(), Exception> {
+    let word = |i: usize| -> Result<u16, Exception> {
+        data.get(i..i + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or(Exception::IllegalDataValue)
+    };
+    match function {
+        0x01 | 0x02 => {
+            let (first, count) = (word(0)?, word(2)?);
+            if count == 0 || count > MAX_READ_BITS {
+                return Err(Exception::IllegalDataValue);
+            }
+            let mut bytes = [0u8; (MAX_READ_BITS as usize).div_ceil(8)];
+            for i in 0..count {
+                let address = first.checked_add(i).ok_or(Exception::IllegalDataAddress)?;
+                let bit = if function == 0x01 {
+                    device.coil(address)
+                } else {
+                    device.discrete_input(address)
+                };
+                if bit.ok_or(Exception::IllegalDataAddress)? {
+                    bytes[usize::from(i / 8)] |= 1 << (i % 8);
+                }
+            }
+            let len = usize::from(count.div_ceil(8));
+            let _ = reply.push(len as u8);
+            let _ = reply.extend_from_slice(&bytes[..len]);
+        }
+        0x03 | 0x04 => {
+            let (first, count) = (word(0)?, word(2)?);
+            if count == 0 || count > MAX_READ_REGISTERS {
+                return Err(Exception::IllegalDataValue);
+            }
+            let _ = reply.push((count * 2) as u8);
+            for i in 0..count {
+                let address = first.checked_add(i).ok_or(Exception::IllegalDataAddress)?;
+                let value = if function == 0x03 {
+                    device.holding_register(address)
+                } else {
+                    device.input_register(address)
+                };
+                let value = value.ok_or(Exception::IllegalDataAddress)?;
+                let _ = reply.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        0x05 => {
+            let (address, value) = (word(0)?, word(2)?);
+            let on = match value {
+                0xFF00 => true,
+                0x0000 => false,
+                _ => return Err(Exception::IllegalDataValue),
+            };
+            device.coil(address).ok_or(Exception::IllegalDataAddress)?;
+            device.set_coil(address, on);
+            // The reply echoes the request
+            let _ = reply.extend_from_slice(&data[..4]);
+        }
+        0x06 => {
+            let (address, value) = (word(0)?, word(2)?);
+            device
+                .holding_register(address)
+                .ok_or(Exception::IllegalDataAddress)?;
+            device.set_holding_register(address, value)?;
+            let _ = reply.extend_from_slice(&data[..4]);
+        }
+        0x0F => {
+            let (first, count) = (word(0)?, word(2)?);
+            let values = data.get(5..).ok_or(Exception::IllegalDataValue)?;
+            if count == 0 || values.len() != usize::from(count.div_ceil(8)) {
+                return Err(Exception::IllegalDataValue);
+            }
+            // Check every address before changing anything
+            for i in 0..count {
+                let address = first.checked_add(i).ok_or(Exception::IllegalDataAddress)?;
+                device.coil(address).ok_or(Exception::IllegalDataAddress)?;
+            }
+            for i in 0..count {
+                let on = values[usize::from(i / 8)] & (1 << (i % 8)) != 0;
+                device.set_coil(first + i, on);
+            }
+            let _ = reply.extend_from_slice(&data[..4]);
+        }
+        0x10 => {
+            let (first, count) = (word(0)?, word(2)?);
+            let values = data.get(5..).ok_or(Exception::IllegalDataValue)?;
+            if count == 0 || values.len() != usize::from(count) * 2 {
+                return Err(Exception::IllegalDataValue);
+            }
+            for i in 0..count {
+                let address = first.checked_add(i).ok_or(Exception::IllegalDataAddress)?;
+                device
+                    .holding_register(address)
+                    .ok_or(Exception::IllegalDataAddress)?;
+            }
+            for (i, value) in values.chunks_exact(2).enumerate() {
+                let value = u16::from_be_bytes([value[0], value[1]]);
+                device.set_holding_register(first + i as u16, value)?;
+            }
+            let _ = reply.extend_from_slice(&data[..4]);
+        }
+        _ => return Err(Exception::IllegalFunction),
+    }
+    Ok(())
+}
+
+/// The Modbus CRC-16: polynomial 0xA001 reflected, from 0xFFFF, sent low
+/// byte first.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFF_u16;
+    for &byte in data {
+        crc ^= u16::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
@@ -0,0 +1,229 @@
+//! Just enough of a Bosch BME280 driver to take one reading at a time.
+//!
+//! The sensor sits in sleep mode, drawing 0.1 µA, until [`Bme280::measure`]
+//! starts a single measurement in forced mode and waits about 10 ms for it.
+//! Each value is oversampled once and unfiltered, as Bosch recommend for
+//! weather monitoring. The readings are compensated with the integer
+//! formulas from the datasheet.
+
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// The address with SDO to ground; 0x77 with it to VDDIO.
+pub const ADDRESS: u8 = 0x76;
+
+const REG_CALIBRATION_TP: u8 = 0x88;
+const REG_CALIBRATION_H1: u8 = 0xA1;
+const REG_CHIP_ID: u8 = 0xD0;
+const REG_CALIBRATION_H: u8 = 0xE1;
+const REG_CTRL_HUM: u8 = 0xF2;
+const REG_STATUS: u8 = 0xF3;
+const REG_CTRL_MEAS: u8 = 0xF4;
+const REG_DATA: u8 = 0xF7;
+
+const CHIP_ID: u8 = 0x60;
+
+/// Humidity oversampled once.
+const CTRL_HUM: u8 = 0b001;
+/// Temperature and pressure oversampled once, in forced mode.
+const CTRL_MEAS: u8 = 0b001 << 5 | 0b001 << 2 | 0b01;
+/// The `measuring` bit of the status register.
+const STATUS_MEASURING: u8 = 1 << 3;
+
+/// Errors from the sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The I²C transfer failed, usually because nothing answered.
+    I2c(E),
+    /// Something answered, but not with a BME280's chip ID.
+    WrongChip(u8),
+    /// The measurement didn't finish in time.
+    Timeout,
+}
+
+/// One compensated reading.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Reading {
+    /// Hundredths of a degree Celsius.
+    pub temperature: i32,
+    /// Pascals.
+    pub pressure: u32,
+    /// Hundredths of a percent relative humidity.
+    pub humidity: u32,
+}
+
+/// The factory calibration, read once at start-up.
+#[derive(Default)]
+struct Calibration {
+    t1: u16,
+    t2: i16,
+    t3: i16,
+    p1: u16,
+    p2: i16,
+    p3: i16,
+    p4: i16,
+    p5: i16,
+    p6: i16,
+    p7: i16,
+    p8: i16,
+    p9: i16,
+    h1: u8,
+    h2: i16,
+    h3: u8,
+    h4: i16,
+    h5: i16,
+    h6: i8,
+}
+
+/// A BME280 on an I²C bus.
+pub struct Bme280<I2C> {
+    i2c: I2C,
+    address: u8,
+    calibration: Calibration,
+}
+
+impl<I2C, E> Bme280<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Check the chip ID at `address` and read the calibration.
+    pub fn new(i2c: I2C, address: u8) -> Result<Self, Error<E>> {
+        let mut sensor = Bme280 {
+            i2c,
+            address,
+            calibration: Calibration::default(),
+        };
+        let mut id = [0];
+        sensor.read(REG_CHIP_ID, &mut id)?;
+        if id[0] != CHIP_ID {
+            return Err(Error::WrongChip(id[0]));
+        }
+
+        let mut tp = [0; 24];
+        sensor.read(REG_CALIBRATION_TP, &mut tp)?;
+        let mut h1 = [0];
+        sensor.read(REG_CALIBRATION_H1, &mut h1)?;
+        let mut h = [0; 7];
+        sensor.read(REG_CALIBRATION_H, &mut h)?;
+
+        let u = |i: usize| u16::from_le_bytes([tp[i], tp[i + 1]]);
+        let s = |i: usize| i16::from_le_bytes([tp[i], tp[i + 1]]);
+        sensor.calibration = Calibration {
+            t1: u(0),
+            t2: s(2),
+            t3: s(4),
+            p1: u(6),
+            p2: s(8),
+            p3: s(10),
+            p4: s(12),
+            p5: s(14),
+            p6: s(16),
+            p7: s(18),
+            p8: s(20),
+            p9: s(22),
+            h1: h1[0],
+            h2: i16::from_le_bytes([h[0], h[1]]),
+            h3: h[2],
+            // Two signed 12 bit values packed into three bytes
+            h4: i16::from(h[3] as i8) << 4 | i16::from(h[4] & 0x0F),
+            h5: i16::from(h[5] as i8) << 4 | i16::from(h[4] >> 4),
+            h6: h[6] as i8,
+        };
+        Ok(sensor)
+    }
+
+    /// Take one reading, leaving the sensor asleep again afterwards.
+    pub fn measure<D: DelayMs<u32>>(&mut self, delay: &mut D) -> Result<Reading, Error<E>> {
+        // The humidity setting only takes effect on the write to CTRL_MEAS
+        self.write(REG_CTRL_HUM, CTRL_HUM)?;
+        self.write(REG_CTRL_MEAS, CTRL_MEAS)?;
+
+        // 9.3 ms at most with everything oversampled once
+        delay.delay_ms(10);
+        let mut status = [STATUS_MEASURING];
+        for _ in 0..10 {
+            self.read(REG_STATUS, &mut status)?;
+            if status[0] & STATUS_MEASURING == 0 {
+                break;
+            }
+            delay.delay_ms(1);
+        }
+        if status[0] & STATUS_MEASURING != 0 {
+            return Err(Error::Timeout);
+        }
+
+        let mut data = [0; 8];
+        self.read(REG_DATA, &mut data)?;
+        let adc_p = i32::from(data[0]) << 12 | i32::from(data[1]) << 4 | i32::from(data[2]) >> 4;
+        let adc_t = i32::from(data[3]) << 12 | i32::from(data[4]) << 4 | i32::from(data[5]) >> 4;
+        let adc_h = i32::from(data[6]) << 8 | i32::from(data[7]);
+
+        let t_fine = self.t_fine(adc_t);
+        Ok(Reading {
+            temperature: (t_fine * 5 + 128) >> 8,
+            pressure: self.pressure(adc_p, t_fine),
+            humidity: self.humidity(adc_h, t_fine),
+        })
+    }
+
+    /// The temperature in the datasheet's fine resolution, which the other
+    /// two compensations need.
+    fn t_fine(&self, adc_t: i32) -> i32 {
+        let c = &self.calibration;
+        let t1 = i32::from(c.t1);
+        let var1 = (((adc_t >> 3) - (t1 << 1)) * i32::from(c.t2)) >> 11;
+        let var2 = (((((adc_t >> 4) - t1) * ((adc_t >> 4) - t1)) >> 12) * i32::from(c.t3)) >> 14;
+        var1 + var2
+    }
+
+    /// Pressure in pascals.
+    fn pressure(&self, adc_p: i32, t_fine: i32) -> u32 {
+        let c = &self.calibration;
+        let mut var1 = i64::from(t_fine) - 128_000;
+        let mut var2 = var1 * var1 * i64::from(c.p6);
+        var2 += (var1 * i64::from(c.p5)) << 17;
+        var2 += i64::from(c.p4) << 35;
+        var1 = ((var1 * var1 * i64::from(c.p3)) >> 8) + ((var1 * i64::from(c.p2)) << 12);
+        var1 = (((1i64 << 47) + var1) * i64::from(c.p1)) >> 33;
+        if var1 == 0 {
+            // Only with a blank calibration
+            return 0;
+        }
+        let mut p = 1_048_576 - i64::from(adc_p);
+        p = (((p << 31) - var2) * 3125) / var1;
+        var1 = (i64::from(c.p9) * (p >> 13) * (p >> 13)) >> 25;
+        var2 = (i64::from(c.p8) * p) >> 19;
+        p = ((p + var1 + var2) >> 8) + (i64::from(c.p7) << 4);
+        // p is in 256ths of a pascal
+        (p >> 8) as u32
+    }
+
+    /// Relative humidity in hundredths of a percent.
+    fn humidity(&self, adc_h: i32, t_fine: i32) -> u32 {
+        let c = &self.calibration;
+        let mut v = t_fine - 76_800;
+        v = (((adc_h << 14) - (i32::from(c.h4) << 20) - (i32::from(c.h5) * v) + 16_384) >> 15)
+            * (((((((v * i32::from(c.h6)) >> 10) * (((v * i32::from(c.h3)) >> 11) + 32_768))
+                >> 10)
+                + 2_097_152)
+                * i32::from(c.h2)
+                + 8192)
+                >> 14);
+        v -= ((((v >> 15) * (v >> 15)) >> 7) * i32::from(c.h1)) >> 4;
+        let v = v.clamp(0, 419_430_400) as u32;
+        // v >> 12 is in 1024ths of a percent
+        (v >> 12) * 100 / 1024
+    }
+
+    fn read(&mut self, register: u8, buf: &mut [u8]) -> Result<(), Error<E>> {
+        self.i2c
+            .write_read(self.address, &[register], buf)
+            .map_err(Error::I2c)
+    }
+
+    fn write(&mut self, register: u8, value: u8) -> Result<(), Error<E>> {
+        self.i2c
+            .write(self.address, &[register, value])
+            .map_err(Error::I2c)
+    }
+}
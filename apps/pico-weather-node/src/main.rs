@@ -0,0 +1,258 @@
+//! # Pico Weather Node
+//!
+//! A battery-powered weather station: a Pico with a BME280 for temperature,
+//! humidity and pressure, a cup anemometer for the wind, and an SX1276 LoRa
+//! radio, such as an RFM95W, to send a reading every minute.
+//!
+//! The node spends almost all of its time asleep, with the PLLs off and only
+//! the RTC and the PWM slices clocked. One PWM slice counts the anemometer's
+//! pulses all the while (see [`wind`]). Every ten seconds the RTC wakes the
+//! node to note the wind, which gives the gust, and every minute it also:
+//!
+//! * reads VSYS and asks an `EnergyBudget` whether the battery can spare a
+//!   transmission,
+//! * if so, reads the BME280 and sends a [`packet`], signed so the gateway
+//!   can tell it came from this node, and not a replay of an older one,
+//! * and otherwise counts the reading as skipped, and the count goes out
+//!   with the next packet.
+//!
+//! Wiring:
+//!
+//! | Pico   | To                                                      |
+//! |--------|---------------------------------------------------------|
+//! | GPIO4  | BME280 SDA                                              |
+//! | GPIO5  | BME280 SCL                                              |
+//! | GPIO9  | Anemometer, with 10 kΩ to 3V3 and 100 nF to ground      |
+//! | GPIO16 | RFM95W MISO                                             |
+//! | GPIO17 | RFM95W NSS                                              |
+//! | GPIO18 | RFM95W SCK                                              |
+//! | GPIO19 | RFM95W MOSI                                             |
+//! | GPIO20 | RFM95W RESET                                            |
+//! | VSYS   | LiPo, through a charger or a diode                      |
+//!
+//! The anemometer's other wire goes to ground.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+mod bme280;
+mod packet;
+mod sx1276;
+mod wind;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use fugit::RateExtU32;
+use hal::Clock;
+
+use hal::gpio::{FunctionI2C, FunctionSpi, PinState};
+use hal::pwm::CountRisingEdge;
+use hal::rtc::{DateTime, DateTimeFilter, DayOfWeek, RealTimeClock};
+use hal::spi;
+
+use rp_hal_boards_common::energy_budget::{EnergyBudget, Storage};
+use rp_hal_boards_common::settings::{default_offset, Store};
+use rp_hal_boards_common::sleep;
+
+use bme280::Bme280;
+use packet::Packet;
+use sx1276::Sx1276;
+use wind::Wind;
+
+/// This node's ID, which the gateway uses to look up its key.
+const NODE_ID: u16 = 1;
+
+/// The key shared with the gateway. Give each node its own.
+const KEY: [u8; 16] = *b"change this key!";
+
+/// Radio settings, which the gateway has to share. 868.1 MHz is for Europe;
+/// use 915 MHz in the Americas.
+const RADIO: sx1276::Config = sx1276::Config {
+    frequency_hz: 868_100_000,
+    spreading_factor: 9,
+    power_dbm: 14,
+};
+
+/// Seconds between wind samples. The gust is the fastest of these.
+const SAMPLE_SECONDS: u8 = 10;
+
+/// Wind samples to each reading: one reading a minute.
+const SAMPLES_PER_READING: u8 = 6;
+
+/// A 1000 mAh LiPo cell.
+const STORAGE: Storage = Storage::Battery {
+    empty_mv: 3300,
+    full_mv: 4200,
+    capacity_mwh: 3700,
+};
+
+/// Kept back for sleeping and counting the wind: about a week at 1 mA.
+const RESERVE_MJ: u32 = 2_000_000;
+
+/// One packet at SF9/125 kHz: about 40 mA at 3.5 V for 250 ms of airtime.
+const RADIO_TX_MJ: u32 = 35;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Run everything from the crystal and leave the PLLs off
+    let clocks = sleep::run_from_xosc(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Count boots in flash, so a packet is never signed with the same boot
+    // count and sequence number twice
+    let mut store = Store::new(default_offset());
+    let mut buf = [0u8; 2];
+    let boot = match store.load(&mut buf) {
+        Some(2) => u16::from_le_bytes(buf).wrapping_add(1),
+        _ => 0,
+    };
+    store.save(&boot.to_le_bytes()).unwrap();
+
+    let i2c = hal::I2C::i2c0(
+        pac.I2C0,
+        pins.gpio4.into_mode::<FunctionI2C>(),
+        pins.gpio5.into_mode::<FunctionI2C>(),
+        100.kHz(),
+        &mut pac.RESETS,
+        &clocks.peripheral_clock,
+    );
+    let mut sensor = Bme280::new(i2c, bme280::ADDRESS).unwrap();
+
+    let _spi_sclk = pins.gpio18.into_mode::<FunctionSpi>();
+    let _spi_mosi = pins.gpio19.into_mode::<FunctionSpi>();
+    let _spi_miso = pins.gpio16.into_mode::<FunctionSpi>();
+    let radio_spi = spi::Spi::<_, _, 8>::new(pac.SPI0).init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        1.MHz(),
+        &embedded_hal::spi::MODE_0,
+    );
+    let radio_cs = pins.gpio17.into_push_pull_output_in_state(PinState::High);
+    let mut radio_reset = pins.gpio20.into_push_pull_output();
+    let mut radio = Sx1276::new(radio_spi, radio_cs, &mut radio_reset, &mut delay, &RADIO).unwrap();
+
+    // The anemometer on PWM4's B input, GPIO9
+    let slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    let mut pwm4 = slices.pwm4.into_mode::<CountRisingEdge>();
+    pwm4.channel_b.input_from(pins.gpio9);
+    let mut wind = Wind::new(pwm4);
+
+    // Set up the ADC and the VSYS divider on GPIO29
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut vsys_pin = pins.voltage_monitor.into_floating_input();
+
+    // The RTC only needs to count from start-up, so any date will do
+    let start = DateTime {
+        year: 2023,
+        month: 1,
+        day: 1,
+        day_of_week: DayOfWeek::Sunday,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    };
+    let mut rtc = RealTimeClock::new(pac.RTC, clocks.rtc_clock, &mut pac.RESETS, start)
+        .ok()
+        .unwrap();
+
+    let mut budget = EnergyBudget::new(STORAGE, RESERVE_MJ);
+    let mut sequence: u32 = 0;
+    let mut skipped: u16 = 0;
+    let mut second: u8 = 0;
+    let mut samples: u8 = 0;
+    loop {
+        second = (second + SAMPLE_SECONDS) % 60;
+        let alarm = DateTimeFilter::default().second(second);
+        sleep::sleep_until_counting(&mut rtc, alarm, &mut core.SCB);
+
+        wind.sample(u32::from(SAMPLE_SECONDS));
+        samples += 1;
+        if samples < SAMPLES_PER_READING {
+            continue;
+        }
+        samples = 0;
+        let (wind_speed, gust) = wind.report();
+
+        budget.sample(&mut adc, &mut vsys_pin);
+        if !budget.allow(RADIO_TX_MJ) {
+            skipped = skipped.saturating_add(1);
+            continue;
+        }
+
+        // A missing reading goes out as an impossible temperature
+        let reading = sensor.measure(&mut delay).unwrap_or(bme280::Reading {
+            temperature: i32::from(i16::MIN),
+            pressure: 0,
+            humidity: 0,
+        });
+        let packet = Packet {
+            node: NODE_ID,
+            boot,
+            sequence,
+            temperature: reading.temperature as i16,
+            humidity: reading.humidity as u16,
+            pressure: reading.pressure,
+            wind: wind_speed,
+            gust,
+            vsys_mv: budget.vsys_mv() as u16,
+            skipped,
+        };
+        sequence = sequence.wrapping_add(1);
+
+        match radio.transmit(&packet.encode(&KEY), &mut delay) {
+            Ok(()) => skipped = 0,
+            Err(_) => {
+                let _ = radio.sleep();
+                skipped = skipped.saturating_add(1);
+            }
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,130 @@
+//! The uplink packet: a reading, signed with a key shared with the gateway.
+//!
+//! Every field is little-endian:
+//!
+//! ```text
+//! offset  size  field
+//!      0     1  format version, 1
+//!      1     2  node ID
+//!      3     2  boot count
+//!      5     4  sequence number, from 0 at each boot
+//!      9     2  temperature, 0.01 °C, signed; 0x8000 if the BME280
+//!                 didn't answer
+//!     11     2  relative humidity, 0.01 %
+//!     13     4  pressure, Pa
+//!     17     2  mean wind speed, 0.1 km/h
+//!     19     2  gust speed, 0.1 km/h
+//!     21     2  VSYS, mV
+//!     23     2  readings skipped to save energy since the last packet
+//!     25     8  SipHash-2-4 of bytes 0 to 24
+//! ```
+//!
+//! The tag proves the packet came from a node holding the key and wasn't
+//! changed on the way. It doesn't hide the reading, which anyone can decode.
+//! The gateway should drop packets whose boot count and sequence number,
+//! taken together, aren't higher than the last it accepted from that node, so
+//! a recorded packet can't be played back. The boot count is kept in flash,
+//! so it goes up even when the battery is changed.
+
+/// Length of an encoded packet.
+pub const LEN: usize = 33;
+
+/// The format version in the first byte.
+const VERSION: u8 = 1;
+
+/// Bytes covered by the tag.
+const SIGNED_LEN: usize = LEN - 8;
+
+/// What goes in each packet.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Packet {
+    pub node: u16,
+    pub boot: u16,
+    pub sequence: u32,
+    pub temperature: i16,
+    pub humidity: u16,
+    pub pressure: u32,
+    pub wind: u16,
+    pub gust: u16,
+    pub vsys_mv: u16,
+    pub skipped: u16,
+}
+
+impl Packet {
+    /// Lay the packet out and sign it with `key`.
+    pub fn encode(&self, key: &[u8; 16]) -> [u8; LEN] {
+        let mut buf = [0; LEN];
+        buf[0] = VERSION;
+        buf[1..3].copy_from_slice(&self.node.to_le_bytes());
+        buf[3..5].copy_from_slice(&self.boot.to_le_bytes());
+        buf[5..9].copy_from_slice(&self.sequence.to_le_bytes());
+        buf[9..11].copy_from_slice(&self.temperature.to_le_bytes());
+        buf[11..13].copy_from_slice(&self.humidity.to_le_bytes());
+        buf[13..17].copy_from_slice(&self.pressure.to_le_bytes());
+        buf[17..19].copy_from_slice(&self.wind.to_le_bytes());
+        buf[19..21].copy_from_slice(&self.gust.to_le_bytes());
+        buf[21..23].copy_from_slice(&self.vsys_mv.to_le_bytes());
+        buf[23..25].copy_from_slice(&self.skipped.to_le_bytes());
+        let tag = siphash24(key, &buf[..SIGNED_LEN]);
+        buf[SIGNED_LEN..].copy_from_slice(&tag.to_le_bytes());
+        buf
+    }
+}
+
+/// SipHash-2-4, a keyed hash short enough for a radio packet and cheap
+/// enough for a microcontroller.
+fn siphash24(key: &[u8; 16], data: &[u8]) -> u64 {
+    let k0 = u64::from_le_bytes([
+        key[0], key[1], key[2], key[3], key[4], key[5], key[6], key[7],
+    ]);
+    let k1 = u64::from_le_bytes([
+        key[8], key[9], key[10], key[11], key[12], key[13], key[14], key[15],
+    ]);
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        let m = u64::from_le_bytes(word);
+        v[3] ^= m;
+        sip_round(&mut v);
+        sip_round(&mut v);
+        v[0] ^= m;
+    }
+
+    // The last word holds the leftover bytes and the length
+    let mut last = [0; 8];
+    let rest = chunks.remainder();
+    last[..rest.len()].copy_from_slice(rest);
+    last[7] = data.len() as u8;
+    let m = u64::from_le_bytes(last);
+    v[3] ^= m;
+    sip_round(&mut v);
+    sip_round(&mut v);
+    v[0] ^= m;
+
+    v[2] ^= 0xFF;
+    for _ in 0..4 {
+        sip_round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+fn sip_round(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13) ^ v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16) ^ v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21) ^ v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17) ^ v[2];
+    v[2] = v[2].rotate_left(32);
+}
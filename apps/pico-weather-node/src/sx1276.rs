@@ -0,0 +1,194 @@
+//! Just enough of a Semtech SX1276 driver to send LoRa packets, as fitted to
+//! HopeRF's RFM95W and most other LoRa modules.
+//!
+//! The radio is set up once with [`Sx1276::new`] and then sleeps, drawing
+//! about 0.2 µA, between calls to [`Sx1276::transmit`]. Nothing is ever
+//! received, so DIO0 needn't be wired: the end of a transmission is polled
+//! from the interrupt flags.
+
+use core::convert::Infallible;
+
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::digital::v2::OutputPin;
+
+const REG_FIFO: u8 = 0x00;
+const REG_OP_MODE: u8 = 0x01;
+const REG_FRF_MSB: u8 = 0x06;
+const REG_PA_CONFIG: u8 = 0x09;
+const REG_FIFO_ADDR_PTR: u8 = 0x0D;
+const REG_FIFO_TX_BASE_ADDR: u8 = 0x0E;
+const REG_IRQ_FLAGS: u8 = 0x12;
+const REG_MODEM_CONFIG_1: u8 = 0x1D;
+const REG_MODEM_CONFIG_2: u8 = 0x1E;
+const REG_PREAMBLE_MSB: u8 = 0x20;
+const REG_PAYLOAD_LENGTH: u8 = 0x22;
+const REG_MODEM_CONFIG_3: u8 = 0x26;
+const REG_SYNC_WORD: u8 = 0x39;
+const REG_VERSION: u8 = 0x42;
+
+const VERSION: u8 = 0x12;
+
+/// The LoRa bit of `RegOpMode`, which can only change in sleep mode.
+const MODE_LONG_RANGE: u8 = 0x80;
+const MODE_SLEEP: u8 = 0x00;
+const MODE_STANDBY: u8 = 0x01;
+const MODE_TX: u8 = 0x03;
+
+const IRQ_TX_DONE: u8 = 0x08;
+
+/// The sync word for private networks, which LoRaWAN gateways ignore.
+const SYNC_WORD: u8 = 0x12;
+
+/// The crystal on every module, in hertz.
+const XTAL_HZ: u64 = 32_000_000;
+
+/// Longest wait for a packet to go out, in milliseconds. A full 255 byte
+/// packet at SF12 takes longer, but the settings here never get near it.
+const TX_TIMEOUT_MS: u32 = 2000;
+
+/// Errors from the radio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The SPI transfer failed.
+    Spi(E),
+    /// Something answered, but not with an SX1276's version.
+    WrongChip(u8),
+    /// The packet didn't finish sending in time.
+    Timeout,
+}
+
+/// Radio settings. Both ends have to agree on all but the power.
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    /// Carrier frequency in hertz, e.g. 868.1 MHz in Europe or 915 MHz in
+    /// the Americas.
+    pub frequency_hz: u32,
+    /// Spreading factor, from 7 to 12. Each step up gains about 2.5 dB of
+    /// range, and doubles the time on air and the energy per packet.
+    pub spreading_factor: u8,
+    /// Transmit power on the PA_BOOST pin, from 2 to 17 dBm.
+    pub power_dbm: u8,
+}
+
+/// An SX1276 on an SPI bus, with its own chip select.
+pub struct Sx1276<SPI, CS> {
+    spi: SPI,
+    cs: CS,
+}
+
+impl<SPI, CS, E> Sx1276<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin<Error = Infallible>,
+{
+    /// Reset the radio through `reset`, check its version and set it up for
+    /// 125 kHz bandwidth, coding rate 4/5 and a CRC on each packet, leaving
+    /// it asleep. The SPI bus must be in mode 0, at up to 10 MHz.
+    pub fn new<RESET, D>(
+        spi: SPI,
+        mut cs: CS,
+        reset: &mut RESET,
+        delay: &mut D,
+        config: &Config,
+    ) -> Result<Self, Error<E>>
+    where
+        RESET: OutputPin<Error = Infallible>,
+        D: DelayMs<u32> + DelayUs<u32>,
+    {
+        let _ = cs.set_high();
+        let _ = reset.set_low();
+        delay.delay_us(100);
+        let _ = reset.set_high();
+        delay.delay_ms(5);
+
+        let mut radio = Sx1276 { spi, cs };
+        let version = radio.read(REG_VERSION)?;
+        if version != VERSION {
+            return Err(Error::WrongChip(version));
+        }
+
+        radio.write(REG_OP_MODE, MODE_SLEEP)?;
+        radio.write(REG_OP_MODE, MODE_LONG_RANGE | MODE_SLEEP)?;
+
+        let frf = (u64::from(config.frequency_hz) << 19) / XTAL_HZ;
+        radio.write_burst(
+            REG_FRF_MSB,
+            &[(frf >> 16) as u8, (frf >> 8) as u8, frf as u8],
+        )?;
+        let power = config.power_dbm.clamp(2, 17);
+        radio.write(REG_PA_CONFIG, 0x80 | 0x70 | (power - 2))?;
+
+        let sf = config.spreading_factor.clamp(7, 12);
+        // 125 kHz, coding rate 4/5, explicit header
+        radio.write(REG_MODEM_CONFIG_1, 0x72)?;
+        // CRC on
+        radio.write(REG_MODEM_CONFIG_2, sf << 4 | 0x04)?;
+        // AGC on, and low data rate optimisation where symbols pass 16 ms
+        let low_data_rate = if sf >= 11 { 0x08 } else { 0 };
+        radio.write(REG_MODEM_CONFIG_3, 0x04 | low_data_rate)?;
+        radio.write_burst(REG_PREAMBLE_MSB, &[0, 8])?;
+        radio.write(REG_SYNC_WORD, SYNC_WORD)?;
+        radio.write(REG_FIFO_TX_BASE_ADDR, 0)?;
+        Ok(radio)
+    }
+
+    /// Send `packet`, of up to 255 bytes, and wait for it to go out before
+    /// putting the radio back to sleep.
+    pub fn transmit<D: DelayMs<u32>>(
+        &mut self,
+        packet: &[u8],
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        let packet = &packet[..packet.len().min(255)];
+        self.write(REG_OP_MODE, MODE_LONG_RANGE | MODE_STANDBY)?;
+        self.write(REG_FIFO_ADDR_PTR, 0)?;
+        self.write_burst(REG_FIFO, packet)?;
+        self.write(REG_PAYLOAD_LENGTH, packet.len() as u8)?;
+        self.write(REG_IRQ_FLAGS, 0xFF)?;
+        self.write(REG_OP_MODE, MODE_LONG_RANGE | MODE_TX)?;
+
+        let mut sent = false;
+        for _ in 0..TX_TIMEOUT_MS {
+            if self.read(REG_IRQ_FLAGS)? & IRQ_TX_DONE != 0 {
+                sent = true;
+                break;
+            }
+            delay.delay_ms(1);
+        }
+        self.write(REG_IRQ_FLAGS, 0xFF)?;
+        self.sleep()?;
+        if sent {
+            Ok(())
+        } else {
+            Err(Error::Timeout)
+        }
+    }
+
+    /// Put the radio to sleep, e.g. after an error left it awake.
+    pub fn sleep(&mut self) -> Result<(), Error<E>> {
+        self.write(REG_OP_MODE, MODE_LONG_RANGE | MODE_SLEEP)
+    }
+
+    fn read(&mut self, register: u8) -> Result<u8, Error<E>> {
+        let _ = self.cs.set_low();
+        let mut buf = [register & 0x7F, 0];
+        let result = self.spi.transfer(&mut buf).map(|buf| buf[1]);
+        let _ = self.cs.set_high();
+        result.map_err(Error::Spi)
+    }
+
+    fn write(&mut self, register: u8, value: u8) -> Result<(), Error<E>> {
+        self.write_burst(register, &[value])
+    }
+
+    fn write_burst(&mut self, register: u8, values: &[u8]) -> Result<(), Error<E>> {
+        let _ = self.cs.set_low();
+        let result = self
+            .spi
+            .write(&[register | 0x80])
+            .and_then(|_| self.spi.write(values));
+        let _ = self.cs.set_high();
+        result.map_err(Error::Spi)
+    }
+}
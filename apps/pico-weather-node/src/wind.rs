@@ -0,0 +1,74 @@
+//! Wind speed from a cup anemometer's reed switch, counted by a PWM slice.
+//!
+//! A slice in [`CountRisingEdge`] mode counts the switch's pulses on its B
+//! pin without waking the processors, as long as the slices stay clocked in
+//! sleep (see `rp_hal_boards_common::sleep::sleep_until_counting`). Each
+//! [`Wind::sample`] takes the pulses since the last one; the highest speed
+//! over one sample is the gust, and the pulses over all of them give the
+//! mean.
+
+use rp_pico::hal::pwm::{CountRisingEdge, Pwm4, Slice};
+
+/// Tenths of a km/h per pulse per second, for the anemometer sold by
+/// SparkFun and Argent Data: it closes once a turn, and one turn a second is
+/// 2.4 km/h.
+const TENTHS_KMH_PER_HZ: u32 = 24;
+
+/// Pulses counted since the last report.
+pub struct Wind {
+    slice: Slice<Pwm4, CountRisingEdge>,
+    last_count: u16,
+    pulses: u32,
+    seconds: u32,
+    gust: u32,
+}
+
+impl Wind {
+    /// Count on `slice`, whose B pin, GPIO9, must already be wired to the
+    /// anemometer.
+    pub fn new(mut slice: Slice<Pwm4, CountRisingEdge>) -> Self {
+        slice.set_div_int(1);
+        slice.set_div_frac(0);
+        slice.enable();
+        let last_count = slice.get_counter();
+        Wind {
+            slice,
+            last_count,
+            pulses: 0,
+            seconds: 0,
+            gust: 0,
+        }
+    }
+
+    /// Take the pulses counted over the last `seconds`. The 16 bit counter
+    /// wraps after 65535 pulses, so sample well before that: at 200 km/h, it
+    /// takes over 18 minutes.
+    pub fn sample(&mut self, seconds: u32) {
+        let count = self.slice.get_counter();
+        let pulses = u32::from(count.wrapping_sub(self.last_count));
+        self.last_count = count;
+
+        self.pulses += pulses;
+        self.seconds += seconds;
+        if let Some(speed) = (pulses * TENTHS_KMH_PER_HZ).checked_div(seconds) {
+            self.gust = self.gust.max(speed);
+        }
+    }
+
+    /// The mean and gust speeds since the last report, in tenths of a km/h,
+    /// starting again from zero.
+    pub fn report(&mut self) -> (u16, u16) {
+        let mean = (self.pulses * TENTHS_KMH_PER_HZ)
+            .checked_div(self.seconds)
+            .unwrap_or(0);
+        let gust = self.gust;
+        self.pulses = 0;
+        self.seconds = 0;
+        self.gust = 0;
+        (clamp_u16(mean), clamp_u16(gust))
+    }
+}
+
+fn clamp_u16(value: u32) -> u16 {
+    value.min(u32::from(u16::MAX)) as u16
+}
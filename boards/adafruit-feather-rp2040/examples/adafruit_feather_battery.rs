@@ -0,0 +1,118 @@
+//! Reports the battery's charge over USB serial every two seconds
+//!
+//! Needs a MAX17048 or LC709203F fuel gauge breakout on the STEMMA QT
+//! connector, with a LiPo cell on its battery connector; set
+//! [`CAPACITY_MAH`] to the cell's capacity for the LC709203F. Without a
+//! gauge, it says so instead.
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use adafruit_feather_rp2040::entry;
+use adafruit_feather_rp2040::{
+    hal::{self, clocks::init_clocks_and_plls, pac, watchdog::Watchdog, Sio},
+    Pins, XOSC_CRYSTAL_FREQ,
+};
+use panic_halt as _;
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+
+/// The cell's capacity, in mAh.
+const CAPACITY_MAH: u16 = 500;
+
+/// Time between reports, in microseconds.
+const REPORT_US: u64 = 2_000_000;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // A missing gauge is reported over USB rather than panicking
+    let mut gauge = adafruit_feather_rp2040::battery(
+        pac.I2C1,
+        pins.sda,
+        pins.scl,
+        CAPACITY_MAH,
+        &mut pac.RESETS,
+        &clocks.system_clock,
+    );
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Battery")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut next_report = timer.get_counter().ticks() + REPORT_US;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let now = timer.get_counter().ticks();
+        if now < next_report {
+            continue;
+        }
+        next_report = now + REPORT_US;
+
+        match &mut gauge {
+            Ok(gauge) => match (gauge.percent(), gauge.millivolts()) {
+                (Ok(percent), Ok(millivolts)) => {
+                    let _ = write!(
+                        serial,
+                        "{:?}: {} %, {}.{:03} V\r\n",
+                        gauge.chip(),
+                        percent,
+                        millivolts / 1000,
+                        millivolts % 1000
+                    );
+                }
+                (Err(error), _) | (_, Err(error)) => {
+                    let _ = write!(serial, "fuel gauge error: {:?}\r\n", error);
+                }
+            },
+            Err(error) => {
+                let _ = write!(serial, "no fuel gauge: {:?}\r\n", error);
+            }
+        }
+    }
+}
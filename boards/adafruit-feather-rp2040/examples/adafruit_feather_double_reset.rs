@@ -0,0 +1,68 @@
+//! Blinks the LED on a Adafruit Feather RP2040 board, and enters the UF2
+//! bootloader on a double press of reset
+//!
+//! Press reset twice within half a second and the board shows up as a USB
+//! drive, ready for the next UF2, without having to hold BOOT. The red LED
+//! flickers while the host talks to the bootloader.
+#![no_std]
+#![no_main]
+
+use adafruit_feather_rp2040::entry;
+use adafruit_feather_rp2040::{
+    hal::{
+        clocks::{init_clocks_and_plls, Clock},
+        pac,
+        watchdog::Watchdog,
+        Sio,
+    },
+    Pins, XOSC_CRYSTAL_FREQ,
+};
+use embedded_hal::digital::v2::OutputPin;
+use panic_halt as _;
+
+use rp_hal_boards_common::{delay, double_reset};
+
+/// The red LED next to the USB connector.
+const LED_GPIO: u8 = 13;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // Check for a double press before doing anything else
+    delay::calibrate(&clocks.system_clock);
+    double_reset::double_reset_bootloader_with(double_reset::DEFAULT_WINDOW_MS, Some(LED_GPIO));
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let mut led_pin = pins.d13.into_push_pull_output();
+
+    loop {
+        led_pin.set_high().unwrap();
+        delay.delay_ms(1500);
+        led_pin.set_low().unwrap();
+        delay.delay_ms(1500);
+    }
+}
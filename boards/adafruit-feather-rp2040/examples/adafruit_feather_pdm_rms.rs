@@ -0,0 +1,131 @@
+//! Prints the loudness at a PDM microphone over USB serial, ten times a second
+//!
+//! Needs a PDM MEMS microphone breakout, such as Adafruit's MP34DT01-M board:
+//! its data pin on D4, its clock on D5 and its L/R select tied low. Each line
+//! gives the RMS amplitude of the last 100 ms, out of 23170 for a full-scale
+//! sine, its level in dBFS and a bar to go with it.
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+use adafruit_feather_rp2040::entry;
+use adafruit_feather_rp2040::{
+    hal::{self, clocks::init_clocks_and_plls, pac, pio::PIOExt, watchdog::Watchdog, Clock, Sio},
+    Pins, XOSC_CRYSTAL_FREQ,
+};
+use panic_halt as _;
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::pio_pdm::{dbfs_tenths, rms, PdmMic};
+
+/// The microphone's clock.
+const PDM_CLOCK_HZ: u32 = 2_048_000;
+
+/// PDM bits per PCM sample, giving 16 kHz.
+const DECIMATION: u32 = 128;
+
+/// Samples in each frame the microphone is read in: 20 ms.
+const FRAME: usize = 320;
+
+/// Frames per reading: 100 ms.
+const FRAMES: usize = 5;
+
+/// Width of the bar at 0 dBFS, each character being 2 dB.
+const BAR: usize = 40;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Hand the microphone's pins, GPIO 6 and 7, to PIO0
+    let _data = pins.d4.into_mode::<hal::gpio::FunctionPio0>();
+    let _clock = pins.d5.into_mode::<hal::gpio::FunctionPio0>();
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut mic = PdmMic::new(
+        &mut pio,
+        sm0,
+        6,
+        7,
+        PDM_CLOCK_HZ,
+        clocks.system_clock.freq().to_Hz(),
+        DECIMATION,
+    );
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("PDM loudness")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut window = [0i16; FRAME * FRAMES];
+
+    loop {
+        // A frame at a time, polling USB in between so the host isn't kept
+        // waiting for 100 ms
+        for frame in window.chunks_exact_mut(FRAME) {
+            mic.read_frame(frame);
+            usb_dev.poll(&mut [&mut serial]);
+        }
+
+        let amplitude = rms(&window);
+        match dbfs_tenths(&window) {
+            Some(level) => {
+                let _ = write!(
+                    serial,
+                    "rms {:5}  {}{:2}.{} dBFS  ",
+                    amplitude,
+                    if level < 0 { "-" } else { " " },
+                    level.unsigned_abs() / 10,
+                    level.unsigned_abs() % 10
+                );
+                // -80 dBFS and below is an empty bar
+                let width = (BAR as i32 + i32::from(level) / 20).clamp(0, BAR as i32);
+                for _ in 0..width {
+                    let _ = serial.write_all(b"#");
+                }
+                let _ = serial.write_all(b"\r\n");
+            }
+            None => {
+                let _ = write!(serial, "rms     0  silent\r\n");
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Shows what the board is doing on the Feather's onboard NeoPixel
+//!
+//! The NeoPixel blinks blue while booting, turns amber once running, and
+//! green when a USB host has configured the board. Over the USB serial port:
+//!
+//! * `l` toggles logging, shown as green going dark once a second,
+//! * `1` to `9` show that error number as red flashes, `0` clears it,
+//! * `p` panics, and the panic handler flickers the NeoPixel red.
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::panic::PanicInfo;
+
+use adafruit_feather_rp2040::entry;
+use adafruit_feather_rp2040::{
+    hal::{
+        self,
+        clocks::init_clocks_and_plls,
+        gpio::{bank0::Gpio16, Pin, PushPullOutput},
+        pac,
+        watchdog::Watchdog,
+        Sio,
+    },
+    Pins, XOSC_CRYSTAL_FREQ,
+};
+use critical_section::Mutex;
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::bitbang::Ws2812;
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::delay;
+use rp_hal_boards_common::status_led::{self, State, StatusLed};
+
+type Neopixel = Ws2812<Pin<Gpio16, PushPullOutput>>;
+
+/// The NeoPixel, shared with the panic handler.
+static NEOPIXEL: Mutex<RefCell<Option<Neopixel>>> = Mutex::new(RefCell::new(None));
+
+/// Brightness, out of 255.
+const BRIGHTNESS: u8 = 32;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The bit-banged driver takes its timing from the calibrated delays, and
+    // needs no timer or state machine, so the panic handler can use it too
+    delay::calibrate(&clocks.system_clock);
+
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let neopixel = Ws2812::new(pins.neopixel.into_push_pull_output());
+    critical_section::with(|cs| NEOPIXEL.borrow(cs).replace(Some(neopixel)));
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+    let now_ms = || (timer.get_counter().ticks() / 1000) as u32;
+
+    let mut status = StatusLed::new();
+    status.set_brightness(BRIGHTNESS);
+    status.set(State::Booting, now_ms());
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Status LED")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    // Show the boot pattern for a moment, so it can be seen
+    let booted_ms = now_ms() + 1000;
+    let mut logging = false;
+    let mut error = 0u8;
+    let mut last_shown = None;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let mut buf = [0u8; 1];
+        if serial.read(&mut buf) == 1 {
+            match buf[0] {
+                b'l' => logging = !logging,
+                digit @ b'0'..=b'9' => error = digit - b'0',
+                b'p' => panic!("asked to over USB serial"),
+                _ => {}
+            }
+        }
+
+        let now = now_ms();
+        if now >= booted_ms {
+            let state = if error != 0 {
+                State::Error(error)
+            } else if logging {
+                State::Logging
+            } else if usb_dev.state() == UsbDeviceState::Configured {
+                State::UsbConnected
+            } else {
+                State::Ready
+            };
+            status.set(state, now);
+        }
+
+        // Each write holds interrupts off for about 30 us, so only send
+        // changes
+        let colour = status.colour(now);
+        if last_shown != Some(colour) {
+            critical_section::with(|cs| {
+                if let Some(neopixel) = NEOPIXEL.borrow(cs).borrow_mut().as_mut() {
+                    neopixel.write(core::iter::once(colour));
+                }
+            });
+            last_shown = Some(colour);
+        }
+    }
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    let neopixel = critical_section::with(|cs| NEOPIXEL.borrow(cs).take());
+    match neopixel {
+        Some(mut neopixel) => status_led::panic_loop(BRIGHTNESS, |colour| {
+            neopixel.write(core::iter::once(colour))
+        }),
+        // Panicked before the NeoPixel was set up
+        None => loop {
+            cortex_m::asm::wfi();
+        },
+    }
+}
@@ -64,3 +64,43 @@ hal::bsp_pins!(
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+pub use rp_hal_boards_common::fuel_gauge::{Error as FuelGaugeError, FuelGauge};
+
+/// The STEMMA QT connector's I²C bus: I2C1, on the `sda` and `scl` pins.
+pub type StemmaI2c = hal::I2C<pac::I2C1, (Sda, Scl)>;
+
+/// Find the LiPo fuel gauge on the STEMMA QT bus, a MAX17048 or LC709203F,
+/// and set it up for a cell of `capacity_mah`.
+///
+/// The Feather RP2040 has no fuel gauge of its own; plug a breakout into the
+/// STEMMA QT connector and its battery leads into the JST connector. The bus
+/// runs at 100 kHz, which other sensors sharing it can use through
+/// [`FuelGauge::free`].
+pub fn battery<MSda, MScl>(
+    i2c1: pac::I2C1,
+    sda: hal::gpio::Pin<hal::gpio::bank0::Gpio2, MSda>,
+    scl: hal::gpio::Pin<hal::gpio::bank0::Gpio3, MScl>,
+    capacity_mah: u16,
+    resets: &mut pac::RESETS,
+    system_clock: &hal::clocks::SystemClock,
+) -> Result<FuelGauge<StemmaI2c>, FuelGaugeError<hal::i2c::Error>>
+where
+    MSda: hal::gpio::PinMode + hal::gpio::ValidPinMode<hal::gpio::bank0::Gpio2>,
+    MScl: hal::gpio::PinMode + hal::gpio::ValidPinMode<hal::gpio::bank0::Gpio3>,
+{
+    use fugit::RateExtU32;
+    use hal::clocks::Clock;
+
+    let i2c = hal::I2C::i2c1(
+        i2c1,
+        sda.into_mode(),
+        scl.into_mode(),
+        100.kHz(),
+        resets,
+        system_clock.freq(),
+    );
+    FuelGauge::new(i2c, capacity_mah)
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
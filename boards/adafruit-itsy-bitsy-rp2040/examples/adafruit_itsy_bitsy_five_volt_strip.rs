@@ -0,0 +1,104 @@
+//! Drives a 5 V NeoPixel strip from the `5!` pin of an Adafruit ItsyBitsy RP2040
+//!
+//! The `5!` pin goes through a level shifter, so it can drive the data line of
+//! a 5 V powered NeoPixel strip directly. Connect the strip's data input to
+//! `5!`, its power to `USB` and its ground to `G`. A light chases along the
+//! strip, the on-board NeoPixel shows the same colour and the red user LED
+//! blinks once per lap.
+#![no_std]
+#![no_main]
+
+use adafruit_itsy_bitsy_rp2040::entry;
+use core::iter::once;
+use embedded_hal::digital::v2::ToggleableOutputPin;
+use embedded_hal::timer::CountDown;
+use fugit::ExtU32;
+use panic_halt as _;
+use smart_leds::{brightness, SmartLedsWrite, RGB8};
+use ws2812_pio::Ws2812;
+
+use adafruit_itsy_bitsy_rp2040::{
+    hal::{
+        clocks::{init_clocks_and_plls, Clock},
+        gpio::FunctionPio0,
+        pac,
+        pio::PIOExt,
+        watchdog::Watchdog,
+        Sio, Timer,
+    },
+    LedPin, NeoPixel, Pins, XOSC_CRYSTAL_FREQ,
+};
+
+/// Number of LEDs on the external strip.
+const STRIP_LEN: usize = 30;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = Sio::new(pac.SIO);
+
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led: LedPin = pins.d13.into_push_pull_output();
+
+    let neopixel = NeoPixel::new(
+        pins.neopixel_power.into_push_pull_output(),
+        pins.neopixel_data.into_mode(),
+    );
+    let (_power, neopixel_data) = neopixel.split();
+
+    let timer = Timer::new(pac.TIMER, &mut pac.RESETS);
+    let mut delay = timer.count_down();
+
+    // One state machine for the on-board NeoPixel, one for the strip on `5!`
+    let (mut pio, sm0, sm1, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut onboard = Ws2812::new(
+        neopixel_data,
+        &mut pio,
+        sm0,
+        clocks.peripheral_clock.freq(),
+        timer.count_down(),
+    );
+    let mut strip = Ws2812::new(
+        pins.d5.into_mode::<FunctionPio0>(),
+        &mut pio,
+        sm1,
+        clocks.peripheral_clock.freq(),
+        timer.count_down(),
+    );
+
+    let colours: [RGB8; 3] = [(255, 0, 0).into(), (0, 255, 0).into(), (0, 0, 255).into()];
+    let mut lap = 0;
+    loop {
+        let colour = colours[lap % colours.len()];
+        for head in 0..STRIP_LEN {
+            let pixels = (0..STRIP_LEN).map(|i| if i == head { colour } else { RGB8::default() });
+            strip.write(brightness(pixels, 64)).unwrap();
+            onboard.write(brightness(once(colour), 32)).unwrap();
+
+            delay.start(20.millis());
+            let _ = nb::block!(delay.wait());
+        }
+        led.toggle().unwrap();
+        lap += 1;
+    }
+}
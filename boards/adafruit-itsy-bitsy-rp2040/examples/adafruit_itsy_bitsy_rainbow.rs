@@ -13,13 +13,12 @@ use ws2812_pio::Ws2812;
 use adafruit_itsy_bitsy_rp2040::{
     hal::{
         clocks::{init_clocks_and_plls, Clock},
-        gpio::PinState,
         pac,
         pio::PIOExt,
         watchdog::Watchdog,
         Sio, Timer,
     },
-    Pins, XOSC_CRYSTAL_FREQ,
+    NeoPixel, Pins, XOSC_CRYSTAL_FREQ,
 };
 
 #[entry]
@@ -48,10 +47,12 @@ fn main() -> ! {
         &mut pac.RESETS,
     );
 
-    let led = pins.neopixel_data.into_mode();
-
-    pins.neopixel_power
-        .into_push_pull_output_in_state(PinState::High);
+    // The NeoPixel needs its power switched on before it will listen
+    let neopixel = NeoPixel::new(
+        pins.neopixel_power.into_push_pull_output(),
+        pins.neopixel_data.into_mode(),
+    );
+    let (_power, led) = neopixel.split();
 
     let timer = Timer::new(pac.TIMER, &mut pac.RESETS);
     let mut delay = timer.count_down();
@@ -17,53 +17,133 @@ pub static BOOT2_FIRMWARE: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
 
 pub use hal::pac;
 
+use embedded_hal::digital::v2::OutputPin;
+
 hal::bsp_pins!(
+    /// Pin `TX`. Functions: UART0 TX, I2C0 SDA, SPI0 RX, PWM0 A
     Gpio0 {
         name: tx,
         aliases: { FunctionUart: UartTx }
     },
+    /// Pin `RX`. Functions: UART0 RX, I2C0 SCL, SPI0 CSn, PWM0 B
     Gpio1 {
         name: rx,
         aliases: { FunctionUart: UartRx }
     },
+    /// Pin `SDA`. Functions: I2C1 SDA, SPI0 SCK, PWM1 A
     Gpio2 {
         name: sda,
         aliases: { FunctionI2C: Sda }
     },
+    /// Pin `SCL`. Functions: I2C1 SCL, SPI0 TX, PWM1 B
     Gpio3 {
         name: scl,
         aliases: { FunctionI2C: Scl }
     },
+    /// Pin `4`. Functions: UART1 TX, I2C0 SDA, SPI0 RX, PWM2 A
     Gpio4 { name: d4 },
+    /// Pin `3`. Functions: UART1 RX, I2C0 SCL, SPI0 CSn, PWM2 B
     Gpio5 { name: d3 },
+    /// Pin `7`. Functions: I2C1 SDA, SPI0 SCK, PWM3 A
     Gpio6 { name: d7 },
+    /// Pin `9`. Functions: I2C1 SCL, SPI0 TX, PWM3 B
     Gpio7 { name: d9 },
+    /// Pin `10`. Functions: UART1 TX, I2C0 SDA, SPI1 RX, PWM4 A
     Gpio8 { name: d10 },
+    /// Pin `11`. Functions: UART1 RX, I2C0 SCL, SPI1 CSn, PWM4 B
     Gpio9 { name: d11 },
+    /// Pin `12`. Functions: I2C1 SDA, SPI1 SCK, PWM5 A
     Gpio10 { name: d12 },
+    /// Pin `13`, also wired to the red user LED. See [`LedPin`].
+    ///
+    /// Functions: I2C1 SCL, SPI1 TX, PWM5 B
     Gpio11 { name: d13 },
+    /// Pin `2`. Functions: I2C0 SDA, UART0 TX, SPI1 RX, PWM6 A
     Gpio12 { name: d2 },
+    /// Pin `5!`, buffered through a level shifter so it always drives 5 V
+    /// logic. Output only. See [`FivePin`].
+    ///
+    /// Functions: I2C1 SDA, UART0 CTS, SPI1 SCK, PWM7 A
     Gpio14 { name: d5 },
+    /// Power switch for the on-board NeoPixel; drive high to turn it on.
     Gpio16 { name: neopixel_power },
+    /// Data line of the on-board NeoPixel. See [`NeoPixel`].
     Gpio17 { name: neopixel_data },
+    /// Pin `SCK`. Functions: SPI0 SCK, I2C0 SDA, PWM1 A
     Gpio18 {
         name: sclk,
         aliases: { FunctionSpi: Sclk }
     },
+    /// Pin `MO`. Functions: SPI0 TX, I2C0 SCL, PWM1 B
     Gpio19 {
         name: mosi,
         aliases: { FunctionSpi: Mosi }
     },
+    /// Pin `MI`. Functions: SPI0 RX, I2C0 SDA, UART1 TX, PWM2 A
     Gpio20 {
-        name: miso
+        name: miso,
         aliases: { FunctionSpi: Miso }
     },
+    /// Pin `24`. Functions: I2C0 SDA, UART1 TX, SPI1 RX, PWM4 A
     Gpio24 { name: d24 },
+    /// Pin `25`. Functions: I2C0 SCL, UART1 RX, SPI1 CSn, PWM4 B
     Gpio25 { name: d25 },
+    /// Pin `A0`. Functions: ADC0, I2C1 SDA, SPI1 SCK, PWM5 A
     Gpio26 { name: a0 },
+    /// Pin `A1`. Functions: ADC1, I2C1 SCL, SPI1 TX, PWM5 B
     Gpio27 { name: a1 },
+    /// Pin `A2`. Functions: ADC2, I2C0 SDA, SPI1 RX, UART0 TX, PWM6 A
     Gpio28 { name: a2 },
+    /// Pin `A3`. Functions: ADC3, I2C0 SCL, SPI1 CSn, UART0 RX, PWM6 B
     Gpio29 { name: a3 },
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+/// The red user LED next to the USB connector, on pin `13`.
+pub type LedPin = hal::gpio::Pin<hal::gpio::bank0::Gpio11, hal::gpio::PushPullOutput>;
+
+/// The `5!` output, level-shifted to 5 V.
+pub type FivePin = hal::gpio::Pin<hal::gpio::bank0::Gpio14, hal::gpio::PushPullOutput>;
+
+/// The NeoPixel power switch.
+pub type NeoPixelPowerPin = hal::gpio::Pin<hal::gpio::bank0::Gpio16, hal::gpio::PushPullOutput>;
+
+/// The NeoPixel data line, handed to the PIO so it can be driven by `ws2812-pio`.
+pub type NeoPixelDataPin = hal::gpio::Pin<hal::gpio::bank0::Gpio17, hal::gpio::FunctionPio0>;
+
+/// The on-board NeoPixel and its power switch.
+///
+/// The NeoPixel is unpowered after reset, so without switching
+/// `neopixel_power` on, writing to `neopixel_data` does nothing.
+pub struct NeoPixel {
+    power: NeoPixelPowerPin,
+    data: NeoPixelDataPin,
+}
+
+impl NeoPixel {
+    /// Take the two NeoPixel pins and switch the power on.
+    pub fn new(mut power: NeoPixelPowerPin, data: NeoPixelDataPin) -> Self {
+        power.set_high().unwrap();
+        NeoPixel { power, data }
+    }
+
+    /// Switch the NeoPixel's power on.
+    pub fn power_on(&mut self) {
+        self.power.set_high().unwrap();
+    }
+
+    /// Switch the NeoPixel's power off, so it draws no current at all.
+    pub fn power_off(&mut self) {
+        self.power.set_low().unwrap();
+    }
+
+    /// Split into the power pin and the data pin, leaving the power as it is.
+    ///
+    /// Pass the data pin to `ws2812_pio::Ws2812::new`.
+    pub fn split(self) -> (NeoPixelPowerPin, NeoPixelDataPin) {
+        (self.power, self.data)
+    }
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
@@ -0,0 +1,163 @@
+//! # USB HID Keyboard Example for the Adafruit KB2040
+//!
+//! Turns the KB2040 into a four-key USB keyboard. Wire a switch from each of
+//! `D4`, `D5`, `D6` and `D7` to ground; they type `a`, `b`, `c` and `d`.
+//!
+//! The on-board NeoPixel lights up while any key is held down.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use adafruit_kb2040::entry;
+use core::iter::once;
+use embedded_hal::digital::v2::InputPin;
+use panic_halt as _;
+
+use adafruit_kb2040::{
+    hal::{
+        clocks::{init_clocks_and_plls, Clock},
+        pac,
+        pio::PIOExt,
+        timer::Timer,
+        usb::UsbBus,
+        watchdog::Watchdog,
+        Sio,
+    },
+    XOSC_CRYSTAL_FREQ,
+};
+use smart_leds::{brightness, SmartLedsWrite, RGB8};
+use ws2812_pio::Ws2812;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Human Interface Device (HID) Class support
+use usbd_hid::descriptor::generator_prelude::*;
+use usbd_hid::descriptor::KeyboardReport;
+use usbd_hid::hid_class::HIDClass;
+
+/// HID usage codes for the keys on `D4` to `D7`.
+const KEYCODES: [u8; 4] = [0x04, 0x05, 0x06, 0x07];
+
+/// How often the keys are scanned and a report is sent, in microseconds.
+const SCAN_PERIOD_US: u64 = 10_000;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this
+/// function as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then polls USB and scans
+/// the keys in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Configure the RP2040 peripherals
+
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = Sio::new(pac.SIO);
+
+    let pins = adafruit_kb2040::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let timer = Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // Configure the addressable LED
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let neopixel: adafruit_kb2040::NeoPixelPin = pins.neopixel.into_mode();
+    let mut ws = Ws2812::new(
+        neopixel,
+        &mut pio,
+        sm0,
+        clocks.peripheral_clock.freq(),
+        timer.count_down(),
+    );
+
+    // The keys pull their pins to ground when pressed
+    let d4 = pins.d4.into_pull_up_input();
+    let d5 = pins.d5.into_pull_up_input();
+    let d6 = pins.d6.into_pull_up_input();
+    let d7 = pins.d7.into_pull_up_input();
+    let keys: [&dyn InputPin<Error = core::convert::Infallible>; 4] = [&d4, &d5, &d6, &d7];
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB HID Class Device driver, providing Keyboard Reports
+    let mut usb_hid = HIDClass::new(&usb_bus, KeyboardReport::desc(), 10);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27db))
+        .manufacturer("Fake company")
+        .product("KB2040 Keys")
+        .serial_number("TEST")
+        .device_class(0)
+        .build();
+
+    let mut next_scan = timer.get_counter().ticks() + SCAN_PERIOD_US;
+    let mut last_report = [0u8; 6];
+    loop {
+        usb_dev.poll(&mut [&mut usb_hid]);
+
+        if timer.get_counter().ticks() < next_scan {
+            continue;
+        }
+        next_scan += SCAN_PERIOD_US;
+
+        let mut keycodes = [0u8; 6];
+        let mut pressed = 0;
+        for (key, code) in keys.iter().zip(KEYCODES.iter()) {
+            if key.is_low().unwrap() {
+                keycodes[pressed] = *code;
+                pressed += 1;
+            }
+        }
+
+        // Only report changes, so the host sees key-up and key-down events
+        if keycodes != last_report {
+            let report = KeyboardReport {
+                modifier: 0,
+                reserved: 0,
+                leds: 0,
+                keycodes,
+            };
+            if usb_hid.push_input(&report).is_ok() {
+                last_report = keycodes;
+            }
+
+            let colour: RGB8 = if pressed > 0 {
+                (0, 255, 0).into()
+            } else {
+                (0, 0, 0).into()
+            };
+            ws.write(brightness(once(colour), 32)).unwrap();
+        }
+    }
+}
+
+// End of file
@@ -17,49 +17,127 @@ pub static BOOT2_FIRMWARE: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
 pub use hal::pac;
 
 hal::bsp_pins!(
+    /// Pro Micro pin `TX`/`D0`.
+    ///
+    /// Functions: UART0 TX, I2C0 SDA, SPI0 RX, PWM0 A
     Gpio0 {
         name: tx,
         aliases: { FunctionUart: UartTx }
     },
+    /// Pro Micro pin `RX`/`D1`.
+    ///
+    /// Functions: UART0 RX, I2C0 SCL, SPI0 CSn, PWM0 B
     Gpio1 {
         name: rx,
         aliases: { FunctionUart: UartRx }
     },
+    /// Pro Micro pin `D2`.
+    ///
+    /// Functions: I2C1 SDA, SPI0 SCK, PWM1 A
     Gpio2 { name: d2 },
+    /// Pro Micro pin `D3`.
+    ///
+    /// Functions: I2C1 SCL, SPI0 TX, PWM1 B
     Gpio3 { name: d3 },
+    /// Pro Micro pin `D4`.
+    ///
+    /// Functions: UART1 TX, I2C0 SDA, SPI0 RX, PWM2 A
     Gpio4 { name: d4 },
+    /// Pro Micro pin `D5`.
+    ///
+    /// Functions: UART1 RX, I2C0 SCL, SPI0 CSn, PWM2 B
     Gpio5 { name: d5 },
+    /// Pro Micro pin `D6`.
+    ///
+    /// Functions: I2C1 SDA, SPI0 SCK, PWM3 A
     Gpio6 { name: d6 },
+    /// Pro Micro pin `D7`.
+    ///
+    /// Functions: I2C1 SCL, SPI0 TX, PWM3 B
     Gpio7 { name: d7 },
+    /// Pro Micro pin `D8`.
+    ///
+    /// Functions: UART1 TX, I2C0 SDA, SPI1 RX, PWM4 A
     Gpio8 { name: d8 },
+    /// Pro Micro pin `D9`.
+    ///
+    /// Functions: UART1 RX, I2C0 SCL, SPI1 CSn, PWM4 B
     Gpio9 { name: d9 },
+    /// Pro Micro pin `D10`.
+    ///
+    /// Functions: I2C1 SDA, SPI1 SCK, PWM5 A
     Gpio10 { name: d10 },
+    /// Not on a Pro Micro pin; broken out on a pad on the underside.
+    ///
+    /// Functions: I2C1 SCL, SPI1 TX, PWM5 B
     Gpio11 { name: d11 },
+    /// STEMMA QT connector SDA.
+    ///
+    /// Functions: I2C0 SDA, UART0 TX, SPI1 RX, PWM6 A
     Gpio12 {
         name: sda,
         aliases: { FunctionI2C: Sda }
     },
+    /// STEMMA QT connector SCL.
+    ///
+    /// Functions: I2C0 SCL, UART0 RX, SPI1 CSn, PWM6 B
     Gpio13 {
         name: scl,
         aliases: { FunctionI2C: Scl }
     },
+    /// The on-board WS2812 NeoPixel. See [`NeoPixelPin`].
     Gpio17 { name: neopixel },
+    /// Pro Micro pin `SCK`.
+    ///
+    /// Functions: SPI0 SCK, I2C1 SDA, PWM1 A
     Gpio18 {
         name: sclk,
         aliases: { FunctionSpi: Sclk }
     },
+    /// Pro Micro pin `MOSI`.
+    ///
+    /// Functions: SPI0 TX, I2C1 SCL, PWM1 B
     Gpio19 {
         name: mosi,
         aliases: { FunctionSpi: Mosi }
     },
+    /// Pro Micro pin `MISO`.
+    ///
+    /// Functions: SPI0 RX, I2C0 SDA, UART1 TX, PWM2 A
     Gpio20 {
         name: miso,
         aliases: { FunctionSpi: Miso }
     },
+    /// Pro Micro pin `A0`.
+    ///
+    /// Functions: ADC0, I2C1 SDA, SPI1 SCK, PWM5 A
     Gpio26 { name: a0 },
+    /// Pro Micro pin `A1`.
+    ///
+    /// Functions: ADC1, I2C1 SCL, SPI1 TX, PWM5 B
     Gpio27 { name: a1 },
+    /// Pro Micro pin `A2`.
+    ///
+    /// Functions: ADC2, I2C0 SDA, SPI1 RX, UART0 TX, PWM6 A
     Gpio28 { name: a2 },
+    /// Pro Micro pin `A3`.
+    ///
+    /// Functions: ADC3, I2C0 SCL, SPI1 CSn, UART0 RX, PWM6 B
     Gpio29 { name: a3 },
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+/// The on-board NeoPixel, handed to the PIO so it can be driven by `ws2812-pio`.
+pub type NeoPixelPin = hal::gpio::Pin<hal::gpio::bank0::Gpio17, hal::gpio::FunctionPio0>;
+
+/// The STEMMA QT SDA line, configured for I2C.
+pub type StemmaQtSda = Sda;
+
+/// The STEMMA QT SCL line, configured for I2C.
+pub type StemmaQtScl = Scl;
+
+/// An I2C bus on the STEMMA QT connector, which is wired to I2C0.
+pub type StemmaQtI2c = hal::I2C<pac::I2C0, (StemmaQtSda, StemmaQtScl)>;
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
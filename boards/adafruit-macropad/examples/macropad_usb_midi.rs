@@ -0,0 +1,177 @@
+//! Turns an Adafruit MacroPad into a USB MIDI controller
+//!
+//! Each of the twelve keys plays a note, sending Note On when pressed and
+//! Note Off when released. The bottom left key is middle C and the notes rise
+//! chromatically from there. Turning the encoder sends the modulation wheel
+//! controller (CC 1), in bigger steps the faster it turns, and pressing it
+//! resets the modulation to 0.
+//!
+//! The MacroPad shows up as a class compliant MIDI device, so any DAW or
+//! synthesizer sees it without drivers.
+#![no_std]
+#![no_main]
+
+use adafruit_macropad::{
+    hal::{self, clocks::init_clocks_and_plls, pac, watchdog::Watchdog, Sio},
+    key_to_note, rotary_encoder, Buttons, Pins, XOSC_CRYSTAL_FREQ,
+};
+use embedded_hal::digital::v2::OutputPin;
+use panic_halt as _;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::input::ButtonEvent;
+use rp_hal_boards_common::usb_midi::MidiClass;
+
+/// Middle C.
+const ROOT_NOTE: u8 = 60;
+
+/// How far one encoder detent moves the modulation wheel.
+const CC_STEP: i16 = 4;
+
+/// Detents a second above which each one moves the modulation wheel four
+/// times as far.
+const FAST_DETENTS: u32 = 15;
+
+/// Status bytes on MIDI channel 1.
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const CONTROL_CHANGE: u8 = 0xB0;
+
+/// The modulation wheel controller.
+const MOD_WHEEL: u8 = 1;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[rp2040_hal::entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables and the spinlock are initialised.
+#[rp2040_hal::entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut buttons = Buttons::new(
+        [
+            pins.key1.into(),
+            pins.key2.into(),
+            pins.key3.into(),
+            pins.key4.into(),
+            pins.key5.into(),
+            pins.key6.into(),
+            pins.key7.into(),
+            pins.key8.into(),
+            pins.key9.into(),
+            pins.key10.into(),
+            pins.key11.into(),
+            pins.key12.into(),
+        ],
+        pins.button,
+    );
+    let mut encoder = rotary_encoder(pins.encoder_rota, pins.encoder_rotb);
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // One MIDI IN jack and one MIDI OUT jack
+    let mut midi = MidiClass::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x5e4))
+        .manufacturer("Fake company")
+        .product("MacroPad MIDI")
+        .serial_number("TEST")
+        .device_class(0)
+        .build();
+
+    let mut modulation: i16 = 0;
+    let mut next_scan = timer.get_counter().ticks();
+
+    loop {
+        usb_dev.poll(&mut [&mut midi]);
+
+        // Drain anything the host sends us; this controller has no sound
+        let mut buffer = [0; 64];
+        let _ = midi.read(&mut buffer);
+
+        // The encoder moves faster than the keys, so decode it every pass
+        let now = timer.get_counter().ticks();
+        encoder.update(now).unwrap();
+        let delta = encoder.delta();
+        if delta != 0 {
+            let step = if encoder.velocity(now) > FAST_DETENTS {
+                4 * CC_STEP
+            } else {
+                CC_STEP
+            };
+            modulation = (modulation + delta as i16 * step).clamp(0, 127);
+            send(&mut midi, CONTROL_CHANGE, MOD_WHEEL, modulation as u8);
+        }
+
+        // Scan the keys and the encoder button at 1 kHz
+        if timer.get_counter().ticks() < next_scan {
+            continue;
+        }
+        next_scan += 1_000;
+
+        for (index, key) in buttons.keys.iter_mut().enumerate() {
+            let note = key_to_note(index, ROOT_NOTE);
+            match key.update().unwrap() {
+                Some(ButtonEvent::Pressed) => send(&mut midi, NOTE_ON, note, 127),
+                Some(ButtonEvent::Released) => send(&mut midi, NOTE_OFF, note, 0),
+                _ => {}
+            }
+        }
+
+        if buttons.encoder.update().unwrap() == Some(ButtonEvent::Pressed) {
+            modulation = 0;
+            send(&mut midi, CONTROL_CHANGE, MOD_WHEEL, modulation as u8);
+        }
+
+        // Light the LED while any key is held
+        if buttons.keys.iter().any(|key| key.is_pressed()) {
+            led_pin.set_high().unwrap();
+        } else {
+            led_pin.set_low().unwrap();
+        }
+    }
+}
+
+/// Send a channel message with two data bytes on the first virtual cable.
+///
+/// Messages are dropped if the host isn't reading, rather than stalling the
+/// key scan.
+fn send<B: UsbBus>(midi: &mut MidiClass<B>, status: u8, data1: u8, data2: u8) {
+    // Cable 0, and a Code Index Number that is the status byte's type
+    let _ = midi.write(&[status >> 4, status, data1, data2]);
+}
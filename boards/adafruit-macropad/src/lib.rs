@@ -19,6 +19,13 @@ pub static BOOT2_FIRMWARE: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
 
 pub use hal::pac;
 
+use hal::gpio::{
+    bank0::{Gpio0, Gpio17, Gpio18},
+    DynPin, Pin, PinMode, PullUpInput, ValidPinMode,
+};
+use rp_hal_boards_common::encoder::Encoder;
+use rp_hal_boards_common::input::{Active, Button};
+
 hal::bsp_pins!(
     Gpio0 { name: button },
     Gpio1 { name: key1 },
@@ -74,3 +81,74 @@ hal::bsp_pins!(
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+/// Number of keys in the MacroPad's grid.
+pub const KEY_COUNT: usize = 12;
+
+/// The MIDI note for key `index`, 0 for `key1` to 11 for `key12`, with the
+/// bottom left key playing `root`.
+///
+/// The keys form a chromatic scale that rises left to right, then upwards
+/// row by row, like the pads of a drum machine.
+pub fn key_to_note(index: usize, root: u8) -> u8 {
+    assert!(index < KEY_COUNT, "the MacroPad has keys 0 to 11");
+    let (row, col) = (index / 3, index % 3);
+    let offset = (3 - row) * 3 + col;
+    root.saturating_add(offset as u8).min(127)
+}
+
+/// The keys and the encoder's push switch, debounced with
+/// [rp_hal_boards_common::input::Button].
+///
+/// They all pull their pin low when pressed. Call `update` on each at a regular
+/// interval - every millisecond or so works well.
+pub struct Buttons {
+    /// `key1` to `key12`, in the order used by [key_to_note].
+    pub keys: [Button<DynPin>; KEY_COUNT],
+    /// Pressing the encoder's knob.
+    pub encoder: Button<Pin<Gpio0, PullUpInput>>,
+}
+
+impl Buttons {
+    /// Configure `keys`, `key1` to `key12` in order, and the encoder's
+    /// `button` as inputs with pull-ups.
+    pub fn new<M>(keys: [DynPin; KEY_COUNT], button: Pin<Gpio0, M>) -> Self
+    where
+        M: PinMode + ValidPinMode<Gpio0>,
+    {
+        Buttons {
+            keys: keys.map(|mut key| {
+                key.into_pull_up_input();
+                Button::new(key, Active::Low)
+            }),
+            encoder: Button::new(button.into_pull_up_input(), Active::Low),
+        }
+    }
+}
+
+/// The rotary encoder's A and B contacts, with
+/// [rp_hal_boards_common::encoder::Encoder]. Pressing the knob is
+/// [Buttons::encoder].
+pub type RotaryEncoder = Encoder<RotaryA, RotaryB>;
+
+/// Configure `encoder_rota` and `encoder_rotb` as inputs with pull-ups and
+/// decode them.
+///
+/// Call `update` on the result from the GPIO interrupt on both edges of both
+/// pins, or at least every few hundred microseconds.
+pub fn rotary_encoder<MA, MB>(
+    encoder_rota: Pin<Gpio17, MA>,
+    encoder_rotb: Pin<Gpio18, MB>,
+) -> RotaryEncoder
+where
+    MA: PinMode + ValidPinMode<Gpio17>,
+    MB: PinMode + ValidPinMode<Gpio18>,
+{
+    Encoder::new(
+        encoder_rota.into_pull_up_input(),
+        encoder_rotb.into_pull_up_input(),
+    )
+    .unwrap()
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
@@ -66,3 +66,5 @@ hal::bsp_pins!(
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
@@ -0,0 +1,174 @@
+//! # Nano Sound Level Example
+//!
+//! A sound level meter using the Arduino Nano RP2040 Connect's on-board PDM
+//! microphone, captured with `rp_hal_boards_common::pio_pdm`.
+//!
+//! The microphone is clocked at 2.048 MHz and decimated to 16 kHz PCM. Ten
+//! times a second, the level of the last 100 ms is sent over USB Serial as a
+//! line like `level: -42.5 dBFS`, and the on-board LED lights while it is
+//! above -30 dBFS.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+use arduino_nano_connect as bsp;
+
+// Pull in any important traits
+use bsp::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use bsp::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use bsp::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Communications Class Device support
+use usbd_serial::SerialPort;
+
+// Used to format the readings
+use core::fmt::Write;
+use heapless::String;
+
+use rp_hal_boards_common::pio_pdm::{dbfs_tenths, PdmMic};
+
+/// The microphone's clock.
+const PDM_CLOCK_HZ: u32 = 2_048_000;
+
+/// PDM bits per PCM sample, giving 16 kHz.
+const DECIMATION: u32 = 128;
+
+/// PCM samples per level reading: 100 ms at 16 kHz.
+const WINDOW: usize = 1600;
+
+/// Light the LED above this level, in tenths of a dB.
+const LOUD_TENTHS: i16 = -300;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[arduino_nano_connect::entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables and the spinlock are initialised.
+#[arduino_nano_connect::entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let clocks = hal::clocks::init_clocks_and_plls(
+        bsp::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = bsp::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // The on-board LED
+    let mut led_pin = pins.sck0.into_push_pull_output();
+
+    // Hand the microphone's pins, GPIO 22 and 23, to PIO0
+    let _data = pins.pdmdin.into_mode::<hal::gpio::FunctionPio0>();
+    let _clock = pins.pdmclk.into_mode::<hal::gpio::FunctionPio0>();
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut mic = PdmMic::new(
+        &mut pio,
+        sm0,
+        22,
+        23,
+        PDM_CLOCK_HZ,
+        clocks.system_clock.freq().to_Hz(),
+        DECIMATION,
+    );
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial = SerialPort::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Sound level meter")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut window = [0i16; WINDOW];
+    let mut filled = 0;
+
+    loop {
+        // Keep the microphone's FIFO drained; USB polls are short enough to
+        // fit in between
+        filled += mic.read(&mut window[filled..]);
+        usb_dev.poll(&mut [&mut serial]);
+
+        if filled < WINDOW {
+            continue;
+        }
+        filled = 0;
+
+        // The FIFO may overflow while this runs; a meter doesn't miss the
+        // few samples lost
+        let level = dbfs_tenths(&window);
+        if level.is_some_and(|level| level > LOUD_TENTHS) {
+            led_pin.set_high().unwrap();
+        } else {
+            led_pin.set_low().unwrap();
+        }
+
+        let mut text: String<32> = String::new();
+        match level {
+            Some(level) => writeln!(
+                &mut text,
+                "level: {}{}.{} dBFS\r",
+                if level < 0 { "-" } else { "" },
+                level.unsigned_abs() / 10,
+                level.unsigned_abs() % 10
+            )
+            .unwrap(),
+            None => writeln!(&mut text, "level: silent\r").unwrap(),
+        }
+        // Readings are dropped while no terminal is listening
+        let _ = serial.write(text.as_bytes());
+    }
+}
+
+// End of file
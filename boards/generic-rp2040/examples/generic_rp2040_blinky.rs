@@ -0,0 +1,51 @@
+//! Blinks an LED on GPIO 25 of a custom RP2040 board
+//!
+//! Change the pin to wherever the LED sits on your board. If your crystal is
+//! not 12 MHz, build with the matching feature, for example:
+//!
+//! ```console
+//! cargo run --release --example generic_rp2040_blinky --features xosc-10mhz
+//! ```
+#![no_std]
+#![no_main]
+
+use embedded_hal::digital::v2::ToggleableOutputPin;
+use generic_rp2040::entry;
+use generic_rp2040::{
+    hal::{clocks::Clock, pac, watchdog::Watchdog, Sio},
+    Board,
+};
+use panic_halt as _;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let sio = Sio::new(pac.SIO);
+
+    let Board { pins, clocks } = Board::init(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .unwrap();
+
+    // The system clock depends on the crystal, so take it from the clocks
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    let mut led = pins.gpio25.into_push_pull_output();
+
+    loop {
+        led.toggle().unwrap();
+        delay.delay_ms(500);
+    }
+}
@@ -0,0 +1,188 @@
+#![no_std]
+
+//! A Board Support Package for custom boards built around a bare RP2040.
+//!
+//! Every GPIO is available under its plain name, `gpio0` to `gpio29`, with
+//! nothing assumed about what is connected to it. The parts that do differ
+//! between boards are picked with Cargo features:
+//!
+//! * the crystal: 12 MHz, or `xosc-8mhz`, `xosc-10mhz` or `xosc-15mhz`,
+//! * the flash chip's second stage bootloader: W25Q080, or
+//!   `boot2-generic-03h`, `boot2-is25lp080`, `boot2-at25sf128a` or
+//!   `boot2-w25x10cl`.
+
+pub extern crate rp2040_hal as hal;
+
+#[cfg(feature = "rt")]
+extern crate cortex_m_rt;
+#[cfg(feature = "rt")]
+pub use hal::entry;
+
+#[cfg(any(
+    all(feature = "xosc-8mhz", feature = "xosc-10mhz"),
+    all(feature = "xosc-8mhz", feature = "xosc-15mhz"),
+    all(feature = "xosc-10mhz", feature = "xosc-15mhz"),
+))]
+compile_error!("Enable at most one of the `xosc-*` features");
+
+#[cfg(any(
+    all(feature = "boot2-generic-03h", feature = "boot2-is25lp080"),
+    all(feature = "boot2-generic-03h", feature = "boot2-at25sf128a"),
+    all(feature = "boot2-generic-03h", feature = "boot2-w25x10cl"),
+    all(feature = "boot2-is25lp080", feature = "boot2-at25sf128a"),
+    all(feature = "boot2-is25lp080", feature = "boot2-w25x10cl"),
+    all(feature = "boot2-at25sf128a", feature = "boot2-w25x10cl"),
+))]
+compile_error!("Enable at most one of the `boot2-*` features");
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[cfg(feature = "boot2")]
+#[link_section = ".boot2"]
+#[no_mangle]
+#[used]
+pub static BOOT2_FIRMWARE: [u8; 256] = {
+    #[cfg(feature = "boot2-generic-03h")]
+    let boot2 = rp2040_boot2::BOOT_LOADER_GENERIC_03H;
+    #[cfg(feature = "boot2-is25lp080")]
+    let boot2 = rp2040_boot2::BOOT_LOADER_IS25LP080;
+    #[cfg(feature = "boot2-at25sf128a")]
+    let boot2 = rp2040_boot2::BOOT_LOADER_AT25SF128A;
+    #[cfg(feature = "boot2-w25x10cl")]
+    let boot2 = rp2040_boot2::BOOT_LOADER_W25X10CL;
+    #[cfg(not(any(
+        feature = "boot2-generic-03h",
+        feature = "boot2-is25lp080",
+        feature = "boot2-at25sf128a",
+        feature = "boot2-w25x10cl",
+    )))]
+    let boot2 = rp2040_boot2::BOOT_LOADER_W25Q080;
+    boot2
+};
+
+pub use hal::pac;
+
+hal::bsp_pins!(
+    Gpio0 { name: gpio0 },
+    Gpio1 { name: gpio1 },
+    Gpio2 { name: gpio2 },
+    Gpio3 { name: gpio3 },
+    Gpio4 { name: gpio4 },
+    Gpio5 { name: gpio5 },
+    Gpio6 { name: gpio6 },
+    Gpio7 { name: gpio7 },
+    Gpio8 { name: gpio8 },
+    Gpio9 { name: gpio9 },
+    Gpio10 { name: gpio10 },
+    Gpio11 { name: gpio11 },
+    Gpio12 { name: gpio12 },
+    Gpio13 { name: gpio13 },
+    Gpio14 { name: gpio14 },
+    Gpio15 { name: gpio15 },
+    Gpio16 { name: gpio16 },
+    Gpio17 { name: gpio17 },
+    Gpio18 { name: gpio18 },
+    Gpio19 { name: gpio19 },
+    Gpio20 { name: gpio20 },
+    Gpio21 { name: gpio21 },
+    Gpio22 { name: gpio22 },
+    Gpio23 { name: gpio23 },
+    Gpio24 { name: gpio24 },
+    Gpio25 { name: gpio25 },
+    /// GPIO 26 is also ADC input 0
+    Gpio26 { name: gpio26 },
+    /// GPIO 27 is also ADC input 1
+    Gpio27 { name: gpio27 },
+    /// GPIO 28 is also ADC input 2
+    Gpio28 { name: gpio28 },
+    /// GPIO 29 is also ADC input 3
+    Gpio29 { name: gpio29 },
+);
+
+/// Frequency of the crystal between XIN and XOUT, as selected by the `xosc-*`
+/// features.
+#[cfg(feature = "xosc-8mhz")]
+pub const XOSC_CRYSTAL_FREQ: u32 = 8_000_000;
+
+/// Frequency of the crystal between XIN and XOUT, as selected by the `xosc-*`
+/// features.
+#[cfg(feature = "xosc-10mhz")]
+pub const XOSC_CRYSTAL_FREQ: u32 = 10_000_000;
+
+/// Frequency of the crystal between XIN and XOUT, as selected by the `xosc-*`
+/// features.
+#[cfg(feature = "xosc-15mhz")]
+pub const XOSC_CRYSTAL_FREQ: u32 = 15_000_000;
+
+/// Frequency of the crystal between XIN and XOUT, as selected by the `xosc-*`
+/// features.
+#[cfg(not(any(feature = "xosc-8mhz", feature = "xosc-10mhz", feature = "xosc-15mhz")))]
+pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+pub use rp_hal_boards_common::clocks::{
+    init_clocks_and_plls_with_freq, init_clocks_from_rosc, init_clocks_from_usb_pll, overclock,
+    Error as ClockError, Overclock,
+};
+
+/// The pins, with the clocks running.
+pub struct Board {
+    pub pins: Pins,
+    pub clocks: hal::clocks::ClocksManager,
+}
+
+impl Board {
+    /// Start the clocks from the crystal selected by the `xosc-*` features and set up the pins.
+    #[allow(clippy::too_many_arguments)]
+    pub fn init(
+        io_bank0: pac::IO_BANK0,
+        pads_bank0: pac::PADS_BANK0,
+        gpio_bank0: hal::sio::SioGpioBank0,
+        xosc: pac::XOSC,
+        clocks: pac::CLOCKS,
+        pll_sys: pac::PLL_SYS,
+        pll_usb: pac::PLL_USB,
+        resets: &mut pac::RESETS,
+        watchdog: &mut hal::Watchdog,
+    ) -> Result<Self, ClockError> {
+        Self::init_with_xosc(
+            XOSC_CRYSTAL_FREQ,
+            io_bank0,
+            pads_bank0,
+            gpio_bank0,
+            xosc,
+            clocks,
+            pll_sys,
+            pll_usb,
+            resets,
+            watchdog,
+        )
+    }
+
+    /// Start the clocks from a crystal of `xosc_hz` and set up the pins, for
+    /// boards and carrier designs that don't use a 12 MHz crystal.
+    ///
+    /// The system clock is as close to 125 MHz as the crystal allows, check
+    /// `clocks.system_clock.freq()`. Fails if the crystal can't give USB an
+    /// exact 48 MHz.
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_with_xosc(
+        xosc_hz: u32,
+        io_bank0: pac::IO_BANK0,
+        pads_bank0: pac::PADS_BANK0,
+        gpio_bank0: hal::sio::SioGpioBank0,
+        xosc: pac::XOSC,
+        clocks: pac::CLOCKS,
+        pll_sys: pac::PLL_SYS,
+        pll_usb: pac::PLL_USB,
+        resets: &mut pac::RESETS,
+        watchdog: &mut hal::Watchdog,
+    ) -> Result<Self, ClockError> {
+        let clocks = init_clocks_and_plls_with_freq(
+            xosc_hz, xosc, clocks, pll_sys, pll_usb, resets, watchdog,
+        )?;
+        let pins = Pins::new(io_bank0, pads_bank0, gpio_bank0, resets);
+        Ok(Board { pins, clocks })
+    }
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
@@ -0,0 +1,155 @@
+//! # Pimoroni Interstate 75 embedded-graphics Example
+//!
+//! Draws a scrolling colour gradient with a bouncing box and some text on a
+//! 64x32 HUB75 panel, using the PIO/DMA HUB75 driver.
+//!
+//! Press button `A` to freeze the animation.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use embedded_hal::digital::v2::InputPin;
+use panic_halt as _;
+
+use pimoroni_interstate75::{
+    entry, hal,
+    hub75::{FrameBuffer64x32, Hub75, Hub75Pins},
+    pac, XOSC_CRYSTAL_FREQ,
+};
+use rp_hal_boards_common::dma::Allocator;
+
+/// The frame buffer is 8 KiB, too big to comfortably live on the stack.
+static mut FRAME: FrameBuffer64x32 = FrameBuffer64x32::new();
+
+/// Panel refreshes between two animation steps.
+const REFRESHES_PER_STEP: u32 = 8;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Enable watchdog and clocks
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = hal::Sio::new(pac.SIO);
+    let pins = pimoroni_interstate75::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let button_a = pins.button_a.into_pull_up_input();
+
+    let hub75_pins = Hub75Pins {
+        r0: pins.r0.into_mode(),
+        g0: pins.g0.into_mode(),
+        b0: pins.b0.into_mode(),
+        r1: pins.r1.into_mode(),
+        g1: pins.g1.into_mode(),
+        b1: pins.b1.into_mode(),
+        row_a: pins.row_a.into_mode(),
+        row_b: pins.row_b.into_mode(),
+        row_c: pins.row_c.into_mode(),
+        row_d: pins.row_d.into_mode(),
+        row_e: pins.row_e.into_mode(),
+        clk: pins.clk.into_mode(),
+        lat: pins.lat.into_mode(),
+        oe: pins.oe.into_mode(),
+    };
+    let mut dma = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let channel = dma.alloc("hub75").unwrap();
+    let mut hub75 = Hub75::new(pac.PIO0, &mut pac.RESETS, channel, hub75_pins);
+
+    // Note (safety): this is the only reference to FRAME
+    let frame = unsafe { &mut *core::ptr::addr_of_mut!(FRAME) };
+    let size = frame.size();
+    let text_style = MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE);
+
+    let mut offset: u8 = 0;
+    let mut position = Point::new(4, 4);
+    let mut velocity = Point::new(1, 1);
+    let mut refreshes = 0;
+    loop {
+        hub75.refresh(frame);
+
+        refreshes += 1;
+        if refreshes < REFRESHES_PER_STEP || button_a.is_low().unwrap() {
+            continue;
+        }
+        refreshes = 0;
+
+        // Background gradient, scrolling sideways
+        for x in 0..size.width as i32 {
+            let hue = offset.wrapping_add((x * 4) as u8);
+            Rectangle::new(Point::new(x, 0), Size::new(1, size.height))
+                .into_styled(PrimitiveStyle::with_fill(dim(wheel(hue))))
+                .draw(frame)
+                .unwrap();
+        }
+        offset = offset.wrapping_add(1);
+
+        // A box bouncing off the edges
+        position += velocity;
+        if position.x <= 0 || position.x >= size.width as i32 - 8 {
+            velocity.x = -velocity.x;
+        }
+        if position.y <= 0 || position.y >= size.height as i32 - 8 {
+            velocity.y = -velocity.y;
+        }
+        Rectangle::new(position, Size::new(8, 8))
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::WHITE))
+            .draw(frame)
+            .unwrap();
+
+        Text::new("rp-rs", Point::new(17, 19), text_style)
+            .draw(frame)
+            .unwrap();
+    }
+}
+
+/// Quarter brightness, so the text stands out.
+fn dim(colour: Rgb888) -> Rgb888 {
+    Rgb888::new(colour.r() / 4, colour.g() / 4, colour.b() / 4)
+}
+
+/// Convert a number from `0..=255` to an RGB color triplet.
+///
+/// The colours are a transition from red, to green, to blue and back to red.
+fn wheel(mut wheel_pos: u8) -> Rgb888 {
+    wheel_pos = 255 - wheel_pos;
+    if wheel_pos < 85 {
+        // No green in this sector - red and blue only
+        Rgb888::new(255 - (wheel_pos * 3), 0, wheel_pos * 3)
+    } else if wheel_pos < 170 {
+        // No red in this sector - green and blue only
+        wheel_pos -= 85;
+        Rgb888::new(0, wheel_pos * 3, 255 - (wheel_pos * 3))
+    } else {
+        // No blue in this sector - red and green only
+        wheel_pos -= 170;
+        Rgb888::new(wheel_pos * 3, 255 - (wheel_pos * 3), 0)
+    }
+}
+
+// End of file
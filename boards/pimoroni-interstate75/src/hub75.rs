@@ -0,0 +1,281 @@
+//! HUB75 RGB LED matrix driver using PIO and DMA.
+//!
+//! A HUB75 panel only shows two rows at a time, each pixel either fully on or
+//! off, so the picture has to be rebuilt thousands of times a second. Colour
+//! depth comes from binary coded modulation: every row is shifted out once per
+//! bit plane, and each plane is left on for twice as long as the one before.
+//!
+//! Two PIO0 state machines do the time-critical part:
+//!
+//! * SM0 shifts one byte per pixel pair out on `R0 G0 B0 R1 G1 B1`, toggling
+//!   `CLK` for each. A DMA channel feeds it straight from the [`FrameBuffer`].
+//! * SM1 sets the row address, pulses `LAT` and holds `OE` low for the bit
+//!   plane's display time.
+//!
+//! The CPU only sequences rows and planes in [`Hub75::refresh`], which needs
+//! to be called continuously - from a loop, or better, on the second core.
+//!
+//! ```ignore
+//! static mut FRAME: FrameBuffer<64, 16> = FrameBuffer::new();
+//! let mut hub75 = Hub75::new(pac.PIO0, &mut pac.RESETS, channel, hub75_pins);
+//! let frame = unsafe { &mut FRAME };
+//! Circle::new(Point::new(10, 10), 12)
+//!     .into_styled(PrimitiveStyle::with_fill(Rgb888::RED))
+//!     .draw(frame)?;
+//! loop {
+//!     hub75.refresh(frame);
+//! }
+//! ```
+
+use embedded_graphics::{
+    pixelcolor::{Rgb888, RgbColor},
+    prelude::*,
+};
+use hal::gpio::bank0::{
+    Gpio0, Gpio1, Gpio10, Gpio11, Gpio12, Gpio13, Gpio2, Gpio3, Gpio4, Gpio5, Gpio6, Gpio7, Gpio8,
+    Gpio9,
+};
+use hal::gpio::{FunctionPio0, Pin};
+use hal::pac;
+use hal::pio::{
+    PIOBuilder, PIOExt, PinDir, Running, ShiftDirection, StateMachine, Tx, PIO, SM0, SM1,
+};
+use rp_hal_boards_common::dma::Channel;
+use rp_hal_boards_pio::hub75;
+
+/// Number of bit planes, i.e. bits per colour channel.
+pub const PLANES: usize = 8;
+
+/// Display time of the least significant bit plane, in state machine cycles.
+///
+/// Longer means brighter but lowers the refresh rate.
+const BASE_ON_CYCLES: u32 = 32;
+
+/// First GPIO of the `R0 G0 B0 R1 G1 B1` data lines.
+const DATA_BASE: u8 = 0;
+/// First GPIO of the `A B C D E` row address lines.
+const ROW_BASE: u8 = 6;
+/// The pixel clock.
+const CLK_PIN: u8 = 11;
+/// `LAT`, followed by `OE` on the next GPIO.
+const LAT_PIN: u8 = 12;
+const OE_PIN: u8 = 13;
+
+/// The data state machine's bit in `FDEBUG.TXSTALL`.
+const DATA_SM_TXSTALL: u8 = 1 << 0;
+
+/// DREQ of PIO0 SM0's TX FIFO.
+const DREQ_PIO0_TX0: u8 = 0;
+
+/// A 64x32 panel: 64 columns, 16 scan rows.
+pub type FrameBuffer64x32 = FrameBuffer<64, 16>;
+/// A 64x64 panel: 64 columns, 32 scan rows, needs the `E` address line.
+pub type FrameBuffer64x64 = FrameBuffer<64, 32>;
+/// A 32x32 panel: 32 columns, 16 scan rows.
+pub type FrameBuffer32x32 = FrameBuffer<32, 16>;
+
+/// The picture, stored as bit planes ready to be shifted out.
+///
+/// `WIDTH` is the number of columns and `ROWS` the number of scan rows, which
+/// is half the panel height. Every byte holds the top pixel in bits 0-2 and
+/// the pixel `ROWS` lines below it in bits 3-5.
+///
+/// A 64x32 frame buffer takes 8 KiB, so make it a `static` rather than a local
+/// variable.
+pub struct FrameBuffer<const WIDTH: usize, const ROWS: usize> {
+    planes: [[[u8; WIDTH]; ROWS]; PLANES],
+}
+
+impl<const WIDTH: usize, const ROWS: usize> FrameBuffer<WIDTH, ROWS> {
+    /// A black frame.
+    pub const fn new() -> Self {
+        FrameBuffer {
+            planes: [[[0; WIDTH]; ROWS]; PLANES],
+        }
+    }
+
+    /// Set the pixel at `(x, y)`, ignoring coordinates outside the panel.
+    pub fn set_pixel(&mut self, x: usize, y: usize, colour: Rgb888) {
+        if x >= WIDTH || y >= 2 * ROWS {
+            return;
+        }
+        let (row, shift) = if y < ROWS { (y, 0) } else { (y - ROWS, 3) };
+        let rgb = [gamma(colour.r()), gamma(colour.g()), gamma(colour.b())];
+        for (plane, bits) in self.planes.iter_mut().enumerate() {
+            let value = rgb
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (i, c)| acc | (((c >> plane) & 1) << i));
+            let byte = &mut bits[row][x];
+            *byte = (*byte & !(0b111 << shift)) | (value << shift);
+        }
+    }
+}
+
+impl<const WIDTH: usize, const ROWS: usize> Default for FrameBuffer<WIDTH, ROWS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Approximate the eye's response, so mid-range values don't look washed out.
+fn gamma(value: u8) -> u8 {
+    ((u16::from(value) * u16::from(value) + 255) >> 8) as u8
+}
+
+impl<const WIDTH: usize, const ROWS: usize> OriginDimensions for FrameBuffer<WIDTH, ROWS> {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, 2 * ROWS as u32)
+    }
+}
+
+impl<const WIDTH: usize, const ROWS: usize> DrawTarget for FrameBuffer<WIDTH, ROWS> {
+    type Color = Rgb888;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, colour) in pixels {
+            if point.x >= 0 && point.y >= 0 {
+                self.set_pixel(point.x as usize, point.y as usize, colour);
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, colour: Self::Color) -> Result<(), Self::Error> {
+        for y in 0..2 * ROWS {
+            for x in 0..WIDTH {
+                self.set_pixel(x, y, colour);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The HUB75 connector pins, handed to PIO0.
+pub struct Hub75Pins {
+    pub r0: Pin<Gpio0, FunctionPio0>,
+    pub g0: Pin<Gpio1, FunctionPio0>,
+    pub b0: Pin<Gpio2, FunctionPio0>,
+    pub r1: Pin<Gpio3, FunctionPio0>,
+    pub g1: Pin<Gpio4, FunctionPio0>,
+    pub b1: Pin<Gpio5, FunctionPio0>,
+    pub row_a: Pin<Gpio6, FunctionPio0>,
+    pub row_b: Pin<Gpio7, FunctionPio0>,
+    pub row_c: Pin<Gpio8, FunctionPio0>,
+    pub row_d: Pin<Gpio9, FunctionPio0>,
+    pub row_e: Pin<Gpio10, FunctionPio0>,
+    pub clk: Pin<Gpio11, FunctionPio0>,
+    pub lat: Pin<Gpio12, FunctionPio0>,
+    pub oe: Pin<Gpio13, FunctionPio0>,
+}
+
+/// The HUB75 driver. See the [module documentation](self).
+pub struct Hub75 {
+    _pio: PIO<pac::PIO0>,
+    _data_sm: StateMachine<(pac::PIO0, SM0), Running>,
+    data_tx: Tx<(pac::PIO0, SM0)>,
+    _row_sm: StateMachine<(pac::PIO0, SM1), Running>,
+    row_tx: Tx<(pac::PIO0, SM1)>,
+    dma: Channel,
+    _pins: Hub75Pins,
+}
+
+impl Hub75 {
+    /// Load the two PIO programs into PIO0 and start the state machines.
+    ///
+    /// `dma` is any free channel, e.g. from
+    /// `rp_hal_boards_common::dma::Allocator::alloc`.
+    pub fn new(pio0: pac::PIO0, resets: &mut pac::RESETS, dma: Channel, pins: Hub75Pins) -> Self {
+        let (mut pio, sm0, sm1, _, _) = pio0.split(resets);
+
+        // One byte per pixel pair, and one word per row and plane
+        let data_installed = pio.install(&hub75::data_program()).unwrap();
+        let (mut data_sm, _, data_tx) = PIOBuilder::from_program(data_installed)
+            .out_pins(DATA_BASE, 6)
+            .side_set_pin_base(CLK_PIN)
+            .out_shift_direction(ShiftDirection::Right)
+            .clock_divisor_fixed_point(2, 0)
+            .build(sm0);
+        data_sm.set_pindirs((DATA_BASE..DATA_BASE + 6).map(|pin| (pin, PinDir::Output)));
+        data_sm.set_pindirs([(CLK_PIN, PinDir::Output)]);
+
+        let row_installed = pio.install(&hub75::row_program()).unwrap();
+        let (mut row_sm, _, row_tx) = PIOBuilder::from_program(row_installed)
+            .out_pins(ROW_BASE, 5)
+            .side_set_pin_base(LAT_PIN)
+            .out_shift_direction(ShiftDirection::Right)
+            .autopull(true)
+            .pull_threshold(32)
+            .build(sm1);
+        row_sm.set_pindirs((ROW_BASE..ROW_BASE + 5).map(|pin| (pin, PinDir::Output)));
+        row_sm.set_pindirs([(LAT_PIN, PinDir::Output), (OE_PIN, PinDir::Output)]);
+
+        Hub75 {
+            _pio: pio,
+            _data_sm: data_sm.start(),
+            data_tx,
+            _row_sm: row_sm.start(),
+            row_tx,
+            dma,
+            _pins: pins,
+        }
+    }
+
+    /// Scan the whole frame once, every row and every bit plane.
+    ///
+    /// Call this continuously; the panel is dark in between calls.
+    pub fn refresh<const WIDTH: usize, const ROWS: usize>(
+        &mut self,
+        frame: &FrameBuffer<WIDTH, ROWS>,
+    ) {
+        for row in 0..ROWS {
+            for (plane, bits) in frame.planes.iter().enumerate() {
+                // The previous plane has to be latched before the next one
+                // starts shifting in. It keeps being displayed meanwhile.
+                while !self.row_tx.is_empty() {}
+                self.shift_out(&bits[row]);
+
+                let on_time = BASE_ON_CYCLES << plane;
+                while !self.row_tx.write(row as u32 | on_time << 5) {}
+            }
+        }
+    }
+
+    /// Shift one row of one bit plane into the panel and wait until the last
+    /// pixel is clocked in.
+    fn shift_out(&mut self, pixels: &[u8]) {
+        let pio = unsafe { &*pac::PIO0::ptr() };
+        let txstall = DATA_SM_TXSTALL;
+        pio.fdebug.write(|w| unsafe { w.txstall().bits(txstall) });
+
+        let regs = self.dma.regs();
+        regs.ch_read_addr
+            .write(|w| unsafe { w.bits(pixels.as_ptr() as u32) });
+        regs.ch_write_addr
+            .write(|w| unsafe { w.bits(self.data_tx.fifo_address() as u32) });
+        regs.ch_trans_count
+            .write(|w| unsafe { w.bits(pixels.len() as u32) });
+        regs.ch_ctrl_trig.write(|w| unsafe {
+            w.data_size()
+                .size_byte()
+                .incr_read()
+                .set_bit()
+                .incr_write()
+                .clear_bit()
+                .treq_sel()
+                .bits(DREQ_PIO0_TX0)
+                // Chaining to ourselves disables chaining
+                .chain_to()
+                .bits(self.dma.id())
+                .en()
+                .set_bit()
+        });
+
+        while self.dma.is_busy() {}
+        while pio.fdebug.read().txstall().bits() & txstall == 0 {}
+    }
+}
@@ -0,0 +1,85 @@
+#![no_std]
+
+pub extern crate rp2040_hal as hal;
+
+#[cfg(feature = "rt")]
+extern crate cortex_m_rt;
+#[cfg(feature = "rt")]
+pub use hal::entry;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[cfg(feature = "boot2")]
+#[link_section = ".boot2"]
+#[no_mangle]
+#[used]
+pub static BOOT2_FIRMWARE: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+pub use hal::pac;
+
+pub mod hub75;
+
+hal::bsp_pins!(
+    /// GPIO 0 is the HUB75 `R0` line, red for the top half of the panel
+    Gpio0 { name: r0 },
+    /// GPIO 1 is the HUB75 `G0` line
+    Gpio1 { name: g0 },
+    /// GPIO 2 is the HUB75 `B0` line
+    Gpio2 { name: b0 },
+    /// GPIO 3 is the HUB75 `R1` line, red for the bottom half of the panel
+    Gpio3 { name: r1 },
+    /// GPIO 4 is the HUB75 `G1` line
+    Gpio4 { name: g1 },
+    /// GPIO 5 is the HUB75 `B1` line
+    Gpio5 { name: b1 },
+    /// GPIO 6 is the HUB75 row address line `A`
+    Gpio6 { name: row_a },
+    /// GPIO 7 is the HUB75 row address line `B`
+    Gpio7 { name: row_b },
+    /// GPIO 8 is the HUB75 row address line `C`
+    Gpio8 { name: row_c },
+    /// GPIO 9 is the HUB75 row address line `D`
+    Gpio9 { name: row_d },
+    /// GPIO 10 is the HUB75 row address line `E`, only used by 64 row panels
+    Gpio10 { name: row_e },
+    /// GPIO 11 is the HUB75 pixel clock `CLK`
+    Gpio11 { name: clk },
+    /// GPIO 12 is the HUB75 latch `LAT`
+    Gpio12 { name: lat },
+    /// GPIO 13 is the HUB75 output enable `OE`, active low
+    Gpio13 { name: oe },
+    /// GPIO 14 is connected to the user button `A`, active low
+    Gpio14 { name: button_a },
+    /// GPIO 16 is the red part of the RGB LED, active low
+    Gpio16 { name: led_red },
+    /// GPIO 17 is the green part of the RGB LED, active low
+    Gpio17 { name: led_green },
+    /// GPIO 18 is the blue part of the RGB LED, active low
+    Gpio18 { name: led_blue },
+    /// GPIO 19 is the interrupt line of the Qw/ST connector
+    Gpio19 { name: i2c_int },
+    /// GPIO 20 is SDA on the Qw/ST connector
+    Gpio20 {
+        name: i2c_sda,
+        aliases: { FunctionI2C: Sda }
+    },
+    /// GPIO 21 is SCL on the Qw/ST connector
+    Gpio21 {
+        name: i2c_scl,
+        aliases: { FunctionI2C: Scl }
+    },
+    /// GPIO 23 is connected to the BOOT button, active low
+    Gpio23 { name: user_sw },
+    /// GPIO 26 is broken out as `A0`
+    Gpio26 { name: adc0 },
+    /// GPIO 27 is broken out as `A1`
+    Gpio27 { name: adc1 },
+    /// GPIO 28 is broken out as `A2`
+    Gpio28 { name: adc2 },
+    /// GPIO 29 is connected to ADC3, which measures the panel current
+    Gpio29 { name: current_sense },
+);
+
+pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
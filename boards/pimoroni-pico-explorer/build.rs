@@ -0,0 +1,62 @@
+//! This build script writes `storage.x`, which keeps the top of flash for
+//! data (see `rp_hal_boards_common::flash_storage`) and, if `OTA_SLOT_SIZE`
+//! is set, splits the rest into two firmware slots (see
+//! `rp_hal_boards_common::ota`).
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// The size of the board's flash chip.
+const FLASH_SIZE: u32 = 2 * 1024 * 1024;
+
+/// The size of the storage region, unless `FLASH_STORAGE_SIZE` says otherwise.
+const DEFAULT_STORAGE_SIZE: u32 = 64 * 1024;
+
+fn main() {
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let storage_size = match env::var("FLASH_STORAGE_SIZE") {
+        Ok(size) => size
+            .parse()
+            .expect("FLASH_STORAGE_SIZE should be a size in bytes"),
+        Err(_) => DEFAULT_STORAGE_SIZE,
+    };
+    assert!(
+        storage_size.is_multiple_of(4096),
+        "FLASH_STORAGE_SIZE should be a multiple of the 4096 byte sector size"
+    );
+    // No second slot unless asked for
+    let slot_size: u32 = match env::var("OTA_SLOT_SIZE") {
+        Ok(size) => size
+            .parse()
+            .expect("OTA_SLOT_SIZE should be a size in bytes"),
+        Err(_) => 0,
+    };
+    assert!(
+        slot_size.is_multiple_of(4096),
+        "OTA_SLOT_SIZE should be a multiple of the 4096 byte sector size"
+    );
+    fs::write(
+        out.join("storage.x"),
+        format!(
+            "__flash_storage_end = 0x10000000 + 0x{:x};\n\
+             __flash_storage_start = __flash_storage_end - 0x{:x};\n\
+             ASSERT(LOADADDR(.data) + SIZEOF(.data) <= __flash_storage_start,\n\
+             \x20   \"The program has grown into the flash storage region\");\n\
+             __ota_slot_size = 0x{:x};\n\
+             __ota_image_end = LOADADDR(.data) + SIZEOF(.data);\n\
+             ASSERT(__ota_slot_size == 0 || __ota_image_end <= 0x10000000 + __ota_slot_size,\n\
+             \x20   \"The program is bigger than OTA_SLOT_SIZE\");\n\
+             ASSERT(__ota_slot_size == 0\n\
+             \x20   || 0x10000000 + 2 * __ota_slot_size + 2 * 0x1000 <= __flash_storage_start,\n\
+             \x20   \"Two OTA_SLOT_SIZE slots and the swap's scratch and journal sectors \
+             don't fit below the flash storage region\");\n",
+            FLASH_SIZE, storage_size, slot_size
+        ),
+    )
+    .unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rerun-if-env-changed=FLASH_STORAGE_SIZE");
+    println!("cargo:rerun-if-env-changed=OTA_SLOT_SIZE");
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,154 @@
+#![no_std]
+#![no_main]
+
+//! Drives both motors from the buttons, beeps the piezo and lists the devices
+//! found in the breakout sockets.
+//!
+//! Hold A or B to run motor 1 forward or in reverse, and X or Y for motor 2.
+//! A motor brakes when its buttons are released. Jumper `AUDIO` to `GP0` to
+//! hear the piezo.
+
+use arrayvec::ArrayString;
+use core::fmt::Write;
+use embedded_graphics::{
+    mono_font::{ascii::FONT_10X20, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Text},
+};
+use embedded_hal::blocking::i2c::Read;
+use hal::{adc::Adc, clocks::*, pwm::Slices, watchdog::Watchdog, Sio};
+use panic_halt as _;
+use pimoroni_pico_explorer::entry;
+use pimoroni_pico_explorer::{
+    breakout_i2c, hal, pac, Button, Motor1, Motor2, MotorAction, PicoExplorer, Piezo,
+    XOSC_CRYSTAL_FREQ,
+};
+
+/// Speed the motors run at while a button is held.
+const SPEED: f32 = 0.6;
+
+#[entry]
+fn main() -> ! {
+    let mut p = pac::Peripherals::take().unwrap();
+    let cp = pac::CorePeripherals::take().unwrap();
+
+    // Enable watchdog and clocks
+    let mut watchdog = Watchdog::new(p.WATCHDOG);
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        p.XOSC,
+        p.CLOCKS,
+        p.PLL_SYS,
+        p.PLL_USB,
+        &mut p.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(cp.SYST, clocks.system_clock.get_freq().to_Hz());
+
+    let adc = Adc::new(p.ADC, &mut p.RESETS);
+
+    let sio = Sio::new(p.SIO);
+
+    let (mut explorer, pins) = PicoExplorer::new(
+        p.IO_BANK0,
+        p.PADS_BANK0,
+        sio.gpio_bank0,
+        p.SPI0,
+        adc,
+        &mut p.RESETS,
+        &mut delay,
+    );
+
+    let pwm_slices = Slices::new(p.PWM, &mut p.RESETS);
+    let mut motor1 = Motor1::new(pwm_slices.pwm4);
+    let mut motor2 = Motor2::new(pwm_slices.pwm5);
+    let mut piezo = Piezo::new(
+        pwm_slices.pwm0,
+        pins.gpio0,
+        clocks.system_clock.freq().to_Hz(),
+    );
+
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_10X20)
+        .text_color(Rgb565::GREEN)
+        .background_color(Rgb565::BLACK)
+        .build();
+
+    // Look for devices in the breakout sockets
+    let mut i2c = breakout_i2c(
+        p.I2C0,
+        pins.i2c_sda,
+        pins.i2c_scl,
+        &mut p.RESETS,
+        clocks.system_clock.freq(),
+    );
+    let mut found = ArrayString::<100>::new();
+    write!(&mut found, "Breakouts:").unwrap();
+    for address in 0x08..0x78 {
+        let mut byte = [0];
+        if i2c.read(address, &mut byte).is_ok() {
+            let _ = write!(&mut found, " {:02x}", address);
+        }
+    }
+    Text::with_alignment(&found, Point::new(10, 220), style, Alignment::Left)
+        .draw(&mut explorer.screen)
+        .unwrap();
+
+    // A short start-up beep
+    piezo.tone(880);
+    delay.delay_ms(100);
+    piezo.off();
+
+    let mut was_moving = false;
+    loop {
+        let action1 = action(
+            explorer.is_pressed(Button::A),
+            explorer.is_pressed(Button::B),
+        );
+        let action2 = action(
+            explorer.is_pressed(Button::X),
+            explorer.is_pressed(Button::Y),
+        );
+        motor1.set(action1);
+        motor2.set(action2);
+
+        // Chirp when a motor starts
+        let moving = action1 != MotorAction::Brake || action2 != MotorAction::Brake;
+        if moving && !was_moving {
+            piezo.tone(1760);
+            delay.delay_ms(30);
+            piezo.off();
+        }
+        was_moving = moving;
+
+        let mut buf = ArrayString::<100>::new();
+        writeln!(&mut buf, "Motor 1: {:8}", describe(action1)).unwrap();
+        writeln!(&mut buf, "Motor 2: {:8}", describe(action2)).unwrap();
+        Text::with_alignment(&buf, Point::new(10, 30), style, Alignment::Left)
+            .draw(&mut explorer.screen)
+            .unwrap();
+
+        delay.delay_ms(20);
+    }
+}
+
+fn action(forward: bool, reverse: bool) -> MotorAction {
+    match (forward, reverse) {
+        (true, false) => MotorAction::Forward(SPEED),
+        (false, true) => MotorAction::Reverse(SPEED),
+        _ => MotorAction::Brake,
+    }
+}
+
+fn describe(action: MotorAction) -> &'static str {
+    match action {
+        MotorAction::Forward(_) => "forward",
+        MotorAction::Reverse(_) => "reverse",
+        MotorAction::Brake => "brake",
+        MotorAction::Coast => "coast",
+    }
+}
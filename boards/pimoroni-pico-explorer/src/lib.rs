@@ -29,8 +29,9 @@ use embedded_hal::{
     blocking::delay::DelayUs,
     digital::v2::{InputPin, OutputPin},
     spi::MODE_0,
+    PwmPin,
 };
-use fugit::RateExtU32;
+use fugit::{HertzU32, RateExtU32};
 pub use hal::pac;
 use hal::{
     adc::Adc,
@@ -43,8 +44,10 @@ use hal::{
         FunctionI2C, FunctionPwm, FunctionSpi, Pin, PinId, PullUpInput, PushPullOutput,
     },
     pac::{RESETS, SPI0},
+    pwm::{FreeRunning, Pwm0, Pwm4, Pwm5, Slice, SliceId, ValidSliceMode},
     sio::SioGpioBank0,
     spi::{Enabled, Spi},
+    I2C,
 };
 use st7789::ST7789;
 
@@ -147,15 +150,152 @@ pub enum Button {
     Y,
 }
 
-pub enum Motor {
-    _1,
-    _2,
-}
-
+/// What a [Motor] should do. Speeds run from 0.0 to 1.0.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MotorAction {
     Forward(f32),
     Reverse(f32),
-    Stop,
+    /// Short the motor's terminals, stopping it quickly
+    Brake,
+    /// Leave the motor's terminals open, letting it spin down
+    Coast,
+}
+
+/// PWM period for the motors: 25 kHz with the default 125 MHz system clock,
+/// above the range of hearing.
+const MOTOR_PWM_TOP: u16 = 4999;
+
+/// One channel of the DRV8833 motor driver.
+///
+/// Channel A of the PWM slice drives the motor's `-` input and channel B its
+/// `+` input. [PicoExplorer::new] has already routed the pins to PWM.
+pub struct Motor<S: SliceId>
+where
+    FreeRunning: ValidSliceMode<S>,
+{
+    pwm: Slice<S, FreeRunning>,
+}
+
+/// The motor on the `MOTOR 1` header, driven by PWM slice 4.
+pub type Motor1 = Motor<Pwm4>;
+
+/// The motor on the `MOTOR 2` header, driven by PWM slice 5.
+pub type Motor2 = Motor<Pwm5>;
+
+impl<S: SliceId> Motor<S>
+where
+    FreeRunning: ValidSliceMode<S>,
+{
+    /// Start the PWM slice with the motor coasting.
+    pub fn new(mut pwm: Slice<S, FreeRunning>) -> Self {
+        pwm.default_config();
+        pwm.set_top(MOTOR_PWM_TOP);
+        pwm.enable();
+        let mut motor = Motor { pwm };
+        motor.coast();
+        motor
+    }
+
+    pub fn forward(&mut self, speed: f32) {
+        self.drive(0, Self::duty(speed));
+    }
+
+    pub fn reverse(&mut self, speed: f32) {
+        self.drive(Self::duty(speed), 0);
+    }
+
+    pub fn brake(&mut self) {
+        self.drive(MOTOR_PWM_TOP + 1, MOTOR_PWM_TOP + 1);
+    }
+
+    pub fn coast(&mut self) {
+        self.drive(0, 0);
+    }
+
+    pub fn set(&mut self, action: MotorAction) {
+        match action {
+            MotorAction::Forward(speed) => self.forward(speed),
+            MotorAction::Reverse(speed) => self.reverse(speed),
+            MotorAction::Brake => self.brake(),
+            MotorAction::Coast => self.coast(),
+        }
+    }
+
+    /// Release the PWM slice.
+    pub fn free(self) -> Slice<S, FreeRunning> {
+        self.pwm
+    }
+
+    fn duty(speed: f32) -> u16 {
+        (speed.clamp(0.0, 1.0) * f32::from(MOTOR_PWM_TOP + 1)) as u16
+    }
+
+    fn drive(&mut self, neg: u16, pos: u16) {
+        self.pwm.channel_a.set_duty(neg);
+        self.pwm.channel_b.set_duty(pos);
+    }
+}
+
+/// The piezo speaker, once its `AUDIO` pin is jumpered to GPIO 0.
+pub struct Piezo {
+    pwm: Slice<Pwm0, FreeRunning>,
+    system_clock_hz: u32,
+}
+
+impl Piezo {
+    pub fn new(
+        mut pwm: Slice<Pwm0, FreeRunning>,
+        pin: Pin<Gpio0, <Gpio0 as PinId>::Reset>,
+        system_clock_hz: u32,
+    ) -> Self {
+        pwm.default_config();
+        pwm.channel_a.output_to(pin);
+        pwm.channel_a.set_duty(0);
+        pwm.enable();
+        Piezo {
+            pwm,
+            system_clock_hz,
+        }
+    }
+
+    /// Play a square wave at `frequency` Hz, until [Piezo::off] or the next
+    /// tone.
+    pub fn tone(&mut self, frequency: u32) {
+        if frequency == 0 {
+            self.off();
+            return;
+        }
+        // The smallest divider that lets the period fit in 16 bits
+        let div = (self.system_clock_hz / frequency / 65536 + 1).min(255);
+        let top = (self.system_clock_hz / div / frequency).clamp(2, 65536) - 1;
+        self.pwm.set_div_int(div as u8);
+        self.pwm.set_top(top as u16);
+        self.pwm.channel_a.set_duty(top as u16 / 2);
+    }
+
+    pub fn off(&mut self) {
+        self.pwm.channel_a.set_duty(0);
+    }
+
+    /// Release the PWM slice.
+    pub fn free(self) -> Slice<Pwm0, FreeRunning> {
+        self.pwm
+    }
+}
+
+/// The I2C bus shared by the two breakout sockets.
+pub type BreakoutI2c = I2C<pac::I2C0, (Pin<Gpio20, FunctionI2C>, Pin<Gpio21, FunctionI2C>)>;
+
+/// Start the I2C bus of the breakout sockets at 400 kHz, using the pins from
+/// [Pins].
+pub fn breakout_i2c(
+    i2c0: pac::I2C0,
+    sda: Pin<Gpio20, FunctionI2C>,
+    scl: Pin<Gpio21, FunctionI2C>,
+    resets: &mut RESETS,
+    system_clock: HertzU32,
+) -> BreakoutI2c {
+    I2C::i2c0(i2c0, sda, scl, 400.kHz(), resets, system_clock)
 }
 
 pub type Screen = ST7789<
@@ -274,3 +414,5 @@ impl PicoExplorer {
         result.clamp(0.0, 1.0)
     }
 }
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
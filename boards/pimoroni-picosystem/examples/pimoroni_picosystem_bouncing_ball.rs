@@ -0,0 +1,155 @@
+//! Bouncing ball demo for the Pimoroni PicoSystem
+//!
+//! A ball bounces around the screen and the piezo beeps whenever it hits a
+//! wall. The d-pad nudges the ball, A and B cycle through colours, X and Y
+//! brighten and dim the backlight. The RGB LED turns red when the battery runs
+//! low.
+#![no_std]
+#![no_main]
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle},
+};
+use embedded_hal::digital::v2::OutputPin;
+use fugit::RateExtU32;
+use hal::{adc::Adc, clocks::*, pwm::Slices, watchdog::Watchdog, Sio};
+use panic_halt as _;
+use pimoroni_picosystem::entry;
+use pimoroni_picosystem::{hal, pac, Button, PicoSystem, SCREEN_SIZE, XOSC_CRYSTAL_FREQ};
+
+const RADIUS: i32 = 8;
+const COLOURS: [Rgb565; 4] = [
+    Rgb565::CSS_ORANGE,
+    Rgb565::CSS_DEEP_SKY_BLUE,
+    Rgb565::CSS_LIME_GREEN,
+    Rgb565::CSS_HOT_PINK,
+];
+
+#[entry]
+fn main() -> ! {
+    let mut p = pac::Peripherals::take().unwrap();
+    let cp = pac::CorePeripherals::take().unwrap();
+
+    // Enable watchdog and clocks
+    let mut watchdog = Watchdog::new(p.WATCHDOG);
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        p.XOSC,
+        p.CLOCKS,
+        p.PLL_SYS,
+        p.PLL_USB,
+        &mut p.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(cp.SYST, clocks.system_clock.freq().to_Hz());
+
+    let adc = Adc::new(p.ADC, &mut p.RESETS);
+    let pwm_slices = Slices::new(p.PWM, &mut p.RESETS);
+    let sio = Sio::new(p.SIO);
+
+    let (mut system, pins) = PicoSystem::new(
+        p.IO_BANK0,
+        p.PADS_BANK0,
+        sio.gpio_bank0,
+        p.SPI0,
+        pwm_slices.pwm5,
+        pwm_slices.pwm6,
+        adc,
+        clocks.system_clock.freq(),
+        &mut p.RESETS,
+        &mut delay,
+    );
+
+    let mut low_battery_led = pins.led_red.into_push_pull_output();
+
+    let size = i32::from(SCREEN_SIZE);
+    let mut position = Point::new(size / 2, size / 3);
+    let mut velocity = Point::new(3, 2);
+    let mut colour = 0;
+    let mut brightness: u8 = 255;
+    let mut beep_frames = 0;
+    let mut frame: u32 = 0;
+
+    loop {
+        system.buttons.update();
+        let buttons = &system.buttons;
+
+        if buttons.just_pressed(Button::A) {
+            colour = (colour + 1) % COLOURS.len();
+        }
+        if buttons.just_pressed(Button::B) {
+            colour = (colour + COLOURS.len() - 1) % COLOURS.len();
+        }
+        if buttons.is_pressed(Button::X) {
+            brightness = brightness.saturating_add(5);
+        }
+        if buttons.is_pressed(Button::Y) {
+            brightness = brightness.saturating_sub(5).max(16);
+        }
+        if buttons.just_pressed(Button::Up) {
+            velocity.y -= 1;
+        }
+        if buttons.just_pressed(Button::Down) {
+            velocity.y += 1;
+        }
+        if buttons.just_pressed(Button::Left) {
+            velocity.x -= 1;
+        }
+        if buttons.just_pressed(Button::Right) {
+            velocity.x += 1;
+        }
+        system.backlight.set_brightness(brightness);
+
+        // Erase the ball at its old position
+        Circle::with_center(position, RADIUS as u32 * 2)
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+            .draw(&mut system.screen)
+            .unwrap();
+
+        position += velocity;
+        let mut bounced = false;
+        if position.x < RADIUS || position.x >= size - RADIUS {
+            velocity.x = -velocity.x;
+            position.x = position.x.clamp(RADIUS, size - RADIUS - 1);
+            bounced = true;
+        }
+        if position.y < RADIUS || position.y >= size - RADIUS {
+            velocity.y = -velocity.y;
+            position.y = position.y.clamp(RADIUS, size - RADIUS - 1);
+            bounced = true;
+        }
+
+        Circle::with_center(position, RADIUS as u32 * 2)
+            .into_styled(PrimitiveStyle::with_fill(COLOURS[colour]))
+            .draw(&mut system.screen)
+            .unwrap();
+
+        if bounced {
+            system.audio.play(880u32.Hz(), 50);
+            beep_frames = 3;
+        } else if beep_frames > 0 {
+            beep_frames -= 1;
+            if beep_frames == 0 {
+                system.audio.stop();
+            }
+        }
+
+        // Check the battery about once a second
+        frame = frame.wrapping_add(1);
+        if frame.is_multiple_of(60) {
+            let low = system.battery.percent() < 10 && !system.battery.is_charging();
+            if low {
+                low_battery_led.set_high().unwrap();
+            } else {
+                low_battery_led.set_low().unwrap();
+            }
+        }
+
+        delay.delay_ms(16);
+    }
+}
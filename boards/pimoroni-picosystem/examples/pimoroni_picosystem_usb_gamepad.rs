@@ -0,0 +1,185 @@
+//! USB gamepad for the Pimoroni PicoSystem
+//!
+//! Plug the PicoSystem into a computer and it shows up as a HID gamepad: the
+//! d-pad drives the X and Y axes, and A, B, X and Y are buttons 1 to 4. The
+//! screen shows which buttons are down, and the RGB LED lights green once the
+//! host has configured the gamepad.
+#![no_std]
+#![no_main]
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle},
+};
+use embedded_hal::digital::v2::OutputPin;
+use hal::{adc::Adc, clocks::*, pwm::Slices, watchdog::Watchdog, Sio, Timer};
+use panic_halt as _;
+use pimoroni_picosystem::entry;
+use pimoroni_picosystem::{hal, pac, Button, PicoSystem, XOSC_CRYSTAL_FREQ};
+use usb_device::{class_prelude::*, prelude::*};
+use usbd_hid::hid_class::HIDClass;
+
+/// A gamepad with four buttons and a d-pad on two signed 8-bit axes.
+const GAMEPAD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x05, // Usage (Game Pad)
+    0xA1, 0x01, // Collection (Application)
+    0x05, 0x09, //   Usage Page (Button)
+    0x19, 0x01, //   Usage Minimum (1)
+    0x29, 0x04, //   Usage Maximum (4)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x01, //   Logical Maximum (1)
+    0x75, 0x01, //   Report Size (1)
+    0x95, 0x04, //   Report Count (4)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x75, 0x04, //   Report Size (4)
+    0x95, 0x01, //   Report Count (1)
+    0x81, 0x01, //   Input (Constant): padding to a byte
+    0x05, 0x01, //   Usage Page (Generic Desktop)
+    0x09, 0x30, //   Usage (X)
+    0x09, 0x31, //   Usage (Y)
+    0x15, 0x81, //   Logical Minimum (-127)
+    0x25, 0x7F, //   Logical Maximum (127)
+    0x75, 0x08, //   Report Size (8)
+    0x95, 0x02, //   Report Count (2)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0xC0, // End Collection
+];
+
+/// Where each button is drawn, in the order of [Button::ALL].
+const LAYOUT: [(i32, i32); 8] = [
+    (60, 80),
+    (60, 160),
+    (20, 120),
+    (100, 120),
+    (200, 120),
+    (160, 160),
+    (160, 80),
+    (120, 120),
+];
+
+#[entry]
+fn main() -> ! {
+    let mut p = pac::Peripherals::take().unwrap();
+    let cp = pac::CorePeripherals::take().unwrap();
+
+    // Enable watchdog and clocks
+    let mut watchdog = Watchdog::new(p.WATCHDOG);
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        p.XOSC,
+        p.CLOCKS,
+        p.PLL_SYS,
+        p.PLL_USB,
+        &mut p.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(cp.SYST, clocks.system_clock.freq().to_Hz());
+    let timer = Timer::new(p.TIMER, &mut p.RESETS);
+
+    let adc = Adc::new(p.ADC, &mut p.RESETS);
+    let pwm_slices = Slices::new(p.PWM, &mut p.RESETS);
+    let sio = Sio::new(p.SIO);
+
+    let (mut system, pins) = PicoSystem::new(
+        p.IO_BANK0,
+        p.PADS_BANK0,
+        sio.gpio_bank0,
+        p.SPI0,
+        pwm_slices.pwm5,
+        pwm_slices.pwm6,
+        adc,
+        clocks.system_clock.freq(),
+        &mut p.RESETS,
+        &mut delay,
+    );
+
+    let mut usb_led = pins.led_green.into_push_pull_output();
+
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        p.USBCTRL_REGS,
+        p.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut p.RESETS,
+    ));
+    let mut usb_hid = HIDClass::new(&usb_bus, GAMEPAD_REPORT_DESCRIPTOR, 10);
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("PicoSystem Gamepad")
+        .serial_number("TEST")
+        .device_class(0)
+        .build();
+
+    let mut next_sample = timer.get_counter().ticks();
+    let mut last_report = [0u8, 0, 0];
+    // Anything but the real state, so the first pass draws every button
+    let mut drawn = !0u8;
+    let mut configured = false;
+
+    loop {
+        usb_dev.poll(&mut [&mut usb_hid]);
+
+        let now_configured = usb_dev.state() == UsbDeviceState::Configured;
+        if now_configured != configured {
+            configured = now_configured;
+            if configured {
+                usb_led.set_high().unwrap();
+            } else {
+                usb_led.set_low().unwrap();
+            }
+        }
+
+        // Sample the buttons once a millisecond, as `Buttons` expects
+        if timer.get_counter().ticks() < next_sample {
+            continue;
+        }
+        next_sample += 1_000;
+        system.buttons.update();
+        let buttons = &system.buttons;
+
+        // Full scale one way or the other, or centred for neither or both
+        let axis = |negative: Button, positive: Button| {
+            (i8::from(buttons.is_pressed(positive)) - i8::from(buttons.is_pressed(negative))) * 127
+        };
+        let x = axis(Button::Left, Button::Right);
+        let y = axis(Button::Up, Button::Down);
+        let mut pressed = 0u8;
+        for (i, &button) in [Button::A, Button::B, Button::X, Button::Y]
+            .iter()
+            .enumerate()
+        {
+            if buttons.is_pressed(button) {
+                pressed |= 1 << i;
+            }
+        }
+
+        let report = [pressed, x as u8, y as u8];
+        if report != last_report && usb_hid.push_raw_input(&report).is_ok() {
+            last_report = report;
+        }
+
+        // Redraw the buttons that changed
+        let state = buttons.state();
+        let changed = state ^ drawn;
+        for (i, &(x, y)) in LAYOUT.iter().enumerate() {
+            if changed & (1 << i) == 0 {
+                continue;
+            }
+            let colour = if state & (1 << i) != 0 {
+                Rgb565::CSS_ORANGE
+            } else {
+                Rgb565::CSS_DIM_GRAY
+            };
+            Circle::with_center(Point::new(x, y), 30)
+                .into_styled(PrimitiveStyle::with_fill(colour))
+                .draw(&mut system.screen)
+                .unwrap();
+        }
+        drawn = state;
+    }
+}
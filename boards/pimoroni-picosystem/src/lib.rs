@@ -0,0 +1,434 @@
+#![no_std]
+
+pub extern crate rp2040_hal as hal;
+
+#[cfg(feature = "rt")]
+extern crate cortex_m_rt;
+#[cfg(feature = "rt")]
+pub use hal::entry;
+
+/// The linker will place this boot block at the start of our program image. We
+/// need this to help the ROM bootloader get our code up and running.
+#[cfg(feature = "boot2")]
+#[link_section = ".boot2"]
+#[no_mangle]
+#[used]
+pub static BOOT2_FIRMWARE: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
+
+use display_interface_spi::SPIInterface;
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    pixelcolor::{Rgb565, RgbColor},
+};
+use embedded_hal::{adc::OneShot, blocking::delay::DelayUs, digital::v2::InputPin, spi::MODE_0};
+use fugit::{HertzU32, RateExtU32};
+pub use hal::pac;
+use hal::{
+    adc::Adc,
+    gpio::{
+        bank0::{Gpio13, Gpio14, Gpio15, Gpio2, Gpio24, Gpio26, Gpio4, Gpio5, Gpio8, Gpio9},
+        DynPin, FloatingInput, FunctionPwm, FunctionSpi, Pin, PinId, PullUpInput, PushPullOutput,
+    },
+    pac::{RESETS, SPI0},
+    pwm::{FreeRunning, Pwm5, Pwm6, Slice},
+    sio::SioGpioBank0,
+    spi::{Enabled, Spi},
+};
+use rp_hal_boards_common::{
+    dma::Channel,
+    framebuffer::{Buffers, Controller, Panel, SpiLcd},
+    input::{self, Active, ButtonEvent},
+};
+use st7789::ST7789;
+
+mod internal_pins {
+    hal::bsp_pins!(
+        Gpio2 { name: charge_led },
+        Gpio4 { name: lcd_reset },
+        Gpio5 { name: lcd_cs },
+        Gpio6 { name: lcd_sclk },
+        Gpio7 { name: lcd_mosi },
+        Gpio8 { name: lcd_vsync },
+        Gpio9 { name: lcd_dc },
+        Gpio11 { name: audio },
+        Gpio12 { name: backlight },
+        Gpio13 { name: led_green },
+        Gpio14 { name: led_red },
+        Gpio15 { name: led_blue },
+        Gpio16 { name: button_y },
+        Gpio17 { name: button_x },
+        Gpio18 { name: button_a },
+        Gpio19 { name: button_b },
+        Gpio20 { name: button_down },
+        Gpio21 { name: button_right },
+        Gpio22 { name: button_left },
+        Gpio23 { name: button_up },
+        Gpio24 { name: charging },
+        Gpio26 {
+            name: battery_level
+        },
+    );
+}
+
+/// The pins that are not used by [PicoSystem] itself.
+pub struct Pins {
+    /// Charge indicator LED next to the USB port.
+    pub charge_led: Pin<Gpio2, <Gpio2 as PinId>::Reset>,
+    /// Tearing effect output of the screen, high during vertical blanking.
+    pub lcd_vsync: Pin<Gpio8, <Gpio8 as PinId>::Reset>,
+    /// Red channel of the RGB LED, active high.
+    pub led_red: Pin<Gpio14, <Gpio14 as PinId>::Reset>,
+    /// Green channel of the RGB LED, active high.
+    pub led_green: Pin<Gpio13, <Gpio13 as PinId>::Reset>,
+    /// Blue channel of the RGB LED, active high.
+    pub led_blue: Pin<Gpio15, <Gpio15 as PinId>::Reset>,
+}
+
+pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+/// Width and height of the screen, in pixels.
+pub const SCREEN_SIZE: u16 = 240;
+
+/// The PicoSystem's 240x240 ST7789 screen.
+pub type Screen = ST7789<
+    SPIInterface<Spi<Enabled, SPI0, 8>, Pin<Gpio9, PushPullOutput>, Pin<Gpio5, PushPullOutput>>,
+    Pin<Gpio4, PushPullOutput>,
+>;
+
+/// How the screen sits on its ST7789, in the portrait orientation
+/// [PicoSystem::new] sets.
+pub const PANEL: Panel = Panel {
+    controller: Controller::St7789,
+    offset: (0, 0),
+    madctl: 0,
+    invert: true,
+};
+
+/// A pair of full screen framebuffers for [buffered_screen]. They take 225
+/// KiB, most of the RAM.
+pub type ScreenBuffers = Buffers<{ SCREEN_SIZE as usize }, { SCREEN_SIZE as usize }>;
+
+/// The screen with DMA flushed framebuffers, as returned by
+/// [buffered_screen].
+pub type BufferedScreen = SpiLcd<
+    SPI0,
+    Pin<Gpio9, PushPullOutput>,
+    Pin<Gpio5, PushPullOutput>,
+    { SCREEN_SIZE as usize },
+    { SCREEN_SIZE as usize },
+>;
+
+/// Switch [PicoSystem::screen] over to drawing into `buffers`, which are
+/// flushed to it by the `dma` channel while the next frame is drawn.
+///
+/// Also hands back the screen's reset pin, which must stay high.
+pub fn buffered_screen(
+    screen: Screen,
+    dma: Channel,
+    buffers: &'static mut ScreenBuffers,
+) -> (BufferedScreen, Pin<Gpio4, PushPullOutput>) {
+    let (interface, reset) = screen.release();
+    let (spi, dc, cs) = interface.release();
+    (
+        SpiLcd::from_initialised(spi, dc, cs, dma, PANEL, buffers),
+        reset,
+    )
+}
+
+/// One of the PicoSystem's eight buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+    X,
+    Y,
+}
+
+impl Button {
+    /// All buttons, in the order of the bits returned by [Buttons::state].
+    pub const ALL: [Button; 8] = [
+        Button::Up,
+        Button::Down,
+        Button::Left,
+        Button::Right,
+        Button::A,
+        Button::B,
+        Button::X,
+        Button::Y,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+/// The d-pad and the A/B/X/Y buttons, debounced with
+/// [rp_hal_boards_common::input::Button].
+///
+/// Call [Buttons::update] at a regular interval - every millisecond or so works
+/// well. A button has to read the same for [Buttons::DEBOUNCE_SAMPLES]
+/// consecutive updates before its debounced state changes, and one kept down
+/// for [Buttons::HOLD_SAMPLES] updates is reported once by
+/// [Buttons::just_held].
+pub struct Buttons {
+    /// In the order of [Button::ALL].
+    buttons: [input::Button<DynPin>; 8],
+    state: u8,
+    previous: u8,
+    held: u8,
+}
+
+impl Buttons {
+    /// Number of identical consecutive samples needed to accept a change.
+    pub const DEBOUNCE_SAMPLES: u8 = input::DEFAULT_DEBOUNCE_UPDATES;
+
+    /// Number of samples a button has to stay down to be held.
+    pub const HOLD_SAMPLES: u16 = input::DEFAULT_HOLD_UPDATES;
+
+    /// Sample all buttons once and update the debounced state.
+    pub fn update(&mut self) {
+        self.previous = self.state;
+        self.held = 0;
+        for (i, button) in self.buttons.iter_mut().enumerate() {
+            let bit = 1 << i;
+            match button.update().unwrap() {
+                Some(ButtonEvent::Pressed) => self.state |= bit,
+                Some(ButtonEvent::Released) => self.state &= !bit,
+                Some(ButtonEvent::Held) => self.held |= bit,
+                None => {}
+            }
+        }
+    }
+
+    /// Debounced state of all buttons, one bit per button in the order of
+    /// [Button::ALL]. A set bit means the button is held down.
+    pub fn state(&self) -> u8 {
+        self.state
+    }
+
+    /// Whether `button` is held down.
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.state & button.bit() != 0
+    }
+
+    /// Whether `button` went down during the last [Buttons::update].
+    pub fn just_pressed(&self, button: Button) -> bool {
+        (self.state & !self.previous) & button.bit() != 0
+    }
+
+    /// Whether `button` was let go during the last [Buttons::update].
+    pub fn just_released(&self, button: Button) -> bool {
+        (!self.state & self.previous) & button.bit() != 0
+    }
+
+    /// Whether `button` reached [Buttons::HOLD_SAMPLES] updates down during
+    /// the last [Buttons::update].
+    pub fn just_held(&self, button: Button) -> bool {
+        self.held & button.bit() != 0
+    }
+
+    /// The undebounced state of all buttons, in the same format as
+    /// [Buttons::state].
+    pub fn raw(&self) -> u8 {
+        self.buttons.iter().enumerate().fold(0, |acc, (i, button)| {
+            acc | (button.is_raw_pressed().unwrap() as u8) << i
+        })
+    }
+}
+
+/// The screen backlight, dimmed with PWM slice 6.
+pub struct Backlight {
+    pwm: Slice<Pwm6, FreeRunning>,
+}
+
+impl Backlight {
+    /// Set the brightness, from 0 (off) to 255 (full).
+    pub fn set_brightness(&mut self, brightness: u8) {
+        use embedded_hal::PwmPin;
+        // Square the value so the steps look roughly even to the eye.
+        let level = u32::from(brightness) * u32::from(brightness);
+        self.pwm.channel_a.set_duty(level as u16);
+    }
+}
+
+/// The piezo speaker, driven by PWM slice 5.
+pub struct Audio {
+    pwm: Slice<Pwm5, FreeRunning>,
+    system_clock: HertzU32,
+}
+
+impl Audio {
+    /// Play a square wave of `frequency` Hz, at `volume` percent (0 - 100).
+    ///
+    /// The tone plays until [Audio::stop] or another call to `play`.
+    pub fn play(&mut self, frequency: HertzU32, volume: u8) {
+        use embedded_hal::PwmPin;
+        let frequency = frequency.to_Hz().max(20);
+        // Pick the smallest integer divider that keeps TOP within 16 bits.
+        let cycles = self.system_clock.to_Hz() / frequency;
+        let div = ((cycles >> 16) + 1).min(255);
+        let top = (cycles / div).min(0xffff);
+        self.pwm.set_div_int(div as u8);
+        self.pwm.set_div_frac(0);
+        self.pwm.set_top(top as u16);
+        // A 50% duty cycle is as loud as a piezo gets.
+        let duty = top * u32::from(volume.min(100)) / 200;
+        self.pwm.channel_b.set_duty(duty as u16);
+    }
+
+    /// Silence the speaker.
+    pub fn stop(&mut self) {
+        use embedded_hal::PwmPin;
+        self.pwm.channel_b.set_duty(0);
+    }
+}
+
+/// Battery monitoring through ADC0 and the charger status line.
+pub struct Battery {
+    adc: Adc,
+    level: Pin<Gpio26, FloatingInput>,
+    charging: Pin<Gpio24, PullUpInput>,
+}
+
+impl Battery {
+    /// Battery voltage, in volts.
+    ///
+    /// The battery is connected to GPIO26 through a 1:3 divider.
+    pub fn voltage(&mut self) -> f32 {
+        let raw: u16 = self.adc.read(&mut self.level).unwrap();
+        f32::from(raw) * 3.3 * 3.0 / 4096.0
+    }
+
+    /// Rough state of charge, in percent, assuming a linear discharge between
+    /// 3.0 V (empty) and 4.1 V (full).
+    pub fn percent(&mut self) -> u8 {
+        let fraction = (self.voltage() - 3.0) / (4.1 - 3.0);
+        (fraction.clamp(0.0, 1.0) * 100.0) as u8
+    }
+
+    /// Whether the battery is currently being charged.
+    pub fn is_charging(&self) -> bool {
+        self.charging.is_low().unwrap()
+    }
+
+    /// Give back the ADC, so it can be used for something else.
+    pub fn free(self) -> Adc {
+        self.adc
+    }
+}
+
+/// Everything on the PicoSystem, set up and ready to use.
+pub struct PicoSystem {
+    pub screen: Screen,
+    pub backlight: Backlight,
+    pub buttons: Buttons,
+    pub audio: Audio,
+    pub battery: Battery,
+}
+
+impl PicoSystem {
+    /// Set up the screen, backlight, buttons, audio and battery monitor.
+    ///
+    /// `system_clock` is the frequency the system clock was configured to, used
+    /// for the SPI bus and to compute tone frequencies. The screen is cleared and
+    /// the backlight turned fully on.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        io: pac::IO_BANK0,
+        pads: pac::PADS_BANK0,
+        sio: SioGpioBank0,
+        spi0: SPI0,
+        mut pwm5: Slice<Pwm5, FreeRunning>,
+        mut pwm6: Slice<Pwm6, FreeRunning>,
+        adc: Adc,
+        system_clock: HertzU32,
+        resets: &mut RESETS,
+        delay: &mut impl DelayUs<u32>,
+    ) -> (Self, Pins) {
+        let internal_pins = internal_pins::Pins::new(io, pads, sio, resets);
+
+        // The buttons short the pin to ground when pressed.
+        let button = |mut pin: DynPin| {
+            pin.into_pull_up_input();
+            input::Button::new(pin, Active::Low)
+        };
+        let buttons = Buttons {
+            buttons: [
+                button(internal_pins.button_up.into()),
+                button(internal_pins.button_down.into()),
+                button(internal_pins.button_left.into()),
+                button(internal_pins.button_right.into()),
+                button(internal_pins.button_a.into()),
+                button(internal_pins.button_b.into()),
+                button(internal_pins.button_x.into()),
+                button(internal_pins.button_y.into()),
+            ],
+            state: 0,
+            previous: 0,
+            held: 0,
+        };
+
+        pwm6.set_top(u16::MAX);
+        pwm6.enable();
+        pwm6.channel_a
+            .output_to(internal_pins.backlight.into_mode::<FunctionPwm>());
+        let mut backlight = Backlight { pwm: pwm6 };
+
+        pwm5.enable();
+        pwm5.channel_b
+            .output_to(internal_pins.audio.into_mode::<FunctionPwm>());
+        let mut audio = Audio {
+            pwm: pwm5,
+            system_clock,
+        };
+        audio.stop();
+
+        let battery = Battery {
+            adc,
+            level: internal_pins.battery_level.into_floating_input(),
+            charging: internal_pins.charging.into_pull_up_input(),
+        };
+
+        let dc: Pin<Gpio9, PushPullOutput> = internal_pins.lcd_dc.into_push_pull_output();
+        let cs: Pin<Gpio5, PushPullOutput> = internal_pins.lcd_cs.into_push_pull_output();
+        let reset: Pin<Gpio4, PushPullOutput> = internal_pins.lcd_reset.into_push_pull_output();
+        let _sclk: Pin<_, FunctionSpi> = internal_pins.lcd_sclk.into_mode();
+        let _mosi: Pin<_, FunctionSpi> = internal_pins.lcd_mosi.into_mode();
+        let spi = Spi::<_, _, 8>::new(spi0).init(resets, system_clock, 62_500u32.kHz(), &MODE_0);
+        let mut screen = ST7789::new(
+            SPIInterface::new(spi, dc, cs),
+            reset,
+            SCREEN_SIZE,
+            SCREEN_SIZE,
+        );
+        screen.init(delay).unwrap();
+        screen
+            .set_orientation(st7789::Orientation::Portrait)
+            .unwrap();
+        screen.clear(Rgb565::BLACK).unwrap();
+        backlight.set_brightness(255);
+
+        (
+            PicoSystem {
+                screen,
+                backlight,
+                buttons,
+                audio,
+                battery,
+            },
+            Pins {
+                charge_led: internal_pins.charge_led,
+                lcd_vsync: internal_pins.lcd_vsync,
+                led_red: internal_pins.led_red,
+                led_green: internal_pins.led_green,
+                led_blue: internal_pins.led_blue,
+            },
+        )
+    }
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
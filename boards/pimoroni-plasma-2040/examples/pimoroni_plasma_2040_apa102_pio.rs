@@ -0,0 +1,184 @@
+//! # Pimoroni Plasma 2040 APA102 PIO Example
+//!
+//! Drives a clocked APA102 (DotStar) strip connected to the `CLK` and `DAT`
+//! terminals with the PIO SPI program from `rp-hal-boards-pio`, so the CPU
+//! only has to push one 32-bit word per LED.
+//!
+//! Buttons `A` and `B` change the brightness, and the current drawn by the
+//! strip is measured with the on-board current sense and printed with defmt
+//! once a second. The RGB LED turns red if the strip draws more than the
+//! `CURRENT_LIMIT_MA`.
+
+#![no_std]
+#![no_main]
+
+use defmt::info;
+use defmt_rtt as _;
+use embedded_hal::digital::v2::OutputPin;
+use panic_halt as _;
+
+// Pull in any important traits
+use pimoroni_plasma_2040::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use pimoroni_plasma_2040::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use pimoroni_plasma_2040::hal;
+
+use hal::pio::{PIOBuilder, PIOExt, PinDir, ShiftDirection, Tx, ValidStateMachine};
+use pimoroni_plasma_2040::{Apa102Pins, Buttons, CurrentSense};
+
+/// Number of LEDs on the strip.
+const STRIP_LEN: usize = 60;
+
+/// Above this current the RGB LED turns red.
+const CURRENT_LIMIT_MA: u32 = 2000;
+
+/// GPIO numbers of the `DAT` and `CLK` terminals.
+const DATA_PIN: u8 = 15;
+const CLOCK_PIN: u8 = 14;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[pimoroni_plasma_2040::entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables and the spinlock are initialised.
+#[pimoroni_plasma_2040::entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let clocks = hal::clocks::init_clocks_and_plls(
+        pimoroni_plasma_2040::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = pimoroni_plasma_2040::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    let buttons = Buttons::new(pins.button_a, pins.button_b, pins.user_sw);
+    let mut current_sense = CurrentSense::new(pins.current_sense);
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+
+    // The RGB LED is active low
+    let mut led_red = pins.led_red.into_push_pull_output();
+    let mut led_green = pins.led_green.into_push_pull_output();
+    let mut led_blue = pins.led_blue.into_push_pull_output();
+    led_blue.set_high().unwrap();
+
+    // Hand the strip terminals to PIO0
+    let _strip_pins: Apa102Pins = (pins.data.into_mode(), pins.clk.into_mode());
+
+    // Shift one bit out on DAT per clock, with CLK as side-set: the data
+    // changes while the clock is low and is latched on the rising edge. The
+    // SPI program also samples an input, which the strip doesn't need.
+    let program = rp_hal_boards_pio::spi::program();
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let installed = pio.install(&program).unwrap();
+    let (mut sm, _, mut tx) = PIOBuilder::from_program(installed)
+        .out_pins(DATA_PIN, 1)
+        .side_set_pin_base(CLOCK_PIN)
+        .out_shift_direction(ShiftDirection::Left)
+        .autopull(true)
+        .pull_threshold(32)
+        // Four cycles per bit: 125 MHz / 5 / 4 = 6.25 MHz clock
+        .clock_divisor_fixed_point(5, 0)
+        .build(sm0);
+    sm.set_pindirs([(DATA_PIN, PinDir::Output), (CLOCK_PIN, PinDir::Output)]);
+    sm.start();
+
+    let mut global_brightness: u8 = 8;
+    let mut hue: u8 = 0;
+    let mut frame: u32 = 0;
+    loop {
+        if buttons.a_pressed() {
+            global_brightness = (global_brightness + 1).min(31);
+        }
+        if buttons.b_pressed() {
+            global_brightness = global_brightness.saturating_sub(1);
+        }
+
+        write_frame(
+            &mut tx,
+            global_brightness,
+            (0..STRIP_LEN).map(|i| wheel(hue.wrapping_add((i * 256 / STRIP_LEN) as u8))),
+        );
+        hue = hue.wrapping_add(1);
+
+        frame = frame.wrapping_add(1);
+        if frame.is_multiple_of(50) {
+            let milliamps = current_sense.read_milliamps(&mut adc);
+            info!("strip current: {} mA", milliamps);
+            let over = milliamps > CURRENT_LIMIT_MA;
+            led_red.set_state((!over).into()).unwrap();
+            led_green.set_state(over.into()).unwrap();
+        }
+
+        delay.delay_ms(20);
+    }
+}
+
+/// Send one APA102 frame: a start frame of zeros, one word per LED and enough
+/// trailing clock edges to push the data through the whole strip.
+fn write_frame<SM: ValidStateMachine>(
+    tx: &mut Tx<SM>,
+    global_brightness: u8,
+    colours: impl Iterator<Item = (u8, u8, u8)>,
+) {
+    let mut push = |word: u32| while !tx.write(word) {};
+
+    push(0x0000_0000);
+    for (r, g, b) in colours {
+        let header = 0xE0 | u32::from(global_brightness & 0x1F);
+        push(header << 24 | u32::from(b) << 16 | u32::from(g) << 8 | u32::from(r));
+    }
+    // One extra clock edge for every two LEDs
+    for _ in 0..STRIP_LEN.div_ceil(64) {
+        push(0xFFFF_FFFF);
+    }
+}
+
+/// Convert a number from `0..=255` to an RGB color triplet.
+///
+/// The colours are a transition from red, to green, to blue and back to red.
+fn wheel(mut wheel_pos: u8) -> (u8, u8, u8) {
+    wheel_pos = 255 - wheel_pos;
+    if wheel_pos < 85 {
+        // No green in this sector - red and blue only
+        (255 - (wheel_pos * 3), 0, wheel_pos * 3)
+    } else if wheel_pos < 170 {
+        // No red in this sector - green and blue only
+        wheel_pos -= 85;
+        (0, wheel_pos * 3, 255 - (wheel_pos * 3))
+    } else {
+        // No blue in this sector - red and green only
+        wheel_pos -= 170;
+        (wheel_pos * 3, 255 - (wheel_pos * 3), 0)
+    }
+}
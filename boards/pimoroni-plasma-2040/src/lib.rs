@@ -15,6 +15,11 @@ pub static BOOT2_FIRMWARE: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
 
 pub use hal::pac;
 
+use embedded_hal::adc::OneShot;
+use embedded_hal::digital::v2::InputPin;
+use hal::gpio::bank0::{Gpio12, Gpio13, Gpio23, Gpio29};
+use hal::gpio::{FloatingInput, Pin, PullUpInput};
+
 hal::bsp_pins!(
     /// GPIO 0 is connected to I2C0_SDA
     Gpio0 { name: i2c0_sda },
@@ -31,9 +36,15 @@ hal::bsp_pins!(
     /// GPIO 13 is connected to button B, active low
     Gpio13 { name: button_b },
     /// GPIO 14 is connected to CLK for APA102 only
-    Gpio14 { name: clk },
+    Gpio14 {
+        name: clk,
+        aliases: { FunctionPio0: LedClock }
+    },
     /// GPIO 15 is connected to DAT for Apa102 and Ws2812
-    Gpio15 { name: data },
+    Gpio15 {
+        name: data,
+        aliases: { FunctionPio0: LedData }
+    },
     /// GPIO 16 is red LED, active low
     Gpio16 { name: led_red },
     /// GPIO 17 is green LED, active low
@@ -61,12 +72,107 @@ hal::bsp_pins!(
     /// GPIO 28 is connected to ADC2
     Gpio28 { name: adc2 },
     /// GPIO 29 is connected to ADC3 which is used for low side current sensing
-    Gpio29 {
-        name: current_sense,
-    },
+    Gpio29 { name: current_sense },
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
 
+/// Gain of the amplifier between the current-sense shunt and the ADC.
 pub const ADC_GAIN: u32 = 50;
+/// Value of the low side current-sense shunt, in ohms.
 pub const SHUNT_RESISTOR: f32 = 0.015;
+
+/// ADC reference voltage, in volts.
+const ADC_REFERENCE: f32 = 3.3;
+
+/// Data pin of a WS2812 (non-clocked) strip on the terminal block.
+///
+/// This is the same `DAT` terminal that APA102 strips use, handed to PIO0.
+pub type Ws2812Data = LedData;
+
+/// Data and clock pins of an APA102 (clocked) strip on the terminal block.
+pub type Apa102Pins = (LedData, LedClock);
+
+/// Button `A`, with its pull-up enabled.
+pub type ButtonA = Pin<Gpio12, PullUpInput>;
+
+/// Button `B`, with its pull-up enabled.
+pub type ButtonB = Pin<Gpio13, PullUpInput>;
+
+/// The `BOOT`/`USER_SW` button, with its pull-up enabled.
+pub type UserSwitch = Pin<Gpio23, PullUpInput>;
+
+/// The on-board buttons. All of them pull their pin low when pressed.
+pub struct Buttons {
+    pub a: ButtonA,
+    pub b: ButtonB,
+    pub user: UserSwitch,
+}
+
+impl Buttons {
+    /// Configure the three button pins as inputs with pull-ups.
+    pub fn new<MA, MB, MU>(a: Pin<Gpio12, MA>, b: Pin<Gpio13, MB>, user: Pin<Gpio23, MU>) -> Self
+    where
+        MA: hal::gpio::PinMode + hal::gpio::ValidPinMode<Gpio12>,
+        MB: hal::gpio::PinMode + hal::gpio::ValidPinMode<Gpio13>,
+        MU: hal::gpio::PinMode + hal::gpio::ValidPinMode<Gpio23>,
+    {
+        Buttons {
+            a: a.into_pull_up_input(),
+            b: b.into_pull_up_input(),
+            user: user.into_pull_up_input(),
+        }
+    }
+
+    /// `true` while button `A` is held down.
+    pub fn a_pressed(&self) -> bool {
+        self.a.is_low().unwrap()
+    }
+
+    /// `true` while button `B` is held down.
+    pub fn b_pressed(&self) -> bool {
+        self.b.is_low().unwrap()
+    }
+
+    /// `true` while the `BOOT`/`USER_SW` button is held down.
+    pub fn user_pressed(&self) -> bool {
+        self.user.is_low().unwrap()
+    }
+}
+
+/// The low side current sense on ADC channel 3, measuring the current drawn
+/// by the LED strip.
+pub struct CurrentSense {
+    pin: Pin<Gpio29, FloatingInput>,
+}
+
+impl CurrentSense {
+    /// Configure `GPIO29` as an ADC input.
+    pub fn new<M>(pin: Pin<Gpio29, M>) -> Self
+    where
+        M: hal::gpio::PinMode + hal::gpio::ValidPinMode<Gpio29>,
+    {
+        CurrentSense {
+            pin: pin.into_floating_input(),
+        }
+    }
+
+    /// Take one ADC sample and convert it to amps.
+    pub fn read_amps(&mut self, adc: &mut hal::Adc) -> f32 {
+        let raw: u16 = adc.read(&mut self.pin).unwrap();
+        let volts = f32::from(raw) * ADC_REFERENCE / 4096.0;
+        volts / (ADC_GAIN as f32 * SHUNT_RESISTOR)
+    }
+
+    /// Take one ADC sample and convert it to milliamps.
+    pub fn read_milliamps(&mut self, adc: &mut hal::Adc) -> u32 {
+        (self.read_amps(adc) * 1000.0) as u32
+    }
+
+    /// Release the pin.
+    pub fn free(self) -> Pin<Gpio29, FloatingInput> {
+        self.pin
+    }
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
@@ -0,0 +1,194 @@
+//! # Pimoroni Servo2040 Hexapod Example
+//!
+//! Walks an 18 servo hexapod with a tripod gait, using the servo helper from
+//! `rp_hal_boards_common::servo`.
+//!
+//! The RP2040 has sixteen PWM channels, and Servo 17 and 18 share theirs with
+//! Servo 1 and 2. So the four legs on Servo 1 to 12 use the chip's own PWM, and
+//! the two remaining legs are driven by a PCA9685 breakout on the Qw/ST
+//! connector (I²C on GPIO20 and GPIO21), on its channels 0 to 5. Both kinds of
+//! output implement `PwmOutput`, so the gait code doesn't care which is which.
+//!
+//! Each leg has three servos, in the order coxa (hip swing), femur (lift) and
+//! tibia (knee). Legs are numbered left front, left middle, left rear, right
+//! front, right middle, right rear.
+#![no_std]
+#![no_main]
+
+use core::convert::Infallible;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use pimoroni_servo2040::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use pimoroni_servo2040::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use pimoroni_servo2040::hal;
+
+use fugit::RateExtU32;
+use hal::pwm::{Slice, SliceId, SliceMode, ValidSliceMode};
+use rp_hal_boards_common::pca9685::{Pca9685, DEFAULT_ADDRESS};
+use rp_hal_boards_common::servo::{self, NativePwm, PwmOutput, Servo, NATIVE_TOP};
+
+/// Hip swing through one gait cycle, in degrees.
+const COXA: [i16; 8] = [-20, -10, 0, 10, 20, 10, 0, -10];
+
+/// Leg lift through one gait cycle: the leg is raised while it swings forward.
+const FEMUR: [i16; 8] = [0, 25, 25, 25, 0, 0, 0, 0];
+
+/// The knee stays bent.
+const TIBIA: i16 = -30;
+
+/// Time between gait steps, in milliseconds.
+const STEP_MS: u32 = 120;
+
+#[pimoroni_servo2040::entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    let sio = hal::Sio::new(pac.SIO);
+
+    let clocks = hal::clocks::init_clocks_and_plls(
+        pimoroni_servo2040::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    let pins = pimoroni_servo2040::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Run PWM slices 0 to 5 at the 50 Hz servo rate, with 16 bit resolution
+    let (div_int, div_frac) = servo::native_clock_divider(clocks.system_clock.freq().to_Hz());
+    let slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    let mut pwm0 = slices.pwm0;
+    let mut pwm1 = slices.pwm1;
+    let mut pwm2 = slices.pwm2;
+    let mut pwm3 = slices.pwm3;
+    let mut pwm4 = slices.pwm4;
+    let mut pwm5 = slices.pwm5;
+    configure(&mut pwm0, div_int, div_frac);
+    configure(&mut pwm1, div_int, div_frac);
+    configure(&mut pwm2, div_int, div_frac);
+    configure(&mut pwm3, div_int, div_frac);
+    configure(&mut pwm4, div_int, div_frac);
+    configure(&mut pwm5, div_int, div_frac);
+
+    pwm0.channel_a.output_to(pins.servo1);
+    pwm0.channel_b.output_to(pins.servo2);
+    pwm1.channel_a.output_to(pins.servo3);
+    pwm1.channel_b.output_to(pins.servo4);
+    pwm2.channel_a.output_to(pins.servo5);
+    pwm2.channel_b.output_to(pins.servo6);
+    pwm3.channel_a.output_to(pins.servo7);
+    pwm3.channel_b.output_to(pins.servo8);
+    pwm4.channel_a.output_to(pins.servo9);
+    pwm4.channel_b.output_to(pins.servo10);
+    pwm5.channel_a.output_to(pins.servo11);
+    pwm5.channel_b.output_to(pins.servo12);
+
+    let mut out1 = NativePwm::new(pwm0.channel_a, NATIVE_TOP);
+    let mut out2 = NativePwm::new(pwm0.channel_b, NATIVE_TOP);
+    let mut out3 = NativePwm::new(pwm1.channel_a, NATIVE_TOP);
+    let mut out4 = NativePwm::new(pwm1.channel_b, NATIVE_TOP);
+    let mut out5 = NativePwm::new(pwm2.channel_a, NATIVE_TOP);
+    let mut out6 = NativePwm::new(pwm2.channel_b, NATIVE_TOP);
+    let mut out7 = NativePwm::new(pwm3.channel_a, NATIVE_TOP);
+    let mut out8 = NativePwm::new(pwm3.channel_b, NATIVE_TOP);
+    let mut out9 = NativePwm::new(pwm4.channel_a, NATIVE_TOP);
+    let mut out10 = NativePwm::new(pwm4.channel_b, NATIVE_TOP);
+    let mut out11 = NativePwm::new(pwm5.channel_a, NATIVE_TOP);
+    let mut out12 = NativePwm::new(pwm5.channel_b, NATIVE_TOP);
+
+    // Every channel is a different type, so keep them behind trait objects
+    let mut native: [Servo<&mut dyn PwmOutput<Error = Infallible>>; 12] = [
+        Servo::new(&mut out1),
+        Servo::new(&mut out2),
+        Servo::new(&mut out3),
+        Servo::new(&mut out4),
+        Servo::new(&mut out5),
+        Servo::new(&mut out6),
+        Servo::new(&mut out7),
+        Servo::new(&mut out8),
+        Servo::new(&mut out9),
+        Servo::new(&mut out10),
+        Servo::new(&mut out11),
+        Servo::new(&mut out12),
+    ];
+
+    // The PCA9685 on the Qw/ST connector drives the last two legs
+    let sda_pin = pins.sda.into_mode::<hal::gpio::FunctionI2C>();
+    let scl_pin = pins.scl.into_mode::<hal::gpio::FunctionI2C>();
+    let i2c = hal::I2C::i2c0(
+        pac.I2C0,
+        sda_pin,
+        scl_pin,
+        400.kHz(),
+        &mut pac.RESETS,
+        &clocks.peripheral_clock,
+    );
+    let pca9685 = Pca9685::new(i2c, DEFAULT_ADDRESS, |us| delay.delay_us(us)).unwrap();
+    let mut expander = [0, 1, 2, 3, 4, 5].map(|channel| Servo::new(pca9685.channel(channel)));
+
+    let mut phase = 0;
+    loop {
+        for (leg, servos) in native.chunks_mut(3).enumerate() {
+            pose(servos, leg, phase).unwrap();
+        }
+        for (leg, servos) in expander.chunks_mut(3).enumerate() {
+            pose(servos, leg + 4, phase).unwrap();
+        }
+
+        phase = (phase + 1) % COXA.len();
+        delay.delay_ms(STEP_MS);
+    }
+}
+
+/// Set up a slice for servo pulses and start it.
+fn configure<I: SliceId, M: SliceMode + ValidSliceMode<I>>(
+    slice: &mut Slice<I, M>,
+    int: u8,
+    frac: u8,
+) {
+    slice.set_div_int(int);
+    slice.set_div_frac(frac);
+    slice.set_top(NATIVE_TOP);
+    slice.enable();
+}
+
+/// Put the three servos of `leg` where they belong at `phase` of the gait.
+///
+/// Left front, left rear and right middle move together, the other three half
+/// a cycle later. The right side is mirrored.
+fn pose<O: PwmOutput>(servos: &mut [Servo<O>], leg: usize, phase: usize) -> Result<(), O::Error> {
+    let tripod = if leg == 0 || leg == 2 || leg == 4 {
+        0
+    } else {
+        4
+    };
+    let step = (phase + tripod) % COXA.len();
+    let mirror = if leg < 3 { 1 } else { -1 };
+    servos[0].set_angle(mirror * COXA[step])?;
+    servos[1].set_angle(mirror * FEMUR[step])?;
+    servos[2].set_angle(mirror * TIBIA)
+}
@@ -4,6 +4,12 @@ pub extern crate rp2040_hal as hal;
 
 pub use hal::pac;
 
+use hal::gpio::{
+    bank0::{Gpio11, Gpio12, Gpio13, Gpio14, Gpio15},
+    Pin, PinMode, PullDownInput, ValidPinMode,
+};
+use rp_hal_boards_common::input::{self, Active};
+
 #[cfg(feature = "rt")]
 pub use rp2040_hal::entry;
 
@@ -94,3 +100,48 @@ hal::bsp_pins!(
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+/// A front button, debounced.
+pub type Button<I> = input::Button<Pin<I, PullDownInput>>;
+
+/// The five buttons along the front, debounced with
+/// [rp_hal_boards_common::input::Button].
+///
+/// The buttons connect their pin to 3.3 V when pressed, so the pins are pulled
+/// down. Call `update` on each at a regular interval - every millisecond or so
+/// works well.
+pub struct Buttons {
+    pub a: Button<Gpio12>,
+    pub b: Button<Gpio13>,
+    pub c: Button<Gpio14>,
+    pub up: Button<Gpio15>,
+    pub down: Button<Gpio11>,
+}
+
+impl Buttons {
+    /// Configure the five button pins as inputs with pull-downs.
+    pub fn new<MA, MB, MC, MU, MD>(
+        sw_a: Pin<Gpio12, MA>,
+        sw_b: Pin<Gpio13, MB>,
+        sw_c: Pin<Gpio14, MC>,
+        sw_up: Pin<Gpio15, MU>,
+        sw_down: Pin<Gpio11, MD>,
+    ) -> Self
+    where
+        MA: PinMode + ValidPinMode<Gpio12>,
+        MB: PinMode + ValidPinMode<Gpio13>,
+        MC: PinMode + ValidPinMode<Gpio14>,
+        MU: PinMode + ValidPinMode<Gpio15>,
+        MD: PinMode + ValidPinMode<Gpio11>,
+    {
+        Buttons {
+            a: input::Button::new(sw_a.into_pull_down_input(), Active::High),
+            b: input::Button::new(sw_b.into_pull_down_input(), Active::High),
+            c: input::Button::new(sw_c.into_pull_down_input(), Active::High),
+            up: input::Button::new(sw_up.into_pull_down_input(), Active::High),
+            down: input::Button::new(sw_down.into_pull_down_input(), Active::High),
+        }
+    }
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
@@ -0,0 +1,270 @@
+//! # Pico W Bluetooth LE GATT Example
+//!
+//! Advertises as "Pico W" and serves two GATT services:
+//!
+//! * an LED service, whose one characteristic reads and writes the on-board
+//!   LED as a single byte, 0 for off and anything else for on;
+//! * the standard Environmental Sensing service, with a Temperature
+//!   characteristic reading the RP2040's own sensor, in hundredths of a
+//!   degree Celsius, and notifying subscribers once a second.
+//!
+//! A phone app such as nRF Connect can find the board, switch the LED and
+//! subscribe to the temperature.
+//!
+//! The Bluetooth half of the CYW43439 is brought up by `cyw43` after the
+//! WiFi half, and speaks HCI to `trouble-host`, the host stack, which runs
+//! the GATT server.
+//!
+//! Needs the `ble` feature:
+//!
+//! ```text
+//! cargo run --example pico_w_ble_gatt --features ble
+//! ```
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico_w::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico_w::hal::pac;
+
+// The macro for marking our interrupt handlers
+use rp_pico_w::hal::pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico_w::hal;
+
+// Some traits we need
+use embedded_hal::adc::OneShot;
+
+use embassy_executor::{Executor, Spawner};
+use embassy_futures::select::{select, Either};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_time::{Duration, Ticker};
+use hal::adc::TempSense;
+use rp_hal_boards_common::time_driver;
+use rp_pico_w::wifi::{cyw43, Gspi, Power, Runner, BLUETOOTH_FIRMWARE, CLM, FIRMWARE};
+use static_cell::StaticCell;
+use trouble_host::prelude::*;
+
+/// One phone at a time.
+const CONNECTIONS_MAX: usize = 1;
+/// The signalling and ATT channels.
+const L2CAP_CHANNELS_MAX: usize = 2;
+
+static EXECUTOR: StaticCell<Executor> = StaticCell::new();
+static STATE: StaticCell<cyw43::State> = StaticCell::new();
+
+#[gatt_server(connections_max = CONNECTIONS_MAX, mutex_type = NoopRawMutex)]
+struct Server {
+    led_service: LedService,
+    environment: EnvironmentalSensing,
+}
+
+#[gatt_service(uuid = "9a3c1a30-6f5b-4a2e-8b1e-5049434f5700")]
+struct LedService {
+    /// On or off
+    #[characteristic(uuid = "9a3c1a31-6f5b-4a2e-8b1e-5049434f5700", read, write, value = 0)]
+    led: u8,
+}
+
+#[gatt_service(uuid = service::ENVIRONMENTAL_SENSING)]
+struct EnvironmentalSensing {
+    /// In hundredths of a degree Celsius
+    #[characteristic(uuid = characteristic::TEMPERATURE, read, notify)]
+    temperature: i16,
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Start the clocks and set the pins up according to their function on
+    // this particular board
+    let rp_pico_w::Board { pins, .. } = rp_pico_w::Board::init(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // Give the TIMER to embassy-time
+    time_driver::start(pac.TIMER, &mut pac.RESETS);
+
+    // Pick a random static address, from the ring oscillator's jitter. The
+    // top two bits of a static address are set.
+    let rosc = hal::rosc::RingOscillator::new(pac.ROSC).initialize();
+    let mut address = [0; 6];
+    for byte in &mut address {
+        *byte = (0..8).fold(0, |byte, _| byte << 1 | u8::from(rosc.get_random_bit()));
+    }
+    address[5] |= 0xc0;
+
+    // The temperature sensor
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let temp_sensor = adc.enable_temp_sensor();
+
+    // The wireless chip's power and bus
+    let power = Power::new(pins.wl_on);
+    let spi = Gspi::new(pins.wl_cs, pins.wl_clk, pins.wl_d);
+
+    let executor = EXECUTOR.init(Executor::new());
+    executor
+        .run(move |spawner| spawner.must_spawn(ble(spawner, power, spi, address, adc, temp_sensor)))
+}
+
+/// Runs the wireless chip: `cyw43` does nothing unless this is polled.
+#[embassy_executor::task]
+async fn wifi(runner: Runner<'static>) -> ! {
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn ble(
+    spawner: Spawner,
+    power: Power,
+    spi: Gspi,
+    address: [u8; 6],
+    mut adc: hal::Adc,
+    mut temp_sensor: TempSense,
+) {
+    let state = STATE.init(cyw43::State::new());
+    let (_net_device, bt_device, mut control, runner) =
+        cyw43::new_with_bluetooth(state, power, spi, FIRMWARE, BLUETOOTH_FIRMWARE).await;
+    spawner.must_spawn(wifi(runner));
+    control.init(CLM).await;
+
+    let controller: ExternalController<_, 10> = ExternalController::new(bt_device);
+    let mut resources: HostResources<DefaultPacketPool, CONNECTIONS_MAX, L2CAP_CHANNELS_MAX> =
+        HostResources::new();
+    let stack =
+        trouble_host::new(controller, &mut resources).set_random_address(Address::random(address));
+    let Host {
+        mut peripheral,
+        mut runner,
+        ..
+    } = stack.build();
+
+    let server = Server::new_with_config(GapConfig::Peripheral(PeripheralConfig {
+        name: "Pico W",
+        appearance: &appearance::sensor::GENERIC_SENSOR,
+    }))
+    .unwrap();
+
+    let serve = async {
+        loop {
+            let Ok(conn) = advertise(&mut peripheral, &server).await else {
+                continue;
+            };
+
+            // Serve the phone until it leaves, sending it the temperature
+            // every second if it has subscribed
+            let mut ticker = Ticker::every(Duration::from_secs(1));
+            loop {
+                match select(conn.next(), ticker.next()).await {
+                    Either::First(GattConnectionEvent::Disconnected { .. }) => break,
+                    Either::First(GattConnectionEvent::Gatt { event }) => {
+                        let led = match &event {
+                            GattEvent::Write(write)
+                                if write.handle() == server.led_service.led.handle =>
+                            {
+                                write.data().first().map(|&on| on != 0)
+                            }
+                            _ => None,
+                        };
+                        if let Ok(reply) = event.accept() {
+                            reply.send().await;
+                        }
+                        if let Some(on) = led {
+                            control.gpio_set(0, on).await;
+                        }
+                    }
+                    Either::First(_) => {}
+                    Either::Second(()) => {
+                        let temperature = read_temperature(&mut adc, &mut temp_sensor);
+                        let _ = server
+                            .environment
+                            .temperature
+                            .notify(&conn, &temperature)
+                            .await;
+                    }
+                }
+            }
+        }
+    };
+
+    // The host's runner talks HCI to the controller, and must run alongside
+    let _ = select(runner.run(), serve).await;
+}
+
+/// Advertise until a phone connects, and serve GATT on its connection.
+async fn advertise<'values, 'server, C: Controller>(
+    peripheral: &mut Peripheral<'values, C, DefaultPacketPool>,
+    server: &'server Server<'values>,
+) -> Result<GattConnection<'values, 'server, DefaultPacketPool>, BleHostError<C::Error>> {
+    let mut adv_data = [0; 31];
+    let len = AdStructure::encode_slice(
+        &[
+            AdStructure::Flags(LE_GENERAL_DISCOVERABLE | BR_EDR_NOT_SUPPORTED),
+            AdStructure::ServiceUuids16(&[[0x1a, 0x18]]),
+            AdStructure::CompleteLocalName(b"Pico W"),
+        ],
+        &mut adv_data[..],
+    )?;
+    let advertiser = peripheral
+        .advertise(
+            &Default::default(),
+            Advertisement::ConnectableScannableUndirected {
+                adv_data: &adv_data[..len],
+                scan_data: &[],
+            },
+        )
+        .await?;
+    let conn = advertiser.accept().await?.with_attribute_server(server)?;
+    Ok(conn)
+}
+
+/// Read the RP2040's temperature sensor, in hundredths of a degree Celsius.
+fn read_temperature(adc: &mut hal::Adc, temp_sensor: &mut TempSense) -> i16 {
+    let raw: u16 = adc.read(temp_sensor).unwrap();
+    // The sensor reads 0.706 V at 27 °C and falls 1.721 mV per degree
+    let microvolts = i32::from(raw) * 3_300_000 / 4096;
+    (2700 - (microvolts - 706_000) * 100 / 1721) as i16
+}
+
+/// Wakes the tasks whose timers are due.
+#[interrupt]
+fn TIMER_IRQ_0() {
+    // Note (safety): the driver was started before the executor
+    unsafe { time_driver::handle_interrupt() };
+}
+
+// End of file
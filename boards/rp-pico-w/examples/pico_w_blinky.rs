@@ -0,0 +1,123 @@
+//! # Pico W Blinky Example
+//!
+//! Blinks the LED on a Pico W board.
+//!
+//! The Pico W's LED isn't on an RP2040 pin, but on GPIO 0 of the CYW43439
+//! wireless chip, so this brings the chip up with `cyw43`, loading its
+//! firmware, and sets the LED through it. `cyw43` is async, so this runs on
+//! `embassy-executor`, with `rp_hal_boards_common::time_driver` for its
+//! timers.
+//!
+//! Needs the `wifi` feature:
+//!
+//! ```text
+//! cargo run --example pico_w_blinky --features wifi
+//! ```
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico_w::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico_w::hal::pac;
+
+// The macro for marking our interrupt handlers
+use rp_pico_w::hal::pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico_w::hal;
+
+use embassy_executor::{Executor, Spawner};
+use embassy_time::Timer;
+use rp_hal_boards_common::time_driver;
+use rp_pico_w::wifi::{cyw43, Gspi, Power, Runner, CLM, FIRMWARE};
+use static_cell::StaticCell;
+
+static EXECUTOR: StaticCell<Executor> = StaticCell::new();
+static STATE: StaticCell<cyw43::State> = StaticCell::new();
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Start the clocks and set the pins up according to their function on
+    // this particular board
+    let rp_pico_w::Board { pins, .. } = rp_pico_w::Board::init(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // Give the TIMER to embassy-time
+    time_driver::start(pac.TIMER, &mut pac.RESETS);
+
+    // The wireless chip's power and bus
+    let power = Power::new(pins.wl_on);
+    let spi = Gspi::new(pins.wl_cs, pins.wl_clk, pins.wl_d);
+
+    let executor = EXECUTOR.init(Executor::new());
+    executor.run(|spawner| spawner.must_spawn(blink(spawner, power, spi)))
+}
+
+/// Runs the wireless chip: `cyw43` does nothing unless this is polled.
+#[embassy_executor::task]
+async fn wifi(runner: Runner<'static>) -> ! {
+    runner.run().await
+}
+
+#[embassy_executor::task]
+async fn blink(spawner: Spawner, power: Power, spi: Gspi) {
+    let state = STATE.init(cyw43::State::new());
+    let (_net_device, mut control, runner) = cyw43::new(state, power, spi, FIRMWARE).await;
+    spawner.must_spawn(wifi(runner));
+
+    control.init(CLM).await;
+    control
+        .set_power_management(cyw43::PowerManagementMode::PowerSave)
+        .await;
+
+    loop {
+        control.gpio_set(0, true).await;
+        Timer::after_millis(500).await;
+        control.gpio_set(0, false).await;
+        Timer::after_millis(500).await;
+    }
+}
+
+/// Wakes the tasks whose timers are due.
+#[interrupt]
+fn TIMER_IRQ_0() {
+    // Note (safety): the driver was started before the executor
+    unsafe { time_driver::handle_interrupt() };
+}
+
+// End of file
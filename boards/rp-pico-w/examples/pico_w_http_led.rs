@@ -0,0 +1,307 @@
+//! # Pico W HTTP LED Example
+//!
+//! Joins a WiFi network and serves a web page on port 80 that shows the
+//! on-board LED and toggles it.
+//!
+//! Set `WIFI_NETWORK` and `WIFI_PASSWORD` below to your network's before
+//! building. The address DHCP hands out is printed over USB Serial; open
+//! `http://<address>/` in a browser. `GET /toggle` flips the LED and sends
+//! the browser back to the page.
+//!
+//! The TCP/IP stack is `embassy-net`, on `smoltcp`, as in the `pico_w_dhcp`
+//! example. Only the request line is looked at, one client is served at a
+//! time, and each connection closes after its response.
+//!
+//! Needs the `wifi` feature:
+//!
+//! ```text
+//! cargo run --example pico_w_http_led --features wifi
+//! ```
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico_w::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico_w::hal::pac;
+
+// The macro for marking our interrupt handlers
+use rp_pico_w::hal::pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico_w::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use embassy_executor::{Executor, Spawner};
+use embassy_net::tcp::TcpSocket;
+use embassy_net::{Config, StackResources};
+use embassy_time::{Duration, Timer};
+use embedded_io_async::Write as _;
+use hal::usb::UsbBus;
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::time_driver;
+use rp_pico_w::wifi::{cyw43, Gspi, Power, Runner, CLM, FIRMWARE};
+use static_cell::StaticCell;
+
+/// The network to join.
+const WIFI_NETWORK: &str = "my-network";
+/// Its WPA2 passphrase.
+const WIFI_PASSWORD: &str = "my-password";
+/// The port to listen on.
+const PORT: u16 = 80;
+
+type Serial = BufferedSerial<'static, UsbBus, 512>;
+
+static EXECUTOR: StaticCell<Executor> = StaticCell::new();
+static STATE: StaticCell<cyw43::State> = StaticCell::new();
+static RESOURCES: StaticCell<StackResources<2>> = StaticCell::new();
+static USB_BUS: StaticCell<UsbBusAllocator<UsbBus>> = StaticCell::new();
+static SERIAL: StaticCell<RefCell<Serial>> = StaticCell::new();
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Start the clocks and set the pins up according to their function on
+    // this particular board
+    let rp_pico_w::Board { pins, clocks } = rp_pico_w::Board::init(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // Give the TIMER to embassy-time
+    time_driver::start(pac.TIMER, &mut pac.RESETS);
+
+    // Seed the stack's random numbers, for its DHCP transaction IDs and
+    // TCP sequence numbers, from the ring oscillator's jitter
+    let rosc = hal::rosc::RingOscillator::new(pac.ROSC).initialize();
+    let seed = (0..64).fold(0, |seed, _| seed << 1 | u64::from(rosc.get_random_bit()));
+
+    // The wireless chip's power and bus
+    let power = Power::new(pins.wl_on);
+    let spi = Gspi::new(pins.wl_cs, pins.wl_clk, pins.wl_d);
+
+    // Set up the USB driver
+    let usb_bus = USB_BUS.init(UsbBusAllocator::new(UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    )));
+
+    // Set up the USB Communications Class Device driver
+    let serial: &'static RefCell<Serial> = SERIAL.init(RefCell::new(BufferedSerial::new(usb_bus)));
+
+    // Create a USB device with a fake VID and PID
+    let usb_dev = UsbDeviceBuilder::new(usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Pico W HTTP LED")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let executor = EXECUTOR.init(Executor::new());
+    executor.run(move |spawner| {
+        spawner.must_spawn(usb(usb_dev, serial));
+        spawner.must_spawn(http(spawner, power, spi, seed, serial));
+    })
+}
+
+/// Runs the wireless chip: `cyw43` does nothing unless this is polled.
+#[embassy_executor::task]
+async fn wifi(runner: Runner<'static>) -> ! {
+    runner.run().await
+}
+
+/// Runs the TCP/IP stack.
+#[embassy_executor::task]
+async fn net(mut runner: embassy_net::Runner<'static, cyw43::NetDriver<'static>>) -> ! {
+    runner.run().await
+}
+
+/// Polls USB, which the RP2040's USB driver needs done often.
+#[embassy_executor::task]
+async fn usb(mut usb_dev: UsbDevice<'static, UsbBus>, serial: &'static RefCell<Serial>) -> ! {
+    loop {
+        usb_dev.poll(&mut [&mut *serial.borrow_mut()]);
+        Timer::after_millis(1).await;
+    }
+}
+
+#[embassy_executor::task]
+async fn http(
+    spawner: Spawner,
+    power: Power,
+    spi: Gspi,
+    seed: u64,
+    serial: &'static RefCell<Serial>,
+) {
+    let state = STATE.init(cyw43::State::new());
+    let (net_device, mut control, runner) = cyw43::new(state, power, spi, FIRMWARE).await;
+    spawner.must_spawn(wifi(runner));
+
+    control.init(CLM).await;
+    control
+        .set_power_management(cyw43::PowerManagementMode::PowerSave)
+        .await;
+
+    let resources = RESOURCES.init(StackResources::new());
+    let (stack, runner) = embassy_net::new(
+        net_device,
+        Config::dhcpv4(Default::default()),
+        resources,
+        seed,
+    );
+    spawner.must_spawn(net(runner));
+
+    // Keep trying until the network is in range
+    while control
+        .join(
+            WIFI_NETWORK,
+            cyw43::JoinOptions::new(WIFI_PASSWORD.as_bytes()),
+        )
+        .await
+        .is_err()
+    {
+        let _ = write!(serial.borrow_mut(), "Failed to join {}\r\n", WIFI_NETWORK);
+        Timer::after_secs(1).await;
+    }
+    let _ = write!(serial.borrow_mut(), "Joined {}\r\n", WIFI_NETWORK);
+
+    stack.wait_config_up().await;
+    if let Some(config) = stack.config_v4() {
+        let _ = write!(
+            serial.borrow_mut(),
+            "Listening on {}:{}\r\n",
+            config.address.address(),
+            PORT
+        );
+    }
+
+    let mut led = false;
+    control.gpio_set(0, led).await;
+
+    let mut rx_buffer = [0; 1024];
+    let mut tx_buffer = [0; 1024];
+    let mut buf = [0; 1024];
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_timeout(Some(Duration::from_secs(10)));
+        if socket.accept(PORT).await.is_err() {
+            continue;
+        }
+
+        // Read until the end of the request line
+        let mut len = 0;
+        while !buf[..len].contains(&b'\n') && len < buf.len() {
+            match socket.read(&mut buf[len..]).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => len += n,
+            }
+        }
+
+        let mut words = buf[..len].split(|&b| b == b' ');
+        let response: &[u8] = match (words.next(), words.next()) {
+            (Some(b"GET"), Some(b"/")) => {
+                if led {
+                    PAGE_ON
+                } else {
+                    PAGE_OFF
+                }
+            }
+            (Some(b"GET"), Some(b"/toggle")) => {
+                led = !led;
+                control.gpio_set(0, led).await;
+                let _ = write!(
+                    serial.borrow_mut(),
+                    "LED {}\r\n",
+                    if led { "on" } else { "off" }
+                );
+                REDIRECT
+            }
+            (Some(_), Some(_)) => NOT_FOUND,
+            _ => BAD_REQUEST,
+        };
+
+        let _ = socket.write_all(response).await;
+        socket.close();
+        let _ = socket.flush().await;
+    }
+}
+
+const PAGE_ON: &[u8] = b"HTTP/1.1 200 OK\r\n\
+Content-Type: text/html\r\n\
+Connection: close\r\n\
+\r\n\
+<!DOCTYPE html><html><body><h1>Pico W</h1>\
+<p>The LED is on.</p><p><a href=\"/toggle\">Turn it off</a></p>\
+</body></html>\r\n";
+
+const PAGE_OFF: &[u8] = b"HTTP/1.1 200 OK\r\n\
+Content-Type: text/html\r\n\
+Connection: close\r\n\
+\r\n\
+<!DOCTYPE html><html><body><h1>Pico W</h1>\
+<p>The LED is off.</p><p><a href=\"/toggle\">Turn it on</a></p>\
+</body></html>\r\n";
+
+const REDIRECT: &[u8] = b"HTTP/1.1 303 See Other\r\n\
+Location: /\r\n\
+Content-Length: 0\r\n\
+Connection: close\r\n\
+\r\n";
+
+const NOT_FOUND: &[u8] = b"HTTP/1.1 404 Not Found\r\n\
+Content-Length: 0\r\n\
+Connection: close\r\n\
+\r\n";
+
+const BAD_REQUEST: &[u8] = b"HTTP/1.1 400 Bad Request\r\n\
+Content-Length: 0\r\n\
+Connection: close\r\n\
+\r\n";
+
+/// Wakes the tasks whose timers are due.
+#[interrupt]
+fn TIMER_IRQ_0() {
+    // Note (safety): the driver was started before the executor
+    unsafe { time_driver::handle_interrupt() };
+}
+
+// End of file
@@ -0,0 +1,188 @@
+//! # Pico W WiFi Scan Example
+//!
+//! Scans for WiFi networks every ten seconds, and lists them over USB Serial.
+//!
+//! Each network is printed on a line of its own, with its BSSID, channel,
+//! signal strength and SSID:
+//!
+//! ```text
+//! 12:34:56:78:9a:bc  ch  6  -52 dBm  my-network
+//! ```
+//!
+//! The wireless chip runs through `cyw43` on `embassy-executor`, as in the
+//! `pico_w_blinky` example. USB is polled from a task of its own every
+//! millisecond, and shares the serial port with the scanning task.
+//!
+//! Needs the `wifi` feature:
+//!
+//! ```text
+//! cargo run --example pico_w_scan --features wifi
+//! ```
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico_w::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico_w::hal::pac;
+
+// The macro for marking our interrupt handlers
+use rp_pico_w::hal::pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico_w::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use embassy_executor::{Executor, Spawner};
+use embassy_time::Timer;
+use hal::usb::UsbBus;
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::time_driver;
+use rp_pico_w::wifi::{cyw43, Gspi, Power, Runner, CLM, FIRMWARE};
+use static_cell::StaticCell;
+
+/// The serial port, with room for a full scan's worth of lines.
+type Serial = BufferedSerial<'static, UsbBus, 2048>;
+
+static EXECUTOR: StaticCell<Executor> = StaticCell::new();
+static STATE: StaticCell<cyw43::State> = StaticCell::new();
+static USB_BUS: StaticCell<UsbBusAllocator<UsbBus>> = StaticCell::new();
+static SERIAL: StaticCell<RefCell<Serial>> = StaticCell::new();
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Start the clocks and set the pins up according to their function on
+    // this particular board
+    let rp_pico_w::Board { pins, clocks } = rp_pico_w::Board::init(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // Give the TIMER to embassy-time
+    time_driver::start(pac.TIMER, &mut pac.RESETS);
+
+    // The wireless chip's power and bus
+    let power = Power::new(pins.wl_on);
+    let spi = Gspi::new(pins.wl_cs, pins.wl_clk, pins.wl_d);
+
+    // Set up the USB driver
+    let usb_bus = USB_BUS.init(UsbBusAllocator::new(UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    )));
+
+    // Set up the USB Communications Class Device driver
+    let serial: &'static RefCell<Serial> = SERIAL.init(RefCell::new(BufferedSerial::new(usb_bus)));
+
+    // Create a USB device with a fake VID and PID
+    let usb_dev = UsbDeviceBuilder::new(usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Pico W WiFi Scan")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let executor = EXECUTOR.init(Executor::new());
+    executor.run(move |spawner| {
+        spawner.must_spawn(usb(usb_dev, serial));
+        spawner.must_spawn(scan(spawner, power, spi, serial));
+    })
+}
+
+/// Runs the wireless chip: `cyw43` does nothing unless this is polled.
+#[embassy_executor::task]
+async fn wifi(runner: Runner<'static>) -> ! {
+    runner.run().await
+}
+
+/// Polls USB, which the RP2040's USB driver needs done often.
+#[embassy_executor::task]
+async fn usb(mut usb_dev: UsbDevice<'static, UsbBus>, serial: &'static RefCell<Serial>) -> ! {
+    loop {
+        usb_dev.poll(&mut [&mut *serial.borrow_mut()]);
+        Timer::after_millis(1).await;
+    }
+}
+
+#[embassy_executor::task]
+async fn scan(spawner: Spawner, power: Power, spi: Gspi, serial: &'static RefCell<Serial>) {
+    let state = STATE.init(cyw43::State::new());
+    let (_net_device, mut control, runner) = cyw43::new(state, power, spi, FIRMWARE).await;
+    spawner.must_spawn(wifi(runner));
+
+    control.init(CLM).await;
+
+    loop {
+        let mut scanner = control.scan(Default::default()).await;
+        while let Some(bss) = scanner.next().await {
+            // Copy the fields out, as `BssInfo` is packed
+            let bssid = bss.bssid;
+            let channel = bss.chanspec & 0xff;
+            let rssi = bss.rssi;
+            let ssid = match &bss.ssid[..usize::from(bss.ssid_len).min(32)] {
+                [] => "(hidden)",
+                ssid => core::str::from_utf8(ssid).unwrap_or("(not UTF-8)"),
+            };
+
+            let mut serial = serial.borrow_mut();
+            for (i, byte) in bssid.iter().enumerate() {
+                let separator = if i == 0 { "" } else { ":" };
+                let _ = write!(serial, "{}{:02x}", separator, byte);
+            }
+            let _ = write!(serial, "  ch {:2}  {:4} dBm  {}\r\n", channel, rssi, ssid);
+        }
+        drop(scanner);
+
+        let _ = write!(serial.borrow_mut(), "\r\n");
+        Timer::after_secs(10).await;
+    }
+}
+
+/// Wakes the tasks whose timers are due.
+#[interrupt]
+fn TIMER_IRQ_0() {
+    // Note (safety): the driver was started before the executor
+    unsafe { time_driver::handle_interrupt() };
+}
+
+// End of file
@@ -0,0 +1,120 @@
+//! # Pico W VSYS Example
+//!
+//! Prints the Pico W's supply voltage, VSYS, over USB Serial once a second.
+//!
+//! On the Pico W, GPIO 29 is both the wireless chip's SPI clock and the ADC
+//! input for VSYS / 3. `rp_pico_w::supply` drives GPIO 25, the wireless
+//! chip's chip select, high to connect the divider; with no wireless driver
+//! running, nothing else needs the pins.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico_w::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico_w::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico_w::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_pico_w::supply::Supply;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Start the clocks and set the pins up according to their function on
+    // this particular board
+    let rp_pico_w::Board { pins, clocks } = rp_pico_w::Board::init(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // Keep the wireless chip powered down, and connect the VSYS divider
+    let mut wl_on = pins.wl_on.into_push_pull_output();
+    wl_on.set_low().unwrap();
+    let mut supply = Supply::new(pins.wl_cs, pins.wl_clk);
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Pico W VSYS")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut next = timer.get_counter().ticks();
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        if timer.get_counter().ticks() < next {
+            continue;
+        }
+        next += 1_000_000;
+
+        let millivolts = supply.vsys_voltage(&mut adc).unwrap();
+        let _ = write!(
+            serial,
+            "VSYS: {}.{:03} V\r\n",
+            millivolts / 1000,
+            millivolts % 1000
+        );
+    }
+}
+
+// End of file
@@ -0,0 +1,67 @@
+//! The Pico W's supply monitor.
+//!
+//! As on the Pico, VSYS reaches GPIO 29, ADC channel 3, through a divider
+//! that gives a third of it. On the Pico W, though, GPIO 29 is also the
+//! wireless chip's SPI clock, and the divider only reaches it while GPIO 25,
+//! the wireless chip's chip select, is high. [Supply::new] takes both pins
+//! from [Pins](crate::Pins) and holds GPIO 25 high, so the wireless chip
+//! can't be used meanwhile; [Supply::free] gives them back.
+//!
+//! VBUS detection is on the wireless chip's GPIO 2 rather than an RP2040
+//! pin, so it needs a wireless driver and isn't offered here.
+//!
+//! ```ignore
+//! let mut supply = Supply::new(pins.wl_cs, pins.wl_clk);
+//! let millivolts = supply.vsys_voltage(&mut adc);
+//! ```
+
+use embedded_hal::adc::OneShot;
+
+use crate::hal::{
+    gpio::{
+        bank0::{Gpio25, Gpio29},
+        FloatingInput, Pin, PinMode, PinState, PushPullOutput, ValidPinMode,
+    },
+    Adc,
+};
+
+/// The ADC's reference voltage on the Pico W, in millivolts.
+pub const ADC_VREF_MV: u32 = 3300;
+
+/// VSYS is this many times what the ADC sees.
+pub const VSYS_DIVIDER: u32 = 3;
+
+/// The Pico W's VSYS monitor.
+pub struct Supply {
+    wl_cs: Pin<Gpio25, PushPullOutput>,
+    vsys: Pin<Gpio29, FloatingInput>,
+}
+
+impl Supply {
+    /// Connect the VSYS divider, by driving the wireless chip select high,
+    /// and set up its pin as an input.
+    pub fn new<M25, M29>(wl_cs: Pin<Gpio25, M25>, wl_clk: Pin<Gpio29, M29>) -> Self
+    where
+        M25: PinMode + ValidPinMode<Gpio25>,
+        M29: PinMode + ValidPinMode<Gpio29>,
+    {
+        Supply {
+            wl_cs: wl_cs.into_push_pull_output_in_state(PinState::High),
+            vsys: wl_clk.into_floating_input(),
+        }
+    }
+
+    /// VSYS, in millivolts, or `None` if the ADC fails to convert.
+    ///
+    /// Accurate to within about 2%, mostly the tolerance of the Pico W's
+    /// 3.3 V regulator, which is also the ADC's reference.
+    pub fn vsys_voltage(&mut self, adc: &mut Adc) -> Option<u32> {
+        let raw: u16 = adc.read(&mut self.vsys).ok()?;
+        Some(u32::from(raw) * ADC_VREF_MV * VSYS_DIVIDER / 4096)
+    }
+
+    /// Give the pins back, for the wireless chip.
+    pub fn free(self) -> (Pin<Gpio25, PushPullOutput>, Pin<Gpio29, FloatingInput>) {
+        (self.wl_cs, self.vsys)
+    }
+}
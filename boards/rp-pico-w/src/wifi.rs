@@ -0,0 +1,223 @@
+//! The CYW43439 wireless chip, driven by the `cyw43` crate.
+//!
+//! `cyw43` loads the chip's firmware, runs its SDPCM/ioctl protocol and hands
+//! out three parts: a [`cyw43::NetDriver`] passing Ethernet frames, for a
+//! TCP/IP stack such as `embassy-net`; a [`cyw43::Control`] to join networks,
+//! scan and drive the chip's GPIOs, one of which lights the on-board LED; and
+//! a [`Runner`] that must be kept running in its own task. It is async, and
+//! waits with `embassy-time`, so it needs `embassy-executor` and a time
+//! driver, such as `rp_hal_boards_common::time_driver`, which this feature
+//! enables.
+//!
+//! This module provides the glue for the Pico W: [`Gspi`], the chip's
+//! half-duplex SPI bus, bit-banged on GPIO 24, 25 and 29, [`Power`] on GPIO
+//! 23, and the firmware blobs.
+//!
+//! ```ignore
+//! let power = Power::new(pins.wl_on);
+//! let spi = Gspi::new(pins.wl_cs, pins.wl_clk, pins.wl_d);
+//! let state = STATE.init(cyw43::State::new());
+//! let (net_device, mut control, runner) = cyw43::new(state, power, spi, FIRMWARE).await;
+//! spawner.must_spawn(wifi_task(runner));
+//!
+//! control.init(CLM).await;
+//! // The LED is on the chip's GPIO 0
+//! control.gpio_set(0, true).await;
+//! ```
+//!
+//! With the `ble` feature, `cyw43::new_with_bluetooth` also loads
+//! [`BLUETOOTH_FIRMWARE`] and hands out a `cyw43::bluetooth::BtDriver`, an
+//! HCI transport for a Bluetooth LE host stack such as `trouble-host`.
+//!
+//! Needs the `wifi` feature.
+
+use core::convert::Infallible;
+
+use embassy_futures::yield_now;
+use hal::gpio::bank0::{Gpio23, Gpio24, Gpio25, Gpio29};
+use hal::gpio::{Pin, PinMode, PinState, PushPullOutput, ReadableOutput, ValidPinMode};
+
+pub use cyw43;
+
+/// The WiFi firmware, for [`cyw43::new`].
+pub static FIRMWARE: &[u8] = include_bytes!("../firmware/43439A0.bin");
+
+/// The Country Locale Matrix, for [`cyw43::Control::init`].
+pub static CLM: &[u8] = include_bytes!("../firmware/43439A0_clm.bin");
+
+/// The Bluetooth firmware, for `cyw43::new_with_bluetooth`.
+#[cfg(feature = "ble")]
+pub static BLUETOOTH_FIRMWARE: &[u8] = include_bytes!("../firmware/43439A0_btfw.bin");
+
+/// The runner for the Pico W's wireless chip, to spawn in a task of its own.
+pub type Runner<'a> = cyw43::Runner<'a, Power, Gspi>;
+
+/// The wireless chip's data line, which also signals its interrupt.
+const DIO: u32 = 1 << 24;
+/// Its chip select, active low.
+const CS: u32 = 1 << 25;
+/// Its clock.
+const CLK: u32 = 1 << 29;
+
+/// The wireless chip's power, `WL_ON` on GPIO 23.
+pub struct Power {
+    pin: Pin<Gpio23, PushPullOutput>,
+}
+
+impl Power {
+    /// Take over `pin`, keeping the chip off until `cyw43` powers it up.
+    pub fn new<M: PinMode + ValidPinMode<Gpio23>>(pin: Pin<Gpio23, M>) -> Self {
+        Power {
+            pin: pin.into_push_pull_output_in_state(PinState::Low),
+        }
+    }
+
+    /// Return the pin.
+    pub fn free(self) -> Pin<Gpio23, PushPullOutput> {
+        self.pin
+    }
+}
+
+impl embedded_hal_1::digital::ErrorType for Power {
+    type Error = Infallible;
+}
+
+impl embedded_hal_1::digital::OutputPin for Power {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        embedded_hal::digital::v2::OutputPin::set_low(&mut self.pin)
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        embedded_hal::digital::v2::OutputPin::set_high(&mut self.pin)
+    }
+}
+
+/// The wireless chip's gSPI bus, bit-banged.
+///
+/// The chip shares one data line between both directions, which SPI
+/// peripherals can't do, and GPIO 24, 25 and 29 aren't on one PIO-friendly
+/// run of pins. So the bus is driven from the CPU through the SIO, at a few
+/// MHz: enough to load the firmware in well under a second, and for a few
+/// Mbit/s of WiFi traffic.
+///
+/// The chip raises the data line while chip select is high to ask for
+/// attention; [`cyw43::SpiBusCyw43::wait_for_event`] polls for that, yielding
+/// to other tasks in between.
+pub struct Gspi {
+    _cs: Pin<Gpio25, PushPullOutput>,
+    _clk: Pin<Gpio29, PushPullOutput>,
+    _dio: Pin<Gpio24, ReadableOutput>,
+}
+
+impl Gspi {
+    /// Take over the bus pins, with the chip deselected.
+    pub fn new<CsMode, ClkMode, DioMode>(
+        cs: Pin<Gpio25, CsMode>,
+        clk: Pin<Gpio29, ClkMode>,
+        dio: Pin<Gpio24, DioMode>,
+    ) -> Self
+    where
+        CsMode: PinMode + ValidPinMode<Gpio25>,
+        ClkMode: PinMode + ValidPinMode<Gpio29>,
+        DioMode: PinMode + ValidPinMode<Gpio24>,
+    {
+        let gspi = Gspi {
+            _cs: cs.into_push_pull_output_in_state(PinState::High),
+            _clk: clk.into_push_pull_output_in_state(PinState::Low),
+            _dio: dio.into_readable_output_in_state(PinState::Low),
+        };
+        // The chip drives the data line until spoken to
+        sio().gpio_oe_clr.write(|w| unsafe { w.bits(DIO) });
+        gspi
+    }
+
+    /// Return the pins.
+    #[allow(clippy::type_complexity)]
+    pub fn free(
+        self,
+    ) -> (
+        Pin<Gpio25, PushPullOutput>,
+        Pin<Gpio29, PushPullOutput>,
+        Pin<Gpio24, ReadableOutput>,
+    ) {
+        (self._cs, self._clk, self._dio)
+    }
+
+    /// Clock `words` out, most significant bit first, then hand the data
+    /// line back to the chip.
+    fn write(&mut self, words: &[u32]) {
+        let sio = sio();
+        sio.gpio_oe_set.write(|w| unsafe { w.bits(DIO) });
+        for &word in words {
+            for bit in (0..32).rev() {
+                // The chip samples on the rising edge
+                if word >> bit & 1 == 1 {
+                    sio.gpio_out_set.write(|w| unsafe { w.bits(DIO) });
+                } else {
+                    sio.gpio_out_clr.write(|w| unsafe { w.bits(DIO) });
+                }
+                sio.gpio_out_set.write(|w| unsafe { w.bits(CLK) });
+                cortex_m::asm::nop();
+                sio.gpio_out_clr.write(|w| unsafe { w.bits(CLK) });
+            }
+        }
+        sio.gpio_oe_clr.write(|w| unsafe { w.bits(DIO) });
+    }
+
+    /// Clock `words` in, most significant bit first.
+    fn read(&mut self, words: &mut [u32]) {
+        let sio = sio();
+        for word in words {
+            let mut value = 0;
+            for _ in 0..32 {
+                // Each bit is valid from one rising edge until the next
+                value = value << 1 | (sio.gpio_in.read().bits() & DIO) >> 24;
+                sio.gpio_out_set.write(|w| unsafe { w.bits(CLK) });
+                cortex_m::asm::nop();
+                sio.gpio_out_clr.write(|w| unsafe { w.bits(CLK) });
+            }
+            *word = value;
+        }
+    }
+
+    fn select(&mut self) {
+        sio().gpio_out_clr.write(|w| unsafe { w.bits(CS) });
+    }
+
+    fn deselect(&mut self) {
+        sio().gpio_out_set.write(|w| unsafe { w.bits(CS) });
+    }
+}
+
+impl cyw43::SpiBusCyw43 for Gspi {
+    async fn cmd_write(&mut self, write: &[u32]) -> u32 {
+        let mut status = 0;
+        self.select();
+        self.write(write);
+        self.read(core::slice::from_mut(&mut status));
+        self.deselect();
+        status
+    }
+
+    async fn cmd_read(&mut self, write: u32, read: &mut [u32]) -> u32 {
+        let mut status = 0;
+        self.select();
+        self.write(&[write]);
+        self.read(read);
+        self.read(core::slice::from_mut(&mut status));
+        self.deselect();
+        status
+    }
+
+    async fn wait_for_event(&mut self) {
+        while sio().gpio_in.read().bits() & DIO == 0 {
+            yield_now().await;
+        }
+    }
+}
+
+fn sio() -> &'static hal::pac::sio::RegisterBlock {
+    // Note (safety): the set, clear and input registers only touch the bus
+    // pins' bits, which belong to the bus
+    unsafe { &*hal::pac::SIO::ptr() }
+}
@@ -0,0 +1,208 @@
+//! # Pico ADC DMA Example
+//!
+//! Samples three ADC pins and the temperature sensor without gaps, and prints
+//! each input's minimum, maximum and mean over USB Serial once a second.
+//!
+//! The ADC runs free, stepping round-robin through GPIO 26, 27 and 28 and the
+//! temperature sensor, 25 000 times a second each. Two DMA channels take
+//! turns to move its results into the halves of a double buffer, with
+//! `rp_hal_boards_common::adc_stream`, so the main loop only ever looks at a
+//! full block while the other fills, and no time is spent waiting on
+//! conversions as with `adc.read()`.
+//!
+//! Leave the pins floating to see noise, or wire potentiometers to them.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// The macro for marking our interrupt functions
+use rp_pico::hal::pac::interrupt;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// Used to demonstrate writing formatted strings
+use core::fmt::Write;
+
+use rp_hal_boards_common::adc_stream::{AdcStream, Buffers, BLOCK_LEN};
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::dma::{self, Allocator, Irq};
+
+/// Samples per second, shared between the inputs.
+const SAMPLE_RATE: u32 = 100_000;
+
+/// The inputs sampled, as a round-robin mask: 0, 1 and 2 for GPIO 26 to 28,
+/// 4 for the temperature sensor.
+const INPUTS: u8 = 0b1_0111;
+
+/// Names of the inputs, in the order their samples are interleaved.
+const NAMES: [&str; 4] = ["gpio26", "gpio27", "gpio28", "temp"];
+
+/// Blocks between reports: about a second's worth.
+const REPORT_BLOCKS: u32 = SAMPLE_RATE / BLOCK_LEN as u32;
+
+/// Running minimum, maximum and sum of one input's samples.
+#[derive(Clone, Copy)]
+struct Stats {
+    min: u16,
+    max: u16,
+    sum: u32,
+    count: u32,
+}
+
+impl Stats {
+    const EMPTY: Stats = Stats {
+        min: u16::MAX,
+        max: 0,
+        sum: 0,
+        count: 0,
+    };
+
+    fn add(&mut self, sample: u16) {
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.sum += u32::from(sample);
+        self.count += 1;
+    }
+
+    fn mean(&self) -> u32 {
+        self.sum / self.count.max(1)
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let _adc0 = pins.gpio26.into_floating_input();
+    let _adc1 = pins.gpio27.into_floating_input();
+    let _adc2 = pins.gpio28.into_floating_input();
+
+    // Start on input 0 and step through the rest, without stopping
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let first = allocator.alloc("adc-dma-a").unwrap();
+    let second = allocator.alloc("adc-dma-b").unwrap();
+    let mut stream = AdcStream::new(pac.ADC, &mut pac.RESETS, first, 0, SAMPLE_RATE);
+    stream.set_round_robin(INPUTS);
+    let buffers = cortex_m::singleton!(: Buffers = Buffers::new()).unwrap();
+    let mut adc = stream.continuous(second, buffers, Irq::Irq0);
+
+    // Enable the DMA interrupt
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+    }
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("ADC DMA")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut stats = [Stats::EMPTY; 4];
+    let mut blocks = 0;
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        // A block fills in about 10 ms, plenty of time to go through it
+        let read = adc.read(|block| {
+            // BLOCK_LEN is a multiple of the number of inputs, so every
+            // block starts with input 0
+            for samples in block.chunks_exact(stats.len()) {
+                for (input, &sample) in stats.iter_mut().zip(samples) {
+                    input.add(sample);
+                }
+            }
+        });
+        if read.is_none() {
+            continue;
+        }
+
+        blocks += 1;
+        if blocks < REPORT_BLOCKS {
+            continue;
+        }
+        blocks = 0;
+
+        for (name, input) in NAMES.iter().zip(stats.iter_mut()) {
+            let _ = write!(
+                serial,
+                "{:6}: min {:4} max {:4} mean {:4}\r\n",
+                name,
+                input.min,
+                input.max,
+                input.mean()
+            );
+            *input = Stats::EMPTY;
+        }
+        let _ = serial.write_all(b"\r\n");
+    }
+}
+
+#[interrupt]
+fn DMA_IRQ_0() {
+    dma::dispatch(Irq::Irq0);
+}
+
+// End of file
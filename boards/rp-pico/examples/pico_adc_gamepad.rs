@@ -0,0 +1,261 @@
+//! # Pico ADC Gamepad Example
+//!
+//! A USB gamepad with an analogue thumbstick on the ADC and four buttons,
+//! plus a USB Serial port for calibrating the stick.
+//!
+//! Wire the stick's X output to GPIO26 (ADC0) and Y output to GPIO27 (ADC1),
+//! powered from 3V3 and AGND. The buttons go from GPIO2 to GPIO5 to ground.
+//!
+//! Open the serial port and type `cal` to start the calibration wizard: move
+//! the stick to every extreme, type `next`, let go of the stick, and type
+//! `next` again. The result is stored in the last sector of flash and used to
+//! scale the axes from then on, including after a reset. `show` prints the
+//! calibration in use and `reset` goes back to the raw ADC range.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO and ADC traits
+use embedded_hal::adc::OneShot;
+use embedded_hal::digital::v2::InputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Communications Class Device support
+use usbd_serial::SerialPort;
+
+// USB Human Interface Device (HID) Class support
+use usbd_hid::hid_class::HIDClass;
+
+use hal::gpio::DynPin;
+use rp_hal_boards_common::joystick::{Calibration, Step, Wizard, DEFAULT_DEAD_ZONE};
+use rp_hal_boards_common::settings::{default_offset, Store};
+
+/// A gamepad with eight buttons and two signed 8-bit axes.
+const GAMEPAD_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x05, // Usage (Game Pad)
+    0xA1, 0x01, // Collection (Application)
+    0x05, 0x09, //   Usage Page (Button)
+    0x19, 0x01, //   Usage Minimum (1)
+    0x29, 0x08, //   Usage Maximum (8)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x01, //   Logical Maximum (1)
+    0x75, 0x01, //   Report Size (1)
+    0x95, 0x08, //   Report Count (8)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x05, 0x01, //   Usage Page (Generic Desktop)
+    0x09, 0x30, //   Usage (X)
+    0x09, 0x31, //   Usage (Y)
+    0x15, 0x81, //   Logical Minimum (-127)
+    0x25, 0x7F, //   Logical Maximum (127)
+    0x75, 0x08, //   Report Size (8)
+    0x95, 0x02, //   Report Count (2)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0xC0, // End Collection
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let buttons: [DynPin; 4] = [
+        pins.gpio2.into_pull_up_input().into(),
+        pins.gpio3.into_pull_up_input().into(),
+        pins.gpio4.into_pull_up_input().into(),
+        pins.gpio5.into_pull_up_input().into(),
+    ];
+
+    // Enable the ADC peripheral, and configure the stick's pins as inputs
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut x_pin = pins.gpio26.into_floating_input();
+    let mut y_pin = pins.gpio27.into_floating_input();
+
+    // Use the stored calibration, if there is a valid one
+    let mut store = Store::new(default_offset());
+    let mut buf = [0u8; 16];
+    let mut calibration = store
+        .load(&mut buf)
+        .and_then(|len| Calibration::<2>::read_from(&buf[..len]))
+        .unwrap_or_default();
+    let mut wizard: Option<Wizard<2>> = None;
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial = SerialPort::new(&usb_bus);
+
+    // Set up the USB HID Class Device driver, providing gamepad reports
+    let mut usb_hid = HIDClass::new(&usb_bus, GAMEPAD_REPORT_DESCRIPTOR, 10);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("ADC Gamepad")
+        .serial_number("TEST")
+        .composite_with_iads()
+        .build();
+
+    let mut line: heapless::Vec<u8, 16> = heapless::Vec::new();
+    let mut next_sample = timer.get_counter().ticks();
+    let mut last_report = [0u8; 3];
+    loop {
+        if usb_dev.poll(&mut [&mut serial, &mut usb_hid]) {
+            let mut rx = [0u8; 64];
+            if let Ok(count) = serial.read(&mut rx) {
+                for &byte in &rx[..count] {
+                    if byte != b'\r' && byte != b'\n' {
+                        let _ = line.push(byte);
+                        continue;
+                    }
+                    if line.is_empty() {
+                        continue;
+                    }
+
+                    let mut text: heapless::String<128> = heapless::String::new();
+                    match &line[..] {
+                        b"cal" => {
+                            wizard = Some(Wizard::new());
+                            let _ = write!(
+                                text,
+                                "Move the stick to every extreme, then type `next`\r\n"
+                            );
+                        }
+                        b"next" => match wizard.as_mut().map(|w| w.advance()) {
+                            Some(Step::Center) => {
+                                let _ = write!(text, "Let go of the stick, then type `next`\r\n");
+                            }
+                            Some(_) => match wizard.take().and_then(|w| w.calibration()) {
+                                Some(new) => {
+                                    calibration = new;
+                                    let len = calibration.write_to(&mut buf);
+                                    let _ = store.save(&buf[..len]);
+                                    let _ = write!(text, "Calibration saved\r\n");
+                                }
+                                None => {
+                                    let _ = write!(
+                                        text,
+                                        "The stick didn't move enough, type `cal` to retry\r\n"
+                                    );
+                                }
+                            },
+                            None => {
+                                let _ = write!(text, "Type `cal` first\r\n");
+                            }
+                        },
+                        b"show" => {
+                            for (name, axis) in ["X", "Y"].iter().zip(calibration.axes.iter()) {
+                                let _ = write!(
+                                    text,
+                                    "{}: min {} center {} max {}\r\n",
+                                    name, axis.min, axis.center, axis.max
+                                );
+                            }
+                        }
+                        b"reset" => {
+                            calibration = Calibration::new();
+                            let len = calibration.write_to(&mut buf);
+                            let _ = store.save(&buf[..len]);
+                            let _ = write!(text, "Calibration cleared\r\n");
+                        }
+                        _ => {
+                            let _ = write!(text, "Commands: cal, next, show, reset\r\n");
+                        }
+                    }
+                    let _ = serial.write(text.as_bytes());
+                    line.clear();
+                }
+            }
+        }
+
+        // Sample the stick and buttons once a millisecond
+        if timer.get_counter().ticks() < next_sample {
+            continue;
+        }
+        next_sample += 1_000;
+
+        let x: u16 = adc.read(&mut x_pin).unwrap();
+        let y: u16 = adc.read(&mut y_pin).unwrap();
+        let raw = [x, y];
+
+        if let Some(wizard) = wizard.as_mut() {
+            wizard.sample(&raw);
+        }
+
+        let [x, y] = calibration.scale(&raw, DEFAULT_DEAD_ZONE);
+        let mut pressed = 0u8;
+        for (i, button) in buttons.iter().enumerate() {
+            if button.is_low().unwrap() {
+                pressed |= 1 << i;
+            }
+        }
+
+        let report = [pressed, x as u8, y as u8];
+        if report != last_report && usb_hid.push_raw_input(&report).is_ok() {
+            last_report = report;
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,212 @@
+//! # Pico ADC Smoothing Example
+//!
+//! Reads a potentiometer and a thermistor through the ADC, smooths the
+//! readings with `rp_hal_boards_common::filters`, and prints raw and smoothed
+//! values over USB Serial twice a second.
+//!
+//! * The pot, wiper on GPIO 26 (ADC0) and ends on 3V3 and GND, goes through a
+//!   median of 5 to drop spikes, then a moving average of 8.
+//! * The thermistor, a 10 kΩ NTC with a B of 3950 from GPIO 27 (ADC1) to GND
+//!   and a 10 kΩ resistor from GPIO 27 to 3V3, goes through exponential
+//!   smoothing. The LED comes on above 30 °C, and with hysteresis only goes
+//!   off again below 28 °C.
+//!
+//! Both are sampled 100 times a second.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO and ADC traits
+use embedded_hal::adc::OneShot;
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::filters::{Exponential, Filter, Hysteresis, Median, MovingAverage};
+
+/// Time between samples, in microseconds.
+const SAMPLE_INTERVAL_US: u64 = 10_000;
+
+/// Time between reports, in microseconds.
+const REPORT_INTERVAL_US: u64 = 500_000;
+
+/// The thermistor's ADC reading from -20 °C to 100 °C in steps of 5 °C.
+const THERMISTOR: [i32; 25] = [
+    3741, 3630, 3496, 3338, 3157, 2956, 2739, 2511, 2278, 2048, 1825, 1614, 1419, 1241, 1082, 940,
+    816, 707, 613, 532, 462, 401, 350, 305, 267,
+];
+
+/// The LED comes on at this temperature, in tenths of a degree...
+const LED_ON: i32 = 300;
+
+/// ...and goes off again at this one.
+const LED_OFF: i32 = 280;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led = pins.led.into_push_pull_output();
+
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut pot_pin = pins.gpio26.into_floating_input();
+    let mut thermistor_pin = pins.gpio27.into_floating_input();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("ADC smoothing")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    let mut pot_median = Median::<5>::new();
+    let mut pot_average = MovingAverage::<8>::new();
+    let mut thermistor_smooth = Exponential::new(4);
+    let mut too_hot = Hysteresis::new(LED_OFF, LED_ON);
+
+    let mut pot_raw = 0;
+    let mut thermistor_raw = 0;
+    let mut next_sample = 0;
+    let mut next_report = REPORT_INTERVAL_US;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let now = timer.get_counter().ticks();
+        if now >= next_sample {
+            next_sample += SAMPLE_INTERVAL_US;
+
+            let raw: u16 = adc.read(&mut pot_pin).unwrap();
+            pot_raw = i32::from(raw);
+            pot_average.update(pot_median.update(pot_raw));
+
+            let raw: u16 = adc.read(&mut thermistor_pin).unwrap();
+            thermistor_raw = i32::from(raw);
+            let temperature = tenths_celsius(thermistor_smooth.update(thermistor_raw));
+            if too_hot.update(temperature) {
+                led.set_high().unwrap();
+            } else {
+                led.set_low().unwrap();
+            }
+        }
+
+        if now >= next_report {
+            next_report += REPORT_INTERVAL_US;
+
+            let pot_percent = pot_average.value() * 100 / 4095;
+            let raw_temperature = tenths_celsius(thermistor_raw);
+            let temperature = tenths_celsius(thermistor_smooth.value());
+            let _ = write!(
+                serial,
+                "pot {:4} -> {:3}%   thermistor {:4} ({:5}) -> {:5} C{}\r\n",
+                pot_raw,
+                pot_percent,
+                thermistor_raw,
+                Tenths(raw_temperature),
+                Tenths(temperature),
+                if too_hot.is_on() { ", too hot" } else { "" }
+            );
+        }
+    }
+}
+
+/// The temperature for a thermistor reading, in tenths of a degree, from
+/// straight lines between the points in the table.
+fn tenths_celsius(raw: i32) -> i32 {
+    if raw >= THERMISTOR[0] {
+        return -200;
+    }
+    for (i, pair) in THERMISTOR.windows(2).enumerate() {
+        let (warmer, colder) = (pair[1], pair[0]);
+        if raw > warmer {
+            let base = -200 + 50 * i as i32;
+            return base + 50 * (colder - raw) / (colder - warmer);
+        }
+    }
+    1000
+}
+
+/// Formats tenths of a unit with one decimal place.
+struct Tenths(i32);
+
+impl core::fmt::Display for Tenths {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let text = {
+            let mut buf: heapless::String<12> = heapless::String::new();
+            let _ = write!(buf, "{}{}.{}", sign, self.0.abs() / 10, self.0.abs() % 10);
+            buf
+        };
+        f.pad(&text)
+    }
+}
+
+// End of file
@@ -0,0 +1,114 @@
+//! # Pico Arduino-Style Blink Example
+//!
+//! Blinks the LED on a Pico board, written with the Arduino-style functions
+//! from `rp_hal_boards_common::arduino_compat`, the way a sketch would be.
+//!
+//! A potentiometer on GPIO 26 sets how fast the LED blinks, and holding a
+//! button between GPIO 15 and ground keeps it lit.
+//!
+//! Build with `--features rp-hal-boards-common/arduino-compat`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// The Arduino-style API
+use rp_hal_boards_common::arduino_compat::{
+    self, analog_read, delay_ms, digital_read, digital_write, pin_mode, PinMode, HIGH, LOW,
+};
+
+/// The on-board LED.
+const LED_BUILTIN: u8 = 25;
+
+/// The button, to ground.
+const BUTTON: u8 = 15;
+
+/// The potentiometer's wiper.
+const POT: u8 = 26;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the clocks, hands the rest to `arduino_compat`,
+/// then runs the sketch's `setup` and `loop`.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // From here on pins are just numbers
+    arduino_compat::begin(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        pac.SIO,
+        pac.ADC,
+        pac.PWM,
+        pac.TIMER,
+        &mut pac.RESETS,
+    );
+
+    setup();
+    loop {
+        sketch_loop();
+    }
+}
+
+/// The sketch's `setup()`.
+fn setup() {
+    pin_mode(LED_BUILTIN, PinMode::Output);
+    pin_mode(BUTTON, PinMode::InputPullup);
+}
+
+/// The sketch's `loop()`.
+fn sketch_loop() {
+    if digital_read(BUTTON) == LOW {
+        digital_write(LED_BUILTIN, HIGH);
+        delay_ms(10);
+        return;
+    }
+
+    // 50 ms to about half a second per half-cycle
+    let wait = 50 + u32::from(analog_read(POT)) / 9;
+    digital_write(LED_BUILTIN, HIGH);
+    delay_ms(wait);
+    digital_write(LED_BUILTIN, LOW);
+    delay_ms(wait);
+}
+
+// End of file
@@ -0,0 +1,151 @@
+//! # Pico Audio Pack I²S Example
+//!
+//! Plays a rising and falling siren through the Pimoroni Pico Audio Pack,
+//! using the PIO I²S driver in `rp_hal_boards_common::i2s`.
+//!
+//! The Audio Pack's PCM5100A DAC takes `DATA` on GPIO9, `BCLK` on GPIO10 and
+//! `LRCLK` on GPIO11. Its headphone amplifier stays muted until GPIO22 is
+//! driven high.
+//!
+//! Samples are rendered into one buffer while DMA streams the other to the
+//! state machine.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use rp_pico::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Import pio crates
+use hal::pio::PIOExt;
+
+use rp_hal_boards_common::dma::Allocator;
+use rp_hal_boards_common::i2s::{self, I2s};
+
+/// CD quality.
+const SAMPLE_RATE: u32 = 44_100;
+
+/// Stereo frames per buffer, about 11.6 ms.
+const FRAMES: usize = 512;
+
+/// The two buffers DMA takes turns to play.
+static mut BUFFER_A: [u32; FRAMES] = [0; FRAMES];
+static mut BUFFER_B: [u32; FRAMES] = [0; FRAMES];
+
+/// One period of a sine wave, at about a quarter of full scale to spare
+/// everybody's ears.
+const SINE: [i16; 32] = [
+    0, 1598, 3135, 4551, 5792, 6811, 7568, 8035, 8192, 8035, 7568, 6811, 5792, 4551, 3135, 1598, 0,
+    -1598, -3135, -4551, -5792, -6811, -7568, -8035, -8192, -8035, -7568, -6811, -5792, -4551,
+    -3135, -1598,
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Hand the I²S pins to PIO0
+    let _data = pins.gpio9.into_mode::<hal::gpio::FunctionPio0>();
+    let _bclk = pins.gpio10.into_mode::<hal::gpio::FunctionPio0>();
+    let _lrclk = pins.gpio11.into_mode::<hal::gpio::FunctionPio0>();
+
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let channel = allocator.alloc("i2s").unwrap();
+
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut i2s = I2s::new(
+        &mut pio,
+        sm0,
+        9,
+        10,
+        SAMPLE_RATE,
+        clocks.system_clock.freq().to_Hz(),
+        channel,
+    );
+
+    // Unmute the amplifier once the DAC has a clock
+    let mut mute = pins.gpio22.into_push_pull_output();
+    mute.set_high().unwrap();
+
+    // Safety: these are the only references ever taken to the buffers
+    let mut spare: &'static mut [u32] = unsafe { &mut *core::ptr::addr_of_mut!(BUFFER_A) };
+    i2s.write_samples(unsafe { &mut *core::ptr::addr_of_mut!(BUFFER_B) });
+
+    // Phase and phase step in 1/65536ths of a table entry
+    let mut phase: u32 = 0;
+    let mut step: u32 = 20 << 16;
+    let mut rising = true;
+
+    loop {
+        for frame in spare.iter_mut() {
+            let sample = SINE[(phase >> 16) as usize % SINE.len()];
+            *frame = i2s::frame(sample, sample);
+            phase = phase.wrapping_add(step);
+        }
+
+        // Sweep between about 400 Hz and 1.8 kHz
+        if rising {
+            step += 1 << 12;
+            rising = step < 90 << 16;
+        } else {
+            step -= 1 << 12;
+            rising = step <= 20 << 16;
+        }
+
+        spare = i2s.write_samples(spare).unwrap();
+    }
+}
+
+// End of file
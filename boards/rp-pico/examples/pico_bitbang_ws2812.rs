@@ -0,0 +1,84 @@
+//! # Pico Bit-banged WS2812 Example
+//!
+//! Drives a short strip of WS2812 LEDs on GPIO4 without using a PIO state
+//! machine, with the bit timing derived from the configured system clock by
+//! `rp_hal_boards_common::delay`.
+//!
+//! Nothing in here assumes 125 MHz: change the system clock set-up and the
+//! strip keeps working, as long as `delay::calibrate` is called afterwards.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+use rp_hal_boards_common::{bitbang::Ws2812, delay};
+
+/// Number of LEDs on the strip.
+const STRIP_LEN: usize = 8;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    delay::calibrate(&clocks.system_clock);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut strip = Ws2812::new(pins.gpio4.into_push_pull_output());
+
+    let mut frame: usize = 0;
+    loop {
+        let head = (frame / 4) % STRIP_LEN;
+        strip.write((0..STRIP_LEN).map(|i| if i == head { (0, 32, 0) } else { (0, 0, 0) }));
+        frame = frame.wrapping_add(1);
+
+        delay::delay_us(10_000);
+    }
+}
+
+// End of file
@@ -0,0 +1,131 @@
+//! # Pico Board Revision Example
+//!
+//! Detects which revision of a (made up) carrier board it is running on from
+//! two strap resistors, then sets up the pins that revision uses.
+//!
+//! * Revision 0: no straps fitted. The status LED is the Pico's own, on GPIO25,
+//!   and the button is on GPIO14.
+//! * Revision 1: GPIO20 strapped to ground. The status LED moved to GPIO16, the
+//!   button stays on GPIO14.
+//! * Revision 2 and up: GPIO21 strapped to ground. The status LED is on GPIO16
+//!   and the button moved to GPIO17.
+//!
+//! The LED follows the button, and blinks the revision number at start-up.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use rp_pico::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+use hal::gpio::DynPin;
+use rp_hal_boards_common::board_rev::{self, board_rev};
+
+/// The pins that moved between revisions.
+struct PinMap {
+    led: DynPin,
+    button: DynPin,
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Read the straps once, after giving the pull-ups a moment to settle
+    let straps: [DynPin; 2] = [
+        pins.gpio20.into_pull_up_input().into(),
+        pins.gpio21.into_pull_up_input().into(),
+    ];
+    delay.delay_us(10);
+    board_rev::detect_gpio(&straps).unwrap();
+
+    // From here on, any code can ask which revision it is running on
+    let mut map = match board_rev() {
+        Some(0) => PinMap {
+            led: pins.led.into_push_pull_output().into(),
+            button: pins.gpio14.into_pull_up_input().into(),
+        },
+        Some(1) => PinMap {
+            led: pins.gpio16.into_push_pull_output().into(),
+            button: pins.gpio14.into_pull_up_input().into(),
+        },
+        _ => PinMap {
+            led: pins.gpio16.into_push_pull_output().into(),
+            button: pins.gpio17.into_pull_up_input().into(),
+        },
+    };
+
+    // Blink the revision number, with one extra blink so revision 0 shows
+    for _ in 0..=board_rev().unwrap_or(0) {
+        map.led.set_high().unwrap();
+        delay.delay_ms(200);
+        map.led.set_low().unwrap();
+        delay.delay_ms(300);
+    }
+
+    loop {
+        if map.button.is_low().unwrap() {
+            map.led.set_high().unwrap();
+        } else {
+            map.led.set_low().unwrap();
+        }
+        delay.delay_ms(5);
+    }
+}
+
+// End of file
@@ -0,0 +1,162 @@
+//! # Pico CAN Echo Example
+//!
+//! Joins a 500 kbit/s CAN bus through an MCP2515 module, using
+//! `rp_hal_boards_common::mcp2515`, and echoes frames back.
+//!
+//! The controller's filters pass only frames with standard IDs 0x100 to
+//! 0x10F; each one comes back with 0x100 added to its ID, e.g. 0x105 as
+//! 0x205, with the same data, and the LED toggles. Everything else on the
+//! bus is ignored without the Pico seeing it. From a Linux machine with a
+//! CAN adapter:
+//!
+//! ```text
+//! cansend can0 105#DEADBEEF
+//! candump can0
+//! ```
+//!
+//! Wiring, for a module with an 8 MHz crystal:
+//!
+//! | MCP2515 | Pico   |
+//! |---------|--------|
+//! | CS      | GPIO17 |
+//! | SCK     | GPIO18 |
+//! | SI      | GPIO19 |
+//! | SO      | GPIO16 |
+//! | INT     | GPIO20 |
+//!
+//! The usual modules power the MCP2515 from the same 5 V as their TJA1050
+//! transceiver, which would put 5 V on GPIO16: use one with a 3.3 V
+//! transceiver, such as the SN65HVD230, or give the MCP2515 its own 3V3.
+//! Remember the 120 Ω terminators at each end of the bus.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, ToggleableOutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use embedded_can::nb::Can;
+use embedded_can::{Frame as _, Id, StandardId};
+use fugit::RateExtU32;
+use hal::Clock;
+
+use hal::gpio;
+use hal::spi;
+
+use rp_hal_boards_common::mcp2515::{Error, Frame, Mcp2515, Mode};
+
+/// The first ID echoed; the filters pass this and the fifteen after it.
+const FIRST_ID: u16 = 0x100;
+
+/// Added to the ID of each frame echoed.
+const ECHO_OFFSET: u16 = 0x100;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led = pins.led.into_push_pull_output();
+
+    // The controller pulls INT low while it holds a frame
+    let int = pins.gpio20.into_pull_up_input();
+
+    let _spi_sclk = pins.gpio18.into_mode::<gpio::FunctionSpi>();
+    let _spi_mosi = pins.gpio19.into_mode::<gpio::FunctionSpi>();
+    let _spi_miso = pins.gpio16.into_mode::<gpio::FunctionSpi>();
+    let spi_cs = pins.gpio17.into_push_pull_output();
+    let spi = spi::Spi::<_, _, 8>::new(pac.SPI0).init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        8.MHz(),
+        &embedded_hal::spi::MODE_0,
+    );
+    let mut can = Mcp2515::new(spi, spi_cs, 8.MHz(), 500.kHz(), Mode::Normal).unwrap();
+
+    // Compare the top seven bits of the ID only
+    let mask = StandardId::new(0x7F0).unwrap();
+    let first = StandardId::new(FIRST_ID).unwrap();
+    can.set_filters(mask.into(), &[first.into()]).unwrap();
+
+    loop {
+        if int.is_high().unwrap() {
+            continue;
+        }
+        let frame = match can.receive() {
+            Ok(frame) => frame,
+            // Frames were lost while we were busy; carry on with the rest
+            Err(nb::Error::Other(Error::Overrun)) => continue,
+            Err(nb::Error::WouldBlock) => continue,
+            Err(nb::Error::Other(error)) => panic!("{:?}", error),
+        };
+        if let Some(echo) = echo(&frame) {
+            nb::block!(can.transmit(&echo)).unwrap();
+            led.toggle().unwrap();
+        }
+    }
+}
+
+/// `frame` with [`ECHO_OFFSET`] added to its ID.
+fn echo(frame: &Frame) -> Option<Frame> {
+    let id = match frame.id() {
+        Id::Standard(id) => StandardId::new(id.as_raw() + ECHO_OFFSET)?,
+        // The filters only pass standard frames
+        Id::Extended(_) => return None,
+    };
+    if frame.is_remote_frame() {
+        Frame::new_remote(id, frame.dlc())
+    } else {
+        Frame::new(id, frame.data())
+    }
+}
+
+// End of file
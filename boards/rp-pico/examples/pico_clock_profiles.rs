@@ -0,0 +1,106 @@
+//! # Pico Clock Profiles Example
+//!
+//! Blinks the LED with the chip running from one of the low-power clock
+//! profiles in `rp_hal_boards_common::clocks`, chosen at reset:
+//!
+//! * by default, everything runs at 48 MHz from the USB PLL, with the system
+//!   PLL in reset,
+//! * with a button between GPIO15 and ground held down through reset,
+//!   everything runs from the ring oscillator, with the crystal, both PLLs
+//!   and USB stopped.
+//!
+//! The LED blinks once a second in the first profile. In the second it blinks
+//! at roughly the same rate, but how roughly depends on the chip, as the ring
+//! oscillator's frequency is only nominal.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use hal::clocks::Clock;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // The pins don't need the clocks set up, so the button can pick them
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let button = pins.gpio15.into_pull_up_input();
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // Give the pull-up time to charge the pin before reading it
+    cortex_m::asm::delay(1_000);
+
+    // Configure the clocks
+    let clocks = if button.is_low().unwrap() {
+        rp_pico::init_clocks_from_rosc(
+            pac.ROSC,
+            pac.XOSC,
+            pac.CLOCKS,
+            pac.PLL_SYS,
+            pac.PLL_USB,
+            &mut pac.RESETS,
+            &mut watchdog,
+        )
+    } else {
+        rp_pico::init_clocks_from_usb_pll(
+            rp_pico::XOSC_CRYSTAL_FREQ,
+            pac.XOSC,
+            pac.CLOCKS,
+            pac.PLL_SYS,
+            pac.PLL_USB,
+            &mut pac.RESETS,
+            &mut watchdog,
+        )
+    }
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    loop {
+        led_pin.set_high().unwrap();
+        delay.delay_ms(500);
+        led_pin.set_low().unwrap();
+        delay.delay_ms(500);
+    }
+}
+
+// End of file
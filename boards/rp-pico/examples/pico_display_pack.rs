@@ -0,0 +1,178 @@
+//! # Pico Display Pack Example
+//!
+//! Shows which of the Pimoroni Pico Display Pack's buttons are pressed, on its
+//! LCD and with its RGB LED.
+//!
+//! Build with `--features pico-display-pack`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use hal::Clock;
+
+// Graphics support
+use embedded_graphics::{
+    mono_font::{ascii::FONT_10X20, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use st7789::{Orientation, ST7789};
+
+use rp_pico::display_pack::{DisplayPack, DisplayPackPins, LCD_HEIGHT, LCD_OFFSET, LCD_WIDTH};
+
+/// The Display Pack doesn't bring out the LCD's reset line.
+struct NoResetPin;
+
+impl OutputPin for NoResetPin {
+    type Error = ();
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let DisplayPack {
+        a,
+        b,
+        x,
+        y,
+        mut led,
+        lcd,
+    } = DisplayPack::new(DisplayPackPins {
+        gpio6: pins.gpio6,
+        gpio7: pins.gpio7,
+        gpio8: pins.gpio8,
+        gpio12: pins.gpio12,
+        gpio13: pins.gpio13,
+        gpio14: pins.gpio14,
+        gpio15: pins.gpio15,
+        gpio16: pins.gpio16,
+        gpio17: pins.gpio17,
+        gpio18: pins.gpio18,
+        gpio19: pins.gpio19,
+        gpio20: pins.gpio20,
+    });
+
+    let (interface, _backlight) = DisplayPack::spi_interface(
+        lcd,
+        pac.SPI0,
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+    );
+    let mut lcd = ST7789::new(interface, NoResetPin, 240, 320);
+    lcd.init(&mut delay).unwrap();
+    lcd.set_orientation(Orientation::Landscape).unwrap();
+
+    // Draw in panel coordinates rather than controller coordinates
+    let mut screen = lcd.translated(Point::new(LCD_OFFSET.0, LCD_OFFSET.1));
+    Rectangle::new(Point::zero(), Size::new(LCD_WIDTH, LCD_HEIGHT))
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+        .draw(&mut screen)
+        .unwrap();
+
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_10X20)
+        .text_color(Rgb565::WHITE)
+        .background_color(Rgb565::BLACK)
+        .build();
+
+    loop {
+        let pressed = [
+            a.is_low().unwrap(),
+            b.is_low().unwrap(),
+            x.is_low().unwrap(),
+            y.is_low().unwrap(),
+        ];
+
+        // A lights red, B green, X blue and Y white
+        led.set(
+            pressed[0] || pressed[3],
+            pressed[1] || pressed[3],
+            pressed[2] || pressed[3],
+        );
+
+        for (i, (name, &down)) in ["A", "B", "X", "Y"].iter().zip(pressed.iter()).enumerate() {
+            let colour = if down {
+                Rgb565::GREEN
+            } else {
+                Rgb565::CSS_DIM_GRAY
+            };
+            let position = Point::new(20 + 55 * i as i32, 55);
+            Rectangle::new(position, Size::new(40, 40))
+                .into_styled(PrimitiveStyle::with_fill(colour))
+                .draw(&mut screen)
+                .unwrap();
+            Text::new(name, position + Point::new(15, 26), style)
+                .draw(&mut screen)
+                .unwrap();
+        }
+
+        delay.delay_ms(20);
+    }
+}
+
+// End of file
@@ -0,0 +1,173 @@
+//! # Pico Display Pack Framebuffer Example
+//!
+//! Bounces a ball around the Pimoroni Pico Display Pack's LCD, drawing each
+//! frame with `embedded-graphics` into one framebuffer while DMA sends the
+//! previous one to the LCD, and shows the frame rate in the corner.
+//!
+//! Build with `--features pico-display-pack`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use hal::Clock;
+
+// Graphics support
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle, Rectangle},
+    text::Text,
+};
+
+use rp_hal_boards_common::dma::Allocator;
+use rp_pico::display_pack::{DisplayPack, DisplayPackPins, LcdBuffers, LCD_HEIGHT, LCD_WIDTH};
+
+/// Diameter of the ball, in pixels.
+const BALL: u32 = 20;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The timer measures the frame rate
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let pack = DisplayPack::new(DisplayPackPins {
+        gpio6: pins.gpio6,
+        gpio7: pins.gpio7,
+        gpio8: pins.gpio8,
+        gpio12: pins.gpio12,
+        gpio13: pins.gpio13,
+        gpio14: pins.gpio14,
+        gpio15: pins.gpio15,
+        gpio16: pins.gpio16,
+        gpio17: pins.gpio17,
+        gpio18: pins.gpio18,
+        gpio19: pins.gpio19,
+        gpio20: pins.gpio20,
+    });
+
+    // One DMA channel flushes the frames
+    let mut dma = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let channel = dma.alloc("display-pack").unwrap();
+
+    // Two frames of 240x135 take 63 KiB, so they live in a static
+    let buffers = cortex_m::singleton!(: LcdBuffers = LcdBuffers::new()).unwrap();
+    let (mut lcd, _backlight) = DisplayPack::framebuffer(
+        pack.lcd,
+        pac.SPI0,
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        channel,
+        buffers,
+        &mut delay,
+    );
+
+    let text_style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let ball_style = PrimitiveStyle::with_fill(Rgb565::CSS_ORANGE);
+    let limit = Point::new((LCD_WIDTH - BALL) as i32, (LCD_HEIGHT - BALL) as i32);
+
+    let mut position = Point::new(10, 10);
+    let mut velocity = Point::new(3, 2);
+    let mut frames = 0u32;
+    let mut fps = 0u32;
+    let mut second_start = timer.get_counter().ticks();
+    let mut label = heapless::String::<16>::new();
+
+    loop {
+        position += velocity;
+        if position.x <= 0 || position.x >= limit.x {
+            velocity.x = -velocity.x;
+        }
+        if position.y <= 0 || position.y >= limit.y {
+            velocity.y = -velocity.y;
+        }
+
+        // The back frame still holds the picture from two frames ago, so
+        // redraw all of it
+        let frame = lcd.back();
+        frame.clear(Rgb565::CSS_NAVY).unwrap();
+        Rectangle::new(Point::zero(), Size::new(LCD_WIDTH, LCD_HEIGHT))
+            .into_styled(PrimitiveStyle::with_stroke(Rgb565::CSS_DIM_GRAY, 2))
+            .draw(frame)
+            .unwrap();
+        Circle::new(position, BALL)
+            .into_styled(ball_style)
+            .draw(frame)
+            .unwrap();
+
+        label.clear();
+        let _ = write!(label, "{} fps", fps);
+        Text::new(&label, Point::new(6, 14), text_style)
+            .draw(frame)
+            .unwrap();
+
+        // Start sending this frame, and carry on with the next one
+        lcd.swap();
+
+        frames += 1;
+        let now = timer.get_counter().ticks();
+        if now - second_start >= 1_000_000 {
+            fps = frames;
+            frames = 0;
+            second_start = now;
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,150 @@
+//! # Pico DMA Allocator Example
+//!
+//! Allocates two DMA channels at runtime with `rp_hal_boards_common::dma`, and
+//! has both of them report completion through the shared `DMA_IRQ_0`
+//! interrupt. One channel uses a callback, the other is polled with
+//! `take_done()`.
+//!
+//! Progress is printed with defmt, so run this with a debug probe attached.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::Cell;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// The macro for marking our interrupt functions
+use rp_pico::hal::pac::interrupt;
+
+// info!() macro for printing information to the debug output
+use defmt::*;
+use defmt_rtt as _;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Pull in any important traits
+use rp_pico::hal::prelude::*;
+
+use critical_section::Mutex;
+use rp_hal_boards_common::dma::{self, Allocator, Channel, Irq};
+
+/// How many times the first channel's callback ran.
+static COMPLETIONS: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let mut with_callback = allocator.alloc("example-callback").unwrap();
+    let mut polled = allocator.alloc("example-polled").unwrap();
+    info!(
+        "allocated channels {} and {}",
+        with_callback.id(),
+        polled.id()
+    );
+
+    with_callback.listen(Irq::Irq0, Some(on_complete));
+    polled.listen(Irq::Irq0, None);
+
+    // Enable the DMA interrupt
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+    }
+
+    let src = [0x1234_5678u32; 64];
+    let mut dst_a = [0u32; 64];
+    let mut dst_b = [0u32; 64];
+
+    loop {
+        start_copy(&mut with_callback, &src, &mut dst_a);
+        start_copy(&mut polled, &src, &mut dst_b);
+
+        while !polled.take_done() {
+            cortex_m::asm::wfi();
+        }
+
+        let count = critical_section::with(|cs| COMPLETIONS.borrow(cs).get());
+        info!(
+            "polled channel done, callback channel has completed {} times",
+            count
+        );
+        delay.delay_ms(1000);
+    }
+}
+
+/// Start a word-by-word memory copy on `channel`.
+fn start_copy(channel: &mut Channel, src: &[u32], dst: &mut [u32]) {
+    let len = src.len().min(dst.len()) as u32;
+    let regs = channel.regs();
+    regs.ch_read_addr
+        .write(|w| unsafe { w.bits(src.as_ptr() as u32) });
+    regs.ch_write_addr
+        .write(|w| unsafe { w.bits(dst.as_mut_ptr() as u32) });
+    regs.ch_trans_count.write(|w| unsafe { w.bits(len) });
+    regs.ch_ctrl_trig.write(|w| unsafe {
+        w.data_size()
+            .size_word()
+            .incr_read()
+            .set_bit()
+            .incr_write()
+            .set_bit()
+            // Unpaced transfer, as fast as the bus allows
+            .treq_sel()
+            .bits(0x3f)
+            // Chaining to ourselves disables chaining
+            .chain_to()
+            .bits(channel.id())
+            .en()
+            .set_bit()
+    });
+}
+
+/// Completion callback for the first channel, run from `DMA_IRQ_0`.
+fn on_complete(_channel: u8) {
+    critical_section::with(|cs| {
+        let count = COMPLETIONS.borrow(cs);
+        count.set(count.get() + 1);
+    });
+}
+
+#[interrupt]
+fn DMA_IRQ_0() {
+    dma::dispatch(Irq::Irq0);
+}
+
+// End of file
@@ -0,0 +1,210 @@
+//! # Pico DMA Memcpy Example
+//!
+//! Copies a 16 KiB buffer with the CPU, with `copy_from_slice`, and with a DMA
+//! channel, using `rp_hal_boards_common::dma::copy_blocking`, and prints each
+//! one's throughput over a USB Serial port each time a terminal opens it.
+//!
+//! A last run starts the copy with `dma::copy` and counts how many times the
+//! CPU goes round a loop before it is done, which is the real gain: the bus
+//! bandwidth is about the same, but the CPU is free.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use core::hint::black_box;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::dma::{self, Allocator};
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 512;
+
+/// Words in each buffer.
+const WORDS: usize = 4096;
+
+/// Copies per timed run.
+const COPIES: u32 = 100;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The timer measures each run
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let mut channel = Some(allocator.alloc("memcpy").unwrap());
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("DMA memcpy")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    // The background copy keeps using its buffers after `dma::copy` returns,
+    // so they have to be static
+    let src = cortex_m::singleton!(: [u32; WORDS] = [0; WORDS]).unwrap();
+    for (i, word) in src.iter_mut().enumerate() {
+        *word = (i as u32).wrapping_mul(0x9E37_79B9);
+    }
+    let mut src: Option<&'static [u32]> = Some(src);
+    let mut dst: Option<&'static mut [u32]> =
+        Some(cortex_m::singleton!(: [u32; WORDS] = [0; WORDS]).unwrap());
+
+    let mut connected = false;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let dtr = serial.port().dtr();
+        if !dtr || connected {
+            connected = dtr;
+            continue;
+        }
+        connected = true;
+
+        let (mut ch, from, to) = (
+            channel.take().unwrap(),
+            src.take().unwrap(),
+            dst.take().unwrap(),
+        );
+
+        let time_us = |run: &mut dyn FnMut()| {
+            let start = timer.get_counter().ticks();
+            run();
+            (timer.get_counter().ticks() - start) as u32
+        };
+
+        let cpu = time_us(&mut || {
+            for _ in 0..COPIES {
+                black_box(&mut *to).copy_from_slice(black_box(from));
+            }
+        });
+        let dma_words = time_us(&mut || {
+            for _ in 0..COPIES {
+                dma::copy_blocking(&mut ch, from, &mut *to);
+            }
+        });
+        let dma_bytes = time_us(&mut || {
+            for _ in 0..COPIES {
+                dma::copy_blocking(&mut ch, as_bytes(from), as_bytes_mut(to));
+            }
+        });
+
+        // One more, in the background, counting loops until it is done
+        let start = timer.get_counter().ticks();
+        let transfer = dma::copy(ch, from, to);
+        let mut spins = 0u32;
+        while !transfer.is_done() {
+            spins = black_box(spins + 1);
+        }
+        let background = (timer.get_counter().ticks() - start) as u32;
+        let (ch, from, to) = transfer.wait();
+
+        let bytes = (WORDS * 4) as u32 * COPIES;
+        let _ = write!(
+            serial,
+            "{} KiB x{}, in microseconds and MB/s:\r\n",
+            WORDS * 4 / 1024,
+            COPIES
+        );
+        for (name, us) in [
+            ("copy_from_slice", cpu),
+            ("dma::copy_blocking, words", dma_words),
+            ("dma::copy_blocking, bytes", dma_bytes),
+        ] {
+            let _ = write!(serial, "  {:<28}{:>8}{:>6}\r\n", name, us, bytes / us);
+        }
+        let _ = write!(
+            serial,
+            "dma::copy of {} KiB took {} us, while the CPU looped {} times\r\n",
+            WORDS * 4 / 1024,
+            background,
+            spins
+        );
+        let _ = write!(
+            serial,
+            "copy {}\r\n",
+            if from == &to[..] {
+                "matches"
+            } else {
+                "differs"
+            }
+        );
+
+        channel = Some(ch);
+        src = Some(from);
+        dst = Some(to);
+    }
+}
+
+/// The bytes of a word slice.
+fn as_bytes(words: &[u32]) -> &[u8] {
+    // Note (safety): any u32 is four valid bytes
+    unsafe { core::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 4) }
+}
+
+/// The bytes of a word slice, for writing.
+fn as_bytes_mut(words: &mut [u32]) -> &mut [u8] {
+    // Note (safety): any four bytes are a valid u32
+    unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, words.len() * 4) }
+}
+
+// End of file
@@ -0,0 +1,124 @@
+//! # Pico Dormant Button Example
+//!
+//! Blinks the LED three times, then goes dormant, with every oscillator
+//! stopped, until a button between GPIO15 and ground is pressed, and starts
+//! over. Hold a button between GPIO14 and ground while pressing GPIO15 to use
+//! sleep mode instead, for comparison.
+//!
+//! `rp_hal_boards_common::power` stops the PLLs and the ring oscillator,
+//! stops the crystal, and on the falling edge restarts them all and puts the
+//! clock tree back, so the blinking carries on at the same rate as before.
+//!
+//! ## Power
+//!
+//! These figures are estimates, not measurements, from the Pico and RP2040
+//! datasheets at 25 °C. Measure your own board with a meter in series with
+//! VSYS; USB must be unplugged, as its 5 V would otherwise feed the board.
+//!
+//! | State                                  | Current          |
+//! | -------------------------------------- | ---------------- |
+//! | Running at 125 MHz, blinking           | ~20 mA           |
+//! | Sleep, GPIO clocked, PLLs running      | ~1.5 mA          |
+//! | Dormant, RP2040 alone                  | a few hundred µA |
+//! | Dormant, whole Pico                    | ~0.8 mA          |
+//!
+//! Dormant takes the RP2040 down to microamps; on a Pico, the flash chip in
+//! standby and the regulator's quiescent current make up most of what is
+//! left. Leave GPIO23 low so the regulator stays in its low-current PFM mode.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use hal::clocks::Clock;
+
+use hal::gpio::Interrupt;
+use rp_hal_boards_common::power;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led_pin = pins.led.into_push_pull_output();
+    let mut wake_button = pins.gpio15.into_pull_up_input();
+    let sleep_button = pins.gpio14.into_pull_up_input();
+
+    // Keep the regulator in its power-saving mode
+    let mut smps_mode = pins.b_power_save.into_push_pull_output();
+    smps_mode.set_low().unwrap();
+
+    loop {
+        for _ in 0..3 {
+            led_pin.set_high().unwrap();
+            delay.delay_ms(200);
+            led_pin.set_low().unwrap();
+            delay.delay_ms(200);
+        }
+
+        if sleep_button.is_low().unwrap() {
+            power::sleep_until_edge(&mut wake_button, Interrupt::EdgeLow, &mut core.SCB);
+        } else {
+            power::dormant_until_edge(&mut wake_button, Interrupt::EdgeLow);
+        }
+
+        // Let the button stop bouncing before the next round
+        delay.delay_ms(50);
+    }
+}
+
+// End of file
@@ -0,0 +1,204 @@
+//! # Pico DTMF Decoder Example
+//!
+//! Decodes phone keypad (DTMF) tones from audio on ADC0 (GPIO 26) and prints
+//! each digit over USB Serial.
+//!
+//! Every DTMF key sends two tones at once, one for its row and one for its
+//! column. Blocks of 205 samples are captured at 8 kHz with
+//! `rp_hal_boards_common::adc_stream`, and eight Goertzel filters from
+//! `rp_hal_boards_common::dsp` measure how much of each tone there is. A digit
+//! counts when one row and one column tone stand out in two blocks in a row,
+//! and is printed once per key press.
+//!
+//! Feed in audio through a 1 µF capacitor, with two 10 kΩ resistors biasing
+//! GPIO 26 to half of 3.3 V. A phone playing keypad tones next to an electret
+//! microphone module works too.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::adc_stream::AdcStream;
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::dma::Allocator;
+use rp_hal_boards_common::dsp::{self, Goertzel};
+
+/// Sample rate of the ADC.
+const SAMPLE_RATE: u32 = 8_000;
+
+/// Samples per block: the classic choice for DTMF at 8 kHz, putting every tone
+/// close to the centre of a bin.
+const BLOCK: usize = 205;
+
+/// The row tones, low group, in Hz.
+const ROWS: [u32; 4] = [697, 770, 852, 941];
+
+/// The column tones, high group, in Hz.
+const COLUMNS: [u32; 4] = [1209, 1336, 1477, 1633];
+
+/// The keypad, by row and column.
+const KEYS: [[u8; 4]; 4] = [
+    *b"123A", //
+    *b"456B", //
+    *b"789C", //
+    *b"*0#D", //
+];
+
+/// Quietest block worth decoding, as a mean square of the samples.
+const MIN_ENERGY: u64 = 10_000;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Sample GPIO 26 at a steady 8 kHz
+    let _audio_in = pins.gpio26.into_floating_input();
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let channel = allocator.alloc("dtmf-adc").unwrap();
+    let mut adc = AdcStream::new(pac.ADC, &mut pac.RESETS, channel, 0, SAMPLE_RATE);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 64> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("DTMF decoder")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut rows = ROWS.map(|frequency| Goertzel::new(frequency, SAMPLE_RATE));
+    let mut columns = COLUMNS.map(|frequency| Goertzel::new(frequency, SAMPLE_RATE));
+
+    let mut raw = [0u16; BLOCK];
+    let mut samples = [0i16; BLOCK];
+    let mut last = None;
+    let mut printed = None;
+
+    loop {
+        // A block takes 26 ms; poll often enough to keep the host happy
+        usb_dev.poll(&mut [&mut serial]);
+        adc.capture(&mut raw);
+        usb_dev.poll(&mut [&mut serial]);
+
+        for (sample, &raw) in samples.iter_mut().zip(raw.iter()) {
+            *sample = ((i32::from(raw) - 2048) << 4) as i16;
+        }
+        dsp::remove_dc(&mut samples);
+
+        for filter in rows.iter_mut().chain(columns.iter_mut()) {
+            filter.reset();
+            for &sample in samples.iter() {
+                filter.push(sample);
+            }
+        }
+
+        let digit = decode(&samples, &rows, &columns);
+
+        // Print a digit once, after seeing it in two blocks in a row
+        if let Some(key) = digit {
+            if digit == last && digit != printed {
+                let _ = serial.write_all(&[key]);
+            }
+        }
+        if digit == last {
+            printed = digit;
+        }
+        last = digit;
+    }
+}
+
+/// The key whose two tones clearly stand out in the block, if any.
+fn decode(samples: &[i16], rows: &[Goertzel; 4], columns: &[Goertzel; 4]) -> Option<u8> {
+    let energy = samples
+        .iter()
+        .map(|&s| (i64::from(s) * i64::from(s)) as u64)
+        .sum::<u64>();
+    if energy / (samples.len() as u64) < MIN_ENERGY {
+        return None;
+    }
+
+    let strongest = |filters: &[Goertzel; 4]| {
+        let mut powers = filters.iter().map(Goertzel::power).enumerate();
+        let first = powers.next().unwrap();
+        powers.fold(
+            first,
+            |best, next| if next.1 > best.1 { next } else { best },
+        )
+    };
+    let (row, row_power) = strongest(rows);
+    let (column, column_power) = strongest(columns);
+
+    // A pure tone gives a power of N × energy / 2, and each of a pair half
+    // that. Demand at least half of what a clean pair would give, so speech
+    // and noise, spread over many frequencies, don't count.
+    let threshold = samples.len() as u64 * energy / 8;
+    if row_power < threshold || column_power < threshold {
+        return None;
+    }
+
+    Some(KEYS[row][column])
+}
+
+// End of file
@@ -0,0 +1,247 @@
+//! # Pico DVI Test Card Example
+//!
+//! Shows a test card at 640x480 on a DVI or HDMI monitor, from a Pico on a
+//! Pico DV Demo Base, using `rp_hal_boards_common::dvi`. The Pico runs at
+//! 252 MHz, with the lanes on GPIO 12 to 19 and a DMA interrupt encoding each
+//! line as it comes up.
+//!
+//! The card has colour bars, a grid, a border and a seconds counter, all drawn
+//! with `embedded-graphics` into a 320x240, 16 colour frame.
+//!
+//! Build with `--features rp-hal-boards-common/dvi`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Our interrupt macro
+use hal::pac::interrupt;
+
+// Some traits we need
+use core::cell::RefCell;
+use core::fmt::Write;
+use critical_section::Mutex;
+use hal::pio::PIOExt;
+
+// Graphics support
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle},
+    text::Text,
+};
+
+use rp_hal_boards_common::dma::{Allocator, Irq};
+use rp_hal_boards_common::dvi::{
+    Buffers, Dvi, Frame, PaletteIndex, HEIGHT, PICO_DV_DEMO_BASE, WIDTH,
+};
+use rp_pico::Overclock;
+
+/// The clock DVI needs, ten times the 25.2 MHz pixel clock.
+const SYS_CLOCK: Overclock = Overclock::mhz(252);
+
+/// The palette indexes of the colour bars, in the usual order.
+const BARS: [u8; 8] = [15, 14, 11, 10, 13, 12, 9, 0];
+
+/// The DVI output, shared with the DMA interrupt.
+static DVI: Mutex<RefCell<Option<Dvi<pac::PIO0, hal::pwm::Pwm7>>>> = Mutex::new(RefCell::new(None));
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks, and the core voltage and flash to suit
+    let _clocks = rp_pico::overclock(
+        SYS_CLOCK,
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.VREG_AND_CHIP_RESET,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The timer paces the seconds counter
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // The three data lanes come from PIO0
+    let _d0 = pins.gpio12.into_mode::<hal::gpio::FunctionPio0>();
+    let _d0n = pins.gpio13.into_mode::<hal::gpio::FunctionPio0>();
+    let _d1 = pins.gpio18.into_mode::<hal::gpio::FunctionPio0>();
+    let _d1n = pins.gpio19.into_mode::<hal::gpio::FunctionPio0>();
+    let _d2 = pins.gpio16.into_mode::<hal::gpio::FunctionPio0>();
+    let _d2n = pins.gpio17.into_mode::<hal::gpio::FunctionPio0>();
+
+    // And the clock lane from PWM slice 7
+    let pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    let mut clock = pwm_slices.pwm7;
+    clock.channel_a.output_to(pins.gpio14);
+    clock.channel_b.output_to(pins.gpio15);
+
+    let (mut pio, sm0, sm1, sm2, _) = pac.PIO0.split(&mut pac.RESETS);
+
+    let mut dma = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let channels = [(); 6].map(|_| dma.alloc("dvi").unwrap());
+
+    // The DMA reads these for as long as the picture is up
+    let frame: &'static Frame = cortex_m::singleton!(: Frame = Frame::new()).unwrap();
+    let buffers = cortex_m::singleton!(: Buffers = Buffers::new()).unwrap();
+
+    draw_card(frame);
+
+    let dvi = Dvi::new(
+        &mut pio,
+        sm0,
+        sm1,
+        sm2,
+        clock,
+        channels,
+        PICO_DV_DEMO_BASE,
+        buffers,
+        frame,
+        Irq::Irq1,
+    );
+    critical_section::with(|cs| DVI.borrow(cs).replace(Some(dvi)));
+
+    // Note (safety): the handler only touches DVI, which is set up
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_1);
+    }
+
+    let text_style = MonoTextStyle::new(&FONT_6X10, PaletteIndex(15));
+    let counter = Rectangle::new(Point::new(8, 8), Size::new(96, 12));
+    let mut label = heapless::String::<16>::new();
+    let mut seconds = 0u32;
+    let mut next = timer.get_counter().ticks();
+
+    loop {
+        if timer.get_counter().ticks() < next {
+            continue;
+        }
+        next += 1_000_000;
+
+        // Drawing straight into the frame shows up on the next scanline
+        let mut canvas = frame;
+        counter
+            .into_styled(PrimitiveStyle::with_fill(PaletteIndex(0)))
+            .draw(&mut canvas)
+            .unwrap();
+        label.clear();
+        let _ = write!(label, "{} s", seconds);
+        Text::new(&label, Point::new(10, 17), text_style)
+            .draw(&mut canvas)
+            .unwrap();
+        seconds += 1;
+    }
+}
+
+/// Draws the parts of the test card that don't change.
+fn draw_card(frame: &Frame) {
+    let mut canvas = frame;
+    let (width, height) = (WIDTH as i32, HEIGHT as i32);
+
+    // A grey grid over the whole card
+    frame.clear(0);
+    let grid = PrimitiveStyle::with_stroke(PaletteIndex(8), 1);
+    for x in (0..width).step_by(20) {
+        Line::new(Point::new(x, 0), Point::new(x, height - 1))
+            .into_styled(grid)
+            .draw(&mut canvas)
+            .unwrap();
+    }
+    for y in (0..height).step_by(20) {
+        Line::new(Point::new(0, y), Point::new(width - 1, y))
+            .into_styled(grid)
+            .draw(&mut canvas)
+            .unwrap();
+    }
+
+    // Colour bars across the middle
+    let bar_width = width / BARS.len() as i32;
+    for (i, &index) in BARS.iter().enumerate() {
+        Rectangle::new(
+            Point::new(i as i32 * bar_width, 60),
+            Size::new(bar_width as u32, 100),
+        )
+        .into_styled(PrimitiveStyle::with_fill(PaletteIndex(index)))
+        .draw(&mut canvas)
+        .unwrap();
+    }
+
+    // Every palette entry along the bottom
+    let swatch = width / 16;
+    for index in 0..16 {
+        Rectangle::new(
+            Point::new(index * swatch, 180),
+            Size::new(swatch as u32, 20),
+        )
+        .into_styled(PrimitiveStyle::with_fill(PaletteIndex(index as u8)))
+        .draw(&mut canvas)
+        .unwrap();
+    }
+
+    // A white border, to check nothing is cropped
+    Rectangle::new(Point::zero(), Size::new(WIDTH as u32, HEIGHT as u32))
+        .into_styled(PrimitiveStyle::with_stroke(PaletteIndex(15), 1))
+        .draw(&mut canvas)
+        .unwrap();
+
+    Text::new(
+        "rp-hal-boards DVI 640x480",
+        Point::new(10, 220),
+        MonoTextStyle::new(&FONT_6X10, PaletteIndex(15)),
+    )
+    .draw(&mut canvas)
+    .unwrap();
+}
+
+/// Keeps the DVI output going, and encodes each line ahead of the beam.
+#[interrupt]
+fn DMA_IRQ_1() {
+    critical_section::with(|cs| {
+        if let Some(dvi) = DVI.borrow_ref_mut(cs).as_mut() {
+            dvi.on_interrupt();
+        }
+    });
+}
+
+// End of file
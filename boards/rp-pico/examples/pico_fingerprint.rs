@@ -0,0 +1,446 @@
+//! # Pico Fingerprint Example
+//!
+//! Access control with a serial fingerprint module such as the R503 or
+//! AS608, using `rp_hal_boards_common::fingerprint`, managed from a command
+//! shell on USB Serial.
+//!
+//! Whenever a finger is on the sensor it is looked up in the module's
+//! library. A match lights the Pico's LED for two seconds and prints the
+//! name it was enrolled with. The templates live in the module; the names
+//! and slot numbers in use are kept in the Pico's flash with
+//! `rp_hal_boards_common::settings`.
+//!
+//! Open the port in a terminal program. The commands are:
+//!
+//! * `enroll <slot> [name]` - enrol a finger in a slot, 0 to 199, pressing it
+//!   on the sensor twice when asked
+//! * `cancel` - stop enrolling
+//! * `delete <slot>` - forget the finger in a slot
+//! * `list` - show the enrolled slots and their names
+//! * `empty` - forget every finger
+//!
+//! Connect the module's TX to GPIO1 (UART0 RX), its RX to GPIO0 (UART0 TX),
+//! and power it from 3V3. The R503's touch and LED ring wires aren't needed.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use embedded_hal::digital::v2::OutputPin;
+use fugit::RateExtU32;
+use hal::Clock;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use hal::gpio::bank0::{Gpio0, Gpio1, Gpio25};
+use hal::gpio::{FunctionUart, Pin, PushPullOutput};
+use hal::uart::{DataBits, StopBits, UartConfig};
+use heapless::{String, Vec};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::delay;
+use rp_hal_boards_common::fingerprint::{self, Fingerprint};
+use rp_hal_boards_common::settings::{default_offset, Store, MAX_LEN};
+use rp_hal_boards_common::shell::{Command, Shell};
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 512;
+
+/// Slots in the module's library; the AS608 has 300 and the R503 200.
+const LIBRARY: u16 = 200;
+
+/// Most fingers named, so their names fit in one settings record.
+const MAX_FINGERS: usize = 12;
+
+/// Longest name kept.
+const NAME_LEN: usize = 16;
+
+/// How often the sensor is checked, in microseconds.
+const POLL_US: u64 = 100_000;
+
+/// How long the LED stays on after a match, in microseconds.
+const OPEN_US: u64 = 2_000_000;
+
+type Uart = hal::uart::UartPeripheral<
+    hal::uart::Enabled,
+    pac::UART0,
+    (Pin<Gpio0, FunctionUart>, Pin<Gpio1, FunctionUart>),
+>;
+
+/// An enrolled finger.
+struct Finger {
+    slot: u16,
+    name: String<NAME_LEN>,
+}
+
+/// What the sensor is being used for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Looking up each finger put on it.
+    Watching,
+    /// Enrolling a finger into `slot`, waiting for the first press.
+    FirstPress { slot: u16 },
+    /// Waiting for the finger to lift between presses.
+    Lift { slot: u16 },
+    /// Waiting for the second press.
+    SecondPress { slot: u16 },
+}
+
+/// Everything the commands work on.
+struct Context {
+    sensor: Fingerprint<Uart>,
+    fingers: Vec<Finger, MAX_FINGERS>,
+    /// Where the names are kept.
+    store: Store,
+    mode: Mode,
+    /// The name for the finger being enrolled.
+    pending_name: String<NAME_LEN>,
+}
+
+impl Context {
+    /// Read the names back from flash.
+    fn load(&mut self) {
+        let mut data = [0; MAX_LEN];
+        let len = self.store.load(&mut data).unwrap_or(0);
+        let mut rest = &data[..len];
+        // Slot, name length, name
+        while rest.len() >= 3 {
+            let slot = u16::from_le_bytes([rest[0], rest[1]]);
+            let name_len = usize::from(rest[2]).min(rest.len() - 3);
+            let mut name = String::new();
+            let text = core::str::from_utf8(&rest[3..3 + name_len]).unwrap_or("?");
+            let _ = name.push_str(text);
+            let _ = self.fingers.push(Finger { slot, name });
+            rest = &rest[3 + name_len..];
+        }
+    }
+
+    /// Write the names to flash.
+    fn save(&mut self) {
+        let mut data: Vec<u8, MAX_LEN> = Vec::new();
+        for finger in &self.fingers {
+            let [low, high] = finger.slot.to_le_bytes();
+            // Twelve names of sixteen bytes and their headers fit
+            let _ = data.extend_from_slice(&[low, high, finger.name.len() as u8]);
+            let _ = data.extend_from_slice(finger.name.as_bytes());
+        }
+        let _ = self.store.save(&data);
+    }
+
+    fn forget(&mut self, slot: u16) {
+        if let Some(index) = self.fingers.iter().position(|finger| finger.slot == slot) {
+            self.fingers.swap_remove(index);
+        }
+    }
+
+    fn name(&self, slot: u16) -> &str {
+        self.fingers
+            .iter()
+            .find(|finger| finger.slot == slot)
+            .map_or("unnamed", |finger| finger.name.as_str())
+    }
+}
+
+/// Read a slot number argument.
+fn slot_arg(args: &mut rp_hal_boards_common::shell::Args<'_>) -> Result<u16, &'static str> {
+    args.next()
+        .and_then(|arg| arg.parse().ok())
+        .filter(|&slot| slot < LIBRARY)
+        .ok_or("expected a slot, 0 to 199")
+}
+
+/// The shell's commands.
+const COMMANDS: [Command<Context>; 5] = [
+    Command {
+        name: "enroll",
+        help: "enroll <slot> [name]",
+        run: |context, args, out| {
+            let slot = slot_arg(args)?;
+            if context.fingers.is_full() && !context.fingers.iter().any(|f| f.slot == slot) {
+                return Err("no room for another name, delete one first");
+            }
+            context.pending_name.clear();
+            // Cut long names short
+            for c in args.next().unwrap_or("").chars() {
+                if context.pending_name.push(c).is_err() {
+                    break;
+                }
+            }
+            context.mode = Mode::FirstPress { slot };
+            write!(out, "Place a finger on the sensor\r\n").map_err(|_| "output failed")
+        },
+    },
+    Command {
+        name: "cancel",
+        help: "cancel",
+        run: |context, _args, _out| {
+            context.mode = Mode::Watching;
+            Ok(())
+        },
+    },
+    Command {
+        name: "delete",
+        help: "delete <slot>",
+        run: |context, args, _out| {
+            let slot = slot_arg(args)?;
+            context
+                .sensor
+                .delete(slot, 1)
+                .map_err(|_| "the sensor refused")?;
+            context.forget(slot);
+            context.save();
+            Ok(())
+        },
+    },
+    Command {
+        name: "list",
+        help: "list",
+        run: |context, _args, out| {
+            let count = context.sensor.template_count().map_err(|_| "no sensor")?;
+            write!(out, "{} fingers stored\r\n", count).map_err(|_| "output failed")?;
+            for finger in &context.fingers {
+                write!(out, "{:>4} {}\r\n", finger.slot, finger.name)
+                    .map_err(|_| "output failed")?;
+            }
+            Ok(())
+        },
+    },
+    Command {
+        name: "empty",
+        help: "empty",
+        run: |context, _args, _out| {
+            context.sensor.empty().map_err(|_| "the sensor refused")?;
+            context.fingers.clear();
+            context.save();
+            Ok(())
+        },
+    },
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    // The sensor's reply timeouts come from here
+    delay::calibrate(&clocks.system_clock);
+
+    // The timer paces the sensor checks
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        pins.gpio0.into_mode::<FunctionUart>(),
+        pins.gpio1.into_mode::<FunctionUart>(),
+    );
+    let uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(
+                fingerprint::DEFAULT_BAUD.Hz(),
+                DataBits::Eight,
+                None,
+                StopBits::One,
+            ),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+    let mut led: Pin<Gpio25, PushPullOutput> = pins.led.into_push_pull_output();
+
+    let mut context = Context {
+        sensor: Fingerprint::new(uart),
+        fingers: Vec::new(),
+        store: Store::new(default_offset()),
+        mode: Mode::Watching,
+        pending_name: String::new(),
+    };
+    context.load();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Fingerprint reader")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut shell: Shell<_, 64> = Shell::new(&COMMANDS);
+    let mut connected = false;
+    let mut next_poll = timer.get_counter().ticks();
+    // Whether the finger looked up last is still on the sensor
+    let mut finger_down = false;
+    // When the LED turns off, if it's on
+    let mut open_until: Option<u64> = None;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        // Greet each terminal as it opens the port
+        let dtr = serial.port().dtr();
+        if dtr && !connected {
+            let _ = shell.prompt(&mut serial);
+        }
+        connected = dtr;
+
+        // Leave room in the transmit queue for the echo and a command's output
+        if serial.pending() < QUEUE_SIZE / 2 {
+            let mut buf = [0u8; 1];
+            if serial.read(&mut buf) == 1 {
+                let _ = shell.feed(buf[0], &mut context, &mut serial);
+            }
+        }
+
+        let now = timer.get_counter().ticks();
+        if open_until.is_some_and(|until| now >= until) {
+            led.set_low().unwrap();
+            open_until = None;
+        }
+        if now < next_poll {
+            continue;
+        }
+        next_poll = now + POLL_US;
+
+        let mut message: String<64> = String::new();
+        match step(&mut context, &mut finger_down, &mut message) {
+            Ok(Some(slot)) => {
+                led.set_high().unwrap();
+                open_until = Some(now + OPEN_US);
+                let _ = write!(message, "Welcome, {} (slot {})", context.name(slot), slot);
+            }
+            Ok(None) => {}
+            Err(error) => {
+                context.mode = Mode::Watching;
+                let _ = write!(message, "Sensor error: {:?}", error);
+            }
+        }
+        if !message.is_empty() {
+            let _ = write!(serial, "\r\n{}\r\n", message);
+            let _ = shell.prompt(&mut serial);
+        }
+    }
+}
+
+/// Check the sensor and move on whatever is under way, putting anything to
+/// tell the user in `message`. Returns the slot of a finger recognised.
+fn step(
+    context: &mut Context,
+    finger_down: &mut bool,
+    message: &mut String<64>,
+) -> Result<Option<u16>, fingerprint::Error> {
+    let sensor = &mut context.sensor;
+    let pressed = sensor.capture()?;
+    // Act once per press
+    let new_press = pressed && !*finger_down;
+    *finger_down = pressed;
+
+    match context.mode {
+        Mode::Watching if new_press => {
+            // A smudged image is just tried again on the next press
+            if sensor.convert(1).is_err() {
+                *finger_down = false;
+                return Ok(None);
+            }
+            match sensor.search(1, 0, LIBRARY)? {
+                Some(found) => return Ok(Some(found.id)),
+                None => {
+                    let _ = message.push_str("Not recognised");
+                }
+            }
+        }
+        Mode::FirstPress { slot } if new_press => {
+            if sensor.convert(1).is_err() {
+                *finger_down = false;
+                let _ = message.push_str("Unclear image, try again");
+            } else {
+                context.mode = Mode::Lift { slot };
+                let _ = message.push_str("Lift the finger");
+            }
+        }
+        Mode::Lift { slot } if !pressed => {
+            context.mode = Mode::SecondPress { slot };
+            let _ = message.push_str("Place the same finger again");
+        }
+        Mode::SecondPress { slot } if new_press => {
+            if sensor.convert(2).is_err() {
+                *finger_down = false;
+                let _ = message.push_str("Unclear image, try again");
+                return Ok(None);
+            }
+            if sensor.create_model().is_err() {
+                context.mode = Mode::FirstPress { slot };
+                let _ = message.push_str("The presses didn't match, start again");
+                return Ok(None);
+            }
+            sensor.store(1, slot)?;
+            context.forget(slot);
+            let name = core::mem::take(&mut context.pending_name);
+            let _ = context.fingers.push(Finger { slot, name });
+            context.save();
+            context.mode = Mode::Watching;
+            let _ = write!(message, "Enrolled slot {}", slot);
+        }
+        _ => {}
+    }
+    Ok(None)
+}
+
+// End of file
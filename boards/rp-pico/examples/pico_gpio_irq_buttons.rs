@@ -0,0 +1,235 @@
+//! # Pico GPIO IRQ Buttons Example
+//!
+//! Two buttons handled entirely from interrupts: nothing polls them while
+//! they are idle.
+//!
+//! Each button's pin raises `IO_IRQ_BANK0` on both edges. A switch bounces
+//! for a few milliseconds when it opens or closes, so the GPIO interrupt
+//! doesn't decide anything itself: it just (re)starts the timer's alarm 0
+//! for [`DEBOUNCE_US`]. Once the pins have been quiet that long,
+//! `TIMER_IRQ_0` reads them, and any button whose level has changed since
+//! the last event gets a new one, pushed onto a queue. The main loop takes
+//! events off the queue, prints them over USB Serial, and toggles the LED
+//! for each press of button A:
+//!
+//! ```text
+//! A pressed (3 presses)
+//! A released
+//! B pressed (1 press)
+//! ```
+//!
+//! Wire the buttons from GPIO14 (A) and GPIO15 (B) to ground; the pins'
+//! pull-ups hold them high while the buttons are open.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, ToggleableOutputPin};
+
+// Time handling traits
+use fugit::ExtU32;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// Our interrupt macro
+use pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use critical_section::Mutex;
+use hal::gpio::{
+    bank0::{Gpio14, Gpio15},
+    Interrupt::{EdgeHigh, EdgeLow},
+    Pin, PullUpInput,
+};
+use hal::timer::{Alarm, Alarm0};
+use heapless::Deque;
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+
+/// How long the pins must be quiet before they are read, in microseconds.
+const DEBOUNCE_US: u32 = 20_000;
+
+/// A button's change of state.
+#[derive(Clone, Copy)]
+struct ButtonEvent {
+    /// 0 for A, 1 for B.
+    button: usize,
+    pressed: bool,
+}
+
+/// The buttons, the alarm that debounces them, and their last reported
+/// state, shared by the two interrupts.
+struct Buttons {
+    a: Pin<Gpio14, PullUpInput>,
+    b: Pin<Gpio15, PullUpInput>,
+    alarm: Alarm0,
+    pressed: [bool; 2],
+}
+
+static BUTTONS: Mutex<RefCell<Option<Buttons>>> = Mutex::new(RefCell::new(None));
+
+/// Events from `TIMER_IRQ_0`, waiting for the main loop.
+static EVENTS: Mutex<RefCell<Deque<ButtonEvent, 16>>> = Mutex::new(RefCell::new(Deque::new()));
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led = pins.led.into_push_pull_output();
+
+    // Interrupt on both edges of both buttons
+    let a = pins.gpio14.into_pull_up_input();
+    let b = pins.gpio15.into_pull_up_input();
+    for edge in [EdgeLow, EdgeHigh] {
+        a.set_interrupt_enabled(edge, true);
+        b.set_interrupt_enabled(edge, true);
+    }
+    let mut alarm = timer.alarm_0().unwrap();
+    alarm.enable_interrupt();
+    critical_section::with(|cs| {
+        BUTTONS.borrow(cs).replace(Some(Buttons {
+            a,
+            b,
+            alarm,
+            pressed: [false; 2],
+        }));
+    });
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
+        pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_0);
+    }
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("IRQ buttons")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut presses = [0u32; 2];
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        while let Some(event) =
+            critical_section::with(|cs| EVENTS.borrow(cs).borrow_mut().pop_front())
+        {
+            let name = ["A", "B"][event.button];
+            let _ = if event.pressed {
+                presses[event.button] += 1;
+                if event.button == 0 {
+                    led.toggle().unwrap();
+                }
+                let count = presses[event.button];
+                let plural = if count == 1 { "" } else { "es" };
+                write!(serial, "{} pressed ({} press{})\r\n", name, count, plural)
+            } else {
+                write!(serial, "{} released\r\n", name)
+            };
+        }
+    }
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    critical_section::with(|cs| {
+        let mut buttons = BUTTONS.borrow(cs).borrow_mut();
+        let buttons = buttons.as_mut().unwrap();
+        for edge in [EdgeLow, EdgeHigh] {
+            buttons.a.clear_interrupt(edge);
+            buttons.b.clear_interrupt(edge);
+        }
+        // Read the pins once they have stopped bouncing; each edge pushes
+        // that back
+        let _ = buttons.alarm.schedule(DEBOUNCE_US.micros());
+    });
+}
+
+#[interrupt]
+fn TIMER_IRQ_0() {
+    critical_section::with(|cs| {
+        let mut buttons = BUTTONS.borrow(cs).borrow_mut();
+        let buttons = buttons.as_mut().unwrap();
+        buttons.alarm.clear_interrupt();
+
+        let now = [buttons.a.is_low().unwrap(), buttons.b.is_low().unwrap()];
+        let mut events = EVENTS.borrow(cs).borrow_mut();
+        for (button, (&pressed, last)) in now.iter().zip(buttons.pressed.iter_mut()).enumerate() {
+            // A press and release both inside the debounce time cancel out
+            if pressed != *last {
+                *last = pressed;
+                let _ = events.push_back(ButtonEvent { button, pressed });
+            }
+        }
+    });
+}
+
+// End of file
@@ -0,0 +1,217 @@
+//! # Pico Hardware Maths Example
+//!
+//! Times the SIO's hardware divider and interpolator against doing the same
+//! work in software, using `rp_hal_boards_common::sio_math`, and prints the
+//! results over a USB Serial port each time a terminal opens it.
+//!
+//! Three dividers are timed: a shift-and-subtract loop, as a chip without a
+//! divider would run; Rust's `/` and `%`, which the HAL already maps onto the
+//! hardware divider; and `div_rem` on the divider itself. Then a table walk,
+//! stepping a 16.16 fixed-point index, is timed with the index worked out by
+//! the CPU and by an interpolator.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use core::hint::black_box;
+use hal::sio::Lane;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::sio_math::{DivRem, InterpLanes, LaneConfig, LaneId};
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 512;
+
+/// Operations per timed run.
+const ROUNDS: u32 = 10_000;
+
+/// Lane 0 adds the step to the fixed-point position on every pop.
+const STEP_LANE: LaneConfig = LaneConfig::new();
+/// Lane 1 takes the integer part of lane 0's position, modulo the table size.
+const INDEX_LANE: LaneConfig = LaneConfig::new().cross_input().shift(16).mask(0, 7);
+
+/// Table walk step: 0.75 entries, in 16.16 fixed point.
+const STEP: u32 = 0xC000;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block holds the divider and the interpolators
+    let mut sio = hal::Sio::new(pac.SIO);
+
+    // The timer measures each run
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Hardware maths")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    // Something to look up in the table walk
+    let mut table = [0u16; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        *entry = (i as u16).wrapping_mul(2654);
+    }
+
+    sio.interp0.lane_config(LaneId::Lane0, STEP_LANE);
+    sio.interp0.lane_config(LaneId::Lane1, INDEX_LANE);
+
+    let mut connected = false;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let dtr = serial.port().dtr();
+        if !dtr || connected {
+            connected = dtr;
+            continue;
+        }
+        connected = true;
+
+        let time_us = |run: &mut dyn FnMut() -> u32| {
+            let start = timer.get_counter().ticks();
+            let check = run();
+            (timer.get_counter().ticks() - start, check)
+        };
+
+        let soft = time_us(&mut || {
+            let mut check = 0u32;
+            for i in 1..=ROUNDS {
+                let (q, r) = soft_div_rem(black_box(0xDEAD_BEEF ^ i), black_box(i));
+                check = check.wrapping_add(q ^ r);
+            }
+            check
+        });
+        let ops = time_us(&mut || {
+            let mut check = 0u32;
+            for i in 1..=ROUNDS {
+                let (n, d) = (black_box(0xDEAD_BEEF ^ i), black_box(i));
+                check = check.wrapping_add((n / d) ^ (n % d));
+            }
+            check
+        });
+        let divider = &sio.hwdivider;
+        let hard = time_us(&mut || {
+            let mut check = 0u32;
+            for i in 1..=ROUNDS {
+                let (q, r) = divider
+                    .div_rem(black_box(0xDEAD_BEEF ^ i), black_box(i))
+                    .unwrap();
+                check = check.wrapping_add(q ^ r);
+            }
+            check
+        });
+
+        let cpu_walk = time_us(&mut || {
+            let mut check = 0u32;
+            let mut position = 0u32;
+            for _ in 0..ROUNDS {
+                let index = (position >> 16) & 0xFF;
+                check = check.wrapping_add(u32::from(table[index as usize]));
+                position = position.wrapping_add(black_box(STEP));
+            }
+            check
+        });
+        let interp = &mut sio.interp0;
+        interp.lane(LaneId::Lane0).set_accum(0);
+        interp.lane(LaneId::Lane0).set_base(STEP);
+        interp.lane(LaneId::Lane1).set_base(0);
+        let index_lane = interp.get_lane1();
+        let interp_walk = time_us(&mut || {
+            let mut check = 0u32;
+            for _ in 0..ROUNDS {
+                // The mask keeps the index inside the table
+                let index = index_lane.pop();
+                check = check.wrapping_add(u32::from(table[index as usize]));
+            }
+            check
+        });
+
+        let _ = write!(serial, "{} rounds each, in microseconds:\r\n", ROUNDS);
+        for (name, (us, check)) in [
+            ("software divide", soft),
+            ("/ and %", ops),
+            ("hardware divider", hard),
+            ("table walk, CPU", cpu_walk),
+            ("table walk, interpolator", interp_walk),
+        ] {
+            let _ = write!(serial, "  {:<26}{:>8}  (check {:08x})\r\n", name, us, check);
+        }
+    }
+}
+
+/// `n / d` and `n % d` by shifting and subtracting, a bit at a time.
+#[inline(never)]
+fn soft_div_rem(n: u32, d: u32) -> (u32, u32) {
+    let mut quotient = 0;
+    let mut remainder = 0u32;
+    for bit in (0..32).rev() {
+        remainder = (remainder << 1) | ((n >> bit) & 1);
+        if remainder >= d {
+            remainder -= d;
+            quotient |= 1 << bit;
+        }
+    }
+    (quotient, remainder)
+}
+
+// End of file
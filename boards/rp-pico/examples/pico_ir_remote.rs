@@ -0,0 +1,240 @@
+//! # Pico IR Remote Example
+//!
+//! Decodes the buttons of an NEC infrared remote control, as sold with most
+//! cheap gadgets, and prints each one over USB Serial:
+//!
+//! ```text
+//! address 0x00 command 0x45
+//! address 0x00 command 0x45 repeat
+//! ```
+//!
+//! The receiver's edges are stamped with the timer's microsecond count in a
+//! GPIO interrupt, and decoded there with `rp_hal_boards_common::ir`.
+//!
+//! Pressing the button on GPIO14 sends the last code received back out of
+//! the IR LED, and holding it sends repeat codes, so the Pico can stand in
+//! for the remote. If the receiver can see the LED, it prints those too.
+//!
+//! Wiring:
+//!
+//! | Pico   | To                                                       |
+//! |--------|----------------------------------------------------------|
+//! | GPIO15 | TSOP38238 (or similar 38 kHz receiver) OUT, powered 3V3  |
+//! | GPIO16 | Base of an NPN transistor through 1 kΩ; the IR LED and   |
+//! |        | a 47 Ω resistor from VBUS to its collector               |
+//! | GPIO14 | Push button to ground                                    |
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::InputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// Our interrupt macro
+use pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use hal::Clock;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use critical_section::Mutex;
+use hal::gpio::{
+    bank0::Gpio15,
+    Interrupt::{EdgeHigh, EdgeLow},
+    Pin, PullUpInput,
+};
+use heapless::Deque;
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::delay;
+use rp_hal_boards_common::ir::{self, Decoder, Event, Transmitter, REPEAT_PERIOD_US};
+
+type ReceiverPin = Pin<Gpio15, PullUpInput>;
+
+/// The receiver pin, and the timer to stamp its edges, for the interrupt.
+static RECEIVER: Mutex<RefCell<Option<(ReceiverPin, hal::Timer)>>> = Mutex::new(RefCell::new(None));
+
+/// The decoder, fed by the interrupt.
+static DECODER: Mutex<RefCell<Decoder>> = Mutex::new(RefCell::new(Decoder::new()));
+
+/// Codes decoded by the interrupt, waiting to be printed.
+static EVENTS: Mutex<RefCell<Deque<Event, 8>>> = Mutex::new(RefCell::new(Deque::new()));
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    // The transmitted bursts are timed from here
+    delay::calibrate(&clocks.system_clock);
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let receiver = pins.gpio15.into_pull_up_input();
+    receiver.set_interrupt_enabled(EdgeLow, true);
+    receiver.set_interrupt_enabled(EdgeHigh, true);
+    critical_section::with(|cs| {
+        RECEIVER.borrow(cs).replace(Some((receiver, timer)));
+    });
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    // The IR LED on PWM slice 0, running at the carrier frequency
+    let top = ir::carrier_top(clocks.system_clock.freq().to_Hz());
+    let slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    let mut pwm0 = slices.pwm0;
+    pwm0.set_div_int(1);
+    pwm0.set_div_frac(0);
+    pwm0.set_top(top);
+    pwm0.enable();
+    pwm0.channel_a.output_to(pins.gpio16);
+    let mut transmitter = Transmitter::new(pwm0.channel_a, top);
+
+    let button = pins.gpio14.into_pull_up_input();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("IR remote")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    // The last code received, to send back
+    let mut last = None;
+    // When the button's frame or last repeat code started, while it's held
+    let mut sending: Option<u64> = None;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        while let Some(event) =
+            critical_section::with(|cs| EVENTS.borrow(cs).borrow_mut().pop_front())
+        {
+            let (code, repeat) = match event {
+                Event::Pressed(code) => (code, ""),
+                Event::Repeated(code) => (code, " repeat"),
+            };
+            let _ = write!(
+                serial,
+                "address {:#04x} command {:#04x}{}\r\n",
+                code.address, code.command, repeat
+            );
+            last = Some(code);
+        }
+
+        let pressed = button.is_low().unwrap();
+        let now = now();
+        match (pressed, sending, last) {
+            (true, None, Some(code)) => {
+                sending = Some(now);
+                transmitter.send(code);
+            }
+            (true, Some(started), _) if now - started >= u64::from(REPEAT_PERIOD_US) => {
+                sending = Some(started + u64::from(REPEAT_PERIOD_US));
+                transmitter.send_repeat();
+            }
+            (false, Some(_), _) => sending = None,
+            _ => {}
+        }
+    }
+}
+
+/// The timer's microsecond count, from the timer shared with the interrupt.
+fn now() -> u64 {
+    critical_section::with(|cs| {
+        let shared = RECEIVER.borrow(cs).borrow();
+        shared.as_ref().unwrap().1.get_counter().ticks()
+    })
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    critical_section::with(|cs| {
+        let mut receiver = RECEIVER.borrow(cs).borrow_mut();
+        let (pin, timer) = receiver.as_mut().unwrap();
+        let now = timer.get_counter().ticks();
+        let mut decoder = DECODER.borrow(cs).borrow_mut();
+        let mut events = EVENTS.borrow(cs).borrow_mut();
+        // The receiver's output goes low for each burst of carrier
+        if pin.interrupt_status(EdgeLow) {
+            pin.clear_interrupt(EdgeLow);
+            if let Some(event) = decoder.on_edge(true, now) {
+                let _ = events.push_back(event);
+            }
+        }
+        if pin.interrupt_status(EdgeHigh) {
+            pin.clear_interrupt(EdgeHigh);
+            if let Some(event) = decoder.on_edge(false, now) {
+                let _ = events.push_back(event);
+            }
+        }
+    });
+}
+
+// End of file
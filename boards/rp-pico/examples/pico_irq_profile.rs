@@ -0,0 +1,277 @@
+//! # Pico Interrupt Priority Profile Example
+//!
+//! Plays a 1 kHz tone on GPIO0 from the PWM wrap interrupt while a USB serial
+//! device runs in the USB interrupt, and shows what
+//! `rp_hal_boards_common::irq_profile` does about the two fighting.
+//!
+//! Connect a piezo or a small amplifier to GPIO0 and open the serial port.
+//! Holding down a key makes the USB handler do about 200 µs of work per
+//! packet. With every interrupt at the same priority the PWM interrupt has to
+//! wait for it, samples go out late and the tone crackles. Press `p` to apply
+//! the `AUDIO` profile: the PWM interrupt now pre-empts USB and the crackle is
+//! gone. Any other key reports how many late samples have been counted.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::{Cell, RefCell};
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// The macro for marking our interrupt functions
+use rp_pico::hal::pac::interrupt;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Embedded HAL trait for setting the PWM duty cycle
+use embedded_hal::PwmPin;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Communications Class Device support
+use usbd_serial::SerialPort;
+
+use critical_section::Mutex;
+use rp_hal_boards_common::irq_profile::{self, Entry, Level};
+
+/// The PWM slice driving the speaker (shared with the interrupt).
+static AUDIO_PWM: Mutex<RefCell<Option<hal::pwm::Slice<hal::pwm::Pwm0, hal::pwm::FreeRunning>>>> =
+    Mutex::new(RefCell::new(None));
+
+/// The USB Device Driver (only used in the USB interrupt).
+static mut USB_DEVICE: Option<UsbDevice<hal::usb::UsbBus>> = None;
+
+/// The USB Bus Driver (only used in the USB interrupt).
+static mut USB_BUS: Option<UsbBusAllocator<hal::usb::UsbBus>> = None;
+
+/// The USB Serial Device Driver (only used in the USB interrupt).
+static mut USB_SERIAL: Option<SerialPort<hal::usb::UsbBus>> = None;
+
+/// Set by the USB interrupt when the user asks for the profile to be toggled.
+static PROFILE_WANTED: AtomicBool = AtomicBool::new(false);
+
+/// How many samples went out noticeably late.
+static LATE_SAMPLES: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+
+/// PWM counter wraps at this value, giving 8-bit samples.
+const PWM_TOP: u16 = 255;
+
+/// 125 MHz / 256 / 15 gives a sample rate of about 32.5 kHz.
+const PWM_DIV: u8 = 15;
+
+/// One sample period is about 31 µs; anything over this counts as late.
+const LATE_THRESHOLD_US: u32 = 45;
+
+/// One period of a sine wave. At 32.5 kHz this plays roughly 1 kHz.
+const SINE: [u8; 32] = [
+    128, 153, 177, 199, 218, 234, 245, 253, 255, 253, 245, 234, 218, 199, 177, 153, 128, 103, 79,
+    57, 38, 22, 11, 3, 1, 3, 11, 22, 38, 57, 79, 103,
+];
+
+/// Every interrupt this example uses at the same level, as they are after
+/// reset.
+const FLAT: &[Entry] = &[
+    Entry::new(pac::Interrupt::PWM_IRQ_WRAP, Level::Highest),
+    Entry::new(pac::Interrupt::USBCTRL_IRQ, Level::Highest),
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The timer is only read directly from the PWM interrupt, but it has to
+    // be taken out of reset first
+    let _timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Configure PWM0 as the audio output on GPIO0
+    let pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    let mut pwm = pwm_slices.pwm0;
+    pwm.set_top(PWM_TOP);
+    pwm.set_div_int(PWM_DIV);
+    pwm.channel_a.output_to(pins.gpio0);
+    pwm.channel_a.set_duty(u16::from(SINE[0]));
+    pwm.enable_interrupt();
+    pwm.enable();
+    critical_section::with(|cs| AUDIO_PWM.borrow(cs).replace(Some(pwm)));
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    unsafe {
+        // Note (safety): This is safe as interrupts haven't been started yet
+        USB_BUS = Some(usb_bus);
+    }
+
+    // Grab a reference to the USB Bus allocator. We are promising to the
+    // compiler not to take mutable access to this global variable whilst this
+    // reference exists!
+    let bus_ref = unsafe { (*core::ptr::addr_of!(USB_BUS)).as_ref().unwrap() };
+
+    // Set up the USB Communications Class Device driver
+    let serial = SerialPort::new(bus_ref);
+    unsafe {
+        USB_SERIAL = Some(serial);
+    }
+
+    // Create a USB device with a fake VID and PID
+    let usb_dev = UsbDeviceBuilder::new(bus_ref, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Serial port")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+    unsafe {
+        // Note (safety): This is safe as interrupts haven't been started yet
+        USB_DEVICE = Some(usb_dev);
+    }
+
+    // Start with every interrupt at the same priority, as after reset
+    irq_profile::apply(&mut core.NVIC, FLAT);
+
+    // Enable the interrupts
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::PWM_IRQ_WRAP);
+        pac::NVIC::unmask(pac::Interrupt::USBCTRL_IRQ);
+    }
+
+    let mut profile_applied = false;
+    loop {
+        if PROFILE_WANTED.load(Ordering::Relaxed) != profile_applied {
+            profile_applied = !profile_applied;
+            let table = if profile_applied {
+                irq_profile::AUDIO
+            } else {
+                FLAT
+            };
+            // Don't change priorities underneath a running handler
+            cortex_m::interrupt::free(|_| irq_profile::apply(&mut core.NVIC, table));
+            critical_section::with(|cs| LATE_SAMPLES.borrow(cs).set(0));
+        }
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Output the next sample each time the PWM counter wraps.
+#[interrupt]
+fn PWM_IRQ_WRAP() {
+    static mut PHASE: usize = 0;
+    // Timer value at the last wrap, in microseconds
+    static mut LAST_SAMPLE_US: u32 = 0;
+
+    // Note how long it has been since the previous sample
+    let timer = unsafe { &*pac::TIMER::ptr() };
+    let now = timer.timerawl.read().bits();
+    let late = now.wrapping_sub(*LAST_SAMPLE_US) > LATE_THRESHOLD_US;
+    *LAST_SAMPLE_US = now;
+
+    *PHASE = (*PHASE + 1) % SINE.len();
+    critical_section::with(|cs| {
+        if late {
+            let count = LATE_SAMPLES.borrow(cs);
+            count.set(count.get() + 1);
+        }
+        if let Some(pwm) = AUDIO_PWM.borrow(cs).borrow_mut().as_mut() {
+            pwm.clear_interrupt();
+            pwm.channel_a.set_duty(u16::from(SINE[*PHASE]));
+        }
+    });
+}
+
+/// Service the USB serial port, deliberately taking far longer than a sample
+/// period for every packet received.
+#[allow(non_snake_case)]
+#[interrupt]
+unsafe fn USBCTRL_IRQ() {
+    // Grab the global objects. This is OK as we only access them under interrupt.
+    let usb_dev = (*core::ptr::addr_of_mut!(USB_DEVICE)).as_mut().unwrap();
+    let serial = (*core::ptr::addr_of_mut!(USB_SERIAL)).as_mut().unwrap();
+
+    if !usb_dev.poll(&mut [serial]) {
+        return;
+    }
+
+    let mut buf = [0u8; 64];
+    let count = match serial.read(&mut buf) {
+        Ok(count) => count,
+        Err(_) => return,
+    };
+
+    // Pretend to be a slow protocol handler: about 200 µs at 125 MHz
+    cortex_m::asm::delay(25_000);
+
+    for &byte in &buf[..count] {
+        let mut text: heapless::String<64> = heapless::String::new();
+        if byte == b'p' {
+            let wanted = !PROFILE_WANTED.load(Ordering::Relaxed);
+            PROFILE_WANTED.store(wanted, Ordering::Relaxed);
+            let _ = write!(
+                text,
+                "audio profile {}\r\n",
+                if wanted { "on" } else { "off" }
+            );
+        } else if byte == b'\r' || byte == b'\n' || byte == b'?' {
+            let _ = write!(
+                text,
+                "late samples: {}\r\n",
+                critical_section::with(|cs| LATE_SAMPLES.borrow(cs).get())
+            );
+        }
+        let _ = serial.write(text.as_bytes());
+    }
+}
+
+// End of file
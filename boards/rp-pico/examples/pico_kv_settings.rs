@@ -0,0 +1,258 @@
+//! # Pico Key-Value Settings Example
+//!
+//! Keeps a device name and the LED's blink rate in flash with
+//! `rp_hal_boards_common::kv`, and edits them from a shell on a USB Serial
+//! port. Open the port in a terminal program and type `help`. The commands
+//! are:
+//!
+//! * `name` - show the device name
+//! * `name <text>` - rename the device, e.g. `name Kitchen sensor`
+//! * `rate` - show the time between LED toggles
+//! * `rate <ms>` - change it, e.g. `rate 100`
+//! * `forget` - go back to the defaults
+//!
+//! Changes are saved as soon as they are made, and survive resets and
+//! reflashing. The store takes the first two sectors of the flash storage
+//! region, and spreads its writes over both.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use embedded_hal::digital::v2::ToggleableOutputPin;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use heapless::String;
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::kv::{self, Settings};
+use rp_hal_boards_common::shell::{Args, Command, Shell};
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 256;
+
+/// Longest device name, in bytes.
+const MAX_NAME_LEN: usize = 32;
+
+/// The name until one is set.
+const DEFAULT_NAME: &str = "Pico";
+
+/// The time between LED toggles until one is set, in milliseconds.
+const DEFAULT_RATE_MS: u32 = 500;
+
+/// The range `rate` accepts, in milliseconds.
+const MIN_RATE_MS: u32 = 20;
+const MAX_RATE_MS: u32 = 10_000;
+
+// Keys in the store
+const NAME_KEY: &str = "name";
+const RATE_KEY: &str = "blink_ms";
+
+/// Everything the commands work on.
+struct Context {
+    settings: Settings,
+    name: String<MAX_NAME_LEN>,
+    rate_ms: u32,
+}
+
+impl Context {
+    /// Read the settings back from flash, using the defaults for any that
+    /// aren't there.
+    fn load(&mut self) {
+        let mut buf = [0u8; kv::MAX_VALUE_LEN];
+        self.name.clear();
+        let stored = self
+            .settings
+            .get(NAME_KEY, &mut buf)
+            .and_then(|len| core::str::from_utf8(&buf[..len]).ok());
+        let _ = self.name.push_str(stored.unwrap_or(DEFAULT_NAME));
+
+        self.rate_ms = match self.settings.get(RATE_KEY, &mut buf) {
+            Some(4) => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+            _ => DEFAULT_RATE_MS,
+        };
+    }
+}
+
+/// The shell's commands.
+const COMMANDS: [Command<Context>; 3] = [
+    Command {
+        name: "name",
+        help: "name [text]",
+        run: |context, args, out| {
+            if let Some(first) = args.next() {
+                let name = join(first, args).ok_or("name too long")?;
+                save(context.settings.set(NAME_KEY, name.as_bytes()))?;
+                context.name = name;
+            }
+            write!(out, "{}\r\n", context.name).map_err(|_| "output failed")
+        },
+    },
+    Command {
+        name: "rate",
+        help: "rate [ms]",
+        run: |context, args, out| {
+            if let Some(arg) = args.next() {
+                let rate_ms: u32 = arg
+                    .parse()
+                    .ok()
+                    .filter(|rate_ms| (MIN_RATE_MS..=MAX_RATE_MS).contains(rate_ms))
+                    .ok_or("expected 20 to 10000 ms")?;
+                save(context.settings.set(RATE_KEY, &rate_ms.to_le_bytes()))?;
+                context.rate_ms = rate_ms;
+            }
+            write!(out, "{} ms\r\n", context.rate_ms).map_err(|_| "output failed")
+        },
+    },
+    Command {
+        name: "forget",
+        help: "forget",
+        run: |context, _args, _out| {
+            save(context.settings.remove(NAME_KEY))?;
+            save(context.settings.remove(RATE_KEY))?;
+            context.load();
+            Ok(())
+        },
+    },
+];
+
+/// The words of a name, put back together with single spaces.
+fn join(first: &str, rest: &mut Args<'_>) -> Option<String<MAX_NAME_LEN>> {
+    let mut name = String::new();
+    name.push_str(first).ok()?;
+    for word in rest {
+        name.push(' ').ok()?;
+        name.push_str(word).ok()?;
+    }
+    Some(name)
+}
+
+/// Turn a store error into a message for the shell.
+fn save(result: Result<(), kv::Error>) -> Result<(), &'static str> {
+    result.map_err(|error| match error {
+        kv::Error::Full => "no room left in flash",
+        _ => "can't write flash",
+    })
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // The timer paces the blinking
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The store sits at the start of the flash storage region
+    let mut context = Context {
+        settings: Settings::new(0),
+        name: String::new(),
+        rate_ms: DEFAULT_RATE_MS,
+    };
+    context.load();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Settings")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut shell: Shell<_, 64> = Shell::new(&COMMANDS);
+    let mut connected = false;
+    let mut next_toggle = timer.get_counter().ticks();
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let now = timer.get_counter().ticks();
+        if now >= next_toggle {
+            led_pin.toggle().unwrap();
+            next_toggle = now + u64::from(context.rate_ms) * 1000;
+        }
+
+        // Greet each terminal with the device's name as it opens the port
+        let dtr = serial.port().dtr();
+        if dtr && !connected {
+            let _ = write!(serial, "{}\r\n", context.name);
+            let _ = shell.prompt(&mut serial);
+        }
+        connected = dtr;
+
+        // Leave room in the transmit queue for the echo and a command's output
+        if serial.pending() > QUEUE_SIZE / 2 {
+            continue;
+        }
+        let mut buf = [0u8; 1];
+        if serial.read(&mut buf) == 1 {
+            let _ = shell.feed(buf[0], &mut context, &mut serial);
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,277 @@
+//! # Pico littlefs Log Example
+//!
+//! Keeps files in a littlefs filesystem in the flash storage region, with
+//! `rp_hal_boards_common::littlefs`. Each boot adds one to the count in
+//! `boot_count`, and every ten seconds a line with the uptime and the
+//! RP2040's temperature is appended to `log.txt`:
+//!
+//! ```text
+//! boot 3, 40 s, 24.3 C
+//! ```
+//!
+//! Once the log passes [`MAX_LOG_LEN`] bytes it becomes `log.old`, replacing
+//! the one before, and a new `log.txt` is started.
+//!
+//! Open the USB Serial port in a terminal program and type `help`. The
+//! commands are:
+//!
+//! * `count` - show the boot count
+//! * `cat` - print `log.txt`, or `cat old` for `log.old`
+//! * `clear` - delete both logs
+//!
+//! The filesystem is formatted on first boot, or if it can't be mounted.
+//! Build with `--features rp-hal-boards-common/littlefs`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use embedded_hal::adc::OneShot;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use heapless::String;
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::littlefs::{littlefs2, Storage};
+use rp_hal_boards_common::shell::{Command, Shell};
+
+use littlefs2::fs::Filesystem;
+use littlefs2::io::{Read as _, Write as _};
+use littlefs2::path;
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 256;
+
+/// Sectors for the filesystem: 32 KiB, half the default storage region.
+const BLOCKS: usize = 8;
+
+/// Time between log lines, in microseconds.
+const INTERVAL_US: u64 = 10_000_000;
+
+/// Size at which `log.txt` is moved to `log.old`.
+const MAX_LOG_LEN: usize = 8 * 1024;
+
+/// Everything the commands work on.
+struct Context {
+    storage: Storage<BLOCKS>,
+    boots: u32,
+}
+
+/// The shell's commands.
+const COMMANDS: [Command<Context>; 3] = [
+    Command {
+        name: "count",
+        help: "count",
+        run: |context, _args, out| {
+            write!(out, "{} boots\r\n", context.boots).map_err(|_| "output failed")
+        },
+    },
+    Command {
+        name: "cat",
+        help: "cat [old]",
+        run: |context, args, out| {
+            let path = match args.next() {
+                None => path!("log.txt"),
+                Some("old") => path!("log.old"),
+                _ => return Err("expected nothing or old"),
+            };
+            Filesystem::mount_and_then(&mut context.storage, |fs| {
+                fs.open_file_and_then(path, |file| {
+                    let mut buf = [0u8; 64];
+                    loop {
+                        let len = file.read(&mut buf)?;
+                        if len == 0 {
+                            return Ok(());
+                        }
+                        // The log is plain text, with `\n` line ends
+                        for &byte in &buf[..len] {
+                            if byte == b'\n' {
+                                let _ = out.write_str("\r\n");
+                            } else {
+                                let _ = out.write_char(char::from(byte));
+                            }
+                        }
+                    }
+                })
+            })
+            .map_err(|_| "no such log")
+        },
+    },
+    Command {
+        name: "clear",
+        help: "clear",
+        run: |context, _args, _out| {
+            Filesystem::mount_and_then(&mut context.storage, |fs| {
+                // Either may not be there yet
+                let _ = fs.remove(path!("log.txt"));
+                let _ = fs.remove(path!("log.old"));
+                Ok(())
+            })
+            .map_err(|_| "can't mount the filesystem")
+        },
+    },
+];
+
+/// Add one to the boot count, returning the new count.
+fn count_boot(storage: &mut Storage<BLOCKS>) -> littlefs2::io::Result<u32> {
+    Filesystem::mount_and_then(storage, |fs| {
+        let boots = match fs.read::<16>(path!("boot_count")) {
+            Ok(text) => core::str::from_utf8(&text)
+                .ok()
+                .and_then(|text| text.trim().parse::<u32>().ok())
+                .unwrap_or(0),
+            // First boot
+            Err(_) => 0,
+        } + 1;
+        let mut text: String<16> = String::new();
+        let _ = writeln!(text, "{}", boots);
+        fs.write(path!("boot_count"), text.as_bytes())?;
+        Ok(boots)
+    })
+}
+
+/// Append `line` to the log, moving a full log out of the way first.
+fn append(storage: &mut Storage<BLOCKS>, line: &str) -> littlefs2::io::Result<()> {
+    Filesystem::mount_and_then(storage, |fs| {
+        if let Ok(metadata) = fs.metadata(path!("log.txt")) {
+            if metadata.len() >= MAX_LOG_LEN {
+                // Renaming replaces any old log
+                fs.rename(path!("log.txt"), path!("log.old"))?;
+            }
+        }
+        fs.open_file_with_options_and_then(
+            |options| options.write(true).create(true).append(true),
+            path!("log.txt"),
+            |file| file.write(line.as_bytes()).map(|_| ()),
+        )
+    })
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The timer paces the logging
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The internal temperature sensor
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut temp_sensor = adc.enable_temp_sensor();
+
+    // The filesystem sits at the start of the flash storage region. A blank
+    // or damaged one is formatted.
+    let mut storage: Storage<BLOCKS> = Storage::new(0).unwrap();
+    if !Filesystem::is_mountable(&mut storage) {
+        Filesystem::format(&mut storage).unwrap();
+    }
+    let boots = count_boot(&mut storage).unwrap();
+    let mut context = Context { storage, boots };
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Log")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut shell: Shell<_, 64> = Shell::new(&COMMANDS);
+    let mut connected = false;
+    let mut next_log = timer.get_counter().ticks() + INTERVAL_US;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let now = timer.get_counter().ticks();
+        if now >= next_log {
+            next_log += INTERVAL_US;
+
+            let temp_raw: u16 = adc.read(&mut temp_sensor).unwrap();
+            // The sensor reads 0.706 V at 27 °C and falls 1.721 mV per degree
+            let microvolts = i32::from(temp_raw) * 3_300_000 / 4096;
+            let tenths = 270 - (microvolts - 706_000) * 10 / 1721;
+            let mut line: String<48> = String::new();
+            let _ = writeln!(
+                line,
+                "boot {}, {} s, {}{}.{} C",
+                context.boots,
+                now / 1_000_000,
+                if tenths < 0 { "-" } else { "" },
+                tenths.abs() / 10,
+                tenths.abs() % 10
+            );
+            // A line that can't be written is dropped
+            let _ = append(&mut context.storage, &line);
+        }
+
+        // Greet each terminal as it opens the port
+        let dtr = serial.port().dtr();
+        if dtr && !connected {
+            let _ = shell.prompt(&mut serial);
+        }
+        connected = dtr;
+
+        // Leave room in the transmit queue for the echo and a command's output
+        if serial.pending() > QUEUE_SIZE / 2 {
+            continue;
+        }
+        let mut buf = [0u8; 1];
+        if serial.read(&mut buf) == 1 {
+            let _ = shell.feed(buf[0], &mut context, &mut serial);
+        }
+    }
+}
+
+// End of file
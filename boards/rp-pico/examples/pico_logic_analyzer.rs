@@ -0,0 +1,183 @@
+//! # Pico Logic Analyzer Example
+//!
+//! Turns the Pico into an eight channel logic analyzer for sigrok and
+//! PulseView, sampling GPIO 0 to 7 at up to 100 MHz.
+//!
+//! A PIO state machine samples the pins and DMA moves the samples into RAM,
+//! with `rp_hal_boards_common::logic_capture`. The capture is then sent over
+//! USB Serial using the SUMP protocol, from `rp_hal_boards_common::sump`,
+//! which sigrok speaks with its Openbench Logic Sniffer (`ols`) driver:
+//!
+//! ```text
+//! sigrok-cli -d ols:conn=/dev/ttyACM0 --config samplerate=10m --samples 16384 -O vcd
+//! ```
+//!
+//! or pick "Openbench Logic Sniffer & SUMP compatibles" in PulseView. Up to
+//! 65536 samples are captured at a time. Triggers aren't supported: capture
+//! starts as soon as the host asks. Slow captures of many samples keep the
+//! USB device from being polled until they finish.
+//!
+//! Connect the signals to GPIO 0 to 7, and a ground.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use rp_pico::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Import pio crates
+use hal::pio::PIOExt;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::dma::Allocator;
+use rp_hal_boards_common::logic_capture::{LogicCapture, CHANNELS};
+use rp_hal_boards_common::sump::{self, Command, Parser};
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 1024;
+
+/// The first GPIO sampled.
+const BASE_PIN: u8 = 0;
+
+/// The most samples in a capture, four to a word of [`BUFFER`].
+const MEMORY: usize = 65536;
+
+/// Where captures go.
+static mut BUFFER: [u32; MEMORY / 4] = [0; MEMORY / 4];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The PIO samples the pins whatever they are set to, so they are left as
+    // they come out of reset
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let channel = allocator.alloc("logic-analyzer").unwrap();
+    let mut analyzer = LogicCapture::new(
+        &mut pio,
+        sm0,
+        BASE_PIN,
+        clocks.system_clock.freq().to_Hz(),
+        channel,
+    );
+    // Note (safety): the only reference to the buffer
+    let buffer: &'static mut [u32] = unsafe { &mut *core::ptr::addr_of_mut!(BUFFER) };
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Logic analyzer")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut parser = Parser::new();
+    let mut sample_rate = 1_000_000;
+    let mut read_count = 4096;
+    // Samples of the last capture still to send, counting down as they go
+    let mut unsent = 0;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let mut byte = [0u8; 1];
+        while serial.read(&mut byte) == 1 {
+            match parser.push(byte[0]) {
+                Some(Command::Reset) => unsent = 0,
+                Some(Command::Id) => {
+                    let _ = serial.write_all(sump::ID);
+                }
+                Some(Command::Metadata) => {
+                    let mut answer = [0u8; 64];
+                    if let Some(len) = sump::metadata(
+                        "Pico logic analyzer",
+                        u32::from(CHANNELS),
+                        MEMORY as u32,
+                        analyzer.max_sample_rate().min(sump::CLOCK_HZ),
+                        &mut answer,
+                    ) {
+                        let _ = serial.write_all(&answer[..len]);
+                    }
+                }
+                Some(Command::Divider(divider)) => sample_rate = sump::sample_rate(divider),
+                Some(Command::Counts { read, .. }) => {
+                    read_count = (read as usize).min(MEMORY);
+                }
+                Some(Command::Run) => {
+                    analyzer.capture(sample_rate, &mut buffer[..read_count.div_ceil(4)]);
+                    unsent = read_count;
+                }
+                // Flags and triggers don't change anything here
+                Some(Command::Flags(_)) | Some(Command::Other(..)) | None => {}
+            }
+        }
+
+        // SUMP sends the newest sample first, one byte per sample for eight
+        // channels
+        while unsent > 0 && serial.pending() < QUEUE_SIZE {
+            let mut chunk = [0u8; 64];
+            let count = unsent.min(chunk.len()).min(QUEUE_SIZE - serial.pending());
+            for (slot, index) in chunk.iter_mut().zip((unsent - count..unsent).rev()) {
+                *slot = (buffer[index / 4] >> (8 * (index % 4))) as u8;
+            }
+            let _ = serial.write_all(&chunk[..count]);
+            unsent -= count;
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,159 @@
+//! # Pico 3x3 Macro Pad Example
+//!
+//! A nine-key USB macro pad built from the `rp_hal_boards_common::keyboard`
+//! blocks: a diode matrix scanned at 1 kHz, per-key debouncing, two layers and
+//! an N-key rollover HID report.
+//!
+//! Wire the rows to GPIO5, GPIO6 and GPIO7 and the columns to GPIO2, GPIO3 and
+//! GPIO4, with the diodes pointing from column to row. The bottom-right key
+//! holds the second layer.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Human Interface Device (HID) Class support
+use usbd_hid::hid_class::HIDClass;
+
+use hal::gpio::DynPin;
+use rp_hal_boards_common::keyboard::{
+    keycode::*, Action, Debouncer, Layout, Matrix, NkroReport, NKRO_REPORT_DESCRIPTOR,
+};
+
+/// Shorthand for the layer table.
+const fn k(code: u8) -> Action {
+    Action::Key(code)
+}
+
+/// Ctrl+C, Ctrl+V and Ctrl+Z.
+const COPY: Action = Action::MultipleKeys(&[LEFT_CTRL, C]);
+const PASTE: Action = Action::MultipleKeys(&[LEFT_CTRL, V]);
+const UNDO: Action = Action::MultipleKeys(&[LEFT_CTRL, Z]);
+
+/// The key map: editing shortcuts on layer 0, media and arrows on layer 1.
+static LAYERS: [[[Action; 3]; 3]; 2] = [
+    [
+        [COPY, PASTE, UNDO],
+        [k(HOME), k(UP), k(END)],
+        [k(LEFT), k(DOWN), Action::Layer(1)],
+    ],
+    [
+        [k(MUTE), k(VOLUME_DOWN), k(VOLUME_UP)],
+        [k(PAGE_UP), Action::Trans, k(PAGE_DOWN)],
+        [Action::Trans, k(ENTER), Action::Trans],
+    ],
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    rp_hal_boards_common::delay::calibrate(&clocks.system_clock);
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let cols: [DynPin; 3] = [
+        pins.gpio2.into_pull_up_input().into(),
+        pins.gpio3.into_pull_up_input().into(),
+        pins.gpio4.into_pull_up_input().into(),
+    ];
+    let rows: [DynPin; 3] = [
+        pins.gpio5.into_push_pull_output().into(),
+        pins.gpio6.into_push_pull_output().into(),
+        pins.gpio7.into_push_pull_output().into(),
+    ];
+    let mut matrix = Matrix::new(cols, rows).unwrap();
+    let mut debouncer: Debouncer<3, 3> = Debouncer::default();
+    let mut layout = Layout::new(&LAYERS);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB HID Class Device driver, providing NKRO Keyboard Reports
+    let mut usb_hid = HIDClass::new(&usb_bus, NKRO_REPORT_DESCRIPTOR, 1);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27db))
+        .manufacturer("Fake company")
+        .product("3x3 Macro Pad")
+        .serial_number("TEST")
+        .device_class(0)
+        .build();
+
+    let mut next_scan = timer.get_counter().ticks();
+    let mut last_report = NkroReport::empty();
+    loop {
+        usb_dev.poll(&mut [&mut usb_hid]);
+
+        if timer.get_counter().ticks() < next_scan {
+            continue;
+        }
+        next_scan += 1_000;
+
+        let scan = matrix.scan().unwrap();
+        debouncer.update(&scan, |event| layout.event(event));
+
+        let report = NkroReport::from_keys(layout.keycodes());
+        if report != last_report && usb_hid.push_raw_input(report.as_bytes()).is_ok() {
+            last_report = report;
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,165 @@
+//! # Pico MCP23017 Buttons Example
+//!
+//! Four buttons and four LEDs on an MCP23017 GPIO expander, with the
+//! expander's interrupt output telling the Pico when a button changes, so
+//! nothing polls the I²C bus while the buttons are idle.
+//!
+//! Wire the MCP23017 to I2C0 on GPIO16 (SDA) and GPIO17 (SCL), with A0 to A2
+//! tied low, and its INTA pin to GPIO15. Put the buttons between GPA0 to GPA3
+//! and ground, and the LEDs, with resistors, from GPB0 to GPB3 to ground.
+//! Each press toggles the LED next to the button.
+//!
+//! The expander pins are ordinary embedded-hal pins: the LEDs here are driven
+//! through `OutputPin`, and the same pins would work as the rows and columns
+//! of a `keyboard::Matrix`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::{Cell, RefCell};
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Our interrupt macro
+use hal::pac::interrupt;
+
+// Some traits we need
+use fugit::RateExtU32;
+
+use critical_section::Mutex;
+use hal::gpio::{bank0::Gpio15, Interrupt::EdgeLow, Pin, PullUpInput};
+use rp_hal_boards_common::mcp23017::{Mcp23017, DEFAULT_ADDRESS};
+
+/// The GPIO wired to the expander's INTA pin.
+static INT_PIN: Mutex<RefCell<Option<Pin<Gpio15, PullUpInput>>>> = Mutex::new(RefCell::new(None));
+
+/// Set by the interrupt handler when the expander has something to report.
+static CHANGED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Configure two pins as being I²C, not GPIO
+    let sda_pin = pins.gpio16.into_mode::<hal::gpio::FunctionI2C>();
+    let scl_pin = pins.gpio17.into_mode::<hal::gpio::FunctionI2C>();
+    let i2c = hal::I2C::i2c0(
+        pac.I2C0,
+        sda_pin,
+        scl_pin,
+        400.kHz(),
+        &mut pac.RESETS,
+        &clocks.peripheral_clock,
+    );
+
+    let expander = Mcp23017::new(i2c, DEFAULT_ADDRESS).unwrap();
+    for button in 0..4 {
+        expander.input(button, true).unwrap();
+        expander.enable_interrupt(button).unwrap();
+    }
+    let mut leds = [
+        expander.output(8).unwrap(),
+        expander.output(9).unwrap(),
+        expander.output(10).unwrap(),
+        expander.output(11).unwrap(),
+    ];
+    let mut lit = [false; 4];
+
+    // The expander's interrupt output is open-drain, so pull it up here
+    let int_pin = pins.gpio15.into_pull_up_input();
+    int_pin.set_interrupt_enabled(EdgeLow, true);
+    critical_section::with(|cs| {
+        INT_PIN.borrow(cs).replace(Some(int_pin));
+    });
+    unsafe {
+        pac::NVIC::unmask(hal::pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    // Release an interrupt left over from before we were listening
+    critical_section::with(|cs| CHANGED.borrow(cs).set(true));
+
+    loop {
+        if critical_section::with(|cs| CHANGED.borrow(cs).replace(false)) {
+            if let Some(change) = expander.take_interrupt().unwrap() {
+                for (button, (led, on)) in leds.iter_mut().zip(lit.iter_mut()).enumerate() {
+                    let bit = 1 << button;
+                    // Buttons read low while pressed
+                    if change.flags & bit != 0 && change.captured & bit == 0 {
+                        *on = !*on;
+                        led.set_state((*on).into()).unwrap();
+                    }
+                }
+            }
+        }
+
+        // The handler sets an event, so a change between the check above and
+        // here still wakes us up
+        cortex_m::asm::wfe();
+    }
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    critical_section::with(|cs| {
+        if let Some(pin) = INT_PIN.borrow(cs).borrow_mut().as_mut() {
+            pin.clear_interrupt(EdgeLow);
+        }
+        CHANGED.borrow(cs).set(true);
+    });
+
+    // Set an event to ensure the main thread always wakes up, even if it's in
+    // the process of going to sleep.
+    cortex_m::asm::sev();
+}
+
+// End of file
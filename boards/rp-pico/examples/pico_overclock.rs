@@ -0,0 +1,140 @@
+//! # Pico Overclock Example
+//!
+//! Runs the system clock at 250 MHz with `rp_pico::overclock`, and reports
+//! the clock over a USB Serial port once a second.
+//!
+//! The reported frequency is measured, not just the one asked for: SysTick
+//! counts `clk_sys` cycles over 10 ms of the timer, which runs from the
+//! crystal whatever `clk_sys` does.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use cortex_m::peripheral::{syst::SystClkSource, SYST};
+use hal::clocks::Clock;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_pico::Overclock;
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 256;
+
+/// The system clock to run at. Outside 125 to 300 MHz, this fails to build.
+const SYS_CLOCK: Overclock = Overclock::mhz(250);
+
+/// How long the measurement counts cycles for, in microseconds.
+const MEASURE_US: u64 = 10_000;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks, and the core voltage and flash to suit
+    let clocks = rp_pico::overclock(
+        SYS_CLOCK,
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.VREG_AND_CHIP_RESET,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The timer counts microseconds from the crystal
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // SysTick counts clk_sys cycles, down from its largest reload value
+    core.SYST.set_clock_source(SystClkSource::Core);
+    core.SYST.set_reload(0x00FF_FFFF);
+    core.SYST.clear_current();
+    core.SYST.enable_counter();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Overclock")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut next_report = timer.get_counter().ticks();
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let now = timer.get_counter().ticks();
+        if now >= next_report {
+            next_report = now + 1_000_000;
+
+            // Count cycles over a fixed time; USB copes with the pause
+            let start = timer.get_counter().ticks();
+            let cycles_start = SYST::get_current();
+            while timer.get_counter().ticks() < start + MEASURE_US {}
+            let cycles = cycles_start.wrapping_sub(SYST::get_current()) & 0x00FF_FFFF;
+            usb_dev.poll(&mut [&mut serial]);
+
+            let measured_hz = u64::from(cycles) * 1_000_000 / MEASURE_US;
+            let _ = write!(
+                serial,
+                "clk_sys asked {} Hz, set {} Hz, measured {} Hz, core {} mV\r\n",
+                SYS_CLOCK.to_hz(),
+                clocks.system_clock.freq().to_Hz(),
+                measured_hz,
+                SYS_CLOCK.millivolts(),
+            );
+        }
+
+        // Throw away anything the host sends
+        let mut buf = [0u8; 64];
+        let _ = serial.read(&mut buf);
+    }
+}
+
+// End of file
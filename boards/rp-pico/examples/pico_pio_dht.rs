@@ -0,0 +1,232 @@
+//! # Pico PIO DHT Example
+//!
+//! Reads a DHT22 (or DHT11) temperature and humidity sensor with a PIO state
+//! machine, using `rp_hal_boards_pio::dht`, and a DS18B20 thermometer on a
+//! bit-banged 1-Wire bus, and prints both every two seconds over USB Serial:
+//!
+//! ```text
+//! DHT22: 21.4 C, 48.2 %RH  DS18B20: 21.1 C
+//! ```
+//!
+//! The state machine times the DHT's single-wire protocol, so the read goes
+//! on without interrupts being disabled. The 1-Wire bus, in contrast, is
+//! timed by the CPU.
+//!
+//! Connect the DHT's data pin to GPIO2, and the DS18B20's to GPIO3 with a
+//! 4.7 kΩ pull-up to 3V3. Either sensor can be left out, and shows as "--".
+//! For a DHT11, change [`MODEL`].
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use rp_pico::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Import pio crates
+use hal::pio::PIOExt;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::bitbang::{OneWire, OneWireError, OpenDrain};
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::delay;
+use rp_hal_boards_pio::dht::{Dht, Model};
+
+/// The DHT fitted.
+const MODEL: Model = Model::Dht22;
+
+/// Time between readings, in microseconds.
+const INTERVAL_US: u64 = 2_000_000;
+
+// DS18B20 commands
+const SKIP_ROM: u8 = 0xCC;
+const CONVERT_T: u8 = 0x44;
+const READ_SCRATCHPAD: u8 = 0xBE;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    // The 1-Wire timing comes from here
+    delay::calibrate(&clocks.system_clock);
+
+    // The delay object lets us wait for specified amounts of time
+    let mut cortex_delay =
+        cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The timer paces the readings
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // The DHT on PIO0
+    let _dht_pin = pins.gpio2.into_mode::<hal::gpio::FunctionPio0>();
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut dht = Dht::new(&mut pio, sm0, 2, clocks.system_clock.freq().to_Hz());
+
+    // The DS18B20 on a GPIO that drives low and otherwise floats
+    let mut one_wire = OneWire::new(OpenDrain::new(pins.gpio3));
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("DHT sensor")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    // The DS18B20 takes 750 ms to convert, so each reading collects the
+    // conversion started by the one before
+    let _ = start_conversion(&mut one_wire);
+    let mut next = timer.get_counter().ticks() + INTERVAL_US;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+        if timer.get_counter().ticks() < next {
+            continue;
+        }
+        next += INTERVAL_US;
+
+        let mut line: heapless::String<64> = heapless::String::new();
+        let name = match MODEL {
+            Model::Dht11 => "DHT11",
+            Model::Dht22 => "DHT22",
+        };
+        let _ = write!(line, "{}: ", name);
+        match dht.read(MODEL, &mut cortex_delay) {
+            Ok(reading) => {
+                let _ = write!(
+                    line,
+                    "{} C, {}.{} %RH",
+                    Tenths(reading.temperature),
+                    reading.humidity / 10,
+                    reading.humidity % 10
+                );
+            }
+            Err(_) => {
+                let _ = write!(line, "--");
+            }
+        }
+
+        let _ = write!(line, "  DS18B20: ");
+        match read_conversion(&mut one_wire) {
+            // 1/16 of a degree to tenths
+            Some(raw) => {
+                let _ = write!(line, "{} C", Tenths((i32::from(raw) * 10 / 16) as i16));
+            }
+            None => {
+                let _ = write!(line, "--");
+            }
+        }
+        let _ = start_conversion(&mut one_wire);
+
+        let _ = writeln!(serial, "{}\r", line);
+    }
+}
+
+/// Ask the DS18B20 to measure.
+fn start_conversion<P, E>(bus: &mut OneWire<P>) -> Result<(), OneWireError>
+where
+    P: embedded_hal::digital::v2::InputPin<Error = E>
+        + embedded_hal::digital::v2::OutputPin<Error = E>,
+{
+    bus.reset()?;
+    bus.write_byte(SKIP_ROM);
+    bus.write_byte(CONVERT_T);
+    Ok(())
+}
+
+/// The DS18B20's last measurement, in sixteenths of a degree.
+fn read_conversion<P, E>(bus: &mut OneWire<P>) -> Option<i16>
+where
+    P: embedded_hal::digital::v2::InputPin<Error = E>
+        + embedded_hal::digital::v2::OutputPin<Error = E>,
+{
+    bus.reset().ok()?;
+    bus.write_byte(SKIP_ROM);
+    bus.write_byte(READ_SCRATCHPAD);
+    let low = bus.read_byte();
+    let high = bus.read_byte();
+    // A missing or unpowered sensor reads all ones
+    let raw = i16::from_le_bytes([low, high]);
+    if raw == -1 {
+        None
+    } else {
+        Some(raw)
+    }
+}
+
+/// Tenths of a degree, shown with one decimal place.
+struct Tenths(i16);
+
+impl core::fmt::Display for Tenths {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let tenths = self.0.unsigned_abs();
+        write!(f, "{}{}.{}", sign, tenths / 10, tenths % 10)
+    }
+}
+
+// End of file
@@ -0,0 +1,182 @@
+//! # Pico PIO UART Bridge Example
+//!
+//! Adds a serial port on any two pins, using two PIO state machines from
+//! `rp_hal_boards_pio::uart`, and bridges it to USB Serial: what the host
+//! sends goes out on GPIO4, and what comes in on GPIO5 goes to the host.
+//!
+//! The port follows the baud rate the host sets, so a terminal program set
+//! to 9600 baud talks to a 9600 baud device. The format is always 8N1.
+//!
+//! The bridging only uses `embedded-hal`'s serial traits, so the same
+//! functions work on the hardware UARTs too.
+//!
+//! Connect GPIO4 to the device's RX, GPIO5 to its TX, and a ground. The
+//! device must use 3.3 V logic levels.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use rp_pico::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Import pio crates
+use hal::pio::PIOExt;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use embedded_hal::serial;
+use heapless::Deque;
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_pio::uart::{UartRx, UartTx};
+
+/// Size of the USB serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 512;
+
+/// Bytes from the host waiting to go out on the PIO port.
+const PENDING_SIZE: usize = 256;
+
+/// The baud rate until the host sets one.
+const DEFAULT_BAUD: u32 = 115_200;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    let system_clock_hz = clocks.system_clock.freq().to_Hz();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Hand the pins to PIO0
+    let _tx_pin = pins.gpio4.into_mode::<hal::gpio::FunctionPio0>();
+    let _rx_pin = pins.gpio5.into_mode::<hal::gpio::FunctionPio0>();
+
+    let (mut pio, sm0, sm1, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut uart_tx = UartTx::new(&mut pio, sm0, 4, DEFAULT_BAUD, system_clock_hz);
+    let mut uart_rx = UartRx::new(&mut pio, sm1, 5, DEFAULT_BAUD, system_clock_hz);
+    let mut baud = DEFAULT_BAUD;
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("PIO UART bridge")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut pending: Deque<u8, PENDING_SIZE> = Deque::new();
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        // Follow the terminal's baud rate, ignoring the 0 sent before one
+        // is set
+        let host_baud = serial.port().line_coding().data_rate();
+        if host_baud != 0 && host_baud != baud {
+            baud = host_baud;
+            uart_tx.set_baud(baud, system_clock_hz);
+            uart_rx.set_baud(baud, system_clock_hz);
+        }
+
+        // Host to device, taking only what there is room for so the host
+        // waits rather than bytes being lost
+        let mut chunk = [0u8; 64];
+        let room = (pending.capacity() - pending.len()).min(chunk.len());
+        let count = serial.read(&mut chunk[..room]);
+        for &byte in &chunk[..count] {
+            // Can't fail, there is room for all of it
+            let _ = pending.push_back(byte);
+        }
+        send(&mut uart_tx, &mut pending);
+
+        // Device to host, while the USB queue has room
+        let room = (QUEUE_SIZE - serial.pending()).min(chunk.len());
+        let count = receive(&mut uart_rx, &mut chunk[..room]);
+        // Can't fail, there is room for all of it
+        let _ = serial.write_all(&chunk[..count]);
+    }
+}
+
+/// Send as much of `pending` as `port` takes without waiting.
+fn send<W: serial::Write<u8>>(port: &mut W, pending: &mut Deque<u8, PENDING_SIZE>) {
+    while let Some(&byte) = pending.front() {
+        if port.write(byte).is_err() {
+            break;
+        }
+        pending.pop_front();
+    }
+}
+
+/// Fill `buf` with what `port` has received, returning how many bytes.
+fn receive<R: serial::Read<u8>>(port: &mut R, buf: &mut [u8]) -> usize {
+    let mut count = 0;
+    for slot in buf.iter_mut() {
+        match port.read() {
+            Ok(byte) => *slot = byte,
+            Err(_) => break,
+        }
+        count += 1;
+    }
+    count
+}
+
+// End of file
@@ -0,0 +1,155 @@
+//! # Pico PIO Ultrasonic Example
+//!
+//! Measures distance with an HC-SR04 ultrasonic rangefinder ten times a
+//! second, like `pico_ultrasonic`, but triggers the sensor and times its echo
+//! in a PIO state machine with `rp_hal_boards_pio::ultrasonic`. The timing
+//! doesn't depend on interrupt latency, so the readings don't jitter, however
+//! busy the CPU is. Each is printed over USB Serial:
+//!
+//! ```text
+//! 1532 us  26.2 cm
+//! ```
+//!
+//! Wiring, with the sensor powered from VBUS:
+//!
+//! | Pico   | To                                                  |
+//! |--------|-----------------------------------------------------|
+//! | GPIO2  | HC-SR04 Trig                                        |
+//! | GPIO3  | HC-SR04 Echo, through 1 kΩ, with 2 kΩ to ground     |
+//!
+//! The divider brings the 5 V echo down to 3.3 V. An HC-SR04P powered from
+//! 3V3 can be wired straight to GPIO3.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use hal::pio::PIOExt;
+use hal::Clock;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::ultrasonic::{self, MAX_ECHO_US};
+use rp_hal_boards_pio::ultrasonic::{Error, Ultrasonic};
+
+/// Time between readings, in microseconds. At least
+/// `ultrasonic::CYCLE_US`, so the last echo is over.
+const INTERVAL_US: u64 = 100_000;
+
+/// The air temperature, in degrees Celsius, for the speed of sound.
+const CELSIUS: i32 = 20;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // The sensor on PIO0
+    let _trigger_pin = pins.gpio2.into_mode::<hal::gpio::FunctionPio0>();
+    let _echo_pin = pins.gpio3.into_mode::<hal::gpio::FunctionPio0>();
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut sensor = Ultrasonic::new(&mut pio, sm0, 2, 3, clocks.system_clock.freq().to_Hz());
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("PIO ultrasonic")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut triggered = false;
+    let mut next = timer.get_counter().ticks() + INTERVAL_US;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+        if timer.get_counter().ticks() < next {
+            continue;
+        }
+        next += INTERVAL_US;
+
+        // Each reading reports the echo from the trigger before, which has
+        // had the whole interval to come back
+        if triggered {
+            let _ = match sensor.read() {
+                Some(Ok(echo_us)) => {
+                    let mm = ultrasonic::distance_mm(echo_us, CELSIUS);
+                    write!(serial, "{} us  {}.{} cm\r\n", echo_us, mm / 10, mm % 10)
+                }
+                Some(Err(Error::OutOfRange)) => write!(serial, "out of range\r\n"),
+                Some(Err(Error::NoEcho)) | None => write!(serial, "no echo\r\n"),
+            };
+        }
+
+        sensor.start(MAX_ECHO_US);
+        triggered = true;
+    }
+}
+
+// End of file
@@ -0,0 +1,138 @@
+//! # Pico PWM Audio Example
+//!
+//! Plays a short chime, embedded in the firmware as a WAV file, from PWM slice
+//! 0 on GPIO0 using `rp_hal_boards_common::audio_pwm`. Two DMA channels stream
+//! the samples, so the CPU only wakes up to convert the next block every 23 ms.
+//!
+//! Connect a piezo buzzer, or a small speaker through a transistor or an
+//! amplifier, between GPIO0 and ground. The chime repeats every two seconds.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// The macro for marking our interrupt functions
+use rp_pico::hal::pac::interrupt;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use rp_pico::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+use rp_hal_boards_common::audio_pwm::{self, AudioPwm, Buffers, Clip, Output, BUFFER_LEN};
+use rp_hal_boards_common::dma::{self, Allocator, Irq};
+
+/// An 11.025 kHz, 8 bit mono recording of two notes.
+static CHIME: &[u8] = include_bytes!("chime.wav");
+
+/// The PWM counter wraps at this value, giving 8-bit samples.
+const PWM_TOP: u16 = 255;
+
+/// Memory for the DMA channels to play from.
+static mut BUFFERS: Buffers = [[0; BUFFER_LEN]; 2];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut clip = Clip::from_wav(CHIME).unwrap();
+
+    // Make PWM0 wrap once per sample
+    let (div_int, div_frac) = audio_pwm::clock_divider(
+        clocks.system_clock.freq().to_Hz(),
+        clip.sample_rate(),
+        PWM_TOP,
+    );
+    let pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    let mut pwm = pwm_slices.pwm0;
+    pwm.set_top(PWM_TOP);
+    pwm.set_div_int(div_int);
+    pwm.set_div_frac(div_frac);
+    pwm.channel_a.output_to(pins.gpio0);
+    pwm.enable();
+
+    // Hand two DMA channels to the audio player
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    // Safety: this is the only reference ever taken to BUFFERS
+    let buffers = unsafe { &mut *core::ptr::addr_of_mut!(BUFFERS) };
+    let mut audio = AudioPwm::new(&mut allocator, 0, Output::A, PWM_TOP, buffers).unwrap();
+
+    // Enable the DMA interrupt
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+    }
+
+    loop {
+        clip.rewind();
+        audio.start(&mut clip);
+
+        // Keep the buffers topped up until the last sample has been queued,
+        // then let both buffers play out
+        let mut tail = 0;
+        while tail < 2 {
+            if audio.refill(&mut clip) && clip.is_finished() {
+                tail += 1;
+            }
+            cortex_m::asm::wfi();
+        }
+        audio.stop();
+
+        delay.delay_ms(2000);
+    }
+}
+
+#[interrupt]
+fn DMA_IRQ_0() {
+    dma::dispatch(Irq::Irq0);
+}
+
+// End of file
@@ -0,0 +1,322 @@
+//! # Pico REPL Example
+//!
+//! An interactive prompt on a USB Serial port for poking at the Pico's pins
+//! before writing any firmware, in the spirit of MicroPython's REPL.
+//!
+//! Open the port in a terminal program and type `help`. Pins are GPIO
+//! numbers, so the LED is 25. The commands are:
+//!
+//! * `gpio 25 out high` - make a pin an output and drive it; `low` and
+//!   `toggle` work too
+//! * `gpio 15 in up` - make a pin an input, with a pull-up, `down` or neither,
+//!   and show its level; `gpio 15` alone shows the level
+//! * `adc 2` - read ADC input 0 to 3 (GPIO 26 to 29), raw and in millivolts
+//! * `pwm 0 50%` - drive a pin with PWM, at a duty cycle in percent or from 0
+//!   to 255
+//! * `i2c scan` - list the devices answering on an I2C bus, bit-banged on
+//!   GPIO 4 (SDA) and 5 (SCL) or on the two pins given, e.g. `i2c scan 0 1`
+//! * `regs gpio`, `regs pwm`, `regs adc`, `regs usb` - show how the hardware
+//!   is set up, decoded from its registers
+//! * `reboot` - restart the firmware
+//! * `reboot bootsel` - restart into the USB bootloader, for the next upload
+//!
+//! The commands work on pin numbers through `rp_hal_boards_common::arduino_compat`.
+//! Build with `--features rp-hal-boards-common/arduino-compat`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::arduino_compat::{
+    self, analog_read, analog_write, delay_us, digital_read, digital_toggle, digital_write,
+    pin_mode, PinMode, HIGH, LOW,
+};
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::regs;
+use rp_hal_boards_common::shell::{Args, Command, Shell};
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 1024;
+
+/// Default I2C pins, I2C0's on the Pico's pinout.
+const I2C_SDA: u8 = 4;
+const I2C_SCL: u8 = 5;
+
+/// Half an I2C clock period, for about 100 kHz.
+const I2C_HALF_PERIOD_US: u32 = 5;
+
+/// The shell's commands. They work on the hardware directly, so there is no
+/// context.
+const COMMANDS: [Command<()>; 6] = [
+    Command {
+        name: "gpio",
+        help: "gpio <pin> [in|out] [up|down] [high|low|toggle]",
+        run: |_, args, out| {
+            let pin = parse_pin(args.next())?;
+            for word in args {
+                match word {
+                    "in" => pin_mode(pin, PinMode::Input),
+                    "up" => pin_mode(pin, PinMode::InputPullup),
+                    "down" => pin_mode(pin, PinMode::InputPulldown),
+                    "out" => pin_mode(pin, PinMode::Output),
+                    "high" => digital_write(pin, HIGH),
+                    "low" => digital_write(pin, LOW),
+                    "toggle" => digital_toggle(pin),
+                    _ => return Err("expected in, out, up, down, high, low or toggle"),
+                }
+            }
+            write!(out, "gpio {} is {}\r\n", pin, u8::from(digital_read(pin)))
+                .map_err(|_| "output failed")
+        },
+    },
+    Command {
+        name: "adc",
+        help: "adc <0-3>",
+        run: |_, args, out| {
+            let input: u8 = args
+                .next()
+                .and_then(|arg| arg.parse().ok())
+                .filter(|&input| input < 4)
+                .ok_or("expected an input from 0 to 3")?;
+            let raw = analog_read(26 + input);
+            let millivolts = u32::from(raw) * 3300 / 4095;
+            write!(out, "adc {}: {} ({} mV)\r\n", input, raw, millivolts)
+                .map_err(|_| "output failed")
+        },
+    },
+    Command {
+        name: "pwm",
+        help: "pwm <pin> <0-255|0-100%>",
+        run: |_, args, out| {
+            let pin = parse_pin(args.next())?;
+            let duty = args.next().ok_or("expected a duty cycle")?;
+            let value = match duty.strip_suffix('%') {
+                Some(percent) => percent
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&percent| percent <= 100)
+                    .map(|percent| (percent * 255 + 50) / 100),
+                None => duty.parse::<u8>().ok().map(u32::from),
+            }
+            .ok_or("expected 0 to 255, or 0% to 100%")?;
+            analog_write(pin, value as u8);
+            write!(out, "pwm on gpio {} at {}/255\r\n", pin, value).map_err(|_| "output failed")
+        },
+    },
+    Command {
+        name: "i2c",
+        help: "i2c scan [<sda> <scl>]",
+        run: |_, args, out| i2c(args, out),
+    },
+    Command {
+        name: "regs",
+        help: regs::HELP,
+        run: |_, args, out| regs::command(args, out),
+    },
+    Command {
+        name: "reboot",
+        help: "reboot [bootsel]",
+        run: |_, args, _out| match args.next() {
+            None => cortex_m::peripheral::SCB::sys_reset(),
+            // Flash the LED while the host talks to the bootloader
+            Some("bootsel") => rp_pico::reboot_to_usb_boot(Some(25), false),
+            _ => Err("expected nothing or bootsel"),
+        },
+    },
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The pins are worked by number from here on
+    arduino_compat::begin(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        pac.SIO,
+        pac.ADC,
+        pac.PWM,
+        pac.TIMER,
+        &mut pac.RESETS,
+    );
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("REPL")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut shell: Shell<_, 64> = Shell::new(&COMMANDS);
+    let mut connected = false;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        // Greet each terminal as it opens the port
+        let dtr = serial.port().dtr();
+        if dtr && !connected {
+            let _ = serial.write_all(b"Pico REPL, type help\r\n");
+            let _ = shell.prompt(&mut serial);
+        }
+        connected = dtr;
+
+        // Leave room in the transmit queue for the echo and a command's
+        // output; `regs gpio` is the longest, at about 30 lines of 30 bytes
+        if serial.pending() > QUEUE_SIZE / 16 {
+            continue;
+        }
+        let mut buf = [0u8; 1];
+        if serial.read(&mut buf) == 1 {
+            let _ = shell.feed(buf[0], &mut (), &mut serial);
+        }
+    }
+}
+
+/// A GPIO number from 0 to 29.
+fn parse_pin(arg: Option<&str>) -> Result<u8, &'static str> {
+    arg.and_then(|arg| arg.parse().ok())
+        .filter(|&pin| pin < 30)
+        .ok_or("expected a pin from 0 to 29")
+}
+
+/// The `i2c scan` command: probe every address and list those that answer.
+fn i2c(args: &mut Args<'_>, out: &mut dyn Write) -> Result<(), &'static str> {
+    if args.next() != Some("scan") {
+        return Err("expected scan");
+    }
+    let (sda, scl) = match args.next() {
+        None => (I2C_SDA, I2C_SCL),
+        sda => (parse_pin(sda)?, parse_pin(args.next())?),
+    };
+
+    // An idle bus is high; low means no pull-ups, or a device stuck mid-byte
+    release(sda);
+    release(scl);
+    delay_us(I2C_HALF_PERIOD_US);
+    if digital_read(sda) == LOW || digital_read(scl) == LOW {
+        return Err("bus held low, check the pull-ups");
+    }
+
+    let mut found = 0;
+    // 0x00-0x07 and 0x78-0x7f are reserved
+    for address in 0x08..0x78 {
+        if i2c_probe(sda, scl, address) {
+            write!(out, "found 0x{:02x}\r\n", address).map_err(|_| "output failed")?;
+            found += 1;
+        }
+    }
+    write!(out, "{} device(s) on sda {} scl {}\r\n", found, sda, scl).map_err(|_| "output failed")
+}
+
+/// Let an I2C line float high, through the pin's pull-up and any on the bus.
+fn release(pin: u8) {
+    pin_mode(pin, PinMode::InputPullup);
+}
+
+/// Pull an I2C line low.
+fn pull_low(pin: u8) {
+    pin_mode(pin, PinMode::Output);
+}
+
+/// Address a write to `address` and report whether anything acknowledged it.
+fn i2c_probe(sda: u8, scl: u8, address: u8) -> bool {
+    let wait = || delay_us(I2C_HALF_PERIOD_US);
+
+    // Start: SDA falls while SCL is high
+    pull_low(sda);
+    wait();
+    pull_low(scl);
+    wait();
+
+    // The address, most significant bit first, then 0 for a write
+    let byte = address << 1;
+    for bit in (0..8).rev() {
+        if byte >> bit & 1 == 1 {
+            release(sda);
+        } else {
+            pull_low(sda);
+        }
+        wait();
+        release(scl);
+        wait();
+        pull_low(scl);
+    }
+
+    // The device acknowledges by holding SDA low for the ninth clock
+    release(sda);
+    wait();
+    release(scl);
+    wait();
+    let ack = digital_read(sda) == LOW;
+    pull_low(scl);
+    wait();
+
+    // Stop: SDA rises while SCL is high
+    pull_low(sda);
+    wait();
+    release(scl);
+    wait();
+    release(sda);
+    wait();
+
+    ack
+}
+
+// End of file
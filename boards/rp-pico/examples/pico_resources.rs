@@ -0,0 +1,206 @@
+//! # Pico Resource Inventory Example
+//!
+//! Records which GPIOs, PIO state machines and PWM slices this firmware uses in
+//! the `rp_hal_boards_common::resources` registry, and prints the allocation
+//! table over USB Serial when you type `resources` followed by Enter.
+//!
+//! A second driver is deliberately configured to use a state machine that is
+//! already taken. Instead of silently doing nothing, the conflict is reported
+//! along with the name of the driver that got there first.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Pull in any important traits
+use embedded_hal::PwmPin;
+use rp_pico::hal::pio::PIOExt;
+use rp_pico::hal::prelude::*;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Communications Class Device support
+use usbd_serial::SerialPort;
+
+// The registry itself
+use rp_hal_boards_common::resources::{self, ClaimError, Resource};
+
+use core::fmt::Write;
+use heapless::String;
+use smart_leds::{SmartLedsWrite, RGB8};
+use ws2812_pio::Ws2812;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // Dim the on-board LED with PWM slice 4, channel B
+    let mut pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    let pwm = &mut pwm_slices.pwm4;
+    pwm.set_ph_correct();
+    pwm.enable();
+    pwm.channel_b.output_to(pins.led);
+    pwm.channel_b.set_duty(0x1000);
+    resources::claim_all(&[Resource::PwmSlice(4), Resource::Gpio(25)], "led-dimmer").unwrap();
+
+    // A WS2812 strip on GPIO4, driven by PIO0 state machine 0
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut ws = Ws2812::new(
+        pins.gpio4.into_mode(),
+        &mut pio,
+        sm0,
+        clocks.peripheral_clock.freq(),
+        timer.count_down(),
+    );
+    resources::claim_all(
+        &[Resource::PioSm { pio: 0, sm: 0 }, Resource::Gpio(4)],
+        "ws2812",
+    )
+    .unwrap();
+    ws.write([RGB8::new(0, 16, 0)].iter().copied()).unwrap();
+
+    // Pretend a second driver was told to use the same state machine. Most PIO
+    // drivers happily reprogram a running state machine, so without the
+    // registry the strip would just stop working.
+    let conflict = resources::claim(Resource::PioSm { pio: 0, sm: 0 }, "i2c-pio").err();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial = SerialPort::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Serial port")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut line: String<32> = String::new();
+    loop {
+        if !usb_dev.poll(&mut [&mut serial]) {
+            continue;
+        }
+
+        let mut buf = [0u8; 64];
+        let count = match serial.read(&mut buf) {
+            Ok(count) => count,
+            Err(_) => continue,
+        };
+
+        for &byte in &buf[..count] {
+            // Echo, so the user can see what they type
+            write_all(&mut usb_dev, &mut serial, &[byte]);
+
+            if byte != b'\r' && byte != b'\n' {
+                // Overlong lines are simply truncated
+                let _ = line.push(byte as char);
+                continue;
+            }
+
+            let mut text: String<1024> = String::new();
+            match line.trim() {
+                "resources" => {
+                    let _ = write!(text, "\r\n");
+                    let _ = resources::write_report(&mut text);
+                    if let Some(ClaimError::InUse(owner)) = conflict {
+                        let _ = write!(
+                            text,
+                            "conflict: i2c-pio wanted PIO0 SM0, owned by {}\r\n",
+                            owner
+                        );
+                    }
+                }
+                "" => {}
+                other => {
+                    let _ = write!(text, "\r\nunknown command: {}\r\n", other);
+                }
+            }
+            let _ = text.push_str("> ");
+            write_all(&mut usb_dev, &mut serial, text.as_bytes());
+            line.clear();
+        }
+    }
+}
+
+/// Write all of `data`, polling the USB device while the endpoint buffer is
+/// full.
+fn write_all(
+    usb_dev: &mut UsbDevice<hal::usb::UsbBus>,
+    serial: &mut SerialPort<hal::usb::UsbBus>,
+    mut data: &[u8],
+) {
+    while !data.is_empty() {
+        match serial.write(data) {
+            Ok(len) => data = &data[len..],
+            Err(UsbError::WouldBlock) => {
+                usb_dev.poll(&mut [serial]);
+            }
+            // The host went away; drop the rest.
+            Err(_) => break,
+        }
+    }
+}
+
+// End of file
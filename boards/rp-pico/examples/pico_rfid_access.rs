@@ -0,0 +1,264 @@
+//! # Pico RFID Access Control Example
+//!
+//! Reads MIFARE cards and tags with an RC522 (MFRC522) module, and switches a
+//! relay on for three seconds when the card is on the list of allowed ones.
+//!
+//! The list is kept in flash with `rp_hal_boards_common::settings`, so it
+//! survives resets and reflashing. To add a card, hold the button while
+//! tapping it; doing the same again removes it. The LED lights while the
+//! relay is on, flickers for a card that isn't allowed, and blinks twice when
+//! a card is added and once when one is removed.
+//!
+//! A card left on the reader counts as one tap, until it has been away for
+//! half a second.
+//!
+//! Wiring, with the RC522 powered from 3V3:
+//!
+//! | RC522 | Pico   |
+//! |-------|--------|
+//! | SDA   | GPIO17 |
+//! | SCK   | GPIO18 |
+//! | MOSI  | GPIO19 |
+//! | MISO  | GPIO16 |
+//! | RST   | GPIO20 |
+//!
+//! The relay module's input goes on GPIO15, and the button between GPIO14 and
+//! ground.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use fugit::RateExtU32;
+use hal::Clock;
+
+use hal::gpio;
+use hal::spi;
+
+use rp_hal_boards_common::mfrc522::{Mfrc522, Uid};
+use rp_hal_boards_common::settings::{default_offset, Store, MAX_LEN};
+
+/// How often the reader is polled, in milliseconds.
+const POLL_MS: u32 = 50;
+
+/// How long a card must be away before it counts as a new tap, in
+/// microseconds.
+const GONE_US: u64 = 500_000;
+
+/// How long the relay stays on, in microseconds.
+const OPEN_US: u64 = 3_000_000;
+
+/// The allowed cards, stored as a length byte followed by that many UID
+/// bytes, one card after another.
+struct AccessList {
+    data: [u8; MAX_LEN],
+    len: usize,
+}
+
+impl AccessList {
+    fn load(store: &Store) -> Self {
+        let mut data = [0; MAX_LEN];
+        let len = store.load(&mut data).unwrap_or(0);
+        AccessList { data, len }
+    }
+
+    fn cards(&self) -> impl Iterator<Item = &[u8]> {
+        let mut rest = &self.data[..self.len];
+        core::iter::from_fn(move || {
+            let (&len, tail) = rest.split_first()?;
+            let len = usize::from(len).min(tail.len());
+            let (card, tail) = tail.split_at(len);
+            rest = tail;
+            Some(card)
+        })
+    }
+
+    fn contains(&self, uid: &Uid) -> bool {
+        self.cards().any(|card| card == uid.as_bytes())
+    }
+
+    /// Add `uid`, returning `false` if the list is full.
+    fn add(&mut self, uid: &Uid) -> bool {
+        let bytes = uid.as_bytes();
+        if self.len + 1 + bytes.len() > MAX_LEN {
+            return false;
+        }
+        self.data[self.len] = bytes.len() as u8;
+        self.data[self.len + 1..self.len + 1 + bytes.len()].copy_from_slice(bytes);
+        self.len += 1 + bytes.len();
+        true
+    }
+
+    fn remove(&mut self, uid: &Uid) {
+        let mut start = 0;
+        while start < self.len {
+            let end = (start + 1 + usize::from(self.data[start])).min(self.len);
+            if self.data[start + 1..end] == *uid.as_bytes() {
+                self.data.copy_within(end..self.len, start);
+                self.len -= end - start;
+                return;
+            }
+            start = end;
+        }
+    }
+
+    fn save(&self, store: &mut Store) {
+        // Can't be too large, the list never grows past MAX_LEN
+        let _ = store.save(&self.data[..self.len]);
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The delay object lets us wait for specified amounts of time (in
+    // milliseconds)
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The timer tells how long ago a card was seen
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Take the reader out of reset
+    let mut reset = pins.gpio20.into_push_pull_output();
+    reset.set_high().unwrap();
+    delay.delay_ms(50);
+
+    let _spi_sclk = pins.gpio18.into_mode::<gpio::FunctionSpi>();
+    let _spi_mosi = pins.gpio19.into_mode::<gpio::FunctionSpi>();
+    let _spi_miso = pins.gpio16.into_mode::<gpio::FunctionSpi>();
+    let spi_cs = pins.gpio17.into_push_pull_output();
+    let spi = spi::Spi::<_, _, 8>::new(pac.SPI0).init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        4.MHz(),
+        &embedded_hal::spi::MODE_0,
+    );
+    let mut reader = Mfrc522::new(spi, spi_cs).unwrap();
+
+    let mut relay = pins.gpio15.into_push_pull_output();
+    let mut led = pins.led.into_push_pull_output();
+    let button = pins.gpio14.into_pull_up_input();
+
+    let mut store = Store::new(default_offset());
+    let mut list = AccessList::load(&store);
+
+    // The last card read, and when
+    let mut last: Option<(Uid, u64)> = None;
+    // When the relay turns off, if it's on
+    let mut open_until: Option<u64> = None;
+
+    loop {
+        delay.delay_ms(POLL_MS);
+        let now = timer.get_counter().ticks();
+
+        if open_until.is_some_and(|until| now >= until) {
+            relay.set_low().unwrap();
+            led.set_low().unwrap();
+            open_until = None;
+        }
+
+        // Errors are usually a card moving away mid-read, and the next poll
+        // tries again
+        let uid = match reader.read_uid() {
+            Ok(Some(uid)) => uid,
+            Ok(None) | Err(_) => continue,
+        };
+        let new_tap = match last {
+            Some((previous, seen)) => previous != uid || now - seen > GONE_US,
+            None => true,
+        };
+        last = Some((uid, now));
+        if !new_tap {
+            continue;
+        }
+
+        if button.is_low().unwrap() {
+            // Enrolling: add the card, or remove it if it was already there
+            let blinks = if list.contains(&uid) {
+                list.remove(&uid);
+                1
+            } else if list.add(&uid) {
+                2
+            } else {
+                // The list is full
+                continue;
+            };
+            list.save(&mut store);
+            for _ in 0..blinks {
+                led.set_high().unwrap();
+                delay.delay_ms(150);
+                led.set_low().unwrap();
+                delay.delay_ms(150);
+            }
+        } else if list.contains(&uid) {
+            relay.set_high().unwrap();
+            led.set_high().unwrap();
+            open_until = Some(now + OPEN_US);
+        } else if open_until.is_none() {
+            for _ in 0..5 {
+                led.set_high().unwrap();
+                delay.delay_ms(40);
+                led.set_low().unwrap();
+                delay.delay_ms(40);
+            }
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,205 @@
+//! # Pico ROM Benchmark Example
+//!
+//! Times the boot ROM's routines, through `rp_hal_boards_common::rom`,
+//! against what the compiler would otherwise link in, and prints the results
+//! over a USB Serial port each time a terminal opens it.
+//!
+//! The ROM versions cost no flash, so a size-constrained project can use them
+//! instead of `core`'s or `libm`'s, as long as they are fast enough.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use core::hint::black_box;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::rom;
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 512;
+
+/// Words in each copied buffer.
+const WORDS: usize = 1024;
+
+/// Copies per timed run.
+const COPIES: u32 = 100;
+
+/// Operations per timed run of the bit and float functions.
+const ROUNDS: u32 = 10_000;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The timer measures each run
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("ROM benchmark")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut src = [0u32; WORDS];
+    for (i, word) in src.iter_mut().enumerate() {
+        *word = (i as u32).wrapping_mul(0x9E37_79B9);
+    }
+    let mut dst = [0u32; WORDS];
+
+    let mut connected = false;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        let dtr = serial.port().dtr();
+        if !dtr || connected {
+            connected = dtr;
+            continue;
+        }
+        connected = true;
+
+        let time_us = |run: &mut dyn FnMut() -> u32| {
+            let start = timer.get_counter().ticks();
+            let check = run();
+            (timer.get_counter().ticks() - start, check)
+        };
+
+        let core_copy = time_us(&mut || {
+            for _ in 0..COPIES {
+                black_box(&mut dst[..]).copy_from_slice(black_box(&src[..]));
+            }
+            dst[WORDS - 1]
+        });
+        let rom_copy = time_us(&mut || {
+            for _ in 0..COPIES {
+                rom::copy(
+                    as_bytes_mut(black_box(&mut dst[..])),
+                    as_bytes(black_box(&src[..])),
+                );
+            }
+            dst[WORDS - 1]
+        });
+        let rom_copy_words = time_us(&mut || {
+            for _ in 0..COPIES {
+                rom::copy_words(black_box(&mut dst[..]), black_box(&src[..]));
+            }
+            dst[WORDS - 1]
+        });
+
+        let core_popcount = time_us(&mut || {
+            (0..ROUNDS).fold(0, |sum, i| {
+                sum + black_box(src[i as usize % WORDS]).count_ones()
+            })
+        });
+        let rom_popcount = time_us(&mut || {
+            (0..ROUNDS).fold(0, |sum, i| {
+                sum + rom::popcount(black_box(src[i as usize % WORDS]))
+            })
+        });
+
+        let soft_sqrt = time_us(&mut || {
+            (0..ROUNDS).fold(0, |sum, i| sum ^ newton_sqrt(black_box(i as f32)).to_bits())
+        });
+        let rom_sqrt = time_us(&mut || {
+            (0..ROUNDS).fold(0, |sum, i| sum ^ rom::sqrt(black_box(i as f32)).to_bits())
+        });
+
+        let _ = write!(serial, "Time in microseconds:\r\n");
+        for (name, (us, check)) in [
+            ("copy_from_slice, 4 KiB x100", core_copy),
+            ("rom::copy, 4 KiB x100", rom_copy),
+            ("rom::copy_words, 4 KiB x100", rom_copy_words),
+            ("count_ones x10000", core_popcount),
+            ("rom::popcount x10000", rom_popcount),
+            ("Newton sqrt x10000", soft_sqrt),
+            ("rom::sqrt x10000", rom_sqrt),
+        ] {
+            let _ = write!(serial, "  {:<30}{:>8}  (check {:08x})\r\n", name, us, check);
+        }
+    }
+}
+
+/// The bytes of a word slice.
+fn as_bytes(words: &[u32]) -> &[u8] {
+    // Note (safety): any u32 is four valid bytes
+    unsafe { core::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 4) }
+}
+
+/// The bytes of a word slice, for writing.
+fn as_bytes_mut(words: &mut [u32]) -> &mut [u8] {
+    // Note (safety): any four bytes are a valid u32
+    unsafe { core::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, words.len() * 4) }
+}
+
+/// A square root by Newton's method, as software without `libm` might do it.
+#[inline(never)]
+fn newton_sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    // Halving the exponent gives a first guess within a factor of two
+    let mut guess = f32::from_bits((x.to_bits() >> 1) + 0x1FC0_0000);
+    for _ in 0..4 {
+        guess = 0.5 * (guess + x / guess);
+    }
+    guess
+}
+
+// End of file
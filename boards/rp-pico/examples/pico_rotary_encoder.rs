@@ -0,0 +1,208 @@
+//! # Pico Rotary Encoder Example
+//!
+//! Reads a rotary encoder with a push switch, such as the common KY-040
+//! module, and prints what it does over USB Serial.
+//!
+//! The encoder's A and B pins raise `IO_IRQ_BANK0` on both edges, and the
+//! interrupt hands each change to a `rp_hal_boards_common::encoder::Encoder`,
+//! so no step is missed however busy the main loop is. The main loop turns
+//! the clicks into a position, moving ten at a time while the knob spins
+//! faster than [`FAST_DETENTS`] a second, and debounces the switch, which
+//! puts the position back to 0:
+//!
+//! ```text
+//! position 3 (2 clicks/s)
+//! position 13 (21 clicks/s)
+//! pressed: position 0
+//! ```
+//!
+//! Wire A (`CLK` on a KY-040) to GPIO14, B (`DT`) to GPIO15 and the switch
+//! (`SW`) to GPIO13, and the common pin to ground; the pins' pull-ups do the
+//! rest.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// Our interrupt macro
+use pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use critical_section::Mutex;
+use hal::gpio::{
+    bank0::{Gpio13, Gpio14, Gpio15},
+    Interrupt::{EdgeHigh, EdgeLow},
+    Pin, PullUpInput,
+};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::encoder::Encoder;
+use rp_hal_boards_common::input::{Active, Button, ButtonEvent};
+
+/// Clicks a second above which each click moves the position by ten.
+const FAST_DETENTS: u32 = 10;
+
+/// The encoder's A and B pins, and its switch.
+type Knob =
+    Encoder<Pin<Gpio14, PullUpInput>, Pin<Gpio15, PullUpInput>, Button<Pin<Gpio13, PullUpInput>>>;
+
+/// The encoder, and the timer to stamp its steps, shared with
+/// `IO_IRQ_BANK0`.
+static ENCODER: Mutex<RefCell<Option<(Knob, hal::Timer)>>> = Mutex::new(RefCell::new(None));
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // The contacts and the switch all pull their pin to ground
+    let switch = Button::new(pins.gpio13.into_pull_up_input(), Active::Low);
+    let mut encoder = Encoder::with_switch(
+        pins.gpio14.into_pull_up_input(),
+        pins.gpio15.into_pull_up_input(),
+        switch,
+    )
+    .unwrap();
+
+    // Interrupt on both edges of A and B
+    let (a, b) = encoder.pins_mut();
+    for edge in [EdgeLow, EdgeHigh] {
+        a.set_interrupt_enabled(edge, true);
+        b.set_interrupt_enabled(edge, true);
+    }
+    critical_section::with(|cs| {
+        ENCODER.borrow(cs).replace(Some((encoder, timer)));
+    });
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Rotary encoder")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut position: i32 = 0;
+    let mut next_scan = now();
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        // Debounce the switch at 1 kHz
+        let now = now();
+        if now < next_scan {
+            continue;
+        }
+        next_scan += 1_000;
+
+        let (switch, delta, velocity) = critical_section::with(|cs| {
+            let mut encoder = ENCODER.borrow(cs).borrow_mut();
+            let (encoder, _) = encoder.as_mut().unwrap();
+            let switch = encoder.update_switch().unwrap();
+            (switch, encoder.delta(), encoder.velocity(now))
+        });
+        if switch == Some(ButtonEvent::Pressed) {
+            position = 0;
+            let _ = write!(serial, "pressed: position 0\r\n");
+        }
+        if delta != 0 {
+            let step = if velocity > FAST_DETENTS { 10 } else { 1 };
+            position += delta * step;
+            let _ = write!(serial, "position {} ({} clicks/s)\r\n", position, velocity);
+        }
+    }
+}
+
+/// The timer's microsecond count, from the timer shared with the interrupt.
+fn now() -> u64 {
+    critical_section::with(|cs| {
+        let shared = ENCODER.borrow(cs).borrow();
+        shared.as_ref().unwrap().1.get_counter().ticks()
+    })
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    critical_section::with(|cs| {
+        let mut encoder = ENCODER.borrow(cs).borrow_mut();
+        let (encoder, timer) = encoder.as_mut().unwrap();
+        let now = timer.get_counter().ticks();
+        let (a, b) = encoder.pins_mut();
+        for edge in [EdgeLow, EdgeHigh] {
+            a.clear_interrupt(edge);
+            b.clear_interrupt(edge);
+        }
+        encoder.update(now).unwrap();
+    });
+}
+
+// End of file
@@ -26,7 +26,7 @@ mod app {
     struct Local {}
 
     #[init]
-    fn init(c: init::Context) -> (Shared, Local, init::Monotonics) {
+    fn init(c: init::Context) -> (Shared, Local) {
         // Soft-reset does not release the hardware spinlocks
         // Release them now to avoid a deadlock after debug or watchdog reset
         unsafe {
@@ -61,7 +61,7 @@ mod app {
         let _ = alarm.schedule(SCAN_TIME_US);
         alarm.enable_interrupt();
 
-        (Shared { timer, alarm, led }, Local {}, init::Monotonics())
+        (Shared { timer, alarm, led }, Local {})
     }
 
     #[task(
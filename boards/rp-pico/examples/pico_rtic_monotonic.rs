@@ -7,15 +7,9 @@ use panic_halt as _;
 mod app {
 
     use embedded_hal::digital::v2::OutputPin;
-    use fugit::ExtU64;
+    use rp_hal_boards_common::rtic_monotonic::{Duration, Timer};
     use rp_pico::{
-        hal::{
-            self,
-            clocks::init_clocks_and_plls,
-            timer::{monotonic::Monotonic, Alarm0},
-            watchdog::Watchdog,
-            Sio,
-        },
+        hal::{self, clocks::init_clocks_and_plls, watchdog::Watchdog, Sio},
         XOSC_CRYSTAL_FREQ,
     };
 
@@ -24,14 +18,11 @@ mod app {
         led: hal::gpio::Pin<hal::gpio::pin::bank0::Gpio25, hal::gpio::PushPullOutput>,
     }
 
-    #[monotonic(binds = TIMER_IRQ_0, default = true)]
-    type MyMono = Monotonic<Alarm0>;
-
     #[local]
     struct Local {}
 
     #[init]
-    fn init(c: init::Context) -> (Shared, Local, init::Monotonics) {
+    fn init(c: init::Context) -> (Shared, Local) {
         // Soft-reset does not release the hardware spinlocks
         // Release them now to avoid a deadlock after debug or watchdog reset
         unsafe {
@@ -61,29 +52,33 @@ mod app {
         let mut led = pins.led.into_push_pull_output();
         led.set_low().unwrap();
 
-        let mut timer = hal::Timer::new(c.device.TIMER, &mut resets);
-        let alarm = timer.alarm_0().unwrap();
-        blink_led::spawn_after(500.millis()).unwrap();
+        // The monotonic runs on the TIMER peripheral's alarm 0
+        Timer::start(c.device.TIMER, &mut resets);
+        blink_led::spawn().unwrap();
 
-        (
-            Shared { led },
-            Local {},
-            init::Monotonics(Monotonic::new(timer, alarm)),
-        )
+        (Shared { led }, Local {})
+    }
+
+    /// Serve the monotonic's timer queue.
+    #[task(binds = TIMER_IRQ_0, priority = 2)]
+    fn timer_irq(_: timer_irq::Context) {
+        unsafe { Timer::handle_interrupt() };
     }
 
     #[task(
         shared = [led],
         local = [tog: bool = true],
     )]
-    fn blink_led(mut c: blink_led::Context) {
-        if *c.local.tog {
-            c.shared.led.lock(|l| l.set_high().unwrap());
-        } else {
-            c.shared.led.lock(|l| l.set_low().unwrap());
-        }
-        *c.local.tog = !*c.local.tog;
+    async fn blink_led(mut c: blink_led::Context) {
+        loop {
+            if *c.local.tog {
+                c.shared.led.lock(|l| l.set_high().unwrap());
+            } else {
+                c.shared.led.lock(|l| l.set_low().unwrap());
+            }
+            *c.local.tog = !*c.local.tog;
 
-        blink_led::spawn_after(500.millis()).unwrap();
+            Timer::delay(Duration::millis(500)).await;
+        }
     }
 }
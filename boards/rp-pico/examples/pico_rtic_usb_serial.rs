@@ -0,0 +1,227 @@
+//! # Pico RTIC USB Serial Example
+//!
+//! A USB Serial device, a button and the LED, run as RTIC 2 tasks:
+//!
+//! * `usb_irq`, a hardware task on `USBCTRL_IRQ`, polls the USB device, and
+//!   spawns `echo` when something arrives,
+//! * `echo`, a software task, sends what arrived back in upper case,
+//! * `button_irq`, a hardware task on `IO_IRQ_BANK0`, starts and stops the
+//!   LED blinking when the button on GPIO 15 is pressed, and spawns `report`
+//!   to say so over USB,
+//! * `blink`, a software task, blinks the LED while it is on, waiting on the
+//!   monotonic between blinks,
+//! * `timer_irq`, a hardware task on `TIMER_IRQ_0`, serves the monotonic
+//!   from `rp_hal_boards_common::rtic_monotonic`.
+//!
+//! The USB device, the serial port and the LED are shared resources, locked
+//! by the tasks that use them.
+//!
+//! Connect a button between GPIO 15 and ground.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+#[rtic::app(device = rp_pico::hal::pac, peripherals = true, dispatchers = [I2C0_IRQ])]
+mod app {
+
+    use core::fmt::Write;
+    use embedded_hal::digital::v2::OutputPin;
+    use rp_hal_boards_common::buffered_serial::BufferedSerial;
+    use rp_hal_boards_common::rtic_monotonic::{Duration, Instant, Timer};
+    use rp_pico::{
+        hal::{
+            self,
+            clocks::init_clocks_and_plls,
+            gpio::{bank0::Gpio15, Interrupt::EdgeLow, Pin, PullUpInput},
+            watchdog::Watchdog,
+            Sio,
+        },
+        XOSC_CRYSTAL_FREQ,
+    };
+    use usb_device::{class_prelude::*, prelude::*};
+
+    /// Size of the serial port's transmit and receive queues.
+    const QUEUE_SIZE: usize = 256;
+
+    /// Presses closer together than this are contact bounce.
+    const DEBOUNCE: Duration = Duration::millis(50);
+
+    #[shared]
+    struct Shared {
+        usb_dev: UsbDevice<'static, hal::usb::UsbBus>,
+        serial: BufferedSerial<'static, hal::usb::UsbBus, QUEUE_SIZE>,
+        led: hal::gpio::Pin<hal::gpio::pin::bank0::Gpio25, hal::gpio::PushPullOutput>,
+        blinking: bool,
+    }
+
+    #[local]
+    struct Local {
+        button: Pin<Gpio15, PullUpInput>,
+    }
+
+    #[init(local = [usb_bus: Option<UsbBusAllocator<hal::usb::UsbBus>> = None])]
+    fn init(c: init::Context) -> (Shared, Local) {
+        // Soft-reset does not release the hardware spinlocks
+        // Release them now to avoid a deadlock after debug or watchdog reset
+        unsafe {
+            hal::sio::spinlock_reset();
+        }
+        let mut resets = c.device.RESETS;
+        let mut watchdog = Watchdog::new(c.device.WATCHDOG);
+        let clocks = init_clocks_and_plls(
+            XOSC_CRYSTAL_FREQ,
+            c.device.XOSC,
+            c.device.CLOCKS,
+            c.device.PLL_SYS,
+            c.device.PLL_USB,
+            &mut resets,
+            &mut watchdog,
+        )
+        .ok()
+        .unwrap();
+
+        let sio = Sio::new(c.device.SIO);
+        let pins = rp_pico::Pins::new(
+            c.device.IO_BANK0,
+            c.device.PADS_BANK0,
+            sio.gpio_bank0,
+            &mut resets,
+        );
+        let mut led = pins.led.into_push_pull_output();
+        led.set_low().unwrap();
+
+        let button = pins.gpio15.into_pull_up_input();
+        button.set_interrupt_enabled(EdgeLow, true);
+
+        // The monotonic runs on the TIMER peripheral's alarm 0
+        Timer::start(c.device.TIMER, &mut resets);
+
+        // The bus allocator has to outlive the device and the port, so it
+        // goes in a local resource of init, which lives forever
+        let usb_bus: &'static _ =
+            c.local
+                .usb_bus
+                .insert(UsbBusAllocator::new(hal::usb::UsbBus::new(
+                    c.device.USBCTRL_REGS,
+                    c.device.USBCTRL_DPRAM,
+                    clocks.usb_clock,
+                    true,
+                    &mut resets,
+                )));
+        let serial = BufferedSerial::new(usb_bus);
+        let usb_dev = UsbDeviceBuilder::new(usb_bus, UsbVidPid(0x16c0, 0x27dd))
+            .manufacturer("Fake company")
+            .product("RTIC serial port")
+            .serial_number("TEST")
+            .device_class(2) // from: https://www.usb.org/defined-class-codes
+            .build();
+
+        blink::spawn().unwrap();
+
+        (
+            Shared {
+                usb_dev,
+                serial,
+                led,
+                blinking: true,
+            },
+            Local { button },
+        )
+    }
+
+    /// Serve the monotonic's timer queue.
+    #[task(binds = TIMER_IRQ_0, priority = 3)]
+    fn timer_irq(_: timer_irq::Context) {
+        unsafe { Timer::handle_interrupt() };
+    }
+
+    /// Keep USB going, and hand anything received to `echo`.
+    #[task(binds = USBCTRL_IRQ, priority = 2, shared = [usb_dev, serial])]
+    fn usb_irq(c: usb_irq::Context) {
+        let received = (c.shared.usb_dev, c.shared.serial).lock(|usb_dev, serial| {
+            usb_dev.poll(&mut [serial]);
+            serial.available() > 0
+        });
+        if received {
+            // Already spawned means it will pick this up too
+            let _ = echo::spawn();
+        }
+    }
+
+    /// Start or stop the blinking on each press of the button.
+    #[task(
+        binds = IO_IRQ_BANK0,
+        priority = 2,
+        shared = [led, blinking],
+        local = [button, last: Option<Instant> = None],
+    )]
+    fn button_irq(c: button_irq::Context) {
+        c.local.button.clear_interrupt(EdgeLow);
+
+        let now = Timer::now();
+        if let Some(last) = *c.local.last {
+            if now - last < DEBOUNCE {
+                return;
+            }
+        }
+        *c.local.last = Some(now);
+
+        let blinking = (c.shared.led, c.shared.blinking).lock(|led, blinking| {
+            *blinking = !*blinking;
+            if !*blinking {
+                led.set_low().unwrap();
+            }
+            *blinking
+        });
+        let _ = report::spawn(now, blinking);
+    }
+
+    /// Send back what arrived, in upper case.
+    #[task(shared = [serial])]
+    async fn echo(mut c: echo::Context) {
+        c.shared.serial.lock(|serial| {
+            // Only take as much as there is room to echo
+            let mut buf = [0u8; 64];
+            let room = (QUEUE_SIZE - serial.pending()).min(buf.len());
+            let count = serial.read(&mut buf[..room]);
+            buf[..count].make_ascii_uppercase();
+            let _ = serial.write_all(&buf[..count]);
+            // Start sending now rather than at the next USB interrupt
+            serial.flush_in_poll();
+        });
+    }
+
+    /// Say when the button was pressed.
+    #[task(shared = [serial])]
+    async fn report(mut c: report::Context, at: Instant, blinking: bool) {
+        c.shared.serial.lock(|serial| {
+            let _ = write!(
+                serial,
+                "\r\nbutton pressed at {} ms, blinking {}\r\n",
+                at.duration_since_epoch().to_millis(),
+                if blinking { "on" } else { "off" }
+            );
+            serial.flush_in_poll();
+        });
+    }
+
+    /// Blink the LED once a second while blinking is on.
+    #[task(shared = [led, blinking])]
+    async fn blink(mut c: blink::Context) {
+        let mut next = Timer::now();
+        loop {
+            let blinking = c.shared.blinking.lock(|blinking| *blinking);
+            if blinking {
+                c.shared.led.lock(|led| led.set_high().unwrap());
+                Timer::delay(Duration::millis(100)).await;
+                c.shared.led.lock(|led| led.set_low().unwrap());
+            }
+            next += Duration::millis(1000);
+            Timer::delay_until(next).await;
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! # Pico Servo Potentiometers Example
+//!
+//! Turns two servos to follow two potentiometers, one through a PWM slice
+//! and one through a PIO state machine, both with
+//! `rp_hal_boards_common::servo`.
+//!
+//! The two backends look the same to the `Servo` driving them. A PWM slice
+//! gives two servos, so the RP2040 has sixteen; each PIO state machine gives
+//! eight more, on consecutive pins.
+//!
+//! The readings are smoothed, so the servos don't twitch with ADC noise.
+//!
+//! Wiring, with the servos powered from a separate 5 V supply that shares
+//! ground with the Pico:
+//!
+//! | Pico   | To                                 |
+//! |--------|------------------------------------|
+//! | GPIO0  | Servo 1 signal (PWM0 A)            |
+//! | GPIO2  | Servo 2 signal (PIO0)              |
+//! | GPIO26 | Potentiometer 1 wiper, ends on 3V3 |
+//! | GPIO27 | Potentiometer 2 wiper, ends on 3V3 |
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// ADC trait
+use embedded_hal::adc::OneShot;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use hal::pio::PIOExt;
+use hal::Clock;
+
+use rp_hal_boards_common::filters::{Exponential, Filter};
+use rp_hal_boards_common::servo::{self, NativePwm, PioServos, Servo, NATIVE_TOP};
+
+/// Time between readings, in milliseconds.
+const INTERVAL_MS: u32 = 10;
+
+/// Turn a 12 bit reading into an angle from -90° to 90°.
+fn angle(reading: i32) -> i16 {
+    (reading * 180 / 4095 - 90) as i16
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The delay object lets us wait for specified amounts of time (in
+    // milliseconds)
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Servo 1 on PWM slice 0, at the 50 Hz servo rate with 16 bit resolution
+    let system_clock_hz = clocks.system_clock.freq().to_Hz();
+    let (div_int, div_frac) = servo::native_clock_divider(system_clock_hz);
+    let slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    let mut pwm0 = slices.pwm0;
+    pwm0.set_div_int(div_int);
+    pwm0.set_div_frac(div_frac);
+    pwm0.set_top(NATIVE_TOP);
+    pwm0.enable();
+    pwm0.channel_a.output_to(pins.gpio0);
+    let mut servo1 = Servo::new(NativePwm::new(pwm0.channel_a, NATIVE_TOP));
+
+    // Servo 2 on PIO0, as the first of up to eight channels from GPIO2
+    let _servo2_pin = pins.gpio2.into_mode::<hal::gpio::FunctionPio0>();
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let pio_servos = PioServos::new(&mut pio, sm0, 2, 1, system_clock_hz);
+    let mut servo2 = Servo::new(pio_servos.channel(0));
+
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut pot1 = pins.gpio26.into_floating_input();
+    let mut pot2 = pins.gpio27.into_floating_input();
+    let mut smooth1 = Exponential::new(3);
+    let mut smooth2 = Exponential::new(3);
+
+    loop {
+        let reading1: u16 = adc.read(&mut pot1).unwrap();
+        let reading2: u16 = adc.read(&mut pot2).unwrap();
+        let angle1 = angle(smooth1.update(i32::from(reading1)));
+        let angle2 = angle(smooth2.update(i32::from(reading2)));
+
+        // Only send an angle when it changes
+        if servo1.angle() != Some(angle1) {
+            servo1.set_angle(angle1).unwrap();
+        }
+        if servo2.angle() != Some(angle2) {
+            servo2.set_angle(angle2).unwrap();
+        }
+
+        // The PIO servos need topping up more often than the pulses change
+        pio_servos.update();
+        delay.delay_ms(INTERVAL_MS);
+    }
+}
+
+// End of file
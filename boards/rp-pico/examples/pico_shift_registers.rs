@@ -0,0 +1,154 @@
+//! # Pico Shift Register Example
+//!
+//! Reads 16 buttons through two 74HC165s and drives 16 LEDs through two
+//! 74HC595s, using six GPIOs in all, with
+//! `rp_hal_boards_common::shift_register`.
+//!
+//! Each button press toggles the LED with the same number. The buttons go
+//! through the keyboard module's debouncer, and LED 0 is also driven through
+//! its own `OutputPin`, the way any driver expecting a GPIO would use it.
+//!
+//! Wiring:
+//!
+//! | GPIO | Function  | Connects to                              |
+//! |------|-----------|------------------------------------------|
+//! | 2    | SPI0 SCK  | `SRCLK` of both 74HC595s                 |
+//! | 3    | SPI0 TX   | `SER` of the first 74HC595               |
+//! | 5    | output    | `RCLK` of both 74HC595s                  |
+//! | 10   | SPI1 SCK  | `CLK` of both 74HC165s                   |
+//! | 12   | SPI1 RX   | `QH` of the first 74HC165                |
+//! | 13   | output    | `SH/LD` of both 74HC165s                 |
+//!
+//! Chain `QH'` of the first 595 to `SER` of the second, and `QH` of the second
+//! 165 to `SER` of the first. Each button pulls its 165 input to ground
+//! against a 10 kΩ pull-up.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use fugit::RateExtU32;
+use rp_pico::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Import the GPIO and SPI abstractions
+use rp_pico::hal::gpio;
+use rp_pico::hal::spi;
+
+use rp_hal_boards_common::keyboard::{Debouncer, Event};
+use rp_hal_boards_common::shift_register::{Hc165, Hc595};
+
+/// Number of buttons and of LEDs.
+const KEYS: usize = 16;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // SPI0 shifts the LED states out to the 74HC595s
+    let _led_sck = pins.gpio2.into_mode::<gpio::FunctionSpi>();
+    let _led_mosi = pins.gpio3.into_mode::<gpio::FunctionSpi>();
+    let led_latch = pins.gpio5.into_push_pull_output();
+    let led_spi = spi::Spi::<_, _, 8>::new(pac.SPI0).init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        4.MHz(),
+        &embedded_hal::spi::MODE_0,
+    );
+    let leds: Hc595<_, _, 2> = Hc595::new(led_spi, led_latch).unwrap();
+
+    // SPI1 shifts the button states in from the 74HC165s
+    let _button_sck = pins.gpio10.into_mode::<gpio::FunctionSpi>();
+    let _button_miso = pins.gpio12.into_mode::<gpio::FunctionSpi>();
+    let button_load = pins.gpio13.into_push_pull_output();
+    let button_spi = spi::Spi::<_, _, 8>::new(pac.SPI1).init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        4.MHz(),
+        &embedded_hal::spi::MODE_0,
+    );
+    let buttons: Hc165<_, _, 2> = Hc165::new(button_spi, button_load).unwrap();
+
+    // LED 0 as a plain OutputPin: blink it once to show the chain works
+    let mut first_led = leds.output(0);
+    first_led.set_high().unwrap();
+    delay.delay_ms(200);
+    first_led.set_low().unwrap();
+
+    let mut debouncer: Debouncer<KEYS, 1> = Debouncer::default();
+    loop {
+        let scan = buttons.scan::<KEYS>(true).unwrap();
+
+        let mut toggled = 0u16;
+        debouncer.update(&scan, |event| {
+            if let Event::Press(_, key) = event {
+                toggled ^= 1 << key;
+            }
+        });
+
+        if toggled != 0 {
+            let [low, high] = leds.levels();
+            let levels = u16::from_le_bytes([low, high]) ^ toggled;
+            leds.write_all(levels.to_le_bytes()).unwrap();
+        }
+
+        // Scan at about 1 kHz, which the debouncer's default is tuned for
+        delay.delay_ms(1);
+    }
+}
+
+// End of file
@@ -0,0 +1,200 @@
+//! # Pico Sleep Logger Example
+//!
+//! A battery-powered sensor node: wakes every few minutes on the RTC alarm,
+//! reads the on-chip temperature sensor and the VSYS voltage, appends them to
+//! a log in flash and goes back to sleep.
+//!
+//! The whole program runs from the 12 MHz crystal with both PLLs stopped, and
+//! sleeps with every clock except the RTC's gated off. Power the Pico from a
+//! battery on VSYS.
+//!
+//! To read the log, connect a USB-serial adapter to GPIO0 (TX) and GPIO1 (RX),
+//! hold GPIO15 to ground and reset the Pico. The log is printed as CSV at
+//! 115200 baud before logging carries on.
+//!
+//! ## Power budget
+//!
+//! These figures are estimates, not measurements: they take the typical
+//! currents from the Pico and RP2040 datasheets at 25 °C and VSYS = 3.7 V,
+//! and will differ from board to board. Measure your own build with a meter in
+//! series with the battery before trusting a runtime.
+//!
+//! | State                                    | Current  | Time per sample     |
+//! | ---------------------------------------- | -------- | ------------------- |
+//! | Asleep, only `clk_rtc` and XOSC running  | ~1.3 mA  | rest of the period  |
+//! | Awake at 12 MHz, sampling                | ~5 mA    | ~2 ms               |
+//! | Programming a page (1 in 31 samples)     | ~10 mA   | ~1 ms               |
+//! | Erasing a sector (1 in 496 samples)      | ~10 mA   | ~50 ms              |
+//!
+//! With a 5 minute period the awake time adds well under 0.1 µA to the
+//! average, so the average is the sleep current, about 1.3 mA: roughly 60
+//! days from a 2000 mAh cell. The sleep current is mostly the crystal, the
+//! flash chip in standby and the Pico's regulator, so a longer period barely
+//! helps. Leave GPIO23 low so the regulator stays in its low-current PFM mode.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO and ADC traits
+use embedded_hal::adc::OneShot;
+use embedded_hal::digital::v2::InputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use fugit::RateExtU32;
+use hal::Clock;
+
+use hal::rtc::{DateTime, DateTimeFilter, DayOfWeek, RealTimeClock};
+use hal::uart::{DataBits, StopBits, UartConfig};
+
+use rp_hal_boards_common::datalog::{Log, RECORD_LEN};
+use rp_hal_boards_common::sleep;
+
+/// Minutes between samples. At most 59, as the alarm matches on the minute of
+/// the hour.
+const INTERVAL_MINUTES: u8 = 5;
+
+/// Where the log starts, in bytes from the start of the `flash_storage`
+/// region.
+const LOG_OFFSET: u32 = 0;
+/// Sectors for the log: the default 64 KiB region less the two sectors
+/// `settings` keeps at its end. 56 KiB holds 6944 samples, about 24 days at
+/// one sample every 5 minutes.
+const LOG_SECTORS: u32 = 14;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Run everything from the crystal and leave the PLLs off
+    let clocks = sleep::run_from_xosc(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The ring oscillator ran the chip until now and isn't needed any more
+    hal::rosc::RingOscillator::new(pac.ROSC)
+        .initialize()
+        .disable();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Fails if `FLASH_STORAGE_SIZE` made the region too small for the log
+    let mut log = Log::new(LOG_OFFSET, LOG_SECTORS).unwrap();
+
+    // Print the log if GPIO15 is held low at start-up
+    let dump = pins.gpio15.into_pull_up_input();
+    if dump.is_low().unwrap() {
+        let uart_pins = (
+            pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+            pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+        );
+        let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+            .enable(
+                UartConfig::new(115200.Hz(), DataBits::Eight, None, StopBits::One),
+                clocks.peripheral_clock.freq(),
+            )
+            .unwrap();
+        writeln!(uart, "minutes,temperature_c,vsys_mv").unwrap();
+        log.for_each(|record| {
+            let minutes = u32::from_le_bytes([record[0], record[1], record[2], record[3]]);
+            let centi = i16::from_le_bytes([record[4], record[5]]);
+            let vsys = u16::from_le_bytes([record[6], record[7]]);
+            let _ = writeln!(
+                uart,
+                "{},{}{}.{:02},{}",
+                minutes,
+                if centi < 0 { "-" } else { "" },
+                centi.unsigned_abs() / 100,
+                centi.unsigned_abs() % 100,
+                vsys
+            );
+        });
+    }
+    dump.into_floating_disabled();
+
+    // Set up the ADC, its temperature sensor and the VSYS divider on GPIO29
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut temp_sensor = adc.enable_temp_sensor();
+    let mut vsys_pin = pins.voltage_monitor.into_floating_input();
+
+    // The RTC only needs to count from start-up, so any date will do
+    let start = DateTime {
+        year: 2023,
+        month: 1,
+        day: 1,
+        day_of_week: DayOfWeek::Sunday,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    };
+    let mut rtc = RealTimeClock::new(pac.RTC, clocks.rtc_clock, &mut pac.RESETS, start)
+        .ok()
+        .unwrap();
+
+    let mut minutes: u32 = 0;
+    loop {
+        let temp_raw: u16 = adc.read(&mut temp_sensor).unwrap();
+        let vsys_raw: u16 = adc.read(&mut vsys_pin).unwrap();
+
+        // The sensor reads 0.706 V at 27 °C and falls 1.721 mV per degree
+        let microvolts = i32::from(temp_raw) * 3_300_000 / 4096;
+        let centi = (2700 - (microvolts - 706_000) * 100 / 1721) as i16;
+        // VSYS is divided by three before reaching the ADC
+        let vsys = (u32::from(vsys_raw) * 3 * 3300 / 4096) as u16;
+
+        let mut record = [0u8; RECORD_LEN];
+        record[0..4].copy_from_slice(&minutes.to_le_bytes());
+        record[4..6].copy_from_slice(&centi.to_le_bytes());
+        record[6..8].copy_from_slice(&vsys.to_le_bytes());
+        log.append(&record);
+
+        minutes += u32::from(INTERVAL_MINUTES);
+        let alarm = DateTimeFilter::default()
+            .minute((minutes % 60) as u8)
+            .second(0);
+        sleep::sleep_until(&mut rtc, alarm, &mut core.SCB);
+    }
+}
+
+// End of file
@@ -0,0 +1,181 @@
+//! # Pico Solar Sensor Example
+//!
+//! A solar-charged sensor node running from a supercapacitor, which only
+//! spends energy on its radio when VSYS says there is enough to spare.
+//!
+//! Every minute it wakes on the RTC alarm, reads the on-chip temperature
+//! sensor and VSYS, and asks an `EnergyBudget` whether it can afford to:
+//!
+//! * send the reading over LoRa, through a Reyax RYLR896 (or any modem using
+//!   the same `AT+SEND` commands) on UART0, GPIO0 (TX) and GPIO1 (RX),
+//! * blink the LED to show it is alive.
+//!
+//! Readings that can't be sent are counted, and the count goes out with the
+//! next reading that is. When the supercap is too low for either task the
+//! node just samples and sleeps, keeping enough in reserve to carry on until
+//! the sun comes back.
+//!
+//! Wire a 1 F, 5.5 V supercap to VSYS, charged from a small solar panel
+//! through a charge controller.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO and ADC traits
+use embedded_hal::adc::OneShot;
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use fugit::RateExtU32;
+use hal::Clock;
+
+use hal::rtc::{DateTime, DateTimeFilter, DayOfWeek, RealTimeClock};
+use hal::uart::{DataBits, StopBits, UartConfig};
+
+use heapless::String;
+use rp_hal_boards_common::energy_budget::{EnergyBudget, Storage};
+use rp_hal_boards_common::sleep;
+
+/// The supercap: 1 F, and the Pico's regulator runs down to 1.8 V.
+const STORAGE: Storage = Storage::Supercap {
+    capacitance_mf: 1000,
+    min_mv: 1800,
+};
+
+/// Kept back for sampling and sleeping: about 1.5 mA at 3.3 V for ten
+/// minutes, to ride out a passing cloud. The radio stays off below about
+/// 3.05 V.
+const RESERVE_MJ: u32 = 3000;
+
+/// A LoRa packet at SF9/125 kHz: about 45 mA at 3.3 V for 200 ms of airtime.
+const RADIO_TX_MJ: u32 = 30;
+
+/// The LED on for 20 ms at about 5 mA.
+const LED_BLINK_MJ: u32 = 1;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let mut core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Run everything from the crystal and leave the PLLs off
+    let clocks = sleep::run_from_xosc(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led = pins.led.into_push_pull_output();
+
+    // The LoRa modem
+    let uart_pins = (
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let mut modem = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(115200.Hz(), DataBits::Eight, None, StopBits::One),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    // Set up the ADC, its temperature sensor and the VSYS divider on GPIO29
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut temp_sensor = adc.enable_temp_sensor();
+    let mut vsys_pin = pins.voltage_monitor.into_floating_input();
+
+    // The RTC only needs to count from start-up, so any date will do
+    let start = DateTime {
+        year: 2023,
+        month: 1,
+        day: 1,
+        day_of_week: DayOfWeek::Sunday,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    };
+    let mut rtc = RealTimeClock::new(pac.RTC, clocks.rtc_clock, &mut pac.RESETS, start)
+        .ok()
+        .unwrap();
+
+    let mut budget = EnergyBudget::new(STORAGE, RESERVE_MJ);
+    let mut unsent: u32 = 0;
+    let mut minute: u8 = 0;
+    loop {
+        budget.sample(&mut adc, &mut vsys_pin);
+
+        let temp_raw: u16 = adc.read(&mut temp_sensor).unwrap();
+        // The sensor reads 0.706 V at 27 °C and falls 1.721 mV per degree
+        let microvolts = i32::from(temp_raw) * 3_300_000 / 4096;
+        let centi = 2700 - (microvolts - 706_000) * 100 / 1721;
+
+        if budget.allow(RADIO_TX_MJ) {
+            let mut payload: String<32> = String::new();
+            let _ = write!(payload, "{},{},{}", centi, budget.vsys_mv(), unsent);
+            // Wake the modem, send to every address, then put it back to sleep
+            let _ = write!(modem, "AT\r\n");
+            delay.delay_ms(10);
+            let _ = write!(modem, "AT+SEND=0,{},{}\r\n", payload.len(), payload);
+            delay.delay_ms(300);
+            let _ = write!(modem, "AT+MODE=1\r\n");
+            unsent = 0;
+        } else {
+            unsent += 1;
+        }
+
+        if budget.allow(LED_BLINK_MJ) {
+            led.set_high().unwrap();
+            delay.delay_ms(20);
+            led.set_low().unwrap();
+        }
+
+        minute = (minute + 1) % 60;
+        let alarm = DateTimeFilter::default().minute(minute).second(0);
+        sleep::sleep_until(&mut rtc, alarm, &mut core.SCB);
+    }
+}
+
+// End of file
@@ -0,0 +1,212 @@
+//! # Pico Spectrum Analyzer Example
+//!
+//! Shows the spectrum of the signal on ADC0 (GPIO 26) on a Pimoroni Pico
+//! Display Pack, using `rp_hal_boards_common::dsp`.
+//!
+//! Blocks of 256 samples are captured at 20 kHz by DMA with
+//! `rp_hal_boards_common::adc_stream`, windowed and transformed, and the 120
+//! bins from 0 to about 9.4 kHz are drawn as bars on a logarithmic scale, each
+//! bin 78 Hz wide.
+//!
+//! Feed in line level audio through a 1 µF capacitor, with two 10 kΩ
+//! resistors biasing GPIO 26 to half of 3.3 V.
+//!
+//! Build with `--features pico-display-pack`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use embedded_hal::digital::v2::OutputPin;
+use hal::Clock;
+
+// Graphics support
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use st7789::{Orientation, ST7789};
+
+use rp_pico::display_pack::{DisplayPack, DisplayPackPins, LCD_HEIGHT, LCD_OFFSET, LCD_WIDTH};
+
+use rp_hal_boards_common::adc_stream::AdcStream;
+use rp_hal_boards_common::dma::Allocator;
+use rp_hal_boards_common::dsp;
+
+/// Samples per transform.
+const FFT_LEN: usize = 256;
+
+/// Sample rate of the ADC.
+const SAMPLE_RATE: u32 = 20_000;
+
+/// Bins shown, two pixels wide each.
+const BINS: usize = LCD_WIDTH as usize / 2;
+
+/// The Display Pack doesn't bring out the LCD's reset line.
+struct NoResetPin;
+
+impl OutputPin for NoResetPin {
+    type Error = ();
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let DisplayPack { lcd, .. } = DisplayPack::new(DisplayPackPins {
+        gpio6: pins.gpio6,
+        gpio7: pins.gpio7,
+        gpio8: pins.gpio8,
+        gpio12: pins.gpio12,
+        gpio13: pins.gpio13,
+        gpio14: pins.gpio14,
+        gpio15: pins.gpio15,
+        gpio16: pins.gpio16,
+        gpio17: pins.gpio17,
+        gpio18: pins.gpio18,
+        gpio19: pins.gpio19,
+        gpio20: pins.gpio20,
+    });
+
+    let (interface, _backlight) = DisplayPack::spi_interface(
+        lcd,
+        pac.SPI0,
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+    );
+    let mut lcd = ST7789::new(interface, NoResetPin, 240, 320);
+    lcd.init(&mut delay).unwrap();
+    lcd.set_orientation(Orientation::Landscape).unwrap();
+
+    // Draw in panel coordinates rather than controller coordinates
+    let mut screen = lcd.translated(Point::new(LCD_OFFSET.0, LCD_OFFSET.1));
+    Rectangle::new(Point::zero(), Size::new(LCD_WIDTH, LCD_HEIGHT))
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+        .draw(&mut screen)
+        .unwrap();
+
+    // Sample GPIO 26 at a steady 20 kHz
+    let _audio_in = pins.gpio26.into_floating_input();
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let channel = allocator.alloc("spectrum-adc").unwrap();
+    let mut adc = AdcStream::new(pac.ADC, &mut pac.RESETS, channel, 0, SAMPLE_RATE);
+
+    let mut raw = [0u16; FFT_LEN];
+    let mut re = [0i16; FFT_LEN];
+    let mut im = [0i16; FFT_LEN];
+    let mut bins = [0u16; BINS];
+    let mut heights = [0u32; BINS];
+
+    loop {
+        adc.capture(&mut raw);
+
+        // 12 bit unsigned readings to Q15, then take out the bias
+        for (re, &raw) in re.iter_mut().zip(raw.iter()) {
+            *re = ((i32::from(raw) - 2048) << 4) as i16;
+        }
+        im.fill(0);
+        dsp::remove_dc(&mut re);
+        dsp::hann(&mut re);
+        dsp::fft(&mut re, &mut im);
+        dsp::magnitudes(&re, &im, &mut bins);
+
+        // Only redraw the part of each bar that changed
+        for (i, (&magnitude, old)) in bins.iter().zip(heights.iter_mut()).enumerate() {
+            let new = bar_height(magnitude);
+            if new == *old {
+                continue;
+            }
+            let (top, bottom, colour) = if new > *old {
+                (LCD_HEIGHT - new, LCD_HEIGHT - *old, Rgb565::GREEN)
+            } else {
+                (LCD_HEIGHT - *old, LCD_HEIGHT - new, Rgb565::BLACK)
+            };
+            Rectangle::new(
+                Point::new(2 * i as i32, top as i32),
+                Size::new(2, bottom - top),
+            )
+            .into_styled(PrimitiveStyle::with_fill(colour))
+            .draw(&mut screen)
+            .unwrap();
+            *old = new;
+        }
+    }
+}
+
+/// The height of a bar, with the full height of the screen covering the 16
+/// octaves (96 dB) a `u16` magnitude spans.
+fn bar_height(magnitude: u16) -> u32 {
+    if magnitude == 0 {
+        return 0;
+    }
+    // log2 in 4.4 fixed point
+    let whole = 15 - magnitude.leading_zeros();
+    let fraction = if whole >= 4 {
+        (u32::from(magnitude) >> (whole - 4)) & 0xF
+    } else {
+        (u32::from(magnitude) << (4 - whole)) & 0xF
+    };
+    let log2 = whole << 4 | fraction;
+    (log2 * LCD_HEIGHT / 256).min(LCD_HEIGHT)
+}
+
+// End of file
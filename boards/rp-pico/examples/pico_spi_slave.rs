@@ -0,0 +1,142 @@
+//! # Pico SPI Peripheral Example
+//!
+//! Makes the Pico an SPI peripheral (slave) for another MCU or a single
+//! board computer, using `rp_hal_boards_common::spi_slave`, exchanging
+//! frames of 32 bytes by DMA.
+//!
+//! Wire the master's MOSI to GPIO16, its chip select to GPIO17, its clock to
+//! GPIO18 and its MISO to GPIO19, with the grounds joined. The master uses
+//! SPI mode 3 at up to 10 MHz, and keeps chip select low for each whole
+//! frame of 32 bytes, e.g. from a Raspberry Pi:
+//!
+//! ```text
+//! spidev_test -D /dev/spidev0.0 -s 1000000 -H -O -p "01234567890123456789012345678901"
+//! ```
+//!
+//! Each frame comes back two frames later, with its first byte replaced by a
+//! count of the frames received and its last by the XOR of all its bytes.
+//! The LED toggles every 100 frames.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// The macro for marking our interrupt functions
+use rp_pico::hal::pac::interrupt;
+
+// GPIO traits
+use embedded_hal::digital::v2::ToggleableOutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+use rp_hal_boards_common::dma::{self, Allocator, Irq};
+use rp_hal_boards_common::spi_slave::{Buffers, SpiSlave};
+
+/// Bytes in each frame.
+const FRAME: usize = 32;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks, giving clk_peri the 125 MHz that allows a 10 MHz
+    // master clock
+    let _clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // SPI0's receive, chip select, clock and transmit pins
+    let _mosi = pins.gpio16.into_mode::<hal::gpio::FunctionSpi>();
+    let _cs = pins.gpio17.into_mode::<hal::gpio::FunctionSpi>();
+    let _sck = pins.gpio18.into_mode::<hal::gpio::FunctionSpi>();
+    let _miso = pins.gpio19.into_mode::<hal::gpio::FunctionSpi>();
+
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let rx = allocator.alloc("spi-slave-rx").unwrap();
+    let tx = allocator.alloc("spi-slave-tx").unwrap();
+    let buffers = cortex_m::singleton!(: Buffers<FRAME> = Buffers::new()).unwrap();
+    let mut slave = SpiSlave::new(
+        pac.SPI0,
+        &mut pac.RESETS,
+        rx,
+        tx,
+        embedded_hal::spi::MODE_3,
+        17,
+        buffers,
+        Irq::Irq0,
+    );
+
+    // Enable the DMA interrupt, which keeps the rings going round
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+    }
+
+    let mut count = 0u32;
+    loop {
+        let received = slave.receive(|request, reply| {
+            reply.copy_from_slice(request);
+            reply[0] = count as u8;
+            reply[FRAME - 1] = request.iter().fold(0, |check, &byte| check ^ byte);
+        });
+        if received {
+            count += 1;
+            if count.is_multiple_of(100) {
+                led_pin.toggle().unwrap();
+            }
+        } else if slave.is_out_of_step() {
+            // The master gave up part way through a frame: start afresh
+            slave.restart(&mut pac.RESETS);
+        }
+    }
+}
+
+/// Restarts each DMA channel when it gets to the end of its ring.
+#[interrupt]
+fn DMA_IRQ_0() {
+    dma::dispatch(Irq::Irq0);
+}
+
+// End of file
@@ -0,0 +1,255 @@
+//! # Pico Split Keyboard Example
+//!
+//! An eighteen-key split keyboard made from two Picos running this same
+//! firmware. Each half has a 3x3 matrix wired like `pico_macropad_3x3`: rows on
+//! GPIO5, GPIO6 and GPIO7, columns on GPIO2, GPIO3 and GPIO4.
+//!
+//! Join GPIO16 of the two boards with a single wire, and join their grounds.
+//! Tie GPIO22 to ground on the right half; leave it open on the left half.
+//!
+//! The left half plugs into USB. It sends its own key events and those
+//! received from the right half, shifted three columns over, through one
+//! layout. The right half sends its key events over the link and lights its
+//! LED while the left half reports layer 1 is active.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Import pio crates
+use hal::pio::PIOExt;
+use hal::Clock;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Human Interface Device (HID) Class support
+use usbd_hid::hid_class::HIDClass;
+
+use hal::gpio::DynPin;
+use rp_hal_boards_common::keyboard::{
+    keycode::*,
+    split::{HalfDuplex, Message, Side},
+    Action, Debouncer, Event, Layout, Matrix, NkroReport, NKRO_REPORT_DESCRIPTOR,
+};
+
+/// Link speed, the same on both halves.
+const LINK_BAUD: u32 = 115_200;
+
+/// Columns on each half.
+const HALF_COLS: u8 = 3;
+
+/// Shorthand for the layer table.
+const fn k(code: u8) -> Action {
+    Action::Key(code)
+}
+
+/// The key map, both halves side by side: letters on layer 0, numbers and
+/// arrows on layer 1. The bottom-left key holds layer 1.
+static LAYERS: [[[Action; 6]; 3]; 2] = [
+    [
+        [k(Q), k(W), k(E), k(R), k(T), k(Y)],
+        [k(A), k(S), k(D), k(F), k(G), k(H)],
+        [
+            Action::Layer(1),
+            k(SPACE),
+            k(ENTER),
+            k(BACKSPACE),
+            k(TAB),
+            k(ESCAPE),
+        ],
+    ],
+    [
+        [k(KB_1), k(KB_2), k(KB_3), k(KB_4), k(KB_5), k(KB_6)],
+        [
+            Action::Trans,
+            k(LEFT),
+            k(DOWN),
+            k(UP),
+            k(RIGHT),
+            Action::Trans,
+        ],
+        [
+            Action::Trans,
+            Action::Trans,
+            Action::Trans,
+            Action::Trans,
+            Action::Trans,
+            Action::Trans,
+        ],
+    ],
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    rp_hal_boards_common::delay::calibrate(&clocks.system_clock);
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Which half are we?
+    let strap = pins.gpio22.into_pull_up_input();
+    let side = if strap.is_low().unwrap() {
+        Side::Right
+    } else {
+        Side::Left
+    };
+
+    let cols: [DynPin; 3] = [
+        pins.gpio2.into_pull_up_input().into(),
+        pins.gpio3.into_pull_up_input().into(),
+        pins.gpio4.into_pull_up_input().into(),
+    ];
+    let rows: [DynPin; 3] = [
+        pins.gpio5.into_push_pull_output().into(),
+        pins.gpio6.into_push_pull_output().into(),
+        pins.gpio7.into_push_pull_output().into(),
+    ];
+    let mut matrix = Matrix::new(cols, rows).unwrap();
+    let mut debouncer: Debouncer<3, 3> = Debouncer::default();
+
+    // The link between the halves
+    let _link_pin = pins.gpio16.into_mode::<hal::gpio::FunctionPio0>();
+    let (mut pio, sm0, sm1, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut link = HalfDuplex::new(
+        &mut pio,
+        sm0,
+        sm1,
+        16,
+        LINK_BAUD,
+        clocks.system_clock.freq().to_Hz(),
+        side,
+    );
+
+    if side == Side::Right {
+        let mut led_pin = pins.led.into_push_pull_output();
+        let mut next_scan = timer.get_counter().ticks();
+        loop {
+            if let Some(Message::Layer(layer)) = link.poll() {
+                if layer == 0 {
+                    led_pin.set_low().unwrap();
+                } else {
+                    led_pin.set_high().unwrap();
+                }
+            }
+
+            if timer.get_counter().ticks() < next_scan {
+                continue;
+            }
+            next_scan += 1_000;
+
+            let scan = matrix.scan().unwrap();
+            debouncer.update(&scan, |event| link.send(Message::Key(event)));
+        }
+    }
+
+    let mut layout = Layout::new(&LAYERS);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB HID Class Device driver, providing NKRO Keyboard Reports
+    let mut usb_hid = HIDClass::new(&usb_bus, NKRO_REPORT_DESCRIPTOR, 1);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27db))
+        .manufacturer("Fake company")
+        .product("Split Keyboard")
+        .serial_number("TEST")
+        .device_class(0)
+        .build();
+
+    let mut next_scan = timer.get_counter().ticks();
+    let mut last_report = NkroReport::empty();
+    let mut last_layer = 0;
+    loop {
+        usb_dev.poll(&mut [&mut usb_hid]);
+
+        // Events from the right half land in the right-hand columns
+        while let Some(message) = link.poll() {
+            if let Message::Key(event) = message {
+                layout.event(match event {
+                    Event::Press(row, col) => Event::Press(row, col + HALF_COLS),
+                    Event::Release(row, col) => Event::Release(row, col + HALF_COLS),
+                });
+            }
+        }
+
+        if timer.get_counter().ticks() < next_scan {
+            continue;
+        }
+        next_scan += 1_000;
+
+        let scan = matrix.scan().unwrap();
+        debouncer.update(&scan, |event| layout.event(event));
+
+        if layout.active_layer() != last_layer {
+            last_layer = layout.active_layer();
+            link.send(Message::Layer(last_layer));
+        }
+
+        let report = NkroReport::from_keys(layout.keycodes());
+        if report != last_report && usb_hid.push_raw_input(report.as_bytes()).is_ok() {
+            last_report = report;
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,264 @@
+//! # Pico Stepper Example
+//!
+//! Drives a NEMA17 stepper motor through an A4988 or DRV8825 driver with
+//! `rp_hal_boards_common::stepper`, stepping from the timer's alarm 0 with
+//! smooth acceleration.
+//!
+//! It homes first, moving slowly backwards until the lower limit switch
+//! closes, and then shuttles between the home position and four turns out.
+//! Each limit switch raises a GPIO interrupt that stops the motor at once
+//! when it is heading that way; if one is hit outside homing, the shuttle
+//! stops and the LED lights.
+//!
+//! Wiring, with the driver's microstep pins set for 1/16 steps and its
+//! motor supply separate from the Pico's:
+//!
+//! | Pico   | To                                      |
+//! |--------|-----------------------------------------|
+//! | GPIO2  | Driver STEP                             |
+//! | GPIO3  | Driver DIR                              |
+//! | GPIO4  | Driver ENABLE (active low)              |
+//! | GPIO14 | Lower limit switch, closing to ground   |
+//! | GPIO15 | Upper limit switch, closing to ground   |
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::{Cell, RefCell};
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// Our interrupt macro
+use pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use hal::Clock;
+
+use critical_section::Mutex;
+use hal::gpio::{
+    bank0::{Gpio14, Gpio15, Gpio2, Gpio3},
+    Interrupt::EdgeLow,
+    Pin, PullUpInput, PushPullOutput,
+};
+use hal::timer::Alarm0;
+
+use rp_hal_boards_common::delay;
+use rp_hal_boards_common::stepper::{AlarmStepper, Stepper};
+
+/// Microsteps per turn: 200 full steps, at 1/16 step.
+const STEPS_PER_TURN: i32 = 200 * 16;
+
+/// How far the shuttle goes from home.
+const TRAVEL: i32 = 4 * STEPS_PER_TURN;
+
+/// Where home is, clear of the lower limit switch.
+const HOME: i32 = STEPS_PER_TURN / 8;
+
+/// Top speed while homing, in steps per second.
+const HOMING_SPEED: u32 = 800;
+
+/// Top speed of the shuttle, in steps per second: two turns a second.
+const SPEED: u32 = 2 * STEPS_PER_TURN as u32;
+
+/// Acceleration, in steps per second per second.
+const ACCELERATION: u32 = 4 * STEPS_PER_TURN as u32;
+
+type Motor = AlarmStepper<Pin<Gpio2, PushPullOutput>, Pin<Gpio3, PushPullOutput>, Alarm0>;
+
+type Limits = (Pin<Gpio14, PullUpInput>, Pin<Gpio15, PullUpInput>);
+
+/// The motor, shared with the timer interrupt that steps it.
+static MOTOR: Mutex<RefCell<Option<Motor>>> = Mutex::new(RefCell::new(None));
+
+/// The limit switches, for their interrupt.
+static LIMITS: Mutex<RefCell<Option<Limits>>> = Mutex::new(RefCell::new(None));
+
+/// Which switch last stopped the motor.
+static HIT: Mutex<Cell<Option<Limit>>> = Mutex::new(Cell::new(None));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Limit {
+    Lower,
+    Upper,
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    // The step pulses are timed from here
+    delay::calibrate(&clocks.system_clock);
+
+    // The delay object lets us wait for specified amounts of time (in
+    // milliseconds)
+    let mut cortex_delay =
+        cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The timer's alarm 0 times the steps
+    let mut timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led = pins.led.into_push_pull_output();
+    let _enable = pins
+        .gpio4
+        .into_push_pull_output_in_state(hal::gpio::PinState::Low);
+
+    let mut stepper = Stepper::new(
+        pins.gpio2.into_push_pull_output(),
+        pins.gpio3.into_push_pull_output(),
+    );
+    stepper.set_speed(HOMING_SPEED);
+    stepper.set_acceleration(ACCELERATION);
+    let motor = AlarmStepper::new(stepper, timer.alarm_0().unwrap());
+
+    let lower = pins.gpio14.into_pull_up_input();
+    let upper = pins.gpio15.into_pull_up_input();
+    lower.set_interrupt_enabled(EdgeLow, true);
+    upper.set_interrupt_enabled(EdgeLow, true);
+    let already_home = lower.is_low().unwrap();
+
+    critical_section::with(|cs| {
+        MOTOR.borrow(cs).replace(Some(motor));
+        LIMITS.borrow(cs).replace(Some((lower, upper)));
+    });
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_0);
+        pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    // Home: back up until the lower switch closes, and call that zero
+    if !already_home {
+        with_motor(|motor| motor.move_by(-i32::MAX / 2));
+        wait_until_stopped();
+        if take_hit() != Some(Limit::Lower) {
+            fault(&mut led);
+        }
+    }
+    with_motor(|motor| {
+        motor.stepper().set_position(0);
+        motor.stepper().set_speed(SPEED);
+        motor.move_to(HOME);
+    });
+    wait_until_stopped();
+
+    let mut going_out = true;
+    loop {
+        let target = if going_out { TRAVEL } else { HOME };
+        with_motor(|motor| motor.move_to(target));
+        wait_until_stopped();
+        if take_hit().is_some() {
+            // Something is in the way, or the travel is too long
+            fault(&mut led);
+        }
+
+        going_out = !going_out;
+        cortex_delay.delay_ms(500);
+    }
+}
+
+/// Light the LED and stop for good.
+fn fault(led: &mut impl OutputPin<Error = core::convert::Infallible>) -> ! {
+    led.set_high().unwrap();
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Which switch stopped the motor since the last call, if any.
+fn take_hit() -> Option<Limit> {
+    critical_section::with(|cs| HIT.borrow(cs).take())
+}
+
+/// Run `f` on the motor.
+fn with_motor<R>(f: impl FnOnce(&mut Motor) -> R) -> R {
+    critical_section::with(|cs| f(MOTOR.borrow(cs).borrow_mut().as_mut().unwrap()))
+}
+
+/// Sleep until the motor has stopped, at its target or a limit.
+fn wait_until_stopped() {
+    while with_motor(|motor| motor.stepper().is_running()) {
+        cortex_m::asm::wfi();
+    }
+}
+
+#[interrupt]
+fn TIMER_IRQ_0() {
+    with_motor(|motor| motor.on_alarm());
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    critical_section::with(|cs| {
+        let mut limits = LIMITS.borrow(cs).borrow_mut();
+        let (lower, upper) = limits.as_mut().unwrap();
+        let mut motor = MOTOR.borrow(cs).borrow_mut();
+        let stepper = motor.as_mut().unwrap().stepper();
+        // Only a switch the motor is heading for stops it, so the bounce as
+        // the motor backs off one doesn't
+        if lower.interrupt_status(EdgeLow) {
+            lower.clear_interrupt(EdgeLow);
+            if stepper.speed() < 0 {
+                stepper.halt();
+                HIT.borrow(cs).set(Some(Limit::Lower));
+            }
+        }
+        if upper.interrupt_status(EdgeLow) {
+            upper.clear_interrupt(EdgeLow);
+            if stepper.speed() > 0 {
+                stepper.halt();
+                HIT.borrow(cs).set(Some(Limit::Upper));
+            }
+        }
+    });
+}
+
+// End of file
@@ -0,0 +1,122 @@
+//! # Pico Supply Example
+//!
+//! Reports VSYS and whether USB power is present, with `rp_pico::supply`,
+//! once a second on UART0, so it works on battery power too: connect a
+//! 3.3 V serial adapter's RX to GPIO0 (pin 1) and ground to pin 3, at
+//! 115200 baud.
+//!
+//! The LED is lit while the Pico runs from USB power.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use fugit::RateExtU32;
+use rp_pico::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use hal::uart::{DataBits, StopBits, UartConfig};
+
+use rp_pico::supply::Supply;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Start the clocks and set the pins up according to their function on
+    // this particular board
+    let rp_pico::Board { pins, clocks } = rp_pico::Board::init(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // Lets us wait for fixed periods of time
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    let uart_pins = (
+        // UART TX (characters sent from RP2040) on pin 1 (GPIO0)
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        // UART RX (characters received by RP2040) on pin 2 (GPIO1)
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(115200.Hz(), DataBits::Eight, None, StopBits::One),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    let mut led_pin = pins.led.into_push_pull_output();
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut supply = Supply::new(pins.voltage_monitor, pins.vbus_detect);
+
+    loop {
+        let on_usb = supply.vbus_present();
+        if on_usb {
+            led_pin.set_high().unwrap();
+        } else {
+            led_pin.set_low().unwrap();
+        }
+
+        match supply.vsys_voltage(&mut adc) {
+            Some(millivolts) => {
+                let _ = writeln!(
+                    uart,
+                    "VSYS {}.{:03} V, USB power {}\r",
+                    millivolts / 1000,
+                    millivolts % 1000,
+                    if on_usb { "present" } else { "absent" },
+                );
+            }
+            None => {
+                let _ = writeln!(uart, "VSYS unreadable\r");
+            }
+        }
+
+        delay.delay_ms(1000);
+    }
+}
+
+// End of file
@@ -0,0 +1,304 @@
+//! # Pico Thermal Printer Example
+//!
+//! Prints a summary of the log kept by the `pico_sleep_logger` example on a
+//! cheap ESC/POS thermal printer: a line per day with the lowest, highest and
+//! mean temperature and the lowest VSYS, a bar chart of the latest day's
+//! temperature hour by hour, and a barcode of the day number.
+//!
+//! Connect the printer's RX to GPIO0 (UART0 TX) and its ground to the Pico's
+//! ground, and power it from its own 5-9 V supply: it draws up to 2 A while
+//! printing. Most of these printers run at 9600 baud, and print their baud
+//! rate on the self-test page when the feed button is held at power-on.
+//!
+//! The summary prints at start-up, and again each time a button between
+//! GPIO15 and ground is pressed.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::InputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use fugit::RateExtU32;
+use hal::Clock;
+
+use hal::uart::{DataBits, StopBits, UartConfig};
+use heapless::Deque;
+
+use rp_hal_boards_common::datalog::{Log, RECORD_LEN};
+use rp_hal_boards_common::escpos::{Align, Barcode, Printer, DOTS_58MM};
+
+/// Where `pico_sleep_logger` keeps its log, in bytes from the start of the
+/// `flash_storage` region, and how many sectors it takes.
+const LOG_OFFSET: u32 = 0;
+const LOG_SECTORS: u32 = 14;
+
+/// Days listed, the most recent ones.
+const DAYS: usize = 7;
+
+/// Height of the chart, in dots.
+const CHART_HEIGHT: usize = 64;
+
+/// Bytes per row of the chart, one bit per dot across the paper.
+const CHART_ROW: usize = DOTS_58MM as usize / 8;
+
+/// One day of the log.
+#[derive(Clone, Copy)]
+struct Day {
+    day: u32,
+    samples: u32,
+    /// Temperatures, in hundredths of a degree.
+    min: i16,
+    max: i16,
+    sum: i32,
+    /// Lowest VSYS, in millivolts.
+    vsys: u16,
+    /// Mean temperature per hour, and the samples in each.
+    hour_sums: [i32; 24],
+    hour_samples: [u16; 24],
+}
+
+impl Day {
+    fn new(day: u32) -> Self {
+        Day {
+            day,
+            samples: 0,
+            min: i16::MAX,
+            max: i16::MIN,
+            sum: 0,
+            vsys: u16::MAX,
+            hour_sums: [0; 24],
+            hour_samples: [0; 24],
+        }
+    }
+
+    fn add(&mut self, minute_of_day: u32, centi: i16, vsys: u16) {
+        self.samples += 1;
+        self.min = self.min.min(centi);
+        self.max = self.max.max(centi);
+        self.sum += i32::from(centi);
+        self.vsys = self.vsys.min(vsys);
+        let hour = (minute_of_day / 60) as usize;
+        self.hour_sums[hour] += i32::from(centi);
+        self.hour_samples[hour] += 1;
+    }
+
+    fn mean(&self) -> i16 {
+        (self.sum / self.samples as i32) as i16
+    }
+
+    fn hour_mean(&self, hour: usize) -> Option<i16> {
+        let samples = i32::from(self.hour_samples[hour]);
+        (samples > 0).then(|| (self.hour_sums[hour] / samples) as i16)
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The delay object lets us wait for specified amounts of time (in
+    // milliseconds)
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    let uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(9600.Hz(), DataBits::Eight, None, StopBits::One),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+    let mut printer = Printer::new(uart);
+
+    let button = pins.gpio15.into_pull_up_input();
+    // Fails if `FLASH_STORAGE_SIZE` made the region too small for the log
+    let log = Log::new(LOG_OFFSET, LOG_SECTORS).unwrap();
+
+    loop {
+        // The serial port can't fail, so neither can printing
+        let _ = print_summary(&mut printer, &log);
+
+        // Wait for a press and a release
+        while button.is_high().unwrap() {}
+        delay.delay_ms(20);
+        while button.is_low().unwrap() {}
+        delay.delay_ms(20);
+    }
+}
+
+/// Print the summary of `log`.
+fn print_summary<W>(printer: &mut Printer<W>, log: &Log) -> Result<(), W::Error>
+where
+    W: embedded_hal::serial::Write<u8>,
+{
+    // Only the latest few days are kept
+    let mut days: Deque<Day, DAYS> = Deque::new();
+    log.for_each(|record: &[u8; RECORD_LEN]| {
+        let minutes = u32::from_le_bytes([record[0], record[1], record[2], record[3]]);
+        let centi = i16::from_le_bytes([record[4], record[5]]);
+        let vsys = u16::from_le_bytes([record[6], record[7]]);
+        let day = minutes / (24 * 60);
+        if days.back().map(|last| last.day) != Some(day) {
+            if days.is_full() {
+                days.pop_front();
+            }
+            let _ = days.push_back(Day::new(day));
+        }
+        if let Some(last) = days.back_mut() {
+            last.add(minutes % (24 * 60), centi, vsys);
+        }
+    });
+
+    printer.init()?;
+    printer.align(Align::Centre)?;
+    printer.size(2, 2)?;
+    printer.line("Daily summary")?;
+    printer.size(1, 1)?;
+    printer.feed(1)?;
+
+    let latest = match days.back() {
+        Some(latest) => *latest,
+        None => {
+            printer.line("The log is empty")?;
+            return printer.feed(4);
+        }
+    };
+
+    printer.align(Align::Left)?;
+    printer.bold(true)?;
+    printer.line("Day   Min   Max  Mean VSYS")?;
+    printer.bold(false)?;
+    for day in days.iter() {
+        // Formatting only fails if the port does, and the port returns its
+        // own error on the next write
+        let _ = writeln!(
+            printer,
+            "{:>3} {} {} {} {:>4}",
+            day.day,
+            Celsius(day.min),
+            Celsius(day.max),
+            Celsius(day.mean()),
+            day.vsys
+        );
+    }
+    printer.feed(1)?;
+
+    printer.align(Align::Centre)?;
+    let _ = writeln!(printer, "Day {}, hour by hour", latest.day);
+    let mut chart = [0u8; CHART_HEIGHT * CHART_ROW];
+    draw_chart(&latest, &mut chart);
+    printer.bitmap(DOTS_58MM, &chart)?;
+    let _ = writeln!(
+        printer,
+        "{} to {} C",
+        Celsius(latest.min),
+        Celsius(latest.max)
+    );
+    printer.feed(1)?;
+
+    let mut label: heapless::String<16> = heapless::String::new();
+    let _ = write!(label, "DAY-{}", latest.day);
+    printer.barcode_with_height(Barcode::Code128, label.as_bytes(), 60)?;
+    printer.feed(4)?;
+    printer.flush()
+}
+
+/// Draw a bar per hour of `day`'s temperature, scaled from its lowest to
+/// its highest, into `chart`.
+fn draw_chart(day: &Day, chart: &mut [u8; CHART_HEIGHT * CHART_ROW]) {
+    let bar_width = DOTS_58MM as usize / 24;
+    let range = i32::from(day.max) - i32::from(day.min);
+    for hour in 0..24 {
+        let mean = match day.hour_mean(hour) {
+            Some(mean) => mean,
+            None => continue,
+        };
+        // At least one dot, so the coldest hours still show
+        let height = if range == 0 {
+            CHART_HEIGHT
+        } else {
+            1 + ((i32::from(mean) - i32::from(day.min)) * (CHART_HEIGHT as i32 - 1) / range)
+                as usize
+        };
+        // Leave a two dot gap between bars
+        for x in hour * bar_width..(hour + 1) * bar_width - 2 {
+            for y in CHART_HEIGHT - height..CHART_HEIGHT {
+                chart[y * CHART_ROW + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+    }
+}
+
+/// Hundredths of a degree, shown to a tenth, five characters wide.
+struct Celsius(i16);
+
+impl core::fmt::Display for Celsius {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let tenths = i32::from(self.0) / 10;
+        let sign = if tenths < 0 { "-" } else { "" };
+        let digits = tenths.unsigned_abs();
+        let mut text: heapless::String<8> = heapless::String::new();
+        write!(text, "{}{}.{}", sign, digits / 10, digits % 10)?;
+        write!(f, "{:>5}", text.as_str())
+    }
+}
+
+// End of file
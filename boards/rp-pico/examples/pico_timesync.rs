@@ -0,0 +1,162 @@
+//! # Pico Time Synchronisation Example
+//!
+//! Puts two Picos on a common timebase over a UART, with
+//! `rp_hal_boards_common::timesync`, and has both of them toggle GPIO 2 (and
+//! their LEDs) every 100 ms of common time. Put a scope on GPIO 2 of both
+//! boards to see how closely their edges line up.
+//!
+//! Flash both Picos with this example and wire them up:
+//!
+//! * GPIO 0 (UART0 TX) of each Pico to GPIO 1 (UART0 RX) of the other.
+//! * GND to GND.
+//! * GPIO 15 to GND on one Pico only. That one becomes the leader, whose clock
+//!   is the common time; the other follows it.
+//!
+//! The follower's edges wander until the first exchange completes, then lock
+//! on to the leader's.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use fugit::RateExtU32;
+use rp_pico::hal::prelude::*;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// UART related types
+use hal::uart::{DataBits, StopBits, UartConfig};
+
+use rp_hal_boards_common::timesync::{Follower, Leader};
+
+/// How often the follower pings the leader, in microseconds.
+const PING_INTERVAL_US: u64 = 100_000;
+
+/// How often both boards toggle their output, in microseconds of common time.
+const TICK_US: u64 = 100_000;
+
+/// Which end of the link this board is.
+///
+/// There's only ever one, so the follower's larger state isn't boxed.
+#[allow(clippy::large_enum_variant)]
+enum Role<S> {
+    Leader(Leader<S>),
+    Follower(Follower<S>),
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+    let now = || timer.get_counter().ticks();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let role_pin = pins.gpio15.into_pull_up_input();
+    let mut tick_pin = pins.gpio2.into_push_pull_output();
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    let uart_pins = (
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+    // A fast link keeps the frames short, leaving less room for asymmetry
+    let uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(921_600.Hz(), DataBits::Eight, None, StopBits::One),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    let mut role = if role_pin.is_low().unwrap() {
+        Role::Leader(Leader::new(uart))
+    } else {
+        Role::Follower(Follower::new(uart))
+    };
+
+    let mut next_ping = now();
+    let mut last_tick = None;
+
+    loop {
+        let common = match &mut role {
+            Role::Leader(leader) => {
+                leader.poll(now).unwrap();
+                Some(now())
+            }
+            Role::Follower(follower) => {
+                follower.poll(now);
+                if now() >= next_ping {
+                    next_ping += PING_INTERVAL_US;
+                    follower.ping(now).unwrap();
+                }
+                follower.to_common(now())
+            }
+        };
+
+        // Toggle on every tick boundary of the common time
+        if let Some(common) = common {
+            let tick = common / TICK_US;
+            if last_tick != Some(tick) {
+                last_tick = Some(tick);
+                if tick.is_multiple_of(2) {
+                    tick_pin.set_high().unwrap();
+                    led_pin.set_high().unwrap();
+                } else {
+                    tick_pin.set_low().unwrap();
+                    led_pin.set_low().unwrap();
+                }
+            }
+        }
+    }
+}
+
+// End of file
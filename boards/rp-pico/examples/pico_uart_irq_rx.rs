@@ -0,0 +1,365 @@
+//! # UART IRQ RX Buffer Example
+//!
+//! This application demonstrates how to use the UART Driver to talk to a
+//! serial connection, with both directions buffered under interrupt. In this
+//! example, the IRQ owns the UART and you cannot do any UART access from the
+//! main thread. You can, however, write to a static transmit queue and read
+//! from a static receive queue, and have the IRQ move bytes between those
+//! queues and the UART's FIFOs. The `pico_uart_irq_buffer` example does the
+//! same for the transmit direction only.
+//!
+//! Each line typed into the terminal is sent back with its length once
+//! Enter is pressed, and the LED lights while a line is half typed.
+//!
+//! The pinouts are:
+//!
+//! * GPIO 0 - UART TX (out of the RP2040)
+//! * GPIO 1 - UART RX (in to the RP2040)
+//! * GPIO 2 - UART CTS (in to the RP2040), if `FLOW_CONTROL` is set
+//! * GPIO 3 - UART RTS (out of the RP2040), if `FLOW_CONTROL` is set
+//! * GPIO 25 - An LED we can blink (active high)
+//!
+//! With `FLOW_CONTROL` set, the UART stops the other end sending when the
+//! receive queue and then the FIFO fill up, so nothing is lost however slow
+//! the main thread is. Without it, bytes that don't fit are dropped.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// These are the traits we need from Embedded HAL to treat our hardware
+// objects as generic embedded devices.
+use embedded_hal::{
+    digital::v2::OutputPin,
+    serial::{Read as UartRead, Write as UartWrite},
+};
+
+// The writeln! trait.
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Time handling traits
+use fugit::RateExtU32;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Alias for our HAL crate
+use rp2040_hal as hal;
+
+// We also need this for the UART's clock.
+use hal::Clock;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use hal::pac;
+
+// Our interrupt macro
+use pac::interrupt;
+
+// Some short-cuts to useful types
+use core::cell::RefCell;
+use critical_section::Mutex;
+use heapless::spsc::Queue;
+
+/// Import the GPIO pins we use
+use hal::gpio::pin::bank0::{Gpio0, Gpio1};
+
+// UART related types
+use hal::uart::{DataBits, StopBits, UartConfig};
+
+// The BSP's hardware flow control helper
+use rp_pico::uart::Uart0FlowControl;
+
+/// Use CTS and RTS on GPIO 2 and 3.
+const FLOW_CONTROL: bool = false;
+
+/// Alias the type for our UART pins to make things clearer.
+type UartPins = (
+    hal::gpio::Pin<Gpio0, hal::gpio::Function<hal::gpio::Uart>>,
+    hal::gpio::Pin<Gpio1, hal::gpio::Function<hal::gpio::Uart>>,
+);
+
+/// Alias the type for our UART to make things clearer.
+type Uart = hal::uart::UartPeripheral<hal::uart::Enabled, pac::UART0, UartPins>;
+
+/// This describes the queues we use for UART data, in either direction
+struct UartQueue<const N: usize> {
+    mutex_cell_queue: Mutex<RefCell<Queue<u8, N>>>,
+}
+
+/// This how we transfer the UART into the Interrupt Handler
+static GLOBAL_UART: Mutex<RefCell<Option<Uart>>> = Mutex::new(RefCell::new(None));
+
+/// This is our outbound UART queue. We write to it from the main thread, and
+/// read from it in the UART IRQ.
+static UART_TX_QUEUE: UartQueue<64> = UartQueue {
+    mutex_cell_queue: Mutex::new(RefCell::new(Queue::new())),
+};
+
+/// This is our inbound UART queue. We write to it from the UART IRQ, and read
+/// from it in the main thread.
+static UART_RX_QUEUE: UartQueue<256> = UartQueue {
+    mutex_cell_queue: Mutex::new(RefCell::new(Queue::new())),
+};
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, then answers each line
+/// received on the UART in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins to their default state
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let uart_pins = (
+        // UART TX (characters sent from RP2040) on pin 1 (GPIO0)
+        pins.gpio0.into_mode::<hal::gpio::FunctionUart>(),
+        // UART RX (characters received by RP2040) on pin 2 (GPIO1)
+        pins.gpio1.into_mode::<hal::gpio::FunctionUart>(),
+    );
+
+    // Make a UART on the given pins
+    let mut uart = hal::uart::UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS)
+        .enable(
+            UartConfig::new(115200.Hz(), DataBits::Eight, None, StopBits::One),
+            clocks.peripheral_clock.freq(),
+        )
+        .unwrap();
+
+    // Hand GPIO 2 and 3 to the UART as CTS and RTS, and turn them on
+    let _flow_control = if FLOW_CONTROL {
+        let flow_control = Uart0FlowControl::new(pins.gpio2, pins.gpio3);
+        flow_control.enable(&mut uart, true, true);
+        Some(flow_control)
+    } else {
+        None
+    };
+
+    // Tell the UART to raise its interrupt line on the NVIC when the RX FIFO
+    // has data in it. The IRQ turns the TX interrupt on and off itself,
+    // depending on whether the TX queue has anything in it.
+    uart.enable_rx_interrupt();
+
+    // Now we give away the entire UART peripheral, via the variable
+    // `GLOBAL_UART`. We can no longer access the UART from this main thread.
+    critical_section::with(|cs| {
+        GLOBAL_UART.borrow(cs).replace(Some(uart));
+    });
+
+    unsafe {
+        // Enable the UART interrupt in the *Nested Vectored Interrupt
+        // Controller*, which is part of the Cortex-M0+ core. It stays enabled,
+        // as the receive side needs it all the time.
+        pac::NVIC::unmask(hal::pac::Interrupt::UART0_IRQ);
+    }
+
+    // But we can light an LED.
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // Note we can only write to &UART_TX_QUEUE, because it's not mutable and
+    // `core::fmt::Write` takes mutable references.
+    writeln!(
+        &UART_TX_QUEUE,
+        "uart_irq_rx example started: type a line and press Enter\r"
+    )
+    .unwrap();
+
+    let mut line = heapless::Vec::<u8, 80>::new();
+    loop {
+        // The normal *Wait For Interrupts* (WFI) has a race-hazard - the
+        // interrupt could occur between the CPU checking for interrupts and
+        // the CPU going to sleep. We wait for events (and interrupts), and
+        // then we set an event in every interrupt handler. This ensures we
+        // always wake up correctly.
+        cortex_m::asm::wfe();
+
+        let mut took_any = false;
+        while let Some(byte) = UART_RX_QUEUE.read_byte() {
+            took_any = true;
+            if byte == b'\r' || byte == b'\n' {
+                if !line.is_empty() {
+                    let text = core::str::from_utf8(&line).unwrap_or("(not UTF-8)");
+                    writeln!(&UART_TX_QUEUE, "\r\n{} bytes: {}\r", line.len(), text).unwrap();
+                    line.clear();
+                }
+            } else {
+                // Anything past the end of a long line is dropped
+                let _ = line.push(byte);
+            }
+        }
+        if took_any {
+            // The IRQ may have stopped reading while the queue was full;
+            // kick it so it carries on
+            pac::NVIC::pend(hal::pac::Interrupt::UART0_IRQ);
+        }
+
+        if line.is_empty() {
+            led_pin.set_low().unwrap();
+        } else {
+            led_pin.set_high().unwrap();
+        }
+    }
+}
+
+impl<const N: usize> UartQueue<N> {
+    /// Try and get some data out of the UART Queue. Returns None if queue empty.
+    fn read_byte(&self) -> Option<u8> {
+        critical_section::with(|cs| {
+            let cell_queue = self.mutex_cell_queue.borrow(cs);
+            let mut queue = cell_queue.borrow_mut();
+            queue.dequeue()
+        })
+    }
+
+    /// Peek at the next byte in the queue without removing it.
+    fn peek_byte(&self) -> Option<u8> {
+        critical_section::with(|cs| {
+            let cell_queue = self.mutex_cell_queue.borrow(cs);
+            let queue = cell_queue.borrow_mut();
+            queue.peek().cloned()
+        })
+    }
+
+    /// Put a byte in the queue. Returns false if it was full.
+    fn write_byte(&self, byte: u8) -> bool {
+        critical_section::with(|cs| {
+            let cell_queue = self.mutex_cell_queue.borrow(cs);
+            let mut queue = cell_queue.borrow_mut();
+            queue.enqueue(byte).is_ok()
+        })
+    }
+
+    /// Whether there is room for another byte.
+    fn has_room(&self) -> bool {
+        critical_section::with(|cs| {
+            let cell_queue = self.mutex_cell_queue.borrow(cs);
+            let queue = cell_queue.borrow_mut();
+            queue.len() < queue.capacity()
+        })
+    }
+
+    /// Write some data to the queue, spinning until it all fits.
+    fn write_bytes_blocking(&self, data: &[u8]) {
+        // Go through all the bytes we need to write.
+        for byte in data.iter() {
+            // Keep trying until there is space in the queue. Each attempt
+            // takes and releases the mutex, otherwise the IRQ would never run
+            // and we would never have space!
+            while !self.write_byte(*byte) {}
+            // Kick the IRQ, so it turns the TX interrupt on if it was off.
+            // If the FIFO has space, the interrupt will run straight away.
+            pac::NVIC::pend(hal::pac::Interrupt::UART0_IRQ);
+        }
+    }
+}
+
+impl<const N: usize> core::fmt::Write for &UartQueue<N> {
+    /// This function allows us to `writeln!` on our global static UART queue.
+    /// Note we have an impl for &UartQueue, because our global static queue
+    /// is not mutable and `core::fmt::Write` takes mutable references.
+    fn write_str(&mut self, data: &str) -> core::fmt::Result {
+        self.write_bytes_blocking(data.as_bytes());
+        Ok(())
+    }
+}
+
+#[interrupt]
+fn UART0_IRQ() {
+    // This variable is special. It gets mangled by the `#[interrupt]` macro
+    // into something that we can access without the `unsafe` keyword. It can
+    // do this because this function cannot be called re-entrantly. We know
+    // this because the function's 'real' name is unknown, and hence it cannot
+    // be called from the main thread. We also know that the NVIC will not
+    // re-entrantly call an interrupt.
+    static mut UART: Option<hal::uart::UartPeripheral<hal::uart::Enabled, pac::UART0, UartPins>> =
+        None;
+
+    // This is one-time lazy initialisation. We steal the variable given to us
+    // via `GLOBAL_UART`.
+    if UART.is_none() {
+        critical_section::with(|cs| {
+            *UART = GLOBAL_UART.borrow(cs).take();
+        });
+    }
+
+    // Check if we have a UART to work with
+    if let Some(uart) = UART {
+        // Move received bytes into the queue while it has room. Reading from
+        // the UART also clears the RX interrupt flag.
+        while UART_RX_QUEUE.has_room() {
+            match uart.read() {
+                Ok(byte) => {
+                    let _ = UART_RX_QUEUE.write_byte(byte);
+                }
+                // A byte with a framing or parity error is dropped
+                Err(nb::Error::Other(_)) => {}
+                Err(nb::Error::WouldBlock) => break,
+            }
+        }
+        // With the queue full, leave the rest in the FIFO, where RTS can hold
+        // the other end off, and stop the interrupt until the main thread has
+        // made room
+        if UART_RX_QUEUE.has_room() {
+            uart.enable_rx_interrupt();
+        } else {
+            uart.disable_rx_interrupt();
+        }
+
+        // Check if we have data to transmit
+        while let Some(byte) = UART_TX_QUEUE.peek_byte() {
+            if uart.write(byte).is_ok() {
+                // The UART took it, so pop it off the queue.
+                let _ = UART_TX_QUEUE.read_byte();
+            } else {
+                break;
+            }
+        }
+        // Only ask for the TX interrupt while there is more to send
+        if UART_TX_QUEUE.peek_byte().is_some() {
+            uart.enable_tx_interrupt();
+        } else {
+            uart.disable_tx_interrupt();
+        }
+    }
+
+    // Set an event to ensure the main thread always wakes up, even if it's in
+    // the process of going to sleep.
+    cortex_m::asm::sev();
+}
+
+// End of file
@@ -0,0 +1,211 @@
+//! # Pico Ultrasonic Example
+//!
+//! Measures distance with an HC-SR04 ultrasonic rangefinder ten times a
+//! second, and prints each reading over USB Serial:
+//!
+//! ```text
+//! 1532 us  26.2 cm
+//! ```
+//!
+//! The echo pulse is timed with `rp_hal_boards_common::ultrasonic`: a GPIO
+//! interrupt on each of its edges stamps it with the timer's microsecond
+//! count. Interrupt latency makes the readings jitter by a few microseconds,
+//! about a millimetre; `pico_pio_ultrasonic` times the echo in a PIO state
+//! machine instead.
+//!
+//! Wiring, with the sensor powered from VBUS:
+//!
+//! | Pico   | To                                                  |
+//! |--------|-----------------------------------------------------|
+//! | GPIO2  | HC-SR04 Trig                                        |
+//! | GPIO3  | HC-SR04 Echo, through 1 kΩ, with 2 kΩ to ground     |
+//!
+//! The divider brings the 5 V echo down to 3.3 V. An HC-SR04P powered from
+//! 3V3 can be wired straight to GPIO3.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// Our interrupt macro
+use pac::interrupt;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use critical_section::Mutex;
+use hal::gpio::{
+    bank0::Gpio3,
+    FloatingInput,
+    Interrupt::{EdgeHigh, EdgeLow},
+    Pin,
+};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::delay;
+use rp_hal_boards_common::ultrasonic::{self, EchoTimer, MAX_ECHO_US};
+
+/// Time between readings, in microseconds. At least
+/// `ultrasonic::CYCLE_US`, so the last echo is over.
+const INTERVAL_US: u64 = 100_000;
+
+/// The air temperature, in degrees Celsius, for the speed of sound.
+const CELSIUS: i32 = 20;
+
+type EchoPin = Pin<Gpio3, FloatingInput>;
+
+/// The echo pin, and the timer to stamp its edges, for the interrupt.
+static ECHO_PIN: Mutex<RefCell<Option<(EchoPin, hal::Timer)>>> = Mutex::new(RefCell::new(None));
+
+/// The echo being timed.
+static ECHO: Mutex<RefCell<EchoTimer>> = Mutex::new(RefCell::new(EchoTimer::new()));
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    // The trigger pulse is timed from here
+    delay::calibrate(&clocks.system_clock);
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut trigger = pins.gpio2.into_push_pull_output();
+    let echo = pins.gpio3.into_floating_input();
+    echo.set_interrupt_enabled(EdgeHigh, true);
+    echo.set_interrupt_enabled(EdgeLow, true);
+    critical_section::with(|cs| {
+        ECHO_PIN.borrow(cs).replace(Some((echo, timer)));
+    });
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
+    }
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, 256> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Ultrasonic")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut triggered = false;
+    let mut next = now() + INTERVAL_US;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+        if now() < next {
+            continue;
+        }
+        next += INTERVAL_US;
+
+        // Each reading reports the echo from the trigger before, which has
+        // had the whole interval to come back
+        if triggered {
+            let result = critical_section::with(|cs| *ECHO.borrow(cs).borrow());
+            let _ = match result.echo_us() {
+                Some(echo_us) if echo_us <= MAX_ECHO_US => {
+                    let mm = ultrasonic::distance_mm(echo_us, CELSIUS);
+                    write!(serial, "{} us  {}.{} cm\r\n", echo_us, mm / 10, mm % 10)
+                }
+                Some(_) => write!(serial, "out of range\r\n"),
+                // Still high after the whole interval: not an HC-SR04
+                None if result.started() => write!(serial, "echo too long\r\n"),
+                None => write!(serial, "no echo\r\n"),
+            };
+        }
+
+        critical_section::with(|cs| ECHO.borrow(cs).borrow_mut().arm());
+        ultrasonic::trigger(&mut trigger);
+        triggered = true;
+    }
+}
+
+/// The timer's microsecond count, from the timer shared with the interrupt.
+fn now() -> u64 {
+    critical_section::with(|cs| {
+        let shared = ECHO_PIN.borrow(cs).borrow();
+        shared.as_ref().unwrap().1.get_counter().ticks()
+    })
+}
+
+#[interrupt]
+fn IO_IRQ_BANK0() {
+    critical_section::with(|cs| {
+        let mut echo_pin = ECHO_PIN.borrow(cs).borrow_mut();
+        let (pin, timer) = echo_pin.as_mut().unwrap();
+        let now = timer.get_counter().ticks();
+        let mut echo = ECHO.borrow(cs).borrow_mut();
+        if pin.interrupt_status(EdgeHigh) {
+            pin.clear_interrupt(EdgeHigh);
+            echo.on_edge(true, now);
+        }
+        if pin.interrupt_status(EdgeLow) {
+            pin.clear_interrupt(EdgeLow);
+            echo.on_edge(false, now);
+        }
+    });
+}
+
+// End of file
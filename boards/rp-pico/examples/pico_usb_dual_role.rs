@@ -0,0 +1,192 @@
+//! # Pico USB Dual-Role Example
+//!
+//! Reads a strap pin at boot and presents one of two USB personalities:
+//!
+//! * GPIO15 left open (pulled up): a USB Serial logger that prints a line
+//!   every time the button on GPIO14 is pressed, and an uptime line every few
+//!   seconds.
+//! * GPIO15 tied to ground: a USB keyboard whose only key is the button on
+//!   GPIO14, typing `a`.
+//!
+//! Only the classes for the selected personality are allocated, so the host
+//! sees a plain CDC device or a plain HID device with its own product string
+//! and PID, never both.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::InputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Communications Class Device support
+use usbd_serial::SerialPort;
+
+// USB Human Interface Device (HID) Class support
+use usbd_hid::descriptor::generator_prelude::*;
+use usbd_hid::descriptor::KeyboardReport;
+use usbd_hid::hid_class::HIDClass;
+
+/// HID usage code for the `a` key.
+const KEY_A: u8 = 0x04;
+
+/// The USB classes for whichever personality the strap selected.
+///
+/// There's only ever one, so the logger's larger class isn't boxed.
+#[allow(clippy::large_enum_variant)]
+enum Personality<'a> {
+    Logger(SerialPort<'a, hal::usb::UsbBus>),
+    Keyboard(HIDClass<'a, hal::usb::UsbBus>),
+}
+
+impl<'a> Personality<'a> {
+    fn class(&mut self) -> &mut dyn UsbClass<hal::usb::UsbBus> {
+        match self {
+            Personality::Logger(serial) => serial,
+            Personality::Keyboard(hid) => hid,
+        }
+    }
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let button = pins.gpio14.into_pull_up_input();
+
+    // Read the strap once, after giving the pull-up a moment to settle
+    let strap = pins.gpio15.into_pull_up_input();
+    cortex_m::asm::delay(1000);
+    let keyboard_mode = strap.is_low().unwrap();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Allocate only the classes this personality needs, and describe the
+    // device to match
+    let (mut personality, product, pid, device_class) = if keyboard_mode {
+        let hid = HIDClass::new(&usb_bus, KeyboardReport::desc(), 10);
+        (Personality::Keyboard(hid), "Strap Keyboard", 0x27db, 0)
+    } else {
+        let serial = SerialPort::new(&usb_bus);
+        // from: https://www.usb.org/defined-class-codes
+        (Personality::Logger(serial), "Strap Logger", 0x27dd, 2)
+    };
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, pid))
+        .manufacturer("Fake company")
+        .product(product)
+        .serial_number("TEST")
+        .device_class(device_class)
+        .build();
+
+    let mut was_pressed = false;
+    let mut next_tick = timer.get_counter().ticks();
+    let mut next_uptime = next_tick;
+    loop {
+        usb_dev.poll(&mut [personality.class()]);
+
+        // Sample the button once a millisecond, which also debounces it
+        let now = timer.get_counter().ticks();
+        if now < next_tick {
+            continue;
+        }
+        next_tick += 1_000;
+
+        let pressed = button.is_low().unwrap();
+        let changed = pressed != was_pressed;
+        was_pressed = pressed;
+
+        match &mut personality {
+            Personality::Logger(serial) => {
+                let mut text: heapless::String<64> = heapless::String::new();
+                if changed && pressed {
+                    let _ = write!(text, "{} us: button pressed\r\n", now);
+                }
+                if now >= next_uptime {
+                    next_uptime += 5_000_000;
+                    let _ = write!(text, "uptime {} s\r\n", now / 1_000_000);
+                }
+                let _ = serial.write(text.as_bytes());
+            }
+            Personality::Keyboard(hid) => {
+                if changed {
+                    let mut keycodes = [0u8; 6];
+                    if pressed {
+                        keycodes[0] = KEY_A;
+                    }
+                    let _ = hid.push_input(&KeyboardReport {
+                        modifier: 0,
+                        reserved: 0,
+                        leds: 0,
+                        keycodes,
+                    });
+                }
+            }
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,227 @@
+//! # Pico USB Joystick Mouse Example
+//!
+//! A USB mouse steered by an analogue thumbstick on the ADC, with two
+//! buttons, and a jiggler that nudges the pointer now and then to keep the
+//! host awake.
+//!
+//! Wire the stick's X output to GPIO26 (ADC0) and Y output to GPIO27 (ADC1),
+//! powered from 3V3 and AGND, as for `pico_adc_gamepad`, whose calibration
+//! this example uses if it has stored one. The left and right buttons go
+//! from GPIO2 and GPIO3 to ground, and a switch from GPIO4 to ground turns
+//! the jiggler on.
+//!
+//! The pointer speeds up with the square of the stick's deflection, so small
+//! movements stay precise, and fractions of a pixel carry over from one
+//! report to the next.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO and ADC traits
+use embedded_hal::adc::OneShot;
+use embedded_hal::digital::v2::InputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Human Interface Device (HID) Class support
+use usbd_hid::hid_class::HIDClass;
+
+use rp_hal_boards_common::joystick::{Calibration, DEFAULT_DEAD_ZONE};
+use rp_hal_boards_common::settings::{default_offset, Store};
+
+/// A mouse with three buttons and relative X, Y and wheel movement, in the
+/// same layout as the HID boot protocol's mouse report.
+const MOUSE_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x02, // Usage (Mouse)
+    0xA1, 0x01, // Collection (Application)
+    0x09, 0x01, //   Usage (Pointer)
+    0xA1, 0x00, //   Collection (Physical)
+    0x05, 0x09, //     Usage Page (Button)
+    0x19, 0x01, //     Usage Minimum (1)
+    0x29, 0x03, //     Usage Maximum (3)
+    0x15, 0x00, //     Logical Minimum (0)
+    0x25, 0x01, //     Logical Maximum (1)
+    0x75, 0x01, //     Report Size (1)
+    0x95, 0x03, //     Report Count (3)
+    0x81, 0x02, //     Input (Data, Variable, Absolute)
+    0x75, 0x05, //     Report Size (5)
+    0x95, 0x01, //     Report Count (1)
+    0x81, 0x01, //     Input (Constant): padding to a byte
+    0x05, 0x01, //     Usage Page (Generic Desktop)
+    0x09, 0x30, //     Usage (X)
+    0x09, 0x31, //     Usage (Y)
+    0x09, 0x38, //     Usage (Wheel)
+    0x15, 0x81, //     Logical Minimum (-127)
+    0x25, 0x7F, //     Logical Maximum (127)
+    0x75, 0x08, //     Report Size (8)
+    0x95, 0x03, //     Report Count (3)
+    0x81, 0x06, //     Input (Data, Variable, Relative)
+    0xC0, //   End Collection
+    0xC0, // End Collection
+];
+
+/// Milliseconds between reports, which is also the HID polling interval.
+const REPORT_MS: u8 = 10;
+
+/// Pixels per report at full deflection.
+const MAX_SPEED: i32 = 12;
+
+/// Seconds the stick has to be idle before the jiggler nudges the pointer.
+const JIGGLE_AFTER_S: u64 = 60;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let left = pins.gpio2.into_pull_up_input();
+    let right = pins.gpio3.into_pull_up_input();
+    let jiggle = pins.gpio4.into_pull_up_input();
+
+    // Enable the ADC peripheral, and configure the stick's pins as inputs
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut x_pin = pins.gpio26.into_floating_input();
+    let mut y_pin = pins.gpio27.into_floating_input();
+
+    // Use the gamepad example's stored calibration, if there is a valid one
+    let store = Store::new(default_offset());
+    let mut buf = [0u8; 16];
+    let calibration = store
+        .load(&mut buf)
+        .and_then(|len| Calibration::<2>::read_from(&buf[..len]))
+        .unwrap_or_default();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB HID Class Device driver, providing mouse reports
+    let mut usb_hid = HIDClass::new(&usb_bus, MOUSE_REPORT_DESCRIPTOR, REPORT_MS);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27da))
+        .manufacturer("Fake company")
+        .product("Joystick Mouse")
+        .serial_number("TEST")
+        .device_class(0)
+        .build();
+
+    let mut next_report = timer.get_counter().ticks();
+    let mut last_active = next_report;
+    let mut jiggle_dir = 1i8;
+    let mut last_buttons = 0u8;
+    // Movement still to send, in 1/256ths of a pixel
+    let mut residue = [0i32; 2];
+    loop {
+        usb_dev.poll(&mut [&mut usb_hid]);
+
+        let now = timer.get_counter().ticks();
+        if now < next_report {
+            continue;
+        }
+        next_report += u64::from(REPORT_MS) * 1_000;
+
+        let raw: [u16; 2] = [adc.read(&mut x_pin).unwrap(), adc.read(&mut y_pin).unwrap()];
+        let stick = calibration.scale(&raw, DEFAULT_DEAD_ZONE);
+
+        let mut movement = [0i8; 2];
+        for ((movement, residue), &deflection) in movement
+            .iter_mut()
+            .zip(residue.iter_mut())
+            .zip(stick.iter())
+        {
+            let deflection = i32::from(deflection);
+            *residue += deflection * deflection.abs() * MAX_SPEED * 256 / (127 * 127);
+            // Send whole pixels, and keep the fraction for next time
+            let pixels = (*residue / 256).clamp(-127, 127);
+            *residue -= pixels * 256;
+            *movement = pixels as i8;
+        }
+
+        let mut buttons = 0u8;
+        if left.is_low().unwrap() {
+            buttons |= 1 << 0;
+        }
+        if right.is_low().unwrap() {
+            buttons |= 1 << 1;
+        }
+
+        if stick != [0, 0] || buttons != 0 {
+            last_active = now;
+        } else if jiggle.is_low().unwrap() && now - last_active >= JIGGLE_AFTER_S * 1_000_000 {
+            // One pixel right, then back again a minute later
+            movement[0] = jiggle_dir;
+            jiggle_dir = -jiggle_dir;
+            last_active = now;
+        }
+
+        // Only report changes: a still stick with unchanged buttons says
+        // nothing
+        if movement == [0, 0] && buttons == last_buttons {
+            continue;
+        }
+        let report = [buttons, movement[0] as u8, movement[1] as u8, 0];
+        if usb_hid.push_raw_input(&report).is_ok() {
+            last_buttons = buttons;
+        }
+    }
+}
+
+// End of file
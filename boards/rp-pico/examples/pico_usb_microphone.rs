@@ -0,0 +1,181 @@
+//! # Pico USB Microphone Example
+//!
+//! Makes the Pico a USB microphone, using `rp_hal_boards_common::usb_audio`:
+//! the host sees a 16 kHz, 16 bit mono sound card input, and can record or
+//! measure whatever the Pico samples with its usual tools, e.g.
+//! `arecord -f S16_LE -r 16000 -c 1` on Linux.
+//!
+//! By default the sound comes from ADC0 on GPIO26: bias an electret capsule
+//! and amplifier, or any other signal, to about 1.65 V and wire it there.
+//! Set `PDM` to `true` to use a PDM MEMS microphone instead, with its data on
+//! GPIO2, its clock on GPIO3 and its L/R select tied low.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// The macro for marking our interrupt functions
+use rp_pico::hal::pac::interrupt;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use hal::pio::PIOExt;
+use hal::Clock;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::adc_stream::{AdcStream, Buffers, Continuous, BLOCK_LEN};
+use rp_hal_boards_common::dma::{self, Allocator, Irq};
+use rp_hal_boards_common::pio_pdm::PdmMic;
+use rp_hal_boards_common::usb_audio::Microphone;
+
+/// Use a PDM microphone on GPIO2 and GPIO3 rather than the ADC.
+const PDM: bool = false;
+
+/// The sample rate sent to the host.
+const SAMPLE_RATE: u32 = 16_000;
+
+/// The PDM microphone's clock, decimated by 128 down to [`SAMPLE_RATE`].
+const PDM_CLOCK_HZ: u32 = 2_048_000;
+
+/// Where the sound comes from.
+enum Input {
+    Adc(Continuous),
+    Pdm(PdmMic<pac::PIO0, hal::pio::SM0>),
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut input = if PDM {
+        let _data = pins.gpio2.into_mode::<hal::gpio::FunctionPio0>();
+        let _clock = pins.gpio3.into_mode::<hal::gpio::FunctionPio0>();
+        let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+        Input::Pdm(PdmMic::new(
+            &mut pio,
+            sm0,
+            2,
+            3,
+            PDM_CLOCK_HZ,
+            clocks.system_clock.freq().to_Hz(),
+            PDM_CLOCK_HZ / SAMPLE_RATE,
+        ))
+    } else {
+        // Two DMA channels take turns filling blocks, so no sample is missed
+        let _adc0 = pins.gpio26.into_floating_input();
+        let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+        let first = allocator.alloc("microphone-a").unwrap();
+        let second = allocator.alloc("microphone-b").unwrap();
+        let stream = AdcStream::new(pac.ADC, &mut pac.RESETS, first, 0, SAMPLE_RATE);
+        let buffers = cortex_m::singleton!(: Buffers = Buffers::new()).unwrap();
+        Input::Adc(stream.continuous(second, buffers, Irq::Irq0))
+    };
+
+    // Enable the DMA interrupt
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+    }
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Audio Class microphone
+    let mut mic = Microphone::new(&usb_bus, SAMPLE_RATE);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Pico Microphone")
+        .serial_number("TEST")
+        .device_class(0)
+        .build();
+
+    loop {
+        usb_dev.poll(&mut [&mut mic]);
+
+        match &mut input {
+            Input::Adc(adc) => {
+                // A block fills every 64 ms, which is as much as the
+                // microphone's queue takes in one go
+                adc.read(|block| {
+                    let mut pcm = [0i16; BLOCK_LEN];
+                    for (pcm, &sample) in pcm.iter_mut().zip(block.iter()) {
+                        // 12 bits around mid-scale, to 16 bits around zero
+                        *pcm = (sample as i16 - 2048) << 4;
+                    }
+                    mic.write(&pcm);
+                });
+            }
+            Input::Pdm(pdm) => {
+                // Keep the microphone's FIFO drained; USB polls are short
+                // enough to fit in between
+                let mut pcm = [0i16; 32];
+                let len = pdm.read(&mut pcm);
+                mic.write(&pcm[..len]);
+            }
+        }
+    }
+}
+
+/// Tells the ADC stream when each block is full.
+#[interrupt]
+fn DMA_IRQ_0() {
+    dma::dispatch(Irq::Irq0);
+}
+
+// End of file
@@ -0,0 +1,245 @@
+//! # Pico USB Network Example
+//!
+//! Makes the Pico a USB network adapter, using
+//! `rp_hal_boards_common::usb_net`, and serves a web page on it that shows
+//! the ADC readings and turns the LED on and off.
+//!
+//! The Pico is 192.168.7.1 and hands the host 192.168.7.2 over DHCP, so once
+//! it is plugged in, browse to <http://192.168.7.1/>. The host's driver for
+//! CDC-ECM comes with Linux and macOS, not with Windows.
+//!
+//! Build with `--features rp-hal-boards-common/usb-net`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO and ADC traits
+use embedded_hal::adc::OneShot;
+use embedded_hal::digital::v2::{OutputPin, StatefulOutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::usb_net::smoltcp::{
+    iface::{Config, Interface, SocketSet, SocketStorage},
+    socket::{tcp, udp},
+    time::Instant,
+    wire::{EthernetAddress, IpAddress, IpCidr, IpEndpoint},
+};
+use rp_hal_boards_common::usb_net::{dhcp_reply, Ecm, DHCP_REPLY_LEN};
+
+/// The host's end of the link.
+const HOST_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+
+/// The Pico's end of the link.
+const DEVICE_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x02];
+
+/// The Pico's address.
+const DEVICE_IP: [u8; 4] = [192, 168, 7, 1];
+
+/// The address the host gets over DHCP.
+const HOST_IP: [u8; 4] = [192, 168, 7, 2];
+
+/// Connections the web server takes at once.
+const HTTP_SOCKETS: usize = 2;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The timer gives smoltcp its clock
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // Enable the ADC peripheral, the temperature sensor and ADC0
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut temperature_sensor = adc.enable_temp_sensor();
+    let mut adc_pin_0 = pins.gpio26.into_floating_input();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB network adapter
+    let mut ecm = Ecm::new(&usb_bus, HOST_MAC);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("USB network")
+        .serial_number("TEST")
+        .composite_with_iads()
+        .build();
+
+    let now = || Instant::from_micros(timer.get_counter().ticks() as i64);
+
+    let config = Config::new(EthernetAddress(DEVICE_MAC).into());
+    let mut iface = Interface::new(config, &mut ecm, now());
+    iface.update_ip_addrs(|addrs| {
+        let [a, b, c, d] = DEVICE_IP;
+        addrs
+            .push(IpCidr::new(IpAddress::v4(a, b, c, d), 24))
+            .unwrap();
+    });
+
+    let mut storage: [SocketStorage; HTTP_SOCKETS + 1] = Default::default();
+    let mut sockets = SocketSet::new(&mut storage[..]);
+
+    let mut dhcp_rx_meta = [udp::PacketMetadata::EMPTY; 2];
+    let mut dhcp_rx = [0u8; 1024];
+    let mut dhcp_tx_meta = [udp::PacketMetadata::EMPTY; 2];
+    let mut dhcp_tx = [0u8; 1024];
+    let dhcp = sockets.add(udp::Socket::new(
+        udp::PacketBuffer::new(&mut dhcp_rx_meta[..], &mut dhcp_rx[..]),
+        udp::PacketBuffer::new(&mut dhcp_tx_meta[..], &mut dhcp_tx[..]),
+    ));
+
+    let mut http_rx = [[0u8; 512]; HTTP_SOCKETS];
+    let mut http_tx = [[0u8; 1024]; HTTP_SOCKETS];
+    let mut http = heapless::Vec::<_, HTTP_SOCKETS>::new();
+    for (rx, tx) in http_rx.iter_mut().zip(http_tx.iter_mut()) {
+        let socket = tcp::Socket::new(
+            tcp::SocketBuffer::new(&mut rx[..]),
+            tcp::SocketBuffer::new(&mut tx[..]),
+        );
+        let _ = http.push(sockets.add(socket));
+    }
+
+    let mut request = [0u8; 512];
+    let mut page: heapless::String<1024> = heapless::String::new();
+    loop {
+        usb_dev.poll(&mut [&mut ecm]);
+        iface.poll(now(), &mut ecm, &mut sockets);
+
+        // Give the host its address
+        let socket = sockets.get_mut::<udp::Socket>(dhcp);
+        if !socket.is_open() {
+            socket.bind(67).unwrap();
+        }
+        if let Ok((len, _)) = socket.recv_slice(&mut request) {
+            let mut reply = [0u8; DHCP_REPLY_LEN];
+            if let Some(len) = dhcp_reply(&request[..len], DEVICE_IP, HOST_IP, &mut reply) {
+                let broadcast = IpEndpoint::new(IpAddress::v4(255, 255, 255, 255), 68);
+                let _ = socket.send_slice(&reply[..len], broadcast);
+            }
+        }
+
+        // Answer every request that has come in with the page, then hang up
+        for &handle in &http {
+            let socket = sockets.get_mut::<tcp::Socket>(handle);
+            if !socket.is_open() {
+                socket.listen(80).unwrap();
+            }
+            if !socket.can_recv() {
+                continue;
+            }
+            let len = socket.recv_slice(&mut request).unwrap_or(0);
+            if !socket.may_send() {
+                // Already answered: this is the rest of the request
+                continue;
+            }
+            if request[..len].starts_with(b"POST /led") {
+                if led_pin.is_set_high().unwrap() {
+                    led_pin.set_low().unwrap();
+                } else {
+                    led_pin.set_high().unwrap();
+                }
+                // Back to the page, so reloading it doesn't toggle again
+                let _ = socket.send_slice(
+                    b"HTTP/1.0 303 See Other\r\nLocation: /\r\nConnection: close\r\n\r\n",
+                );
+                socket.close();
+                continue;
+            }
+
+            let adc0: u16 = adc.read(&mut adc_pin_0).unwrap();
+            let raw: u16 = adc.read(&mut temperature_sensor).unwrap();
+            // From the RP2040 datasheet: 0.706 V at 27 C, falling 1.721 mV/C
+            let volts = f32::from(raw) * 3.3 / 4096.0;
+            let celsius = 27.0 - (volts - 0.706) / 0.001721;
+            let led = if led_pin.is_set_high().unwrap() {
+                "on"
+            } else {
+                "off"
+            };
+
+            page.clear();
+            let _ = write!(
+                page,
+                "HTTP/1.0 200 OK\r\n\
+                 Content-Type: text/html\r\n\
+                 Connection: close\r\n\r\n\
+                 <!DOCTYPE html><html><head><title>Pico</title></head><body>\
+                 <h1>Raspberry Pi Pico</h1>\
+                 <p>ADC0: {} of 4095</p>\
+                 <p>Temperature: {:.1} &deg;C</p>\
+                 <form method=\"post\" action=\"/led\">\
+                 <p>The LED is {}. <button>Toggle</button></p>\
+                 </form></body></html>",
+                adc0, celsius, led
+            );
+            let _ = socket.send_slice(page.as_bytes());
+            socket.close();
+        }
+    }
+}
+
+// End of file
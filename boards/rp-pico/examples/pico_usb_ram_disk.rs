@@ -0,0 +1,294 @@
+//! # Pico USB RAM Disk Example
+//!
+//! Shows up on the host as a 64 KiB USB flash drive, served from the Pico's
+//! RAM with the USB Mass Storage class (SCSI over Bulk-Only Transport).
+//!
+//! The disk is formatted as FAT12 at start-up and holds a single
+//! `README.TXT`. Files can be added, edited and deleted from the host like on
+//! any other drive, but everything is lost on reset. To keep a configuration
+//! file, read it out of the disk image after the host has written it, and
+//! store it in flash.
+//!
+//! The on-board LED lights while the host is reading or writing.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::OutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Mass Storage Class support
+use usbd_storage::subclass::scsi::{Scsi, ScsiCommand};
+use usbd_storage::subclass::Command;
+use usbd_storage::transport::bbb::{BulkOnly, BulkOnlyError};
+use usbd_storage::transport::TransportError;
+
+/// Size of a disk block, as every host expects.
+const BLOCK_SIZE: usize = 512;
+
+/// Number of blocks on the disk.
+const BLOCKS: usize = 128;
+
+/// The disk image.
+static mut DISK: [u8; BLOCKS * BLOCK_SIZE] = [0; BLOCKS * BLOCK_SIZE];
+
+/// Contents of the one file on a freshly formatted disk.
+const README: &[u8] = b"This drive lives in the RAM of a Raspberry Pi Pico.\r\n\
+Anything you save here is gone after a reset.\r\n";
+
+/// The SCSI INQUIRY response: a removable direct-access device.
+const INQUIRY: [u8; 36] = [
+    0x00, // Direct-access block device
+    0x80, // Removable
+    0x04, // SPC-2
+    0x02, // Response data format
+    0x20, // Additional length
+    0x00, 0x00, 0x00, // Flags
+    b'r', b'p', b'-', b'r', b's', b' ', b' ', b' ', // Vendor
+    b'P', b'i', b'c', b'o', b' ', b'R', b'A', b'M', // Product
+    b' ', b'D', b'i', b's', b'k', b' ', b' ', b' ', //
+    b'1', b'.', b'0', b' ', // Revision
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // Safety: this is the only reference ever taken to DISK
+    let mut disk = RamDisk {
+        image: unsafe { &mut *core::ptr::addr_of_mut!(DISK) },
+        progress: 0,
+    };
+    disk.format();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the Mass Storage class, with one logical unit and a buffer for
+    // one block
+    let mut transport_buffer = [0u8; BLOCK_SIZE];
+    let mut scsi = Scsi::new(&usb_bus, 64, 0, &mut transport_buffer[..]).unwrap();
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("RAM disk")
+        .serial_number("TEST")
+        .build();
+
+    loop {
+        if !usb_dev.poll(&mut [&mut scsi]) {
+            continue;
+        }
+        let _ = scsi.poll(|command| {
+            let _ = disk.process(command);
+        });
+
+        if disk.progress != 0 {
+            led_pin.set_high().unwrap();
+        } else {
+            led_pin.set_low().unwrap();
+        }
+    }
+}
+
+/// A block device in RAM.
+struct RamDisk {
+    image: &'static mut [u8; BLOCKS * BLOCK_SIZE],
+    /// Bytes of the current read or write transferred so far.
+    progress: usize,
+}
+
+impl RamDisk {
+    /// Lay out an empty FAT12 file system with one file on it.
+    ///
+    /// Block 0 is the boot sector, block 1 the only FAT, block 2 the root
+    /// directory with room for 16 entries, and the data area starts at block 3
+    /// with cluster 2. Clusters are one block each.
+    fn format(&mut self) {
+        self.image.fill(0);
+
+        let boot = &mut self.image[..BLOCK_SIZE];
+        boot[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+        boot[3..11].copy_from_slice(b"MSWIN4.1");
+        boot[11..13].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+        boot[13] = 1; // Sectors per cluster
+        boot[14..16].copy_from_slice(&1u16.to_le_bytes()); // Reserved sectors
+        boot[16] = 1; // Number of FATs
+        boot[17..19].copy_from_slice(&16u16.to_le_bytes()); // Root entries
+        boot[19..21].copy_from_slice(&(BLOCKS as u16).to_le_bytes());
+        boot[21] = 0xF8; // Fixed media
+        boot[22..24].copy_from_slice(&1u16.to_le_bytes()); // Sectors per FAT
+        boot[24..26].copy_from_slice(&32u16.to_le_bytes()); // Sectors per track
+        boot[26..28].copy_from_slice(&1u16.to_le_bytes()); // Heads
+        boot[36] = 0x80; // Drive number
+        boot[38] = 0x29; // Extended boot signature
+        boot[39..43].copy_from_slice(&0x2040_0001u32.to_le_bytes()); // Serial
+        boot[43..54].copy_from_slice(b"PICO RAM   ");
+        boot[54..62].copy_from_slice(b"FAT12   ");
+        boot[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+        // Entries 0 and 1 are reserved, entry 2 (README.TXT) ends the chain
+        let fat = &mut self.image[BLOCK_SIZE..2 * BLOCK_SIZE];
+        fat[0..5].copy_from_slice(&[0xF8, 0xFF, 0xFF, 0xFF, 0x0F]);
+
+        let root = &mut self.image[2 * BLOCK_SIZE..3 * BLOCK_SIZE];
+        root[0..11].copy_from_slice(b"PICO RAM   ");
+        root[11] = 0x08; // Volume label
+        let file = &mut root[32..64];
+        file[0..11].copy_from_slice(b"README  TXT");
+        file[11] = 0x20; // Archive
+        file[26..28].copy_from_slice(&2u16.to_le_bytes()); // First cluster
+        file[28..32].copy_from_slice(&(README.len() as u32).to_le_bytes());
+
+        self.image[3 * BLOCK_SIZE..3 * BLOCK_SIZE + README.len()].copy_from_slice(README);
+    }
+
+    /// Answer one SCSI command from the host.
+    ///
+    /// Reads and writes can span several calls; `progress` keeps track of how
+    /// much has been transferred so far.
+    fn process(
+        &mut self,
+        mut command: Command<ScsiCommand, Scsi<BulkOnly<hal::usb::UsbBus, &mut [u8]>>>,
+    ) -> Result<(), TransportError<BulkOnlyError>> {
+        match command.kind {
+            ScsiCommand::TestUnitReady => {
+                command.pass();
+            }
+            ScsiCommand::Inquiry { .. } => {
+                command.try_write_data_all(&INQUIRY)?;
+                command.pass();
+            }
+            ScsiCommand::RequestSense { .. } => {
+                // No sense data: everything is fine
+                let mut sense = [0u8; 18];
+                sense[0] = 0x70;
+                sense[7] = 10;
+                command.try_write_data_all(&sense)?;
+                command.pass();
+            }
+            ScsiCommand::ReadCapacity10 => {
+                let mut data = [0u8; 8];
+                data[0..4].copy_from_slice(&(BLOCKS as u32 - 1).to_be_bytes());
+                data[4..8].copy_from_slice(&(BLOCK_SIZE as u32).to_be_bytes());
+                command.try_write_data_all(&data)?;
+                command.pass();
+            }
+            ScsiCommand::ReadCapacity16 { .. } => {
+                let mut data = [0u8; 32];
+                data[0..8].copy_from_slice(&(BLOCKS as u64 - 1).to_be_bytes());
+                data[8..12].copy_from_slice(&(BLOCK_SIZE as u32).to_be_bytes());
+                command.try_write_data_all(&data)?;
+                command.pass();
+            }
+            ScsiCommand::ReadFormatCapacities { .. } => {
+                let mut data = [0u8; 12];
+                data[3] = 8; // Capacity list length
+                data[4..8].copy_from_slice(&(BLOCKS as u32).to_be_bytes());
+                data[8] = 0x02; // Formatted media
+                data[10..12].copy_from_slice(&(BLOCK_SIZE as u16).to_be_bytes());
+                command.try_write_data_all(&data)?;
+                command.pass();
+            }
+            ScsiCommand::ModeSense6 { .. } => {
+                // No mode pages, not write protected
+                command.try_write_data_all(&[0x03, 0x00, 0x00, 0x00])?;
+                command.pass();
+            }
+            ScsiCommand::Read { lba, len } => {
+                let (start, end) = self.range(lba as usize, len as usize);
+                if start + self.progress < end {
+                    let count = command.write_data(&self.image[start + self.progress..end])?;
+                    self.progress += count;
+                } else {
+                    self.progress = 0;
+                    command.pass();
+                }
+            }
+            ScsiCommand::Write { lba, len } => {
+                let (start, end) = self.range(lba as usize, len as usize);
+                if start + self.progress < end {
+                    let count = command.read_data(&mut self.image[start + self.progress..end])?;
+                    self.progress += count;
+                }
+                if start + self.progress >= end {
+                    self.progress = 0;
+                    command.pass();
+                }
+            }
+            _ => {
+                command.fail();
+            }
+        }
+        Ok(())
+    }
+
+    /// The byte range of `len` blocks from `lba`, clamped to the disk.
+    fn range(&self, lba: usize, len: usize) -> (usize, usize) {
+        let start = (lba * BLOCK_SIZE).min(self.image.len());
+        let end = ((lba + len) * BLOCK_SIZE).min(self.image.len());
+        (start, end)
+    }
+}
+
+// End of file
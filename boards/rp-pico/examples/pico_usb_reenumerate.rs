@@ -0,0 +1,128 @@
+//! # Pico USB Re-enumeration Example
+//!
+//! Creates a USB Serial device that survives its own resets: the host sees the
+//! serial port disappear and come back every time, instead of being left with
+//! a stale port that only a replug fixes.
+//!
+//! Type `w` to restart through the watchdog or `s` for a software reset. On
+//! start-up the device always forces the host to enumerate it afresh with
+//! `rp_hal_boards_common::usb::force_reenumeration`, and the software reset
+//! detaches from the bus first with `usb::soft_reset`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// Pull in any important traits
+use embedded_hal::watchdog::WatchdogEnable;
+use fugit::ExtU32;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Communications Class Device support
+use usbd_serial::SerialPort;
+
+use rp_hal_boards_common::{delay, usb};
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    delay::calibrate(&clocks.system_clock);
+
+    // Make sure the host notices we restarted, however we got here
+    usb::force_reenumeration(&pac.USBCTRL_REGS, &mut pac.RESETS, usb::DEFAULT_DETACH_MS);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial = SerialPort::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Serial port")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    loop {
+        if !usb_dev.poll(&mut [&mut serial]) {
+            continue;
+        }
+
+        let mut buf = [0u8; 64];
+        let count = match serial.read(&mut buf) {
+            Ok(count) => count,
+            Err(_) => continue,
+        };
+
+        for &byte in &buf[..count] {
+            match byte {
+                b'w' => {
+                    let _ = serial.write(b"watchdog reset\r\n");
+                    // Let the watchdog bite; the new firmware's
+                    // force_reenumeration does the rest
+                    watchdog.start(1.millis());
+                    loop {
+                        cortex_m::asm::nop();
+                    }
+                }
+                b's' => {
+                    let _ = serial.write(b"software reset\r\n");
+                    // Note (safety): the USB controller is out of reset
+                    unsafe { usb::soft_reset(usb::DEFAULT_DETACH_MS) };
+                }
+                _ => {
+                    let _ = serial.write(b"type w or s to reset\r\n");
+                }
+            }
+        }
+    }
+}
+
+// End of file
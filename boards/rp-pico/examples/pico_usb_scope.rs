@@ -0,0 +1,219 @@
+//! # Pico USB Scope Example
+//!
+//! Turns the Pico into a simple USB oscilloscope: it captures bursts of
+//! samples from ADC0 (GPIO 26) and streams them over USB Serial as binary
+//! frames, for a program on the host to plot.
+//!
+//! Each burst is one frame in the format of `rp_hal_boards_common::scope`: a
+//! `SC` sync word, a header with a sequence number, the sample rate and the
+//! number of samples, the samples as little-endian `u16`s and a CRC-16. See
+//! that module's documentation for the exact layout.
+//!
+//! The host controls the capture by sending lines of text, ending in a
+//! newline, which are not echoed:
+//!
+//! * `rate 20000` - sample at 20 kHz, anywhere from 733 Hz to 500 kHz
+//! * `samples 512` - put 512 samples in each burst, from 16 to 1024
+//! * `stop` - stop capturing
+//! * `run` - capture burst after burst, the default
+//! * `single` - capture one more burst, then stop
+//!
+//! The USB device isn't polled while a burst is captured, so long, slow
+//! bursts make the port sluggish: 1024 samples at 1 kHz take a second.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use heapless::Vec;
+use rp_hal_boards_common::adc_stream::{AdcStream, MAX_SAMPLE_RATE, MIN_SAMPLE_RATE};
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::dma::Allocator;
+use rp_hal_boards_common::scope::{self, Header};
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 512;
+
+/// The most samples in a burst.
+const MAX_SAMPLES: usize = 1024;
+
+/// The fewest samples in a burst.
+const MIN_SAMPLES: usize = 16;
+
+/// The longest command line.
+const LINE_LEN: usize = 32;
+
+/// What the host has asked for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Stopped,
+    Running,
+    Single,
+}
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Sample GPIO 26, at 10 kHz to begin with
+    let _probe = pins.gpio26.into_floating_input();
+    let mut allocator = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let channel = allocator.alloc("scope-adc").unwrap();
+    let mut adc = AdcStream::new(pac.ADC, &mut pac.RESETS, channel, 0, 10_000);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("USB scope")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut samples = [0u16; MAX_SAMPLES];
+    let mut count = 256;
+    let mut mode = Mode::Running;
+    let mut line: Vec<u8, LINE_LEN> = Vec::new();
+
+    // The frame being sent, and how much of it is queued already
+    let mut frame = [0u8; scope::frame_len(MAX_SAMPLES)];
+    let mut frame_len = 0;
+    let mut queued = 0;
+    let mut sequence: u16 = 0;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        // Gather the host's commands a line at a time
+        let mut byte = [0u8; 1];
+        while serial.read(&mut byte) == 1 {
+            match byte[0] {
+                b'\r' | b'\n' => {
+                    if let Ok(text) = core::str::from_utf8(&line) {
+                        command(text, &mut adc, &mut count, &mut mode);
+                    }
+                    line.clear();
+                }
+                // Overlong lines are garbage; drop them
+                other => {
+                    if line.push(other).is_err() {
+                        line.clear();
+                    }
+                }
+            }
+        }
+
+        // Feed the frame into the transmit queue as room comes free
+        if queued < frame_len {
+            let room = QUEUE_SIZE - serial.pending();
+            let end = frame_len.min(queued + room);
+            if serial.write_all(&frame[queued..end]).is_ok() {
+                queued = end;
+            }
+            continue;
+        }
+
+        // Only capture when the host is listening, and wants a burst
+        if mode == Mode::Stopped || !serial.port().dtr() {
+            continue;
+        }
+        adc.capture(&mut samples[..count]);
+        let header = Header {
+            sequence,
+            sample_rate: adc.sample_rate(),
+            contiguous: false,
+        };
+        frame_len = scope::encode(header, &samples[..count], &mut frame).unwrap();
+        queued = 0;
+        sequence = sequence.wrapping_add(1);
+        if mode == Mode::Single {
+            mode = Mode::Stopped;
+        }
+    }
+}
+
+/// Act on one line from the host, ignoring anything not understood.
+fn command(line: &str, adc: &mut AdcStream, count: &mut usize, mode: &mut Mode) {
+    let mut words = line.split_whitespace();
+    let value = |word: Option<&str>| word.and_then(|word| word.parse::<u32>().ok());
+    match words.next() {
+        Some("rate") => {
+            if let Some(rate) = value(words.next()) {
+                adc.set_sample_rate(rate.clamp(MIN_SAMPLE_RATE, MAX_SAMPLE_RATE));
+            }
+        }
+        Some("samples") => {
+            if let Some(samples) = value(words.next()) {
+                *count = (samples as usize).clamp(MIN_SAMPLES, MAX_SAMPLES);
+            }
+        }
+        Some("stop") => *mode = Mode::Stopped,
+        Some("run") => *mode = Mode::Running,
+        Some("single") => *mode = Mode::Single,
+        _ => {}
+    }
+}
+
+// End of file
@@ -11,6 +11,9 @@
 //! ASCII characters are converted to upercase, so you can tell it is working
 //! and not just local-echo!
 //!
+//! It also has the `picotool` reset interface, so `picotool reboot -u` puts
+//! the Pico back in BOOTSEL mode for the next upload.
+//!
 //! See the `Cargo.toml` file for Copyright and license details.
 
 #![no_std]
@@ -37,13 +40,23 @@ use rp_pico::hal;
 // USB Device support
 use usb_device::{class_prelude::*, prelude::*};
 
-// USB Communications Class Device support
-use usbd_serial::SerialPort;
+// USB Communications Class Device support, with queues so nothing we write
+// is dropped
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+
+// The picotool reset interface
+use rp_hal_boards_common::usb::ResetInterface;
+
+// Runs the welcome message once the timer reaches it
+use rp_hal_boards_common::schedule::{Duration, Instant, Monotonic, Scheduler};
 
 // Used to demonstrate writing formatted strings
 use core::fmt::Write;
 use heapless::String;
 
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 256;
+
 
 /// Entry point to our bare-metal application.
 ///
@@ -111,74 +124,60 @@ fn main() -> ! {
     ));
 
     // Set up the USB Communications Class Device driver
-    let mut serial = SerialPort::new(&usb_bus);
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Let picotool reboot us into the bootloader
+    let mut reset = ResetInterface::new(&usb_bus);
 
     // Create a USB device with a fake VID and PID
     let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
         .manufacturer("Fake company")
         .product("Serial port")
         .serial_number("TEST")
-        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .composite_with_iads()
         .build();
 
     let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
 
+    // A welcome message to show we're alive, five seconds in
+    let mut scheduler: Scheduler<_, 1> = Scheduler::new(Monotonic::new(timer));
+    scheduler.after(Duration::secs(5), say_hello).unwrap();
 
-    let mut said_hello = false;
     let mut pin_state = PinState::from(false);
     loop {
-        // A welcome message to show we're alive
-        if !said_hello && timer.get_counter().ticks() >= 5_000_000 {
-            said_hello = true;
-            
-            let _ = serial.write(b"Hello, world!\r\n");
-
-            let time = timer.get_counter().ticks();
-            let mut text: String<64> = String::new();
-            writeln!(&mut text, "Current timer ticks: {}\r\n", time).unwrap();
-
-            // This only works reliably because the number of bytes written to
-            // the serial port is smaller than the buffers available to the USB
-            // peripheral. In general, the return value should be handled, so that
-            // bytes not transferred yet don't get lost.
-            let _ = serial.write(text.as_bytes());
-        }
-
-        // Check for new data
-        if usb_dev.poll(&mut [&mut serial]) {
-            let mut buf = [0u8; 64];
-            match serial.read(&mut buf) {
-                Err(_e) => {
-                    // Do nothing
-                    // let _ = serial.write(b"No Data in serial buffer!\r\n");
-                }
-                Ok(0) => {
-                    // Do nothing
-                }
-                Ok(count) => {
-                    // Convert to upper case
-                    buf.iter_mut().take(count).for_each(|b| {
-                        b.make_ascii_uppercase();
-                    });
-                    // Send back to the host
-                    let mut wr_ptr = &buf[..count];
-                    while !wr_ptr.is_empty() {
-                        match serial.write(wr_ptr) {
-                            Ok(len) => {
-                                wr_ptr = &wr_ptr[len..];
-                                pin_state = !pin_state;
-                                led_pin.set_state(pin_state).unwrap();
-                                },
-                            // On error, just drop unwritten data.
-                            // One possible error is Err(WouldBlock), meaning the USB
-                            // write buffer is full.
-                            Err(_) => break,
-                        };
-                    }
-                }
-            }
+        scheduler.run_pending(&mut serial);
+
+        // Move data between the queues and the USB endpoints
+        usb_dev.poll(&mut [&mut serial, &mut reset]);
+
+        // Only take as much as there is room to echo, so the rest waits in
+        // the receive queue
+        let mut buf = [0u8; 64];
+        let room = (QUEUE_SIZE - serial.pending()).min(buf.len());
+        let count = serial.read(&mut buf[..room]);
+        if count > 0 {
+            // Convert to upper case
+            buf.iter_mut().take(count).for_each(|b| {
+                b.make_ascii_uppercase();
+            });
+            // Send back to the host
+            serial.write_all(&buf[..count]).unwrap();
+            pin_state = !pin_state;
+            led_pin.set_state(pin_state).unwrap();
         }
     }
 }
 
+/// Greet the host, with the time.
+fn say_hello(serial: &mut BufferedSerial<hal::usb::UsbBus, QUEUE_SIZE>, now: Instant) {
+    let _ = serial.write_all(b"Hello, world!\r\n");
+
+    let mut text: String<64> = String::new();
+    writeln!(&mut text, "Current timer ticks: {}\r\n", now.ticks()).unwrap();
+
+    // The message is queued and sent over the next few polls. It is only
+    // refused if the queue is already full.
+    let _ = serial.write_all(text.as_bytes());
+}
+
 // End of file
@@ -0,0 +1,130 @@
+//! # Pico USB Serial DFU Example
+//!
+//! The USB Serial echo of `pico_usb_serial`, with one addition: typing `dfu`
+//! reboots the Pico into its UF2 bootloader, so new firmware can be copied
+//! over without holding BOOTSEL and replugging. It reboots with
+//! `rp_hal_boards_common::usb::soft_reset_to_usb_boot`, which detaches from
+//! the bus first, so the host drops the serial port before the bootloader's
+//! drive appears.
+//!
+//! The on-board LED flickers while the bootloader is busy.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+// USB Communications Class Device support
+use usbd_serial::SerialPort;
+
+use rp_hal_boards_common::{delay, usb};
+
+/// What to type to reboot into the bootloader.
+const COMMAND: &[u8] = b"dfu";
+
+/// The on-board LED, which the bootloader uses as its activity light.
+const LED_GPIO: u8 = 25;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // Time the detach before the reboot from the system clock
+    delay::calibrate(&clocks.system_clock);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial = SerialPort::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Serial port")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    // How much of COMMAND has been typed so far
+    let mut matched = 0;
+
+    loop {
+        if !usb_dev.poll(&mut [&mut serial]) {
+            continue;
+        }
+
+        let mut buf = [0u8; 64];
+        let count = match serial.read(&mut buf) {
+            Ok(count) => count,
+            Err(_) => continue,
+        };
+
+        for &byte in &buf[..count] {
+            let byte = byte.to_ascii_lowercase();
+            if byte == COMMAND[matched] {
+                matched += 1;
+            } else if byte == COMMAND[0] {
+                matched = 1;
+            } else {
+                matched = 0;
+            }
+
+            if matched == COMMAND.len() {
+                // Safety: the chip resets straight away, taking the bus with it
+                unsafe { usb::soft_reset_to_usb_boot(usb::DEFAULT_DETACH_MS, 1 << LED_GPIO, 0) };
+            }
+        }
+
+        // Echo back in upper case, dropping whatever doesn't fit
+        buf[..count].make_ascii_uppercase();
+        let _ = serial.write(&buf[..count]);
+    }
+}
+
+// End of file
@@ -0,0 +1,214 @@
+//! # Pico USB Serial (Shared with Interrupt) Example
+//!
+//! Creates a USB Serial device on a Pico board, serviced from the USB
+//! interrupt, while the main loop gets on with its own work and talks to the
+//! port whenever it likes.
+//!
+//! The USB device and a `BufferedSerial` live in `static` cells guarded by a
+//! `critical_section::Mutex`. The interrupt polls the device, which moves
+//! bytes between the endpoints and the serial port's queues. The main loop
+//! blinks the LED, echoes what arrives in upper case and prints a status line
+//! every second, each time taking the port in a short critical section.
+//!
+//! Compare with `pico_usb_serial_interrupt`, where everything to do with USB
+//! happens inside the interrupt.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::cell::RefCell;
+use core::fmt::Write;
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// The macro for marking our interrupt functions
+use rp_pico::hal::pac::interrupt;
+
+// GPIO traits
+use embedded_hal::digital::v2::ToggleableOutputPin;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use critical_section::Mutex;
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 256;
+
+/// The USB Bus Driver, set once before the interrupt is enabled.
+static mut USB_BUS: Option<UsbBusAllocator<hal::usb::UsbBus>> = None;
+
+/// The USB Device Driver (shared with the interrupt).
+static USB_DEVICE: Mutex<RefCell<Option<UsbDevice<hal::usb::UsbBus>>>> =
+    Mutex::new(RefCell::new(None));
+
+/// The USB Serial Device Driver and its queues (shared with the interrupt).
+static USB_SERIAL: Mutex<RefCell<Option<BufferedSerial<hal::usb::UsbBus, QUEUE_SIZE>>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+///
+/// The function configures the RP2040 peripherals, hands USB over to the
+/// interrupt, then runs the application in an infinite loop.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    //
+    // The default is to generate a 125 MHz system clock
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+    unsafe {
+        // Note (safety): This is safe as interrupts haven't been started yet
+        USB_BUS = Some(usb_bus);
+    }
+
+    // Grab a reference to the USB Bus allocator. We are promising to the
+    // compiler not to take mutable access to this global variable whilst this
+    // reference exists!
+    let bus_ref = unsafe { (*core::ptr::addr_of!(USB_BUS)).as_ref().unwrap() };
+
+    // Set up the USB Communications Class Device driver
+    let serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(bus_ref);
+
+    // Create a USB device with a fake VID and PID
+    let usb_dev = UsbDeviceBuilder::new(bus_ref, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Serial port")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    critical_section::with(|cs| {
+        USB_SERIAL.borrow(cs).replace(Some(serial));
+        USB_DEVICE.borrow(cs).replace(Some(usb_dev));
+    });
+
+    // Enable the USB interrupt
+    unsafe {
+        pac::NVIC::unmask(hal::pac::Interrupt::USBCTRL_IRQ);
+    };
+
+    // From here on USB runs by itself, and the main loop is ours
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Set the LED to be an output
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+    let mut next_blink = 0;
+    let mut next_status = 1_000_000;
+    let mut received: u32 = 0;
+
+    loop {
+        let now = timer.get_counter().ticks();
+
+        // Blink the LED at 1 Hz
+        if now >= next_blink {
+            next_blink += 500_000;
+            led_pin.toggle().unwrap();
+        }
+
+        // Only hold the port for as long as it takes to copy bytes in and out,
+        // so the interrupt is never kept waiting for long
+        critical_section::with(|cs| {
+            let mut serial = USB_SERIAL.borrow(cs).borrow_mut();
+            let serial = serial.as_mut().unwrap();
+
+            // Only take as much as there is room to echo
+            let mut buf = [0u8; 64];
+            let room = (QUEUE_SIZE - serial.pending()).min(buf.len());
+            let count = serial.read(&mut buf[..room]);
+            if count > 0 {
+                received += count as u32;
+                buf[..count].make_ascii_uppercase();
+                let _ = serial.write_all(&buf[..count]);
+            }
+
+            if now >= next_status {
+                next_status += 1_000_000;
+                let _ = write!(
+                    serial,
+                    "\r\nuptime {} s, {} bytes received\r\n",
+                    now / 1_000_000,
+                    received
+                );
+            }
+
+            // The interrupt only fires on USB events, so start sending what
+            // we queued, and make room for more to arrive
+            serial.flush_in_poll();
+        });
+    }
+}
+
+/// This function is called whenever the USB Hardware generates an Interrupt
+/// Request.
+///
+/// It polls the device, which keeps the serial port's queues moving, and
+/// leaves the contents of the queues to the main loop.
+#[allow(non_snake_case)]
+#[interrupt]
+fn USBCTRL_IRQ() {
+    critical_section::with(|cs| {
+        let mut usb_dev = USB_DEVICE.borrow(cs).borrow_mut();
+        let mut serial = USB_SERIAL.borrow(cs).borrow_mut();
+        if let (Some(usb_dev), Some(serial)) = (usb_dev.as_mut(), serial.as_mut()) {
+            usb_dev.poll(&mut [serial]);
+        }
+    });
+}
+
+// End of file
@@ -0,0 +1,267 @@
+//! # Pico USB Shell Example
+//!
+//! A command shell on a USB Serial port, using `rp_hal_boards_common::shell`.
+//!
+//! Open the port in a terminal program and type `help`. The commands are:
+//!
+//! * `led on` / `led off` - switch the Pico's LED
+//! * `adc` - read ADC0 (GPIO 26) in millivolts
+//! * `temp` - read the RP2040's temperature sensor
+//! * `cal` - show, adjust and save the calibration of `adc` and `temp`, e.g.
+//!   `cal temp offset -15` if it reads 1.5 °C high, then `cal save`
+//! * `regs gpio`, `regs pwm`, `regs adc`, `regs usb` - show how the hardware
+//!   is set up, decoded from its registers
+//! * `resources` - list the GPIOs, PIO state machines, DMA channels and PWM
+//!   slices drivers have claimed
+//! * `reboot` - restart the firmware
+//! * `reboot bootsel` - restart into the USB bootloader, for the next upload
+//!
+//! Backspace, Ctrl-C and Ctrl-U edit the line as usual.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+use core::cell::RefCell;
+
+// Some traits we need
+use embedded_hal::adc::{Channel, OneShot};
+use embedded_hal::digital::v2::OutputPin;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use critical_section::Mutex;
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::calibration::{self, Calibrate, Calibrated, Calibration, Sensor};
+use rp_hal_boards_common::regs;
+use rp_hal_boards_common::settings::{default_offset, Store};
+use rp_hal_boards_common::shell::{self, Command, Shell};
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 256;
+
+/// The ADC, shared by the sensors reading through it.
+static ADC: Mutex<RefCell<Option<hal::Adc>>> = Mutex::new(RefCell::new(None));
+
+/// One of the ADC's inputs, giving raw 12 bit readings.
+struct AdcInput<P> {
+    pin: P,
+}
+
+impl<P> Sensor for AdcInput<P>
+where
+    P: Channel<hal::Adc, ID = u8>,
+    hal::Adc: OneShot<hal::Adc, u16, P>,
+{
+    type Error = ();
+
+    fn read(&mut self) -> Result<i32, ()> {
+        critical_section::with(|cs| {
+            let mut adc = ADC.borrow(cs).borrow_mut();
+            let raw: u16 = adc
+                .as_mut()
+                .ok_or(())?
+                .read(&mut self.pin)
+                .map_err(|_| ())?;
+            Ok(i32::from(raw))
+        })
+    }
+}
+
+/// Everything the commands work on.
+struct Context {
+    led: hal::gpio::Pin<hal::gpio::bank0::Gpio25, hal::gpio::PushPullOutput>,
+    /// ADC0, in millivolts.
+    adc: Calibrated<AdcInput<hal::gpio::Pin<hal::gpio::bank0::Gpio26, hal::gpio::FloatingInput>>>,
+    /// The temperature sensor, in tenths of a degree.
+    temp: Calibrated<AdcInput<hal::adc::TempSense>>,
+    /// Where the calibrations are kept.
+    store: Store,
+}
+
+/// The shell's commands.
+const COMMANDS: [Command<Context>; 7] = [
+    Command {
+        name: "led",
+        help: "led on|off",
+        run: |context, args, _out| match args.next() {
+            Some("on") => context.led.set_high().map_err(|_| "can't drive the LED"),
+            Some("off") => context.led.set_low().map_err(|_| "can't drive the LED"),
+            _ => Err("expected on or off"),
+        },
+    },
+    Command {
+        name: "adc",
+        help: "adc",
+        run: |context, _args, out| {
+            let millivolts = context.adc.read().map_err(|_| "read failed")?;
+            write!(out, "{} mV\r\n", millivolts).map_err(|_| "output failed")
+        },
+    },
+    Command {
+        name: "temp",
+        help: "temp",
+        run: |context, _args, out| {
+            let tenths = context.temp.read().map_err(|_| "read failed")?;
+            write!(
+                out,
+                "{}{}.{} C\r\n",
+                if tenths < 0 { "-" } else { "" },
+                tenths.abs() / 10,
+                tenths.abs() % 10
+            )
+            .map_err(|_| "output failed")
+        },
+    },
+    Command {
+        name: "cal",
+        help: calibration::HELP,
+        run: |context, args, out| {
+            let sensors: &mut [&mut dyn Calibrate] = &mut [&mut context.adc, &mut context.temp];
+            calibration::command(sensors, &mut context.store, args, out)
+        },
+    },
+    Command {
+        name: "regs",
+        help: regs::HELP,
+        run: |_context, args, out| regs::command(args, out),
+    },
+    Command {
+        name: "resources",
+        help: "resources",
+        run: |_context, args, out| shell::resources(args, out),
+    },
+    Command {
+        name: "reboot",
+        help: "reboot [bootsel]",
+        run: |_context, args, _out| match args.next() {
+            None => cortex_m::peripheral::SCB::sys_reset(),
+            // Flash the LED while the host talks to the bootloader
+            Some("bootsel") => rp_pico::reboot_to_usb_boot(Some(25), false),
+            _ => Err("expected nothing or bootsel"),
+        },
+    },
+];
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let temp_sensor = adc.enable_temp_sensor();
+    critical_section::with(|cs| ADC.borrow(cs).replace(Some(adc)));
+
+    // Until calibrated: 0 to 3.3 V across the ADC's range, and the
+    // datasheet's 0.706 V at 27 °C, falling 1.721 mV per degree
+    let millivolts = Calibration::with_points(&[(0, 0), (4095, 3300)]).unwrap();
+    let tenths_celsius = Calibration::with_points(&[(700, 1095), (1000, -309)]).unwrap();
+    let mut context = Context {
+        led: pins.led.into_push_pull_output(),
+        adc: Calibrated::new(
+            "adc",
+            AdcInput {
+                pin: pins.gpio26.into_floating_input(),
+            },
+            millivolts,
+        ),
+        temp: Calibrated::new("temp", AdcInput { pin: temp_sensor }, tenths_celsius),
+        store: Store::new(default_offset()),
+    };
+
+    // Use any calibrations saved with `cal save`
+    calibration::load(&mut [&mut context.adc, &mut context.temp], &context.store);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Shell")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    let mut shell: Shell<_, 64> = Shell::new(&COMMANDS);
+    let mut connected = false;
+
+    loop {
+        usb_dev.poll(&mut [&mut serial]);
+
+        // Greet each terminal as it opens the port
+        let dtr = serial.port().dtr();
+        if dtr && !connected {
+            let _ = shell.prompt(&mut serial);
+        }
+        connected = dtr;
+
+        // Leave room in the transmit queue for the echo and a command's output
+        if serial.pending() > QUEUE_SIZE / 2 {
+            continue;
+        }
+        let mut buf = [0u8; 1];
+        if serial.read(&mut buf) == 1 {
+            let _ = shell.feed(buf[0], &mut context, &mut serial);
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,199 @@
+//! # Pico USB Serial Update Example
+//!
+//! Takes new firmware over a USB Serial port and switches to it, with
+//! `rp_hal_boards_common::ota`, rolling back if the new firmware doesn't come
+//! up.
+//!
+//! Build it with room for two slots, then load it the usual way:
+//!
+//! ```console
+//! $ OTA_SLOT_SIZE=1011712 cargo build --release --example pico_usb_update
+//! ```
+//!
+//! To update, build again, say with a different [`BLINK_MS`], and make a
+//! binary image of the whole of slot A:
+//!
+//! ```console
+//! $ arm-none-eabi-objcopy -O binary \
+//!     target/thumbv6m-none-eabi/release/examples/pico_usb_update update.bin
+//! ```
+//!
+//! then send it with its header, and wait for the answer:
+//!
+//! ```python
+//! import serial, struct, sys, zlib
+//! image = open("update.bin", "rb").read()
+//! port = serial.Serial(sys.argv[1])
+//! port.write(b"RPUP" + struct.pack("<II", len(image), zlib.crc32(image)) + image)
+//! print(port.readline())
+//! ```
+//!
+//! The Pico answers `OK` and restarts into the new firmware, or `ERR` and why.
+//! The new firmware keeps itself once a terminal opens its port, which shows
+//! USB still works; if it hangs before then, the watchdog restarts it and the
+//! old firmware comes back. Either way, opening the port prints the version
+//! and how the firmware started.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Some traits we need
+use core::fmt::Write;
+use embedded_hal::digital::v2::ToggleableOutputPin;
+use embedded_hal::watchdog::{Watchdog, WatchdogEnable};
+use fugit::ExtU32;
+
+// USB Device support
+use usb_device::{class_prelude::*, prelude::*};
+
+use rp_hal_boards_common::buffered_serial::BufferedSerial;
+use rp_hal_boards_common::ota::{self, Receiver, Status};
+use rp_hal_boards_common::usb;
+
+/// Size of the serial port's transmit and receive queues.
+const QUEUE_SIZE: usize = 256;
+
+/// Shown when the port opens; change it to tell builds apart.
+const VERSION: &str = "1";
+
+/// Time between LED toggles, in milliseconds.
+const BLINK_MS: u64 = 500;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Before anything else: this may put the old firmware back
+    let status = ota::check();
+
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // The timer paces the blinking
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The swap restarts only the processor, so make sure the host notices
+    usb::force_reenumeration(&pac.USBCTRL_REGS, &mut pac.RESETS, usb::DEFAULT_DETACH_MS);
+
+    // Set up the USB driver
+    let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(
+        pac.USBCTRL_REGS,
+        pac.USBCTRL_DPRAM,
+        clocks.usb_clock,
+        true,
+        &mut pac.RESETS,
+    ));
+
+    // Set up the USB Communications Class Device driver
+    let mut serial: BufferedSerial<_, QUEUE_SIZE> = BufferedSerial::new(&usb_bus);
+
+    // Create a USB device with a fake VID and PID
+    let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
+        .manufacturer("Fake company")
+        .product("Updatable")
+        .serial_number("TEST")
+        .device_class(2) // from: https://www.usb.org/defined-class-codes
+        .build();
+
+    // A hang restarts the firmware, which rolls back an unconfirmed update.
+    // The longest flash write, a sector erase, is well inside this.
+    watchdog.start(2.secs());
+
+    let mut receiver = Receiver::new();
+    let mut connected = false;
+    let mut next_toggle = timer.get_counter().ticks();
+
+    loop {
+        watchdog.feed();
+        usb_dev.poll(&mut [&mut serial]);
+
+        let now = timer.get_counter().ticks();
+        if now >= next_toggle {
+            led_pin.toggle().unwrap();
+            next_toggle = now + BLINK_MS * 1000;
+        }
+
+        let dtr = serial.port().dtr();
+        if dtr && !connected {
+            // A terminal got through, so the new firmware works
+            ota::confirm();
+            let started = match status {
+                Status::Normal => "started normally",
+                Status::Trial => "updated",
+                Status::RolledBack => "rolled back after a failed update",
+            };
+            let _ = write!(serial, "version {}, {}\r\n", VERSION, started);
+        }
+        connected = dtr;
+
+        let mut buf = [0u8; 64];
+        let count = serial.read(&mut buf);
+        for &byte in &buf[..count] {
+            match receiver.feed(byte) {
+                Ok(None) => {}
+                Ok(Some(image)) => {
+                    let _ = write!(serial, "OK {} bytes\r\n", image.len());
+                    // Let the answer out before the swap stops everything
+                    let deadline = timer.get_counter().ticks() + 100_000;
+                    while serial.pending() > 0 && timer.get_counter().ticks() < deadline {
+                        watchdog.feed();
+                        usb_dev.poll(&mut [&mut serial]);
+                    }
+                    image.apply();
+                }
+                Err(error) => {
+                    let _ = write!(serial, "ERR {:?}\r\n", error);
+                }
+            }
+        }
+    }
+}
+
+// End of file
@@ -0,0 +1,171 @@
+//! # Pico VGA Text Example
+//!
+//! Shows 80x30 text on a VGA monitor, using `rp_hal_boards_common::vga`, with
+//! a resistor ladder on GPIO 0 to 7 and the syncs on GPIO 16 and 17, wired as
+//! in that module's documentation.
+//!
+//! A title bar and the palette stay at the top, while the rest of the screen
+//! scrolls a line of uptime each second.
+//!
+//! Build with `--features rp-hal-boards-common/vga`.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use rp_pico::entry;
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use rp_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use rp_pico::hal;
+
+// Our interrupt macro
+use hal::pac::interrupt;
+
+// Some traits we need
+use core::cell::RefCell;
+use core::fmt::Write;
+use critical_section::Mutex;
+use hal::pio::PIOExt;
+use hal::Clock;
+
+use embedded_graphics::mono_font::ascii::FONT_8X13;
+
+use rp_hal_boards_common::dma::{Allocator, Irq};
+use rp_hal_boards_common::vga::{attr, Console, Font, Lines, Screen, Vga, COLS, DEFAULT_PINOUT};
+
+/// The VGA output, shared with the DMA interrupt.
+static VGA: Mutex<RefCell<Option<Vga<pac::PIO0>>>> = Mutex::new(RefCell::new(None));
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // Configure the clocks, to the 125 MHz VGA needs
+    let clocks = hal::clocks::init_clocks_and_plls(
+        rp_pico::XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    // The timer paces the uptime lines
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Set the pins up according to their function on this particular board
+    let pins = rp_pico::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Colour and sync all come from PIO0
+    let _blue0 = pins.gpio0.into_mode::<hal::gpio::FunctionPio0>();
+    let _blue1 = pins.gpio1.into_mode::<hal::gpio::FunctionPio0>();
+    let _green0 = pins.gpio2.into_mode::<hal::gpio::FunctionPio0>();
+    let _green1 = pins.gpio3.into_mode::<hal::gpio::FunctionPio0>();
+    let _green2 = pins.gpio4.into_mode::<hal::gpio::FunctionPio0>();
+    let _red0 = pins.gpio5.into_mode::<hal::gpio::FunctionPio0>();
+    let _red1 = pins.gpio6.into_mode::<hal::gpio::FunctionPio0>();
+    let _red2 = pins.gpio7.into_mode::<hal::gpio::FunctionPio0>();
+    let _hsync = pins.gpio16.into_mode::<hal::gpio::FunctionPio0>();
+    let _vsync = pins.gpio17.into_mode::<hal::gpio::FunctionPio0>();
+
+    let (mut pio, sm0, sm1, sm2, _) = pac.PIO0.split(&mut pac.RESETS);
+
+    let mut dma = Allocator::new(pac.DMA, &mut pac.RESETS);
+    let channels = [(); 2].map(|_| dma.alloc("vga").unwrap());
+
+    // The interrupt reads these for as long as the picture is up
+    let screen: &'static Screen = cortex_m::singleton!(: Screen = Screen::new()).unwrap();
+    let font: &'static Font = cortex_m::singleton!(: Font = Font::new(&FONT_8X13)).unwrap();
+    let lines = cortex_m::singleton!(: Lines = Lines::new()).unwrap();
+
+    // White on blue for the title bar, then a swatch of each palette colour
+    let title = attr(15, 1);
+    screen.clear_rows(0..1, title);
+    screen.write_str(1, 0, "rp-hal-boards VGA text mode, 80x30", title);
+    for index in 0..16u8 {
+        let col = usize::from(index) * COLS / 16;
+        for col in col..col + COLS / 16 {
+            screen.put(col, 1, b' ', attr(0, index));
+        }
+    }
+
+    let vga = Vga::new(
+        &mut pio,
+        sm0,
+        sm1,
+        sm2,
+        channels,
+        DEFAULT_PINOUT,
+        clocks.system_clock.freq().to_Hz(),
+        lines,
+        screen,
+        font,
+        Irq::Irq0,
+    );
+    critical_section::with(|cs| VGA.borrow(cs).replace(Some(vga)));
+
+    // Note (safety): the handler only touches VGA, which is set up
+    unsafe {
+        pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0);
+    }
+
+    // Everything below the palette scrolls
+    let mut console = Console::new(screen, 3, attr(7, 0));
+    let mut seconds = 0u32;
+    let mut next = timer.get_counter().ticks();
+
+    loop {
+        if timer.get_counter().ticks() < next {
+            continue;
+        }
+        next += 1_000_000;
+
+        console.set_attr(attr(10, 0));
+        let _ = write!(console, "{:>6} s ", seconds);
+        console.set_attr(attr(7, 0));
+        let _ = writeln!(console, "The quick brown fox jumps over the lazy dog");
+        seconds += 1;
+    }
+}
+
+/// Keeps the VGA output going, rendering each line ahead of the beam.
+#[interrupt]
+fn DMA_IRQ_0() {
+    critical_section::with(|cs| {
+        if let Some(vga) = VGA.borrow_ref_mut(cs).as_mut() {
+            vga.on_interrupt();
+        }
+    });
+}
+
+// End of file
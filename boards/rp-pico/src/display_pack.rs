@@ -0,0 +1,183 @@
+//! Support for the Pimoroni Pico Display Pack.
+//!
+//! The Display Pack plugs onto the Pico's headers and adds a 240x135 ST7789
+//! LCD, an RGB LED and four buttons. [DisplayPack::new] takes the pins it
+//! uses from [Pins](crate::Pins) and sets each one up for its job; the other
+//! pins are left free.
+//!
+//! The LCD is driven through [DisplayPack::spi_interface], with whichever
+//! ST7789 driver you prefer. The panel only covers part of the controller's
+//! memory, see [LCD_OFFSET]. Or [DisplayPack::framebuffer] draws into a pair
+//! of framebuffers in RAM instead, flushed to the LCD by DMA.
+
+use display_interface_spi::SPIInterface;
+use embedded_hal::{blocking::delay::DelayMs, digital::v2::OutputPin, spi::MODE_0};
+use fugit::{HertzU32, RateExtU32};
+use rp_hal_boards_common::{
+    dma::Channel,
+    framebuffer::{Buffers, Controller, Panel, SpiLcd, MADCTL_MV, MADCTL_MX},
+};
+
+use crate::hal::{
+    gpio::{
+        bank0::{
+            Gpio12, Gpio13, Gpio14, Gpio15, Gpio16, Gpio17, Gpio18, Gpio19, Gpio20, Gpio6, Gpio7,
+            Gpio8,
+        },
+        FunctionSpi, Pin, PinId, PinState, PullUpInput, PushPullOutput,
+    },
+    pac::{RESETS, SPI0},
+    spi::{Enabled, Spi},
+};
+
+/// Width of the LCD in landscape orientation, in pixels.
+pub const LCD_WIDTH: u32 = 240;
+
+/// Height of the LCD in landscape orientation, in pixels.
+pub const LCD_HEIGHT: u32 = 135;
+
+/// Where the top-left pixel of the panel sits in the controller's memory, in
+/// landscape orientation.
+pub const LCD_OFFSET: (i32, i32) = (40, 53);
+
+/// The SPI interface to the LCD, as returned by [DisplayPack::spi_interface].
+pub type LcdInterface =
+    SPIInterface<Spi<Enabled, SPI0, 8>, Pin<Gpio16, PushPullOutput>, Pin<Gpio17, PushPullOutput>>;
+
+/// How the panel sits on its ST7789, in landscape orientation.
+pub const PANEL: Panel = Panel {
+    controller: Controller::St7789,
+    offset: (LCD_OFFSET.0 as u16, LCD_OFFSET.1 as u16),
+    madctl: MADCTL_MV | MADCTL_MX,
+    invert: true,
+};
+
+/// A pair of full screen framebuffers for [DisplayPack::framebuffer].
+pub type LcdBuffers = Buffers<{ LCD_WIDTH as usize }, { LCD_HEIGHT as usize }>;
+
+/// The LCD with DMA flushed framebuffers, as returned by
+/// [DisplayPack::framebuffer].
+pub type Lcd = SpiLcd<
+    SPI0,
+    Pin<Gpio16, PushPullOutput>,
+    Pin<Gpio17, PushPullOutput>,
+    { LCD_WIDTH as usize },
+    { LCD_HEIGHT as usize },
+>;
+
+type ResetPin<I> = Pin<I, <I as PinId>::Reset>;
+
+/// The Pico pins the Display Pack uses, in their reset state.
+pub struct DisplayPackPins {
+    pub gpio6: ResetPin<Gpio6>,
+    pub gpio7: ResetPin<Gpio7>,
+    pub gpio8: ResetPin<Gpio8>,
+    pub gpio12: ResetPin<Gpio12>,
+    pub gpio13: ResetPin<Gpio13>,
+    pub gpio14: ResetPin<Gpio14>,
+    pub gpio15: ResetPin<Gpio15>,
+    pub gpio16: ResetPin<Gpio16>,
+    pub gpio17: ResetPin<Gpio17>,
+    pub gpio18: ResetPin<Gpio18>,
+    pub gpio19: ResetPin<Gpio19>,
+    pub gpio20: ResetPin<Gpio20>,
+}
+
+/// The LCD's control pins.
+pub struct LcdPins {
+    pub dc: Pin<Gpio16, PushPullOutput>,
+    pub cs: Pin<Gpio17, PushPullOutput>,
+    pub sclk: Pin<Gpio18, FunctionSpi>,
+    pub mosi: Pin<Gpio19, FunctionSpi>,
+    /// The backlight, on when high
+    pub backlight: Pin<Gpio20, PushPullOutput>,
+}
+
+/// The RGB LED. Each colour is on when its pin is low.
+pub struct RgbLed {
+    pub red: Pin<Gpio6, PushPullOutput>,
+    pub green: Pin<Gpio7, PushPullOutput>,
+    pub blue: Pin<Gpio8, PushPullOutput>,
+}
+
+impl RgbLed {
+    /// Switch each colour on or off.
+    pub fn set(&mut self, red: bool, green: bool, blue: bool) {
+        // Setting a GPIO can't fail
+        let _ = self.red.set_state((!red).into());
+        let _ = self.green.set_state((!green).into());
+        let _ = self.blue.set_state((!blue).into());
+    }
+}
+
+/// The Display Pack's buttons, LED and LCD pins.
+pub struct DisplayPack {
+    /// Button `A`, low when pressed
+    pub a: Pin<Gpio12, PullUpInput>,
+    /// Button `B`, low when pressed
+    pub b: Pin<Gpio13, PullUpInput>,
+    /// Button `X`, low when pressed
+    pub x: Pin<Gpio14, PullUpInput>,
+    /// Button `Y`, low when pressed
+    pub y: Pin<Gpio15, PullUpInput>,
+    pub led: RgbLed,
+    pub lcd: LcdPins,
+}
+
+impl DisplayPack {
+    /// Configure the pins: buttons as pulled-up inputs, the LED off and the
+    /// backlight on.
+    pub fn new(pins: DisplayPackPins) -> Self {
+        DisplayPack {
+            a: pins.gpio12.into_pull_up_input(),
+            b: pins.gpio13.into_pull_up_input(),
+            x: pins.gpio14.into_pull_up_input(),
+            y: pins.gpio15.into_pull_up_input(),
+            led: RgbLed {
+                red: pins.gpio6.into_push_pull_output_in_state(PinState::High),
+                green: pins.gpio7.into_push_pull_output_in_state(PinState::High),
+                blue: pins.gpio8.into_push_pull_output_in_state(PinState::High),
+            },
+            lcd: LcdPins {
+                dc: pins.gpio16.into_push_pull_output(),
+                cs: pins.gpio17.into_push_pull_output_in_state(PinState::High),
+                sclk: pins.gpio18.into_mode(),
+                mosi: pins.gpio19.into_mode(),
+                backlight: pins.gpio20.into_push_pull_output_in_state(PinState::High),
+            },
+        }
+    }
+
+    /// Start SPI0 at 62.5 MHz and wrap it up for an ST7789 driver.
+    ///
+    /// Returns the interface and the backlight pin, which stays yours.
+    pub fn spi_interface(
+        lcd: LcdPins,
+        spi0: SPI0,
+        resets: &mut RESETS,
+        peripheral_clock: HertzU32,
+    ) -> (LcdInterface, Pin<Gpio20, PushPullOutput>) {
+        let spi = Spi::<_, _, 8>::new(spi0).init(resets, peripheral_clock, 62_500.kHz(), &MODE_0);
+        (SPIInterface::new(spi, lcd.dc, lcd.cs), lcd.backlight)
+    }
+
+    /// Start SPI0 at 62.5 MHz and set the LCD up for drawing into
+    /// `buffers`, which are flushed to it by the `dma` channel.
+    ///
+    /// Returns the LCD and the backlight pin, which stays yours.
+    pub fn framebuffer(
+        lcd: LcdPins,
+        spi0: SPI0,
+        resets: &mut RESETS,
+        peripheral_clock: HertzU32,
+        dma: Channel,
+        buffers: &'static mut LcdBuffers,
+        delay: &mut impl DelayMs<u8>,
+    ) -> (Lcd, Pin<Gpio20, PushPullOutput>) {
+        let spi = Spi::<_, _, 8>::new(spi0).init(resets, peripheral_clock, 62_500.kHz(), &MODE_0);
+        (
+            SpiLcd::new(spi, lcd.dc, lcd.cs, dma, PANEL, buffers, delay),
+            lcd.backlight,
+        )
+    }
+}
@@ -68,6 +68,11 @@ pub static BOOT2_FIRMWARE: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
 
 pub use hal::pac;
 
+#[cfg(feature = "pico-display-pack")]
+pub mod display_pack;
+pub mod supply;
+pub mod uart;
+
 hal::bsp_pins!(
     /// GPIO 0 supports following functions:
     ///
@@ -819,3 +824,71 @@ hal::bsp_pins!(
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+pub use rp_hal_boards_common::clocks::{
+    init_clocks_and_plls_with_freq, init_clocks_from_rosc, init_clocks_from_usb_pll, overclock,
+    Error as ClockError, Overclock,
+};
+
+/// The pins, with the clocks running.
+pub struct Board {
+    pub pins: Pins,
+    pub clocks: hal::clocks::ClocksManager,
+}
+
+impl Board {
+    /// Start the clocks from the Pico's 12 MHz crystal and set up the pins.
+    #[allow(clippy::too_many_arguments)]
+    pub fn init(
+        io_bank0: pac::IO_BANK0,
+        pads_bank0: pac::PADS_BANK0,
+        gpio_bank0: hal::sio::SioGpioBank0,
+        xosc: pac::XOSC,
+        clocks: pac::CLOCKS,
+        pll_sys: pac::PLL_SYS,
+        pll_usb: pac::PLL_USB,
+        resets: &mut pac::RESETS,
+        watchdog: &mut hal::Watchdog,
+    ) -> Result<Self, ClockError> {
+        Self::init_with_xosc(
+            XOSC_CRYSTAL_FREQ,
+            io_bank0,
+            pads_bank0,
+            gpio_bank0,
+            xosc,
+            clocks,
+            pll_sys,
+            pll_usb,
+            resets,
+            watchdog,
+        )
+    }
+
+    /// Start the clocks from a crystal of `xosc_hz` and set up the pins, for
+    /// boards and carrier designs that don't use a 12 MHz crystal.
+    ///
+    /// The system clock is as close to 125 MHz as the crystal allows, check
+    /// `clocks.system_clock.freq()`. Fails if the crystal can't give USB an
+    /// exact 48 MHz.
+    #[allow(clippy::too_many_arguments)]
+    pub fn init_with_xosc(
+        xosc_hz: u32,
+        io_bank0: pac::IO_BANK0,
+        pads_bank0: pac::PADS_BANK0,
+        gpio_bank0: hal::sio::SioGpioBank0,
+        xosc: pac::XOSC,
+        clocks: pac::CLOCKS,
+        pll_sys: pac::PLL_SYS,
+        pll_usb: pac::PLL_USB,
+        resets: &mut pac::RESETS,
+        watchdog: &mut hal::Watchdog,
+    ) -> Result<Self, ClockError> {
+        let clocks = init_clocks_and_plls_with_freq(
+            xosc_hz, xosc, clocks, pll_sys, pll_usb, resets, watchdog,
+        )?;
+        let pins = Pins::new(io_bank0, pads_bank0, gpio_bank0, resets);
+        Ok(Board { pins, clocks })
+    }
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
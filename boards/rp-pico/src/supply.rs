@@ -0,0 +1,66 @@
+//! The Pico's supply monitors.
+//!
+//! VSYS, whichever of USB or a battery powers the board, reaches GPIO 29
+//! through a divider that gives a third of it, for ADC channel 3. GPIO 24 is
+//! high while VBUS, the USB supply, is present. [Supply::new] takes both pins
+//! from [Pins](crate::Pins):
+//!
+//! ```ignore
+//! let mut supply = Supply::new(pins.voltage_monitor, pins.vbus_detect);
+//! let millivolts = supply.vsys_voltage(&mut adc);
+//! ```
+
+use embedded_hal::{adc::OneShot, digital::v2::InputPin};
+
+use crate::hal::{
+    gpio::{
+        bank0::{Gpio24, Gpio29},
+        FloatingInput, Pin, PinMode, ValidPinMode,
+    },
+    Adc,
+};
+
+/// The ADC's reference voltage on the Pico, in millivolts.
+pub const ADC_VREF_MV: u32 = 3300;
+
+/// VSYS is this many times what the ADC sees.
+pub const VSYS_DIVIDER: u32 = 3;
+
+/// The Pico's VSYS and VBUS monitors.
+pub struct Supply {
+    vsys: Pin<Gpio29, FloatingInput>,
+    vbus: Pin<Gpio24, FloatingInput>,
+}
+
+impl Supply {
+    /// Set up the voltage monitor and VBUS detect pins as inputs.
+    pub fn new<M29, M24>(voltage_monitor: Pin<Gpio29, M29>, vbus_detect: Pin<Gpio24, M24>) -> Self
+    where
+        M29: PinMode + ValidPinMode<Gpio29>,
+        M24: PinMode + ValidPinMode<Gpio24>,
+    {
+        Supply {
+            vsys: voltage_monitor.into_floating_input(),
+            vbus: vbus_detect.into_floating_input(),
+        }
+    }
+
+    /// VSYS, in millivolts, or `None` if the ADC fails to convert.
+    ///
+    /// Accurate to within about 2%, mostly the tolerance of the Pico's 3.3 V
+    /// regulator, which is also the ADC's reference.
+    pub fn vsys_voltage(&mut self, adc: &mut Adc) -> Option<u32> {
+        let raw: u16 = adc.read(&mut self.vsys).ok()?;
+        Some(u32::from(raw) * ADC_VREF_MV * VSYS_DIVIDER / 4096)
+    }
+
+    /// Whether the board is plugged into USB power.
+    pub fn vbus_present(&self) -> bool {
+        self.vbus.is_high().unwrap_or(false)
+    }
+
+    /// Give the pins back.
+    pub fn free(self) -> (Pin<Gpio29, FloatingInput>, Pin<Gpio24, FloatingInput>) {
+        (self.vsys, self.vbus)
+    }
+}
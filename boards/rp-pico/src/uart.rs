@@ -0,0 +1,84 @@
+//! Hardware flow control for UART0.
+//!
+//! UART0's default pins on the Pico, GPIO 0 and 1, are followed by its CTS
+//! and RTS on GPIO 2 and 3. With flow control on, the UART holds RTS high to
+//! ask the other end to pause while its receive FIFO is full, and only
+//! transmits while the other end holds CTS low, so neither side loses bytes
+//! when the other falls behind. The HAL's `UartPeripheral` doesn't set this
+//! up; [Uart0FlowControl] does, on a UART already enabled:
+//!
+//! ```ignore
+//! let flow = Uart0FlowControl::new(pins.gpio2, pins.gpio3);
+//! flow.enable(&mut uart, true, true);
+//! ```
+//!
+//! Wire CTS to the other end's RTS, and RTS to its CTS.
+
+use crate::hal::{
+    gpio::{
+        bank0::{Gpio2, Gpio3},
+        FunctionUart, Pin, PinMode, ValidPinMode,
+    },
+    pac,
+    uart::{Enabled, UartPeripheral, ValidUartPinout},
+};
+
+/// UART0's CTS and RTS pins, GPIO 2 and 3.
+pub struct Uart0FlowControl {
+    cts: Pin<Gpio2, FunctionUart>,
+    rts: Pin<Gpio3, FunctionUart>,
+}
+
+impl Uart0FlowControl {
+    /// Hand GPIO 2 and 3 to UART0 as its CTS and RTS.
+    pub fn new<M2, M3>(cts: Pin<Gpio2, M2>, rts: Pin<Gpio3, M3>) -> Self
+    where
+        M2: PinMode + ValidPinMode<Gpio2>,
+        M3: PinMode + ValidPinMode<Gpio3>,
+    {
+        Uart0FlowControl {
+            cts: cts.into_mode(),
+            rts: rts.into_mode(),
+        }
+    }
+
+    /// Turn on CTS, RTS or both for `uart`.
+    ///
+    /// Waits for anything being sent to finish, as the UART has to be
+    /// disabled while its flow control changes.
+    pub fn enable<P>(&self, uart: &mut UartPeripheral<Enabled, pac::UART0, P>, cts: bool, rts: bool)
+    where
+        P: ValidUartPinout<pac::UART0>,
+    {
+        set_flow_control(uart, cts, rts);
+    }
+
+    /// Turn flow control off for `uart`.
+    pub fn disable<P>(&self, uart: &mut UartPeripheral<Enabled, pac::UART0, P>)
+    where
+        P: ValidUartPinout<pac::UART0>,
+    {
+        set_flow_control(uart, false, false);
+    }
+
+    /// Give the pins back, having turned flow control off with
+    /// [disable](Self::disable).
+    pub fn free(self) -> (Pin<Gpio2, FunctionUart>, Pin<Gpio3, FunctionUart>) {
+        (self.cts, self.rts)
+    }
+}
+
+/// Set UART0's CTSEN and RTSEN, disabling it around the change as the
+/// datasheet asks.
+fn set_flow_control<P>(_uart: &mut UartPeripheral<Enabled, pac::UART0, P>, cts: bool, rts: bool)
+where
+    P: ValidUartPinout<pac::UART0>,
+{
+    // Note (safety): the `UartPeripheral` is borrowed mutably, so nothing
+    // else is using the UART
+    let regs = unsafe { &*pac::UART0::ptr() };
+    while regs.uartfr.read().busy().bit_is_set() {}
+    regs.uartcr.modify(|_, w| w.uarten().clear_bit());
+    regs.uartcr
+        .modify(|_, w| w.ctsen().bit(cts).rtsen().bit(rts).uarten().set_bit());
+}
@@ -0,0 +1,80 @@
+//! Battery charge indicator using the onboard NeoPixel on a Solder Party RP2040 Stamp
+//!
+//! Reads the carrier's LiPo charger status line on GP20 and shows it on the
+//! onboard NeoPixel: orange while the battery is charging, green otherwise.
+#![no_std]
+#![no_main]
+
+use core::iter::once;
+use embedded_hal::timer::CountDown;
+use fugit::ExtU32;
+use panic_halt as _;
+use smart_leds::{brightness, SmartLedsWrite, RGB8};
+use solderparty_rp2040_stamp::entry;
+use solderparty_rp2040_stamp::{
+    hal::{
+        clocks::{init_clocks_and_plls, Clock},
+        pac,
+        pio::PIOExt,
+        timer::Timer,
+        watchdog::Watchdog,
+        Sio,
+    },
+    is_charging, ChargeStatusPin, NeoPixelPin, Pins, XOSC_CRYSTAL_FREQ,
+};
+use ws2812_pio::Ws2812;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let timer = Timer::new(pac.TIMER, &mut pac.RESETS);
+    let mut delay = timer.count_down();
+
+    let charge_status: ChargeStatusPin = pins.gpio20.into_pull_up_input();
+    let neopixel: NeoPixelPin = pins.neopixel.into_mode();
+
+    // Configure the addressable LED
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut ws = Ws2812::new(
+        neopixel,
+        &mut pio,
+        sm0,
+        clocks.peripheral_clock.freq(),
+        timer.count_down(),
+    );
+
+    loop {
+        let colour: RGB8 = if is_charging(&charge_status) {
+            (255, 80, 0).into()
+        } else {
+            (0, 255, 0).into()
+        };
+        ws.write(brightness(once(colour), 32)).unwrap();
+
+        delay.start(250u32.millis());
+        let _ = nb::block!(delay.wait());
+    }
+}
@@ -18,36 +18,147 @@ pub static BOOT2_FIRMWARE: [u8; 256] = rp2040_boot2::BOOT_LOADER_GD25Q64CS;
 pub use hal::pac;
 
 hal::bsp_pins!(
+    /// Castellated pad `GP0`.
+    ///
+    /// Functions: UART0 TX, I2C0 SDA, SPI0 RX, PWM0 A
     Gpio0 { name: gpio0 },
+    /// Castellated pad `GP1`.
+    ///
+    /// Functions: UART0 RX, I2C0 SCL, SPI0 CSn, PWM0 B
     Gpio1 { name: gpio1 },
+    /// Castellated pad `GP2`.
+    ///
+    /// Functions: I2C1 SDA, SPI0 SCK, PWM1 A
     Gpio2 { name: gpio2 },
+    /// Castellated pad `GP3`.
+    ///
+    /// Functions: I2C1 SCL, SPI0 TX, PWM1 B
     Gpio3 { name: gpio3 },
+    /// Castellated pad `GP4`.
+    ///
+    /// Functions: UART1 TX, I2C0 SDA, SPI0 RX, PWM2 A
     Gpio4 { name: gpio4 },
+    /// Castellated pad `GP5`.
+    ///
+    /// Functions: UART1 RX, I2C0 SCL, SPI0 CSn, PWM2 B
     Gpio5 { name: gpio5 },
+    /// Castellated pad `GP6`.
+    ///
+    /// Functions: I2C1 SDA, SPI0 SCK, PWM3 A
     Gpio6 { name: gpio6 },
+    /// Castellated pad `GP7`.
+    ///
+    /// Functions: I2C1 SCL, SPI0 TX, PWM3 B
     Gpio7 { name: gpio7 },
+    /// Castellated pad `GP8`.
+    ///
+    /// Functions: UART1 TX, I2C0 SDA, SPI1 RX, PWM4 A
     Gpio8 { name: gpio8 },
+    /// Castellated pad `GP9`.
+    ///
+    /// Functions: UART1 RX, I2C0 SCL, SPI1 CSn, PWM4 B
     Gpio9 { name: gpio9 },
+    /// Castellated pad `GP10`.
+    ///
+    /// Functions: I2C1 SDA, SPI1 SCK, PWM5 A
     Gpio10 { name: gpio10 },
+    /// Castellated pad `GP11`.
+    ///
+    /// Functions: I2C1 SCL, SPI1 TX, PWM5 B
     Gpio11 { name: gpio11 },
+    /// Castellated pad `GP12`.
+    ///
+    /// Functions: UART0 TX, I2C0 SDA, SPI1 RX, PWM6 A
     Gpio12 { name: gpio12 },
+    /// Castellated pad `GP13`.
+    ///
+    /// Functions: UART0 RX, I2C0 SCL, SPI1 CSn, PWM6 B
     Gpio13 { name: gpio13 },
+    /// Castellated pad `GP14`.
+    ///
+    /// Functions: I2C1 SDA, SPI1 SCK, PWM7 A
     Gpio14 { name: gpio14 },
+    /// Castellated pad `GP15`.
+    ///
+    /// Functions: I2C1 SCL, SPI1 TX, PWM7 B
     Gpio15 { name: gpio15 },
+    /// Castellated pad `GP16`.
+    ///
+    /// Functions: UART0 TX, I2C0 SDA, SPI0 RX, PWM0 A
     Gpio16 { name: gpio16 },
+    /// Castellated pad `GP17`.
+    ///
+    /// Functions: UART0 RX, I2C0 SCL, SPI0 CSn, PWM0 B
     Gpio17 { name: gpio17 },
+    /// Castellated pad `GP18`.
+    ///
+    /// Functions: I2C1 SDA, SPI0 SCK, PWM1 A
     Gpio18 { name: gpio18 },
+    /// Castellated pad `GP19`.
+    ///
+    /// Functions: I2C1 SCL, SPI0 TX, PWM1 B
     Gpio19 { name: gpio19 },
+    /// Castellated pad `GP20`. On the Stamp carriers this is driven by the
+    /// LiPo charger's open-drain status output, which is pulled low while the
+    /// battery is charging. See [ChargeStatusPin].
+    ///
+    /// Functions: UART1 TX, I2C0 SDA, SPI0 RX, PWM2 A
     Gpio20 { name: gpio20 },
+    /// Castellated pad `GP21`, also wired to the onboard NeoPixel.
+    ///
+    /// Functions: UART1 RX, I2C0 SCL, SPI0 CSn, PWM2 B
     Gpio21 { name: neopixel },
+    /// Castellated pad `GP22`.
+    ///
+    /// Functions: I2C1 SDA, SPI0 SCK, PWM3 A
     Gpio22 { name: gpio22 },
+    /// Castellated pad `GP23`.
+    ///
+    /// Functions: I2C1 SCL, SPI0 TX, PWM3 B
     Gpio23 { name: gpio23 },
+    /// Castellated pad `GP24`.
+    ///
+    /// Functions: UART1 TX, I2C0 SDA, SPI1 RX, PWM4 A
     Gpio24 { name: gpio24 },
+    /// Castellated pad `GP25`.
+    ///
+    /// Functions: UART1 RX, I2C0 SCL, SPI1 CSn, PWM4 B
     Gpio25 { name: gpio25 },
+    /// Castellated pad `GP26`.
+    ///
+    /// Functions: ADC0, I2C1 SDA, SPI1 SCK, PWM5 A
     Gpio26 { name: gpio26 },
+    /// Castellated pad `GP27`.
+    ///
+    /// Functions: ADC1, I2C1 SCL, SPI1 TX, PWM5 B
     Gpio27 { name: gpio27 },
+    /// Castellated pad `GP28`.
+    ///
+    /// Functions: ADC2, UART0 TX, I2C0 SDA, SPI1 RX, PWM6 A
     Gpio28 { name: gpio28 },
+    /// Castellated pad `GP29`.
+    ///
+    /// Functions: ADC3, UART0 RX, I2C0 SCL, SPI1 CSn, PWM6 B
     Gpio29 { name: gpio29 },
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+/// The onboard NeoPixel, configured for use with a PIO0 state machine.
+pub type NeoPixelPin = hal::gpio::Pin<hal::gpio::bank0::Gpio21, hal::gpio::FunctionPio0>;
+
+/// The charger status line, configured as an input with its pull-up enabled.
+///
+/// Get one with `pins.gpio20.into_pull_up_input()`.
+pub type ChargeStatusPin = hal::gpio::Pin<hal::gpio::bank0::Gpio20, hal::gpio::PullUpInput>;
+
+/// Returns `true` while the carrier's LiPo charger reports that it is charging.
+///
+/// The status output is open drain, so this also returns `false` when no
+/// battery (or no USB power) is connected.
+pub fn is_charging(pin: &ChargeStatusPin) -> bool {
+    use embedded_hal::digital::v2::InputPin;
+    pin.is_low().unwrap()
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
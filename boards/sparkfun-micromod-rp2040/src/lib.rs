@@ -412,3 +412,5 @@ impl BatteryVoltage {
         value as u16
     }
 }
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
@@ -0,0 +1,152 @@
+//! embedded-graphics demo for the LCD of the Waveshare RP2040-LCD-0.96
+//!
+//! Uses `Board::display` to get a ready-to-use ST7735 driver, draws a title,
+//! a few shapes and a bouncing ball, and slowly breathes the backlight up and
+//! down using PWM.
+#![no_std]
+#![no_main]
+
+use cortex_m::delay::Delay;
+use panic_halt as _;
+
+use waveshare_rp2040_lcd_0_96::entry;
+use waveshare_rp2040_lcd_0_96::{
+    hal::{
+        self,
+        clocks::{init_clocks_and_plls, Clock},
+        pac,
+        watchdog::Watchdog,
+        Sio,
+    },
+    Board, LcdPins, Pins, LCD_HEIGHT, LCD_WIDTH, XOSC_CRYSTAL_FREQ,
+};
+
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle, Rectangle, Triangle},
+    text::Text,
+};
+
+/// Diameter of the bouncing ball, in pixels.
+const BALL_SIZE: u32 = 10;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    // Set up the delay for the first core.
+    let sys_freq = clocks.system_clock.freq().to_Hz();
+    let mut delay = Delay::new(core.SYST, sys_freq);
+
+    let pwm_slices = hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    let board = Board::new(
+        pac.SPI1,
+        pwm_slices.pwm4,
+        LcdPins {
+            dc: pins.gp8,
+            cs: pins.gp9,
+            clk: pins.gp10,
+            mosi: pins.gp11,
+            rst: pins.gp12,
+            backlight: pins.gp25,
+        },
+    );
+    let (mut display, mut backlight) =
+        board.display(&mut pac.RESETS, clocks.peripheral_clock.freq(), &mut delay);
+
+    display.clear(Rgb565::BLACK).unwrap();
+
+    // The static part of the picture: a title and three shapes
+    let text_style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    Text::new("RP2040-LCD-0.96", Point::new(35, 10), text_style)
+        .draw(&mut display)
+        .unwrap();
+
+    Triangle::new(Point::new(10, 70), Point::new(30, 70), Point::new(20, 50))
+        .into_styled(PrimitiveStyle::with_stroke(Rgb565::RED, 1))
+        .draw(&mut display)
+        .unwrap();
+    Rectangle::new(Point::new(40, 50), Size::new(20, 20))
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::GREEN))
+        .draw(&mut display)
+        .unwrap();
+    Circle::new(Point::new(70, 50), 20)
+        .into_styled(PrimitiveStyle::with_stroke(Rgb565::BLUE, 2))
+        .draw(&mut display)
+        .unwrap();
+
+    // The ball bounces around the right-hand side of the screen
+    let area = Rectangle::new(
+        Point::new(100, 20),
+        Size::new(LCD_WIDTH - 101, LCD_HEIGHT - 21),
+    );
+    Rectangle::new(
+        area.top_left - Point::new(1, 1),
+        area.size + Size::new(2, 2),
+    )
+    .into_styled(PrimitiveStyle::with_stroke(Rgb565::YELLOW, 1))
+    .draw(&mut display)
+    .unwrap();
+
+    let bottom_right = area.bottom_right().unwrap();
+    let mut position = area.top_left;
+    let mut velocity = Point::new(1, 1);
+    let mut brightness: u8 = 100;
+    let mut fading = true;
+    loop {
+        Circle::new(position, BALL_SIZE)
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+            .draw(&mut display)
+            .unwrap();
+
+        position += velocity;
+        if position.x <= area.top_left.x || position.x + BALL_SIZE as i32 > bottom_right.x {
+            velocity.x = -velocity.x;
+        }
+        if position.y <= area.top_left.y || position.y + BALL_SIZE as i32 > bottom_right.y {
+            velocity.y = -velocity.y;
+        }
+
+        Circle::new(position, BALL_SIZE)
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::CYAN))
+            .draw(&mut display)
+            .unwrap();
+
+        // Breathe the backlight between 10 and 100 percent
+        if fading {
+            brightness -= 1;
+            fading = brightness > 10;
+        } else {
+            brightness += 1;
+            fading = brightness >= 100;
+        }
+        backlight.set_brightness(brightness);
+
+        delay.delay_ms(20);
+    }
+}
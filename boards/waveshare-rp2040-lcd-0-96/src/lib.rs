@@ -17,6 +17,24 @@ pub static BOOT2_FIRMWARE: [u8; 256] = rp2040_boot2::BOOT_LOADER_W25Q080;
 
 pub use hal::pac;
 
+use embedded_hal::{blocking::delay::DelayMs, digital::v2::OutputPin, spi::MODE_0, PwmPin};
+use fugit::{HertzU32, RateExtU32};
+use hal::{
+    gpio::{
+        bank0::{Gpio10, Gpio11, Gpio12, Gpio25, Gpio8, Gpio9},
+        FunctionPwm, FunctionSpi, Pin, PinId, PushPullOutput,
+    },
+    pac::{RESETS, SPI1},
+    pwm::{FreeRunning, Pwm4, Slice},
+    spi::Enabled,
+    Spi,
+};
+use rp_hal_boards_common::{
+    dma::Channel,
+    framebuffer::{Buffers, Controller, Panel, SpiLcd, MADCTL_BGR, MADCTL_MV, MADCTL_MX},
+};
+use st7735_lcd::{Orientation, ST7735};
+
 hal::bsp_pins!(
     /// GPIO 0 supports following functions:
     ///
@@ -242,7 +260,7 @@ hal::bsp_pins!(
         }
     },
 
-    /// GPIO 8 supports following functions:
+    /// GPIO 8 is connected to the LCD data/command line `DC`, and supports following functions:
     ///
     /// | Function     | Alias with applied function |
     /// |--------------|-----------------------------|
@@ -270,7 +288,7 @@ hal::bsp_pins!(
         }
     },
 
-    /// GPIO 9 supports following functions:
+    /// GPIO 9 is connected to the LCD chip select `CS`, and supports following functions:
     ///
     /// | Function     | Alias with applied function |
     /// |--------------|-----------------------------|
@@ -298,7 +316,7 @@ hal::bsp_pins!(
         }
     },
 
-    /// GPIO 10 supports following functions:
+    /// GPIO 10 is connected to the LCD SPI clock `CLK`, and supports following functions:
     ///
     /// | Function     | Alias with applied function |
     /// |--------------|-----------------------------|
@@ -326,7 +344,7 @@ hal::bsp_pins!(
         }
     },
 
-    /// GPIO 11 supports following functions:
+    /// GPIO 11 is connected to the LCD SPI data `DIN`, and supports following functions:
     ///
     /// | Function     | Alias with applied function |
     /// |--------------|-----------------------------|
@@ -354,7 +372,7 @@ hal::bsp_pins!(
         }
     },
 
-    /// GPIO 12 supports following functions:
+    /// GPIO 12 is connected to the LCD reset line `RST`, and supports following functions:
     ///
     /// | Function     | Alias with applied function |
     /// |--------------|-----------------------------|
@@ -663,7 +681,7 @@ hal::bsp_pins!(
         }
     },
 
-    /// GPIO 25
+    /// GPIO 25 is connected to the LCD backlight, see [Backlight]
     Gpio25 {
         name: gp25,
         aliases: {
@@ -783,3 +801,157 @@ hal::bsp_pins!(
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+/// Width of the LCD in landscape orientation, in pixels.
+pub const LCD_WIDTH: u32 = 160;
+
+/// Height of the LCD in landscape orientation, in pixels.
+pub const LCD_HEIGHT: u32 = 80;
+
+/// The on-board ST7735S LCD, as returned by [Board::display].
+pub type Display =
+    ST7735<Spi<Enabled, SPI1, 8>, Pin<Gpio8, PushPullOutput>, Pin<Gpio12, PushPullOutput>>;
+
+/// How the panel sits on its ST7735S, in landscape orientation.
+pub const PANEL: Panel = Panel {
+    controller: Controller::St7735,
+    offset: (1, 26),
+    madctl: MADCTL_MV | MADCTL_MX | MADCTL_BGR,
+    invert: true,
+};
+
+/// A pair of full screen framebuffers for [Board::framebuffer].
+pub type LcdBuffers = Buffers<{ LCD_WIDTH as usize }, { LCD_HEIGHT as usize }>;
+
+/// The LCD with DMA flushed framebuffers, as returned by
+/// [Board::framebuffer].
+pub type Lcd = SpiLcd<
+    SPI1,
+    Pin<Gpio8, PushPullOutput>,
+    Pin<Gpio9, PushPullOutput>,
+    { LCD_WIDTH as usize },
+    { LCD_HEIGHT as usize },
+>;
+
+/// A pin as handed out by [Pins::new].
+type ResetPin<I> = Pin<I, <I as PinId>::Reset>;
+
+/// The pins wired to the LCD, taken from [Pins].
+pub struct LcdPins {
+    pub dc: ResetPin<Gpio8>,
+    pub cs: ResetPin<Gpio9>,
+    pub clk: ResetPin<Gpio10>,
+    pub mosi: ResetPin<Gpio11>,
+    pub rst: ResetPin<Gpio12>,
+    pub backlight: ResetPin<Gpio25>,
+}
+
+/// The LCD backlight on GPIO 25, dimmed by PWM slice 4.
+pub struct Backlight {
+    pwm: Slice<Pwm4, FreeRunning>,
+    pin: Pin<Gpio25, FunctionPwm>,
+}
+
+impl Backlight {
+    /// Start the PWM slice and turn the backlight fully on.
+    pub fn new(mut pwm: Slice<Pwm4, FreeRunning>, pin: ResetPin<Gpio25>) -> Self {
+        pwm.set_ph_correct();
+        pwm.enable();
+        let pin = pwm.channel_b.output_to(pin);
+
+        let mut backlight = Backlight { pwm, pin };
+        backlight.set_brightness(100);
+        backlight
+    }
+
+    /// Set the brightness, from 0 (off) to 100 percent.
+    pub fn set_brightness(&mut self, percent: u8) {
+        let max = u32::from(self.pwm.channel_b.get_max_duty());
+        let duty = max * u32::from(percent.min(100)) / 100;
+        self.pwm.channel_b.set_duty(duty as u16);
+    }
+
+    /// Release the PWM slice and the pin.
+    pub fn free(self) -> (Slice<Pwm4, FreeRunning>, Pin<Gpio25, FunctionPwm>) {
+        (self.pwm, self.pin)
+    }
+}
+
+/// Everything needed to drive the LCD: SPI1, the LCD pins and the backlight.
+pub struct Board {
+    spi: SPI1,
+    pins: LcdPins,
+    pwm: Slice<Pwm4, FreeRunning>,
+}
+
+impl Board {
+    /// Collect the LCD peripherals. Nothing is touched until [Board::display]
+    /// is called.
+    pub fn new(spi: SPI1, pwm: Slice<Pwm4, FreeRunning>, pins: LcdPins) -> Self {
+        Board { spi, pins, pwm }
+    }
+
+    /// Bring up SPI1 at 10 MHz and initialise the LCD in landscape
+    /// orientation, with the colour order, inversion and panel offset this
+    /// board needs. The backlight is switched fully on.
+    ///
+    /// `peripheral_clock` is the frequency of `clocks.peripheral_clock`.
+    pub fn display(
+        self,
+        resets: &mut RESETS,
+        peripheral_clock: HertzU32,
+        delay: &mut impl DelayMs<u8>,
+    ) -> (Display, Backlight) {
+        let dc = self.pins.dc.into_push_pull_output();
+        let rst = self.pins.rst.into_push_pull_output();
+        self.pins.cs.into_mode::<FunctionSpi>();
+        self.pins.clk.into_mode::<FunctionSpi>();
+        self.pins.mosi.into_mode::<FunctionSpi>();
+
+        let spi = Spi::<_, _, 8>::new(self.spi).init(resets, peripheral_clock, 10.MHz(), &MODE_0);
+
+        // The panel is BGR with inverted colours, and sits at an offset of
+        // (1, 26) in the controller's memory.
+        let mut display = ST7735::new(spi, dc, rst, false, true, LCD_WIDTH, LCD_HEIGHT);
+        display.init(delay).unwrap();
+        display.set_orientation(&Orientation::Landscape).unwrap();
+        display.set_offset(1, 26);
+
+        let backlight = Backlight::new(self.pwm, self.pins.backlight);
+        (display, backlight)
+    }
+
+    /// Bring up SPI1 at 10 MHz and set the LCD up for drawing into
+    /// `buffers`, which are flushed to it by the `dma` channel. The backlight
+    /// is switched fully on.
+    ///
+    /// Chip select is driven as a GPIO here, to stay low for a whole frame.
+    pub fn framebuffer(
+        self,
+        resets: &mut RESETS,
+        peripheral_clock: HertzU32,
+        dma: Channel,
+        buffers: &'static mut LcdBuffers,
+        delay: &mut impl DelayMs<u8>,
+    ) -> (Lcd, Backlight) {
+        let dc = self.pins.dc.into_push_pull_output();
+        let cs = self.pins.cs.into_push_pull_output();
+        self.pins.clk.into_mode::<FunctionSpi>();
+        self.pins.mosi.into_mode::<FunctionSpi>();
+
+        // Pulse the reset line, then leave it high
+        let mut rst = self.pins.rst.into_push_pull_output();
+        let _ = rst.set_low();
+        delay.delay_ms(10);
+        let _ = rst.set_high();
+        delay.delay_ms(10);
+
+        let spi = Spi::<_, _, 8>::new(self.spi).init(resets, peripheral_clock, 10.MHz(), &MODE_0);
+        let lcd = SpiLcd::new(spi, dc, cs, dma, PANEL, buffers, delay);
+
+        let backlight = Backlight::new(self.pwm, self.pins.backlight);
+        (lcd, backlight)
+    }
+}
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
@@ -0,0 +1,90 @@
+//! Blinks the onboard NeoPixel on a Waveshare RP2040 Zero board
+//!
+//! The RP2040 Zero has no plain user LED, so the traditional blinky example
+//! switches the onboard WS2812 between white and off instead. It also toggles
+//! GP0, so you can hang an ordinary LED (with a series resistor) off that pin if
+//! you prefer.
+//!
+//! Build it with `cargo run --release --example waveshare_rp2040_zero_blinky`
+//! while the board is in BOOTSEL mode and the UF2 file is copied across for you.
+#![no_std]
+#![no_main]
+
+use core::iter::once;
+use embedded_hal::digital::v2::ToggleableOutputPin;
+use embedded_hal::timer::CountDown;
+use fugit::ExtU32;
+use panic_halt as _;
+use smart_leds::{SmartLedsWrite, RGB8};
+use waveshare_rp2040_zero::entry;
+use waveshare_rp2040_zero::{
+    hal::{
+        clocks::{init_clocks_and_plls, Clock},
+        pac,
+        pio::PIOExt,
+        timer::Timer,
+        watchdog::Watchdog,
+        Sio,
+    },
+    Pins, XOSC_CRYSTAL_FREQ,
+};
+use ws2812_pio::Ws2812;
+
+#[entry]
+fn main() -> ! {
+    let mut pac = pac::Peripherals::take().unwrap();
+
+    let mut watchdog = Watchdog::new(pac.WATCHDOG);
+
+    let clocks = init_clocks_and_plls(
+        XOSC_CRYSTAL_FREQ,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let sio = Sio::new(pac.SIO);
+    let pins = Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
+
+    let timer = Timer::new(pac.TIMER, &mut pac.RESETS);
+    let mut delay = timer.count_down();
+
+    // An optional external LED on GP0
+    let mut external_led = pins.gp0.into_push_pull_output();
+
+    // Configure the addressable LED
+    let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+    let mut ws = Ws2812::new(
+        // The onboard NeoPixel is attached to GPIO pin #16 on the RP2040 Zero.
+        pins.neopixel.into_mode(),
+        &mut pio,
+        sm0,
+        clocks.peripheral_clock.freq(),
+        timer.count_down(),
+    );
+
+    let mut on = false;
+    loop {
+        on = !on;
+        let colour: RGB8 = if on {
+            (32, 32, 32).into()
+        } else {
+            (0, 0, 0).into()
+        };
+        ws.write(once(colour)).unwrap();
+        external_led.toggle().unwrap();
+
+        delay.start(500u32.millis());
+        let _ = nb::block!(delay.wait());
+    }
+}
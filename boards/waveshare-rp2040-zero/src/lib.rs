@@ -31,17 +31,17 @@ hal::bsp_pins!(
     Gpio0 {
         name: gp0,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio0].
+            /// UART Function alias for pin [crate::Pins::gp0].
             FunctionUart: Gp0Uart0Tx,
-            /// SPI Function alias for pin [crate::Pins::gpio0].
+            /// SPI Function alias for pin [crate::Pins::gp0].
             FunctionSpi: Gp0Spi0Rx,
-            /// I2C Function alias for pin [crate::Pins::gpio0].
+            /// I2C Function alias for pin [crate::Pins::gp0].
             FunctionI2C: Gp0I2C0Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio0].
+            /// PWM Function alias for pin [crate::Pins::gp0].
             FunctionPwm: Gp0Pwm0A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio0].
+            /// PIO0 Function alias for pin [crate::Pins::gp0].
             FunctionPio0: Gp0Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio0].
+            /// PIO1 Function alias for pin [crate::Pins::gp0].
             FunctionPio1: Gp0Pio1
         }
     },
@@ -59,17 +59,17 @@ hal::bsp_pins!(
     Gpio1 {
         name: gp1,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio1].
+            /// UART Function alias for pin [crate::Pins::gp1].
             FunctionUart: Gp1Uart0Rx,
-            /// SPI Function alias for pin [crate::Pins::gpio1].
+            /// SPI Function alias for pin [crate::Pins::gp1].
             FunctionSpi: Gp1Spi0Csn,
-            /// I2C Function alias for pin [crate::Pins::gpio1].
+            /// I2C Function alias for pin [crate::Pins::gp1].
             FunctionI2C: Gp1I2C0Scl,
-            /// PWM Function alias for pin [crate::Pins::gpio1].
+            /// PWM Function alias for pin [crate::Pins::gp1].
             FunctionPwm: Gp1Pwm0B,
-            /// PIO0 Function alias for pin [crate::Pins::gpio1].
+            /// PIO0 Function alias for pin [crate::Pins::gp1].
             FunctionPio0: Gp1Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio1].
+            /// PIO1 Function alias for pin [crate::Pins::gp1].
             FunctionPio1: Gp1Pio1
         }
     },
@@ -87,17 +87,17 @@ hal::bsp_pins!(
     Gpio2 {
         name: gp2,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio2].
+            /// UART Function alias for pin [crate::Pins::gp2].
             FunctionUart: Gp2Uart0Cts,
-            /// SPI Function alias for pin [crate::Pins::gpio2].
+            /// SPI Function alias for pin [crate::Pins::gp2].
             FunctionSpi: Gp2Spi0Sck,
-            /// I2C Function alias for pin [crate::Pins::gpio2].
+            /// I2C Function alias for pin [crate::Pins::gp2].
             FunctionI2C: Gp2I2C1Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio2].
+            /// PWM Function alias for pin [crate::Pins::gp2].
             FunctionPwm: Gp2Pwm1A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio2].
+            /// PIO0 Function alias for pin [crate::Pins::gp2].
             FunctionPio0: Gp2Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio2].
+            /// PIO1 Function alias for pin [crate::Pins::gp2].
             FunctionPio1: Gp2Pio1
         }
     },
@@ -115,17 +115,17 @@ hal::bsp_pins!(
     Gpio3 {
         name: gp3,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio3].
+            /// UART Function alias for pin [crate::Pins::gp3].
             FunctionUart: Gp3Uart0Rts,
-            /// SPI Function alias for pin [crate::Pins::gpio3].
+            /// SPI Function alias for pin [crate::Pins::gp3].
             FunctionSpi: Gp3Spi0Tx,
-            /// I2C Function alias for pin [crate::Pins::gpio3].
+            /// I2C Function alias for pin [crate::Pins::gp3].
             FunctionI2C: Gp3I2C1Scl,
-            /// PWM Function alias for pin [crate::Pins::gpio3].
+            /// PWM Function alias for pin [crate::Pins::gp3].
             FunctionPwm: Gp3Pwm1B,
-            /// PIO0 Function alias for pin [crate::Pins::gpio3].
+            /// PIO0 Function alias for pin [crate::Pins::gp3].
             FunctionPio0: Gp3Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio3].
+            /// PIO1 Function alias for pin [crate::Pins::gp3].
             FunctionPio1: Gp3Pio1
         }
     },
@@ -143,17 +143,17 @@ hal::bsp_pins!(
     Gpio4 {
         name: gp4,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio4].
+            /// UART Function alias for pin [crate::Pins::gp4].
             FunctionUart: Gp4Uart1Tx,
-            /// SPI Function alias for pin [crate::Pins::gpio4].
+            /// SPI Function alias for pin [crate::Pins::gp4].
             FunctionSpi: Gp4Spi0Rx,
-            /// I2C Function alias for pin [crate::Pins::gpio4].
+            /// I2C Function alias for pin [crate::Pins::gp4].
             FunctionI2C: Gp4I2C0Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio4].
+            /// PWM Function alias for pin [crate::Pins::gp4].
             FunctionPwm: Gp4Pwm2A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio4].
+            /// PIO0 Function alias for pin [crate::Pins::gp4].
             FunctionPio0: Gp4Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio4].
+            /// PIO1 Function alias for pin [crate::Pins::gp4].
             FunctionPio1: Gp4Pio1
         }
     },
@@ -171,17 +171,17 @@ hal::bsp_pins!(
     Gpio5 {
         name: gp5,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio5].
+            /// UART Function alias for pin [crate::Pins::gp5].
             FunctionUart: Gp5Uart1Rx,
-            /// SPI Function alias for pin [crate::Pins::gpio5].
+            /// SPI Function alias for pin [crate::Pins::gp5].
             FunctionSpi: Gp5Spi0Csn,
-            /// I2C Function alias for pin [crate::Pins::gpio5].
+            /// I2C Function alias for pin [crate::Pins::gp5].
             FunctionI2C: Gp5I2C0Scl,
-            /// PWM Function alias for pin [crate::Pins::gpio5].
+            /// PWM Function alias for pin [crate::Pins::gp5].
             FunctionPwm: Gp5Pwm2B,
-            /// PIO0 Function alias for pin [crate::Pins::gpio5].
+            /// PIO0 Function alias for pin [crate::Pins::gp5].
             FunctionPio0: Gp5Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio5].
+            /// PIO1 Function alias for pin [crate::Pins::gp5].
             FunctionPio1: Gp5Pio1
         }
     },
@@ -199,17 +199,17 @@ hal::bsp_pins!(
     Gpio6 {
         name: gp6,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio6].
+            /// UART Function alias for pin [crate::Pins::gp6].
             FunctionUart: Gp6Uart1Cts,
-            /// SPI Function alias for pin [crate::Pins::gpio6].
+            /// SPI Function alias for pin [crate::Pins::gp6].
             FunctionSpi: Gp6Spi0Sck,
-            /// I2C Function alias for pin [crate::Pins::gpio6].
+            /// I2C Function alias for pin [crate::Pins::gp6].
             FunctionI2C: Gp6I2C1Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio6].
+            /// PWM Function alias for pin [crate::Pins::gp6].
             FunctionPwm: Gp6Pwm3A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio6].
+            /// PIO0 Function alias for pin [crate::Pins::gp6].
             FunctionPio0: Gp6Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio6].
+            /// PIO1 Function alias for pin [crate::Pins::gp6].
             FunctionPio1: Gp6Pio1
         }
     },
@@ -227,17 +227,17 @@ hal::bsp_pins!(
     Gpio7 {
         name: gp7,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio7].
+            /// UART Function alias for pin [crate::Pins::gp7].
             FunctionUart: Gp7Uart1Rts,
-            /// SPI Function alias for pin [crate::Pins::gpio7].
+            /// SPI Function alias for pin [crate::Pins::gp7].
             FunctionSpi: Gp7Spi0Tx,
-            /// I2C Function alias for pin [crate::Pins::gpio7].
+            /// I2C Function alias for pin [crate::Pins::gp7].
             FunctionI2C: Gp7I2C1Scl,
-            /// PWM Function alias for pin [crate::Pins::gpio7].
+            /// PWM Function alias for pin [crate::Pins::gp7].
             FunctionPwm: Gp7Pwm3B,
-            /// PIO0 Function alias for pin [crate::Pins::gpio7].
+            /// PIO0 Function alias for pin [crate::Pins::gp7].
             FunctionPio0: Gp7Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio7].
+            /// PIO1 Function alias for pin [crate::Pins::gp7].
             FunctionPio1: Gp7Pio1
         }
     },
@@ -255,17 +255,17 @@ hal::bsp_pins!(
     Gpio8 {
         name: gp8,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio8].
+            /// UART Function alias for pin [crate::Pins::gp8].
             FunctionUart: Gp8Uart1Tx,
-            /// SPI Function alias for pin [crate::Pins::gpio8].
+            /// SPI Function alias for pin [crate::Pins::gp8].
             FunctionSpi: Gp8Spi1Rx,
-            /// I2C Function alias for pin [crate::Pins::gpio8].
+            /// I2C Function alias for pin [crate::Pins::gp8].
             FunctionI2C: Gp8I2C0Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio8].
+            /// PWM Function alias for pin [crate::Pins::gp8].
             FunctionPwm: Gp8Pwm4A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio8].
+            /// PIO0 Function alias for pin [crate::Pins::gp8].
             FunctionPio0: Gp8Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio8].
+            /// PIO1 Function alias for pin [crate::Pins::gp8].
             FunctionPio1: Gp8Pio1
         }
     },
@@ -283,17 +283,17 @@ hal::bsp_pins!(
     Gpio9 {
         name: gp9,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio9].
+            /// UART Function alias for pin [crate::Pins::gp9].
             FunctionUart: Gp9Uart1Rx,
-            /// SPI Function alias for pin [crate::Pins::gpio9].
+            /// SPI Function alias for pin [crate::Pins::gp9].
             FunctionSpi: Gp9Spi1Csn,
-            /// I2C Function alias for pin [crate::Pins::gpio9].
+            /// I2C Function alias for pin [crate::Pins::gp9].
             FunctionI2C: Gp9I2C0Scl,
-            /// PWM Function alias for pin [crate::Pins::gpio9].
+            /// PWM Function alias for pin [crate::Pins::gp9].
             FunctionPwm: Gp9Pwm4B,
-            /// PIO0 Function alias for pin [crate::Pins::gpio9].
+            /// PIO0 Function alias for pin [crate::Pins::gp9].
             FunctionPio0: Gp9Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio9].
+            /// PIO1 Function alias for pin [crate::Pins::gp9].
             FunctionPio1: Gp9Pio1
         }
     },
@@ -311,17 +311,17 @@ hal::bsp_pins!(
     Gpio10 {
         name: gp10,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio10].
+            /// UART Function alias for pin [crate::Pins::gp10].
             FunctionUart: Gp10Uart1Cts,
-            /// SPI Function alias for pin [crate::Pins::gpio10].
+            /// SPI Function alias for pin [crate::Pins::gp10].
             FunctionSpi: Gp10Spi1Sck,
-            /// I2C Function alias for pin [crate::Pins::gpio10].
+            /// I2C Function alias for pin [crate::Pins::gp10].
             FunctionI2C: Gp10I2C1Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio10].
+            /// PWM Function alias for pin [crate::Pins::gp10].
             FunctionPwm: Gp10Pwm5A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio10].
+            /// PIO0 Function alias for pin [crate::Pins::gp10].
             FunctionPio0: Gp10Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio10].
+            /// PIO1 Function alias for pin [crate::Pins::gp10].
             FunctionPio1: Gp10Pio1
         }
     },
@@ -339,17 +339,17 @@ hal::bsp_pins!(
     Gpio11 {
         name: gp11,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio11].
+            /// UART Function alias for pin [crate::Pins::gp11].
             FunctionUart: Gp11Uart1Rts,
-            /// SPI Function alias for pin [crate::Pins::gpio11].
+            /// SPI Function alias for pin [crate::Pins::gp11].
             FunctionSpi: Gp11Spi1Tx,
-            /// I2C Function alias for pin [crate::Pins::gpio11].
+            /// I2C Function alias for pin [crate::Pins::gp11].
             FunctionI2C: Gp11I2C1Scl,
-            /// PWM Function alias for pin [crate::Pins::gpio11].
+            /// PWM Function alias for pin [crate::Pins::gp11].
             FunctionPwm: Gp11Pwm5B,
-            /// PIO0 Function alias for pin [crate::Pins::gpio11].
+            /// PIO0 Function alias for pin [crate::Pins::gp11].
             FunctionPio0: Gp11Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio11].
+            /// PIO1 Function alias for pin [crate::Pins::gp11].
             FunctionPio1: Gp11Pio1
         }
     },
@@ -367,17 +367,17 @@ hal::bsp_pins!(
     Gpio12 {
         name: gp12,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio12].
+            /// UART Function alias for pin [crate::Pins::gp12].
             FunctionUart: Gp12Uart0Tx,
-            /// SPI Function alias for pin [crate::Pins::gpio12].
+            /// SPI Function alias for pin [crate::Pins::gp12].
             FunctionSpi: Gp12Spi1Rx,
-            /// I2C Function alias for pin [crate::Pins::gpio12].
+            /// I2C Function alias for pin [crate::Pins::gp12].
             FunctionI2C: Gp12I2C0Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio12].
+            /// PWM Function alias for pin [crate::Pins::gp12].
             FunctionPwm: Gp12Pwm6A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio12].
+            /// PIO0 Function alias for pin [crate::Pins::gp12].
             FunctionPio0: Gp12Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio12].
+            /// PIO1 Function alias for pin [crate::Pins::gp12].
             FunctionPio1: Gp12Pio1
         }
     },
@@ -395,17 +395,17 @@ hal::bsp_pins!(
     Gpio13 {
         name: gp13,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio13].
+            /// UART Function alias for pin [crate::Pins::gp13].
             FunctionUart: Gp13Uart0Rx,
-            /// SPI Function alias for pin [crate::Pins::gpio13].
+            /// SPI Function alias for pin [crate::Pins::gp13].
             FunctionSpi: Gp13Spi1Csn,
-            /// I2C Function alias for pin [crate::Pins::gpio13].
+            /// I2C Function alias for pin [crate::Pins::gp13].
             FunctionI2C: Gp13I2C0Scl,
-            /// PWM Function alias for pin [crate::Pins::gpio13].
+            /// PWM Function alias for pin [crate::Pins::gp13].
             FunctionPwm: Gp13Pwm6B,
-            /// PIO0 Function alias for pin [crate::Pins::gpio13].
+            /// PIO0 Function alias for pin [crate::Pins::gp13].
             FunctionPio0: Gp13Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio13].
+            /// PIO1 Function alias for pin [crate::Pins::gp13].
             FunctionPio1: Gp13Pio1
         }
     },
@@ -423,17 +423,17 @@ hal::bsp_pins!(
     Gpio14 {
         name: gp14,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio14].
+            /// UART Function alias for pin [crate::Pins::gp14].
             FunctionUart: Gp14Uart0Cts,
-            /// SPI Function alias for pin [crate::Pins::gpio14].
+            /// SPI Function alias for pin [crate::Pins::gp14].
             FunctionSpi: Gp14Spi1Sck,
-            /// I2C Function alias for pin [crate::Pins::gpio14].
+            /// I2C Function alias for pin [crate::Pins::gp14].
             FunctionI2C: Gp14I2C1Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio14].
+            /// PWM Function alias for pin [crate::Pins::gp14].
             FunctionPwm: Gp14Pwm7A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio14].
+            /// PIO0 Function alias for pin [crate::Pins::gp14].
             FunctionPio0: Gp14Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio14].
+            /// PIO1 Function alias for pin [crate::Pins::gp14].
             FunctionPio1: Gp14Pio1
         }
     },
@@ -451,17 +451,17 @@ hal::bsp_pins!(
     Gpio15 {
         name: gp15,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio15].
+            /// UART Function alias for pin [crate::Pins::gp15].
             FunctionUart: Gp15Uart0Rts,
-            /// SPI Function alias for pin [crate::Pins::gpio15].
+            /// SPI Function alias for pin [crate::Pins::gp15].
             FunctionSpi: Gp15Spi1Tx,
-            /// I2C Function alias for pin [crate::Pins::gpio15].
+            /// I2C Function alias for pin [crate::Pins::gp15].
             FunctionI2C: Gp15I2C1Scl,
-            /// PWM Function alias for pin [crate::Pins::gpio15].
+            /// PWM Function alias for pin [crate::Pins::gp15].
             FunctionPwm: Gp15Pwm7B,
-            /// PIO0 Function alias for pin [crate::Pins::gpio15].
+            /// PIO0 Function alias for pin [crate::Pins::gp15].
             FunctionPio0: Gp15Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio15].
+            /// PIO1 Function alias for pin [crate::Pins::gp15].
             FunctionPio1: Gp15Pio1
         }
     },
@@ -691,17 +691,17 @@ hal::bsp_pins!(
     Gpio26 {
         name: gp26,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio26].
+            /// UART Function alias for pin [crate::Pins::gp26].
             FunctionUart: Gp26Uart1Cts,
-            /// SPI Function alias for pin [crate::Pins::gpio26].
+            /// SPI Function alias for pin [crate::Pins::gp26].
             FunctionSpi: Gp26Spi1Sck,
-            /// I2C Function alias for pin [crate::Pins::gpio26].
+            /// I2C Function alias for pin [crate::Pins::gp26].
             FunctionI2C: Gp26I2C1Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio26].
+            /// PWM Function alias for pin [crate::Pins::gp26].
             FunctionPwm: Gp26Pwm5A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio26].
+            /// PIO0 Function alias for pin [crate::Pins::gp26].
             FunctionPio0: Gp26Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio26].
+            /// PIO1 Function alias for pin [crate::Pins::gp26].
             FunctionPio1: Gp26Pio1
         }
     },
@@ -721,17 +721,17 @@ hal::bsp_pins!(
     Gpio27 {
         name: gp27,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio27].
+            /// UART Function alias for pin [crate::Pins::gp27].
             FunctionUart: Gp27Uart1Rts,
-            /// SPI Function alias for pin [crate::Pins::gpio27].
+            /// SPI Function alias for pin [crate::Pins::gp27].
             FunctionSpi: Gp27Spi1Tx,
-            /// I2C Function alias for pin [crate::Pins::gpio27].
+            /// I2C Function alias for pin [crate::Pins::gp27].
             FunctionI2C: Gp27I2C1Scl,
-            /// PWM Function alias for pin [crate::Pins::gpio27].
+            /// PWM Function alias for pin [crate::Pins::gp27].
             FunctionPwm: Gp27Pwm5B,
-            /// PIO0 Function alias for pin [crate::Pins::gpio27].
+            /// PIO0 Function alias for pin [crate::Pins::gp27].
             FunctionPio0: Gp27Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio27].
+            /// PIO1 Function alias for pin [crate::Pins::gp27].
             FunctionPio1: Gp27Pio1
         }
     },
@@ -751,17 +751,17 @@ hal::bsp_pins!(
     Gpio28 {
         name: gp28,
         aliases: {
-            /// UART Function alias for pin [crate::Pins::gpio28].
+            /// UART Function alias for pin [crate::Pins::gp28].
             FunctionUart: Gp28Uart0Tx,
-            /// SPI Function alias for pin [crate::Pins::gpio28].
+            /// SPI Function alias for pin [crate::Pins::gp28].
             FunctionSpi: Gp28Spi1Rx,
-            /// I2C Function alias for pin [crate::Pins::gpio28].
+            /// I2C Function alias for pin [crate::Pins::gp28].
             FunctionI2C: Gp28I2C0Sda,
-            /// PWM Function alias for pin [crate::Pins::gpio28].
+            /// PWM Function alias for pin [crate::Pins::gp28].
             FunctionPwm: Gp28Pwm6A,
-            /// PIO0 Function alias for pin [crate::Pins::gpio28].
+            /// PIO0 Function alias for pin [crate::Pins::gp28].
             FunctionPio0: Gp28Pio0,
-            /// PIO1 Function alias for pin [crate::Pins::gpio28].
+            /// PIO1 Function alias for pin [crate::Pins::gp28].
             FunctionPio1: Gp28Pio1
         }
     },
@@ -783,3 +783,5 @@ hal::bsp_pins!(
 );
 
 pub const XOSC_CRYSTAL_FREQ: u32 = 12_000_000;
+
+pub use rp_hal_boards_common::rom::reboot_to_usb_boot;
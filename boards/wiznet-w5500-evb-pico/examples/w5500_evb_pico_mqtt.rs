@@ -0,0 +1,306 @@
+//! # W5500-EVB-Pico MQTT Example
+//!
+//! Publishes the RP2040's internal temperature and a DS18B20's reading to an
+//! MQTT broker every five seconds, and lights the LED as told on the
+//! [`LED_TOPIC`] topic:
+//!
+//! ```text
+//! $ mosquitto_sub -h 192.168.1.10 -t 'pico/#' -v
+//! pico/temperature 24.3
+//! pico/ds18b20 21.1
+//! $ mosquitto_pub -h 192.168.1.10 -t pico/led -m on
+//! ```
+//!
+//! The MQTT client is `rp_hal_boards_common::mqtt`, over the W5500's TCP
+//! stack. When the broker can't be reached, or the connection drops, the
+//! example keeps trying, waiting twice as long after each failed attempt, up
+//! to [`MAX_RETRY_US`]; once connected again, it subscribes again.
+//!
+//! Set [`BROKER`] to your broker's address, and [`IP`], [`SUBNET`] and
+//! [`GATEWAY`] to suit your network. Connect the DS18B20's data pin to GPIO3,
+//! with a 4.7 kΩ pull-up to 3V3; without one, only the internal temperature
+//! is published.
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+
+// The macro for our start-up function
+use wiznet_w5500_evb_pico::entry;
+
+// GPIO traits
+use embedded_hal::adc::OneShot;
+use embedded_hal::digital::v2::{OutputPin, ToggleableOutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use wiznet_w5500_evb_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use wiznet_w5500_evb_pico::hal;
+
+// Some traits we need
+use fugit::RateExtU32;
+use hal::clocks::Clock;
+
+use embedded_nal::{IpAddr, Ipv4Addr, SocketAddr};
+use heapless::String;
+use rp_hal_boards_common::bitbang::{OneWire, OneWireError, OpenDrain};
+use rp_hal_boards_common::delay;
+use rp_hal_boards_common::mqtt::Client;
+use rp_hal_boards_common::w5500::{NetworkConfig, W5500};
+
+/// The board's Ethernet address, a locally administered one.
+const MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x55, 0x01];
+
+/// The board's IPv4 address.
+const IP: [u8; 4] = [192, 168, 1, 100];
+
+/// The subnet mask.
+const SUBNET: [u8; 4] = [255, 255, 255, 0];
+
+/// The gateway to the rest of the network.
+const GATEWAY: [u8; 4] = [192, 168, 1, 1];
+
+/// The MQTT broker's address.
+const BROKER: [u8; 4] = [192, 168, 1, 10];
+
+/// The MQTT broker's port.
+const BROKER_PORT: u16 = 1883;
+
+/// What the board calls itself to the broker.
+const CLIENT_ID: &str = "w5500-evb-pico";
+
+/// Where the internal temperature goes, in °C.
+const TEMPERATURE_TOPIC: &str = "pico/temperature";
+
+/// Where the DS18B20's temperature goes, in °C.
+const DS18B20_TOPIC: &str = "pico/ds18b20";
+
+/// Where `on` and `off` for the LED come from.
+const LED_TOPIC: &str = "pico/led";
+
+/// Time between readings, in microseconds.
+const INTERVAL_US: u64 = 5_000_000;
+
+/// The wait after the first failed connection, in microseconds.
+const FIRST_RETRY_US: u64 = 1_000_000;
+
+/// The longest wait between connection attempts, in microseconds.
+const MAX_RETRY_US: u64 = 30_000_000;
+
+// DS18B20 commands
+const SKIP_ROM: u8 = 0xCC;
+const CONVERT_T: u8 = 0x44;
+const READ_SCRATCHPAD: u8 = 0xBE;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Start the clocks and set the pins up according to their function on
+    // this particular board
+    let wiznet_w5500_evb_pico::Board { pins, clocks } = wiznet_w5500_evb_pico::Board::init(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+    // The 1-Wire timing comes from here
+    delay::calibrate(&clocks.system_clock);
+
+    let mut cortex_delay =
+        cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+
+    // The timer paces the readings and the reconnections
+    let timer = hal::Timer::new(pac.TIMER, &mut pac.RESETS);
+
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // The internal temperature sensor
+    let mut adc = hal::Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut temp_sensor = adc.enable_temp_sensor();
+
+    // The DS18B20 on a GPIO that drives low and otherwise floats
+    let mut one_wire = OneWire::new(OpenDrain::new(pins.gpio3));
+
+    // Hold the W5500 in reset for 500 µs, then give its PLL a moment to lock
+    let mut reset = pins.w5500_reset.into_push_pull_output();
+    reset.set_low().unwrap();
+    cortex_delay.delay_us(500);
+    reset.set_high().unwrap();
+    cortex_delay.delay_ms(1);
+
+    // The W5500 is on SPI0, with a chip select of its own
+    let _spi_sclk = pins.w5500_sclk.into_mode::<hal::gpio::FunctionSpi>();
+    let _spi_mosi = pins.w5500_mosi.into_mode::<hal::gpio::FunctionSpi>();
+    let _spi_miso = pins.w5500_miso.into_mode::<hal::gpio::FunctionSpi>();
+    let cs = pins.w5500_cs.into_push_pull_output();
+    let spi = hal::spi::Spi::<_, _, 8>::new(pac.SPI0).init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        16.MHz(),
+        &embedded_hal::spi::MODE_0,
+    );
+
+    let mut w5500 = W5500::new(spi, cs).unwrap();
+    w5500
+        .set_network(&NetworkConfig {
+            mac: MAC,
+            ip: Ipv4Addr::from(IP),
+            subnet: Ipv4Addr::from(SUBNET),
+            gateway: Ipv4Addr::from(GATEWAY),
+        })
+        .unwrap();
+
+    // Wait for a cable, blinking
+    while !w5500.link_up().unwrap() {
+        led_pin.toggle().unwrap();
+        cortex_delay.delay_ms(250);
+    }
+    led_pin.set_low().unwrap();
+
+    let broker = SocketAddr::new(IpAddr::V4(Ipv4Addr::from(BROKER)), BROKER_PORT);
+    let mut client: Client<_, 256> = Client::new(broker, CLIENT_ID);
+
+    // The DS18B20 takes 750 ms to convert, so each reading collects the
+    // conversion started by the one before
+    let _ = start_conversion(&mut one_wire);
+    let mut next_reading = timer.get_counter().ticks() + INTERVAL_US;
+    let mut retry_at = 0;
+    let mut retry_us = FIRST_RETRY_US;
+
+    loop {
+        let now = timer.get_counter().ticks();
+
+        if !client.is_connected() {
+            if now < retry_at {
+                continue;
+            }
+            let connected = match client.connect(&mut w5500, now) {
+                Ok(()) => client.subscribe(&mut w5500, LED_TOPIC).is_ok(),
+                Err(nb::Error::WouldBlock) => continue,
+                Err(nb::Error::Other(_)) => false,
+            };
+            if connected {
+                retry_us = FIRST_RETRY_US;
+            } else {
+                // Back off, so a broker that is down isn't hammered
+                retry_at = now + retry_us;
+                retry_us = (retry_us * 2).min(MAX_RETRY_US);
+                continue;
+            }
+        }
+
+        // A lost connection shows up here or when publishing, and the
+        // client closes it; the next time round reconnects
+        let mut led = None;
+        let _ = client.poll(&mut w5500, now, |topic, payload| {
+            if topic == LED_TOPIC {
+                led = match payload {
+                    b"on" | b"1" => Some(true),
+                    b"off" | b"0" => Some(false),
+                    _ => None,
+                };
+            }
+        });
+        match led {
+            Some(true) => led_pin.set_high().unwrap(),
+            Some(false) => led_pin.set_low().unwrap(),
+            None => {}
+        }
+
+        if now < next_reading || !client.is_connected() {
+            continue;
+        }
+        next_reading += INTERVAL_US;
+
+        let temp_raw: u16 = adc.read(&mut temp_sensor).unwrap();
+        // The sensor reads 0.706 V at 27 °C and falls 1.721 mV per degree
+        let microvolts = i32::from(temp_raw) * 3_300_000 / 4096;
+        let tenths = 270 - (microvolts - 706_000) * 10 / 1721;
+        let mut payload: String<16> = String::new();
+        let _ = write!(payload, "{}", Tenths(tenths as i16));
+        let _ = client.publish(&mut w5500, TEMPERATURE_TOPIC, payload.as_bytes(), false);
+
+        if let Some(raw) = read_conversion(&mut one_wire) {
+            // 1/16 of a degree to tenths
+            let mut payload: String<16> = String::new();
+            let _ = write!(payload, "{}", Tenths((i32::from(raw) * 10 / 16) as i16));
+            let _ = client.publish(&mut w5500, DS18B20_TOPIC, payload.as_bytes(), false);
+        }
+        let _ = start_conversion(&mut one_wire);
+    }
+}
+
+/// Ask the DS18B20 to measure.
+fn start_conversion<P, E>(bus: &mut OneWire<P>) -> Result<(), OneWireError>
+where
+    P: embedded_hal::digital::v2::InputPin<Error = E>
+        + embedded_hal::digital::v2::OutputPin<Error = E>,
+{
+    bus.reset()?;
+    bus.write_byte(SKIP_ROM);
+    bus.write_byte(CONVERT_T);
+    Ok(())
+}
+
+/// The DS18B20's last measurement, in sixteenths of a degree.
+fn read_conversion<P, E>(bus: &mut OneWire<P>) -> Option<i16>
+where
+    P: embedded_hal::digital::v2::InputPin<Error = E>
+        + embedded_hal::digital::v2::OutputPin<Error = E>,
+{
+    bus.reset().ok()?;
+    bus.write_byte(SKIP_ROM);
+    bus.write_byte(READ_SCRATCHPAD);
+    let low = bus.read_byte();
+    let high = bus.read_byte();
+    // A missing or unpowered sensor reads all ones
+    let raw = i16::from_le_bytes([low, high]);
+    if raw == -1 {
+        None
+    } else {
+        Some(raw)
+    }
+}
+
+/// Tenths of a degree, shown with one decimal place.
+struct Tenths(i16);
+
+impl core::fmt::Display for Tenths {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let tenths = self.0.unsigned_abs();
+        write!(f, "{}{}.{}", sign, tenths / 10, tenths % 10)
+    }
+}
+
+// End of file
@@ -0,0 +1,190 @@
+//! # W5500-EVB-Pico TCP Echo Example
+//!
+//! Sends back everything it receives over TCP on port 7, the echo port, to up
+//! to [`MAX_CLIENTS`] clients at once.
+//!
+//! The W5500 is driven through `rp_hal_boards_common::w5500`, and the echo
+//! itself is written against the `embedded-nal` traits alone, so it would run
+//! unchanged on any other network stack that implements them.
+//!
+//! The board takes the address in [`IP`], on the network given by [`SUBNET`]
+//! and [`GATEWAY`]; change them to suit yours. The LED blinks until the
+//! Ethernet link comes up, then lights while any client is connected. Try it
+//! with:
+//!
+//! ```text
+//! $ nc 192.168.1.100 7
+//! ```
+//!
+//! See the `Cargo.toml` file for Copyright and license details.
+
+#![no_std]
+#![no_main]
+
+// The macro for our start-up function
+use wiznet_w5500_evb_pico::entry;
+
+// GPIO traits
+use embedded_hal::digital::v2::{OutputPin, ToggleableOutputPin};
+
+// Ensure we halt the program on panic (if we don't mention this crate it won't
+// be linked)
+use panic_halt as _;
+
+// A shorter alias for the Peripheral Access Crate, which provides low-level
+// register access
+use wiznet_w5500_evb_pico::hal::pac;
+
+// A shorter alias for the Hardware Abstraction Layer, which provides
+// higher-level drivers.
+use wiznet_w5500_evb_pico::hal;
+
+// Some traits we need
+use fugit::RateExtU32;
+use hal::clocks::Clock;
+
+use embedded_nal::{Ipv4Addr, TcpClientStack, TcpFullStack};
+use rp_hal_boards_common::w5500::{NetworkConfig, W5500};
+
+/// The board's Ethernet address, a locally administered one.
+const MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x55, 0x00];
+
+/// The board's IPv4 address.
+const IP: [u8; 4] = [192, 168, 1, 100];
+
+/// The subnet mask.
+const SUBNET: [u8; 4] = [255, 255, 255, 0];
+
+/// The gateway to the rest of the network.
+const GATEWAY: [u8; 4] = [192, 168, 1, 1];
+
+/// The TCP echo port.
+const ECHO_PORT: u16 = 7;
+
+/// Clients served at once. With the listener, this must leave one of the
+/// W5500's eight sockets free for the next connection.
+const MAX_CLIENTS: usize = 4;
+
+/// Entry point to our bare-metal application.
+///
+/// The `#[entry]` macro ensures the Cortex-M start-up code calls this function
+/// as soon as all global variables are initialised.
+#[entry]
+fn main() -> ! {
+    // Grab our singleton objects
+    let mut pac = pac::Peripherals::take().unwrap();
+    let core = pac::CorePeripherals::take().unwrap();
+
+    // Set up the watchdog driver - needed by the clock setup code
+    let mut watchdog = hal::Watchdog::new(pac.WATCHDOG);
+
+    // The single-cycle I/O block controls our GPIO pins
+    let sio = hal::Sio::new(pac.SIO);
+
+    // Start the clocks and set the pins up according to their function on
+    // this particular board
+    let wiznet_w5500_evb_pico::Board { pins, clocks } = wiznet_w5500_evb_pico::Board::init(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        pac.XOSC,
+        pac.CLOCKS,
+        pac.PLL_SYS,
+        pac.PLL_USB,
+        &mut pac.RESETS,
+        &mut watchdog,
+    )
+    .ok()
+    .unwrap();
+
+    let mut delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    let mut led_pin = pins.led.into_push_pull_output();
+
+    // Hold the W5500 in reset for 500 µs, then give its PLL a moment to lock
+    let mut reset = pins.w5500_reset.into_push_pull_output();
+    reset.set_low().unwrap();
+    delay.delay_us(500);
+    reset.set_high().unwrap();
+    delay.delay_ms(1);
+
+    // The W5500 is on SPI0, with a chip select of its own
+    let _spi_sclk = pins.w5500_sclk.into_mode::<hal::gpio::FunctionSpi>();
+    let _spi_mosi = pins.w5500_mosi.into_mode::<hal::gpio::FunctionSpi>();
+    let _spi_miso = pins.w5500_miso.into_mode::<hal::gpio::FunctionSpi>();
+    let cs = pins.w5500_cs.into_push_pull_output();
+    let spi = hal::spi::Spi::<_, _, 8>::new(pac.SPI0).init(
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+        16.MHz(),
+        &embedded_hal::spi::MODE_0,
+    );
+
+    let mut w5500 = W5500::new(spi, cs).unwrap();
+    w5500
+        .set_network(&NetworkConfig {
+            mac: MAC,
+            ip: Ipv4Addr::from(IP),
+            subnet: Ipv4Addr::from(SUBNET),
+            gateway: Ipv4Addr::from(GATEWAY),
+        })
+        .unwrap();
+
+    // Wait for a cable, blinking
+    while !w5500.link_up().unwrap() {
+        led_pin.toggle().unwrap();
+        delay.delay_ms(250);
+    }
+    led_pin.set_low().unwrap();
+
+    let mut server = TcpClientStack::socket(&mut w5500).unwrap();
+    w5500.bind(&mut server, ECHO_PORT).unwrap();
+    w5500.listen(&mut server).unwrap();
+
+    let mut clients: [Option<_>; MAX_CLIENTS] = Default::default();
+
+    loop {
+        if let Ok((client, _remote)) = w5500.accept(&mut server) {
+            match clients.iter_mut().find(|slot| slot.is_none()) {
+                Some(slot) => *slot = Some(client),
+                // No room, so hang up
+                None => TcpClientStack::close(&mut w5500, client).unwrap(),
+            }
+        }
+
+        for slot in clients.iter_mut() {
+            if let Some(client) = slot {
+                if !echo(&mut w5500, client) {
+                    TcpClientStack::close(&mut w5500, slot.take().unwrap()).unwrap();
+                }
+            }
+        }
+
+        if clients.iter().any(|slot| slot.is_some()) {
+            led_pin.set_high().unwrap();
+        } else {
+            led_pin.set_low().unwrap();
+        }
+    }
+}
+
+/// Send back whatever `socket` has received, returning false once the other
+/// end has gone.
+fn echo<S: TcpClientStack>(stack: &mut S, socket: &mut S::TcpSocket) -> bool {
+    let mut buffer = [0; 512];
+    let len = match stack.receive(socket, &mut buffer) {
+        Ok(len) => len,
+        Err(nb::Error::WouldBlock) => return true,
+        Err(nb::Error::Other(_)) => return false,
+    };
+    let mut sent = 0;
+    while sent < len {
+        match stack.send(socket, &buffer[sent..len]) {
+            Ok(count) => sent += count,
+            Err(nb::Error::WouldBlock) => {}
+            Err(nb::Error::Other(_)) => return false,
+        }
+    }
+    true
+}
+
+// End of file
@@ -0,0 +1,348 @@
+//! Evenly spaced ADC samples, captured by DMA.
+//!
+//! `hal::Adc` converts one sample per call, at whatever pace the code calling
+//! it manages. For audio and spectra the samples have to be evenly spaced, so
+//! [`AdcStream`] runs the ADC free-running from its own clock divider and lets
+//! a DMA channel move each result out of the ADC's FIFO into a buffer.
+//!
+//! ```ignore
+//! let _input = pins.gpio26.into_floating_input();
+//! let mut stream = AdcStream::new(pac.ADC, &mut pac.RESETS, channel, 0, 20_000);
+//! let mut samples = [0u16; 256];
+//! stream.capture(&mut samples);
+//! ```
+//!
+//! [`capture`](AdcStream::capture) fills one buffer and stops. To sample
+//! without gaps, [`continuous`](AdcStream::continuous) takes a second DMA
+//! channel and keeps the ADC running, the two channels taking turns to fill
+//! the two halves of a [`Buffers`] while the code reads the other half.
+//! [`set_round_robin`](AdcStream::set_round_robin) has the ADC cycle through
+//! several inputs, interleaving their samples.
+//!
+//! The ADC is clocked from `clk_adc`, which has to be running at 48 MHz, as
+//! `init_clocks_and_plls` leaves it.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::dma::{Channel, Irq};
+use crate::hal::pac;
+
+/// The ADC's clock, from the USB PLL.
+const ADC_CLOCK_HZ: u32 = 48_000_000;
+
+/// The fastest the ADC can sample: one conversion takes 96 clocks.
+pub const MAX_SAMPLE_RATE: u32 = ADC_CLOCK_HZ / 96;
+
+/// The slowest the ADC can sample, its divider's integer part being 16 bits.
+pub const MIN_SAMPLE_RATE: u32 = ADC_CLOCK_HZ / (1 << 16) + 1;
+
+/// DREQ number of the ADC FIFO.
+const DREQ_ADC: u8 = 36;
+
+/// Samples in each half of [`Buffers`].
+pub const BLOCK_LEN: usize = 1024;
+
+/// `CH_CTRL` for filling a block from the ADC's FIFO: enabled, halfwords,
+/// incrementing the write address within the 4 kB [`Buffers`], paced by the
+/// ADC and chaining to channel `chain_to`.
+///
+/// Built by hand as the non-triggering alias `CH_AL1_CTRL` has no fields.
+fn continuous_ctrl(chain_to: u8) -> u32 {
+    const EN: u32 = 1 << 0;
+    const SIZE_HALFWORD: u32 = 1 << 2;
+    const INCR_WRITE: u32 = 1 << 5;
+    // Wrap the write address at 1 << 12 bytes
+    const RING_SIZE_4K: u32 = 12 << 6;
+    const RING_SEL_WRITE: u32 = 1 << 10;
+    EN | SIZE_HALFWORD
+        | INCR_WRITE
+        | RING_SIZE_4K
+        | RING_SEL_WRITE
+        | u32::from(chain_to) << 11
+        | u32::from(DREQ_ADC) << 15
+}
+
+/// The two blocks [`Continuous`] fills in turn.
+///
+/// Aligned to its size, so the DMA's write address wraps within it: reading
+/// too slowly loses samples but never writes outside the buffers.
+#[repr(C, align(4096))]
+pub struct Buffers {
+    blocks: [[u16; BLOCK_LEN]; 2],
+}
+
+impl Buffers {
+    /// Zeroed buffers, e.g. for `cortex_m::singleton!`.
+    pub const fn new() -> Self {
+        Buffers {
+            blocks: [[0; BLOCK_LEN]; 2],
+        }
+    }
+}
+
+impl Default for Buffers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The ADC sampling one input at a fixed rate, into buffers filled by DMA.
+pub struct AdcStream {
+    adc: pac::ADC,
+    dma: Channel,
+    sample_rate: u32,
+}
+
+impl AdcStream {
+    /// Take over the ADC and sample `input` (0 to 3 for GPIO 26 to 29, 4 for
+    /// the temperature sensor) at `sample_rate`.
+    ///
+    /// The GPIO must already be an input with no pulls. `dma` is any free
+    /// channel, e.g. from [`Allocator::alloc`](crate::dma::Allocator::alloc).
+    pub fn new(
+        adc: pac::ADC,
+        resets: &mut pac::RESETS,
+        dma: Channel,
+        input: u8,
+        sample_rate: u32,
+    ) -> Self {
+        assert!(input <= 4, "the ADC has five inputs");
+
+        resets.reset.modify(|_, w| w.adc().clear_bit());
+        while resets.reset_done.read().adc().bit_is_clear() {}
+
+        adc.cs.write(|w| unsafe {
+            w.en()
+                .set_bit()
+                .ts_en()
+                .bit(input == 4)
+                .ainsel()
+                .bits(input)
+        });
+        while adc.cs.read().ready().bit_is_clear() {}
+
+        // Raise DREQ for every sample, with no error bit in the results
+        adc.fcs.write(|w| unsafe {
+            w.en()
+                .set_bit()
+                .dreq_en()
+                .set_bit()
+                .thresh()
+                .bits(1)
+                .over()
+                .set_bit()
+                .under()
+                .set_bit()
+        });
+
+        let mut stream = AdcStream {
+            adc,
+            dma,
+            sample_rate,
+        };
+        stream.set_sample_rate(sample_rate);
+        stream
+    }
+
+    /// Change the rate samples are taken at, between captures.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        assert!(
+            (MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&sample_rate),
+            "the ADC samples at 733 Hz to 500 kHz"
+        );
+        // One sample every (1 + div) clocks, div in 16.8 fixed point
+        let divisor = ((u64::from(ADC_CLOCK_HZ) << 8) / u64::from(sample_rate)) - (1 << 8);
+        self.adc.div.write(|w| unsafe {
+            w.int()
+                .bits((divisor >> 8) as u16)
+                .frac()
+                .bits(divisor as u8)
+        });
+        self.sample_rate = sample_rate;
+    }
+
+    /// The rate samples are taken at, in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Cycle through the inputs set in `inputs`, bit 0 for input 0 up to bit
+    /// 4 for the temperature sensor, one per sample, or stick to one input if
+    /// it is 0.
+    ///
+    /// Sampling starts from the input given to [`new`](AdcStream::new), so
+    /// make that one of `inputs`. The sample rate is shared between the
+    /// inputs, and their samples are interleaved in the order of the bits.
+    pub fn set_round_robin(&mut self, inputs: u8) {
+        assert!(inputs < 1 << 5, "the ADC has five inputs");
+        self.adc.cs.modify(|r, w| unsafe {
+            w.rrobin()
+                .bits(inputs)
+                .ts_en()
+                .bit(r.ts_en().bit_is_set() || inputs & 1 << 4 != 0)
+        });
+    }
+
+    /// Fill `samples` with consecutive 12 bit readings, blocking until the
+    /// last one is in.
+    ///
+    /// The ADC only runs during the capture, so there is a gap between one
+    /// buffer and the next.
+    pub fn capture(&mut self, samples: &mut [u16]) {
+        // Throw away anything left over from before
+        while self.adc.fcs.read().empty().bit_is_clear() {
+            let _ = self.adc.fifo.read();
+        }
+
+        let regs = self.dma.regs();
+        regs.ch_read_addr
+            .write(|w| unsafe { w.bits(self.adc.fifo.as_ptr() as u32) });
+        regs.ch_write_addr
+            .write(|w| unsafe { w.bits(samples.as_mut_ptr() as u32) });
+        regs.ch_trans_count
+            .write(|w| unsafe { w.bits(samples.len() as u32) });
+        compiler_fence(Ordering::SeqCst);
+        regs.ch_ctrl_trig.write(|w| unsafe {
+            w.data_size()
+                .size_halfword()
+                .incr_read()
+                .clear_bit()
+                .incr_write()
+                .set_bit()
+                .treq_sel()
+                .bits(DREQ_ADC)
+                // Chaining to ourselves disables chaining
+                .chain_to()
+                .bits(self.dma.id())
+                .en()
+                .set_bit()
+        });
+
+        self.adc.cs.modify(|_, w| w.start_many().set_bit());
+        while self.dma.is_busy() {}
+        self.adc.cs.modify(|_, w| w.start_many().clear_bit());
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    /// Start sampling without gaps, into `buffers`.
+    ///
+    /// `second` is another free DMA channel. Both channels report completion
+    /// on `irq`, which needs [`dma::dispatch`](crate::dma::dispatch) called
+    /// from its handler and unmasking.
+    pub fn continuous(
+        mut self,
+        mut second: Channel,
+        buffers: &'static mut Buffers,
+        irq: Irq,
+    ) -> Continuous {
+        // Throw away anything left over from before
+        while self.adc.fcs.read().empty().bit_is_clear() {
+            let _ = self.adc.fifo.read();
+        }
+        self.dma.listen(irq, None);
+        second.listen(irq, None);
+
+        let fifo = self.adc.fifo.as_ptr() as u32;
+        let channels = [&self.dma, &second];
+        for (channel, block) in channels.iter().zip(buffers.blocks.iter_mut()) {
+            let regs = channel.regs();
+            regs.ch_read_addr.write(|w| unsafe { w.bits(fifo) });
+            regs.ch_write_addr
+                .write(|w| unsafe { w.bits(block.as_mut_ptr() as u32) });
+            regs.ch_trans_count
+                .write(|w| unsafe { w.bits(BLOCK_LEN as u32) });
+        }
+        compiler_fence(Ordering::SeqCst);
+        // Set the second channel up without starting it, then start the first
+        second
+            .regs()
+            .ch_al1_ctrl
+            .write(|w| unsafe { w.bits(continuous_ctrl(self.dma.id())) });
+        self.dma
+            .regs()
+            .ch_ctrl_trig
+            .write(|w| unsafe { w.bits(continuous_ctrl(second.id())) });
+
+        self.adc.cs.modify(|_, w| w.start_many().set_bit());
+        Continuous {
+            stream: self,
+            second,
+            buffers,
+            next: 0,
+        }
+    }
+
+    /// Stop, handing back the ADC and the DMA channel.
+    pub fn free(self) -> (pac::ADC, Channel) {
+        self.adc.cs.write(|w| unsafe { w.bits(0) });
+        self.adc.fcs.write(|w| unsafe { w.bits(0) });
+        (self.adc, self.dma)
+    }
+}
+
+/// An [`AdcStream`] sampling without gaps, from [`AdcStream::continuous`].
+pub struct Continuous {
+    stream: AdcStream,
+    second: Channel,
+    buffers: &'static mut Buffers,
+    /// Which block fills up next.
+    next: usize,
+}
+
+impl Continuous {
+    /// The rate samples are taken at, in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.stream.sample_rate
+    }
+
+    /// Run `f` on the next block of [`BLOCK_LEN`] samples if it is full, or
+    /// return `None` if it is still filling.
+    ///
+    /// The block is left alone while the other one fills, so `f` has that
+    /// long, `BLOCK_LEN` samples' time. Blocks have to be read as fast as
+    /// they fill, or they get overwritten.
+    pub fn read<R>(&mut self, f: impl FnOnce(&[u16; BLOCK_LEN]) -> R) -> Option<R> {
+        let channel = if self.next == 0 {
+            &self.stream.dma
+        } else {
+            &self.second
+        };
+        if !channel.take_done() {
+            return None;
+        }
+        compiler_fence(Ordering::SeqCst);
+
+        // Point the channel back at the start of its block for its next turn,
+        // which starts once the other channel is done
+        let block = &self.buffers.blocks[self.next];
+        channel
+            .regs()
+            .ch_write_addr
+            .write(|w| unsafe { w.bits(block.as_ptr() as u32) });
+
+        self.next ^= 1;
+        Some(f(block))
+    }
+
+    /// Stop sampling, handing back the stream, the second channel and the
+    /// buffers.
+    pub fn stop(mut self) -> (AdcStream, Channel, &'static mut Buffers) {
+        let adc = &self.stream.adc;
+        adc.cs.modify(|_, w| w.start_many().clear_bit());
+
+        // Abort both at once, so neither chains to the other again
+        let dma = unsafe { &*pac::DMA::ptr() };
+        let both = 1 << self.stream.dma.id() | 1 << self.second.id();
+        dma.chan_abort
+            .write(|w| unsafe { w.chan_abort().bits(both) });
+        while dma.chan_abort.read().chan_abort().bits() & both != 0 {}
+        self.stream.dma.unlisten();
+        self.second.unlisten();
+
+        while adc.fcs.read().empty().bit_is_clear() {
+            let _ = adc.fifo.read();
+        }
+        compiler_fence(Ordering::SeqCst);
+        (self.stream, self.second, self.buffers)
+    }
+}
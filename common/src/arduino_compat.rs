@@ -0,0 +1,291 @@
+//! Arduino-style free functions, for porting sketches.
+//!
+//! Code coming from the Arduino core for the Pico passes pins around as
+//! numbers and calls `pinMode`, `digitalWrite`, `analogWrite` and `delay`. This module
+//! offers the same shape of API, so such code can be ported line by line and
+//! moved over to the typed HAL a piece at a time:
+//!
+//! ```ignore
+//! arduino_compat::begin(
+//!     pac.IO_BANK0, pac.PADS_BANK0, pac.SIO, pac.ADC, pac.PWM, pac.TIMER, &mut pac.RESETS,
+//! );
+//! pin_mode(25, PinMode::Output);
+//! loop {
+//!     digital_write(25, HIGH);
+//!     delay_ms(500);
+//!     digital_write(25, LOW);
+//!     delay_ms(500);
+//! }
+//! ```
+//!
+//! Pins are bank 0's GPIO numbers, 0 to 29, and any other number panics.
+//! [`begin`] takes the GPIO, ADC, PWM and timer peripherals, so the HAL's drivers
+//! for those can't be used alongside. The typed API stays the one to reach
+//! for in new code: it catches at compile time the mistakes, such as writing
+//! to an input, that these functions let through.
+//!
+//! [`analog_read`] returns the ADC's full 12 bits, 0 to 4095, as the Arduino
+//! core does after `analogReadResolution(12)`. [`analog_write`] takes 0 to
+//! 255, as the Arduino core does by default, and runs the PWM at about 1.9 kHz
+//! with the default 125 MHz system clock.
+//!
+//! Needs the `arduino-compat` feature.
+
+use crate::hal::pac;
+
+/// A high level, for [`digital_write`] and from [`digital_read`].
+pub const HIGH: bool = true;
+
+/// A low level, for [`digital_write`] and from [`digital_read`].
+pub const LOW: bool = false;
+
+/// The ways a pin can be set up with [`pin_mode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinMode {
+    /// A floating input.
+    Input,
+    /// An input pulled up.
+    InputPullup,
+    /// An input pulled down.
+    InputPulldown,
+    /// A push-pull output, starting low.
+    Output,
+}
+
+/// Number of bank 0 pins.
+const PIN_COUNT: u8 = 30;
+
+/// The first pin wired to the ADC; pins 26 to 29 are its inputs 0 to 3.
+const FIRST_ADC_PIN: u8 = 26;
+
+/// `GPIOx_CTRL` `FUNCSEL` values.
+const FUNCSEL_PWM: u32 = 4;
+const FUNCSEL_SIO: u32 = 5;
+const FUNCSEL_NULL: u32 = 0x1f;
+
+/// `PADS_BANK0` `GPIOx` bits.
+const PAD_PDE: u32 = 1 << 2;
+const PAD_PUE: u32 = 1 << 3;
+const PAD_IE: u32 = 1 << 6;
+const PAD_OD: u32 = 1 << 7;
+
+/// `ADC` `CS` bits.
+const ADC_EN: u32 = 1 << 0;
+const ADC_START_ONCE: u32 = 1 << 2;
+const ADC_READY: u32 = 1 << 8;
+const ADC_AINSEL_SHIFT: u32 = 12;
+
+/// PWM `TOP`, for 255 steps from off to fully on.
+const PWM_TOP: u32 = 254;
+
+/// PWM `DIV`, the largest whole divider: 255 in `INT`, bits 4-11.
+const PWM_DIV: u32 = 255 << 4;
+
+/// PWM `CSR` `EN` bit.
+const PWM_EN: u32 = 1 << 0;
+
+/// Take over the peripherals the other functions use, and bring them out of
+/// reset.
+///
+/// Call once, after the clocks are set up, before anything else here.
+pub fn begin(
+    _io: pac::IO_BANK0,
+    _pads: pac::PADS_BANK0,
+    _sio: pac::SIO,
+    _adc: pac::ADC,
+    _pwm: pac::PWM,
+    _timer: pac::TIMER,
+    resets: &mut pac::RESETS,
+) {
+    resets.reset.modify(|_, w| {
+        w.io_bank0()
+            .clear_bit()
+            .pads_bank0()
+            .clear_bit()
+            .adc()
+            .clear_bit()
+            .pwm()
+            .clear_bit()
+            .timer()
+            .clear_bit()
+    });
+    loop {
+        let done = resets.reset_done.read();
+        if done.io_bank0().bit_is_set()
+            && done.pads_bank0().bit_is_set()
+            && done.adc().bit_is_set()
+            && done.pwm().bit_is_set()
+            && done.timer().bit_is_set()
+        {
+            break;
+        }
+    }
+
+    adc().cs.write(|w| unsafe { w.bits(ADC_EN) });
+    while adc().cs.read().bits() & ADC_READY == 0 {}
+}
+
+/// Set `pin` up as an input or an output, driven by software.
+pub fn pin_mode(pin: u8, mode: PinMode) {
+    let mask = mask(pin);
+    let pull = match mode {
+        PinMode::InputPullup => PAD_PUE,
+        PinMode::InputPulldown => PAD_PDE,
+        PinMode::Input | PinMode::Output => 0,
+    };
+    // Keep the drive strength, slew rate and Schmitt trigger as they are
+    pads().gpio[usize::from(pin)]
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(PAD_PUE | PAD_PDE | PAD_OD) | PAD_IE | pull) });
+
+    if mode == PinMode::Output {
+        sio().gpio_out_clr.write(|w| unsafe { w.bits(mask) });
+        sio().gpio_oe_set.write(|w| unsafe { w.bits(mask) });
+    } else {
+        sio().gpio_oe_clr.write(|w| unsafe { w.bits(mask) });
+    }
+    io().gpio[usize::from(pin)]
+        .gpio_ctrl
+        .write(|w| unsafe { w.bits(FUNCSEL_SIO) });
+}
+
+/// Drive an output `pin` [`HIGH`] or [`LOW`].
+pub fn digital_write(pin: u8, level: bool) {
+    let mask = mask(pin);
+    if level {
+        sio().gpio_out_set.write(|w| unsafe { w.bits(mask) });
+    } else {
+        sio().gpio_out_clr.write(|w| unsafe { w.bits(mask) });
+    }
+}
+
+/// The level on `pin`, [`HIGH`] or [`LOW`].
+pub fn digital_read(pin: u8) -> bool {
+    sio().gpio_in.read().bits() & mask(pin) != 0
+}
+
+/// Flip an output `pin`.
+pub fn digital_toggle(pin: u8) {
+    let mask = mask(pin);
+    sio().gpio_out_xor.write(|w| unsafe { w.bits(mask) });
+}
+
+/// Read the voltage on `pin`, one of 26 to 29, from 0 to 4095.
+///
+/// The first read from a pin turns it over to the ADC, disconnecting its
+/// digital input and output; call [`pin_mode`] to take it back.
+pub fn analog_read(pin: u8) -> u16 {
+    assert!(
+        (FIRST_ADC_PIN..PIN_COUNT).contains(&pin),
+        "pin {} has no ADC input",
+        pin
+    );
+    let index = usize::from(pin);
+    if io().gpio[index].gpio_ctrl.read().bits() & 0x1f != FUNCSEL_NULL {
+        io().gpio[index]
+            .gpio_ctrl
+            .write(|w| unsafe { w.bits(FUNCSEL_NULL) });
+        pads().gpio[index]
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(PAD_PUE | PAD_PDE | PAD_IE) | PAD_OD) });
+    }
+
+    let channel = u32::from(pin - FIRST_ADC_PIN);
+    adc()
+        .cs
+        .write(|w| unsafe { w.bits(ADC_EN | channel << ADC_AINSEL_SHIFT | ADC_START_ONCE) });
+    while adc().cs.read().bits() & ADC_READY == 0 {}
+    adc().result.read().bits() as u16
+}
+
+/// Drive `pin` with PWM, from 0, always low, to 255, always high.
+///
+/// The first write to a pin turns it over to its PWM slice; call
+/// [`pin_mode`] to take it back. Pins 16 apart share a PWM output, so they
+/// follow each other.
+pub fn analog_write(pin: u8, value: u8) {
+    assert!(pin < PIN_COUNT, "no such pin {}", pin);
+    let index = usize::from(pin);
+    let slice = &pwm().ch[usize::from(pin / 2 % 8)];
+    // Channel A is on even pins, B on odd ones
+    let shift = if pin.is_multiple_of(2) { 0 } else { 16 };
+    slice.cc.modify(|r, w| unsafe {
+        w.bits(r.bits() & !(0xFFFF << shift) | u32::from(value) << shift)
+    });
+
+    if slice.csr.read().bits() & PWM_EN == 0 {
+        slice.top.write(|w| unsafe { w.bits(PWM_TOP) });
+        slice.div.write(|w| unsafe { w.bits(PWM_DIV) });
+        slice.csr.write(|w| unsafe { w.bits(PWM_EN) });
+    }
+    if io().gpio[index].gpio_ctrl.read().bits() & 0x1f != FUNCSEL_PWM {
+        pads().gpio[index]
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(PAD_PUE | PAD_PDE | PAD_OD) | PAD_IE) });
+        io().gpio[index]
+            .gpio_ctrl
+            .write(|w| unsafe { w.bits(FUNCSEL_PWM) });
+    }
+}
+
+/// Microseconds since the timer came out of reset.
+pub fn micros() -> u64 {
+    let timer = timer();
+    // The raw registers don't latch, so make sure the high word didn't change
+    // while reading the low one
+    loop {
+        let high = timer.timerawh.read().bits();
+        let low = timer.timerawl.read().bits();
+        if timer.timerawh.read().bits() == high {
+            return u64::from(high) << 32 | u64::from(low);
+        }
+    }
+}
+
+/// Milliseconds since the timer came out of reset.
+pub fn millis() -> u64 {
+    micros() / 1000
+}
+
+/// Busy-wait for `us` microseconds.
+pub fn delay_us(us: u32) {
+    let until = micros() + u64::from(us);
+    while micros() < until {}
+}
+
+/// Busy-wait for `ms` milliseconds.
+pub fn delay_ms(ms: u32) {
+    let until = micros() + u64::from(ms) * 1000;
+    while micros() < until {}
+}
+
+/// The single bit for `pin` in the SIO's registers.
+fn mask(pin: u8) -> u32 {
+    assert!(pin < PIN_COUNT, "no such pin {}", pin);
+    1 << pin
+}
+
+// Note (safety): these peripherals were taken by `begin`, and only this
+// module touches them from then on. The SIO's set, clear and XOR registers
+// make each write atomic.
+
+fn io() -> &'static pac::io_bank0::RegisterBlock {
+    unsafe { &*pac::IO_BANK0::ptr() }
+}
+
+fn pads() -> &'static pac::pads_bank0::RegisterBlock {
+    unsafe { &*pac::PADS_BANK0::ptr() }
+}
+
+fn sio() -> &'static pac::sio::RegisterBlock {
+    unsafe { &*pac::SIO::ptr() }
+}
+
+fn adc() -> &'static pac::adc::RegisterBlock {
+    unsafe { &*pac::ADC::ptr() }
+}
+
+fn pwm() -> &'static pac::pwm::RegisterBlock {
+    unsafe { &*pac::PWM::ptr() }
+}
+
+fn timer() -> &'static pac::timer::RegisterBlock {
+    unsafe { &*pac::TIMER::ptr() }
+}
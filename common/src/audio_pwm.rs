@@ -0,0 +1,360 @@
+//! PCM audio playback from a PWM slice, streamed by DMA.
+//!
+//! A PWM output filtered by a speaker, a piezo or a simple RC network makes a
+//! serviceable DAC. [`AudioPwm`] keeps one going without the CPU touching
+//! every sample: two DMA channels, chained to each other, take turns feeding
+//! the slice's compare register from two buffers, paced by the slice's wrap
+//! DREQ. While one buffer plays, [`AudioPwm::refill`] converts the next block
+//! of a [`Clip`] into the other.
+//!
+//! The slice itself is set up with the HAL as usual. Its wrap rate is the
+//! sample rate, which [`clock_divider`] works out:
+//!
+//! ```ignore
+//! let clip = Clip::from_wav(include_bytes!("beep.wav")).unwrap();
+//! let (int, frac) = audio_pwm::clock_divider(125_000_000, clip.sample_rate(), TOP);
+//! pwm.set_top(TOP);
+//! pwm.set_div_int(int);
+//! pwm.set_div_frac(frac);
+//! pwm.channel_a.output_to(pins.gpio0);
+//! pwm.enable();
+//!
+//! let mut audio = AudioPwm::new(&mut dma, 0, Output::A, TOP, BUFFERS.take().unwrap())?;
+//! audio.start(&mut clip);
+//! loop {
+//!     audio.refill(&mut clip);
+//!     cortex_m::asm::wfi();
+//! }
+//! ```
+//!
+//! Both channels report completion through [`dma::dispatch`], so the
+//! `DMA_IRQ_0` handler has to call it.
+
+use crate::dma::{self, Allocator, Channel, Irq};
+use crate::hal::pac;
+use crate::resources::{self, Resource};
+
+/// Samples per DMA buffer. At 22.05 kHz each buffer lasts about 11.6 ms,
+/// which is how long [`AudioPwm::refill`] may be kept waiting.
+pub const BUFFER_LEN: usize = 256;
+
+/// The two buffers the DMA channels take turns to play from.
+pub type Buffers = [[u32; BUFFER_LEN]; 2];
+
+/// DREQ number of PWM slice 0's wrap; the other slices follow on.
+const DREQ_PWM_WRAP0: u32 = 24;
+
+// CTRL register fields
+const CTRL_EN: u32 = 1 << 0;
+const CTRL_DATA_SIZE_WORD: u32 = 2 << 2;
+const CTRL_INCR_READ: u32 = 1 << 4;
+const CTRL_CHAIN_TO_SHIFT: u32 = 11;
+const CTRL_TREQ_SEL_SHIFT: u32 = 15;
+
+/// Sample formats [`Clip`] can play.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Unsigned 8 bit, silence at 128, as used by 8 bit WAV files.
+    U8,
+    /// Signed 16 bit little endian, silence at 0.
+    I16,
+}
+
+impl Format {
+    fn bytes(self) -> usize {
+        match self {
+            Format::U8 => 1,
+            Format::I16 => 2,
+        }
+    }
+}
+
+/// Errors from [`Clip::from_wav`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WavError {
+    /// The data doesn't start with a RIFF WAVE header.
+    NotWav,
+    /// The `fmt ` or `data` chunk is missing or cut short.
+    Truncated,
+    /// Not uncompressed mono PCM with 8 or 16 bit samples.
+    Unsupported,
+}
+
+/// A block of PCM samples to play, usually embedded with `include_bytes!`.
+pub struct Clip {
+    data: &'static [u8],
+    format: Format,
+    sample_rate: u32,
+    position: usize,
+}
+
+impl Clip {
+    /// Play raw samples in `format` at `sample_rate`.
+    pub const fn new(data: &'static [u8], format: Format, sample_rate: u32) -> Self {
+        Clip {
+            data,
+            format,
+            sample_rate,
+            position: 0,
+        }
+    }
+
+    /// Play the samples in a mono PCM WAV file.
+    pub fn from_wav(wav: &'static [u8]) -> Result<Self, WavError> {
+        if wav.len() < 12 || &wav[0..4] != b"RIFF" || &wav[8..12] != b"WAVE" {
+            return Err(WavError::NotWav);
+        }
+        let mut format = None;
+        let mut rest = &wav[12..];
+        while rest.len() >= 8 {
+            let id = &rest[0..4];
+            let len = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+            let body = rest.get(8..8 + len).ok_or(WavError::Truncated)?;
+            if id == b"fmt " {
+                if body.len() < 16 {
+                    return Err(WavError::Truncated);
+                }
+                let tag = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                format = match (tag, channels, bits) {
+                    (1, 1, 8) => Some((Format::U8, rate)),
+                    (1, 1, 16) => Some((Format::I16, rate)),
+                    _ => return Err(WavError::Unsupported),
+                };
+            } else if id == b"data" {
+                let (format, rate) = format.ok_or(WavError::Truncated)?;
+                return Ok(Clip::new(body, format, rate));
+            }
+            // Chunks are padded to an even length
+            rest = rest.get(8 + len + (len & 1)..).unwrap_or(&[]);
+        }
+        Err(WavError::Truncated)
+    }
+
+    /// The rate the samples were recorded at, in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The number of samples in the clip.
+    pub fn len(&self) -> usize {
+        self.data.len() / self.format.bytes()
+    }
+
+    /// Whether the clip has no samples at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether every sample has been handed out.
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.len()
+    }
+
+    /// Start again from the first sample.
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+
+    /// The next sample scaled to `0..=65535`, or `None` at the end.
+    fn next_sample(&mut self) -> Option<u16> {
+        if self.is_finished() {
+            return None;
+        }
+        let offset = self.position * self.format.bytes();
+        self.position += 1;
+        Some(match self.format {
+            Format::U8 => u16::from(self.data[offset]) << 8,
+            Format::I16 => {
+                let sample = i16::from_le_bytes([self.data[offset], self.data[offset + 1]]);
+                (sample as u16) ^ 0x8000
+            }
+        })
+    }
+}
+
+/// Which of the slice's outputs plays the audio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Output {
+    /// Channel A, the even numbered GPIO.
+    A,
+    /// Channel B, the odd numbered GPIO.
+    B,
+    /// Both channels, for a speaker driven differentially or two mono pins.
+    Both,
+}
+
+/// Work out the 8.4 fixed point clock divider that makes a slice counting to
+/// `top` wrap `sample_rate` times a second.
+///
+/// Returns the integer and fractional parts, for the HAL's `set_div_int` and
+/// `set_div_frac`. The divider is clamped to the 1 to 255 the hardware can do.
+pub fn clock_divider(sys_clk_hz: u32, sample_rate: u32, top: u16) -> (u8, u8) {
+    let period = u64::from(sample_rate) * (u64::from(top) + 1);
+    let sixteenths = (u64::from(sys_clk_hz) * 16 + period / 2) / period;
+    let sixteenths = sixteenths.clamp(16, 255 * 16 + 15);
+    ((sixteenths >> 4) as u8, (sixteenths & 0xF) as u8)
+}
+
+/// A PWM slice playing samples from two DMA buffers.
+pub struct AudioPwm {
+    channels: [Channel; 2],
+    buffers: &'static mut Buffers,
+    slice: u8,
+    output: Output,
+    top: u16,
+}
+
+impl AudioPwm {
+    /// Allocate two DMA channels to feed PWM slice `slice`, which counts to
+    /// `top`.
+    ///
+    /// The slice is claimed in the [`resources`] registry as `"audio-pwm"`.
+    pub fn new(
+        dma: &mut Allocator,
+        slice: u8,
+        output: Output,
+        top: u16,
+        buffers: &'static mut Buffers,
+    ) -> Result<Self, dma::Error> {
+        assert!(
+            (slice as usize) < resources::PWM_SLICE_COUNT,
+            "the RP2040 has PWM slices 0 to 7"
+        );
+        let first = dma.alloc("audio-pwm")?;
+        let second = match dma.alloc("audio-pwm") {
+            Ok(channel) => channel,
+            Err(e) => {
+                dma.free(first);
+                return Err(e);
+            }
+        };
+        let _ = resources::claim(Resource::PwmSlice(slice), "audio-pwm");
+        let mut channels = [first, second];
+        for channel in channels.iter_mut() {
+            channel.listen(Irq::Irq0, None);
+        }
+        Ok(AudioPwm {
+            channels,
+            buffers,
+            slice,
+            output,
+            top,
+        })
+    }
+
+    /// Fill both buffers from `clip` and start playing.
+    pub fn start(&mut self, clip: &mut Clip) {
+        self.stop();
+        self.fill(0, clip);
+        self.fill(1, clip);
+        let cc = unsafe { (*pac::PWM::ptr()).ch[self.slice as usize].cc.as_ptr() } as u32;
+        let dreq = DREQ_PWM_WRAP0 + u32::from(self.slice);
+        // Set up the second channel first, so it is ready to be chained to
+        // when the first one is triggered
+        for index in [1, 0] {
+            let other = self.channels[1 - index].id();
+            let regs = self.channels[index].regs();
+            regs.ch_read_addr
+                .write(|w| unsafe { w.bits(self.buffers[index].as_ptr() as u32) });
+            regs.ch_write_addr.write(|w| unsafe { w.bits(cc) });
+            regs.ch_trans_count
+                .write(|w| unsafe { w.bits(BUFFER_LEN as u32) });
+            let ctrl = CTRL_EN
+                | CTRL_DATA_SIZE_WORD
+                | CTRL_INCR_READ
+                | u32::from(other) << CTRL_CHAIN_TO_SHIFT
+                | dreq << CTRL_TREQ_SEL_SHIFT;
+            if index == 1 {
+                regs.ch_al1_ctrl.write(|w| unsafe { w.bits(ctrl) });
+            } else {
+                regs.ch_ctrl_trig.write(|w| unsafe { w.bits(ctrl) });
+            }
+        }
+    }
+
+    /// Refill any buffer that finished playing since the last call.
+    ///
+    /// Once `clip` runs out the remaining samples are silence. Returns `true`
+    /// if a buffer was refilled.
+    pub fn refill(&mut self, clip: &mut Clip) -> bool {
+        let mut refilled = false;
+        for index in 0..2 {
+            if !self.channels[index].take_done() {
+                continue;
+            }
+            self.fill(index, clip);
+            // Re-arm without triggering; the other channel chains back to us
+            let regs = self.channels[index].regs();
+            regs.ch_read_addr
+                .write(|w| unsafe { w.bits(self.buffers[index].as_ptr() as u32) });
+            regs.ch_trans_count
+                .write(|w| unsafe { w.bits(BUFFER_LEN as u32) });
+            refilled = true;
+        }
+        refilled
+    }
+
+    /// Stop both channels and leave the output at its midpoint.
+    pub fn stop(&mut self) {
+        for channel in self.channels.iter_mut() {
+            // Break the chain first, so the aborted channel can't restart the other
+            channel
+                .regs()
+                .ch_al1_ctrl
+                .modify(|r, w| unsafe { w.bits(r.bits() & !CTRL_EN) });
+            channel.abort();
+            channel.take_done();
+        }
+        // Only the halves in use, leaving the other channel's compare value
+        let level = self.cc_value(0x8000);
+        let mask = self.cc_mask();
+        let pwm = unsafe { &*pac::PWM::ptr() };
+        pwm.ch[self.slice as usize]
+            .cc
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask | level) });
+    }
+
+    /// Whether either channel is still moving samples.
+    pub fn is_playing(&self) -> bool {
+        self.channels.iter().any(|channel| channel.is_busy())
+    }
+
+    /// Stop playing and give the DMA channels back.
+    pub fn free(mut self, dma: &mut Allocator) -> &'static mut Buffers {
+        self.stop();
+        let [first, second] = self.channels;
+        dma.free(first);
+        dma.free(second);
+        resources::release(Resource::PwmSlice(self.slice));
+        self.buffers
+    }
+
+    fn fill(&mut self, index: usize, clip: &mut Clip) {
+        for slot in 0..BUFFER_LEN {
+            let sample = clip.next_sample().unwrap_or(0x8000);
+            self.buffers[index][slot] = self.cc_value(sample);
+        }
+    }
+
+    /// The CC register value that outputs `sample`, scaled from `0..=65535`.
+    fn cc_value(&self, sample: u16) -> u32 {
+        let duty = (u32::from(sample) * (u32::from(self.top) + 1)) >> 16;
+        match self.output {
+            Output::A => duty,
+            Output::B => duty << 16,
+            Output::Both => duty | duty << 16,
+        }
+    }
+
+    /// The bits of the CC register [`cc_value`](Self::cc_value) fills.
+    fn cc_mask(&self) -> u32 {
+        match self.output {
+            Output::A => 0x0000_FFFF,
+            Output::B => 0xFFFF_0000,
+            Output::Both => 0xFFFF_FFFF,
+        }
+    }
+}
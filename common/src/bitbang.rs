@@ -0,0 +1,244 @@
+//! Bit-banged WS2812 and 1-Wire drivers.
+//!
+//! The PIO drivers (`ws2812-pio`, `i2c-pio`) are the right choice whenever a
+//! state machine is free. These fallbacks only need a GPIO, and take all their
+//! timing from [`delay`](crate::delay), so they keep working after the system
+//! clock is changed as long as [`delay::calibrate`](crate::delay::calibrate) is
+//! called again.
+//!
+//! Both drivers disable interrupts while they shift out time-critical bits.
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use hal::gpio::{bank0::BankPinId, Pin, PinId, PinMode, PinState, ReadableOutput, ValidPinMode};
+use hal::pac;
+
+use crate::delay;
+
+/// WS2812 "0" bit high time.
+const WS2812_T0H_NS: u32 = 400;
+/// WS2812 "1" bit high time.
+const WS2812_T1H_NS: u32 = 800;
+/// WS2812 bit period.
+const WS2812_PERIOD_NS: u32 = 1250;
+/// WS2812 latch time between frames.
+const WS2812_RESET_US: u32 = 300;
+
+/// WS2812 / NeoPixel driver that toggles a push-pull output directly.
+///
+/// Call [`Ws2812::recalibrate`] after changing the system clock.
+pub struct Ws2812<P> {
+    pin: P,
+    t0h: u32,
+    t0l: u32,
+    t1h: u32,
+    t1l: u32,
+}
+
+impl<P: OutputPin> Ws2812<P> {
+    /// Create a driver on `pin`, using the current [`delay`] calibration.
+    pub fn new(mut pin: P) -> Self {
+        let _ = pin.set_low();
+        let mut ws = Ws2812 {
+            pin,
+            t0h: 0,
+            t0l: 0,
+            t1h: 0,
+            t1l: 0,
+        };
+        ws.recalibrate();
+        ws
+    }
+
+    /// Recompute the bit timings from the current [`delay`] calibration.
+    pub fn recalibrate(&mut self) {
+        self.t0h = delay::ns_to_cycles(WS2812_T0H_NS);
+        self.t0l = delay::ns_to_cycles(WS2812_PERIOD_NS - WS2812_T0H_NS);
+        self.t1h = delay::ns_to_cycles(WS2812_T1H_NS);
+        self.t1l = delay::ns_to_cycles(WS2812_PERIOD_NS - WS2812_T1H_NS);
+    }
+
+    /// Send one frame of `(red, green, blue)` colours and latch it.
+    pub fn write<I>(&mut self, colours: I)
+    where
+        I: IntoIterator<Item = (u8, u8, u8)>,
+    {
+        for (r, g, b) in colours {
+            let grb = (u32::from(g) << 16) | (u32::from(r) << 8) | u32::from(b);
+            critical_section::with(|_| self.write_bits(grb));
+        }
+        delay::delay_us(WS2812_RESET_US);
+    }
+
+    #[inline(always)]
+    fn write_bits(&mut self, grb: u32) {
+        for bit in (0..24).rev() {
+            let (high, low) = if grb & (1 << bit) != 0 {
+                (self.t1h, self.t1l)
+            } else {
+                (self.t0h, self.t0l)
+            };
+            let _ = self.pin.set_high();
+            delay::delay_cycles(high);
+            let _ = self.pin.set_low();
+            delay::delay_cycles(low);
+        }
+    }
+
+    /// Return the pin.
+    pub fn free(self) -> P {
+        self.pin
+    }
+}
+
+/// Errors returned by [`OneWire`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OneWireError {
+    /// No device answered the reset pulse.
+    NoPresence,
+    /// The bus is held low, e.g. a short or a missing pull-up.
+    BusLow,
+}
+
+/// A GPIO that pulls the line low or lets it go, and reads what is on it.
+///
+/// The RP2040 has no open-drain outputs, so the pin is kept a readable output
+/// driving low, and setting it high turns the output driver off instead.
+pub struct OpenDrain<I: PinId + BankPinId> {
+    pin: Pin<I, ReadableOutput>,
+}
+
+impl<I: PinId + BankPinId> OpenDrain<I> {
+    /// Take over `pin`, releasing the line.
+    pub fn new<M: PinMode + ValidPinMode<I>>(pin: Pin<I, M>) -> Self {
+        let mut pin = OpenDrain {
+            pin: pin.into_readable_output_in_state(PinState::Low),
+        };
+        let _ = pin.set_high();
+        pin
+    }
+
+    /// Return the pin, as a readable output with its driver off.
+    pub fn free(self) -> Pin<I, ReadableOutput> {
+        self.pin
+    }
+
+    fn mask() -> u32 {
+        1 << I::DYN.num
+    }
+}
+
+impl<I: PinId + BankPinId> OutputPin for OpenDrain<I> {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        // The set and clear aliases touch this pin's bit alone
+        let sio = unsafe { &*pac::SIO::ptr() };
+        sio.gpio_oe_set.write(|w| unsafe { w.bits(Self::mask()) });
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        let sio = unsafe { &*pac::SIO::ptr() };
+        sio.gpio_oe_clr.write(|w| unsafe { w.bits(Self::mask()) });
+        Ok(())
+    }
+}
+
+impl<I: PinId + BankPinId> InputPin for OpenDrain<I> {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        self.pin.is_high()
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        self.pin.is_low()
+    }
+}
+
+/// 1-Wire bus master on a single open-drain pin.
+///
+/// `P` must drive the line low when set low and release it when set high, and
+/// read the actual line level - for example [`OpenDrain`]. The bus needs an
+/// external pull-up, typically 4.7 kΩ.
+pub struct OneWire<P> {
+    pin: P,
+}
+
+impl<P, E> OneWire<P>
+where
+    P: InputPin<Error = E> + OutputPin<Error = E>,
+{
+    /// Create a bus master on `pin`, releasing the line.
+    pub fn new(mut pin: P) -> Self {
+        let _ = pin.set_high();
+        OneWire { pin }
+    }
+
+    fn is_high(&self) -> bool {
+        self.pin.is_high().unwrap_or(false)
+    }
+
+    /// Send a reset pulse and wait for a presence pulse.
+    pub fn reset(&mut self) -> Result<(), OneWireError> {
+        if !self.is_high() {
+            return Err(OneWireError::BusLow);
+        }
+        let _ = self.pin.set_low();
+        delay::delay_us(480);
+        let present = critical_section::with(|_| {
+            let _ = self.pin.set_high();
+            delay::delay_us(70);
+            !self.is_high()
+        });
+        delay::delay_us(410);
+        if present {
+            Ok(())
+        } else {
+            Err(OneWireError::NoPresence)
+        }
+    }
+
+    /// Write a single bit.
+    pub fn write_bit(&mut self, bit: bool) {
+        let (low, high) = if bit { (6, 64) } else { (60, 10) };
+        critical_section::with(|_| {
+            let _ = self.pin.set_low();
+            delay::delay_us(low);
+            let _ = self.pin.set_high();
+        });
+        delay::delay_us(high);
+    }
+
+    /// Read a single bit.
+    pub fn read_bit(&mut self) -> bool {
+        let bit = critical_section::with(|_| {
+            let _ = self.pin.set_low();
+            delay::delay_us(6);
+            let _ = self.pin.set_high();
+            delay::delay_us(9);
+            self.is_high()
+        });
+        delay::delay_us(55);
+        bit
+    }
+
+    /// Write a byte, least significant bit first.
+    pub fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.write_bit(byte & (1 << i) != 0);
+        }
+    }
+
+    /// Read a byte, least significant bit first.
+    pub fn read_byte(&mut self) -> u8 {
+        (0..8).fold(0, |byte, i| byte | (u8::from(self.read_bit()) << i))
+    }
+
+    /// Return the pin.
+    pub fn free(self) -> P {
+        self.pin
+    }
+}
@@ -0,0 +1,77 @@
+//! Hardware revision detection from strapping resistors.
+//!
+//! Small-batch boards built around a BSP tend to change pins between
+//! revisions. Rather than building one firmware per revision, fit each
+//! revision with a different set of strap resistors and read them once at
+//! boot:
+//!
+//! * GPIO straps: each strap pin is read with its internal pull-up enabled,
+//!   and a resistor (or 0 Ω link) to ground makes its bit a 1.
+//!   [`detect_gpio`] turns `n` straps into a revision from 0 to 2ⁿ - 1.
+//! * An ADC strap: a resistor divider on one ADC input, placed at one of
+//!   `levels` evenly spaced voltages between 0 V and 3.3 V. [`detect_adc`]
+//!   turns the reading into a revision from 0 to `levels - 1`, so one pin can
+//!   tell apart many revisions.
+//!
+//! Either function records the result, and [`board_rev`] returns it from
+//! anywhere in the application afterwards.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use embedded_hal::digital::v2::InputPin;
+
+/// Marks the revision as not detected yet.
+const UNKNOWN: u8 = 0xFF;
+
+static REVISION: AtomicU8 = AtomicU8::new(UNKNOWN);
+
+/// The revision found by [`detect_gpio`] or [`detect_adc`], or `None` if
+/// neither has been called yet.
+pub fn board_rev() -> Option<u8> {
+    match REVISION.load(Ordering::Relaxed) {
+        UNKNOWN => None,
+        revision => Some(revision),
+    }
+}
+
+/// Record `revision` as the board's revision, for boards that work it out
+/// some other way.
+pub fn set_board_rev(revision: u8) {
+    assert!(revision != UNKNOWN, "revision 255 is reserved");
+    REVISION.store(revision, Ordering::Relaxed);
+}
+
+/// Read up to seven GPIO straps, `straps[0]` giving bit 0, and record the
+/// revision they encode.
+///
+/// The pins must already be inputs with their pull-ups enabled, and have had
+/// a few microseconds to settle.
+pub fn detect_gpio<P: InputPin>(straps: &[P]) -> Result<u8, P::Error> {
+    assert!(straps.len() < 8, "at most seven GPIO straps");
+    let mut revision = 0;
+    for (bit, strap) in straps.iter().enumerate() {
+        if strap.is_low()? {
+            revision |= 1 << bit;
+        }
+    }
+    set_board_rev(revision);
+    Ok(revision)
+}
+
+/// Turn a 12 bit ADC reading of a divider strap into one of `levels`
+/// revisions, and record it.
+///
+/// Revision `n` is a divider putting `n / (levels - 1)` of the 3.3 V rail on
+/// the pin. Readings are rounded to the nearest level, so resistor tolerance
+/// and ADC noise only matter once `levels` gets above about ten.
+pub fn detect_adc(reading: u16, levels: u8) -> u8 {
+    assert!(
+        (2..UNKNOWN).contains(&levels),
+        "an ADC strap needs between 2 and 254 levels"
+    );
+    let steps = u32::from(levels - 1);
+    let reading = u32::from(reading.min(4095));
+    let revision = ((reading * steps + 4095 / 2) / 4095) as u8;
+    set_board_rev(revision);
+    revision
+}
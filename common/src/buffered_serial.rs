@@ -0,0 +1,187 @@
+//! A USB serial port that queues instead of dropping bytes.
+//!
+//! `usbd_serial::SerialPort::write` only takes what fits in the endpoint's
+//! buffer, and returns `WouldBlock` once that is full. Code that ignores the
+//! count, as most examples do, silently loses the rest of its output.
+//! [`BufferedSerial`] puts an `N` byte ring buffer in front of each
+//! direction: [`write_all`](BufferedSerial::write_all) queues a whole message
+//! or nothing, and [`flush_in_poll`](BufferedSerial::flush_in_poll) moves
+//! bytes between the queues and the endpoints.
+//!
+//! It is a `UsbClass` itself, and flushes whenever the device is polled:
+//!
+//! ```ignore
+//! let mut serial: BufferedSerial<_, 512> = BufferedSerial::new(&usb_bus);
+//! // ...
+//! loop {
+//!     usb_dev.poll(&mut [&mut serial]);
+//!     writeln!(serial, "ticks: {}\r", timer.get_counter().ticks()).ok();
+//! }
+//! ```
+//!
+//! Incoming bytes stop being read from the endpoint while the receive queue is
+//! full, so the host waits rather than losing data.
+
+use heapless::Deque;
+use usb_device::class_prelude::*;
+use usb_device::Result as UsbResult;
+use usbd_serial::SerialPort;
+
+/// Size of the serial port's bulk endpoints.
+const PACKET_SIZE: usize = 64;
+
+/// The queue had no room for the whole message, so none of it was queued.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Full;
+
+/// A [`SerialPort`] with `N` byte transmit and receive queues.
+pub struct BufferedSerial<'a, B: UsbBus, const N: usize> {
+    port: SerialPort<'a, B>,
+    tx: Deque<u8, N>,
+    rx: Deque<u8, N>,
+}
+
+impl<'a, B: UsbBus, const N: usize> BufferedSerial<'a, B, N> {
+    /// Allocate a serial port on `alloc`.
+    pub fn new(alloc: &'a UsbBusAllocator<B>) -> Self {
+        Self::from_port(SerialPort::new(alloc))
+    }
+
+    /// Put queues in front of an existing port, e.g. one with non-default
+    /// buffers.
+    pub fn from_port(port: SerialPort<'a, B>) -> Self {
+        BufferedSerial {
+            port,
+            tx: Deque::new(),
+            rx: Deque::new(),
+        }
+    }
+
+    /// Queue all of `data` for sending, or none of it if there isn't room.
+    pub fn write_all(&mut self, data: &[u8]) -> Result<(), Full> {
+        if self.tx.capacity() - self.tx.len() < data.len() {
+            return Err(Full);
+        }
+        for &byte in data {
+            // Can't fail, there is room for all of it
+            let _ = self.tx.push_back(byte);
+        }
+        Ok(())
+    }
+
+    /// Take received bytes out of the queue into `buf`, returning how many.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let mut count = 0;
+        for slot in buf.iter_mut() {
+            match self.rx.pop_front() {
+                Some(byte) => *slot = byte,
+                None => break,
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Number of bytes waiting to be sent.
+    pub fn pending(&self) -> usize {
+        self.tx.len()
+    }
+
+    /// Number of received bytes waiting to be read.
+    pub fn available(&self) -> usize {
+        self.rx.len()
+    }
+
+    /// Send as much of the transmit queue as the endpoint takes, and receive
+    /// as much as the receive queue has room for.
+    ///
+    /// Called from the device's poll through the `UsbClass` implementation,
+    /// so only call it by hand when polling the port some other way.
+    pub fn flush_in_poll(&mut self) {
+        while !self.tx.is_empty() {
+            let mut packet = [0u8; PACKET_SIZE];
+            let mut len = 0;
+            for (slot, &byte) in packet.iter_mut().zip(self.tx.iter()) {
+                *slot = byte;
+                len += 1;
+            }
+            match self.port.write(&packet[..len]) {
+                Ok(written) if written > 0 => {
+                    for _ in 0..written {
+                        self.tx.pop_front();
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        while self.rx.capacity() - self.rx.len() >= PACKET_SIZE {
+            let mut packet = [0u8; PACKET_SIZE];
+            match self.port.read(&mut packet) {
+                Ok(count) if count > 0 => {
+                    for &byte in &packet[..count] {
+                        let _ = self.rx.push_back(byte);
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// The underlying port, for its line coding and control lines.
+    pub fn port(&mut self) -> &mut SerialPort<'a, B> {
+        &mut self.port
+    }
+}
+
+impl<B: UsbBus, const N: usize> core::fmt::Write for BufferedSerial<'_, B, N> {
+    /// Queue `s`, failing without queueing any of it if it doesn't fit.
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+impl<B: UsbBus, const N: usize> UsbClass<B> for BufferedSerial<'_, B, N> {
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> UsbResult<()> {
+        self.port.get_configuration_descriptors(writer)
+    }
+
+    fn get_bos_descriptors(&self, writer: &mut BosWriter) -> UsbResult<()> {
+        self.port.get_bos_descriptors(writer)
+    }
+
+    fn get_string(&self, index: StringIndex, lang_id: u16) -> Option<&str> {
+        self.port.get_string(index, lang_id)
+    }
+
+    fn reset(&mut self) {
+        self.port.reset();
+        self.tx.clear();
+        self.rx.clear();
+    }
+
+    fn poll(&mut self) {
+        self.port.poll();
+        self.flush_in_poll();
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        self.port.control_out(xfer);
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        self.port.control_in(xfer);
+    }
+
+    fn endpoint_setup(&mut self, addr: EndpointAddress) {
+        self.port.endpoint_setup(addr);
+    }
+
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        self.port.endpoint_out(addr);
+    }
+
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        self.port.endpoint_in_complete(addr);
+    }
+}
@@ -0,0 +1,435 @@
+//! Per-sensor calibration, editable from the shell and kept in flash.
+//!
+//! A [`Sensor`] gives raw readings. Wrapping it in [`Calibrated`] gives it a
+//! name and a [`Calibration`], which is applied to every reading on the way
+//! out:
+//!
+//! 1. the curve: straight lines through the calibration points, each mapping
+//!    a raw reading to the value it should read as,
+//! 2. the scale, in thousandths,
+//! 3. the offset.
+//!
+//! With no points the curve leaves readings alone, and with one it shifts
+//! them so that point reads right. Two points make the usual two-point
+//! calibration, and more follow a non-linear sensor such as a thermistor.
+//!
+//! A [`Filtered`] sensor runs its readings through one of the
+//! [`filters`](crate::filters) first, so a calibration point can be taken from
+//! a steady reading.
+//!
+//! [`command`] is a [`shell`](crate::shell) command for viewing and editing
+//! the calibrations of a set of sensors, and saving them with [`save`]:
+//!
+//! ```text
+//! > cal
+//! pot: raw 2051 -> 2051, offset 0, scale 1000, no points
+//! > cal pot point 0 0
+//! > cal pot point 4095 1000
+//! > cal pot offset -3
+//! > cal save
+//! ```
+//!
+//! `cal <name> point <value>` takes the sensor's current raw reading as the
+//! point's raw side. `cal <name> reset` goes back to the calibration the
+//! firmware was built with.
+//!
+//! [`load`] restores saved calibrations at start-up, matching them to sensors
+//! by name, so sensors can be added or removed without losing the others.
+//! Calibrations take a [`settings::Store`](crate::settings::Store) of their
+//! own, not shared with any other settings record.
+
+use core::fmt::Write;
+
+use crate::filters::Filter;
+use crate::settings::{Store, MAX_LEN};
+
+/// Most points a curve can have.
+pub const MAX_POINTS: usize = 8;
+
+/// Scale of 1, in thousandths.
+pub const UNITY_SCALE: i32 = 1000;
+
+/// Version of the stored record's layout.
+const VERSION: u16 = 1;
+
+/// Something that takes readings.
+pub trait Sensor {
+    type Error;
+
+    /// Take a reading.
+    fn read(&mut self) -> Result<i32, Self::Error>;
+}
+
+/// How to turn a sensor's raw readings into values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    /// Added last.
+    pub offset: i32,
+    /// Applied after the curve, in thousandths.
+    pub scale: i32,
+    /// `(raw, value)` pairs, sorted by raw reading.
+    points: [(i32, i32); MAX_POINTS],
+    count: usize,
+}
+
+/// The curve already has [`MAX_POINTS`] points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooManyPoints;
+
+impl Calibration {
+    /// Readings pass straight through.
+    pub const IDENTITY: Calibration = Calibration {
+        offset: 0,
+        scale: UNITY_SCALE,
+        points: [(0, 0); MAX_POINTS],
+        count: 0,
+    };
+
+    /// A calibration following the curve through `points`, e.g. from a
+    /// datasheet table.
+    pub fn with_points(points: &[(i32, i32)]) -> Result<Self, TooManyPoints> {
+        let mut calibration = Self::IDENTITY;
+        for &(raw, value) in points {
+            calibration.set_point(raw, value)?;
+        }
+        Ok(calibration)
+    }
+
+    /// The curve's points, sorted by raw reading.
+    pub fn points(&self) -> &[(i32, i32)] {
+        &self.points[..self.count]
+    }
+
+    /// Make `raw` read as `value`, replacing any point already at `raw`.
+    pub fn set_point(&mut self, raw: i32, value: i32) -> Result<(), TooManyPoints> {
+        let points = &mut self.points[..self.count];
+        match points.binary_search_by_key(&raw, |&(raw, _)| raw) {
+            Ok(index) => points[index].1 = value,
+            Err(index) => {
+                if self.count == MAX_POINTS {
+                    return Err(TooManyPoints);
+                }
+                self.points.copy_within(index..self.count, index + 1);
+                self.points[index] = (raw, value);
+                self.count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove all points, leaving the offset and scale.
+    pub fn clear_points(&mut self) {
+        self.count = 0;
+    }
+
+    /// The calibrated value of a raw reading.
+    pub fn apply(&self, raw: i32) -> i32 {
+        let value = self.curve(raw);
+        let scaled = i64::from(value) * i64::from(self.scale) / i64::from(UNITY_SCALE);
+        (scaled + i64::from(self.offset)).clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32
+    }
+
+    /// Follow the curve, carrying on along the first and last lines outside
+    /// the points.
+    fn curve(&self, raw: i32) -> i32 {
+        let points = self.points();
+        match points.len() {
+            0 => raw,
+            1 => raw - points[0].0 + points[0].1,
+            len => {
+                let segment = points
+                    .windows(2)
+                    .position(|pair| raw < pair[1].0)
+                    .unwrap_or(len - 2);
+                let ((x0, y0), (x1, y1)) = (points[segment], points[segment + 1]);
+                let (x0, y0, x1, y1) = (i64::from(x0), i64::from(y0), i64::from(x1), i64::from(y1));
+                let value = y0 + (i64::from(raw) - x0) * (y1 - y0) / (x1 - x0);
+                value.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32
+            }
+        }
+    }
+
+    /// Bytes taken by [`write_to`](Self::write_to).
+    pub fn encoded_len(&self) -> usize {
+        9 + 8 * self.count
+    }
+
+    /// Pack into `buf`, returning the length used, or `None` if it doesn't
+    /// fit.
+    pub fn write_to(&self, buf: &mut [u8]) -> Option<usize> {
+        let len = self.encoded_len();
+        let buf = buf.get_mut(..len)?;
+        buf[0..4].copy_from_slice(&self.offset.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.scale.to_le_bytes());
+        buf[8] = self.count as u8;
+        for (chunk, &(raw, value)) in buf[9..].chunks_exact_mut(8).zip(self.points()) {
+            chunk[0..4].copy_from_slice(&raw.to_le_bytes());
+            chunk[4..8].copy_from_slice(&value.to_le_bytes());
+        }
+        Some(len)
+    }
+
+    /// Unpack from the start of `buf`, returning the calibration and the
+    /// length it took.
+    pub fn read_from(buf: &[u8]) -> Option<(Self, usize)> {
+        let word = |at: usize| -> Option<i32> {
+            let bytes = buf.get(at..at + 4)?;
+            Some(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
+        let mut calibration = Self::IDENTITY;
+        calibration.offset = word(0)?;
+        calibration.scale = word(4)?;
+        let count = usize::from(*buf.get(8)?);
+        if count > MAX_POINTS {
+            return None;
+        }
+        let len = 9 + 8 * count;
+        for index in 0..count {
+            let at = 9 + 8 * index;
+            calibration.set_point(word(at)?, word(at + 4)?).ok()?;
+        }
+        Some((calibration, len))
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// A sensor whose readings go through a filter.
+pub struct Filtered<S, F> {
+    pub sensor: S,
+    pub filter: F,
+}
+
+impl<S: Sensor, F: Filter> Sensor for Filtered<S, F> {
+    type Error = S::Error;
+
+    fn read(&mut self) -> Result<i32, S::Error> {
+        Ok(self.filter.update(self.sensor.read()?))
+    }
+}
+
+/// A named sensor with a calibration applied to its readings.
+pub struct Calibrated<S> {
+    name: &'static str,
+    sensor: S,
+    calibration: Calibration,
+    default: Calibration,
+}
+
+impl<S: Sensor> Calibrated<S> {
+    /// Calibrate `sensor`, starting from `default` until a saved calibration
+    /// is loaded.
+    ///
+    /// The name is how the shell and the stored record refer to the sensor,
+    /// so keep it short, unique and free of spaces.
+    pub fn new(name: &'static str, sensor: S, default: Calibration) -> Self {
+        Calibrated {
+            name,
+            sensor,
+            calibration: default,
+            default,
+        }
+    }
+
+    /// The sensor underneath.
+    pub fn sensor(&mut self) -> &mut S {
+        &mut self.sensor
+    }
+}
+
+impl<S: Sensor> Sensor for Calibrated<S> {
+    type Error = S::Error;
+
+    fn read(&mut self) -> Result<i32, S::Error> {
+        Ok(self.calibration.apply(self.sensor.read()?))
+    }
+}
+
+/// A calibrated sensor, whatever its type, as [`command`], [`load`] and
+/// [`save`] see it.
+pub trait Calibrate {
+    /// The name it is known by.
+    fn name(&self) -> &'static str;
+
+    /// The calibration in use.
+    fn calibration(&mut self) -> &mut Calibration;
+
+    /// The calibration the firmware was built with.
+    fn default_calibration(&self) -> Calibration;
+
+    /// A reading before calibration, or `None` if the sensor failed.
+    fn read_raw(&mut self) -> Option<i32>;
+}
+
+impl<S: Sensor> Calibrate for Calibrated<S> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn calibration(&mut self) -> &mut Calibration {
+        &mut self.calibration
+    }
+
+    fn default_calibration(&self) -> Calibration {
+        self.default
+    }
+
+    fn read_raw(&mut self) -> Option<i32> {
+        self.sensor.read().ok()
+    }
+}
+
+/// Restore the calibrations saved in `store`, for the sensors it has one
+/// for. Returns how many were restored.
+pub fn load(sensors: &mut [&mut dyn Calibrate], store: &Store) -> usize {
+    let mut buf = [0u8; MAX_LEN];
+    let len = match store.load_version(&mut buf) {
+        Some((VERSION, len)) => len,
+        _ => return 0,
+    };
+    let mut data = &buf[..len];
+    let mut restored = 0;
+    // Each entry: name length, name, calibration
+    while let Some((&name_len, rest)) = data.split_first() {
+        let name_len = usize::from(name_len);
+        let name = match rest.get(..name_len) {
+            Some(name) => name,
+            None => break,
+        };
+        let (calibration, used) = match Calibration::read_from(&rest[name_len..]) {
+            Some(entry) => entry,
+            None => break,
+        };
+        if let Some(sensor) = sensors
+            .iter_mut()
+            .find(|sensor| sensor.name().as_bytes() == name)
+        {
+            *sensor.calibration() = calibration;
+            restored += 1;
+        }
+        data = &rest[name_len + used..];
+    }
+    restored
+}
+
+/// The saved calibrations don't fit in a settings record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooLarge;
+
+/// Save the calibrations of `sensors` to `store`.
+///
+/// This writes flash; see [`Store::save`] for what that means for the rest
+/// of the program.
+pub fn save(sensors: &mut [&mut dyn Calibrate], store: &mut Store) -> Result<(), TooLarge> {
+    let mut buf = [0u8; MAX_LEN];
+    let mut len = 0;
+    for sensor in sensors.iter_mut() {
+        let name = sensor.name().as_bytes();
+        let entry = buf.get_mut(len..).ok_or(TooLarge)?;
+        let (name_len, rest) = entry.split_first_mut().ok_or(TooLarge)?;
+        *name_len = name.len() as u8;
+        rest.get_mut(..name.len())
+            .ok_or(TooLarge)?
+            .copy_from_slice(name);
+        let used = sensor
+            .calibration()
+            .write_to(&mut rest[name.len()..])
+            .ok_or(TooLarge)?;
+        len += 1 + name.len() + used;
+    }
+    store
+        .save_version(VERSION, &buf[..len])
+        .map_err(|_| TooLarge)
+}
+
+/// The `cal` shell command, for the calibrations of `sensors`.
+///
+/// Forward to it from a [`Command`](crate::shell::Command) handler:
+///
+/// ```ignore
+/// Command {
+///     name: "cal",
+///     help: calibration::HELP,
+///     run: |context, args, out| {
+///         calibration::command(&mut [&mut context.pot], &mut context.store, args, out)
+///     },
+/// }
+/// ```
+pub fn command(
+    sensors: &mut [&mut dyn Calibrate],
+    store: &mut Store,
+    args: &mut crate::shell::Args<'_>,
+    out: &mut dyn Write,
+) -> Result<(), &'static str> {
+    let name = match args.next() {
+        None => {
+            for sensor in sensors.iter_mut() {
+                show(&mut **sensor, out).map_err(|_| "output failed")?;
+            }
+            return Ok(());
+        }
+        Some("save") => return save(sensors, store).map_err(|_| "too much to save"),
+        Some(name) => name,
+    };
+
+    let sensor = sensors
+        .iter_mut()
+        .find(|sensor| sensor.name() == name)
+        .ok_or("no such sensor")?;
+    let number = |arg: Option<&str>| -> Result<i32, &'static str> {
+        arg.ok_or("missing number")?
+            .parse()
+            .map_err(|_| "not a number")
+    };
+    match args.next() {
+        None => {}
+        Some("offset") => sensor.calibration().offset = number(args.next())?,
+        Some("scale") => sensor.calibration().scale = number(args.next())?,
+        Some("point") => {
+            let first = number(args.next())?;
+            let (raw, value) = match args.next() {
+                Some(value) => (first, number(Some(value))?),
+                None => (sensor.read_raw().ok_or("sensor failed")?, first),
+            };
+            sensor
+                .calibration()
+                .set_point(raw, value)
+                .map_err(|_| "too many points")?;
+        }
+        Some("clear") => sensor.calibration().clear_points(),
+        Some("reset") => *sensor.calibration() = sensor.default_calibration(),
+        Some(_) => return Err("expected offset, scale, point, clear or reset"),
+    }
+    show(&mut **sensor, out).map_err(|_| "output failed")
+}
+
+/// Usage of [`command`], for its [`Command::help`](crate::shell::Command::help).
+pub const HELP: &str =
+    "cal [save | <sensor> [offset <n> | scale <n> | point [<raw>] <value> | clear | reset]]";
+
+/// Print a sensor's reading and calibration on one line.
+fn show(sensor: &mut dyn Calibrate, out: &mut dyn Write) -> core::fmt::Result {
+    write!(out, "{}: ", sensor.name())?;
+    match sensor.read_raw() {
+        Some(raw) => write!(out, "raw {} -> {}", raw, sensor.calibration().apply(raw))?,
+        None => out.write_str("failed")?,
+    }
+    let calibration = *sensor.calibration();
+    write!(
+        out,
+        ", offset {}, scale {}, ",
+        calibration.offset, calibration.scale
+    )?;
+    if calibration.points().is_empty() {
+        out.write_str("no points")?;
+    }
+    for (index, (raw, value)) in calibration.points().iter().enumerate() {
+        let separator = if index == 0 { "points" } else { "," };
+        write!(out, "{} {}:{}", separator, raw, value)?;
+    }
+    out.write_str("\r\n")
+}
@@ -0,0 +1,488 @@
+//! Clock set-up for crystals other than 12 MHz.
+//!
+//! `hal::clocks::init_clocks_and_plls` takes the crystal frequency, but its
+//! PLL settings only work out exactly for a 12 MHz crystal. Here the PLL
+//! settings are worked out from the crystal: USB gets exactly 48 MHz, and the
+//! system clock the nearest frequency to 125 MHz the crystal allows.
+//!
+//! For battery-powered firmware there are two slower profiles, each a
+//! replacement for `init_clocks_and_plls`:
+//!
+//! * [`init_clocks_from_usb_pll`] runs everything at 48 MHz from the USB PLL,
+//!   which keeps USB working, and leaves the system PLL in reset,
+//! * [`init_clocks_from_rosc`] runs everything from the ring oscillator, with
+//!   the crystal, both PLLs and USB stopped.
+//!
+//! Going the other way, [`overclock`] runs the system clock faster than
+//! 125 MHz, raising the core voltage and slowing the flash interface to suit:
+//!
+//! ```ignore
+//! const SYS: Overclock = Overclock::mhz(250);
+//! let clocks = clocks::overclock(SYS, 12_000_000, /* ... */)?;
+//! ```
+
+use fugit::RateExtU32;
+
+use crate::hal::{
+    clocks::{Clock, ClockSource, ClocksManager, InitError, StoppableClock},
+    pac,
+    pll::{setup_pll_blocking, PLLConfig},
+    rosc::RingOscillator,
+    xosc::setup_xosc_blocking,
+    Watchdog,
+};
+
+/// Fastest and slowest crystal the XOSC drives.
+const XOSC_HZ: core::ops::RangeInclusive<u32> = 1_000_000..=15_000_000;
+/// Range of the PLL's voltage-controlled oscillator.
+const VCO_HZ: core::ops::RangeInclusive<u32> = 750_000_000..=1_600_000_000;
+/// Range of the PLL's feedback divider.
+const FBDIV: core::ops::RangeInclusive<u32> = 16..=320;
+
+const SYS_TARGET_HZ: u32 = 125_000_000;
+const USB_HZ: u32 = 48_000_000;
+/// `clk_rtc` frequency, as `init_clocks_and_plls` sets it.
+const RTC_HZ: u32 = 46_875;
+
+/// Errors from the clock set-up functions.
+pub enum Error {
+    /// The crystal is outside the 1 to 15 MHz the XOSC supports.
+    CrystalOutOfRange,
+    /// No PLL setting gives exactly 48 MHz for USB from this crystal.
+    NoUsbClock,
+    /// No PLL setting gives exactly the [`Overclock`] frequency from this
+    /// crystal.
+    NoSysClock,
+    /// Starting the crystal, the PLLs or the clocks failed.
+    Init(InitError),
+}
+
+// Written out, as the HAL's `InitError` isn't `Debug`, though what it holds is
+impl core::fmt::Debug for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::CrystalOutOfRange => f.write_str("CrystalOutOfRange"),
+            Error::NoUsbClock => f.write_str("NoUsbClock"),
+            Error::NoSysClock => f.write_str("NoSysClock"),
+            Error::Init(InitError::XoscErr(e)) => f.debug_tuple("Xosc").field(e).finish(),
+            Error::Init(InitError::PllError(e)) => f.debug_tuple("Pll").field(e).finish(),
+            Error::Init(InitError::ClockError(e)) => f.debug_tuple("Clock").field(e).finish(),
+        }
+    }
+}
+
+/// PLL settings for the system clock, as near 125 MHz as this crystal allows
+/// without going over.
+pub fn pll_sys_config(xosc_hz: u32) -> Option<PLLConfig> {
+    let mut best: Option<(u32, PLLConfig)> = None;
+    for fbdiv in FBDIV {
+        let vco = xosc_hz.saturating_mul(fbdiv);
+        if !VCO_HZ.contains(&vco) {
+            continue;
+        }
+        for post_div1 in 1..=7u8 {
+            for post_div2 in 1..=post_div1 {
+                let sys = vco / (u32::from(post_div1) * u32::from(post_div2));
+                if sys > SYS_TARGET_HZ || best.as_ref().is_some_and(|(hz, _)| *hz >= sys) {
+                    continue;
+                }
+                best = Some((
+                    sys,
+                    PLLConfig {
+                        vco_freq: vco.Hz(),
+                        refdiv: 1,
+                        post_div1,
+                        post_div2,
+                    },
+                ));
+            }
+        }
+    }
+    best.map(|(_, config)| config)
+}
+
+/// PLL settings giving exactly 48 MHz for USB, if this crystal allows it.
+pub fn pll_usb_config(xosc_hz: u32) -> Option<PLLConfig> {
+    for post_div1 in (1..=7u8).rev() {
+        for post_div2 in (1..=post_div1).rev() {
+            let vco = USB_HZ * u32::from(post_div1) * u32::from(post_div2);
+            if VCO_HZ.contains(&vco)
+                && vco.is_multiple_of(xosc_hz)
+                && FBDIV.contains(&(vco / xosc_hz))
+            {
+                return Some(PLLConfig {
+                    vco_freq: vco.Hz(),
+                    refdiv: 1,
+                    post_div1,
+                    post_div2,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Like `hal::clocks::init_clocks_and_plls`, but with PLL settings worked out
+/// for a crystal of `xosc_hz`.
+///
+/// Check `system_clock.freq()` on the result rather than assuming 125 MHz.
+pub fn init_clocks_and_plls_with_freq(
+    xosc_hz: u32,
+    xosc_dev: pac::XOSC,
+    clocks_dev: pac::CLOCKS,
+    pll_sys_dev: pac::PLL_SYS,
+    pll_usb_dev: pac::PLL_USB,
+    resets: &mut pac::RESETS,
+    watchdog: &mut Watchdog,
+) -> Result<ClocksManager, Error> {
+    if !XOSC_HZ.contains(&xosc_hz) {
+        return Err(Error::CrystalOutOfRange);
+    }
+    let sys_config = pll_sys_config(xosc_hz).ok_or(Error::CrystalOutOfRange)?;
+    let usb_config = pll_usb_config(xosc_hz).ok_or(Error::NoUsbClock)?;
+
+    start_clocks(
+        xosc_hz,
+        sys_config,
+        usb_config,
+        xosc_dev,
+        clocks_dev,
+        pll_sys_dev,
+        pll_usb_dev,
+        resets,
+        watchdog,
+    )
+}
+
+/// Start the crystal and both PLLs with these settings, and the clocks as
+/// `init_clocks_and_plls` does.
+#[allow(clippy::too_many_arguments)]
+fn start_clocks(
+    xosc_hz: u32,
+    sys_config: PLLConfig,
+    usb_config: PLLConfig,
+    xosc_dev: pac::XOSC,
+    clocks_dev: pac::CLOCKS,
+    pll_sys_dev: pac::PLL_SYS,
+    pll_usb_dev: pac::PLL_USB,
+    resets: &mut pac::RESETS,
+    watchdog: &mut Watchdog,
+) -> Result<ClocksManager, Error> {
+    let xosc = setup_xosc_blocking(xosc_dev, xosc_hz.Hz())
+        .map_err(|e| Error::Init(InitError::XoscErr(e)))?;
+
+    // Configure watchdog tick generation to tick over every microsecond
+    watchdog.enable_tick_generation((xosc_hz / 1_000_000) as u8);
+
+    let mut clocks = ClocksManager::new(clocks_dev);
+
+    let pll_sys = setup_pll_blocking(
+        pll_sys_dev,
+        xosc.operating_frequency(),
+        sys_config,
+        &mut clocks,
+        resets,
+    )
+    .map_err(|e| Error::Init(InitError::PllError(e)))?;
+    let pll_usb = setup_pll_blocking(
+        pll_usb_dev,
+        xosc.operating_frequency(),
+        usb_config,
+        &mut clocks,
+        resets,
+    )
+    .map_err(|e| Error::Init(InitError::PllError(e)))?;
+
+    clocks
+        .init_default(&xosc, &pll_sys, &pll_usb)
+        .map_err(|e| Error::Init(InitError::ClockError(e)))?;
+    Ok(clocks)
+}
+
+/// Start the crystal and the USB PLL, and run every clock from the PLL's
+/// 48 MHz, leaving the system PLL in reset.
+///
+/// USB, the ADC and the RTC work as after `init_clocks_and_plls`, while
+/// `clk_sys` and `clk_peri` run at 48 MHz rather than 125 MHz, for roughly
+/// a third of the core's running current.
+pub fn init_clocks_from_usb_pll(
+    xosc_hz: u32,
+    xosc_dev: pac::XOSC,
+    clocks_dev: pac::CLOCKS,
+    _pll_sys_dev: pac::PLL_SYS,
+    pll_usb_dev: pac::PLL_USB,
+    resets: &mut pac::RESETS,
+    watchdog: &mut Watchdog,
+) -> Result<ClocksManager, Error> {
+    if !XOSC_HZ.contains(&xosc_hz) {
+        return Err(Error::CrystalOutOfRange);
+    }
+    let usb_config = pll_usb_config(xosc_hz).ok_or(Error::NoUsbClock)?;
+
+    let xosc = setup_xosc_blocking(xosc_dev, xosc_hz.Hz())
+        .map_err(|e| Error::Init(InitError::XoscErr(e)))?;
+
+    // Configure watchdog tick generation to tick over every microsecond
+    watchdog.enable_tick_generation((xosc_hz / 1_000_000) as u8);
+
+    let mut clocks = ClocksManager::new(clocks_dev);
+
+    let pll_usb = setup_pll_blocking(
+        pll_usb_dev,
+        xosc.operating_frequency(),
+        usb_config,
+        &mut clocks,
+        resets,
+    )
+    .map_err(|e| Error::Init(InitError::PllError(e)))?;
+
+    let clock_err = |e| Error::Init(InitError::ClockError(e));
+    clocks
+        .reference_clock
+        .configure_clock(&xosc, xosc.get_freq())
+        .map_err(clock_err)?;
+    clocks
+        .system_clock
+        .configure_clock(&pll_usb, pll_usb.get_freq())
+        .map_err(clock_err)?;
+    clocks
+        .usb_clock
+        .configure_clock(&pll_usb, pll_usb.get_freq())
+        .map_err(clock_err)?;
+    clocks
+        .adc_clock
+        .configure_clock(&pll_usb, pll_usb.get_freq())
+        .map_err(clock_err)?;
+    clocks
+        .rtc_clock
+        .configure_clock(&pll_usb, RTC_HZ.Hz())
+        .map_err(clock_err)?;
+    clocks
+        .peripheral_clock
+        .configure_clock(&clocks.system_clock, clocks.system_clock.freq())
+        .map_err(clock_err)?;
+
+    // Nothing runs from the system PLL now, and taking it keeps it that way
+    resets.reset.modify(|_, w| w.pll_sys().set_bit());
+    Ok(clocks)
+}
+
+/// Run every clock from the ring oscillator, with the crystal, both PLLs and
+/// USB stopped.
+///
+/// This is the least current the chip draws while running. The ring
+/// oscillator is nominally 6.5 MHz, which is what the returned frequencies
+/// say, but varies between chips and with voltage and temperature, from
+/// roughly 2 to 12 MHz: the timer, which counts `clk_ref` cycles as
+/// microseconds, and UART baud rates are only as accurate as that. `clk_usb`
+/// and `clk_rtc` are stopped, and
+/// [`dormant_until_edge`](crate::power::dormant_until_edge), which needs
+/// `clk_ref` on the crystal, can't be used.
+pub fn init_clocks_from_rosc(
+    rosc_dev: pac::ROSC,
+    xosc_dev: pac::XOSC,
+    clocks_dev: pac::CLOCKS,
+    _pll_sys_dev: pac::PLL_SYS,
+    _pll_usb_dev: pac::PLL_USB,
+    resets: &mut pac::RESETS,
+    watchdog: &mut Watchdog,
+) -> Result<ClocksManager, Error> {
+    let rosc = RingOscillator::new(rosc_dev).initialize();
+    let rosc_hz = rosc.get_freq();
+
+    // As near to a microsecond as the nominal frequency allows
+    watchdog.enable_tick_generation((rosc_hz.to_Hz() / 1_000_000) as u8);
+
+    let mut clocks = ClocksManager::new(clocks_dev);
+    let clock_err = |e| Error::Init(InitError::ClockError(e));
+    clocks
+        .reference_clock
+        .configure_clock(&rosc, rosc_hz)
+        .map_err(clock_err)?;
+    clocks
+        .system_clock
+        .configure_clock(&rosc, rosc_hz)
+        .map_err(clock_err)?;
+    clocks
+        .adc_clock
+        .configure_clock(&rosc, rosc_hz)
+        .map_err(clock_err)?;
+    clocks
+        .peripheral_clock
+        .configure_clock(&clocks.system_clock, clocks.system_clock.freq())
+        .map_err(clock_err)?;
+    clocks.usb_clock.disable();
+    clocks.rtc_clock.disable();
+
+    // Nothing runs from the PLLs or the crystal now, and taking them keeps
+    // it that way
+    resets
+        .reset
+        .modify(|_, w| w.pll_sys().set_bit().pll_usb().set_bit());
+    xosc_dev.ctrl.modify(|_, w| w.enable().disable());
+    Ok(clocks)
+}
+
+/// Slowest system clock [`overclock`] takes, in MHz.
+const OVERCLOCK_MIN_MHZ: u32 = 125;
+/// Fastest system clock [`overclock`] takes, in MHz.
+const OVERCLOCK_MAX_MHZ: u32 = 300;
+
+/// Fastest the flash interface is run, the limit of the usual QSPI flash
+/// chips.
+const MAX_QSPI_HZ: u32 = 133_000_000;
+
+/// A system clock frequency for [`overclock`], with the core voltage it
+/// needs.
+///
+/// Make it in a `const` item, and a frequency outside 125 to 300 MHz fails
+/// the build rather than panicking at run time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overclock {
+    mhz: u32,
+}
+
+impl Overclock {
+    /// A system clock of `mhz` MHz.
+    ///
+    /// # Panics
+    ///
+    /// If `mhz` is outside 125 to 300.
+    pub const fn mhz(mhz: u32) -> Self {
+        assert!(mhz >= OVERCLOCK_MIN_MHZ, "overclock below 125 MHz");
+        assert!(mhz <= OVERCLOCK_MAX_MHZ, "overclock above 300 MHz");
+        Overclock { mhz }
+    }
+
+    /// The frequency, in Hz.
+    pub const fn to_hz(self) -> u32 {
+        self.mhz * 1_000_000
+    }
+
+    /// The core voltage this frequency needs, in millivolts.
+    ///
+    /// These are margins most RP2040s run with, from the voltage the Pico SDK
+    /// uses for 200 MHz upwards; an individual chip may need more.
+    pub const fn millivolts(self) -> u32 {
+        match self.mhz {
+            0..=133 => 1100,
+            134..=200 => 1150,
+            201..=250 => 1200,
+            251..=275 => 1250,
+            _ => 1300,
+        }
+    }
+
+    /// `VREG.VSEL` for [`millivolts`](Self::millivolts): 1.10 V is 0b1011,
+    /// and each step above it is 50 mV.
+    const fn vsel(self) -> u8 {
+        (0b1011 + (self.millivolts() - 1100) / 50) as u8
+    }
+
+    /// The smallest even flash clock divisor that keeps the flash within
+    /// [`MAX_QSPI_HZ`].
+    const fn qspi_divisor(self) -> u32 {
+        let div = self.to_hz().div_ceil(MAX_QSPI_HZ);
+        (div + 1) & !1
+    }
+}
+
+/// PLL settings giving exactly `sys_hz`, if this crystal allows it, with the
+/// fastest VCO for the least jitter.
+pub fn pll_sys_config_exact(xosc_hz: u32, sys_hz: u32) -> Option<PLLConfig> {
+    for fbdiv in FBDIV.rev() {
+        let vco = xosc_hz.saturating_mul(fbdiv);
+        if !VCO_HZ.contains(&vco) {
+            continue;
+        }
+        for post_div1 in 1..=7u8 {
+            for post_div2 in 1..=post_div1 {
+                if vco == sys_hz * u32::from(post_div1) * u32::from(post_div2) {
+                    return Some(PLLConfig {
+                        vco_freq: vco.Hz(),
+                        refdiv: 1,
+                        post_div1,
+                        post_div2,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Like [`init_clocks_and_plls_with_freq`], with the system clock at
+/// `frequency`.
+///
+/// The core voltage is raised to [`Overclock::millivolts`] and the flash
+/// clock divisor to keep the flash within 133 MHz, both before the system
+/// clock speeds up; neither is lowered. `clk_peri` follows `clk_sys`, as after
+/// `init_clocks_and_plls`, so peripherals like SPI can run faster too. USB,
+/// the ADC and the RTC are unchanged.
+///
+/// Call this before starting the other core, as it briefly stops the flash
+/// interface.
+#[allow(clippy::too_many_arguments)]
+pub fn overclock(
+    frequency: Overclock,
+    xosc_hz: u32,
+    xosc_dev: pac::XOSC,
+    clocks_dev: pac::CLOCKS,
+    pll_sys_dev: pac::PLL_SYS,
+    pll_usb_dev: pac::PLL_USB,
+    vreg_dev: &mut pac::VREG_AND_CHIP_RESET,
+    resets: &mut pac::RESETS,
+    watchdog: &mut Watchdog,
+) -> Result<ClocksManager, Error> {
+    if !XOSC_HZ.contains(&xosc_hz) {
+        return Err(Error::CrystalOutOfRange);
+    }
+    let sys_config = pll_sys_config_exact(xosc_hz, frequency.to_hz()).ok_or(Error::NoSysClock)?;
+    let usb_config = pll_usb_config(xosc_hz).ok_or(Error::NoUsbClock)?;
+
+    let vsel = vreg_dev.vreg.read().vsel().bits();
+    if frequency.vsel() > vsel {
+        vreg_dev
+            .vreg
+            .modify(|_, w| unsafe { w.vsel().bits(frequency.vsel()) });
+        // Let the voltage settle: at least 1 ms at the ring oscillator's
+        // fastest
+        cortex_m::asm::delay(15_000);
+    }
+
+    // Note (safety): interrupts are off, so nothing runs from flash meanwhile
+    cortex_m::interrupt::free(|_| unsafe { raise_qspi_divisor(frequency.qspi_divisor()) });
+
+    start_clocks(
+        xosc_hz,
+        sys_config,
+        usb_config,
+        xosc_dev,
+        clocks_dev,
+        pll_sys_dev,
+        pll_usb_dev,
+        resets,
+        watchdog,
+    )
+}
+
+/// Raise the flash interface's clock divisor to `divisor`, if it is lower.
+///
+/// Runs from RAM, as the interface stops while the divisor changes. The
+/// caller must disable interrupts.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn raise_qspi_divisor(divisor: u32) {
+    const SSIENR: *mut u32 = 0x1800_0008 as *mut u32;
+    const BAUDR: *mut u32 = 0x1800_0014 as *mut u32;
+    const SR: *const u32 = 0x1800_0028 as *const u32;
+    const SR_BUSY: u32 = 1;
+
+    if BAUDR.read_volatile() >= divisor {
+        return;
+    }
+    while SR.read_volatile() & SR_BUSY != 0 {}
+    SSIENR.write_volatile(0);
+    BAUDR.write_volatile(divisor);
+    SSIENR.write_volatile(1);
+}
@@ -0,0 +1,140 @@
+//! An append-only log of fixed-size records in a ring of flash sectors.
+//!
+//! Records are collected in a RAM page and programmed to flash when the page
+//! is full, or on [`Log::flush`], so flash is written once per
+//! [`RECORDS_PER_PAGE`] records. Each page starts with a sequence number:
+//!
+//! ```text
+//! sequence (u32) | record 0 | record 1 | ... | record 30
+//! ```
+//!
+//! so [`Log::new`] can find where the last run stopped. When the log reaches
+//! the end of its region it wraps around, erasing the oldest sector.
+//!
+//! Records still in RAM are lost on a reset or power failure. Like
+//! [`settings`](crate::settings), writing runs from RAM with interrupts
+//! disabled, and the other core must not be running from flash meanwhile.
+
+use crate::flash_storage::{self, Error, PAGE_SIZE, SECTOR_SIZE};
+
+/// Size of one record.
+pub const RECORD_LEN: usize = 8;
+
+/// Records that fit in one flash page after the sequence number.
+pub const RECORDS_PER_PAGE: usize = (PAGE_SIZE - 4) / RECORD_LEN;
+
+const PAGES_PER_SECTOR: u32 = SECTOR_SIZE / PAGE_SIZE as u32;
+const ERASED: u32 = 0xFFFF_FFFF;
+
+/// A log in sectors of the [`flash_storage`] region.
+pub struct Log {
+    start: u32,
+    /// The log's sectors, read through XIP
+    flash: &'static [u8],
+    pages: u32,
+    /// Index of the next page to program
+    next: u32,
+    sequence: u32,
+    page: [u8; PAGE_SIZE],
+    count: usize,
+}
+
+impl Log {
+    /// A log in the `sectors` sectors starting `start` bytes into the
+    /// [`flash_storage`] region, which must be a multiple of [`SECTOR_SIZE`].
+    /// Carries on after the newest page already there.
+    ///
+    /// Fails unless there is at least one sector and they are all inside the
+    /// region.
+    pub fn new(start: u32, sectors: u32) -> Result<Self, Error> {
+        if !start.is_multiple_of(SECTOR_SIZE) {
+            return Err(Error::Unaligned);
+        }
+        let len = sectors
+            .checked_mul(SECTOR_SIZE)
+            .filter(|&len| len > 0)
+            .ok_or(Error::OutOfRange)?;
+        let mut log = Log {
+            start,
+            flash: flash_storage::mapped(start, len)?,
+            pages: sectors * PAGES_PER_SECTOR,
+            next: 0,
+            sequence: 0,
+            page: [0xFF; PAGE_SIZE],
+            count: 0,
+        };
+        let newest = (0..log.pages)
+            .filter(|&index| log.sequence_at(index) != ERASED)
+            .max_by_key(|&index| log.sequence_at(index));
+        if let Some(index) = newest {
+            log.sequence = log.sequence_at(index).wrapping_add(1);
+            log.next = (index + 1) % log.pages;
+        }
+        Ok(log)
+    }
+
+    /// Add a record, programming the page to flash if it is now full.
+    pub fn append(&mut self, record: &[u8; RECORD_LEN]) {
+        let at = 4 + self.count * RECORD_LEN;
+        self.page[at..at + RECORD_LEN].copy_from_slice(record);
+        self.count += 1;
+        if self.count == RECORDS_PER_PAGE {
+            self.flush();
+        }
+    }
+
+    /// Program the records held in RAM to flash, even if the page isn't full.
+    pub fn flush(&mut self) {
+        if self.count == 0 {
+            return;
+        }
+        self.page[0..4].copy_from_slice(&self.sequence.to_le_bytes());
+
+        let offset = self.start + self.next * PAGE_SIZE as u32;
+        // Can't fail: `new` checked the log is inside the region
+        if self.next.is_multiple_of(PAGES_PER_SECTOR) {
+            let _ = flash_storage::erase_sector(offset);
+        }
+        let _ = flash_storage::program_page(offset, &self.page);
+
+        self.next = (self.next + 1) % self.pages;
+        self.sequence = self.sequence.wrapping_add(1);
+        self.page = [0xFF; PAGE_SIZE];
+        self.count = 0;
+    }
+
+    /// Records held in RAM, not yet in flash.
+    pub fn pending(&self) -> usize {
+        self.count
+    }
+
+    /// Call `f` with every record in flash, oldest first.
+    pub fn for_each(&self, mut f: impl FnMut(&[u8; RECORD_LEN])) {
+        for step in 0..self.pages {
+            let index = (self.next + step) % self.pages;
+            if self.sequence_at(index) == ERASED {
+                continue;
+            }
+            let page = self.page_at(index);
+            for record in page[4..].chunks_exact(RECORD_LEN) {
+                // Unused slots in a flushed page are still erased
+                if record.iter().all(|&byte| byte == 0xFF) {
+                    break;
+                }
+                let mut copy = [0; RECORD_LEN];
+                copy.copy_from_slice(record);
+                f(&copy);
+            }
+        }
+    }
+
+    fn page_at(&self, index: u32) -> &'static [u8] {
+        let at = index as usize * PAGE_SIZE;
+        &self.flash[at..at + PAGE_SIZE]
+    }
+
+    fn sequence_at(&self, index: u32) -> u32 {
+        let page = self.page_at(index);
+        u32::from_le_bytes([page[0], page[1], page[2], page[3]])
+    }
+}
@@ -0,0 +1,93 @@
+//! Busy-wait delays measured in CPU cycles and nanoseconds.
+//!
+//! `cortex_m::delay::Delay` is set up once with a fixed frequency, and the bit
+//! timings in most bit-banged drivers are written for 125 MHz. Both go wrong as
+//! soon as the system clock is changed. The helpers here convert nanoseconds to
+//! cycles using the frequency recorded by [`calibrate`], so call it again after
+//! every change to `clk_sys`:
+//!
+//! ```ignore
+//! let clocks = hal::clocks::init_clocks_and_plls(/* ... */).ok().unwrap();
+//! rp_hal_boards_common::delay::calibrate(&clocks.system_clock);
+//! rp_hal_boards_common::delay::delay_ns(400);
+//! ```
+//!
+//! These are minimum delays: an interrupt or a flash cache miss makes them
+//! longer. Code with tight timing, such as [`bitbang`](crate::bitbang), runs
+//! them with interrupts disabled.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::hal::clocks::{Clock, SystemClock};
+
+/// Frequency assumed until [`calibrate`] is called.
+///
+/// This is the fastest the RP2040 is rated for, so an uncalibrated delay is
+/// never shorter than requested at or below the rated clock.
+pub const DEFAULT_SYSTEM_CLOCK_HZ: u32 = 133_000_000;
+
+/// The frequency last passed to [`calibrate_hz`].
+static SYSTEM_CLOCK_HZ: AtomicU32 = AtomicU32::new(DEFAULT_SYSTEM_CLOCK_HZ);
+
+/// `clk_sys` cycles per nanosecond, as a 16.16 fixed point number.
+static CYCLES_PER_NS_Q16: AtomicU32 = AtomicU32::new(q16_from_hz(DEFAULT_SYSTEM_CLOCK_HZ));
+
+/// `clk_sys` cycles per microsecond.
+static CYCLES_PER_US: AtomicU32 = AtomicU32::new(DEFAULT_SYSTEM_CLOCK_HZ / 1_000_000);
+
+const fn q16_from_hz(hz: u32) -> u32 {
+    // Round up, so a delay is never shorter than requested
+    ((hz as u64) << 16).div_ceil(1_000_000_000) as u32
+}
+
+/// Record the current system clock frequency.
+pub fn calibrate(system_clock: &SystemClock) {
+    calibrate_hz(system_clock.freq().to_Hz());
+}
+
+/// Record the system clock frequency in Hz, for code that changes `clk_sys`
+/// without going through a [`SystemClock`].
+pub fn calibrate_hz(hz: u32) {
+    SYSTEM_CLOCK_HZ.store(hz, Ordering::Relaxed);
+    CYCLES_PER_NS_Q16.store(q16_from_hz(hz), Ordering::Relaxed);
+    CYCLES_PER_US.store(hz.div_ceil(1_000_000), Ordering::Relaxed);
+}
+
+/// The system clock frequency last passed to [`calibrate`], in Hz.
+pub fn system_clock_hz() -> u32 {
+    SYSTEM_CLOCK_HZ.load(Ordering::Relaxed)
+}
+
+/// The number of `clk_sys` cycles in `ns` nanoseconds, rounded up.
+#[inline(always)]
+pub fn ns_to_cycles(ns: u32) -> u32 {
+    let q16 = CYCLES_PER_NS_Q16.load(Ordering::Relaxed);
+    ((u64::from(ns) * u64::from(q16) + 0xFFFF) >> 16) as u32
+}
+
+/// The number of `clk_sys` cycles in `us` microseconds.
+#[inline(always)]
+pub fn us_to_cycles(us: u32) -> u32 {
+    us.saturating_mul(CYCLES_PER_US.load(Ordering::Relaxed))
+}
+
+/// Spin for at least `cycles` CPU cycles.
+#[inline(always)]
+pub fn delay_cycles(cycles: u32) {
+    cortex_m::asm::delay(cycles);
+}
+
+/// Spin for at least `ns` nanoseconds.
+///
+/// Below a few hundred nanoseconds the cost of the call itself dominates;
+/// convert once with [`ns_to_cycles`] and use [`delay_cycles`] in hot loops.
+#[inline(always)]
+pub fn delay_ns(ns: u32) {
+    delay_cycles(ns_to_cycles(ns));
+}
+
+/// Spin for at least `us` microseconds.
+#[inline(always)]
+pub fn delay_us(us: u32) {
+    delay_cycles(us_to_cycles(us));
+}
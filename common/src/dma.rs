@@ -0,0 +1,379 @@
+//! DMA channel allocation and interrupt dispatch.
+//!
+//! The HAL's `DMAExt::split` hands out all twelve channels as distinct types, which
+//! works well until several independent drivers each want "a channel" and a
+//! completion interrupt. The [`Allocator`] instead owns the DMA block, hands out
+//! the first free channel at runtime, records the owner in the
+//! [`resources`](crate::resources) registry, and routes the shared `DMA_IRQ_0` and
+//! `DMA_IRQ_1` interrupts to per-channel callbacks or wakers.
+//!
+//! ```ignore
+//! let mut dma = Allocator::new(pac.DMA, &mut pac.RESETS);
+//! let mut ch = dma.alloc("adc-stream").unwrap();
+//! ch.listen(Irq::Irq0, Some(on_adc_block));
+//! unsafe { pac::NVIC::unmask(pac::Interrupt::DMA_IRQ_0) };
+//!
+//! #[interrupt]
+//! fn DMA_IRQ_0() {
+//!     rp_hal_boards_common::dma::dispatch(Irq::Irq0);
+//! }
+//! ```
+//!
+//! [`copy_blocking`] and [`copy`] move memory to memory on a channel, the
+//! first while the CPU waits, the second in the background, returning a
+//! [`Transfer`] to wait on or `.await`:
+//!
+//! ```ignore
+//! let transfer = dma::copy(ch, &FRAME[..], back_buffer);
+//! render_next_line();
+//! let (ch, _src, back_buffer) = transfer.wait();
+//! ```
+
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use critical_section::Mutex;
+
+use crate::hal::pac;
+use crate::resources::{self, Resource};
+
+/// Number of DMA channels on the RP2040.
+pub const CHANNEL_COUNT: usize = resources::DMA_CHANNEL_COUNT;
+
+/// One of the two shared DMA interrupt lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Irq {
+    /// `DMA_IRQ_0`, enabled through `INTE0`.
+    Irq0,
+    /// `DMA_IRQ_1`, enabled through `INTE1`.
+    Irq1,
+}
+
+/// Function called from [`dispatch`] when a channel completes, with the channel
+/// number as argument.
+pub type Callback = fn(u8);
+
+/// Errors returned by the [`Allocator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// All twelve channels are in use.
+    NoFreeChannel,
+}
+
+const NO_WAKER: Option<Waker> = None;
+
+struct Dispatch {
+    callbacks: [Option<Callback>; CHANNEL_COUNT],
+    wakers: [Option<Waker>; CHANNEL_COUNT],
+    /// Channels that completed since their flag was last taken.
+    done: u16,
+}
+
+static DISPATCH: Mutex<RefCell<Dispatch>> = Mutex::new(RefCell::new(Dispatch {
+    callbacks: [None; CHANNEL_COUNT],
+    wakers: [NO_WAKER; CHANNEL_COUNT],
+    done: 0,
+}));
+
+/// Hands out DMA channels at runtime.
+pub struct Allocator {
+    _dma: pac::DMA,
+}
+
+impl Allocator {
+    /// Take ownership of the DMA block and bring it out of reset.
+    pub fn new(dma: pac::DMA, resets: &mut pac::RESETS) -> Self {
+        resets.reset.modify(|_, w| w.dma().clear_bit());
+        while resets.reset_done.read().dma().bit_is_clear() {}
+        Allocator { _dma: dma }
+    }
+
+    /// Allocate the lowest numbered free channel for `owner`.
+    ///
+    /// Channels claimed in the [`resources`] registry by other code (for
+    /// example a driver using the HAL's typed channels) are skipped.
+    pub fn alloc(&mut self, owner: &'static str) -> Result<Channel, Error> {
+        for id in 0..CHANNEL_COUNT as u8 {
+            let resource = Resource::DmaChannel(id);
+            if resources::owner(resource).is_none() && resources::claim(resource, owner).is_ok() {
+                return Ok(Channel { id });
+            }
+        }
+        Err(Error::NoFreeChannel)
+    }
+
+    /// Give a channel back, aborting any transfer in progress and dropping its
+    /// interrupt handler.
+    pub fn free(&mut self, mut channel: Channel) {
+        channel.abort();
+        channel.unlisten();
+        resources::release(Resource::DmaChannel(channel.id));
+    }
+}
+
+/// A DMA channel handed out by the [`Allocator`].
+pub struct Channel {
+    id: u8,
+}
+
+impl Channel {
+    /// The channel number, 0 to 11.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// The channel's register block.
+    ///
+    /// Owning the `Channel` means nothing else touches these registers.
+    pub fn regs(&self) -> &pac::dma::CH {
+        // Safety: the allocator only hands out each channel once, so we have
+        // exclusive access to this register block.
+        unsafe { &(*pac::DMA::ptr()).ch[self.id as usize] }
+    }
+
+    /// Whether a transfer is in progress.
+    pub fn is_busy(&self) -> bool {
+        self.regs().ch_ctrl_trig.read().busy().bit_is_set()
+    }
+
+    /// Abort any transfer in progress and wait for the channel to go idle.
+    pub fn abort(&mut self) {
+        let dma = unsafe { &*pac::DMA::ptr() };
+        dma.chan_abort
+            .write(|w| unsafe { w.chan_abort().bits(1 << self.id) });
+        while dma.chan_abort.read().chan_abort().bits() & (1 << self.id) != 0 {}
+    }
+
+    /// Route this channel's completion interrupt to `irq`, calling `callback` (if
+    /// any) from [`dispatch`].
+    ///
+    /// Completion is also recorded for [`Channel::take_done`] and wakes any
+    /// registered waker, so `callback` may be `None`.
+    pub fn listen(&mut self, irq: Irq, callback: Option<Callback>) {
+        let mask = 1u32 << self.id;
+        critical_section::with(|cs| {
+            let mut dispatch = DISPATCH.borrow_ref_mut(cs);
+            dispatch.callbacks[self.id as usize] = callback;
+            dispatch.done &= !(mask as u16);
+        });
+        let dma = unsafe { &*pac::DMA::ptr() };
+        // Clear any stale status before enabling.
+        dma.ints0.write(|w| unsafe { w.bits(mask) });
+        dma.ints1.write(|w| unsafe { w.bits(mask) });
+        // Safety: INTEx is shared with other channels. Read-modify-write in a
+        // critical section so that we don't race with another `listen`.
+        critical_section::with(|_| match irq {
+            Irq::Irq0 => {
+                dma.inte1.modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+                dma.inte0.modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+            }
+            Irq::Irq1 => {
+                dma.inte0.modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+                dma.inte1.modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+            }
+        });
+    }
+
+    /// Stop routing this channel's completion interrupt anywhere.
+    pub fn unlisten(&mut self) {
+        let mask = 1u32 << self.id;
+        let dma = unsafe { &*pac::DMA::ptr() };
+        critical_section::with(|cs| {
+            dma.inte0.modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+            dma.inte1.modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+            let mut dispatch = DISPATCH.borrow_ref_mut(cs);
+            dispatch.callbacks[self.id as usize] = None;
+            dispatch.wakers[self.id as usize] = None;
+        });
+    }
+
+    /// Wake `waker` the next time this channel completes.
+    ///
+    /// Meant to be called from a `Future::poll` implementation.
+    pub fn register_waker(&self, waker: &Waker) {
+        critical_section::with(|cs| {
+            let mut dispatch = DISPATCH.borrow_ref_mut(cs);
+            let slot = &mut dispatch.wakers[self.id as usize];
+            match slot {
+                Some(current) if current.will_wake(waker) => {}
+                _ => *slot = Some(waker.clone()),
+            }
+        });
+    }
+
+    /// Returns `true`, once, for every completion seen by [`dispatch`] since the
+    /// last call.
+    pub fn take_done(&self) -> bool {
+        critical_section::with(|cs| {
+            let mut dispatch = DISPATCH.borrow_ref_mut(cs);
+            let mask = 1 << self.id;
+            let done = dispatch.done & mask != 0;
+            dispatch.done &= !mask;
+            done
+        })
+    }
+}
+
+/// Handle a DMA interrupt.
+///
+/// Call this from the `DMA_IRQ_0` or `DMA_IRQ_1` handler. It acknowledges every
+/// pending channel on that line, then runs the channel's callback and wakes its
+/// waker.
+pub fn dispatch(irq: Irq) {
+    let dma = unsafe { &*pac::DMA::ptr() };
+    let status = match irq {
+        Irq::Irq0 => {
+            let status = dma.ints0.read().bits();
+            dma.ints0.write(|w| unsafe { w.bits(status) });
+            status
+        }
+        Irq::Irq1 => {
+            let status = dma.ints1.read().bits();
+            dma.ints1.write(|w| unsafe { w.bits(status) });
+            status
+        }
+    };
+
+    for id in 0..CHANNEL_COUNT {
+        if status & (1 << id) == 0 {
+            continue;
+        }
+        let (callback, waker) = critical_section::with(|cs| {
+            let mut dispatch = DISPATCH.borrow_ref_mut(cs);
+            dispatch.done |= 1 << id;
+            (dispatch.callbacks[id], dispatch.wakers[id].take())
+        });
+        // Run user code outside the critical section.
+        if let Some(callback) = callback {
+            callback(id as u8);
+        }
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// An element DMA can copy: a byte, halfword or word.
+pub trait Word: Copy + sealed::Sealed + 'static {
+    /// `CH_CTRL.DATA_SIZE` for this element.
+    #[doc(hidden)]
+    const DATA_SIZE: u32;
+}
+
+macro_rules! word {
+    ($($t:ty => $size:expr),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+            impl Word for $t {
+                const DATA_SIZE: u32 = $size;
+            }
+        )*
+    };
+}
+
+word!(u8 => 0, i8 => 0, u16 => 1, i16 => 1, u32 => 2, i32 => 2);
+
+/// `CH_CTRL` for an unpaced memory-to-memory copy on channel `id`:
+/// enabled, incrementing both addresses, and not chained.
+fn copy_ctrl<W: Word>(id: u8) -> u32 {
+    const EN: u32 = 1 << 0;
+    const INCR_READ: u32 = 1 << 4;
+    const INCR_WRITE: u32 = 1 << 5;
+    const TREQ_PERMANENT: u32 = 0x3f << 15;
+    // Chaining to ourselves disables chaining
+    EN | W::DATA_SIZE << 2 | INCR_READ | INCR_WRITE | u32::from(id) << 11 | TREQ_PERMANENT
+}
+
+/// Point `channel` at `src` and `dst` and start it.
+fn start_copy<W: Word>(channel: &Channel, src: &[W], dst: &mut [W]) {
+    assert_eq!(src.len(), dst.len(), "DMA copy between different lengths");
+    let regs = channel.regs();
+    regs.ch_read_addr
+        .write(|w| unsafe { w.bits(src.as_ptr() as u32) });
+    regs.ch_write_addr
+        .write(|w| unsafe { w.bits(dst.as_mut_ptr() as u32) });
+    regs.ch_trans_count
+        .write(|w| unsafe { w.bits(src.len() as u32) });
+    // The buffers must be written out before the DMA reads them
+    compiler_fence(Ordering::SeqCst);
+    regs.ch_ctrl_trig
+        .write(|w| unsafe { w.bits(copy_ctrl::<W>(channel.id())) });
+}
+
+/// Copy `src` into `dst` on `channel`, returning once it is done.
+///
+/// The CPU only waits, so this is only worth it over `copy_from_slice` when
+/// an interrupt handler, or the other core, has work to do meanwhile.
+///
+/// # Panics
+///
+/// If the lengths differ.
+pub fn copy_blocking<W: Word>(channel: &mut Channel, src: &[W], dst: &mut [W]) {
+    start_copy(channel, src, dst);
+    while channel.is_busy() {}
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Start copying `src` into `dst` on `channel`, in the background.
+///
+/// The buffers are `'static` as the DMA keeps using them until the returned
+/// [`Transfer`] is done, which hands them back.
+///
+/// # Panics
+///
+/// If the lengths differ.
+pub fn copy<W: Word>(channel: Channel, src: &'static [W], dst: &'static mut [W]) -> Transfer<W> {
+    start_copy(&channel, src, dst);
+    Transfer { channel, src, dst }
+}
+
+/// A memory-to-memory copy running in the background, from [`copy`].
+///
+/// It is also a `Future`, ready once the copy is done, so an `async` task
+/// can `(&mut transfer).await` before calling [`wait`](Transfer::wait) to get
+/// the channel and buffers back. That needs the channel to
+/// [`listen`](Channel::listen), with [`dispatch`] called from the handler;
+/// otherwise nothing wakes the task.
+pub struct Transfer<W: Word> {
+    channel: Channel,
+    src: &'static [W],
+    dst: &'static mut [W],
+}
+
+impl<W: Word> Transfer<W> {
+    /// Whether the copy is done.
+    pub fn is_done(&self) -> bool {
+        !self.channel.is_busy()
+    }
+
+    /// Block until the copy is done, handing back the channel and buffers.
+    pub fn wait(self) -> (Channel, &'static [W], &'static mut [W]) {
+        while self.channel.is_busy() {}
+        compiler_fence(Ordering::SeqCst);
+        (self.channel, self.src, self.dst)
+    }
+}
+
+impl<W: Word> Future for Transfer<W> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.is_done() {
+            return Poll::Ready(());
+        }
+        self.channel.register_waker(cx.waker());
+        // It may have finished before the waker was in place
+        if self.is_done() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Double-tap reset to enter the USB bootloader.
+//!
+//! Many boards have a reset button that is easy to reach and a BOOTSEL button
+//! that is not, or none at all. Calling [`double_reset_bootloader`] early in
+//! `main` makes a quick double press of reset reboot into the UF2 bootloader,
+//! the way Adafruit's boards behave:
+//!
+//! ```ignore
+//! let clocks = hal::clocks::init_clocks_and_plls(/* ... */).ok().unwrap();
+//! rp_hal_boards_common::delay::calibrate(&clocks.system_clock);
+//! rp_hal_boards_common::double_reset::double_reset_bootloader();
+//! ```
+//!
+//! On the way in it leaves a token in RAM and waits for the window to pass,
+//! then wipes the token. A reset during the wait finds the token still there
+//! and goes to the bootloader instead. The token lives in RAM rather than the
+//! watchdog scratch registers, because the RUN pin that a reset button pulls
+//! low clears those, while RAM keeps its contents. A power cycle leaves RAM
+//! full of noise, which matches the two-word token only by extreme chance.
+//!
+//! The wait uses [`delay`](crate::delay), so calibrate it first. Every boot
+//! takes the window longer, so keep it short.
+
+use core::mem::MaybeUninit;
+use core::ptr::{addr_of, addr_of_mut};
+
+use crate::{delay, rom};
+
+/// How long after a reset a second one still counts, in milliseconds.
+pub const DEFAULT_WINDOW_MS: u32 = 500;
+
+/// The token left in RAM during the window. The second word is the first
+/// inverted, so memory stuck at all zeros or all ones never matches.
+const TOKEN: [u32; 2] = [0xD0B1_E7A9, !0xD0B1_E7A9];
+
+/// Not zeroed or initialised at start-up, so it survives a reset.
+#[link_section = ".uninit.double_reset"]
+static mut TOKEN_LOCATION: MaybeUninit<[u32; 2]> = MaybeUninit::uninit();
+
+/// Reboot into the bootloader if the board was reset twice within
+/// [`DEFAULT_WINDOW_MS`], with no activity light.
+pub fn double_reset_bootloader() {
+    double_reset_bootloader_with(DEFAULT_WINDOW_MS, None);
+}
+
+/// Reboot into the bootloader if the board was reset twice within
+/// `window_ms`, flashing `gpio_activity_pin`, if given, while the host talks
+/// to the bootloader. Otherwise return after `window_ms`.
+pub fn double_reset_bootloader_with(window_ms: u32, gpio_activity_pin: Option<u8>) {
+    // Safety: nothing else touches the token, and any bit pattern is a valid
+    // pair of u32s
+    let location = addr_of_mut!(TOKEN_LOCATION) as *mut [u32; 2];
+    let found = unsafe { core::ptr::read_volatile(addr_of!(TOKEN_LOCATION) as *const [u32; 2]) };
+
+    if found == TOKEN {
+        unsafe { core::ptr::write_volatile(location, [0, 0]) };
+        rom::reboot_to_usb_boot(gpio_activity_pin, false);
+    }
+
+    unsafe { core::ptr::write_volatile(location, TOKEN) };
+    delay::delay_us(window_ms.saturating_mul(1000));
+    unsafe { core::ptr::write_volatile(location, [0, 0]) };
+}
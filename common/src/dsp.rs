@@ -0,0 +1,376 @@
+//! Fixed-point signal processing: FFT, windows, magnitudes and tone detection.
+//!
+//! Everything works on Q15 samples (`i16`, with 32767 standing for just under
+//! 1.0), so it runs at a useful speed on the RP2040's cores, which have no
+//! FPU. Transforms are done in place on separate real and imaginary arrays,
+//! so a 1024 point FFT needs only 4 KiB of RAM, and the twiddle factors come
+//! from a 514 byte sine table in flash.
+//!
+//! ```ignore
+//! let mut re = [0i16; 256];
+//! let mut im = [0i16; 256];
+//! // ... fill `re` with samples
+//! dsp::hann(&mut re);
+//! dsp::fft(&mut re, &mut im);
+//! let mut bins = [0u16; 128];
+//! dsp::magnitudes(&re, &im, &mut bins);
+//! ```
+//!
+//! Each FFT stage halves its output to rule out overflow, so the result is the
+//! DFT divided by the length: a full-scale sine comes out at half scale in
+//! its bin, and at a quarter after a Hann window.
+//!
+//! To look for a handful of known frequencies, such as DTMF tones, a
+//! [`Goertzel`] filter per frequency is cheaper than a whole FFT, and can be
+//! tuned to any frequency rather than just the bin centres.
+
+/// The longest transform [`fft`] and [`hann`] support.
+pub const MAX_LEN: usize = 1024;
+
+/// `sin(2π k / 1024)` in Q15 for `k` from 0 to 256, the first quarter wave.
+const QUARTER_SINE: [i16; MAX_LEN / 4 + 1] = [
+    0, 201, 402, 603, 804, 1005, 1206, 1407, 1608, 1809, 2009, 2210, 2411, 2611, 2811, 3012, 3212,
+    3412, 3612, 3812, 4011, 4211, 4410, 4609, 4808, 5007, 5205, 5404, 5602, 5800, 5998, 6195, 6393,
+    6590, 6787, 6983, 7180, 7376, 7571, 7767, 7962, 8157, 8351, 8546, 8740, 8933, 9127, 9319, 9512,
+    9704, 9896, 10088, 10279, 10469, 10660, 10850, 11039, 11228, 11417, 11605, 11793, 11980, 12167,
+    12354, 12540, 12725, 12910, 13095, 13279, 13463, 13646, 13828, 14010, 14192, 14373, 14553,
+    14733, 14912, 15091, 15269, 15447, 15624, 15800, 15976, 16151, 16326, 16500, 16673, 16846,
+    17018, 17190, 17361, 17531, 17700, 17869, 18037, 18205, 18372, 18538, 18703, 18868, 19032,
+    19195, 19358, 19520, 19681, 19841, 20001, 20160, 20318, 20475, 20632, 20788, 20943, 21097,
+    21251, 21403, 21555, 21706, 21856, 22006, 22154, 22302, 22449, 22595, 22740, 22884, 23028,
+    23170, 23312, 23453, 23593, 23732, 23870, 24008, 24144, 24279, 24414, 24548, 24680, 24812,
+    24943, 25073, 25202, 25330, 25457, 25583, 25708, 25833, 25956, 26078, 26199, 26320, 26439,
+    26557, 26674, 26791, 26906, 27020, 27133, 27246, 27357, 27467, 27576, 27684, 27791, 27897,
+    28002, 28106, 28209, 28311, 28411, 28511, 28610, 28707, 28803, 28899, 28993, 29086, 29178,
+    29269, 29359, 29448, 29535, 29622, 29707, 29792, 29875, 29957, 30038, 30118, 30196, 30274,
+    30350, 30425, 30499, 30572, 30644, 30715, 30784, 30853, 30920, 30986, 31050, 31114, 31177,
+    31238, 31298, 31357, 31415, 31471, 31527, 31581, 31634, 31686, 31737, 31786, 31834, 31881,
+    31927, 31972, 32015, 32058, 32099, 32138, 32177, 32214, 32251, 32286, 32319, 32352, 32383,
+    32413, 32442, 32470, 32496, 32522, 32546, 32568, 32590, 32610, 32629, 32647, 32664, 32679,
+    32693, 32706, 32718, 32729, 32738, 32746, 32753, 32758, 32762, 32766, 32767, 32767,
+];
+
+/// `sin(2π k / 1024)` in Q15, for any `k`.
+pub fn sin_q15(k: usize) -> i16 {
+    let k = k % MAX_LEN;
+    let quarter = MAX_LEN / 4;
+    match k / quarter {
+        0 => QUARTER_SINE[k],
+        1 => QUARTER_SINE[2 * quarter - k],
+        2 => -QUARTER_SINE[k - 2 * quarter],
+        _ => -QUARTER_SINE[MAX_LEN - k],
+    }
+}
+
+/// `cos(2π k / 1024)` in Q15, for any `k`.
+pub fn cos_q15(k: usize) -> i16 {
+    sin_q15(k + MAX_LEN / 4)
+}
+
+/// Multiply two Q15 numbers, rounding.
+pub fn mul_q15(a: i16, b: i16) -> i16 {
+    ((i32::from(a) * i32::from(b) + (1 << 14)) >> 15) as i16
+}
+
+/// Check that `len` is a length the transforms support.
+fn check_len(len: usize) {
+    assert!(
+        len.is_power_of_two() && (2..=MAX_LEN).contains(&len),
+        "length must be a power of two from 2 to 1024"
+    );
+}
+
+/// Apply a Hann window to `samples`, to stop the edges of the block smearing
+/// each bin into its neighbours.
+pub fn hann(samples: &mut [i16]) {
+    check_len(samples.len());
+    let step = MAX_LEN / samples.len();
+    for (i, sample) in samples.iter_mut().enumerate() {
+        // 0.5 - 0.5 cos(2πi / N), in Q15
+        let window = ((32768 - i32::from(cos_q15(i * step))) >> 1).min(32767) as i16;
+        *sample = mul_q15(*sample, window);
+    }
+}
+
+/// Subtract the mean of `samples` from each of them, for inputs such as the
+/// ADC that sit half way up their range.
+pub fn remove_dc(samples: &mut [i16]) {
+    if samples.is_empty() {
+        return;
+    }
+    let mean = samples.iter().map(|&s| i32::from(s)).sum::<i32>() / samples.len() as i32;
+    for sample in samples.iter_mut() {
+        *sample = (i32::from(*sample) - mean).clamp(-32768, 32767) as i16;
+    }
+}
+
+/// Radix-2 FFT, in place, of the complex signal `re + j im`, scaled by
+/// `1 / len`.
+///
+/// Both slices must be the same length, a power of two up to [`MAX_LEN`].
+/// For a real signal, zero `im` first; bins above `len / 2` then mirror the
+/// ones below.
+///
+/// Halving each stage keeps every value in range as long as no input sample
+/// has a modulus above 32767. Complex samples beyond that, such as both parts
+/// at -32768, saturate instead of wrapping around.
+pub fn fft(re: &mut [i16], im: &mut [i16]) {
+    let len = re.len();
+    assert_eq!(len, im.len(), "real and imaginary parts differ in length");
+    check_len(len);
+
+    // Put the input in bit-reversed order
+    let bits = len.trailing_zeros();
+    for i in 0..len {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut half = 1;
+    while half < len {
+        let step = MAX_LEN / (2 * half);
+        for start in (0..len).step_by(2 * half) {
+            for k in 0..half {
+                // Twiddle factor e^(-2πjk / 2half)
+                let wr = i32::from(cos_q15(k * step));
+                let wi = -i32::from(sin_q15(k * step));
+
+                let (a, b) = (start + k, start + k + half);
+                let (br, bi) = (i32::from(re[b]), i32::from(im[b]));
+                let tr = (br * wr - bi * wi + (1 << 14)) >> 15;
+                let ti = (br * wi + bi * wr + (1 << 14)) >> 15;
+                let (ar, ai) = (i32::from(re[a]), i32::from(im[a]));
+
+                re[a] = halve(ar + tr);
+                im[a] = halve(ai + ti);
+                re[b] = halve(ar - tr);
+                im[b] = halve(ai - ti);
+            }
+        }
+        half *= 2;
+    }
+}
+
+/// Half of a butterfly output, saturated to Q15.
+fn halve(x: i32) -> i16 {
+    (x >> 1).clamp(-32768, 32767) as i16
+}
+
+/// The integer square root of `x`, rounded down.
+pub fn isqrt(x: u32) -> u16 {
+    let mut root = 0u32;
+    let mut bit = 1u32 << 30;
+    let mut rest = x;
+    while bit > rest {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rest >= root + bit {
+            rest -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root as u16
+}
+
+/// The magnitude of one complex bin.
+pub fn magnitude(re: i16, im: i16) -> u16 {
+    let (re, im) = (i32::from(re), i32::from(im));
+    // Each square fits in 31 bits, but their sum needs 32
+    isqrt((re * re) as u32 + (im * im) as u32)
+}
+
+/// The magnitudes of the first `out.len()` bins of an [`fft`] result.
+pub fn magnitudes(re: &[i16], im: &[i16], out: &mut [u16]) {
+    for ((out, &re), &im) in out.iter_mut().zip(re).zip(im) {
+        *out = magnitude(re, im);
+    }
+}
+
+/// `cos(2π phase / 65536)` in Q15, interpolating between table entries.
+pub fn cos_phase_q15(phase: u16) -> i16 {
+    let k = usize::from(phase >> 6);
+    let fraction = i32::from(phase & 0x3F);
+    let (a, b) = (i32::from(cos_q15(k)), i32::from(cos_q15(k + 1)));
+    (a + (((b - a) * fraction) >> 6)) as i16
+}
+
+/// Measures how much of one frequency there is in a block of samples.
+///
+/// Feed it a block with [`push`](Goertzel::push), read [`power`](Goertzel::power),
+/// then [`reset`](Goertzel::reset) it for the next block. A sine of amplitude
+/// `A` at the filter's frequency gives a power of about `(N A / 2)²` over `N`
+/// samples. Blocks of up to about 1000 full-scale samples are safe from
+/// overflow.
+#[derive(Clone, Debug)]
+pub struct Goertzel {
+    /// `2 cos(2π f / fs)` in Q14.
+    coeff: i64,
+    s1: i64,
+    s2: i64,
+}
+
+impl Goertzel {
+    /// A filter for `frequency` Hz, in samples taken at `sample_rate` Hz.
+    pub fn new(frequency: u32, sample_rate: u32) -> Self {
+        assert!(
+            frequency < sample_rate / 2,
+            "the frequency must be below half the sample rate"
+        );
+        let phase = ((u64::from(frequency) << 16) / u64::from(sample_rate)) as u16;
+        Goertzel {
+            coeff: i64::from(cos_phase_q15(phase)),
+            s1: 0,
+            s2: 0,
+        }
+    }
+
+    /// Run one sample through the filter.
+    pub fn push(&mut self, sample: i16) {
+        // coeff is cos in Q15, which is 2 cos in Q14
+        let s = i64::from(sample) + ((self.coeff * self.s1) >> 14) - self.s2;
+        self.s2 = self.s1;
+        self.s1 = s;
+    }
+
+    /// The squared magnitude at the filter's frequency, over the samples
+    /// pushed since the last reset.
+    pub fn power(&self) -> u64 {
+        let cross = ((self.coeff * self.s1) >> 14) * self.s2;
+        (self.s1 * self.s1 + self.s2 * self.s2 - cross).max(0) as u64
+    }
+
+    /// Clear the filter for the next block.
+    pub fn reset(&mut self) {
+        self.s1 = 0;
+        self.s2 = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 8000;
+    const BLOCK: usize = 205;
+    const ROWS: [u32; 4] = [697, 770, 852, 941];
+    const COLUMNS: [u32; 4] = [1209, 1336, 1477, 1633];
+
+    /// `BLOCK` samples of two tones, each of amplitude `amplitude`.
+    fn tone_pair(low: u32, high: u32, amplitude: i16) -> [i16; BLOCK] {
+        let step = |frequency: u32| ((u64::from(frequency) << 16) / u64::from(SAMPLE_RATE)) as u16;
+        let (low_step, high_step) = (step(low), step(high));
+        let (mut low_phase, mut high_phase) = (0u16, 0u16);
+        let mut samples = [0; BLOCK];
+        for sample in samples.iter_mut() {
+            *sample = mul_q15(amplitude, cos_phase_q15(low_phase))
+                + mul_q15(amplitude, cos_phase_q15(high_phase));
+            low_phase = low_phase.wrapping_add(low_step);
+            high_phase = high_phase.wrapping_add(high_step);
+        }
+        samples
+    }
+
+    /// The frequencies among `ROWS` and `COLUMNS` whose filters pick up at
+    /// least half the power a clean tone pair in `samples` would give.
+    fn detected(samples: &[i16]) -> [bool; 8] {
+        let energy: u64 = samples
+            .iter()
+            .map(|&s| (i64::from(s) * i64::from(s)) as u64)
+            .sum();
+        let threshold = samples.len() as u64 * energy / 8;
+        let mut found = [false; 8];
+        for (found, &frequency) in found.iter_mut().zip(ROWS.iter().chain(&COLUMNS)) {
+            let mut filter = Goertzel::new(frequency, SAMPLE_RATE);
+            for &sample in samples {
+                filter.push(sample);
+            }
+            *found = filter.power() >= threshold;
+        }
+        found
+    }
+
+    #[test]
+    fn goertzel_detects_each_dtmf_pair() {
+        for (row, &low) in ROWS.iter().enumerate() {
+            for (column, &high) in COLUMNS.iter().enumerate() {
+                let mut expected = [false; 8];
+                expected[row] = true;
+                expected[4 + column] = true;
+                let samples = tone_pair(low, high, 12000);
+                assert_eq!(detected(&samples), expected, "{} Hz + {} Hz", low, high);
+            }
+        }
+    }
+
+    #[test]
+    fn goertzel_power_matches_amplitude() {
+        let samples = tone_pair(770, 1336, 8000);
+        let mut filter = Goertzel::new(770, SAMPLE_RATE);
+        for &sample in &samples {
+            filter.push(sample);
+        }
+        // About (N A / 2)², within 10%
+        let expected = (BLOCK as u64 * 8000 / 2).pow(2);
+        let power = filter.power();
+        assert!(
+            power > expected * 9 / 10 && power < expected * 11 / 10,
+            "{}",
+            power
+        );
+    }
+
+    #[test]
+    fn goertzel_ignores_silence_and_resets() {
+        let mut filter = Goertzel::new(941, SAMPLE_RATE);
+        for &sample in &tone_pair(941, 1633, 12000) {
+            filter.push(sample);
+        }
+        assert!(filter.power() > 0);
+        filter.reset();
+        for _ in 0..BLOCK {
+            filter.push(0);
+        }
+        assert_eq!(filter.power(), 0);
+    }
+
+    #[test]
+    fn fft_puts_a_sine_in_its_bin() {
+        const LEN: usize = 64;
+        for bin in [1, 5, 8, 31] {
+            let mut re = [0i16; LEN];
+            let mut im = [0i16; LEN];
+            for (i, sample) in re.iter_mut().enumerate() {
+                *sample = mul_q15(16384, sin_q15(i * bin * MAX_LEN / LEN));
+            }
+            fft(&mut re, &mut im);
+            let mut bins = [0u16; LEN / 2];
+            magnitudes(&re, &im, &mut bins);
+
+            let (peak, &height) = bins.iter().enumerate().max_by_key(|(_, &m)| m).unwrap();
+            assert_eq!(peak, bin);
+            // Half the amplitude, as the transform is scaled by 1 / len
+            assert!((8100..=8200).contains(&height), "{}", height);
+            for (other, &m) in bins.iter().enumerate() {
+                if other != bin {
+                    assert!(m < 16, "bin {} has {} for a sine in bin {}", other, m, bin);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fft_of_dc_lands_in_bin_zero() {
+        let mut re = [10000i16; 16];
+        let mut im = [0i16; 16];
+        fft(&mut re, &mut im);
+        assert_eq!((re[0], im[0]), (10000, 0));
+        assert!(re[1..].iter().chain(&im[1..]).all(|&x| x.abs() <= 1));
+    }
+}
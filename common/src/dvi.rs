@@ -0,0 +1,644 @@
+//! Experimental DVI output from PIO and DMA, after picodvi.
+//!
+//! DVI sends three TMDS-encoded colour lanes and a pixel clock as
+//! differential pairs. Each lane here is a PIO state machine shifting out
+//! one bit per system clock cycle on two pins, fed by a DMA channel. The
+//! clock lane is a PWM slice running at a tenth of that. The system clock has
+//! to be 252 MHz, e.g. from [`clocks::overclock`](crate::clocks::overclock),
+//! for 640x480 at 60 Hz.
+//!
+//! There is no time to TMDS-encode 640 arbitrary pixels per line, so the
+//! picture comes from a [`Frame`] of 320x240 pixels, each shown as a 2x2
+//! block, in 16 palette colours. Each palette colour is encoded once, into a
+//! pair of symbols that is DC balanced on its own, which only works for
+//! levels from 16 to 239 and not all of those: [`Dvi::set_palette`] picks
+//! the nearest.
+//!
+//! ```ignore
+//! let frame = cortex_m::singleton!(: Frame = Frame::new()).unwrap();
+//! let buffers = cortex_m::singleton!(: Buffers = Buffers::new()).unwrap();
+//! let dvi = Dvi::new(&mut pio, sm0, sm1, sm2, pwm7, channels, PICO_DV_DEMO_BASE, buffers, frame, Irq::Irq1);
+//! frame.set_pixel(10, 10, 15);
+//!
+//! #[interrupt]
+//! fn DMA_IRQ_1() {
+//!     // Once per half scanline: keeps the DMA going and encodes lines ahead
+//!     dvi.on_interrupt();
+//! }
+//! ```
+//!
+//! The interrupt encodes the next line every other scanline, taking about a
+//! quarter of a core, and has to be serviced within a few microseconds, so
+//! keep it at a high priority, away from `dma::dispatch`, or run it on core
+//! 1. Needs the `dvi` feature.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Size},
+    pixelcolor::{raw::RawU4, PixelColor},
+    Pixel,
+};
+use embedded_hal::PwmPin;
+
+use crate::dma::{Channel, Irq};
+use crate::hal::{
+    pac,
+    pio::{
+        Buffers as PioBuffers, PIOBuilder, PIOExt, PinDir, ShiftDirection, StateMachine, Stopped,
+        Tx, UninitStateMachine, PIO, SM0, SM1, SM2,
+    },
+    pwm::{FreeRunning, Slice, SliceId, ValidSliceMode},
+};
+
+/// The system clock DVI needs: ten bits per pixel at 25.2 MHz.
+pub const SYS_CLOCK_HZ: u32 = 252_000_000;
+
+/// Width of a [`Frame`], in pixels.
+pub const WIDTH: usize = 320;
+
+/// Height of a [`Frame`], in pixels.
+pub const HEIGHT: usize = 240;
+
+/// 640x480 at 60 Hz, in pixels and lines. Both syncs are active low.
+const H_ACTIVE: usize = 640;
+const H_FRONT: usize = 16;
+const H_SYNC: usize = 96;
+const V_ACTIVE: usize = 480;
+const V_FRONT: usize = 10;
+const V_SYNC: usize = 2;
+const LINES: usize = 525;
+
+/// Each word holds two 10 bit symbols, so two pixels.
+const ACTIVE_WORDS: usize = H_ACTIVE / 2;
+const HBLANK_WORDS: usize = 80;
+
+/// DMA blocks per frame: every line has its active part and its blanking.
+const BLOCKS: usize = LINES * 2;
+
+/// The control symbols, by `VSYNC << 1 | HSYNC`.
+const CTRL_SYMBOLS: [u32; 4] = [0x354, 0x0AB, 0x154, 0x2AB];
+
+/// Where a board has the DVI lanes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pinout {
+    /// The positive pin of the blue, green and red lanes, with the negative
+    /// pin on the next GPIO.
+    pub data: [u8; 3],
+    /// The positive clock pin, even, with the negative pin on the next GPIO.
+    pub clock: u8,
+    /// Whether the board swaps every pair's positive and negative pins.
+    pub invert: bool,
+}
+
+/// The Pimoroni Pico DV Demo Base's HDMI socket.
+pub const PICO_DV_DEMO_BASE: Pinout = Pinout {
+    data: [12, 18, 16],
+    clock: 14,
+    invert: true,
+};
+
+/// A colour in a [`Frame`]: an index into the [`Dvi`] palette, 0 to 15.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaletteIndex(pub u8);
+
+impl PixelColor for PaletteIndex {
+    type Raw = RawU4;
+}
+
+/// A 320x240 picture in 16 palette colours, shared by the code drawing it
+/// and the [`Dvi`] interrupt showing it.
+///
+/// Drawing while it is shown can tear, but never blocks the interrupt.
+/// `&Frame` is an `embedded-graphics` draw target.
+pub struct Frame {
+    /// Eight pixels per word, the leftmost in the lowest nibble.
+    words: [AtomicU32; WIDTH * HEIGHT / 8],
+}
+
+impl Frame {
+    /// A frame in palette colour 0.
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU32 = AtomicU32::new(0);
+        Frame {
+            words: [ZERO; WIDTH * HEIGHT / 8],
+        }
+    }
+
+    /// Set the pixel at `x`, `y` to palette colour `index`, if it is inside
+    /// the frame.
+    pub fn set_pixel(&self, x: usize, y: usize, index: u8) {
+        if x >= WIDTH || y >= HEIGHT {
+            return;
+        }
+        let word = &self.words[(y * WIDTH + x) / 8];
+        let shift = (x % 8) * 4;
+        // Only this code writes the frame, so a plain read-modify-write
+        // is enough
+        let value = word.load(Ordering::Relaxed) & !(0xF << shift);
+        word.store(value | u32::from(index & 0xF) << shift, Ordering::Relaxed);
+    }
+
+    /// The palette colour of the pixel at `x`, `y`.
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        let word = self.words[(y * WIDTH + x) / 8].load(Ordering::Relaxed);
+        (word >> ((x % 8) * 4)) as u8 & 0xF
+    }
+
+    /// Set every pixel to palette colour `index`.
+    pub fn clear(&self, index: u8) {
+        let value = u32::from(index & 0xF) * 0x1111_1111;
+        for word in &self.words {
+            word.store(value, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OriginDimensions for &Frame {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+impl DrawTarget for &Frame {
+    type Color = PaletteIndex;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, PaletteIndex(index)) in pixels {
+            if point.x >= 0 && point.y >= 0 {
+                self.set_pixel(point.x as usize, point.y as usize, index);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// TMDS-encode `data`, updating the running `disparity`.
+fn encode(data: u8, disparity: &mut i32) -> u32 {
+    let ones = data.count_ones();
+    let xnor = ones > 4 || (ones == 4 && data & 1 == 0);
+    let mut q_m = u32::from(data & 1);
+    for bit in 1..8 {
+        let previous = (q_m >> (bit - 1)) & 1;
+        let next = previous ^ u32::from((data >> bit) & 1) ^ u32::from(xnor);
+        q_m |= next << bit;
+    }
+    if !xnor {
+        q_m |= 1 << 8;
+    }
+
+    let ones = (q_m & 0xFF).count_ones() as i32;
+    let balance = ones - (8 - ones);
+    let q_m8 = (q_m >> 8) as i32;
+    if *disparity == 0 || balance == 0 {
+        if q_m8 == 1 {
+            *disparity += balance;
+            q_m
+        } else {
+            *disparity -= balance;
+            0x200 | (!q_m & 0xFF)
+        }
+    } else if (*disparity > 0) == (balance > 0) {
+        *disparity += 2 * q_m8 - balance;
+        0x200 | (q_m & 0x100) | (!q_m & 0xFF)
+    } else {
+        *disparity += balance - 2 * (1 - q_m8);
+        q_m
+    }
+}
+
+/// Two symbols for `level` that add up to no disparity, packed for the PIO,
+/// for the nearest level that has them.
+fn balanced_pair(level: u8) -> u32 {
+    for distance in 0..=255u8 {
+        for candidate in [
+            level.saturating_sub(distance),
+            level.saturating_add(distance),
+        ] {
+            let mut disparity = 0;
+            let first = encode(candidate, &mut disparity);
+            let second = encode(candidate, &mut disparity);
+            if disparity == 0 {
+                return first | second << 10;
+            }
+        }
+    }
+    unreachable!("some levels always balance")
+}
+
+/// Two control symbols for the sync levels, packed for the PIO.
+fn ctrl_pair(hsync: bool, vsync: bool) -> u32 {
+    let symbol = CTRL_SYMBOLS[usize::from(vsync) << 1 | usize::from(hsync)];
+    symbol | symbol << 10
+}
+
+/// The 16 colours of the default palette, as red, green, blue.
+const DEFAULT_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (0, 0, 170),
+    (0, 170, 0),
+    (0, 170, 170),
+    (170, 0, 0),
+    (170, 0, 170),
+    (170, 85, 0),
+    (170, 170, 170),
+    (85, 85, 85),
+    (85, 85, 255),
+    (85, 255, 85),
+    (85, 255, 255),
+    (255, 85, 85),
+    (255, 85, 255),
+    (255, 255, 85),
+    (255, 255, 255),
+];
+
+/// The DMA's buffers: encoded lines, blanking and the per-lane lists of
+/// blocks, about 37 kB, e.g. for `cortex_m::singleton!`.
+pub struct Buffers {
+    /// Two encoded lines per lane, taking turns.
+    lines: [[[u32; ACTIVE_WORDS]; 2]; 3],
+    /// Lane 0's active part of a blank line, outside and during vsync.
+    vblank: [[u32; ACTIVE_WORDS]; 2],
+    /// Lane 0's horizontal blanking, outside and during vsync.
+    hblank: [[u32; HBLANK_WORDS]; 2],
+    /// Lanes 1 and 2 carry no syncs, so blank the same way throughout.
+    idle: [u32; ACTIVE_WORDS],
+    /// For each lane, a transfer count and read address for every block.
+    lists: [[[u32; 2]; BLOCKS]; 3],
+}
+
+impl Buffers {
+    /// Empty buffers, filled in by [`Dvi::new`].
+    pub const fn new() -> Self {
+        Buffers {
+            lines: [[[0; ACTIVE_WORDS]; 2]; 3],
+            vblank: [[0; ACTIVE_WORDS]; 2],
+            hblank: [[0; HBLANK_WORDS]; 2],
+            idle: [0; ACTIVE_WORDS],
+            lists: [[[0; 2]; BLOCKS]; 3],
+        }
+    }
+
+    /// Fill in the blanking and the block lists.
+    fn build(&mut self) {
+        for (vsync, line) in self.vblank.iter_mut().enumerate() {
+            line.fill(ctrl_pair(true, vsync == 0));
+        }
+        for (vsync, line) in self.hblank.iter_mut().enumerate() {
+            let vsync = vsync == 0;
+            let (front, rest) = line.split_at_mut(H_FRONT / 2);
+            let (sync, back) = rest.split_at_mut(H_SYNC / 2);
+            front.fill(ctrl_pair(true, vsync));
+            sync.fill(ctrl_pair(false, vsync));
+            back.fill(ctrl_pair(true, vsync));
+        }
+        self.idle.fill(ctrl_pair(false, false));
+
+        for (lane, list) in self.lists.iter_mut().enumerate() {
+            for (line, blocks) in list.chunks_exact_mut(2).enumerate() {
+                let in_vsync = (V_ACTIVE + V_FRONT..V_ACTIVE + V_FRONT + V_SYNC).contains(&line);
+                let active = if line < V_ACTIVE {
+                    self.lines[lane][(line / 2) % 2].as_ptr()
+                } else if lane == 0 {
+                    self.vblank[usize::from(in_vsync)].as_ptr()
+                } else {
+                    self.idle.as_ptr()
+                };
+                let blanking = if lane == 0 {
+                    self.hblank[usize::from(in_vsync)].as_ptr()
+                } else {
+                    self.idle.as_ptr()
+                };
+                blocks[0] = [ACTIVE_WORDS as u32, active as u32];
+                blocks[1] = [HBLANK_WORDS as u32, blanking as u32];
+            }
+        }
+    }
+}
+
+impl Default for Buffers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `CH_CTRL` for a lane's data channel: enabled, high priority, words,
+/// incrementing the read address, paced by `dreq`, chaining to its list
+/// channel, and interrupting only for lane 0.
+fn data_ctrl(list: u8, dreq: u8, quiet: bool) -> u32 {
+    const EN: u32 = 1 << 0;
+    const HIGH_PRIORITY: u32 = 1 << 1;
+    const SIZE_WORD: u32 = 2 << 2;
+    const INCR_READ: u32 = 1 << 4;
+    EN | HIGH_PRIORITY
+        | SIZE_WORD
+        | INCR_READ
+        | u32::from(list) << 11
+        | u32::from(dreq) << 15
+        | u32::from(quiet) << 21
+}
+
+/// `CH_CTRL` for a lane's list channel, which writes a block's count and
+/// address to its data channel's `AL3_TRANS_COUNT` and
+/// `AL3_READ_ADDR_TRIG`, starting it.
+fn list_ctrl(own: u8) -> u32 {
+    const EN: u32 = 1 << 0;
+    const SIZE_WORD: u32 = 2 << 2;
+    const INCR_READ: u32 = 1 << 4;
+    const INCR_WRITE: u32 = 1 << 5;
+    // Wrap the write address at 8 bytes, over the two registers
+    const RING_SIZE_8: u32 = 3 << 6;
+    const RING_SEL_WRITE: u32 = 1 << 10;
+    const TREQ_PERMANENT: u32 = 0x3f << 15;
+    const IRQ_QUIET: u32 = 1 << 21;
+    // Chaining to ourselves disables chaining
+    EN | SIZE_WORD
+        | INCR_READ
+        | INCR_WRITE
+        | RING_SIZE_8
+        | RING_SEL_WRITE
+        | u32::from(own) << 11
+        | TREQ_PERMANENT
+        | IRQ_QUIET
+}
+
+/// Encode line `y` of `frame` into each lane's buffer for it.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+fn render_line(
+    frame: &Frame,
+    palette: &[[u32; 3]; 16],
+    lines: &mut [[[u32; ACTIVE_WORDS]; 2]; 3],
+    y: usize,
+) {
+    let [blue, green, red] = lines;
+    let (blue, green, red) = (&mut blue[y % 2], &mut green[y % 2], &mut red[y % 2]);
+    let words = &frame.words[y * WIDTH / 8..(y + 1) * WIDTH / 8];
+    for (x, word) in words.iter().enumerate() {
+        let mut pixels = word.load(Ordering::Relaxed);
+        for x in x * 8..x * 8 + 8 {
+            let [b, g, r] = palette[(pixels & 0xF) as usize];
+            blue[x] = b;
+            green[x] = g;
+            red[x] = r;
+            pixels >>= 4;
+        }
+    }
+}
+
+/// Each lane's state machine: blue, green, then red.
+type LaneMachines<P> = (
+    StateMachine<(P, SM0), Stopped>,
+    StateMachine<(P, SM1), Stopped>,
+    StateMachine<(P, SM2), Stopped>,
+);
+
+/// Each lane's FIFO, in the same order.
+type LaneFifos<P> = (Tx<(P, SM0)>, Tx<(P, SM1)>, Tx<(P, SM2)>);
+
+/// DVI output running from a PIO block, a PWM slice and six DMA channels.
+pub struct Dvi<P: PIOExt, S: SliceId>
+where
+    FreeRunning: ValidSliceMode<S>,
+{
+    _sms: LaneMachines<P>,
+    _tx: LaneFifos<P>,
+    _clock: Slice<S, FreeRunning>,
+    /// Each lane's data channel, then each lane's list channel.
+    channels: [Channel; 6],
+    buffers: &'static mut Buffers,
+    frame: &'static Frame,
+    /// Each colour's symbol pair for the blue, green and red lanes.
+    palette: [[u32; 3]; 16],
+    /// The next block lane 0 finishes.
+    block: usize,
+}
+
+impl<P, S> Dvi<P, S>
+where
+    P: PIOExt,
+    S: SliceId,
+    FreeRunning: ValidSliceMode<S>,
+{
+    /// Start sending `frame` out on `pinout`.
+    ///
+    /// The data pins must already be set to the PIO's function and the
+    /// clock pins to `clock`'s, which has to be their PWM slice. The system
+    /// clock must be [`SYS_CLOCK_HZ`]. `channels` are any six free DMA
+    /// channels; completion interrupts come in on `irq`, which has to be
+    /// unmasked straight away, with [`on_interrupt`](Self::on_interrupt)
+    /// called from its handler.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm0: UninitStateMachine<(P, SM0)>,
+        sm1: UninitStateMachine<(P, SM1)>,
+        sm2: UninitStateMachine<(P, SM2)>,
+        mut clock: Slice<S, FreeRunning>,
+        mut channels: [Channel; 6],
+        pinout: Pinout,
+        buffers: &'static mut Buffers,
+        frame: &'static Frame,
+        irq: Irq,
+    ) -> Self {
+        assert!(
+            pinout.clock.is_multiple_of(2),
+            "DVI clock needs PWM channel A"
+        );
+
+        let mut palette = [[0; 3]; 16];
+        for (entry, &(r, g, b)) in palette.iter_mut().zip(DEFAULT_PALETTE.iter()) {
+            *entry = [balanced_pair(b), balanced_pair(g), balanced_pair(r)];
+        }
+        buffers.build();
+        render_line(frame, &palette, &mut buffers.lines, 0);
+        render_line(frame, &palette, &mut buffers.lines, 1);
+
+        // Note (safety): the pins belong to the caller, who has handed them
+        // to the PIO and PWM; only their output inversion, slew rate and
+        // drive strength change
+        let io = unsafe { &*pac::IO_BANK0::ptr() };
+        let pads = unsafe { &*pac::PADS_BANK0::ptr() };
+        let pins = pinout.data.iter().chain(core::iter::once(&pinout.clock));
+        for &pin in pins {
+            for pin in [pin, pin + 1] {
+                let pin = usize::from(pin);
+                if pinout.invert {
+                    io.gpio[pin].gpio_ctrl.modify(|_, w| w.outover().invert());
+                }
+                // 12 mA drive, fast slew
+                pads.gpio[pin].modify(|r, w| unsafe { w.bits(r.bits() | 3 << 4 | 1) });
+            }
+        }
+
+        // Every instruction sends one bit and jumps to the instruction that
+        // drives the pair to that bit's levels, hence the origin
+        let program = pio_proc::pio_asm!(
+            ".side_set 2",
+            ".origin 0",
+            "    out pc, 1    side 0b10",
+            "    out pc, 1    side 0b01",
+        );
+        let installed = pio
+            .install(&program.program)
+            .expect("DVI needs offset 0 of the PIO's instruction memory");
+        // Note (safety): the program never changes, so all three state
+        // machines can run the one copy
+        let shared = || unsafe { installed.share() };
+        let build = |program, pin: u8| {
+            PIOBuilder::from_program(program)
+                .side_set_pin_base(pin)
+                .out_shift_direction(ShiftDirection::Right)
+                .autopull(true)
+                .pull_threshold(20)
+                .buffers(PioBuffers::OnlyTx)
+                .clock_divisor_fixed_point(1, 0)
+        };
+        let (mut sm0, _, tx0) = build(shared(), pinout.data[0]).build(sm0);
+        let (mut sm1, _, tx1) = build(shared(), pinout.data[1]).build(sm1);
+        let (mut sm2, _, tx2) = build(installed, pinout.data[2]).build(sm2);
+        sm0.set_pindirs([
+            (pinout.data[0], PinDir::Output),
+            (pinout.data[0] + 1, PinDir::Output),
+        ]);
+        sm1.set_pindirs([
+            (pinout.data[1], PinDir::Output),
+            (pinout.data[1] + 1, PinDir::Output),
+        ]);
+        sm2.set_pindirs([
+            (pinout.data[2], PinDir::Output),
+            (pinout.data[2] + 1, PinDir::Output),
+        ]);
+
+        // Five cycles high, five low, with B the inverse of A
+        clock.set_div_int(1);
+        clock.set_top(9);
+        clock.channel_a.set_duty(5);
+        clock.channel_b.set_duty(5);
+        clock.channel_b.set_inverted();
+
+        let fifos = [
+            tx0.fifo_address() as u32,
+            tx1.fifo_address() as u32,
+            tx2.fifo_address() as u32,
+        ];
+        for (lane, &fifo) in fifos.iter().enumerate() {
+            let (data, list) = (&channels[lane], &channels[lane + 3]);
+            let dreq = (P::id() * 8 + lane) as u8;
+            let regs = data.regs();
+            regs.ch_write_addr.write(|w| unsafe { w.bits(fifo) });
+            regs.ch_al1_ctrl
+                .write(|w| unsafe { w.bits(data_ctrl(list.id(), dreq, lane != 0)) });
+
+            let regs = list.regs();
+            regs.ch_read_addr
+                .write(|w| unsafe { w.bits(buffers.lists[lane].as_ptr() as u32) });
+            regs.ch_write_addr
+                .write(|w| unsafe { w.bits(data.regs().ch_al3_trans_count.as_ptr() as u32) });
+            regs.ch_trans_count.write(|w| unsafe { w.bits(2) });
+            regs.ch_al1_ctrl
+                .write(|w| unsafe { w.bits(list_ctrl(list.id())) });
+        }
+        channels[0].listen(irq, None);
+
+        // Start the list channels, which fill the FIFOs, then every lane
+        // and the clock together
+        let dma = unsafe { &*pac::DMA::ptr() };
+        let lists = channels[3..]
+            .iter()
+            .fold(0u32, |mask, channel| mask | 1 << channel.id());
+        dma.multi_chan_trigger.write(|w| unsafe { w.bits(lists) });
+        while !(tx0.is_full() && tx1.is_full() && tx2.is_full()) {}
+        let pio_regs = unsafe {
+            &*match P::id() {
+                0 => pac::PIO0::ptr(),
+                _ => pac::PIO1::ptr(),
+            }
+        };
+        cortex_m::interrupt::free(|_| {
+            clock.enable();
+            // SM_ENABLE and CLKDIV_RESTART for state machines 0 to 2
+            pio_regs
+                .ctrl
+                .modify(|r, w| unsafe { w.bits(r.bits() | 0x7 | 0x7 << 8) });
+        });
+
+        Dvi {
+            _sms: (sm0, sm1, sm2),
+            _tx: (tx0, tx1, tx2),
+            _clock: clock,
+            channels,
+            buffers,
+            frame,
+            palette,
+            block: 0,
+        }
+    }
+
+    /// Set palette colour `index` to the nearest of `red`, `green` and
+    /// `blue` that DVI can send, from about 16 to 239 each.
+    pub fn set_palette(&mut self, index: u8, red: u8, green: u8, blue: u8) {
+        self.palette[usize::from(index & 0xF)] = [
+            balanced_pair(blue),
+            balanced_pair(green),
+            balanced_pair(red),
+        ];
+    }
+
+    /// The frame being shown.
+    pub fn frame(&self) -> &'static Frame {
+        self.frame
+    }
+
+    /// Keep the output going. Call this from the DMA interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        let dma = unsafe { &*pac::DMA::ptr() };
+        let mask = 1 << self.channels[0].id();
+        dma.ints0.write(|w| unsafe { w.bits(mask) });
+        dma.ints1.write(|w| unsafe { w.bits(mask) });
+
+        let done = self.block;
+        self.block = (done + 1) % BLOCKS;
+
+        if done == BLOCKS - 2 {
+            // Every list channel has loaded, or is about to load, the last
+            // block; point them back at the first before they need it
+            let lists = self.buffers.lists.iter().zip(&self.channels[3..]);
+            for (list, channel) in lists {
+                let end = list.as_ptr_range().end as u32;
+                let regs = channel.regs();
+                while regs.ch_read_addr.read().bits() != end {}
+                regs.ch_read_addr
+                    .write(|w| unsafe { w.bits(list.as_ptr() as u32) });
+            }
+        }
+
+        if done.is_multiple_of(2) {
+            // The active part of `line` is out, so the buffer the line
+            // before it used is free
+            let line = done / 2;
+            let next = if line == LINES - 1 {
+                Some(0)
+            } else if line.is_multiple_of(2) && line < V_ACTIVE - 2 {
+                Some(line / 2 + 1)
+            } else {
+                None
+            };
+            if let Some(y) = next {
+                render_line(self.frame, &self.palette, &mut self.buffers.lines, y);
+            }
+        }
+    }
+}
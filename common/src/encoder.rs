@@ -0,0 +1,237 @@
+//! Rotary encoders with detents, and the push switch many of them have.
+//!
+//! An encoder's A and B contacts open and close a quarter cycle apart, A
+//! first when turning clockwise; a knob with detents usually clicks once
+//! every [`DEFAULT_STEPS_PER_DETENT`] changes. [`Detents`] adds those changes
+//! up into clicks, handed out by [`Detents::delta`], and times them, so that
+//! [`Detents::velocity`] can make a fast spin cover more ground than a slow
+//! one.
+//!
+//! [`Encoder`] reads A and B from two GPIOs. Call [`Encoder::update`] from
+//! the GPIO interrupt on both edges of both pins, stamped with the timer's
+//! 64-bit microsecond count, or poll it often enough to see every change:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn IO_IRQ_BANK0() {
+//!     critical_section::with(|cs| {
+//!         let mut encoder = ENCODER.borrow_ref_mut(cs);
+//!         let encoder = encoder.as_mut().unwrap();
+//!         let (a, b) = encoder.pins_mut();
+//!         for edge in [EdgeLow, EdgeHigh] {
+//!             a.clear_interrupt(edge);
+//!             b.clear_interrupt(edge);
+//!         }
+//!         encoder.update(timer.get_counter().ticks()).unwrap();
+//!     });
+//! }
+//! ```
+//!
+//! For an encoder decoded by PIO, such as
+//! `rp_hal_boards_pio::quadrature::Quadrature`, feed its running count to a
+//! [`Detents`] with [`Detents::set_count`] instead.
+//!
+//! The switch is an [`input::Button`](crate::input::Button), given to
+//! [`Encoder::with_switch`]; it is debounced by [`Encoder::update_switch`],
+//! which wants calling at a steady rate like any other button.
+
+use embedded_hal::digital::v2::InputPin;
+
+use crate::input::{Button, ButtonEvent};
+
+/// Changes of A and B per click, for most knobs with detents.
+pub const DEFAULT_STEPS_PER_DETENT: u8 = 4;
+
+/// How long after the last click [`Detents::velocity`] drops to 0, in
+/// microseconds.
+pub const VELOCITY_TIMEOUT_US: u64 = 250_000;
+
+/// The change in steps for each `previous << 2 | current` state, with A in
+/// bit 1 and B in bit 0. Clockwise, A leads: 00, 10, 11, 01.
+const STEPS: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
+/// Quadrature steps gathered into clicks, and how fast they come.
+#[derive(Clone, Copy, Debug)]
+pub struct Detents {
+    steps_per_detent: i32,
+    /// Steps towards the next click, one way or the other.
+    steps: i32,
+    /// Clicks not yet handed out by `delta`.
+    delta: i32,
+    /// The count last given to `set_count`.
+    count: i32,
+    /// When the last click was, in microseconds.
+    last_us: Option<u64>,
+    /// The time between the last two clicks, in microseconds.
+    interval_us: u64,
+}
+
+impl Detents {
+    /// Clicks of `steps_per_detent` steps, at least 1.
+    pub const fn new(steps_per_detent: u8) -> Self {
+        let steps_per_detent = if steps_per_detent == 0 {
+            1
+        } else {
+            steps_per_detent
+        };
+        Detents {
+            steps_per_detent: steps_per_detent as i32,
+            steps: 0,
+            delta: 0,
+            count: 0,
+            last_us: None,
+            interval_us: 0,
+        }
+    }
+
+    /// Add `steps`, positive clockwise, taken at `now_us`.
+    pub fn step(&mut self, steps: i32, now_us: u64) {
+        self.steps += steps;
+        while self.steps.abs() >= self.steps_per_detent {
+            let direction = self.steps.signum();
+            self.steps -= direction * self.steps_per_detent;
+            self.delta += direction;
+            self.interval_us = match self.last_us {
+                Some(last) => now_us.saturating_sub(last),
+                None => VELOCITY_TIMEOUT_US,
+            };
+            self.last_us = Some(now_us);
+        }
+    }
+
+    /// Take the running count of steps of a decoder that keeps its own, as
+    /// read at `now_us`.
+    pub fn set_count(&mut self, count: i32, now_us: u64) {
+        let steps = count.wrapping_sub(self.count);
+        self.count = count;
+        if steps != 0 {
+            self.step(steps, now_us);
+        }
+    }
+
+    /// The clicks since the last call, positive clockwise.
+    pub fn delta(&mut self) -> i32 {
+        core::mem::take(&mut self.delta)
+    }
+
+    /// How fast the knob is turning at `now_us`, in clicks a second, from the
+    /// time between the last two clicks; 0 once it has stopped for
+    /// [`VELOCITY_TIMEOUT_US`].
+    pub fn velocity(&self, now_us: u64) -> u32 {
+        match self.last_us {
+            Some(last) if now_us.saturating_sub(last) < VELOCITY_TIMEOUT_US => {
+                (1_000_000 / self.interval_us.max(1)) as u32
+            }
+            _ => 0,
+        }
+    }
+}
+
+impl Default for Detents {
+    fn default() -> Self {
+        Detents::new(DEFAULT_STEPS_PER_DETENT)
+    }
+}
+
+/// An encoder on two GPIOs, and its switch, if it has one and it is given.
+pub struct Encoder<A, B, S = ()> {
+    a: A,
+    b: B,
+    switch: S,
+    /// `A << 1 | B` when last read.
+    state: u8,
+    detents: Detents,
+}
+
+impl<A, B, E> Encoder<A, B>
+where
+    A: InputPin<Error = E>,
+    B: InputPin<Error = E>,
+{
+    /// An encoder on `a` and `b`, already inputs with the pull-ups the
+    /// contacts need, clicking every [`DEFAULT_STEPS_PER_DETENT`] steps.
+    pub fn new(a: A, b: B) -> Result<Self, E> {
+        Encoder::with_switch(a, b, ())
+    }
+}
+
+impl<A, B, S, E> Encoder<A, B, S>
+where
+    A: InputPin<Error = E>,
+    B: InputPin<Error = E>,
+{
+    /// An encoder on `a` and `b`, as for [`Encoder::new`], with its push
+    /// `switch`.
+    pub fn with_switch(a: A, b: B, switch: S) -> Result<Self, E> {
+        let mut encoder = Encoder {
+            a,
+            b,
+            switch,
+            state: 0,
+            detents: Detents::default(),
+        };
+        encoder.state = encoder.read()?;
+        Ok(encoder)
+    }
+
+    /// Set how many steps make a click, at least 1.
+    pub fn set_steps_per_detent(&mut self, steps: u8) {
+        self.detents = Detents::new(steps);
+    }
+
+    /// Read A and B, counting the step they have taken since the last read,
+    /// if any, at `now_us`.
+    ///
+    /// A read that has missed a state can't tell which way the knob went, so
+    /// counts nothing.
+    pub fn update(&mut self, now_us: u64) -> Result<(), E> {
+        let state = self.read()?;
+        if state != self.state {
+            let step = STEPS[usize::from(self.state << 2 | state)];
+            self.state = state;
+            self.detents.step(i32::from(step), now_us);
+        }
+        Ok(())
+    }
+
+    /// The clicks since the last call, positive clockwise.
+    pub fn delta(&mut self) -> i32 {
+        self.detents.delta()
+    }
+
+    /// How fast the knob is turning at `now_us`, in clicks a second.
+    ///
+    /// See [`Detents::velocity`].
+    pub fn velocity(&self, now_us: u64) -> u32 {
+        self.detents.velocity(now_us)
+    }
+
+    /// A and B, to enable and clear their interrupts.
+    pub fn pins_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.a, &mut self.b)
+    }
+
+    /// Give back A, B and the switch.
+    pub fn free(self) -> (A, B, S) {
+        (self.a, self.b, self.switch)
+    }
+
+    fn read(&self) -> Result<u8, E> {
+        Ok(u8::from(self.a.is_high()?) << 1 | u8::from(self.b.is_high()?))
+    }
+}
+
+impl<A, B, P> Encoder<A, B, Button<P>>
+where
+    P: InputPin,
+{
+    /// Read the switch, returning the change this completes.
+    pub fn update_switch(&mut self) -> Result<Option<ButtonEvent>, P::Error> {
+        self.switch.update()
+    }
+
+    /// Whether the switch is down, debounced.
+    pub fn is_pressed(&self) -> bool {
+        self.switch.is_pressed()
+    }
+}
@@ -0,0 +1,126 @@
+//! Gating optional work on the energy left in a supercap or battery.
+//!
+//! On a solar or supercap powered node, VSYS is a direct measure of the energy
+//! stored. [`EnergyBudget`] turns a VSYS reading into an estimate of the
+//! energy available above a reserve, and hands it out to optional tasks such
+//! as a radio transmission or a display refresh. A task is only allowed if
+//! its cost fits, and its cost is taken off the estimate until the next
+//! reading, so several tasks in one cycle don't all spend the same energy.
+//!
+//! Energies are in millijoules. Work out a task's cost as current × supply
+//! voltage × duration, e.g. 40 mA at 3.3 V for 200 ms is about 26 mJ.
+
+use embedded_hal::adc::{Channel, OneShot};
+
+use crate::hal::Adc;
+
+/// How the board stores its energy.
+#[derive(Clone, Copy, Debug)]
+pub enum Storage {
+    /// A supercapacitor on VSYS, usable down to `min_mv`.
+    Supercap {
+        /// Capacitance in millifarads
+        capacitance_mf: u32,
+        /// Lowest voltage the regulator still runs from
+        min_mv: u32,
+    },
+    /// A battery, taken as linear between `empty_mv` and `full_mv`.
+    Battery {
+        empty_mv: u32,
+        full_mv: u32,
+        /// Capacity in milliwatt-hours
+        capacity_mwh: u32,
+    },
+}
+
+impl Storage {
+    /// Energy stored at `vsys_mv`, in millijoules, down to empty.
+    pub fn energy_mj(&self, vsys_mv: u32) -> u32 {
+        match *self {
+            Storage::Supercap {
+                capacitance_mf,
+                min_mv,
+            } => {
+                // E = C (V² - Vmin²) / 2, with C in mF and V in mV
+                let v = u64::from(vsys_mv.max(min_mv));
+                let min = u64::from(min_mv);
+                let mj = u64::from(capacitance_mf) * (v * v - min * min) / 2_000_000;
+                mj.min(u64::from(u32::MAX)) as u32
+            }
+            Storage::Battery {
+                empty_mv,
+                full_mv,
+                capacity_mwh,
+            } => {
+                let span = u64::from(full_mv.saturating_sub(empty_mv).max(1));
+                let above = u64::from(vsys_mv.min(full_mv).saturating_sub(empty_mv));
+                let mj = u64::from(capacity_mwh) * 3600 * above / span;
+                mj.min(u64::from(u32::MAX)) as u32
+            }
+        }
+    }
+}
+
+/// Read VSYS through its divide-by-three network, in millivolts.
+///
+/// `pin` is the ADC input wired to the divider, GPIO29 on the Pico.
+pub fn read_vsys_mv<P: Channel<Adc, ID = u8>>(adc: &mut Adc, pin: &mut P) -> u32 {
+    let raw: u16 = adc.read(pin).unwrap_or(0);
+    u32::from(raw) * 3 * 3300 / 4096
+}
+
+/// Estimate of the energy available for optional tasks.
+pub struct EnergyBudget {
+    storage: Storage,
+    reserve_mj: u32,
+    vsys_mv: u32,
+    available_mj: u32,
+}
+
+impl EnergyBudget {
+    /// A budget for `storage` that keeps `reserve_mj` back for the node's
+    /// essential work, such as sleeping until the next reading. Nothing is
+    /// allowed until the first [`update`](Self::update).
+    pub const fn new(storage: Storage, reserve_mj: u32) -> Self {
+        EnergyBudget {
+            storage,
+            reserve_mj,
+            vsys_mv: 0,
+            available_mj: 0,
+        }
+    }
+
+    /// Re-estimate the available energy from a fresh VSYS reading.
+    pub fn update(&mut self, vsys_mv: u32) {
+        self.vsys_mv = vsys_mv;
+        self.available_mj = self
+            .storage
+            .energy_mj(vsys_mv)
+            .saturating_sub(self.reserve_mj);
+    }
+
+    /// Read VSYS with [`read_vsys_mv`] and [`update`](Self::update).
+    pub fn sample<P: Channel<Adc, ID = u8>>(&mut self, adc: &mut Adc, pin: &mut P) {
+        self.update(read_vsys_mv(adc, pin));
+    }
+
+    /// The VSYS voltage at the last update, in millivolts.
+    pub fn vsys_mv(&self) -> u32 {
+        self.vsys_mv
+    }
+
+    /// Energy left above the reserve, in millijoules.
+    pub fn available_mj(&self) -> u32 {
+        self.available_mj
+    }
+
+    /// Whether a task costing `cost_mj` can run. If so, its cost is taken off
+    /// the estimate.
+    pub fn allow(&mut self, cost_mj: u32) -> bool {
+        if cost_mj > self.available_mj {
+            return false;
+        }
+        self.available_mj -= cost_mj;
+        true
+    }
+}
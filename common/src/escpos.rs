@@ -0,0 +1,176 @@
+//! Printing on ESC/POS thermal printers, such as the cheap 58 mm ones with
+//! a TTL serial input.
+//!
+//! A [`Printer`] wraps any serial port and sends the commands for text
+//! styles, barcodes and bitmaps, waiting for room in the port's FIFO as it
+//! goes. Text goes out as is, so stick to ASCII unless the printer's code
+//! page is known; `write!` works too.
+//!
+//! ```ignore
+//! let mut printer = Printer::new(uart);
+//! printer.init()?;
+//! printer.align(Align::Centre)?;
+//! printer.size(2, 2)?;
+//! printer.line("Daily summary")?;
+//! printer.size(1, 1)?;
+//! printer.barcode(Barcode::Code128, b"DAY-12")?;
+//! printer.feed(3)?;
+//! ```
+//!
+//! These printers take far more current than a Pico can supply, up to 2 A
+//! while printing dark areas, so power them separately and share only ground.
+//! Most print 384 dots across, 8 per millimetre; wider bitmaps are cut off.
+
+use embedded_hal::serial::Write;
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+
+/// Dots across the print head of a 58 mm printer.
+pub const DOTS_58MM: u16 = 384;
+
+/// Horizontal alignment of text, barcodes and bitmaps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left = 0,
+    Centre = 1,
+    Right = 2,
+}
+
+/// Barcode symbologies, each with the limits the printer checks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Barcode {
+    /// 11 or 12 digits.
+    UpcA = 65,
+    /// 12 or 13 digits.
+    Ean13 = 67,
+    /// 7 or 8 digits.
+    Ean8 = 68,
+    /// Digits, capitals, space and `$%*+-./`.
+    Code39 = 69,
+    /// Any ASCII, in code set B.
+    Code128 = 73,
+}
+
+/// A thermal printer on a serial port.
+pub struct Printer<W> {
+    port: W,
+}
+
+impl<W: Write<u8>> Printer<W> {
+    /// Wrap `port`, set to the printer's baud rate, usually 9600 or 19200.
+    pub fn new(port: W) -> Self {
+        Printer { port }
+    }
+
+    /// Return the serial port.
+    pub fn free(self) -> W {
+        self.port
+    }
+
+    /// Reset the printer's styles to their defaults.
+    pub fn init(&mut self) -> Result<(), W::Error> {
+        self.write_all(&[ESC, b'@'])
+    }
+
+    /// Print `text` without ending the line.
+    pub fn text(&mut self, text: &str) -> Result<(), W::Error> {
+        self.write_all(text.as_bytes())
+    }
+
+    /// Print `text` and end the line.
+    pub fn line(&mut self, text: &str) -> Result<(), W::Error> {
+        self.text(text)?;
+        self.write_all(b"\n")
+    }
+
+    /// Print what's buffered and feed `lines` lines of paper.
+    pub fn feed(&mut self, lines: u8) -> Result<(), W::Error> {
+        self.write_all(&[ESC, b'd', lines])
+    }
+
+    /// Turn bold on or off.
+    pub fn bold(&mut self, on: bool) -> Result<(), W::Error> {
+        self.write_all(&[ESC, b'E', u8::from(on)])
+    }
+
+    /// Turn underlining on or off.
+    pub fn underline(&mut self, on: bool) -> Result<(), W::Error> {
+        self.write_all(&[ESC, b'-', u8::from(on)])
+    }
+
+    /// Align what follows, from the start of a line.
+    pub fn align(&mut self, align: Align) -> Result<(), W::Error> {
+        self.write_all(&[ESC, b'a', align as u8])
+    }
+
+    /// Scale characters by `width` and `height`, each 1 to 8.
+    pub fn size(&mut self, width: u8, height: u8) -> Result<(), W::Error> {
+        let width = width.clamp(1, 8) - 1;
+        let height = height.clamp(1, 8) - 1;
+        self.write_all(&[GS, b'!', width << 4 | height])
+    }
+
+    /// Print `data` as a barcode `height` dots tall, 162 by default, with its
+    /// text underneath.
+    ///
+    /// Only the first 255 bytes are sent, 253 for Code 128. The printer
+    /// prints nothing if `data` doesn't suit `kind`.
+    pub fn barcode(&mut self, kind: Barcode, data: &[u8]) -> Result<(), W::Error> {
+        self.barcode_with_height(kind, data, 162)
+    }
+
+    /// [`barcode`](Self::barcode) at `height` dots.
+    pub fn barcode_with_height(
+        &mut self,
+        kind: Barcode,
+        data: &[u8],
+        height: u8,
+    ) -> Result<(), W::Error> {
+        // Code set B takes a two byte prefix
+        let prefix: &[u8] = if kind == Barcode::Code128 { b"{B" } else { b"" };
+        let data = &data[..data.len().min(255 - prefix.len())];
+        self.write_all(&[GS, b'h', height])?;
+        // Text below, two dots per module
+        self.write_all(&[GS, b'H', 2, GS, b'w', 2])?;
+        self.write_all(&[GS, b'k', kind as u8, (prefix.len() + data.len()) as u8])?;
+        self.write_all(prefix)?;
+        self.write_all(data)
+    }
+
+    /// Print a bitmap `width` dots across, from `rows` of `(width + 7) / 8`
+    /// bytes each, the leftmost dot in the MSB of the first byte and set bits
+    /// black.
+    pub fn bitmap(&mut self, width: u16, rows: &[u8]) -> Result<(), W::Error> {
+        let row_bytes = width.div_ceil(8);
+        let height = (rows.len() / usize::from(row_bytes)) as u16;
+        let [xl, xh] = row_bytes.to_le_bytes();
+        let [yl, yh] = height.to_le_bytes();
+        self.write_all(&[GS, b'v', b'0', 0, xl, xh, yl, yh])?;
+        self.write_all(&rows[..usize::from(row_bytes) * usize::from(height)])
+    }
+
+    /// Cut the paper, on printers with a cutter, after feeding it past the
+    /// blade.
+    pub fn cut(&mut self) -> Result<(), W::Error> {
+        self.write_all(&[GS, b'V', 66, 0])
+    }
+
+    /// Wait until everything sent has left the serial port.
+    pub fn flush(&mut self) -> Result<(), W::Error> {
+        nb::block!(self.port.flush())
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), W::Error> {
+        for &byte in bytes {
+            nb::block!(self.port.write(byte))?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write<u8>> core::fmt::Write for Printer<W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.text(s).map_err(|_| core::fmt::Error)
+    }
+}
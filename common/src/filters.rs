@@ -0,0 +1,351 @@
+//! Smoothing for noisy sensor readings.
+//!
+//! The RP2040's ADC is noisy, and pots and thermistors add noise of their own.
+//! Each filter here takes one reading at a time and returns the smoothed
+//! value, using integer arithmetic only:
+//!
+//! * [`MovingAverage`]: the mean of the last `N` readings,
+//! * [`Exponential`]: exponential smoothing, cheap and with a long memory,
+//! * [`Median`]: the median of the last `N` readings, which throws out
+//!   occasional wild readings without blurring real steps,
+//! * [`Hysteresis`]: a threshold with separate on and off levels, so a value
+//!   hovering near it doesn't flicker between the two.
+//!
+//! The first three implement [`Filter`], so they chain, e.g. a median to drop
+//! spikes followed by an average to smooth what is left:
+//!
+//! ```ignore
+//! let mut median = Median::<5>::new();
+//! let mut average = MovingAverage::<8>::new();
+//! let smooth = average.update(median.update(raw));
+//! ```
+
+/// Something that smooths a stream of readings.
+pub trait Filter {
+    /// Take the next reading, returning the filter's new output.
+    fn update(&mut self, sample: i32) -> i32;
+
+    /// The latest output, without adding a reading.
+    fn value(&self) -> i32;
+
+    /// Forget all readings so far.
+    fn reset(&mut self);
+}
+
+/// The mean of the last `N` readings.
+///
+/// Until `N` readings have arrived it averages the ones it has. Readings up to
+/// `i32::MAX / N` in size are safe from overflow.
+#[derive(Clone, Debug)]
+pub struct MovingAverage<const N: usize> {
+    samples: [i32; N],
+    next: usize,
+    count: usize,
+    sum: i32,
+}
+
+impl<const N: usize> MovingAverage<N> {
+    /// Stops a window of no readings from compiling.
+    const NOT_EMPTY: () = assert!(N > 0, "the window must hold at least one reading");
+
+    /// An empty average. `N` must be at least 1.
+    pub const fn new() -> Self {
+        let () = Self::NOT_EMPTY;
+        MovingAverage {
+            samples: [0; N],
+            next: 0,
+            count: 0,
+            sum: 0,
+        }
+    }
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Filter for MovingAverage<N> {
+    fn update(&mut self, sample: i32) -> i32 {
+        self.sum += sample - self.samples[self.next];
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        self.count = (self.count + 1).min(N);
+        self.value()
+    }
+
+    fn value(&self) -> i32 {
+        if self.count == 0 {
+            0
+        } else {
+            self.sum / self.count as i32
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Exponential smoothing: each reading moves the output `1 / 2^shift` of the
+/// way towards it.
+///
+/// A `shift` of 3 takes about 8 readings to settle, 4 about 16, and so on. The
+/// output is kept with `shift` extra fraction bits, so small changes still
+/// get through rather than being rounded away. The first reading is taken as
+/// is. Readings up to `i32::MAX >> shift` in size are safe from overflow.
+#[derive(Clone, Debug)]
+pub struct Exponential {
+    shift: u8,
+    /// The output, scaled by `2^shift`.
+    acc: i32,
+    primed: bool,
+}
+
+impl Exponential {
+    /// A filter with a smoothing factor of `1 / 2^shift`.
+    pub const fn new(shift: u8) -> Self {
+        assert!(shift < 16, "the shift must be below 16");
+        Exponential {
+            shift,
+            acc: 0,
+            primed: false,
+        }
+    }
+}
+
+impl Filter for Exponential {
+    fn update(&mut self, sample: i32) -> i32 {
+        if self.primed {
+            self.acc += sample - (self.acc >> self.shift);
+        } else {
+            self.acc = sample << self.shift;
+            self.primed = true;
+        }
+        self.value()
+    }
+
+    fn value(&self) -> i32 {
+        // Round to nearest rather than towards minus infinity
+        (self.acc + ((1 << self.shift) >> 1)) >> self.shift
+    }
+
+    fn reset(&mut self) {
+        self.acc = 0;
+        self.primed = false;
+    }
+}
+
+/// The median of the last `N` readings.
+///
+/// Until `N` readings have arrived it takes the median of the ones it has.
+/// Each update sorts a copy of the window, so keep `N` small; 3 to 9 is usual.
+#[derive(Clone, Debug)]
+pub struct Median<const N: usize> {
+    samples: [i32; N],
+    next: usize,
+    count: usize,
+}
+
+impl<const N: usize> Median<N> {
+    /// Stops a window of no readings from compiling.
+    const NOT_EMPTY: () = assert!(N > 0, "the window must hold at least one reading");
+
+    /// An empty window. `N` must be at least 1.
+    pub const fn new() -> Self {
+        let () = Self::NOT_EMPTY;
+        Median {
+            samples: [0; N],
+            next: 0,
+            count: 0,
+        }
+    }
+}
+
+impl<const N: usize> Default for Median<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Filter for Median<N> {
+    fn update(&mut self, sample: i32) -> i32 {
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        self.count = (self.count + 1).min(N);
+        self.value()
+    }
+
+    fn value(&self) -> i32 {
+        if self.count == 0 {
+            return 0;
+        }
+        // Until the window fills, the readings are at its start
+        let mut sorted = self.samples;
+        let sorted = &mut sorted[..self.count];
+        sorted.sort_unstable();
+        sorted[sorted.len() / 2]
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// A threshold that turns on at `high` and only turns off again at `low`.
+#[derive(Clone, Debug)]
+pub struct Hysteresis {
+    low: i32,
+    high: i32,
+    on: bool,
+}
+
+impl Hysteresis {
+    /// A threshold that starts off, turns on once a reading reaches `high`,
+    /// and off once one falls to `low`.
+    pub const fn new(low: i32, high: i32) -> Self {
+        assert!(low <= high, "low must not be above high");
+        Hysteresis {
+            low,
+            high,
+            on: false,
+        }
+    }
+
+    /// Take the next reading, returning whether the threshold is now on.
+    pub fn update(&mut self, sample: i32) -> bool {
+        if self.on && sample <= self.low {
+            self.on = false;
+        } else if !self.on && sample >= self.high {
+            self.on = true;
+        }
+        self.on
+    }
+
+    /// Whether the threshold is on, without adding a reading.
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_average_averages_what_it_has_until_full() {
+        let mut average = MovingAverage::<4>::new();
+        assert_eq!(average.value(), 0);
+        assert_eq!(average.update(10), 10);
+        assert_eq!(average.update(20), 15);
+        assert_eq!(average.update(30), 20);
+        assert_eq!(average.update(40), 25);
+        // The 10 drops out
+        assert_eq!(average.update(50), 35);
+        assert_eq!(average.value(), 35);
+    }
+
+    #[test]
+    fn moving_average_resets() {
+        let mut average = MovingAverage::<3>::new();
+        average.update(-9);
+        average.update(-3);
+        assert_eq!(average.value(), -6);
+        average.reset();
+        assert_eq!(average.value(), 0);
+        assert_eq!(average.update(7), 7);
+    }
+
+    #[test]
+    fn exponential_starts_at_the_first_reading() {
+        let mut smooth = Exponential::new(3);
+        assert_eq!(smooth.update(1000), 1000);
+        assert_eq!(smooth.value(), 1000);
+    }
+
+    #[test]
+    fn exponential_settles_on_a_step() {
+        let mut smooth = Exponential::new(2);
+        smooth.update(0);
+        // A quarter of the remaining way each time
+        assert_eq!(smooth.update(100), 25);
+        assert_eq!(smooth.update(100), 44);
+        let settled = (0..40).map(|_| smooth.update(100)).last().unwrap();
+        assert_eq!(settled, 100);
+    }
+
+    #[test]
+    fn exponential_keeps_small_changes() {
+        let mut smooth = Exponential::new(4);
+        smooth.update(0);
+        // Each step alone rounds to nothing, but they add up
+        for _ in 0..16 {
+            smooth.update(1);
+        }
+        assert_eq!(smooth.value(), 1);
+        smooth.reset();
+        assert_eq!(smooth.update(-50), -50);
+    }
+
+    #[test]
+    fn median_drops_spikes() {
+        let mut median = Median::<3>::new();
+        assert_eq!(median.value(), 0);
+        assert_eq!(median.update(10), 10);
+        assert_eq!(median.update(11), 11);
+        assert_eq!(median.update(1000), 11);
+        assert_eq!(median.update(12), 12);
+        assert_eq!(median.update(-500), 12);
+        assert_eq!(median.update(13), 12);
+        assert_eq!(median.update(14), 13);
+    }
+
+    #[test]
+    fn median_follows_a_step() {
+        let mut median = Median::<5>::new();
+        for _ in 0..5 {
+            median.update(0);
+        }
+        assert_eq!(median.update(50), 0);
+        assert_eq!(median.update(50), 0);
+        assert_eq!(median.update(50), 50);
+        median.reset();
+        assert_eq!(median.update(3), 3);
+    }
+
+    #[test]
+    fn filters_chain() {
+        let mut median = Median::<3>::new();
+        let mut average = MovingAverage::<2>::new();
+        let mut out = 0;
+        for &raw in &[100, 100, 5000, 100, 102] {
+            out = average.update(median.update(raw));
+        }
+        assert_eq!(out, 101);
+    }
+
+    #[test]
+    fn hysteresis_switches_at_separate_levels() {
+        let mut threshold = Hysteresis::new(10, 20);
+        assert!(!threshold.is_on());
+        assert!(!threshold.update(15));
+        assert!(!threshold.update(19));
+        assert!(threshold.update(20));
+        // Dropping back between the levels doesn't turn it off
+        assert!(threshold.update(15));
+        assert!(threshold.update(11));
+        assert!(!threshold.update(10));
+        assert!(!threshold.update(19));
+        assert!(!threshold.is_on());
+    }
+
+    #[test]
+    fn hysteresis_with_equal_levels_is_a_plain_threshold() {
+        let mut threshold = Hysteresis::new(5, 5);
+        assert!(threshold.update(5));
+        assert!(!threshold.update(5));
+        assert!(threshold.update(6));
+        assert!(!threshold.update(4));
+    }
+}
@@ -0,0 +1,293 @@
+//! Driver for serial fingerprint modules speaking the ZhianTec protocol, such
+//! as the R503, R307 and AS608.
+//!
+//! The module keeps the fingerprint templates in its own flash, in numbered
+//! slots, and does the matching itself. The host only asks it to take an
+//! image, turn images into features in one of its two character buffers, and
+//! then either search the library or combine two buffers into a template and
+//! store it:
+//!
+//! ```ignore
+//! let mut sensor = Fingerprint::new(uart);
+//! // Enrol slot 3 from two presses of the same finger
+//! while !sensor.capture()? {}
+//! sensor.convert(1)?;
+//! // ... wait for the finger to lift, and press again ...
+//! while !sensor.capture()? {}
+//! sensor.convert(2)?;
+//! sensor.create_model()?;
+//! sensor.store(1, 3)?;
+//! // Later, identify a finger
+//! while !sensor.capture()? {}
+//! sensor.convert(1)?;
+//! if let Some(found) = sensor.search(1, 0, 200)? { /* found.id */ }
+//! ```
+//!
+//! The modules talk 8N1 at 57600 baud unless set otherwise, and take 3.3 V.
+//! Replies are waited for with [`delay`](crate::delay), so timeouts are only
+//! right after [`delay::calibrate`](crate::delay::calibrate).
+
+use embedded_hal::serial::{Read, Write};
+
+use crate::delay;
+
+/// The baud rate the modules start at.
+pub const DEFAULT_BAUD: u32 = 57_600;
+
+/// The address the modules answer to unless set otherwise.
+pub const DEFAULT_ADDRESS: u32 = 0xFFFF_FFFF;
+
+const HEADER: [u8; 2] = [0xEF, 0x01];
+
+// Packet identifiers
+const COMMAND: u8 = 0x01;
+const ACK: u8 = 0x07;
+
+// Instructions
+const GEN_IMG: u8 = 0x01;
+const IMG_2_TZ: u8 = 0x02;
+const SEARCH: u8 = 0x04;
+const REG_MODEL: u8 = 0x05;
+const STORE: u8 = 0x06;
+const DELETE_CHAR: u8 = 0x0C;
+const EMPTY: u8 = 0x0D;
+const VFY_PWD: u8 = 0x13;
+const TEMPLATE_NUM: u8 = 0x1D;
+const AURA_LED_CONFIG: u8 = 0x35;
+
+// Confirmation codes the driver treats as answers rather than errors
+const OK: u8 = 0x00;
+const NO_FINGER: u8 = 0x02;
+const NOT_FOUND: u8 = 0x09;
+
+/// How long to wait for a reply, in microseconds. Searching a full library
+/// is the slowest command, at about half a second.
+const REPLY_TIMEOUT_US: u32 = 2_000_000;
+
+/// Time between checks for a reply byte, in microseconds.
+const POLL_US: u32 = 10;
+
+/// Errors from the module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The serial port failed.
+    Serial,
+    /// No reply came.
+    Timeout,
+    /// A reply was malformed, or failed its checksum.
+    Corrupt,
+    /// The module refused the command, with this confirmation code: for
+    /// example 0x06 or 0x07 for a poor image, 0x0A if the two images don't
+    /// match, 0x0B for a slot beyond the library, 0x13 for a wrong password.
+    Sensor(u8),
+}
+
+/// A template found by [`Fingerprint::search`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match {
+    /// The slot it is stored in.
+    pub id: u16,
+    /// How closely it matched; the module's threshold is around 50.
+    pub score: u16,
+}
+
+/// Colours of the R503's LED ring.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colour {
+    Red = 1,
+    Blue = 2,
+    Purple = 3,
+}
+
+/// What the R503's LED ring does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aura {
+    Breathing = 1,
+    Flashing = 2,
+    On = 3,
+    Off = 4,
+}
+
+/// A fingerprint module on a serial port.
+pub struct Fingerprint<S> {
+    serial: S,
+    address: u32,
+}
+
+impl<S> Fingerprint<S>
+where
+    S: Read<u8> + Write<u8>,
+{
+    /// Talk to the module at [`DEFAULT_ADDRESS`] on `serial`, set to its
+    /// baud rate, usually [`DEFAULT_BAUD`].
+    pub fn new(serial: S) -> Self {
+        Self::with_address(serial, DEFAULT_ADDRESS)
+    }
+
+    /// Talk to the module at `address`.
+    pub fn with_address(serial: S, address: u32) -> Self {
+        Fingerprint { serial, address }
+    }
+
+    /// Return the serial port.
+    pub fn free(self) -> S {
+        self.serial
+    }
+
+    /// Unlock a module that has a password set. Modules without one accept
+    /// anything.
+    pub fn verify_password(&mut self, password: u32) -> Result<(), Error> {
+        let [a, b, c, d] = password.to_be_bytes();
+        self.command(&[VFY_PWD, a, b, c, d], &mut [])
+    }
+
+    /// Take an image of the finger on the sensor, returning `false` if there
+    /// isn't one.
+    pub fn capture(&mut self) -> Result<bool, Error> {
+        match self.command(&[GEN_IMG], &mut []) {
+            Ok(()) => Ok(true),
+            Err(Error::Sensor(NO_FINGER)) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Turn the image into features in character buffer `buffer`, 1 or 2.
+    pub fn convert(&mut self, buffer: u8) -> Result<(), Error> {
+        self.command(&[IMG_2_TZ, buffer], &mut [])
+    }
+
+    /// Combine the features in both buffers into a template, left in both.
+    pub fn create_model(&mut self) -> Result<(), Error> {
+        self.command(&[REG_MODEL], &mut [])
+    }
+
+    /// Store the template in `buffer` in slot `id` of the library.
+    pub fn store(&mut self, buffer: u8, id: u16) -> Result<(), Error> {
+        let [high, low] = id.to_be_bytes();
+        self.command(&[STORE, buffer, high, low], &mut [])
+    }
+
+    /// Look for the features in `buffer` among `count` slots from `start`.
+    pub fn search(&mut self, buffer: u8, start: u16, count: u16) -> Result<Option<Match>, Error> {
+        let [start_high, start_low] = start.to_be_bytes();
+        let [count_high, count_low] = count.to_be_bytes();
+        let mut answer = [0; 4];
+        match self.command(
+            &[SEARCH, buffer, start_high, start_low, count_high, count_low],
+            &mut answer,
+        ) {
+            Ok(()) => Ok(Some(Match {
+                id: u16::from_be_bytes([answer[0], answer[1]]),
+                score: u16::from_be_bytes([answer[2], answer[3]]),
+            })),
+            Err(Error::Sensor(NOT_FOUND)) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Delete `count` templates from slot `id` on.
+    pub fn delete(&mut self, id: u16, count: u16) -> Result<(), Error> {
+        let [id_high, id_low] = id.to_be_bytes();
+        let [count_high, count_low] = count.to_be_bytes();
+        self.command(
+            &[DELETE_CHAR, id_high, id_low, count_high, count_low],
+            &mut [],
+        )
+    }
+
+    /// Delete every template.
+    pub fn empty(&mut self) -> Result<(), Error> {
+        self.command(&[EMPTY], &mut [])
+    }
+
+    /// The number of templates stored.
+    pub fn template_count(&mut self) -> Result<u16, Error> {
+        let mut answer = [0; 2];
+        self.command(&[TEMPLATE_NUM], &mut answer)?;
+        Ok(u16::from_be_bytes(answer))
+    }
+
+    /// Set the LED ring of an R503: `speed` for breathing and flashing, from
+    /// 0 (fast) to 255, and `times` to flash, 0 for ever. Other modules
+    /// refuse this.
+    pub fn aura(&mut self, aura: Aura, colour: Colour, speed: u8, times: u8) -> Result<(), Error> {
+        self.command(
+            &[AURA_LED_CONFIG, aura as u8, speed, colour as u8, times],
+            &mut [],
+        )
+    }
+
+    /// Send `instruction`, its code and parameters, and wait for the
+    /// acknowledgement, filling `answer` with what follows its confirmation
+    /// code.
+    fn command(&mut self, instruction: &[u8], answer: &mut [u8]) -> Result<(), Error> {
+        // Drop anything left from an earlier reply that timed out
+        while self.serial.read().is_ok() {}
+
+        let length = instruction.len() as u16 + 2;
+        let mut sum = u16::from(COMMAND) + length_sum(length);
+        for &byte in instruction {
+            sum = sum.wrapping_add(u16::from(byte));
+        }
+        self.write(&HEADER)?;
+        self.write(&self.address.to_be_bytes())?;
+        self.write(&[COMMAND])?;
+        self.write(&length.to_be_bytes())?;
+        self.write(instruction)?;
+        self.write(&sum.to_be_bytes())?;
+
+        // Header, address, identifier and length
+        let mut head = [0; 9];
+        for slot in head.iter_mut() {
+            *slot = self.read_byte()?;
+        }
+        let length = u16::from_be_bytes([head[7], head[8]]);
+        // The confirmation code and checksum, and what's between
+        let len = usize::from(length).wrapping_sub(3);
+        if head[..2] != HEADER || head[6] != ACK || len > answer.len() {
+            return Err(Error::Corrupt);
+        }
+        let code = self.read_byte()?;
+        let mut sum = u16::from(ACK)
+            .wrapping_add(length_sum(length))
+            .wrapping_add(u16::from(code));
+        for slot in answer[..len].iter_mut() {
+            *slot = self.read_byte()?;
+            sum = sum.wrapping_add(u16::from(*slot));
+        }
+        let expected = u16::from_be_bytes([self.read_byte()?, self.read_byte()?]);
+        if sum != expected {
+            return Err(Error::Corrupt);
+        }
+        match code {
+            // Some refusals come without the rest of the answer
+            OK if len == answer.len() => Ok(()),
+            OK => Err(Error::Corrupt),
+            _ => Err(Error::Sensor(code)),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        for &byte in bytes {
+            nb::block!(self.serial.write(byte)).map_err(|_| Error::Serial)?;
+        }
+        Ok(())
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        for _ in 0..REPLY_TIMEOUT_US / POLL_US {
+            match self.serial.read() {
+                Ok(byte) => return Ok(byte),
+                Err(nb::Error::WouldBlock) => delay::delay_us(POLL_US),
+                Err(nb::Error::Other(_)) => return Err(Error::Serial),
+            }
+        }
+        Err(Error::Timeout)
+    }
+}
+
+/// The checksum's share of the length field.
+fn length_sum(length: u16) -> u16 {
+    let [high, low] = length.to_be_bytes();
+    u16::from(high) + u16::from(low)
+}
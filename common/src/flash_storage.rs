@@ -0,0 +1,318 @@
+//! Reading, erasing and programming the part of flash kept for data.
+//!
+//! Every BSP's build script writes a linker script fragment, `storage.x`,
+//! which `memory.x` includes. It keeps the top of the board's flash for data,
+//! 64 KiB unless the `FLASH_STORAGE_SIZE` environment variable gives another
+//! size in bytes at build time, and the link fails if the program grows into
+//! it. The functions here only reach that region, and take offsets from its
+//! start:
+//!
+//! ```ignore
+//! flash_storage::erase_sector(0)?;
+//! flash_storage::program_page(0, &page)?;
+//! flash_storage::read(0, &mut buf)?;
+//! ```
+//!
+//! [`FlashStorage`] offers the same region through the `embedded-storage`
+//! [`NorFlash`] and [`ReadNorFlash`] traits, for crates such as
+//! `sequential-storage` that are written against them:
+//!
+//! ```ignore
+//! let mut flash = FlashStorage::new();
+//! flash.erase(0, ERASE_SIZE)?;
+//! flash.write(0, b"hello")?;
+//! ```
+//!
+//! Erasing and programming stop execute-in-place, so they run from RAM with
+//! interrupts disabled and XIP put back afterwards. The other core must not
+//! be running from flash meanwhile. Reads go through XIP and need no care.
+//!
+//! [`settings`](crate::settings) and [`datalog`](crate::datalog) keep their
+//! sectors in the region too, at offsets from its start;
+//! [`settings::default_offset`](crate::settings::default_offset) is its last
+//! two sectors, whatever the board's flash and storage sizes.
+
+use embedded_storage::nor_flash::{
+    ErrorType, MultiwriteNorFlash, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+/// Size of an erasable flash sector.
+pub const SECTOR_SIZE: u32 = 4096;
+
+/// Size of a programmable flash page.
+pub const PAGE_SIZE: usize = 256;
+
+/// Where the flash is mapped for execute-in-place reads.
+pub(crate) const XIP_BASE: u32 = 0x1000_0000;
+
+extern "C" {
+    // Set by `storage.x`, as XIP addresses
+    static __flash_storage_start: u8;
+    static __flash_storage_end: u8;
+}
+
+/// Errors from the flash functions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The offset isn't a multiple of the sector or page size.
+    Unaligned,
+    /// The access goes past the end of the region.
+    OutOfRange,
+}
+
+/// Where the region starts, in bytes from the start of flash.
+pub fn start() -> u32 {
+    // Note (safety): only the symbol's address is taken
+    unsafe { &__flash_storage_start as *const u8 as u32 - XIP_BASE }
+}
+
+/// The size of the region, in bytes.
+pub fn capacity() -> u32 {
+    // Note (safety): only the symbols' addresses are taken
+    unsafe { &__flash_storage_end as *const u8 as u32 - &__flash_storage_start as *const u8 as u32 }
+}
+
+/// Copy `buf.len()` bytes from `offset` into the region.
+pub fn read(offset: u32, buf: &mut [u8]) -> Result<(), Error> {
+    buf.copy_from_slice(mapped(offset, buf.len() as u32)?);
+    Ok(())
+}
+
+/// The `len` bytes at `offset` into the region, read in place through XIP.
+pub(crate) fn mapped(offset: u32, len: u32) -> Result<&'static [u8], Error> {
+    check(offset, len)?;
+    let address = XIP_BASE + start() + offset;
+    // Note (safety): the flash is always mapped at XIP_BASE, and the range is
+    // inside the region
+    Ok(unsafe { core::slice::from_raw_parts(address as *const u8, len as usize) })
+}
+
+/// Erase the sector at `offset` into the region, a multiple of
+/// [`SECTOR_SIZE`], to all ones.
+pub fn erase_sector(offset: u32) -> Result<(), Error> {
+    if !offset.is_multiple_of(SECTOR_SIZE) {
+        return Err(Error::Unaligned);
+    }
+    check(offset, SECTOR_SIZE)?;
+    write(start() + offset, true, None);
+    Ok(())
+}
+
+/// Program `page` at `offset` into the region, a multiple of [`PAGE_SIZE`].
+///
+/// Programming only clears bits, so the page should have been erased first;
+/// bytes left as `0xFF` in `page` leave flash as it was.
+pub fn program_page(offset: u32, page: &[u8; PAGE_SIZE]) -> Result<(), Error> {
+    if !offset.is_multiple_of(PAGE_SIZE as u32) {
+        return Err(Error::Unaligned);
+    }
+    check(offset, PAGE_SIZE as u32)?;
+    write(start() + offset, false, Some(page));
+    Ok(())
+}
+
+/// Program `data` at `offset` into the region, which needn't be aligned, a
+/// page at a time. The rest of each page is left as it was.
+pub fn program(offset: u32, data: &[u8]) -> Result<(), Error> {
+    check(offset, data.len() as u32)?;
+    let mut done = 0;
+    while done < data.len() {
+        let at = offset + done as u32;
+        let page_start = at - at % PAGE_SIZE as u32;
+        let within = (at - page_start) as usize;
+        let count = (PAGE_SIZE - within).min(data.len() - done);
+        let mut page = [0xFFu8; PAGE_SIZE];
+        page[within..within + count].copy_from_slice(&data[done..done + count]);
+        program_page(page_start, &page)?;
+        done += count;
+    }
+    Ok(())
+}
+
+fn check(offset: u32, len: u32) -> Result<(), Error> {
+    match offset.checked_add(len) {
+        Some(end) if end <= capacity() => Ok(()),
+        _ => Err(Error::OutOfRange),
+    }
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::Unaligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfRange => NorFlashErrorKind::OutOfBounds,
+        }
+    }
+}
+
+/// The region as `embedded-storage` NOR flash, with offsets from its start.
+///
+/// Any byte can be read or programmed, and programming the same bytes again
+/// only clears more bits, so it is also a [`MultiwriteNorFlash`]. Erasing
+/// works on whole sectors.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FlashStorage {
+    _private: (),
+}
+
+impl FlashStorage {
+    /// The flash storage region.
+    pub const fn new() -> Self {
+        FlashStorage { _private: () }
+    }
+}
+
+impl ErrorType for FlashStorage {
+    type Error = Error;
+}
+
+impl ReadNorFlash for FlashStorage {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
+        read(offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        capacity() as usize
+    }
+}
+
+impl NorFlash for FlashStorage {
+    const WRITE_SIZE: usize = 1;
+    const ERASE_SIZE: usize = SECTOR_SIZE as usize;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        if !from.is_multiple_of(SECTOR_SIZE) || !to.is_multiple_of(SECTOR_SIZE) {
+            return Err(Error::Unaligned);
+        }
+        if from > to {
+            return Err(Error::OutOfRange);
+        }
+        check(from, to - from)?;
+        for sector in (from..to).step_by(SECTOR_SIZE as usize) {
+            erase_sector(sector)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        program(offset, bytes)
+    }
+}
+
+impl MultiwriteNorFlash for FlashStorage {}
+
+/// Program `page` at `offset` into OTA slot B, erasing the sector first if
+/// `offset` starts one.
+///
+/// Slot B lies below the region, so this is checked against the slot instead.
+pub(crate) fn program_slot_b(offset: u32, page: &[u8; PAGE_SIZE]) -> Result<(), Error> {
+    let slot_size = crate::ota::slot_size();
+    if !offset.is_multiple_of(PAGE_SIZE as u32) {
+        return Err(Error::Unaligned);
+    }
+    match offset.checked_add(PAGE_SIZE as u32) {
+        Some(end) if end <= slot_size => {}
+        _ => return Err(Error::OutOfRange),
+    }
+    write(
+        slot_size + offset,
+        offset.is_multiple_of(SECTOR_SIZE),
+        Some(page),
+    );
+    Ok(())
+}
+
+/// Erase the sector holding `offset`, from the start of flash, if `erase` is
+/// set, then program `page` there, if given.
+///
+/// Unchecked: callers check `offset` against the region or slot it is for.
+fn write(offset: u32, erase: bool, page: Option<&[u8; PAGE_SIZE]>) {
+    let rom = RomFunctions::lookup();
+    cortex_m::interrupt::free(|_| {
+        // Note (safety): the functions come from the boot ROM, the page is in
+        // RAM and interrupts are off, so nothing touches flash while XIP is
+        // down.
+        unsafe { write_page(&rom, offset, erase, page) };
+    });
+}
+
+/// Boot ROM flash functions, looked up while XIP still works.
+struct RomFunctions {
+    connect_internal_flash: unsafe extern "C" fn(),
+    flash_exit_xip: unsafe extern "C" fn(),
+    flash_range_erase: unsafe extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: unsafe extern "C" fn(u32, *const u8, usize),
+    flash_flush_cache: unsafe extern "C" fn(),
+    flash_enter_cmd_xip: unsafe extern "C" fn(),
+    /// The second stage bootloader, copied to RAM, restores fast XIP
+    boot2: [u32; 64],
+}
+
+impl RomFunctions {
+    fn lookup() -> Self {
+        // Note (safety): these addresses and tags are fixed by the RP2040
+        // boot ROM, see section 2.8.3 of the datasheet.
+        unsafe {
+            let lookup: extern "C" fn(*const u16, u32) -> usize =
+                core::mem::transmute(usize::from(*(0x18 as *const u16)));
+            let table = usize::from(*(0x14 as *const u16)) as *const u16;
+            let find = |tag: &[u8; 2]| lookup(table, u32::from(u16::from_le_bytes(*tag)));
+
+            let mut boot2 = [0u32; 64];
+            core::ptr::copy_nonoverlapping(XIP_BASE as *const u32, boot2.as_mut_ptr(), 64);
+
+            RomFunctions {
+                connect_internal_flash: core::mem::transmute::<usize, unsafe extern "C" fn()>(
+                    find(b"IF"),
+                ),
+                flash_exit_xip: core::mem::transmute::<usize, unsafe extern "C" fn()>(find(b"EX")),
+                flash_range_erase: core::mem::transmute::<
+                    usize,
+                    unsafe extern "C" fn(u32, usize, u32, u8),
+                >(find(b"RE")),
+                flash_range_program: core::mem::transmute::<
+                    usize,
+                    unsafe extern "C" fn(u32, *const u8, usize),
+                >(find(b"RP")),
+                flash_flush_cache: core::mem::transmute::<usize, unsafe extern "C" fn()>(find(
+                    b"FC",
+                )),
+                flash_enter_cmd_xip: core::mem::transmute::<usize, unsafe extern "C" fn()>(find(
+                    b"CX",
+                )),
+                boot2,
+            }
+        }
+    }
+}
+
+/// Erase the sector holding `offset` if `erase` is set, then program `page`
+/// at `offset`, which must be page aligned, if given.
+///
+/// Runs from RAM: nothing here may call into flash. The caller must disable
+/// interrupts.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn write_page(rom: &RomFunctions, offset: u32, erase: bool, page: Option<&[u8; PAGE_SIZE]>) {
+    const BLOCK_SIZE: u32 = 65536;
+    const SECTOR_ERASE_CMD: u8 = 0x20;
+
+    (rom.connect_internal_flash)();
+    (rom.flash_exit_xip)();
+    if erase {
+        let sector = offset & !(SECTOR_SIZE - 1);
+        (rom.flash_range_erase)(sector, SECTOR_SIZE as usize, BLOCK_SIZE, SECTOR_ERASE_CMD);
+    }
+    if let Some(page) = page {
+        (rom.flash_range_program)(offset, page.as_ptr(), PAGE_SIZE);
+    }
+    (rom.flash_flush_cache)();
+    (rom.flash_enter_cmd_xip)();
+
+    // Jump into the copy of boot2 to put XIP back in its fast mode. The +1
+    // marks it as Thumb code.
+    let boot2: extern "C" fn() = core::mem::transmute(rom.boot2.as_ptr() as usize + 1);
+    boot2();
+}
@@ -0,0 +1,440 @@
+//! Double-buffered RGB565 framebuffers, flushed to an SPI LCD by DMA.
+//!
+//! The driver crates for the ST7789, ST7735 and ILI9341 draw straight to the
+//! controller, one blocking SPI write at a time, so the CPU spends most of a
+//! frame waiting on the bus. [`SpiLcd`] instead keeps two [`Framebuffer`]s in
+//! RAM: the CPU draws the next frame into one, with `embedded-graphics`,
+//! while a DMA channel streams the other to the LCD.
+//!
+//! ```ignore
+//! let buffers = cortex_m::singleton!(: Buffers<240, 135> = Buffers::new()).unwrap();
+//! let mut lcd = SpiLcd::new(spi, dc, cs, channel, PANEL, buffers, &mut delay);
+//! loop {
+//!     let frame = lcd.back();
+//!     frame.clear(Rgb565::BLACK).unwrap();
+//!     Text::new("Hello", Point::new(10, 20), style).draw(frame).unwrap();
+//!     lcd.swap();
+//! }
+//! ```
+//!
+//! All three controllers share the MIPI DCS commands used here, and [`Panel`]
+//! holds what differs from one board to the next. A 240x240 pair takes
+//! 225 kB, most of the RAM, so size the buffers to what the application can
+//! spare.
+//!
+//! Needs the `framebuffer` feature.
+
+use core::convert::Infallible;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, OriginDimensions, Size},
+    pixelcolor::{
+        raw::{RawData, RawU16},
+        Rgb565,
+    },
+    primitives::Rectangle,
+    Pixel,
+};
+use embedded_hal::{blocking::delay::DelayMs, blocking::spi::Write, digital::v2::OutputPin};
+
+use crate::dma::Channel;
+use crate::hal::{
+    pac,
+    spi::{Enabled, Spi, SpiDevice},
+};
+
+/// Software reset.
+const SWRESET: u8 = 0x01;
+/// Leave sleep mode.
+const SLPOUT: u8 = 0x11;
+/// Normal display mode.
+const NORON: u8 = 0x13;
+/// Inversion off.
+const INVOFF: u8 = 0x20;
+/// Inversion on.
+const INVON: u8 = 0x21;
+/// Display on.
+const DISPON: u8 = 0x29;
+/// Column address window.
+const CASET: u8 = 0x2A;
+/// Row address window.
+const RASET: u8 = 0x2B;
+/// Write to the window.
+const RAMWR: u8 = 0x2C;
+/// Memory access order: rotation, mirroring and colour order.
+const MADCTL: u8 = 0x36;
+/// Interface pixel format.
+const COLMOD: u8 = 0x3A;
+
+/// [`Panel::madctl`] bit swapping rows and columns, for landscape.
+pub const MADCTL_MV: u8 = 1 << 5;
+/// [`Panel::madctl`] bit mirroring columns.
+pub const MADCTL_MX: u8 = 1 << 6;
+/// [`Panel::madctl`] bit mirroring rows.
+pub const MADCTL_MY: u8 = 1 << 7;
+/// [`Panel::madctl`] bit for panels wired blue-green-red.
+pub const MADCTL_BGR: u8 = 1 << 3;
+
+/// A display controller [`SpiLcd`] can drive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Controller {
+    St7789,
+    St7735,
+    Ili9341,
+}
+
+impl Controller {
+    /// `COLMOD` for 16 bit pixels.
+    fn colmod(self) -> u8 {
+        match self {
+            // The ST7735 only has the MCU interface format bits
+            Controller::St7735 => 0x05,
+            Controller::St7789 | Controller::Ili9341 => 0x55,
+        }
+    }
+}
+
+/// How a board's panel is wired to its controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Panel {
+    pub controller: Controller,
+    /// Where the panel's top-left pixel sits in the controller's memory.
+    pub offset: (u16, u16),
+    /// Rotation, mirroring and colour order, from the `MADCTL_*` bits.
+    pub madctl: u8,
+    /// Whether the panel needs its colours inverted, as most IPS ones do.
+    pub invert: bool,
+}
+
+/// A `W` by `H` RGB565 image, drawn on with `embedded-graphics`.
+///
+/// Pixels are kept big-endian, the order the LCD takes them in, so a flush
+/// is a plain copy.
+#[repr(C)]
+pub struct Framebuffer<const W: usize, const H: usize> {
+    pixels: [[u16; W]; H],
+}
+
+impl<const W: usize, const H: usize> Framebuffer<W, H> {
+    /// A black frame.
+    pub const fn new() -> Self {
+        Framebuffer {
+            pixels: [[0; W]; H],
+        }
+    }
+
+    /// Set the pixel at `x`, `y`, if it is inside the frame.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Rgb565) {
+        if let Some(pixel) = self.pixels.get_mut(y).and_then(|row| row.get_mut(x)) {
+            *pixel = raw(color);
+        }
+    }
+
+    /// The frame as the bytes sent to the LCD.
+    fn as_bytes(&self) -> &[u8] {
+        // Safety: the pixels are plain u16s, with no padding between rows
+        unsafe { core::slice::from_raw_parts(self.pixels.as_ptr() as *const u8, W * H * 2) }
+    }
+}
+
+impl<const W: usize, const H: usize> Default for Framebuffer<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `color` as the LCD expects it in memory.
+fn raw(color: Rgb565) -> u16 {
+    RawU16::from(color).into_inner().to_be()
+}
+
+impl<const W: usize, const H: usize> OriginDimensions for Framebuffer<W, H> {
+    fn size(&self) -> Size {
+        Size::new(W as u32, H as u32)
+    }
+}
+
+impl<const W: usize, const H: usize> DrawTarget for Framebuffer<W, H> {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x >= 0 && point.y >= 0 {
+                self.set_pixel(point.x as usize, point.y as usize, color);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if let Some(bottom_right) = area.bottom_right() {
+            let value = raw(color);
+            let (left, right) = (area.top_left.x as usize, bottom_right.x as usize);
+            for row in &mut self.pixels[area.top_left.y as usize..=bottom_right.y as usize] {
+                row[left..=right].fill(value);
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let value = raw(color);
+        for row in &mut self.pixels {
+            row.fill(value);
+        }
+        Ok(())
+    }
+}
+
+/// The two frames an [`SpiLcd`] takes turns with, e.g. for
+/// `cortex_m::singleton!`.
+pub struct Buffers<const W: usize, const H: usize> {
+    frames: [Framebuffer<W, H>; 2],
+}
+
+impl<const W: usize, const H: usize> Buffers<W, H> {
+    /// Two black frames.
+    pub const fn new() -> Self {
+        Buffers {
+            frames: [Framebuffer::new(), Framebuffer::new()],
+        }
+    }
+}
+
+impl<const W: usize, const H: usize> Default for Buffers<W, H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An SPI block the DMA can feed.
+pub trait SpiTx: SpiDevice {
+    /// DREQ number of the block's transmit FIFO.
+    const TX_DREQ: u8;
+
+    /// The block's registers.
+    fn regs() -> &'static pac::spi0::RegisterBlock;
+}
+
+impl SpiTx for pac::SPI0 {
+    const TX_DREQ: u8 = 16;
+
+    fn regs() -> &'static pac::spi0::RegisterBlock {
+        unsafe { &*pac::SPI0::ptr() }
+    }
+}
+
+impl SpiTx for pac::SPI1 {
+    const TX_DREQ: u8 = 18;
+
+    fn regs() -> &'static pac::spi0::RegisterBlock {
+        unsafe { &*pac::SPI1::ptr() }
+    }
+}
+
+/// An SPI LCD with a pair of framebuffers, flushed by DMA.
+///
+/// `DC` is the data/command pin and `CS` the chip select, both plain GPIO
+/// outputs rather than the SPI block's own `CSn`, which would rise between
+/// bytes.
+pub struct SpiLcd<D: SpiTx, DC, CS, const W: usize, const H: usize> {
+    spi: Spi<Enabled, D, 8>,
+    dc: DC,
+    cs: CS,
+    dma: Channel,
+    panel: Panel,
+    buffers: &'static mut Buffers<W, H>,
+    /// The frame being drawn into; the other one may be flushing.
+    back: usize,
+    flushing: bool,
+}
+
+impl<D, DC, CS, const W: usize, const H: usize> SpiLcd<D, DC, CS, W, H>
+where
+    D: SpiTx,
+    DC: OutputPin,
+    CS: OutputPin,
+{
+    /// Reset and set up the controller for `panel`, then clear the screen.
+    ///
+    /// `spi` must run in mode 0, at up to 62.5 MHz for the ST7789 and
+    /// ILI9341, or 15 MHz for the ST7735. `dma` is any free channel, e.g. from
+    /// [`Allocator::alloc`](crate::dma::Allocator::alloc).
+    pub fn new(
+        spi: Spi<Enabled, D, 8>,
+        dc: DC,
+        cs: CS,
+        dma: Channel,
+        panel: Panel,
+        buffers: &'static mut Buffers<W, H>,
+        delay: &mut impl DelayMs<u8>,
+    ) -> Self {
+        let mut lcd = Self::from_initialised(spi, dc, cs, dma, panel, buffers);
+        lcd.command(SWRESET, &[]);
+        delay.delay_ms(150);
+        lcd.command(SLPOUT, &[]);
+        delay.delay_ms(120);
+        lcd.command(COLMOD, &[panel.controller.colmod()]);
+        lcd.command(MADCTL, &[panel.madctl]);
+        lcd.command(if panel.invert { INVON } else { INVOFF }, &[]);
+        lcd.command(NORON, &[]);
+        lcd.flush_blocking();
+        lcd.command(DISPON, &[]);
+        lcd
+    }
+
+    /// Take over a controller that a driver crate has already set up for
+    /// 16 bit pixels, and the pins it used.
+    pub fn from_initialised(
+        spi: Spi<Enabled, D, 8>,
+        mut dc: DC,
+        mut cs: CS,
+        dma: Channel,
+        panel: Panel,
+        buffers: &'static mut Buffers<W, H>,
+    ) -> Self {
+        // Setting a GPIO can't fail
+        let _ = dc.set_high();
+        let _ = cs.set_high();
+        SpiLcd {
+            spi,
+            dc,
+            cs,
+            dma,
+            panel,
+            buffers,
+            back: 0,
+            flushing: false,
+        }
+    }
+
+    /// The frame to draw the next picture into.
+    ///
+    /// It holds the picture from two [`swap`](Self::swap)s ago, so either
+    /// redraw all of it or clear it first.
+    pub fn back(&mut self) -> &mut Framebuffer<W, H> {
+        &mut self.buffers.frames[self.back]
+    }
+
+    /// Wait for the previous frame to be on the screen, then start flushing
+    /// the one just drawn and hand out the other for the next picture.
+    pub fn swap(&mut self) {
+        self.wait();
+        self.start_flush(self.back);
+        self.back ^= 1;
+    }
+
+    /// Send the back frame and wait for it to be on the screen.
+    pub fn flush_blocking(&mut self) {
+        self.wait();
+        self.start_flush(self.back);
+        self.wait();
+    }
+
+    /// Whether a frame is still being sent.
+    pub fn is_flushing(&self) -> bool {
+        self.flushing && (self.dma.is_busy() || D::regs().sspsr.read().bsy().bit_is_set())
+    }
+
+    /// Wait for the frame being sent, if any, to be on the screen.
+    pub fn wait(&mut self) {
+        if !self.flushing {
+            return;
+        }
+        while self.is_flushing() {}
+        compiler_fence(Ordering::SeqCst);
+
+        let regs = D::regs();
+        regs.sspdmacr.modify(|_, w| w.txdmae().clear_bit());
+        // The DMA ignored everything received, so empty the receive FIFO
+        // before the next blocking write, which counts on it
+        while regs.sspsr.read().rne().bit_is_set() {
+            let _ = regs.sspdr.read();
+        }
+        regs.sspicr.write(|w| w.roric().set_bit());
+        let _ = self.cs.set_high();
+        self.flushing = false;
+    }
+
+    /// Stop, handing back the SPI bus, the pins, the DMA channel and the
+    /// buffers.
+    #[allow(clippy::type_complexity)]
+    pub fn free(
+        mut self,
+    ) -> (
+        Spi<Enabled, D, 8>,
+        DC,
+        CS,
+        Channel,
+        &'static mut Buffers<W, H>,
+    ) {
+        self.wait();
+        (self.spi, self.dc, self.cs, self.dma, self.buffers)
+    }
+
+    /// Send `command`, followed by `params`.
+    fn command(&mut self, command: u8, params: &[u8]) {
+        let _ = self.cs.set_low();
+        let _ = self.dc.set_low();
+        let _ = self.spi.write(&[command]);
+        let _ = self.dc.set_high();
+        if !params.is_empty() {
+            let _ = self.spi.write(params);
+        }
+        let _ = self.cs.set_high();
+    }
+
+    /// Point the controller at the whole panel and start streaming `frame`.
+    fn start_flush(&mut self, frame: usize) {
+        let (x, y) = self.panel.offset;
+        let (right, bottom) = (x + W as u16 - 1, y + H as u16 - 1);
+        let [x0, x1] = x.to_be_bytes();
+        let [r0, r1] = right.to_be_bytes();
+        self.command(CASET, &[x0, x1, r0, r1]);
+        let [y0, y1] = y.to_be_bytes();
+        let [b0, b1] = bottom.to_be_bytes();
+        self.command(RASET, &[y0, y1, b0, b1]);
+
+        // RAMWR, then leave CS low and DC high for the pixels
+        let _ = self.cs.set_low();
+        let _ = self.dc.set_low();
+        let _ = self.spi.write(&[RAMWR]);
+        let _ = self.dc.set_high();
+
+        let bytes = self.buffers.frames[frame].as_bytes();
+        let spi_regs = D::regs();
+        let regs = self.dma.regs();
+        regs.ch_read_addr
+            .write(|w| unsafe { w.bits(bytes.as_ptr() as u32) });
+        regs.ch_write_addr
+            .write(|w| unsafe { w.bits(spi_regs.sspdr.as_ptr() as u32) });
+        regs.ch_trans_count
+            .write(|w| unsafe { w.bits(bytes.len() as u32) });
+        // The frame must be written out before the DMA reads it
+        compiler_fence(Ordering::SeqCst);
+        regs.ch_ctrl_trig.write(|w| unsafe {
+            w.data_size()
+                .size_byte()
+                .incr_read()
+                .set_bit()
+                .incr_write()
+                .clear_bit()
+                .treq_sel()
+                .bits(D::TX_DREQ)
+                // Chaining to ourselves disables chaining
+                .chain_to()
+                .bits(self.dma.id())
+                .en()
+                .set_bit()
+        });
+        spi_regs.sspdmacr.modify(|_, w| w.txdmae().set_bit());
+        self.flushing = true;
+    }
+}
@@ -0,0 +1,202 @@
+//! Driver for LiPo fuel gauges: the Maxim MAX17048 and the onsemi LC709203F.
+//!
+//! Both report a single cell's voltage and charge over I²C, and both come on
+//! STEMMA QT breakouts and some Feather boards. [`FuelGauge::new`] finds
+//! whichever is on the bus, at its fixed address, and sets it up:
+//!
+//! ```ignore
+//! let mut gauge = FuelGauge::new(i2c, 500)?;
+//! let percent = gauge.percent()?;
+//! ```
+//!
+//! The MAX17048 models the cell from its voltage alone and needs no setting
+//! up. The LC709203F is told the cell's capacity, and is put in its operating
+//! mode, with the temperature taken as 25 °C rather than from a thermistor.
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// The MAX17048's I²C address.
+pub const MAX17048_ADDRESS: u8 = 0x36;
+/// The LC709203F's I²C address.
+pub const LC709203F_ADDRESS: u8 = 0x0B;
+
+// MAX17048 registers, big-endian
+const MAX_VCELL: u8 = 0x02;
+const MAX_SOC: u8 = 0x04;
+const MAX_VERSION: u8 = 0x08;
+
+// LC709203F registers, little-endian and followed by a CRC-8
+const LC_CELL_VOLTAGE: u8 = 0x09;
+const LC_APA: u8 = 0x0B;
+const LC_RSOC: u8 = 0x0D;
+const LC_IC_VERSION: u8 = 0x11;
+const LC_BATTERY_PROFILE: u8 = 0x12;
+const LC_POWER_MODE: u8 = 0x15;
+const LC_STATUS: u8 = 0x16;
+
+/// `LC_POWER_MODE`: measuring continuously.
+const LC_OPERATIONAL: u16 = 0x0001;
+/// `LC_BATTERY_PROFILE`: a 3.7 V LiPo, charged to 4.2 V.
+const LC_PROFILE_LIPO: u16 = 0x0001;
+/// `LC_STATUS`: temperature from I²C, which defaults to 25 °C.
+const LC_TEMPERATURE_I2C: u16 = 0x0000;
+
+/// The LC709203F's adjustment pack application value for a few cell
+/// capacities in mAh, from its datasheet.
+const LC_APA_TABLE: [(u16, u16); 6] = [
+    (100, 0x08),
+    (200, 0x0B),
+    (500, 0x10),
+    (1000, 0x19),
+    (2000, 0x2D),
+    (3000, 0x36),
+];
+
+/// Errors from the fuel gauge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The I²C bus failed.
+    I2c(E),
+    /// Neither chip answered at its address.
+    NotFound,
+    /// An LC709203F answer failed its CRC check.
+    Crc,
+}
+
+/// Which fuel gauge [`FuelGauge::new`] found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip {
+    /// Maxim MAX17048, at [`MAX17048_ADDRESS`].
+    Max17048,
+    /// onsemi LC709203F, at [`LC709203F_ADDRESS`].
+    Lc709203f,
+}
+
+/// A MAX17048 or LC709203F on an I²C bus.
+pub struct FuelGauge<I2C> {
+    i2c: I2C,
+    chip: Chip,
+}
+
+impl<I2C, E> FuelGauge<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Find the fuel gauge on `i2c` and set it up for a cell of
+    /// `capacity_mah`, which only the LC709203F uses.
+    pub fn new(i2c: I2C, capacity_mah: u16) -> Result<Self, Error<E>> {
+        let mut gauge = FuelGauge {
+            i2c,
+            chip: Chip::Max17048,
+        };
+        if let Ok(version) = gauge.max_read(MAX_VERSION) {
+            if version & 0xFFF0 == 0x0010 {
+                return Ok(gauge);
+            }
+        }
+
+        // The LC709203F may miss the first transfer while it wakes up
+        gauge.chip = Chip::Lc709203f;
+        if gauge.lc_read(LC_IC_VERSION).is_err() && gauge.lc_read(LC_IC_VERSION).is_err() {
+            return Err(Error::NotFound);
+        }
+        gauge.lc_write(LC_POWER_MODE, LC_OPERATIONAL)?;
+        gauge.lc_write(LC_APA, lc_apa(capacity_mah))?;
+        gauge.lc_write(LC_BATTERY_PROFILE, LC_PROFILE_LIPO)?;
+        gauge.lc_write(LC_STATUS, LC_TEMPERATURE_I2C)?;
+        Ok(gauge)
+    }
+
+    /// Which chip this is.
+    pub fn chip(&self) -> Chip {
+        self.chip
+    }
+
+    /// The cell's state of charge, from 0 to 100 %.
+    pub fn percent(&mut self) -> Result<u8, Error<E>> {
+        let percent = match self.chip {
+            // Whole percent in the high byte
+            Chip::Max17048 => self.max_read(MAX_SOC)? >> 8,
+            Chip::Lc709203f => self.lc_read(LC_RSOC)?,
+        };
+        Ok(percent.min(100) as u8)
+    }
+
+    /// The cell's voltage, in millivolts.
+    pub fn millivolts(&mut self) -> Result<u16, Error<E>> {
+        match self.chip {
+            // 78.125 µV per step
+            Chip::Max17048 => {
+                let raw = u32::from(self.max_read(MAX_VCELL)?);
+                Ok((raw * 78_125 / 1_000_000) as u16)
+            }
+            Chip::Lc709203f => self.lc_read(LC_CELL_VOLTAGE),
+        }
+    }
+
+    /// Give the bus back.
+    pub fn free(self) -> I2C {
+        self.i2c
+    }
+
+    fn max_read(&mut self, register: u8) -> Result<u16, Error<E>> {
+        let mut buf = [0; 2];
+        self.i2c
+            .write_read(MAX17048_ADDRESS, &[register], &mut buf)
+            .map_err(Error::I2c)?;
+        Ok(u16::from_be_bytes(buf))
+    }
+
+    fn lc_read(&mut self, register: u8) -> Result<u16, Error<E>> {
+        let mut buf = [0; 3];
+        self.i2c
+            .write_read(LC709203F_ADDRESS, &[register], &mut buf)
+            .map_err(Error::I2c)?;
+        let crc = crc8(&[
+            LC709203F_ADDRESS << 1,
+            register,
+            (LC709203F_ADDRESS << 1) | 1,
+            buf[0],
+            buf[1],
+        ]);
+        if crc != buf[2] {
+            return Err(Error::Crc);
+        }
+        Ok(u16::from_le_bytes([buf[0], buf[1]]))
+    }
+
+    fn lc_write(&mut self, register: u8, value: u16) -> Result<(), Error<E>> {
+        let [low, high] = value.to_le_bytes();
+        let crc = crc8(&[LC709203F_ADDRESS << 1, register, low, high]);
+        self.i2c
+            .write(LC709203F_ADDRESS, &[register, low, high, crc])
+            .map_err(Error::I2c)
+    }
+}
+
+/// The APA value for the listed capacity nearest `capacity_mah`.
+fn lc_apa(capacity_mah: u16) -> u16 {
+    let mut best = LC_APA_TABLE[0];
+    for entry in LC_APA_TABLE {
+        if entry.0.abs_diff(capacity_mah) < best.0.abs_diff(capacity_mah) {
+            best = entry;
+        }
+    }
+    best.1
+}
+
+/// CRC-8 with polynomial 0x07, as the LC709203F checks its transfers.
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
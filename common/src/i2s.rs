@@ -0,0 +1,174 @@
+//! I²S audio output from a PIO state machine.
+//!
+//! The RP2040 has no I²S peripheral, but one state machine is enough to be an
+//! I²S master: it generates the bit clock `BCLK` and the word clock `LRCLK`
+//! and shifts out 16 bit stereo samples on `DATA`, MSB first, one bit clock
+//! after each `LRCLK` edge. `LRCLK` has to be on the GPIO after `BCLK`.
+//!
+//! Samples are packed one stereo frame per word with [`frame`], left channel
+//! in the upper half. [`I2s::write_samples`] hands a whole buffer to a DMA
+//! channel and returns the previous one once it has played, so a pair of
+//! buffers keeps the DAC going without gaps:
+//!
+//! ```ignore
+//! let mut i2s = I2s::new(&mut pio, sm0, 9, 10, 44_100, sys_hz, channel);
+//! let (mut spare, first) = (buffer_b, buffer_a);
+//! i2s.write_samples(first);
+//! loop {
+//!     render(spare);
+//!     spare = i2s.write_samples(spare).unwrap();
+//! }
+//! ```
+//!
+//! The pins must already be set to the PIO's function.
+
+use hal::pio::{
+    Buffers, PIOBuilder, PIOExt, PinDir, Running, ShiftDirection, StateMachine, StateMachineIndex,
+    Tx, UninitStateMachine, PIO,
+};
+
+use crate::dma::Channel;
+use crate::hal;
+use crate::resources::{self, Resource};
+
+/// State machine cycles per stereo frame: two per bit, 32 bits per frame.
+const CYCLES_PER_FRAME: u32 = 2 * 32;
+
+/// Pack a left and right sample into the word [`I2s`] shifts out.
+pub fn frame(left: i16, right: i16) -> u32 {
+    u32::from(left as u16) << 16 | u32::from(right as u16)
+}
+
+/// A PIO I²S master with DMA fed output.
+pub struct I2s<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    tx: Tx<(P, SM)>,
+    dma: Channel,
+    playing: Option<&'static mut [u32]>,
+}
+
+impl<P, SM> I2s<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Start clocking out silence at `sample_rate` on `data_pin`, with
+    /// `BCLK` on `bclk_pin` and `LRCLK` on `bclk_pin + 1`.
+    ///
+    /// `dma` is any free channel, e.g. from
+    /// [`Allocator::alloc`](crate::dma::Allocator::alloc).
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        data_pin: u8,
+        bclk_pin: u8,
+        sample_rate: u32,
+        system_clock_hz: u32,
+        dma: Channel,
+    ) -> Self {
+        // Side-set bit 0 is BCLK, bit 1 is LRCLK. Data changes on the falling
+        // edge of BCLK and the DAC samples it on the rising edge.
+        let program = pio_proc::pio_asm!(
+            ".side_set 2",
+            ".wrap_target",
+            "    set x, 14          side 0b01",
+            "left:",
+            "    out pins, 1        side 0b00",
+            "    jmp x-- left       side 0b01",
+            "    out pins, 1        side 0b10",
+            "    set x, 14          side 0b11",
+            "right:",
+            "    out pins, 1        side 0b10",
+            "    jmp x-- right      side 0b11",
+            "    out pins, 1        side 0b00",
+            ".wrap",
+        );
+
+        let divisor = (u64::from(system_clock_hz) << 8)
+            / (u64::from(sample_rate) * u64::from(CYCLES_PER_FRAME));
+        let (int, frac) = ((divisor >> 8) as u16, divisor as u8);
+
+        let installed = pio.install(&program.program).unwrap();
+        let (mut state, _, tx) = PIOBuilder::from_program(installed)
+            .out_pins(data_pin, 1)
+            .side_set_pin_base(bclk_pin)
+            .out_shift_direction(ShiftDirection::Left)
+            .autopull(true)
+            .pull_threshold(32)
+            .buffers(Buffers::OnlyTx)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        let _ = resources::claim_all(
+            &[
+                Resource::pio_sm::<P, SM>(),
+                Resource::Gpio(data_pin),
+                Resource::Gpio(bclk_pin),
+                Resource::Gpio(bclk_pin + 1),
+            ],
+            "i2s",
+        );
+        state.set_pindirs([
+            (data_pin, PinDir::Output),
+            (bclk_pin, PinDir::Output),
+            (bclk_pin + 1, PinDir::Output),
+        ]);
+
+        I2s {
+            _sm: state.start(),
+            tx,
+            dma,
+            playing: None,
+        }
+    }
+
+    /// Play `samples`, packed with [`frame`], once the buffer already playing
+    /// has finished.
+    ///
+    /// Blocks until the previous buffer is done, then starts `samples` and
+    /// returns the previous buffer for refilling.
+    pub fn write_samples(&mut self, samples: &'static mut [u32]) -> Option<&'static mut [u32]> {
+        self.wait();
+
+        let dreq = (P::id() * 8 + SM::id()) as u8;
+        let regs = self.dma.regs();
+        regs.ch_read_addr
+            .write(|w| unsafe { w.bits(samples.as_ptr() as u32) });
+        regs.ch_write_addr
+            .write(|w| unsafe { w.bits(self.tx.fifo_address() as u32) });
+        regs.ch_trans_count
+            .write(|w| unsafe { w.bits(samples.len() as u32) });
+        regs.ch_ctrl_trig.write(|w| unsafe {
+            w.data_size()
+                .size_word()
+                .incr_read()
+                .set_bit()
+                .incr_write()
+                .clear_bit()
+                .treq_sel()
+                .bits(dreq)
+                // Chaining to ourselves disables chaining
+                .chain_to()
+                .bits(self.dma.id())
+                .en()
+                .set_bit()
+        });
+
+        self.playing.replace(samples)
+    }
+
+    /// Whether a buffer is still being played.
+    pub fn is_busy(&self) -> bool {
+        self.dma.is_busy()
+    }
+
+    /// Wait until the buffer being played has been handed to the PIO.
+    pub fn wait(&mut self) {
+        while self.dma.is_busy() {}
+    }
+
+    /// Wait for the last buffer to finish and give it back.
+    pub fn take_buffer(&mut self) -> Option<&'static mut [u32]> {
+        self.wait();
+        self.playing.take()
+    }
+}
@@ -0,0 +1,228 @@
+//! Debounced push buttons, each on its own GPIO or in a scanned matrix.
+//!
+//! Call `update` at a steady rate, every millisecond or so, from the main
+//! loop or a timer. A button has to read the same for
+//! [`DEFAULT_DEBOUNCE_UPDATES`] updates in a row before a press or release
+//! counts, and one kept down for [`DEFAULT_HOLD_UPDATES`] also reports that
+//! it is being held, once per press:
+//!
+//! ```ignore
+//! let mut select = Button::new(pins.gpio14.into_pull_up_input(), Active::Low);
+//! loop {
+//!     match select.update().unwrap() {
+//!         Some(ButtonEvent::Pressed) => next_page(),
+//!         Some(ButtonEvent::Held) => go_home(),
+//!         _ => {}
+//!     }
+//!     delay.delay_ms(1);
+//! }
+//! ```
+//!
+//! [`ButtonMatrix`] does the same for every key of a
+//! [`keyboard::Matrix`](crate::keyboard::Matrix). Keyboards, which only
+//! need presses and releases, can use the lighter
+//! [`keyboard::Debouncer`](crate::keyboard::Debouncer) instead.
+
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use crate::keyboard::Matrix;
+
+/// Updates a button has to read the same before a change counts: 5 ms at
+/// 1 kHz, which covers most switches.
+pub const DEFAULT_DEBOUNCE_UPDATES: u8 = 5;
+
+/// Updates a button has to stay down before it is held: half a second at
+/// 1 kHz.
+pub const DEFAULT_HOLD_UPDATES: u16 = 500;
+
+/// The level a pressed button puts on its pin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Active {
+    /// Pressed pulls the pin low, against a pull-up.
+    Low,
+    /// Pressed pulls the pin high, against a pull-down.
+    High,
+}
+
+/// A change to a button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonEvent {
+    Pressed,
+    Released,
+    /// Still down after the hold time, reported once per press, after
+    /// [`ButtonEvent::Pressed`].
+    Held,
+}
+
+/// How long changes and holds take, in updates.
+#[derive(Clone, Copy, Debug)]
+struct Timing {
+    debounce: u8,
+    /// 0 for no [`ButtonEvent::Held`].
+    hold: u16,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing {
+            debounce: DEFAULT_DEBOUNCE_UPDATES,
+            hold: DEFAULT_HOLD_UPDATES,
+        }
+    }
+}
+
+/// One button's debounced state.
+#[derive(Clone, Copy, Debug, Default)]
+struct Debounce {
+    pressed: bool,
+    /// Updates in a row that have read differently from `pressed`.
+    changing: u8,
+    /// Updates since the press, up to the hold time.
+    down: u16,
+}
+
+impl Debounce {
+    fn update(&mut self, raw: bool, timing: Timing) -> Option<ButtonEvent> {
+        if raw == self.pressed {
+            self.changing = 0;
+            if self.pressed && timing.hold != 0 && self.down < timing.hold {
+                self.down += 1;
+                if self.down == timing.hold {
+                    return Some(ButtonEvent::Held);
+                }
+            }
+            return None;
+        }
+        self.changing += 1;
+        if self.changing < timing.debounce {
+            return None;
+        }
+        self.changing = 0;
+        self.pressed = raw;
+        self.down = 0;
+        Some(if raw {
+            ButtonEvent::Pressed
+        } else {
+            ButtonEvent::Released
+        })
+    }
+}
+
+/// A button on its own pin.
+pub struct Button<P> {
+    pin: P,
+    active: Active,
+    timing: Timing,
+    state: Debounce,
+}
+
+impl<P: InputPin> Button<P> {
+    /// A button on `pin`, already an input with the pull-up or pull-down it
+    /// needs, that reads as pressed when the pin is at `active`. It starts
+    /// released, so a button held down at start-up reports a press.
+    pub fn new(pin: P, active: Active) -> Self {
+        Button {
+            pin,
+            active,
+            timing: Timing::default(),
+            state: Debounce::default(),
+        }
+    }
+
+    /// Set how many updates a change has to last to count, at least 1.
+    pub fn set_debounce(&mut self, updates: u8) {
+        self.timing.debounce = updates.max(1);
+    }
+
+    /// Set how many updates the button has to stay down to be held, or 0
+    /// for it never to be.
+    pub fn set_hold(&mut self, updates: u16) {
+        self.timing.hold = updates;
+    }
+
+    /// Read the pin, returning the change this completes.
+    pub fn update(&mut self) -> Result<Option<ButtonEvent>, P::Error> {
+        let raw = self.is_raw_pressed()?;
+        Ok(self.state.update(raw, self.timing))
+    }
+
+    /// Whether the button is down, debounced.
+    pub fn is_pressed(&self) -> bool {
+        self.state.pressed
+    }
+
+    /// Whether the button is down and has been held.
+    pub fn is_held(&self) -> bool {
+        self.state.pressed && self.timing.hold != 0 && self.state.down == self.timing.hold
+    }
+
+    /// Whether the pin reads as pressed right now, bounces and all.
+    pub fn is_raw_pressed(&self) -> Result<bool, P::Error> {
+        match self.active {
+            Active::Low => self.pin.is_low(),
+            Active::High => self.pin.is_high(),
+        }
+    }
+
+    /// Give back the pin.
+    pub fn free(self) -> P {
+        self.pin
+    }
+}
+
+/// Every key of a scanned switch matrix, each debounced like a [`Button`].
+pub struct ButtonMatrix<C, R, const COLS: usize, const ROWS: usize> {
+    matrix: Matrix<C, R, COLS, ROWS>,
+    timing: Timing,
+    keys: [[Debounce; COLS]; ROWS],
+}
+
+impl<C, R, E, const COLS: usize, const ROWS: usize> ButtonMatrix<C, R, COLS, ROWS>
+where
+    C: InputPin<Error = E>,
+    R: OutputPin<Error = E>,
+{
+    /// Scan `matrix`, all of whose keys start released.
+    pub fn new(matrix: Matrix<C, R, COLS, ROWS>) -> Self {
+        ButtonMatrix {
+            matrix,
+            timing: Timing::default(),
+            keys: [[Debounce::default(); COLS]; ROWS],
+        }
+    }
+
+    /// Set how many updates a change has to last to count, at least 1.
+    pub fn set_debounce(&mut self, updates: u8) {
+        self.timing.debounce = updates.max(1);
+    }
+
+    /// Set how many updates a key has to stay down to be held, or 0 for
+    /// none ever to be.
+    pub fn set_hold(&mut self, updates: u16) {
+        self.timing.hold = updates;
+    }
+
+    /// Scan the matrix, calling `on_event` with the row, column and change
+    /// of every key that has one.
+    pub fn update(&mut self, mut on_event: impl FnMut(u8, u8, ButtonEvent)) -> Result<(), E> {
+        let scan = self.matrix.scan()?;
+        for (row, (raw, keys)) in scan.iter().zip(self.keys.iter_mut()).enumerate() {
+            for (col, (&raw, key)) in raw.iter().zip(keys.iter_mut()).enumerate() {
+                if let Some(event) = key.update(raw, self.timing) {
+                    on_event(row as u8, col as u8, event);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the key at `row` and `col` is down, debounced.
+    pub fn is_pressed(&self, row: usize, col: usize) -> bool {
+        self.keys[row][col].pressed
+    }
+
+    /// Give back the matrix.
+    pub fn free(self) -> Matrix<C, R, COLS, ROWS> {
+        self.matrix
+    }
+}
@@ -0,0 +1,312 @@
+//! Infrared remote controls using the NEC protocol.
+//!
+//! An NEC frame is a 9 ms burst of 38 kHz carrier, a 4.5 ms space, then 32
+//! bits sent least significant first: address, inverted address, command and
+//! inverted command. Every bit starts with a 562.5 µs burst; the space after
+//! it lasts 562.5 µs for a 0 and 1687.5 µs for a 1. A final burst ends the
+//! frame. While a button is held, the remote sends a repeat code every
+//! 108 ms instead: a 9 ms burst, a 2.25 ms space and the final burst.
+//!
+//! A receiver module such as the TSOP38238 strips the carrier, pulling its
+//! output low during each burst. A [`Decoder`] takes the edges of that
+//! output, stamped with the timer's 64-bit microsecond count, from the GPIO
+//! interrupts on both edges:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn IO_IRQ_BANK0() {
+//!     critical_section::with(|cs| {
+//!         let mut decoder = DECODER.borrow_ref_mut(cs);
+//!         let now = timer.get_counter().ticks();
+//!         if pin.interrupt_status(EdgeLow) {
+//!             pin.clear_interrupt(EdgeLow);
+//!             if let Some(event) = decoder.on_edge(true, now) {
+//!                 // A code, or a repeat of one
+//!             }
+//!         }
+//!         // ... and the same for EdgeHigh, with false
+//!     });
+//! }
+//! ```
+//!
+//! A [`Transmitter`] sends frames through an IR LED on a PWM channel, whose
+//! slice runs at the carrier frequency, with its top from [`carrier_top`].
+//! It times the bursts with [`delay`](crate::delay), which needs
+//! [`delay::calibrate`](crate::delay::calibrate), and a frame keeps the CPU
+//! busy for up to 67.5 ms.
+
+use embedded_hal::PwmPin;
+
+use crate::delay;
+
+/// The carrier frequency, in hertz.
+pub const CARRIER_HZ: u32 = 38_000;
+
+/// Length of the burst that starts a frame or a repeat code, in
+/// microseconds.
+const LEADER_MARK_US: u32 = 9000;
+
+/// The space after the leader burst in a frame, in microseconds.
+const LEADER_SPACE_US: u32 = 4500;
+
+/// The space after the leader burst in a repeat code, in microseconds.
+const REPEAT_SPACE_US: u32 = 2250;
+
+/// Length of each bit's burst, in microseconds.
+const BIT_MARK_US: u32 = 562;
+
+/// The space after a 0 bit's burst, in microseconds.
+const ZERO_SPACE_US: u32 = 562;
+
+/// The space after a 1 bit's burst, in microseconds.
+const ONE_SPACE_US: u32 = 1687;
+
+/// Time from the start of one frame or repeat code to the next, in
+/// microseconds.
+pub const REPEAT_PERIOD_US: u32 = 108_000;
+
+/// How long after the last frame or repeat code a repeat code still counts,
+/// in microseconds: a little over one repeat period.
+const REPEAT_WINDOW_US: u64 = 120_000;
+
+/// A button press: which device, and which button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Code {
+    /// The device address. Original NEC remotes send an 8 bit address and
+    /// its inverse; extended NEC remotes send 16 bits in their place. An
+    /// extended address whose high byte happens to be the inverse of its low
+    /// byte is indistinguishable from the 8 bit one.
+    pub address: u16,
+    /// The button.
+    pub command: u8,
+}
+
+impl Code {
+    /// The 32 bits of the frame, in the order they are sent, least
+    /// significant first.
+    fn to_bits(self) -> u32 {
+        let address = if self.address <= 0xFF {
+            self.address | ((!self.address & 0xFF) << 8)
+        } else {
+            self.address
+        };
+        u32::from(address) | (u32::from(self.command) << 16) | (u32::from(!self.command) << 24)
+    }
+
+    /// The code in a frame's 32 bits, unless its command check fails.
+    fn from_bits(bits: u32) -> Option<Self> {
+        let [low, high, command, inverse] = bits.to_le_bytes();
+        if command != !inverse {
+            return None;
+        }
+        let address = if high == !low {
+            u16::from(low)
+        } else {
+            u16::from_le_bytes([low, high])
+        };
+        Some(Code { address, command })
+    }
+}
+
+/// What the [`Decoder`] made of the signal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A frame, sent when a button is pressed.
+    Pressed(Code),
+    /// A repeat code, sent while the button is held, with the code it
+    /// repeats.
+    Repeated(Code),
+}
+
+/// Where the [`Decoder`] is in a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    /// Waiting for a leader burst.
+    Idle,
+    /// In the space after a leader burst.
+    Leader,
+    /// In the space after the burst of bit `count`, with the bits so far.
+    Bit { count: u8, bits: u32 },
+    /// In a bit's burst, with the bits so far.
+    Mark { count: u8, bits: u32 },
+}
+
+/// Decodes NEC frames from the edges of a receiver module's output.
+#[derive(Clone, Copy, Debug)]
+pub struct Decoder {
+    state: State,
+    /// When the last edge came.
+    edge_us: u64,
+    /// The last code, and when its frame or latest repeat code started.
+    last: Option<(Code, u64)>,
+    /// When the current leader burst started.
+    leader_us: u64,
+}
+
+impl Decoder {
+    /// A decoder waiting for a frame.
+    pub const fn new() -> Self {
+        Decoder {
+            state: State::Idle,
+            edge_us: 0,
+            last: None,
+            leader_us: 0,
+        }
+    }
+
+    /// Record an edge of the receiver's output at `now_us`: the start of a
+    /// burst, with the output going low, if `mark`, else the end of one.
+    /// Returns the frame or repeat code this edge completes.
+    pub fn on_edge(&mut self, mark: bool, now_us: u64) -> Option<Event> {
+        let width = now_us.saturating_sub(self.edge_us).min(u64::from(u32::MAX)) as u32;
+        self.edge_us = now_us;
+
+        if !mark {
+            // A burst has ended. A leader burst starts over, wherever we were
+            if near(width, LEADER_MARK_US) {
+                self.leader_us = now_us - u64::from(width);
+                self.state = State::Leader;
+                return None;
+            }
+            self.state = match self.state {
+                State::Mark { count, bits } if near(width, BIT_MARK_US) => {
+                    State::Bit { count, bits }
+                }
+                _ => State::Idle,
+            };
+            return None;
+        }
+
+        // A burst has started, ending a space
+        let (state, event) = match self.state {
+            State::Leader if near(width, LEADER_SPACE_US) => {
+                (State::Mark { count: 0, bits: 0 }, None)
+            }
+            State::Leader if near(width, REPEAT_SPACE_US) => {
+                let event = match self.last {
+                    Some((code, started))
+                        if self.leader_us.saturating_sub(started) < REPEAT_WINDOW_US =>
+                    {
+                        self.last = Some((code, self.leader_us));
+                        Some(Event::Repeated(code))
+                    }
+                    _ => None,
+                };
+                (State::Idle, event)
+            }
+            State::Bit { count, bits } => {
+                let bits = if near(width, ZERO_SPACE_US) {
+                    Some(bits)
+                } else if near(width, ONE_SPACE_US) {
+                    Some(bits | (1 << count))
+                } else {
+                    None
+                };
+                match bits {
+                    // The last bit: this burst ends the frame
+                    Some(bits) if count == 31 => {
+                        let code = Code::from_bits(bits);
+                        self.last = code.map(|code| (code, self.leader_us));
+                        (State::Idle, code.map(Event::Pressed))
+                    }
+                    Some(bits) => (
+                        State::Mark {
+                            count: count + 1,
+                            bits,
+                        },
+                        None,
+                    ),
+                    None => (State::Idle, None),
+                }
+            }
+            _ => (State::Idle, None),
+        };
+        self.state = state;
+        event
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Decoder::new()
+    }
+}
+
+/// Whether `width` is within 25 % of `nominal`, allowing for receivers
+/// stretching bursts and for interrupt latency.
+fn near(width: u32, nominal: u32) -> bool {
+    width >= nominal - nominal / 4 && width <= nominal + nominal / 4
+}
+
+/// The PWM slice top that makes the carrier, with the divider at 1, for a
+/// system clock of `sys_clk_hz`.
+pub fn carrier_top(sys_clk_hz: u32) -> u16 {
+    let period = (sys_clk_hz + CARRIER_HZ / 2) / CARRIER_HZ;
+    period.saturating_sub(1).min(u32::from(u16::MAX)) as u16
+}
+
+/// Sends NEC frames through an IR LED driven from a PWM channel.
+pub struct Transmitter<P> {
+    pwm: P,
+    /// The duty cycle of a burst: a third, as NEC remotes use.
+    duty: u16,
+    /// Whether a frame has been sent, for repeat codes to repeat.
+    sent: bool,
+}
+
+impl<P: PwmPin<Duty = u16>> Transmitter<P> {
+    /// Send through `pwm`, whose slice has its top at `top` from
+    /// [`carrier_top`], and is running. The LED stays off between frames.
+    pub fn new(mut pwm: P, top: u16) -> Self {
+        pwm.set_duty(0);
+        pwm.enable();
+        Transmitter {
+            pwm,
+            duty: ((u32::from(top) + 1) / 3) as u16,
+            sent: false,
+        }
+    }
+
+    /// Send `code`, taking 67.5 ms with all its bits.
+    pub fn send(&mut self, code: Code) {
+        self.burst(LEADER_MARK_US, LEADER_SPACE_US);
+        let bits = code.to_bits();
+        for i in 0..32 {
+            let space = if bits & (1 << i) != 0 {
+                ONE_SPACE_US
+            } else {
+                ZERO_SPACE_US
+            };
+            self.burst(BIT_MARK_US, space);
+        }
+        self.mark(BIT_MARK_US);
+        self.sent = true;
+    }
+
+    /// Send a repeat code, as for a held button, taking 11.8 ms. Send one
+    /// [`REPEAT_PERIOD_US`] after the start of the frame or the last repeat
+    /// code. Does nothing if no frame has been sent.
+    pub fn send_repeat(&mut self) {
+        if !self.sent {
+            return;
+        }
+        self.burst(LEADER_MARK_US, REPEAT_SPACE_US);
+        self.mark(BIT_MARK_US);
+    }
+
+    /// Give back the PWM channel.
+    pub fn free(self) -> P {
+        self.pwm
+    }
+
+    fn burst(&mut self, mark_us: u32, space_us: u32) {
+        self.mark(mark_us);
+        delay::delay_us(space_us);
+    }
+
+    fn mark(&mut self, mark_us: u32) {
+        self.pwm.set_duty(self.duty);
+        delay::delay_us(mark_us);
+        self.pwm.set_duty(0);
+    }
+}
@@ -0,0 +1,125 @@
+//! Interrupt priorities from a declarative table.
+//!
+//! Every interrupt on the RP2040 starts out at the same priority, so a slow
+//! USB poll can hold off a PWM wrap interrupt long enough to be heard as a
+//! click in an audio stream. A [`Profile`] is a table of interrupts and the
+//! [`Level`] each should run at; [`apply`] programs the whole table into the
+//! NVIC in one go.
+//!
+//! The Cortex-M0+ only implements the top two bits of each priority byte, so
+//! there are four distinct levels. Lower numbers pre-empt higher ones.
+
+use crate::hal::pac::{Interrupt, NVIC};
+
+/// One of the four priority levels the Cortex-M0+ implements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Pre-empts everything else. Keep for short, time-critical handlers such
+    /// as control-loop or audio PWM updates.
+    Highest,
+    /// DMA completion and other handlers that re-arm hardware.
+    High,
+    /// Peripheral housekeeping that can tolerate some jitter.
+    Medium,
+    /// USB and other protocol stacks whose hosts retry on their own.
+    Low,
+}
+
+impl Level {
+    /// The raw value written to the NVIC priority register.
+    pub const fn priority(self) -> u8 {
+        match self {
+            Level::Highest => 0x00,
+            Level::High => 0x40,
+            Level::Medium => 0x80,
+            Level::Low => 0xC0,
+        }
+    }
+}
+
+/// An interrupt and the level it should run at.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    /// The interrupt to configure.
+    pub interrupt: Interrupt,
+    /// The level to run it at.
+    pub level: Level,
+}
+
+impl Entry {
+    /// Create a new entry.
+    pub const fn new(interrupt: Interrupt, level: Level) -> Self {
+        Entry { interrupt, level }
+    }
+}
+
+/// A table of interrupt priorities.
+pub type Profile = &'static [Entry];
+
+/// PWM audio output: the sample-rate PWM wrap interrupt pre-empts everything,
+/// DMA refills sit in the middle and USB runs last.
+pub const AUDIO: Profile = &[
+    Entry::new(Interrupt::PWM_IRQ_WRAP, Level::Highest),
+    Entry::new(Interrupt::DMA_IRQ_0, Level::High),
+    Entry::new(Interrupt::DMA_IRQ_1, Level::High),
+    Entry::new(Interrupt::PIO0_IRQ_0, Level::Medium),
+    Entry::new(Interrupt::PIO1_IRQ_0, Level::Medium),
+    Entry::new(Interrupt::USBCTRL_IRQ, Level::Low),
+];
+
+/// Motor and other control loops clocked from a PWM wrap or a timer alarm,
+/// with serial links for telemetry.
+pub const CONTROL_LOOP: Profile = &[
+    Entry::new(Interrupt::PWM_IRQ_WRAP, Level::Highest),
+    Entry::new(Interrupt::TIMER_IRQ_0, Level::Highest),
+    Entry::new(Interrupt::IO_IRQ_BANK0, Level::High),
+    Entry::new(Interrupt::DMA_IRQ_0, Level::Medium),
+    Entry::new(Interrupt::DMA_IRQ_1, Level::Medium),
+    Entry::new(Interrupt::UART0_IRQ, Level::Medium),
+    Entry::new(Interrupt::UART1_IRQ, Level::Medium),
+    Entry::new(Interrupt::USBCTRL_IRQ, Level::Low),
+];
+
+/// USB-first devices that still move data with DMA.
+pub const USB_DEVICE: Profile = &[
+    Entry::new(Interrupt::DMA_IRQ_0, Level::High),
+    Entry::new(Interrupt::DMA_IRQ_1, Level::High),
+    Entry::new(Interrupt::USBCTRL_IRQ, Level::Medium),
+    Entry::new(Interrupt::UART0_IRQ, Level::Low),
+    Entry::new(Interrupt::UART1_IRQ, Level::Low),
+];
+
+/// Program every entry in `profile` into the NVIC.
+///
+/// Interrupts not listed keep their current priority. Entries later in the
+/// table win if an interrupt is listed twice, so a board-specific table can
+/// start from one of the presets and override a few entries.
+///
+/// Call this before unmasking the interrupts: changing the priority of an
+/// interrupt that is already running can break assumptions its handler makes
+/// about what can pre-empt it.
+pub fn apply(nvic: &mut NVIC, profile: &[Entry]) {
+    for entry in profile {
+        // Safety: changing priorities can break priority-based critical
+        // sections. Nothing in the rp-hal stack relies on those; everything
+        // uses `critical_section`, which masks all interrupts.
+        unsafe { nvic.set_priority(entry.interrupt, entry.level.priority()) };
+    }
+}
+
+/// Apply several tables in turn, e.g. a preset followed by local overrides.
+pub fn apply_all(nvic: &mut NVIC, profiles: &[&[Entry]]) {
+    for profile in profiles {
+        apply(nvic, profile);
+    }
+}
+
+/// The level `interrupt` currently runs at.
+pub fn level(interrupt: Interrupt) -> Level {
+    match NVIC::get_priority(interrupt) & 0xC0 {
+        0x00 => Level::Highest,
+        0x40 => Level::High,
+        0x80 => Level::Medium,
+        _ => Level::Low,
+    }
+}
@@ -0,0 +1,232 @@
+//! Calibration for analogue joysticks read through the ADC.
+//!
+//! Cheap thumbsticks rarely rest at mid-scale or reach both rails, so raw ADC
+//! readings make a poor gamepad. An [`Axis`] records where the stick actually
+//! sits at rest and at its extremes, and [`Axis::scale`] maps readings onto
+//! `-127..=127` with the centre at 0.
+//!
+//! The [`Wizard`] collects those points from live readings:
+//!
+//! 1. [`Step::Extremes`]: the user moves the stick all the way round,
+//! 2. [`Step::Center`]: the user lets go and the rest position is averaged,
+//! 3. [`Step::Done`]: [`Wizard::calibration`] returns the result.
+//!
+//! [`Wizard::advance`] moves from one step to the next.
+//!
+//! A [`Calibration`] packs into a few bytes, so it can be kept in the
+//! [`settings`](crate::settings) store.
+
+/// Full scale of the RP2040's 12-bit ADC.
+pub const ADC_MAX: u16 = 4095;
+
+/// Readings this close to the centre are reported as 0.
+pub const DEFAULT_DEAD_ZONE: u16 = 40;
+
+/// At most this many readings are averaged for the centre position: plenty,
+/// and far from overflowing the sums.
+const MAX_CENTER_SAMPLES: u32 = 65536;
+
+/// Bytes taken by one axis in [`Calibration::write_to`].
+pub const AXIS_BYTES: usize = 6;
+
+/// The measured range of one axis, in raw ADC counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Axis {
+    pub min: u16,
+    pub center: u16,
+    pub max: u16,
+}
+
+impl Axis {
+    /// The whole ADC range, centred at mid-scale.
+    pub const UNCALIBRATED: Axis = Axis {
+        min: 0,
+        center: ADC_MAX / 2,
+        max: ADC_MAX,
+    };
+
+    /// Map a raw reading onto `-127..=127`, with readings within
+    /// `dead_zone` of the centre giving 0.
+    pub fn scale(&self, raw: u16, dead_zone: u16) -> i8 {
+        let raw = raw.clamp(self.min, self.max);
+        if raw.saturating_add(dead_zone) >= self.center
+            && raw <= self.center.saturating_add(dead_zone)
+        {
+            return 0;
+        }
+        if raw > self.center {
+            let span = u32::from(self.max - self.center).max(1);
+            (u32::from(raw - self.center) * 127 / span) as i8
+        } else {
+            let span = u32::from(self.center - self.min).max(1);
+            -((u32::from(self.center - raw) * 127 / span) as i8)
+        }
+    }
+
+    /// `true` if the points are in order and not all the same.
+    pub fn is_valid(&self) -> bool {
+        self.min < self.center && self.center < self.max && self.max <= ADC_MAX
+    }
+}
+
+impl Default for Axis {
+    fn default() -> Self {
+        Axis::UNCALIBRATED
+    }
+}
+
+/// Calibration for a stick with `AXES` axes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration<const AXES: usize> {
+    pub axes: [Axis; AXES],
+}
+
+impl<const AXES: usize> Calibration<AXES> {
+    /// Every axis uncalibrated.
+    pub const fn new() -> Self {
+        Calibration {
+            axes: [Axis::UNCALIBRATED; AXES],
+        }
+    }
+
+    /// Scale one reading per axis.
+    pub fn scale(&self, raw: &[u16; AXES], dead_zone: u16) -> [i8; AXES] {
+        let mut out = [0; AXES];
+        for ((out, axis), &raw) in out.iter_mut().zip(self.axes.iter()).zip(raw.iter()) {
+            *out = axis.scale(raw, dead_zone);
+        }
+        out
+    }
+
+    /// Pack into `buf`, which needs `AXES * AXIS_BYTES` bytes. Returns the
+    /// number of bytes used.
+    pub fn write_to(&self, buf: &mut [u8]) -> usize {
+        for (chunk, axis) in buf.chunks_exact_mut(AXIS_BYTES).zip(self.axes.iter()) {
+            chunk[0..2].copy_from_slice(&axis.min.to_le_bytes());
+            chunk[2..4].copy_from_slice(&axis.center.to_le_bytes());
+            chunk[4..6].copy_from_slice(&axis.max.to_le_bytes());
+        }
+        AXES * AXIS_BYTES
+    }
+
+    /// Unpack what [`Calibration::write_to`] wrote. Returns `None` if `buf`
+    /// is the wrong length or any axis is invalid.
+    pub fn read_from(buf: &[u8]) -> Option<Self> {
+        if buf.len() != AXES * AXIS_BYTES {
+            return None;
+        }
+        let mut calibration = Self::new();
+        for (chunk, axis) in buf
+            .chunks_exact(AXIS_BYTES)
+            .zip(calibration.axes.iter_mut())
+        {
+            *axis = Axis {
+                min: u16::from_le_bytes([chunk[0], chunk[1]]),
+                center: u16::from_le_bytes([chunk[2], chunk[3]]),
+                max: u16::from_le_bytes([chunk[4], chunk[5]]),
+            };
+            if !axis.is_valid() {
+                return None;
+            }
+        }
+        Some(calibration)
+    }
+}
+
+impl<const AXES: usize> Default for Calibration<AXES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where the [`Wizard`] has got to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Recording the extremes while the stick is moved around.
+    Extremes,
+    /// Averaging the rest position while the stick is left alone.
+    Center,
+    /// Finished, see [`Wizard::calibration`].
+    Done,
+}
+
+/// Builds a [`Calibration`] from live readings.
+pub struct Wizard<const AXES: usize> {
+    step: Step,
+    min: [u16; AXES],
+    max: [u16; AXES],
+    sum: [u32; AXES],
+    samples: u32,
+}
+
+impl<const AXES: usize> Wizard<AXES> {
+    /// Start at [`Step::Extremes`].
+    pub const fn new() -> Self {
+        Wizard {
+            step: Step::Extremes,
+            min: [ADC_MAX; AXES],
+            max: [0; AXES],
+            sum: [0; AXES],
+            samples: 0,
+        }
+    }
+
+    /// The current step.
+    pub fn step(&self) -> Step {
+        self.step
+    }
+
+    /// Feed in one reading per axis.
+    pub fn sample(&mut self, raw: &[u16; AXES]) {
+        match self.step {
+            Step::Extremes => {
+                for (i, &raw) in raw.iter().enumerate() {
+                    self.min[i] = self.min[i].min(raw);
+                    self.max[i] = self.max[i].max(raw);
+                }
+            }
+            Step::Center if self.samples < MAX_CENTER_SAMPLES => {
+                for (sum, &raw) in self.sum.iter_mut().zip(raw.iter()) {
+                    *sum += u32::from(raw);
+                }
+                self.samples += 1;
+            }
+            Step::Center | Step::Done => {}
+        }
+    }
+
+    /// Move on to the next step.
+    pub fn advance(&mut self) -> Step {
+        self.step = match self.step {
+            Step::Extremes => Step::Center,
+            Step::Center | Step::Done => Step::Done,
+        };
+        self.step
+    }
+
+    /// The result, once [`Step::Done`] is reached and every axis came out
+    /// valid.
+    pub fn calibration(&self) -> Option<Calibration<AXES>> {
+        if self.step != Step::Done || self.samples == 0 {
+            return None;
+        }
+        let mut calibration = Calibration::new();
+        for (i, axis) in calibration.axes.iter_mut().enumerate() {
+            *axis = Axis {
+                min: self.min[i],
+                center: (self.sum[i] / self.samples) as u16,
+                max: self.max[i],
+            };
+            if !axis.is_valid() {
+                return None;
+            }
+        }
+        Some(calibration)
+    }
+}
+
+impl<const AXES: usize> Default for Wizard<AXES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,64 @@
+use super::Event;
+
+/// Scans a key has to stay in its new state before the change is reported.
+///
+/// At a 1 kHz scan rate this is 5 ms, which covers most switches.
+pub const DEFAULT_DEBOUNCE_SCANS: u8 = 5;
+
+/// Per-key debouncing.
+///
+/// Each key has its own counter, so a bouncing key never delays events from
+/// the others.
+pub struct Debouncer<const COLS: usize, const ROWS: usize> {
+    stable: [[bool; COLS]; ROWS],
+    counters: [[u8; COLS]; ROWS],
+    scans: u8,
+}
+
+impl<const COLS: usize, const ROWS: usize> Debouncer<COLS, ROWS> {
+    /// Create a debouncer that reports a change after `scans` identical scans.
+    pub const fn new(scans: u8) -> Self {
+        Debouncer {
+            stable: [[false; COLS]; ROWS],
+            counters: [[0; COLS]; ROWS],
+            scans,
+        }
+    }
+
+    /// Feed in one scan, calling `on_event` for every key whose debounced
+    /// state changed.
+    pub fn update(&mut self, scan: &[[bool; COLS]; ROWS], mut on_event: impl FnMut(Event)) {
+        for (row, keys) in scan.iter().enumerate() {
+            for (col, &pressed) in keys.iter().enumerate() {
+                let stable = &mut self.stable[row][col];
+                let counter = &mut self.counters[row][col];
+                if pressed == *stable {
+                    *counter = 0;
+                    continue;
+                }
+                *counter += 1;
+                if *counter >= self.scans {
+                    *counter = 0;
+                    *stable = pressed;
+                    let (row, col) = (row as u8, col as u8);
+                    on_event(if pressed {
+                        Event::Press(row, col)
+                    } else {
+                        Event::Release(row, col)
+                    });
+                }
+            }
+        }
+    }
+
+    /// The current debounced state of every key.
+    pub fn state(&self) -> &[[bool; COLS]; ROWS] {
+        &self.stable
+    }
+}
+
+impl<const COLS: usize, const ROWS: usize> Default for Debouncer<COLS, ROWS> {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEBOUNCE_SCANS)
+    }
+}
@@ -0,0 +1,84 @@
+use super::keycode;
+
+/// Number of key codes covered by the bitmap, `0x00..=0xDF`.
+const BITMAP_KEYS: usize = 224;
+
+/// HID report descriptor for [`NkroReport`].
+///
+/// Pass this to `usbd_hid::hid_class::HIDClass::new` and send reports with
+/// `push_raw_input`. NKRO keyboards do not speak the BIOS boot protocol, so a
+/// few PC firmware setup screens will not see key presses.
+pub const NKRO_REPORT_DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x06, // Usage (Keyboard)
+    0xA1, 0x01, // Collection (Application)
+    0x05, 0x07, //   Usage Page (Keyboard/Keypad)
+    0x19, 0xE0, //   Usage Minimum (Left Control)
+    0x29, 0xE7, //   Usage Maximum (Right GUI)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x01, //   Logical Maximum (1)
+    0x75, 0x01, //   Report Size (1)
+    0x95, 0x08, //   Report Count (8)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x19, 0x00, //   Usage Minimum (0)
+    0x29, 0xDF, //   Usage Maximum (0xDF)
+    0x95, 0xE0, //   Report Count (224)
+    0x81, 0x02, //   Input (Data, Variable, Absolute)
+    0x05, 0x08, //   Usage Page (LEDs)
+    0x19, 0x01, //   Usage Minimum (Num Lock)
+    0x29, 0x05, //   Usage Maximum (Kana)
+    0x95, 0x05, //   Report Count (5)
+    0x91, 0x02, //   Output (Data, Variable, Absolute)
+    0x95, 0x01, //   Report Count (1)
+    0x75, 0x03, //   Report Size (3)
+    0x91, 0x01, //   Output (Constant)
+    0xC0, // End Collection
+];
+
+/// An N-key rollover keyboard report: one modifier byte, then one bit for
+/// every key code up to `0xDF`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NkroReport {
+    bytes: [u8; 1 + BITMAP_KEYS / 8],
+}
+
+impl NkroReport {
+    /// A report with no keys pressed.
+    pub const fn empty() -> Self {
+        NkroReport {
+            bytes: [0; 1 + BITMAP_KEYS / 8],
+        }
+    }
+
+    /// Build a report from the key codes currently held down.
+    pub fn from_keys(keys: impl IntoIterator<Item = u8>) -> Self {
+        let mut report = Self::empty();
+        for key in keys {
+            report.press(key);
+        }
+        report
+    }
+
+    /// Add a key code; modifiers go into the modifier byte.
+    pub fn press(&mut self, key: u8) {
+        if key >= keycode::LEFT_CTRL {
+            if key <= keycode::RIGHT_GUI {
+                self.bytes[0] |= 1 << (key - keycode::LEFT_CTRL);
+            }
+        } else {
+            let key = usize::from(key);
+            self.bytes[1 + key / 8] |= 1 << (key % 8);
+        }
+    }
+
+    /// The raw report, ready for `push_raw_input`.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Default for NkroReport {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
@@ -0,0 +1,125 @@
+use super::Event;
+
+/// Number of layers a [`Layout`] can switch between.
+pub const MAX_LAYERS: usize = 16;
+
+/// What a key does on one layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Nothing.
+    NoOp,
+    /// Use whatever the next active layer down has in this position.
+    Trans,
+    /// Send one HID key code, see [`keycode`](super::keycode).
+    Key(u8),
+    /// Send several key codes at once, e.g. a modifier and a key.
+    MultipleKeys(&'static [u8]),
+    /// Activate a layer while the key is held.
+    Layer(u8),
+    /// Switch a layer on or off on each press.
+    ToggleLayer(u8),
+    /// Change the base layer that is used when no other layer is active.
+    DefaultLayer(u8),
+}
+
+/// Maps debounced key events to actions through a stack of layers.
+///
+/// The action for a key is looked up when it is pressed and remembered until
+/// it is released, so releasing a layer key while another key is held does not
+/// leave that key stuck down.
+pub struct Layout<const COLS: usize, const ROWS: usize> {
+    layers: &'static [[[Action; COLS]; ROWS]],
+    held: [[Action; COLS]; ROWS],
+    default_layer: u8,
+    momentary: [u8; MAX_LAYERS],
+    toggled: u16,
+}
+
+impl<const COLS: usize, const ROWS: usize> Layout<COLS, ROWS> {
+    /// Create a layout from a table of layers, layer 0 first.
+    ///
+    /// Panics if there are more than [`MAX_LAYERS`] layers.
+    pub fn new(layers: &'static [[[Action; COLS]; ROWS]]) -> Self {
+        assert!(layers.len() <= MAX_LAYERS);
+        Layout {
+            layers,
+            held: [[Action::NoOp; COLS]; ROWS],
+            default_layer: 0,
+            momentary: [0; MAX_LAYERS],
+            toggled: 0,
+        }
+    }
+
+    /// The highest layer that is currently active.
+    pub fn active_layer(&self) -> u8 {
+        (0..self.layers.len())
+            .rev()
+            .find(|&layer| self.is_active(layer))
+            .unwrap_or(usize::from(self.default_layer)) as u8
+    }
+
+    fn is_active(&self, layer: usize) -> bool {
+        layer == usize::from(self.default_layer)
+            || self.momentary[layer] > 0
+            || self.toggled & (1 << layer) != 0
+    }
+
+    fn resolve(&self, row: usize, col: usize) -> Action {
+        for layer in (0..self.layers.len()).rev() {
+            if !self.is_active(layer) {
+                continue;
+            }
+            match self.layers[layer][row][col] {
+                Action::Trans => continue,
+                action => return action,
+            }
+        }
+        Action::NoOp
+    }
+
+    /// Process one debounced key event.
+    pub fn event(&mut self, event: Event) {
+        let (row, col) = event.coord();
+        let (row, col) = (usize::from(row), usize::from(col));
+        if row >= ROWS || col >= COLS {
+            return;
+        }
+
+        if event.is_press() {
+            let action = self.resolve(row, col);
+            self.held[row][col] = action;
+            match action {
+                Action::Layer(layer) => {
+                    if let Some(count) = self.momentary.get_mut(usize::from(layer)) {
+                        *count += 1;
+                    }
+                }
+                Action::ToggleLayer(layer) => self.toggled ^= 1 << (layer & 0xF),
+                Action::DefaultLayer(layer) => self.default_layer = layer,
+                _ => {}
+            }
+        } else {
+            let action = core::mem::replace(&mut self.held[row][col], Action::NoOp);
+            if let Action::Layer(layer) = action {
+                if let Some(count) = self.momentary.get_mut(usize::from(layer)) {
+                    *count = count.saturating_sub(1);
+                }
+            }
+        }
+    }
+
+    /// The key codes of every key currently held down.
+    pub fn keycodes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.held
+            .iter()
+            .flat_map(|row| row.iter())
+            .flat_map(|action| {
+                let codes: &[u8] = match action {
+                    Action::Key(code) => core::slice::from_ref(code),
+                    Action::MultipleKeys(codes) => codes,
+                    _ => &[],
+                };
+                codes.iter().copied()
+            })
+    }
+}
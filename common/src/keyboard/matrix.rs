@@ -0,0 +1,49 @@
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use crate::delay;
+
+/// How long a row is held low before the columns are read.
+const SETTLE_NS: u32 = 1000;
+
+/// A switch matrix with one diode per key, rows driven and columns read.
+///
+/// Rows are push-pull outputs, idle high; columns are inputs with pull-ups.
+/// A closed switch pulls its column low while its row is driven low. Use
+/// `hal::gpio::DynPin` when the pins have different types.
+pub struct Matrix<C, R, const COLS: usize, const ROWS: usize> {
+    cols: [C; COLS],
+    rows: [R; ROWS],
+}
+
+impl<C, R, E, const COLS: usize, const ROWS: usize> Matrix<C, R, COLS, ROWS>
+where
+    C: InputPin<Error = E>,
+    R: OutputPin<Error = E>,
+{
+    /// Take the column and row pins, and set all rows idle.
+    pub fn new(cols: [C; COLS], mut rows: [R; ROWS]) -> Result<Self, E> {
+        for row in rows.iter_mut() {
+            row.set_high()?;
+        }
+        Ok(Matrix { cols, rows })
+    }
+
+    /// Read the whole matrix. `true` means the switch is closed.
+    pub fn scan(&mut self) -> Result<[[bool; COLS]; ROWS], E> {
+        let mut keys = [[false; COLS]; ROWS];
+        for (row, pressed) in self.rows.iter_mut().zip(keys.iter_mut()) {
+            row.set_low()?;
+            delay::delay_ns(SETTLE_NS);
+            for (col, key) in self.cols.iter().zip(pressed.iter_mut()) {
+                *key = col.is_low()?;
+            }
+            row.set_high()?;
+        }
+        Ok(keys)
+    }
+
+    /// Return the pins.
+    pub fn free(self) -> ([C; COLS], [R; ROWS]) {
+        (self.cols, self.rows)
+    }
+}
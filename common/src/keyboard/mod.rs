@@ -0,0 +1,54 @@
+//! Building blocks for keyboard firmware.
+//!
+//! A keyboard loop is the same on every board:
+//!
+//! 1. [`Matrix::scan`] reads which switches are closed right now,
+//! 2. [`Debouncer::update`] turns that into clean press and release
+//!    [`Event`]s,
+//! 3. [`Layout::event`] maps each event to an [`Action`] on the active layer,
+//! 4. [`NkroReport::from_keys`] packs the keys held down into a HID report.
+//!
+//! ```ignore
+//! let scan = matrix.scan()?;
+//! debouncer.update(&scan, |event| layout.event(event));
+//! let report = NkroReport::from_keys(layout.keycodes());
+//! hid.push_raw_input(report.as_bytes());
+//! ```
+//!
+//! Boards with one GPIO per key, such as the MacroPad, can skip the matrix and
+//! feed a `[[bool; COLS]; 1]` straight into the debouncer.
+
+mod debounce;
+mod hid;
+pub mod keycode;
+mod layout;
+mod matrix;
+pub mod split;
+
+pub use debounce::{Debouncer, DEFAULT_DEBOUNCE_SCANS};
+pub use hid::{NkroReport, NKRO_REPORT_DESCRIPTOR};
+pub use layout::{Action, Layout};
+pub use matrix::Matrix;
+
+/// A key changing state, identified by its position in the matrix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The key at `(row, column)` was pressed.
+    Press(u8, u8),
+    /// The key at `(row, column)` was released.
+    Release(u8, u8),
+}
+
+impl Event {
+    /// The `(row, column)` of the key.
+    pub fn coord(self) -> (u8, u8) {
+        match self {
+            Event::Press(row, col) | Event::Release(row, col) => (row, col),
+        }
+    }
+
+    /// `true` for [`Event::Press`].
+    pub fn is_press(self) -> bool {
+        matches!(self, Event::Press(..))
+    }
+}
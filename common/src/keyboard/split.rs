@@ -0,0 +1,268 @@
+//! A link between the two halves of a split keyboard.
+//!
+//! Both halves share a single wire (plus ground), driven open-drain, so either
+//! side can talk while the other listens. Each message travels in a small
+//! frame:
+//!
+//! ```text
+//! 0xA5 | sender | kind | payload | payload | CRC-8
+//! ```
+//!
+//! A frame with a bad CRC - line noise, or both halves talking at once - is
+//! dropped, and the [`Decoder`] hunts for the next `0xA5` to get back in step.
+//! Frames a half sent itself come back on the shared wire and are ignored.
+//!
+//! [`HalfDuplex`] carries the bytes using two PIO state machines, so the link
+//! costs no CPU time between frames. The wire needs a pull-up; the pad's
+//! internal one is enabled, an external 4.7 kΩ is better for long cables.
+
+use hal::pio::{
+    PIOBuilder, PIOExt, PinDir, Rx, ShiftDirection, StateMachineIndex, Tx, UninitStateMachine, PIO,
+};
+
+use super::Event;
+use crate::hal;
+
+/// First byte of every frame.
+pub const SYNC: u8 = 0xA5;
+
+/// Length of an encoded frame.
+pub const FRAME_LEN: usize = 6;
+
+/// Which half of the keyboard sent a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left = 0,
+    Right = 1,
+}
+
+/// What travels over the link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// A debounced key event, in the sending half's own coordinates.
+    Key(Event),
+    /// The active layer, for halves that show it on an LED.
+    Layer(u8),
+}
+
+const KIND_PRESS: u8 = 0;
+const KIND_RELEASE: u8 = 1;
+const KIND_LAYER: u8 = 2;
+
+/// CRC-8 with polynomial 0x07, as used by SMBus.
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Encode `message` from `sender` into a frame.
+pub fn encode(sender: Side, message: Message) -> [u8; FRAME_LEN] {
+    let (kind, a, b) = match message {
+        Message::Key(Event::Press(row, col)) => (KIND_PRESS, row, col),
+        Message::Key(Event::Release(row, col)) => (KIND_RELEASE, row, col),
+        Message::Layer(layer) => (KIND_LAYER, layer, 0),
+    };
+    let mut frame = [SYNC, sender as u8, kind, a, b, 0];
+    frame[5] = crc8(&frame[1..5]);
+    frame
+}
+
+/// Reassembles frames from a byte stream, resynchronising after errors.
+pub struct Decoder {
+    buffer: [u8; FRAME_LEN],
+    len: usize,
+    errors: u32,
+}
+
+impl Decoder {
+    /// A decoder waiting for the start of a frame.
+    pub const fn new() -> Self {
+        Decoder {
+            buffer: [0; FRAME_LEN],
+            len: 0,
+            errors: 0,
+        }
+    }
+
+    /// Feed in one received byte. Returns the sender and message when it
+    /// completes a valid frame.
+    pub fn push(&mut self, byte: u8) -> Option<(Side, Message)> {
+        if self.len == 0 && byte != SYNC {
+            return None;
+        }
+        self.buffer[self.len] = byte;
+        self.len += 1;
+        if self.len < FRAME_LEN {
+            return None;
+        }
+        self.len = 0;
+
+        let decoded = if crc8(&self.buffer[1..5]) == self.buffer[5] {
+            Self::decode(&self.buffer)
+        } else {
+            None
+        };
+        if decoded.is_none() {
+            self.errors = self.errors.wrapping_add(1);
+            self.resync();
+        }
+        decoded
+    }
+
+    fn decode(frame: &[u8; FRAME_LEN]) -> Option<(Side, Message)> {
+        let sender = match frame[1] {
+            0 => Side::Left,
+            1 => Side::Right,
+            _ => return None,
+        };
+        let message = match frame[2] {
+            KIND_PRESS => Message::Key(Event::Press(frame[3], frame[4])),
+            KIND_RELEASE => Message::Key(Event::Release(frame[3], frame[4])),
+            KIND_LAYER => Message::Layer(frame[3]),
+            _ => return None,
+        };
+        Some((sender, message))
+    }
+
+    /// After a bad frame, restart from the next `SYNC` inside it, in case the
+    /// real frame started part-way through.
+    fn resync(&mut self) {
+        if let Some(start) = self.buffer[1..].iter().position(|&b| b == SYNC) {
+            let start = start + 1;
+            let rest = FRAME_LEN - start;
+            self.buffer.copy_within(start.., 0);
+            self.len = rest;
+        }
+    }
+
+    /// Number of frames dropped so far.
+    pub fn errors(&self) -> u32 {
+        self.errors
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single-wire, open-drain, half-duplex byte link on two PIO state machines.
+pub struct HalfDuplex<P: PIOExt, TX: StateMachineIndex, RX: StateMachineIndex> {
+    tx: Tx<(P, TX)>,
+    rx: Rx<(P, RX)>,
+    side: Side,
+    decoder: Decoder,
+}
+
+impl<P, TX, RX> HalfDuplex<P, TX, RX>
+where
+    P: PIOExt,
+    TX: StateMachineIndex,
+    RX: StateMachineIndex,
+{
+    /// Start the link on GPIO `pin`, which must already be set to the PIO's
+    /// function.
+    ///
+    /// Both halves must use the same `baud`; a few hundred kilobaud is plenty
+    /// for key events and forgiving of long cables.
+    pub fn new(
+        pio: &mut PIO<P>,
+        tx_sm: UninitStateMachine<(P, TX)>,
+        rx_sm: UninitStateMachine<(P, RX)>,
+        pin: u8,
+        baud: u32,
+        system_clock_hz: u32,
+        side: Side,
+    ) -> Self {
+        // Eight state machine cycles per bit. Data bits are sent inverted, so
+        // a 1 in the FIFO drives the line low and a 0 releases it.
+        let tx_program = pio_proc::pio_asm!(
+            ".side_set 1 opt pindirs",
+            "    pull            side 0 [7]",
+            "    set x, 7        side 1 [7]",
+            "bitloop:",
+            "    out pindirs, 1         [6]",
+            "    jmp x-- bitloop",
+        );
+        // Wait for the start bit, then sample each bit in the middle.
+        let rx_program = pio_proc::pio_asm!(
+            "    wait 0 pin 0",
+            "    set x, 7               [10]",
+            "bitloop:",
+            "    in pins, 1",
+            "    jmp x-- bitloop        [6]",
+        );
+
+        let divisor = (u64::from(system_clock_hz) << 8) / (u64::from(baud) * 8);
+        let (int, frac) = ((divisor >> 8) as u16, divisor as u8);
+
+        // The line idles high through the pull-up. The PIO only ever drives
+        // it low, by switching the pin to an output.
+        let pads = unsafe { &*hal::pac::PADS_BANK0::ptr() };
+        pads.gpio[usize::from(pin)].modify(|_, w| w.pue().set_bit().pde().clear_bit());
+
+        let tx_installed = pio.install(&tx_program.program).unwrap();
+        let (mut tx_state, _, tx) = PIOBuilder::from_program(tx_installed)
+            .out_pins(pin, 1)
+            .side_set_pin_base(pin)
+            .out_shift_direction(ShiftDirection::Right)
+            .clock_divisor_fixed_point(int, frac)
+            .build(tx_sm);
+        tx_state.set_pins([(pin, hal::pio::PinState::Low)]);
+        tx_state.set_pindirs([(pin, PinDir::Input)]);
+
+        let rx_installed = pio.install(&rx_program.program).unwrap();
+        let (rx_state, rx, _) = PIOBuilder::from_program(rx_installed)
+            .in_pin_base(pin)
+            .jmp_pin(pin)
+            .in_shift_direction(ShiftDirection::Right)
+            .autopush(true)
+            .push_threshold(8)
+            .clock_divisor_fixed_point(int, frac)
+            .build(rx_sm);
+
+        tx_state.start();
+        rx_state.start();
+
+        HalfDuplex {
+            tx,
+            rx,
+            side,
+            decoder: Decoder::new(),
+        }
+    }
+
+    /// Queue one frame for sending, waiting for FIFO space if needed.
+    pub fn send(&mut self, message: Message) {
+        for byte in encode(self.side, message).iter() {
+            while !self.tx.write(u32::from(!byte)) {}
+        }
+    }
+
+    /// Process received bytes. Returns the next message from the other half,
+    /// or `None` if no complete frame has arrived yet.
+    pub fn poll(&mut self) -> Option<Message> {
+        while let Some(word) = self.rx.read() {
+            // Bits shift in from the top, so the byte ends up in bits 24-31
+            let byte = (word >> 24) as u8;
+            match self.decoder.push(byte) {
+                Some((sender, message)) if sender != self.side => return Some(message),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Number of frames dropped because of bad CRCs or collisions.
+    pub fn errors(&self) -> u32 {
+        self.decoder.errors()
+    }
+}
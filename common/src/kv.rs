@@ -0,0 +1,383 @@
+//! Named settings kept in the flash storage region, with wear leveling.
+//!
+//! [`Settings`] is a small key-value store: [`Settings::set`] appends a
+//! record to a log, and [`Settings::get`] finds the last record for a key.
+//! Values are up to [`MAX_VALUE_LEN`] bytes, under keys of up to
+//! [`MAX_KEY_LEN`]:
+//!
+//! ```ignore
+//! let mut settings = Settings::new(0);
+//! settings.set("name", b"kitchen")?;
+//! let mut buf = [0; MAX_VALUE_LEN];
+//! if let Some(len) = settings.get("name", &mut buf) {
+//!     // buf[..len] is b"kitchen"
+//! }
+//! ```
+//!
+//! The log lives in two sectors of the [`flash_storage`] region. Records are
+//! packed one after another, each as:
+//!
+//! ```text
+//! key length (u8) | value length (u8) | CRC-32 | key | value
+//! ```
+//!
+//! and a record whose CRC doesn't match, left by a write that was cut short,
+//! ends the log. When a sector fills up, the latest value of every key is
+//! copied into the other one, which then takes over. Each sector starts with
+//! a header carrying a sequence number; the copy's header is written last,
+//! so losing power partway through leaves the old sector in charge. The two
+//! sectors take turns being erased, and each is erased only once per sector's
+//! worth of writes.
+//!
+//! Writing flash stops execute-in-place, see [`flash_storage`] for what that
+//! means for interrupts and the other core.
+
+use crate::flash_storage::{self, program, SECTOR_SIZE};
+use crate::settings::crc32;
+
+/// Longest key, in bytes.
+pub const MAX_KEY_LEN: usize = 16;
+
+/// Longest value, in bytes.
+pub const MAX_VALUE_LEN: usize = 64;
+
+/// Flash used, from the offset given to [`Settings::new`].
+pub const SIZE: u32 = 2 * SECTOR_SIZE;
+
+const MAGIC: [u8; 4] = *b"RPKV";
+
+/// Magic, sequence number and its complement.
+const HEADER_LEN: u32 = 12;
+
+/// Lengths and CRC.
+const RECORD_HEADER_LEN: usize = 6;
+
+const MAX_RECORD_LEN: usize = RECORD_HEADER_LEN + MAX_KEY_LEN + MAX_VALUE_LEN;
+
+/// The value length of a removed key.
+const REMOVED: u8 = 0xFF;
+
+/// The key length of erased flash, where the log ends.
+const ERASED: u8 = 0xFF;
+
+/// Errors from [`Settings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The key is empty or longer than [`MAX_KEY_LEN`].
+    BadKey,
+    /// The value is longer than [`MAX_VALUE_LEN`].
+    TooLarge,
+    /// The latest values of all keys don't fit in a sector.
+    Full,
+    /// The store reaches past the flash storage region, or its offset isn't
+    /// a multiple of [`SECTOR_SIZE`].
+    Flash(flash_storage::Error),
+}
+
+impl From<flash_storage::Error> for Error {
+    fn from(error: flash_storage::Error) -> Self {
+        Error::Flash(error)
+    }
+}
+
+/// A record in the log.
+#[derive(Clone, Copy)]
+struct Record {
+    /// Where it starts, from the start of the region.
+    at: u32,
+    key_len: u8,
+    value_len: u8,
+}
+
+impl Record {
+    fn removed(&self) -> bool {
+        self.value_len == REMOVED
+    }
+
+    fn value_len(&self) -> usize {
+        if self.removed() {
+            0
+        } else {
+            usize::from(self.value_len)
+        }
+    }
+
+    fn len(&self) -> u32 {
+        (RECORD_HEADER_LEN + usize::from(self.key_len) + self.value_len()) as u32
+    }
+
+    fn value_at(&self) -> u32 {
+        self.at + (RECORD_HEADER_LEN + usize::from(self.key_len)) as u32
+    }
+}
+
+/// The sector holding the current log.
+#[derive(Clone, Copy)]
+struct Active {
+    /// Where it starts, from the start of the region.
+    sector: u32,
+    sequence: u32,
+    /// Where the next record goes.
+    end: u32,
+    /// False if the log ended at a damaged record, after which nothing more
+    /// can be appended.
+    clean: bool,
+}
+
+/// A key-value store in two sectors of the flash storage region.
+pub struct Settings {
+    offset: u32,
+}
+
+impl Settings {
+    /// A store in the [`SIZE`] bytes `offset` bytes into the flash storage
+    /// region, which must be a multiple of [`SECTOR_SIZE`].
+    pub const fn new(offset: u32) -> Self {
+        Settings { offset }
+    }
+
+    /// Copy the value of `key` into `buf`. Returns its length, or `None` if
+    /// the key isn't set or its value doesn't fit in `buf`.
+    pub fn get(&self, key: &str, buf: &mut [u8]) -> Option<usize> {
+        let active = self.active().ok()??;
+        let record = self.latest(active, key.as_bytes())?;
+        let len = record.value_len();
+        if record.removed() || len > buf.len() {
+            return None;
+        }
+        flash_storage::read(record.value_at(), &mut buf[..len]).ok()?;
+        Some(len)
+    }
+
+    /// Set `key` to `value`.
+    pub fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Error> {
+        if value.len() > MAX_VALUE_LEN {
+            return Err(Error::TooLarge);
+        }
+        self.append(key.as_bytes(), Some(value))
+    }
+
+    /// Forget `key`. Removing a key that isn't set does nothing.
+    pub fn remove(&mut self, key: &str) -> Result<(), Error> {
+        let set = match self.active()? {
+            Some(active) => self
+                .latest(active, key.as_bytes())
+                .is_some_and(|record| !record.removed()),
+            None => false,
+        };
+        if set {
+            self.append(key.as_bytes(), None)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Forget every key, erasing both sectors.
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.check()?;
+        flash_storage::erase_sector(self.offset)?;
+        flash_storage::erase_sector(self.offset + SECTOR_SIZE)?;
+        Ok(())
+    }
+
+    /// Add a record for `key`, with `value` or removing it, copying the log
+    /// to the other sector first if it won't fit.
+    fn append(&mut self, key: &[u8], value: Option<&[u8]>) -> Result<(), Error> {
+        if key.is_empty() || key.len() > MAX_KEY_LEN {
+            return Err(Error::BadKey);
+        }
+        let mut record = [0u8; MAX_RECORD_LEN];
+        let len = encode(key, value, &mut record);
+        let record = &record[..len];
+
+        if let Some(active) = self.active()? {
+            if active.clean && active.end + len as u32 <= active.sector + SECTOR_SIZE {
+                return Ok(program(active.end, record)?);
+            }
+        }
+        self.compact(key, record)
+    }
+
+    /// Copy the latest value of every key but `key` into the other sector,
+    /// add `record`, then make that sector the active one.
+    fn compact(&mut self, key: &[u8], record: &[u8]) -> Result<(), Error> {
+        let active = self.active()?;
+        let (sector, sequence) = match active {
+            Some(active) => (self.other(active.sector), active.sequence.wrapping_add(1)),
+            None => (self.offset, 1),
+        };
+        flash_storage::erase_sector(sector)?;
+
+        let mut end = sector + HEADER_LEN;
+        if let Some(active) = active {
+            let mut key_buf = [0u8; MAX_KEY_LEN];
+            let mut at = active.sector + HEADER_LEN;
+            while let Some(kept) = self.record(at, active.end, &mut key_buf) {
+                at += kept.len();
+                let kept_key = &key_buf[..usize::from(kept.key_len)];
+                if kept.removed() || kept_key == key || self.later(active, at, kept_key) {
+                    continue;
+                }
+                let mut copy = [0u8; MAX_RECORD_LEN];
+                let copy = &mut copy[..kept.len() as usize];
+                flash_storage::read(kept.at, copy)?;
+                if end + kept.len() > sector + SECTOR_SIZE {
+                    return Err(Error::Full);
+                }
+                program(end, copy)?;
+                end += kept.len();
+            }
+        }
+
+        if end + record.len() as u32 > sector + SECTOR_SIZE {
+            return Err(Error::Full);
+        }
+        program(end, record)?;
+
+        let mut header = [0u8; HEADER_LEN as usize];
+        header[0..4].copy_from_slice(&MAGIC);
+        header[4..8].copy_from_slice(&sequence.to_le_bytes());
+        header[8..12].copy_from_slice(&(!sequence).to_le_bytes());
+        Ok(program(sector, &header)?)
+    }
+
+    /// The sector with the newest valid header, and where its log ends.
+    fn active(&self) -> Result<Option<Active>, Error> {
+        self.check()?;
+        let first = self.sequence(self.offset)?;
+        let second = self.sequence(self.offset + SECTOR_SIZE)?;
+        let (sector, sequence) = match (first, second) {
+            (Some(a), Some(b)) if (b.wrapping_sub(a) as i32) > 0 => (self.offset + SECTOR_SIZE, b),
+            (Some(a), _) => (self.offset, a),
+            (None, Some(b)) => (self.offset + SECTOR_SIZE, b),
+            (None, None) => return Ok(None),
+        };
+
+        // Walk the log to find its end
+        let limit = sector + SECTOR_SIZE;
+        let mut key_buf = [0u8; MAX_KEY_LEN];
+        let mut end = sector + HEADER_LEN;
+        while let Some(record) = self.record(end, limit, &mut key_buf) {
+            end += record.len();
+        }
+        let clean = end + 1 > limit || {
+            let mut next = [0u8; 1];
+            flash_storage::read(end, &mut next)?;
+            next[0] == ERASED
+        };
+        Ok(Some(Active {
+            sector,
+            sequence,
+            end,
+            clean,
+        }))
+    }
+
+    /// The sequence number in the header of `sector`, if it has a valid one.
+    fn sequence(&self, sector: u32) -> Result<Option<u32>, Error> {
+        let mut header = [0u8; HEADER_LEN as usize];
+        flash_storage::read(sector, &mut header)?;
+        let sequence = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let check = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        Ok(if header[0..4] == MAGIC && check == !sequence {
+            Some(sequence)
+        } else {
+            None
+        })
+    }
+
+    /// The record at `at`, with its key read into `key`, if there is a valid
+    /// one that ends before `limit`.
+    fn record(&self, at: u32, limit: u32, key: &mut [u8; MAX_KEY_LEN]) -> Option<Record> {
+        if at + RECORD_HEADER_LEN as u32 > limit {
+            return None;
+        }
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        flash_storage::read(at, &mut header).ok()?;
+        let record = Record {
+            at,
+            key_len: header[0],
+            value_len: header[1],
+        };
+        if record.key_len == 0
+            || usize::from(record.key_len) > MAX_KEY_LEN
+            || (!record.removed() && usize::from(record.value_len) > MAX_VALUE_LEN)
+            || at + record.len() > limit
+        {
+            return None;
+        }
+
+        let mut body = [0u8; MAX_RECORD_LEN];
+        body[..2].copy_from_slice(&header[..2]);
+        let body = &mut body[..record.len() as usize - 4];
+        flash_storage::read(at + RECORD_HEADER_LEN as u32, &mut body[2..]).ok()?;
+        if crc32(body) != u32::from_le_bytes([header[2], header[3], header[4], header[5]]) {
+            return None;
+        }
+        let key_len = usize::from(record.key_len);
+        key[..key_len].copy_from_slice(&body[2..2 + key_len]);
+        Some(record)
+    }
+
+    /// The last record for `key` in the active sector.
+    fn latest(&self, active: Active, key: &[u8]) -> Option<Record> {
+        let mut key_buf = [0u8; MAX_KEY_LEN];
+        let mut at = active.sector + HEADER_LEN;
+        let mut found = None;
+        while let Some(record) = self.record(at, active.end, &mut key_buf) {
+            if &key_buf[..usize::from(record.key_len)] == key {
+                found = Some(record);
+            }
+            at += record.len();
+        }
+        found
+    }
+
+    /// Whether the active log has a record for `key` at or after `at`.
+    fn later(&self, active: Active, mut at: u32, key: &[u8]) -> bool {
+        let mut key_buf = [0u8; MAX_KEY_LEN];
+        while let Some(record) = self.record(at, active.end, &mut key_buf) {
+            if &key_buf[..usize::from(record.key_len)] == key {
+                return true;
+            }
+            at += record.len();
+        }
+        false
+    }
+
+    fn other(&self, sector: u32) -> u32 {
+        if sector == self.offset {
+            self.offset + SECTOR_SIZE
+        } else {
+            self.offset
+        }
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if !self.offset.is_multiple_of(SECTOR_SIZE) {
+            return Err(Error::Flash(flash_storage::Error::Unaligned));
+        }
+        match self.offset.checked_add(SIZE) {
+            Some(end) if end <= flash_storage::capacity() => Ok(()),
+            _ => Err(Error::Flash(flash_storage::Error::OutOfRange)),
+        }
+    }
+}
+
+/// Write the record for `key` into `buf`, returning its length.
+fn encode(key: &[u8], value: Option<&[u8]>, buf: &mut [u8; MAX_RECORD_LEN]) -> usize {
+    let value_len = value.map_or(REMOVED, |value| value.len() as u8);
+    let value = value.unwrap_or(&[]);
+    let len = RECORD_HEADER_LEN + key.len() + value.len();
+
+    // The CRC covers the lengths, key and value, gathered after it for now
+    buf[4] = key.len() as u8;
+    buf[5] = value_len;
+    buf[6..6 + key.len()].copy_from_slice(key);
+    buf[6 + key.len()..len].copy_from_slice(value);
+    let crc = crc32(&buf[4..len]);
+    buf[0] = key.len() as u8;
+    buf[1] = value_len;
+    buf[2..6].copy_from_slice(&crc.to_le_bytes());
+    len
+}
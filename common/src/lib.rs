@@ -0,0 +1,85 @@
+#![no_std]
+
+//! Board-independent helpers shared by the rp-hal-boards Board Support Packages.
+//!
+//! Everything in this crate works on any RP2040 based board. The BSPs provide the
+//! pin names, this crate provides the bits of glue that firmware for all of them
+//! tends to need.
+
+pub extern crate rp2040_hal as hal;
+
+pub mod adc_stream;
+#[cfg(feature = "arduino-compat")]
+pub mod arduino_compat;
+pub mod audio_pwm;
+pub mod bitbang;
+pub mod board_rev;
+pub mod buffered_serial;
+pub mod calibration;
+pub mod clocks;
+pub mod datalog;
+pub mod delay;
+pub mod dma;
+pub mod double_reset;
+pub mod dsp;
+#[cfg(feature = "dvi")]
+pub mod dvi;
+pub mod encoder;
+pub mod energy_budget;
+pub mod escpos;
+pub mod filters;
+pub mod fingerprint;
+pub mod flash_storage;
+#[cfg(feature = "framebuffer")]
+pub mod framebuffer;
+pub mod fuel_gauge;
+pub mod i2s;
+pub mod input;
+pub mod ir;
+pub mod irq_profile;
+pub mod joystick;
+pub mod keyboard;
+pub mod kv;
+#[cfg(feature = "littlefs")]
+pub mod littlefs;
+pub mod logic_capture;
+pub mod mcp23017;
+#[cfg(feature = "mcp2515")]
+pub mod mcp2515;
+pub mod mfrc522;
+pub mod mqtt;
+pub mod nmea;
+pub mod ota;
+pub mod pca9685;
+pub mod pio_pdm;
+pub mod power;
+pub mod regs;
+pub mod resources;
+pub mod rom;
+#[cfg(feature = "rtic-monotonic")]
+pub mod rtic_monotonic;
+pub mod schedule;
+pub mod scope;
+pub mod servo;
+pub mod settings;
+pub mod shell;
+pub mod shift_register;
+pub mod sio_math;
+pub mod sleep;
+pub mod spi_slave;
+pub mod status_led;
+pub mod stepper;
+pub mod sump;
+#[cfg(feature = "embassy-time")]
+pub mod time_driver;
+pub mod timesync;
+pub mod uart_bridge;
+pub mod ultrasonic;
+pub mod usb;
+pub mod usb_audio;
+pub mod usb_midi;
+#[cfg(feature = "usb-net")]
+pub mod usb_net;
+#[cfg(feature = "vga")]
+pub mod vga;
+pub mod w5500;
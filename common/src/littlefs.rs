@@ -0,0 +1,83 @@
+//! A littlefs filesystem in the flash storage region.
+//!
+//! With the `littlefs` feature, [`Storage`] puts `BLOCKS` sectors of the
+//! [`flash_storage`] region behind the `littlefs2` crate's storage trait, so
+//! firmware can keep files that survive resets, power cuts partway through a
+//! write, and reflashing:
+//!
+//! ```ignore
+//! use rp_hal_boards_common::littlefs::{littlefs2, Storage};
+//! use littlefs2::fs::Filesystem;
+//!
+//! let mut storage: Storage<16> = Storage::new(0).unwrap();
+//! if !Filesystem::is_mountable(&mut storage) {
+//!     Filesystem::format(&mut storage).unwrap();
+//! }
+//! Filesystem::mount_and_then(&mut storage, |fs| {
+//!     fs.write(littlefs2::path!("hello.txt"), b"hello")
+//! })?;
+//! ```
+//!
+//! `littlefs2` is re-exported, so firmware needn't depend on it directly.
+//! Its C sources are built for the target, which needs an `arm-none-eabi`
+//! C compiler.
+//!
+//! littlefs spreads its writes over all its blocks, moving metadata on after
+//! [`BLOCK_CYCLES`] erases. Writing flash stops execute-in-place, see
+//! [`flash_storage`] for what that means for interrupts and the other core.
+
+pub use littlefs2;
+
+use crate::flash_storage::{self, SECTOR_SIZE};
+use littlefs2::consts::{U1, U256};
+use littlefs2::io::{Error, Result};
+
+/// Erases of a metadata block before littlefs moves it, to level wear.
+pub const BLOCK_CYCLES: isize = 500;
+
+/// `BLOCKS` sectors of the flash storage region, as littlefs storage.
+pub struct Storage<const BLOCKS: usize> {
+    offset: u32,
+}
+
+impl<const BLOCKS: usize> Storage<BLOCKS> {
+    /// The sectors from `offset` bytes into the flash storage region, which
+    /// must be a multiple of [`SECTOR_SIZE`]. Returns `None` if they don't
+    /// fit in the region.
+    pub fn new(offset: u32) -> Option<Self> {
+        let end = offset.checked_add(BLOCKS as u32 * SECTOR_SIZE)?;
+        if offset % SECTOR_SIZE != 0 || end > flash_storage::capacity() {
+            return None;
+        }
+        Some(Storage { offset })
+    }
+}
+
+impl<const BLOCKS: usize> littlefs2::driver::Storage for Storage<BLOCKS> {
+    const READ_SIZE: usize = 1;
+    const WRITE_SIZE: usize = 1;
+    const BLOCK_SIZE: usize = SECTOR_SIZE as usize;
+    const BLOCK_COUNT: usize = BLOCKS;
+    const BLOCK_CYCLES: isize = BLOCK_CYCLES;
+
+    type CACHE_SIZE = U256;
+    type LOOKAHEAD_SIZE = U1;
+
+    fn read(&mut self, off: usize, buf: &mut [u8]) -> Result<usize> {
+        flash_storage::read(self.offset + off as u32, buf).map_err(|_| Error::Io)?;
+        Ok(buf.len())
+    }
+
+    fn write(&mut self, off: usize, data: &[u8]) -> Result<usize> {
+        flash_storage::program(self.offset + off as u32, data).map_err(|_| Error::Io)?;
+        Ok(data.len())
+    }
+
+    fn erase(&mut self, off: usize, len: usize) -> Result<usize> {
+        let start = self.offset + off as u32;
+        for sector in (start..start + len as u32).step_by(SECTOR_SIZE as usize) {
+            flash_storage::erase_sector(sector).map_err(|_| Error::Io)?;
+        }
+        Ok(len)
+    }
+}
@@ -0,0 +1,231 @@
+//! Driver for the Microchip MCP23017 16-bit I²C GPIO expander.
+//!
+//! The expander's pins implement the embedded-hal `InputPin` and `OutputPin`
+//! traits, so they can be handed to anything that takes GPIOs, such as
+//! [`keyboard::Matrix`](crate::keyboard::Matrix), alongside or instead of the
+//! RP2040's own pins. Every pin borrows the [`Mcp23017`], which owns the bus,
+//! so all sixteen can be in use at once.
+//!
+//! Pins are numbered 0 to 15: GPA0 to GPA7, then GPB0 to GPB7.
+//!
+//! The expander can also report changes on its inputs. [`Mcp23017::new`] ties
+//! INTA and INTB together as one open-drain, active-low output: wire it to an
+//! RP2040 GPIO with a pull-up, enable the inputs to watch with
+//! [`Mcp23017::enable_interrupt`], and call [`Mcp23017::take_interrupt`] when
+//! the GPIO goes low.
+
+use core::cell::RefCell;
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// The address with A0, A1 and A2 all tied low.
+pub const DEFAULT_ADDRESS: u8 = 0x20;
+
+// Register addresses with IOCON.BANK = 0, the A register of each pair
+const IODIR: u8 = 0x00;
+const GPINTEN: u8 = 0x04;
+const IOCON: u8 = 0x0A;
+const GPPU: u8 = 0x0C;
+const INTF: u8 = 0x0E;
+const INTCAP: u8 = 0x10;
+const GPIO: u8 = 0x12;
+const OLAT: u8 = 0x14;
+
+/// IOCON: INTA and INTB mirror each other, open-drain
+const IOCON_MIRROR: u8 = 1 << 6;
+const IOCON_ODR: u8 = 1 << 2;
+
+struct Inner<I2C> {
+    i2c: I2C,
+    address: u8,
+    iodir: u16,
+    gppu: u16,
+    olat: u16,
+    gpinten: u16,
+}
+
+impl<I2C, E> Inner<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    fn write_pair(&mut self, register: u8, value: u16) -> Result<(), E> {
+        let [a, b] = value.to_le_bytes();
+        self.i2c.write(self.address, &[register, a, b])
+    }
+
+    fn read_pair(&mut self, register: u8) -> Result<u16, E> {
+        let mut buf = [0; 2];
+        self.i2c.write_read(self.address, &[register], &mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+}
+
+/// Changes reported by [`Mcp23017::take_interrupt`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// The pins that caused the interrupt, one bit per pin.
+    pub flags: u16,
+    /// The state of every pin when the interrupt fired.
+    pub captured: u16,
+}
+
+/// An MCP23017 on an I²C bus.
+pub struct Mcp23017<I2C> {
+    inner: RefCell<Inner<I2C>>,
+}
+
+impl<I2C, E> Mcp23017<I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    /// Set up the expander at `address`: every pin an input without pull-up,
+    /// and the interrupt outputs mirrored and open-drain.
+    pub fn new(i2c: I2C, address: u8) -> Result<Self, E> {
+        let mut inner = Inner {
+            i2c,
+            address,
+            iodir: 0xFFFF,
+            gppu: 0,
+            olat: 0,
+            gpinten: 0,
+        };
+        inner.write_pair(IOCON, u16::from(IOCON_MIRROR | IOCON_ODR) * 0x0101)?;
+        inner.write_pair(IODIR, inner.iodir)?;
+        inner.write_pair(GPPU, inner.gppu)?;
+        inner.write_pair(OLAT, inner.olat)?;
+        inner.write_pair(GPINTEN, inner.gpinten)?;
+        Ok(Mcp23017 {
+            inner: RefCell::new(inner),
+        })
+    }
+
+    /// Make `pin` an input, with the expander's 100 kΩ pull-up if `pull_up`.
+    pub fn input(&self, pin: u8, pull_up: bool) -> Result<ExpanderInput<'_, I2C>, E> {
+        let mask = mask(pin);
+        let mut inner = self.inner.borrow_mut();
+        if pull_up {
+            inner.gppu |= mask;
+        } else {
+            inner.gppu &= !mask;
+        }
+        inner.iodir |= mask;
+        let (gppu, iodir) = (inner.gppu, inner.iodir);
+        inner.write_pair(GPPU, gppu)?;
+        inner.write_pair(IODIR, iodir)?;
+        Ok(ExpanderInput { chip: self, mask })
+    }
+
+    /// Make `pin` an output, starting low.
+    pub fn output(&self, pin: u8) -> Result<ExpanderOutput<'_, I2C>, E> {
+        let mask = mask(pin);
+        let mut inner = self.inner.borrow_mut();
+        inner.olat &= !mask;
+        inner.iodir &= !mask;
+        let (olat, iodir) = (inner.olat, inner.iodir);
+        inner.write_pair(OLAT, olat)?;
+        inner.write_pair(IODIR, iodir)?;
+        Ok(ExpanderOutput { chip: self, mask })
+    }
+
+    /// Read all sixteen pins at once, GPA0 in bit 0.
+    pub fn read_all(&self) -> Result<u16, E> {
+        self.inner.borrow_mut().read_pair(GPIO)
+    }
+
+    /// Set every output pin at once from `levels`, GPA0 in bit 0.
+    pub fn write_all(&self, levels: u16) -> Result<(), E> {
+        let mut inner = self.inner.borrow_mut();
+        inner.olat = levels;
+        inner.write_pair(OLAT, levels)
+    }
+
+    /// Raise the interrupt output whenever `pin` changes.
+    pub fn enable_interrupt(&self, pin: u8) -> Result<(), E> {
+        let mut inner = self.inner.borrow_mut();
+        inner.gpinten |= mask(pin);
+        let gpinten = inner.gpinten;
+        inner.write_pair(GPINTEN, gpinten)
+    }
+
+    /// Stop `pin` raising the interrupt output.
+    pub fn disable_interrupt(&self, pin: u8) -> Result<(), E> {
+        let mut inner = self.inner.borrow_mut();
+        inner.gpinten &= !mask(pin);
+        let gpinten = inner.gpinten;
+        inner.write_pair(GPINTEN, gpinten)
+    }
+
+    /// Find out what raised the interrupt output, and release it.
+    ///
+    /// Returns `None` if no pin has changed since the last call.
+    pub fn take_interrupt(&self) -> Result<Option<Change>, E> {
+        let mut inner = self.inner.borrow_mut();
+        let flags = inner.read_pair(INTF)?;
+        // Reading the captured state clears the interrupt
+        let captured = inner.read_pair(INTCAP)?;
+        Ok(if flags == 0 {
+            None
+        } else {
+            Some(Change { flags, captured })
+        })
+    }
+
+    /// Return the I²C bus.
+    pub fn free(self) -> I2C {
+        self.inner.into_inner().i2c
+    }
+}
+
+fn mask(pin: u8) -> u16 {
+    assert!(pin < 16, "the MCP23017 has pins 0 to 15");
+    1 << pin
+}
+
+/// An expander pin set up as an input.
+pub struct ExpanderInput<'a, I2C> {
+    chip: &'a Mcp23017<I2C>,
+    mask: u16,
+}
+
+impl<'a, I2C, E> InputPin for ExpanderInput<'a, I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    type Error = E;
+
+    fn is_high(&self) -> Result<bool, E> {
+        Ok(self.chip.read_all()? & self.mask != 0)
+    }
+
+    fn is_low(&self) -> Result<bool, E> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+/// An expander pin set up as an output.
+pub struct ExpanderOutput<'a, I2C> {
+    chip: &'a Mcp23017<I2C>,
+    mask: u16,
+}
+
+impl<'a, I2C, E> OutputPin for ExpanderOutput<'a, I2C>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+{
+    type Error = E;
+
+    fn set_high(&mut self) -> Result<(), E> {
+        let mut inner = self.chip.inner.borrow_mut();
+        inner.olat |= self.mask;
+        let olat = inner.olat;
+        inner.write_pair(OLAT, olat)
+    }
+
+    fn set_low(&mut self) -> Result<(), E> {
+        let mut inner = self.chip.inner.borrow_mut();
+        inner.olat &= !self.mask;
+        let olat = inner.olat;
+        inner.write_pair(OLAT, olat)
+    }
+}
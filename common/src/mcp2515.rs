@@ -0,0 +1,450 @@
+//! Driver for the Microchip MCP2515 CAN controller, over SPI.
+//!
+//! The RP2040 has no CAN controller of its own; the MCP2515, usually sold on
+//! a module together with a transceiver, adds one. [`Mcp2515`] sets up the
+//! bit timing for the module's crystal and implements `embedded-can`'s
+//! non-blocking [`Can`] trait, so drivers written against that trait work
+//! with it unchanged.
+//!
+//! ```ignore
+//! let mut can = Mcp2515::new(spi, cs, 8.MHz(), 500.kHz(), Mode::Normal)?;
+//! // Only frames with standard IDs 0x100 to 0x10F
+//! can.set_filters(StandardId::new(0x7F0).unwrap().into(), &[StandardId::new(0x100).unwrap().into()])?;
+//! if let Ok(frame) = can.receive() {
+//!     nb::block!(can.transmit(&frame))?;
+//! }
+//! ```
+//!
+//! Frames go out of a single transmit buffer, so they leave in the order
+//! they were given; received frames roll over from the first receive buffer
+//! to the second, so there is room for two before any are lost. The INT pin
+//! goes low while a received frame is waiting, for callers that would rather
+//! not poll the bus.
+//!
+//! The bus must be SPI mode 0 or 3, at up to 10 MHz.
+
+use core::convert::Infallible;
+use core::fmt::Debug;
+
+use embedded_can::nb::Can;
+use embedded_can::{ErrorKind, ExtendedId, Id, StandardId};
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+use fugit::HertzU32;
+
+// Instructions
+const RESET: u8 = 0xC0;
+const READ: u8 = 0x03;
+const WRITE: u8 = 0x02;
+const BIT_MODIFY: u8 = 0x05;
+const READ_STATUS: u8 = 0xA0;
+/// Read from RXB0SIDH, clearing RX0IF afterwards; `| 0x04` for RXB1.
+const READ_RX: u8 = 0x90;
+/// Load from TXB0SIDH.
+const LOAD_TX: u8 = 0x40;
+/// Request to send TXB0.
+const RTS_TXB0: u8 = 0x81;
+
+// Registers
+const RXF0: u8 = 0x00;
+const RXF2: u8 = 0x08;
+const RXF3: u8 = 0x10;
+const RXM0: u8 = 0x20;
+const CANSTAT: u8 = 0x0E;
+const CANCTRL: u8 = 0x0F;
+/// CNF3, CNF2, CNF1 and CANINTE, written in one go.
+const CNF3: u8 = 0x28;
+const EFLG: u8 = 0x2D;
+const RXB0CTRL: u8 = 0x60;
+const RXB1CTRL: u8 = 0x70;
+
+// READ_STATUS bits
+const RX0IF: u8 = 1 << 0;
+const RX1IF: u8 = 1 << 1;
+const TXB0_TXREQ: u8 = 1 << 2;
+
+// EFLG bits
+const RX0OVR: u8 = 1 << 6;
+const RX1OVR: u8 = 1 << 7;
+
+// RXBnCTRL bits
+const RXM_ANY: u8 = 0x60;
+const BUKT: u8 = 0x04;
+
+// ID and DLC bits
+const EXIDE: u8 = 0x08;
+const SRR: u8 = 0x10;
+const RTR: u8 = 0x40;
+
+/// The operating mode, CANCTRL's REQOP.
+const CONFIGURATION: u8 = 0x80;
+
+/// Register polls before giving up on a change of mode.
+const POLLS: u32 = 10_000;
+
+/// Errors from the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The SPI bus failed.
+    Spi(E),
+    /// The bit rate can't be made from the crystal.
+    Bitrate,
+    /// The controller didn't change mode, e.g. because it isn't connected.
+    Timeout,
+    /// Frames arrived while both receive buffers were full, and were lost.
+    Overrun,
+}
+
+impl<E: Debug> embedded_can::Error for Error<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Overrun => ErrorKind::Overrun,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// How the controller takes part on the bus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Sending, receiving and acknowledging frames.
+    Normal,
+    /// Receiving its own frames without touching the bus, for testing
+    /// without another node.
+    Loopback,
+    /// Receiving without ever driving the bus, not even to acknowledge, for
+    /// watching a bus without disturbing it.
+    ListenOnly,
+}
+
+impl Mode {
+    fn reqop(self) -> u8 {
+        match self {
+            Mode::Normal => 0x00,
+            Mode::Loopback => 0x40,
+            Mode::ListenOnly => 0x60,
+        }
+    }
+}
+
+/// A CAN 2.0 frame, with up to 8 bytes of data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    id: Id,
+    remote: bool,
+    dlc: u8,
+    data: [u8; 8],
+}
+
+impl embedded_can::Frame for Frame {
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        if data.len() > 8 {
+            return None;
+        }
+        let mut frame = Frame {
+            id: id.into(),
+            remote: false,
+            dlc: data.len() as u8,
+            data: [0; 8],
+        };
+        frame.data[..data.len()].copy_from_slice(data);
+        Some(frame)
+    }
+
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        if dlc > 8 {
+            return None;
+        }
+        Some(Frame {
+            id: id.into(),
+            remote: true,
+            dlc: dlc as u8,
+            data: [0; 8],
+        })
+    }
+
+    fn is_extended(&self) -> bool {
+        matches!(self.id, Id::Extended(_))
+    }
+
+    fn is_remote_frame(&self) -> bool {
+        self.remote
+    }
+
+    fn id(&self) -> Id {
+        self.id
+    }
+
+    fn dlc(&self) -> usize {
+        usize::from(self.dlc)
+    }
+
+    fn data(&self) -> &[u8] {
+        if self.remote {
+            &[]
+        } else {
+            &self.data[..usize::from(self.dlc)]
+        }
+    }
+}
+
+/// An MCP2515 on an SPI bus, with its own chip select.
+pub struct Mcp2515<SPI, CS> {
+    spi: SPI,
+    cs: CS,
+    mode: Mode,
+}
+
+impl<SPI, CS, E> Mcp2515<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E>,
+    CS: OutputPin<Error = Infallible>,
+{
+    /// Reset the controller, set it to `bitrate` from its `crystal`, and
+    /// join the bus in `mode`, receiving every frame.
+    ///
+    /// Most modules have an 8 MHz crystal, which reaches 500 kbit/s at
+    /// most; 1 Mbit/s needs a 16 MHz one.
+    pub fn new(
+        spi: SPI,
+        mut cs: CS,
+        crystal: HertzU32,
+        bitrate: HertzU32,
+        mode: Mode,
+    ) -> Result<Self, Error<E>> {
+        let cnf = timing(crystal.to_Hz(), bitrate.to_Hz()).ok_or(Error::Bitrate)?;
+        let _ = cs.set_high();
+        let mut can = Mcp2515 { spi, cs, mode };
+        can.exchange(&mut [RESET])?;
+        can.wait_for_mode(CONFIGURATION)?;
+
+        // CANINTE: INT goes low while either receive buffer is full
+        can.exchange(&mut [WRITE, CNF3, cnf[0], cnf[1], cnf[2], RX0IF | RX1IF])?;
+        can.write(RXB0CTRL, RXM_ANY | BUKT)?;
+        can.write(RXB1CTRL, RXM_ANY)?;
+        can.request_mode(mode.reqop())?;
+        Ok(can)
+    }
+
+    /// Switch to `mode`.
+    pub fn set_mode(&mut self, mode: Mode) -> Result<(), Error<E>> {
+        self.request_mode(mode.reqop())?;
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// Receive only frames whose ID, in the bits set in `mask`, matches one
+    /// of `ids`, or every frame if `ids` is empty.
+    ///
+    /// `mask` and `ids` should all be standard or all be extended: a
+    /// standard ID only matches standard frames, and an extended one
+    /// extended frames. There are six filters, so at most six `ids`. Frames
+    /// already received are kept.
+    pub fn set_filters(&mut self, mask: Id, ids: &[Id]) -> Result<(), Error<E>> {
+        assert!(ids.len() <= 6, "the MCP2515 has six filters");
+        self.request_mode(CONFIGURATION)?;
+        if ids.is_empty() {
+            self.write(RXB0CTRL, RXM_ANY | BUKT)?;
+            self.write(RXB1CTRL, RXM_ANY)?;
+        } else {
+            // Both buffers' masks, which the mask registers have no EXIDE
+            // bit for; for standard frames their EID bits would compare the
+            // first two data bytes
+            let mut mask = id_bytes(mask);
+            mask[1] &= !EXIDE;
+            let mut write = [WRITE, RXM0, 0, 0, 0, 0, 0, 0, 0, 0];
+            write[2..6].copy_from_slice(&mask);
+            write[6..].copy_from_slice(&mask);
+            self.exchange(&mut write)?;
+
+            // Spare filters repeat the last ID, so accept nothing else
+            let filter = |n: usize| id_bytes(ids[n.min(ids.len() - 1)]);
+            let mut write = [WRITE, RXF0, 0, 0, 0, 0, 0, 0, 0, 0];
+            write[2..6].copy_from_slice(&filter(0));
+            write[6..].copy_from_slice(&filter(1));
+            self.exchange(&mut write)?;
+            // RXF2 is alone before the gap to RXF3
+            let mut write = [WRITE, RXF2, 0, 0, 0, 0];
+            write[2..].copy_from_slice(&filter(2));
+            self.exchange(&mut write)?;
+            let mut write = [WRITE, RXF3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+            write[2..6].copy_from_slice(&filter(3));
+            write[6..10].copy_from_slice(&filter(4));
+            write[10..].copy_from_slice(&filter(5));
+            self.exchange(&mut write)?;
+
+            self.write(RXB0CTRL, BUKT)?;
+            self.write(RXB1CTRL, 0)?;
+        }
+        self.request_mode(self.mode.reqop())
+    }
+
+    /// Stop, handing back the bus and chip select. The controller is left
+    /// in its current mode.
+    pub fn free(self) -> (SPI, CS) {
+        (self.spi, self.cs)
+    }
+
+    fn read(&mut self, register: u8) -> Result<u8, Error<E>> {
+        let mut buf = [READ, register, 0];
+        self.exchange(&mut buf)?;
+        Ok(buf[2])
+    }
+
+    fn write(&mut self, register: u8, value: u8) -> Result<(), Error<E>> {
+        self.exchange(&mut [WRITE, register, value])
+    }
+
+    fn modify(&mut self, register: u8, mask: u8, value: u8) -> Result<(), Error<E>> {
+        self.exchange(&mut [BIT_MODIFY, register, mask, value])
+    }
+
+    fn status(&mut self) -> Result<u8, Error<E>> {
+        let mut buf = [READ_STATUS, 0];
+        self.exchange(&mut buf)?;
+        Ok(buf[1])
+    }
+
+    /// Ask for operating mode `reqop`, and wait until the controller is in
+    /// it.
+    fn request_mode(&mut self, reqop: u8) -> Result<(), Error<E>> {
+        self.modify(CANCTRL, 0xE0, reqop)?;
+        self.wait_for_mode(reqop)
+    }
+
+    fn wait_for_mode(&mut self, reqop: u8) -> Result<(), Error<E>> {
+        for _ in 0..POLLS {
+            if self.read(CANSTAT)? & 0xE0 == reqop {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+
+    fn exchange(&mut self, buf: &mut [u8]) -> Result<(), Error<E>> {
+        let _ = self.cs.set_low();
+        let result = self.spi.transfer(buf).map(|_| ()).map_err(Error::Spi);
+        let _ = self.cs.set_high();
+        result
+    }
+}
+
+impl<SPI, CS, E> Can for Mcp2515<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E>,
+    CS: OutputPin<Error = Infallible>,
+    E: Debug,
+{
+    type Frame = Frame;
+    type Error = Error<E>;
+
+    /// Queue `frame`, or `WouldBlock` while the one before is still going
+    /// out. Never hands back a frame it replaced.
+    fn transmit(&mut self, frame: &Frame) -> nb::Result<Option<Frame>, Error<E>> {
+        if self.status()? & TXB0_TXREQ != 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        let mut load = [0; 14];
+        load[0] = LOAD_TX;
+        load[1..5].copy_from_slice(&id_bytes(frame.id));
+        load[5] = frame.dlc | if frame.remote { RTR } else { 0 };
+        load[6..].copy_from_slice(&frame.data);
+        let len = if frame.remote {
+            6
+        } else {
+            6 + usize::from(frame.dlc)
+        };
+        self.exchange(&mut load[..len])?;
+        self.exchange(&mut [RTS_TXB0])?;
+        Ok(None)
+    }
+
+    /// Take the oldest frame received, or `WouldBlock` if there isn't one.
+    ///
+    /// Reports [`Error::Overrun`] once after frames have been lost.
+    fn receive(&mut self) -> nb::Result<Frame, Error<E>> {
+        if self.read(EFLG)? & (RX0OVR | RX1OVR) != 0 {
+            self.modify(EFLG, RX0OVR | RX1OVR, 0)?;
+            return Err(nb::Error::Other(Error::Overrun));
+        }
+        // RXB0 first: frames only roll over into RXB1 while it is full
+        let status = self.status()?;
+        let instruction = if status & RX0IF != 0 {
+            READ_RX
+        } else if status & RX1IF != 0 {
+            READ_RX | 0x04
+        } else {
+            return Err(nb::Error::WouldBlock);
+        };
+        let mut buf = [0; 14];
+        buf[0] = instruction;
+        self.exchange(&mut buf)?;
+
+        let [sidh, sidl, eid8, eid0] = [buf[1], buf[2], buf[3], buf[4]];
+        let sid = u32::from(sidh) << 3 | u32::from(sidl) >> 5;
+        let (id, remote) = if sidl & EXIDE != 0 {
+            let id =
+                sid << 18 | u32::from(sidl & 0x03) << 16 | u32::from(eid8) << 8 | u32::from(eid0);
+            // Can't fail, the ID is 29 bits
+            let id = Id::Extended(ExtendedId::new(id).unwrap());
+            (id, buf[5] & RTR != 0)
+        } else {
+            // Can't fail, the ID is 11 bits
+            let id = Id::Standard(StandardId::new(sid as u16).unwrap());
+            (id, sidl & SRR != 0)
+        };
+        // DLCs from 9 to 15 also mean 8 bytes
+        let mut frame = Frame {
+            id,
+            remote,
+            dlc: (buf[5] & 0x0F).min(8),
+            data: [0; 8],
+        };
+        frame.data.copy_from_slice(&buf[6..]);
+        Ok(frame)
+    }
+}
+
+/// SIDH, SIDL, EID8 and EID0 for `id`.
+fn id_bytes(id: Id) -> [u8; 4] {
+    match id {
+        Id::Standard(id) => {
+            let id = id.as_raw();
+            [(id >> 3) as u8, (id << 5) as u8, 0, 0]
+        }
+        Id::Extended(id) => {
+            let id = id.as_raw();
+            let sid = id >> 18;
+            [
+                (sid >> 3) as u8,
+                (sid << 5) as u8 | EXIDE | (id >> 16) as u8 & 0x03,
+                (id >> 8) as u8,
+                id as u8,
+            ]
+        }
+    }
+}
+
+/// CNF3, CNF2 and CNF1 for `bitrate` from a `crystal` in Hz, if it divides
+/// into 8 to 20 time quanta a bit, sampling about three quarters of the way
+/// through.
+fn timing(crystal: u32, bitrate: u32) -> Option<[u8; 3]> {
+    if bitrate == 0 {
+        return None;
+    }
+    // Most quanta a bit, for the finest sample point
+    let (brp, quanta) = (8..=20u32).rev().find_map(|quanta| {
+        let divisor = 2 * quanta * bitrate;
+        let brp = crystal / divisor;
+        (crystal.is_multiple_of(divisor) && (1..=64).contains(&brp)).then_some((brp, quanta))
+    })?;
+    // One quantum of sync, then propagation and phase 1, then phase 2
+    let ps2 = (quanta / 4).max(2);
+    let prseg = (quanta - 1 - ps2) / 2;
+    let ps1 = quanta - 1 - ps2 - prseg;
+    // SJW of one quantum; BTLMODE, so PS2 comes from CNF3
+    let cnf1 = (brp - 1) as u8;
+    let cnf2 = 0x80 | ((ps1 - 1) as u8) << 3 | (prseg - 1) as u8;
+    let cnf3 = (ps2 - 1) as u8;
+    Some([cnf3, cnf2, cnf1])
+}
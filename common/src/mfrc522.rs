@@ -0,0 +1,306 @@
+//! Driver for the NXP MFRC522 13.56 MHz RFID reader, as on the common RC522
+//! modules, over SPI.
+//!
+//! It does just enough of ISO 14443A to read the UID of a MIFARE card or tag:
+//! wake it, run the anticollision loop through each cascade level, and put it
+//! back to sleep. [`Mfrc522::read_uid`] returns the same UID on every call for
+//! as long as the card stays in the field, so callers that act once per tap
+//! have to debounce it themselves.
+//!
+//! ```ignore
+//! let mut reader = Mfrc522::new(spi, cs)?;
+//! if let Some(uid) = reader.read_uid()? {
+//!     // uid.as_bytes() is 4, 7 or 10 bytes long
+//! }
+//! ```
+//!
+//! The bus must be SPI mode 0, at up to 10 MHz. Cards that answer at the
+//! same time collide and read as [`Error::Collision`]; with a single card at
+//! the reader this doesn't happen.
+
+use core::convert::Infallible;
+
+use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::digital::v2::OutputPin;
+
+// Registers
+const COMMAND: u8 = 0x01;
+const COM_IRQ: u8 = 0x04;
+const DIV_IRQ: u8 = 0x05;
+const ERROR: u8 = 0x06;
+const FIFO_DATA: u8 = 0x09;
+const FIFO_LEVEL: u8 = 0x0A;
+const BIT_FRAMING: u8 = 0x0D;
+const COLL: u8 = 0x0E;
+const MODE: u8 = 0x11;
+const TX_CONTROL: u8 = 0x14;
+const TX_ASK: u8 = 0x15;
+const CRC_RESULT_H: u8 = 0x21;
+const CRC_RESULT_L: u8 = 0x22;
+const T_MODE: u8 = 0x2A;
+const T_PRESCALER: u8 = 0x2B;
+const T_RELOAD_H: u8 = 0x2C;
+const T_RELOAD_L: u8 = 0x2D;
+const VERSION: u8 = 0x37;
+
+// Commands
+const IDLE: u8 = 0x00;
+const CALC_CRC: u8 = 0x03;
+const TRANSCEIVE: u8 = 0x0C;
+const SOFT_RESET: u8 = 0x0F;
+
+// Card commands
+const WUPA: u8 = 0x52;
+const HLTA: u8 = 0x50;
+const SELECT: [u8; 3] = [0x93, 0x95, 0x97];
+const CASCADE_TAG: u8 = 0x88;
+
+/// Register polls before giving up on the reader. Its own timer ends every
+/// exchange after 25 ms, well before this runs out.
+const POLLS: u32 = 10_000;
+
+/// Errors from the reader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The SPI bus failed.
+    Spi(E),
+    /// The reader didn't finish, or nothing answered.
+    Timeout,
+    /// Two or more cards answered at once.
+    Collision,
+    /// An answer failed its parity, BCC or CRC check.
+    Corrupt,
+    /// An answer was the wrong length.
+    Protocol,
+}
+
+/// A card's unique identifier, 4, 7 or 10 bytes long.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Uid {
+    bytes: [u8; 10],
+    len: u8,
+}
+
+impl Uid {
+    /// The UID, first byte first.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len)]
+    }
+}
+
+/// An MFRC522 on an SPI bus, with its own chip select.
+pub struct Mfrc522<SPI, CS> {
+    spi: SPI,
+    cs: CS,
+}
+
+impl<SPI, CS, E> Mfrc522<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E>,
+    CS: OutputPin<Error = Infallible>,
+{
+    /// Reset the reader and turn its antenna on.
+    pub fn new(spi: SPI, mut cs: CS) -> Result<Self, Error<E>> {
+        let _ = cs.set_high();
+        let mut reader = Mfrc522 { spi, cs };
+        reader.write(COMMAND, SOFT_RESET)?;
+        // The reset clears the power down bit once the oscillator runs
+        let mut polls = 0;
+        while reader.read(COMMAND)? & 0x10 != 0 {
+            polls += 1;
+            if polls == POLLS {
+                return Err(Error::Timeout);
+            }
+        }
+        // A 25 ms timeout on every exchange, started as each frame goes out:
+        // 13.56 MHz / (2 * 169 + 1) = 40 kHz, counting down from 1000
+        reader.write(T_MODE, 0x80)?;
+        reader.write(T_PRESCALER, 169)?;
+        reader.write(T_RELOAD_H, 0x03)?;
+        reader.write(T_RELOAD_L, 0xE8)?;
+        // 100% ASK modulation, and the CRC preset 0x6363 of ISO 14443A
+        reader.write(TX_ASK, 0x40)?;
+        reader.write(MODE, 0x3D)?;
+        reader.set_bits(TX_CONTROL, 0x03)?;
+        Ok(reader)
+    }
+
+    /// The chip's version register: 0x91 or 0x92 for a genuine MFRC522,
+    /// other values for clones. Reads 0x00 or 0xFF if the reader isn't
+    /// connected.
+    pub fn version(&mut self) -> Result<u8, Error<E>> {
+        self.read(VERSION)
+    }
+
+    /// Read the UID of the card in the field, if there is one, and put the
+    /// card to sleep until the next call wakes it.
+    pub fn read_uid(&mut self) -> Result<Option<Uid>, Error<E>> {
+        let mut atqa = [0; 2];
+        // Seven bit short frame
+        match self.transceive(&[WUPA], 7, &mut atqa) {
+            Ok(2) => {}
+            Ok(_) => return Err(Error::Protocol),
+            Err(Error::Timeout) => return Ok(None),
+            Err(error) => return Err(error),
+        }
+
+        let mut uid = Uid {
+            bytes: [0; 10],
+            len: 0,
+        };
+        for &level in SELECT.iter() {
+            // Anticollision: the card answers with four UID bytes and their
+            // BCC
+            let mut answer = [0; 5];
+            self.clear_bits(COLL, 0x80)?;
+            if self.transceive(&[level, 0x20], 0, &mut answer)? != 5 {
+                return Err(Error::Protocol);
+            }
+            if answer.iter().fold(0, |bcc, byte| bcc ^ byte) != 0 {
+                return Err(Error::Corrupt);
+            }
+
+            // Select those bytes, and the card says if there are more
+            let mut frame = [level, 0x70, 0, 0, 0, 0, 0, 0, 0];
+            frame[2..7].copy_from_slice(&answer);
+            let crc = self.crc(&frame[..7])?;
+            frame[7..].copy_from_slice(&crc);
+            let mut sak = [0; 3];
+            if self.transceive(&frame, 0, &mut sak)? != 3 {
+                return Err(Error::Protocol);
+            }
+            if self.crc(&sak[..1])? != [sak[1], sak[2]] {
+                return Err(Error::Corrupt);
+            }
+
+            let more = sak[0] & 0x04 != 0;
+            // A cascade tag stands in for the first byte until the last level
+            let bytes = if more && answer[0] == CASCADE_TAG {
+                &answer[1..4]
+            } else {
+                &answer[..4]
+            };
+            let len = usize::from(uid.len);
+            uid.bytes[len..len + bytes.len()].copy_from_slice(bytes);
+            uid.len += bytes.len() as u8;
+            if !more {
+                break;
+            }
+        }
+
+        // The card doesn't answer a halt, so the timeout is expected
+        let mut hlta = [HLTA, 0, 0, 0];
+        let crc = self.crc(&hlta[..2])?;
+        hlta[2..].copy_from_slice(&crc);
+        match self.transceive(&hlta, 0, &mut []) {
+            Ok(_) | Err(Error::Timeout) => {}
+            Err(error) => return Err(error),
+        }
+        Ok(Some(uid))
+    }
+
+    /// Return the SPI bus and chip select.
+    pub fn free(self) -> (SPI, CS) {
+        (self.spi, self.cs)
+    }
+
+    /// Send `data`, whose last byte has `last_bits` bits (0 for all eight),
+    /// and receive the answer into `answer`, returning its length.
+    fn transceive(
+        &mut self,
+        data: &[u8],
+        last_bits: u8,
+        answer: &mut [u8],
+    ) -> Result<usize, Error<E>> {
+        self.write(COMMAND, IDLE)?;
+        self.write(COM_IRQ, 0x7F)?;
+        self.write(FIFO_LEVEL, 0x80)?;
+        for &byte in data {
+            self.write(FIFO_DATA, byte)?;
+        }
+        self.write(BIT_FRAMING, last_bits)?;
+        self.write(COMMAND, TRANSCEIVE)?;
+        self.set_bits(BIT_FRAMING, 0x80)?;
+
+        // Wait for the answer, or the timer
+        let mut polls = 0;
+        let irq = loop {
+            let irq = self.read(COM_IRQ)?;
+            if irq & 0x31 != 0 {
+                break irq;
+            }
+            polls += 1;
+            if polls == POLLS {
+                break 0x01;
+            }
+        };
+        self.clear_bits(BIT_FRAMING, 0x80)?;
+        if irq & 0x30 == 0 {
+            return Err(Error::Timeout);
+        }
+
+        let error = self.read(ERROR)?;
+        if error & 0x08 != 0 {
+            return Err(Error::Collision);
+        }
+        if error & 0x13 != 0 {
+            return Err(Error::Corrupt);
+        }
+        let len = usize::from(self.read(FIFO_LEVEL)?);
+        if len > answer.len() {
+            return Err(Error::Protocol);
+        }
+        for slot in answer[..len].iter_mut() {
+            *slot = self.read(FIFO_DATA)?;
+        }
+        Ok(len)
+    }
+
+    /// The ISO 14443A CRC of `data`, low byte first, worked out by the
+    /// reader.
+    fn crc(&mut self, data: &[u8]) -> Result<[u8; 2], Error<E>> {
+        self.write(COMMAND, IDLE)?;
+        self.write(DIV_IRQ, 0x04)?;
+        self.write(FIFO_LEVEL, 0x80)?;
+        for &byte in data {
+            self.write(FIFO_DATA, byte)?;
+        }
+        self.write(COMMAND, CALC_CRC)?;
+        let mut polls = 0;
+        while self.read(DIV_IRQ)? & 0x04 == 0 {
+            polls += 1;
+            if polls == POLLS {
+                return Err(Error::Timeout);
+            }
+        }
+        self.write(COMMAND, IDLE)?;
+        Ok([self.read(CRC_RESULT_L)?, self.read(CRC_RESULT_H)?])
+    }
+
+    fn read(&mut self, register: u8) -> Result<u8, Error<E>> {
+        let mut buf = [0x80 | register << 1, 0];
+        self.exchange(&mut buf)?;
+        Ok(buf[1])
+    }
+
+    fn write(&mut self, register: u8, value: u8) -> Result<(), Error<E>> {
+        self.exchange(&mut [register << 1, value])
+    }
+
+    fn set_bits(&mut self, register: u8, bits: u8) -> Result<(), Error<E>> {
+        let value = self.read(register)?;
+        self.write(register, value | bits)
+    }
+
+    fn clear_bits(&mut self, register: u8, bits: u8) -> Result<(), Error<E>> {
+        let value = self.read(register)?;
+        self.write(register, value & !bits)
+    }
+
+    fn exchange(&mut self, buf: &mut [u8]) -> Result<(), Error<E>> {
+        let _ = self.cs.set_low();
+        let result = self.spi.transfer(buf).map(|_| ()).map_err(Error::Spi);
+        let _ = self.cs.set_high();
+        result
+    }
+}
@@ -0,0 +1,509 @@
+//! A small MQTT 3.1.1 client over any `embedded-nal` TCP stack.
+//!
+//! [`Client`] publishes and receives at QoS 0, which is all a sensor node
+//! usually needs: readings go out as they come, and a lost one is replaced by
+//! the next. It keeps its own send and receive buffers of `N` bytes, so one
+//! packet, topic and payload together, has to fit in each.
+//!
+//! The stack is passed to every call rather than owned, so that the same
+//! stack can serve other sockets too:
+//!
+//! ```ignore
+//! let mut client: Client<_, 256> = Client::new(broker, "pico");
+//! loop {
+//!     let now = timer.get_counter().ticks();
+//!     match client.connect(&mut stack, now) {
+//!         Ok(()) => {}
+//!         Err(nb::Error::WouldBlock) => continue,
+//!         Err(nb::Error::Other(_)) => { /* wait a while, then try again */ }
+//!     }
+//!     client.subscribe(&mut stack, "led")?;
+//!     while client.is_connected() {
+//!         client.poll(&mut stack, timer.get_counter().ticks(), |topic, payload| {
+//!             // ...
+//!         })?;
+//!     }
+//! }
+//! ```
+//!
+//! Any error closes the connection, and [`Client::is_connected`] goes false;
+//! [`Client::connect`] starts again from scratch, with a clean session, so
+//! subscriptions have to be made again each time.
+
+use core::convert::TryFrom;
+
+use embedded_nal::{SocketAddr, TcpClientStack};
+
+/// How long to keep the connection alive without traffic, unless
+/// [`Client::set_keep_alive`] says otherwise, in seconds.
+pub const DEFAULT_KEEP_ALIVE_S: u16 = 60;
+
+/// How long the broker has to accept a connection, in microseconds.
+const CONNACK_TIMEOUT_US: u64 = 5_000_000;
+
+// Packet types, in the top four bits of the first byte
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+const PINGREQ: u8 = 0xC0;
+const PINGRESP: u8 = 0xD0;
+const DISCONNECT: u8 = 0xE0;
+
+// CONNECT flags
+const CLEAN_SESSION: u8 = 0x02;
+const PASSWORD: u8 = 0x40;
+const USERNAME: u8 = 0x80;
+
+/// The longest fixed header: a type byte and four bytes of length.
+const HEADER_LEN: usize = 5;
+
+/// Errors from the network and the broker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The TCP stack failed, or the broker closed the connection.
+    Network(E),
+    /// Not connected; call [`Client::connect`].
+    NotConnected,
+    /// The broker turned the connection down, with the CONNACK return code:
+    /// 4 for a bad username or password, 5 for not authorised.
+    Refused(u8),
+    /// The broker turned a subscription down.
+    SubscribeFailed,
+    /// The broker didn't answer a CONNECT or a PINGREQ in time.
+    Timeout,
+    /// A packet too long for the client's buffers.
+    TooLong,
+    /// Something the broker sent wasn't MQTT.
+    Protocol,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Disconnected,
+    /// Waiting for the TCP connection.
+    Opening,
+    /// CONNECT sent at the given time, waiting for CONNACK.
+    Handshake(u64),
+    Connected,
+}
+
+/// An MQTT client for the TCP stack `S`, with buffers of `N` bytes.
+pub struct Client<'a, S: TcpClientStack, const N: usize> {
+    broker: SocketAddr,
+    client_id: &'a str,
+    credentials: Option<(&'a str, &'a str)>,
+    keep_alive_s: u16,
+    socket: Option<S::TcpSocket>,
+    state: State,
+    /// When a packet last went out, as far as `poll` knows, in microseconds.
+    last_sent_us: u64,
+    /// Whether a packet has gone out since the last `poll`.
+    sent: bool,
+    /// When an unanswered PINGREQ went out.
+    ping_sent_us: Option<u64>,
+    next_packet_id: u16,
+    tx: [u8; N],
+    rx: [u8; N],
+    rx_len: usize,
+}
+
+impl<'a, S: TcpClientStack, const N: usize> Client<'a, S, N> {
+    /// A client for the broker at `broker`, usually port 1883, calling
+    /// itself `client_id`, which must be unique on the broker.
+    pub fn new(broker: SocketAddr, client_id: &'a str) -> Self {
+        Client {
+            broker,
+            client_id,
+            credentials: None,
+            keep_alive_s: DEFAULT_KEEP_ALIVE_S,
+            socket: None,
+            state: State::Disconnected,
+            last_sent_us: 0,
+            sent: false,
+            ping_sent_us: None,
+            next_packet_id: 1,
+            tx: [0; N],
+            rx: [0; N],
+            rx_len: 0,
+        }
+    }
+
+    /// Log in with `username` and `password` from the next connection on.
+    pub fn set_credentials(&mut self, username: &'a str, password: &'a str) {
+        self.credentials = Some((username, password));
+    }
+
+    /// Set how long the connection stays up without traffic, in seconds,
+    /// from the next connection on. [`Client::poll`] pings the broker
+    /// whenever nothing has been sent for this long.
+    pub fn set_keep_alive(&mut self, seconds: u16) {
+        self.keep_alive_s = seconds;
+    }
+
+    /// Whether the broker has accepted the connection.
+    pub fn is_connected(&self) -> bool {
+        self.state == State::Connected
+    }
+
+    /// Connect to the broker, opening the TCP connection and logging in.
+    ///
+    /// Call again with the time, in microseconds, until it stops returning
+    /// `WouldBlock`; it returns `Ok` straight away when already connected.
+    pub fn connect(&mut self, stack: &mut S, now_us: u64) -> nb::Result<(), Error<S::Error>> {
+        let result = self.advance(stack, now_us);
+        if let Err(nb::Error::Other(_)) = result {
+            self.close(stack);
+        }
+        result
+    }
+
+    /// Ask the broker for messages on `topic`, which may have `+` and `#`
+    /// wildcards. They arrive through [`Client::poll`].
+    pub fn subscribe(&mut self, stack: &mut S, topic: &str) -> Result<(), Error<S::Error>> {
+        if self.state != State::Connected {
+            return Err(Error::NotConnected);
+        }
+        let packet_id = self.packet_id();
+        let result = self.send_packet(stack, SUBSCRIBE, |packet| {
+            packet.u16(packet_id)?;
+            packet.string(topic)?;
+            // QoS 0
+            packet.byte(0)
+        });
+        self.check(stack, result)
+    }
+
+    /// Publish `payload` on `topic`, at QoS 0. A `retain`ed message is kept
+    /// by the broker for whoever subscribes next.
+    pub fn publish(
+        &mut self,
+        stack: &mut S,
+        topic: &str,
+        payload: &[u8],
+        retain: bool,
+    ) -> Result<(), Error<S::Error>> {
+        if self.state != State::Connected {
+            return Err(Error::NotConnected);
+        }
+        let result = self.send_packet(stack, PUBLISH | u8::from(retain), |packet| {
+            packet.string(topic)?;
+            packet.bytes(payload)
+        });
+        self.check(stack, result)
+    }
+
+    /// Handle whatever the broker has sent, handing each message to
+    /// `on_message` with its topic and payload, and keep the connection
+    /// alive. Call it often, with the time in microseconds.
+    pub fn poll<F>(
+        &mut self,
+        stack: &mut S,
+        now_us: u64,
+        mut on_message: F,
+    ) -> Result<(), Error<S::Error>>
+    where
+        F: FnMut(&str, &[u8]),
+    {
+        if self.state != State::Connected {
+            return Err(Error::NotConnected);
+        }
+        let result = self.service(stack, now_us, &mut on_message);
+        if result.is_err() {
+            self.close(stack);
+        }
+        result
+    }
+
+    /// Close the connection if `result` is an error, unless it was only a
+    /// packet too long to send.
+    fn check(
+        &mut self,
+        stack: &mut S,
+        result: Result<(), Error<S::Error>>,
+    ) -> Result<(), Error<S::Error>> {
+        match result {
+            Err(Error::TooLong) | Ok(()) => {}
+            Err(_) => self.close(stack),
+        }
+        result
+    }
+
+    /// Say goodbye to the broker and close the connection.
+    pub fn disconnect(&mut self, stack: &mut S) {
+        if self.state == State::Connected {
+            let _ = self.send_packet(stack, DISCONNECT, |_| Some(()));
+        }
+        self.close(stack);
+    }
+
+    /// The next step of connecting.
+    fn advance(&mut self, stack: &mut S, now_us: u64) -> nb::Result<(), Error<S::Error>> {
+        if self.state == State::Disconnected {
+            self.socket = Some(stack.socket().map_err(Error::Network)?);
+            self.state = State::Opening;
+        }
+        if self.state == State::Opening {
+            let socket = self.socket.as_mut().ok_or(Error::NotConnected)?;
+            stack
+                .connect(socket, self.broker)
+                .map_err(|error| error.map(Error::Network))?;
+            self.send_connect(stack)?;
+            self.state = State::Handshake(now_us);
+        }
+        if let State::Handshake(since_us) = self.state {
+            if let Err(nb::Error::Other(error)) = self.receive(stack, &mut |_, _| {}) {
+                return Err(nb::Error::Other(error));
+            }
+            if self.state == State::Connected {
+                self.last_sent_us = now_us;
+                self.sent = false;
+            } else {
+                if now_us.saturating_sub(since_us) > CONNACK_TIMEOUT_US {
+                    return Err(nb::Error::Other(Error::Timeout));
+                }
+                return Err(nb::Error::WouldBlock);
+            }
+        }
+        Ok(())
+    }
+
+    fn send_connect(&mut self, stack: &mut S) -> Result<(), Error<S::Error>> {
+        let client_id = self.client_id;
+        let credentials = self.credentials;
+        let keep_alive_s = self.keep_alive_s;
+        let flags = match credentials {
+            Some(_) => CLEAN_SESSION | USERNAME | PASSWORD,
+            None => CLEAN_SESSION,
+        };
+        self.send_packet(stack, CONNECT, |packet| {
+            packet.string("MQTT")?;
+            // Protocol level 4 is MQTT 3.1.1
+            packet.byte(4)?;
+            packet.byte(flags)?;
+            packet.u16(keep_alive_s)?;
+            packet.string(client_id)?;
+            if let Some((username, password)) = credentials {
+                packet.string(username)?;
+                packet.string(password)?;
+            }
+            Some(())
+        })
+    }
+
+    /// Handle incoming packets and ping the broker when it is time.
+    fn service(
+        &mut self,
+        stack: &mut S,
+        now_us: u64,
+        on_message: &mut dyn FnMut(&str, &[u8]),
+    ) -> Result<(), Error<S::Error>> {
+        match self.receive(stack, on_message) {
+            Ok(()) | Err(nb::Error::WouldBlock) => {}
+            Err(nb::Error::Other(error)) => return Err(error),
+        }
+        // `send_packet` doesn't know the time, so stamp it here
+        if core::mem::take(&mut self.sent) {
+            self.last_sent_us = now_us;
+        }
+        let keep_alive_us = u64::from(self.keep_alive_s) * 1_000_000;
+        if keep_alive_us == 0 {
+            return Ok(());
+        }
+        match self.ping_sent_us {
+            Some(sent_us) if now_us.saturating_sub(sent_us) > keep_alive_us => Err(Error::Timeout),
+            None if now_us.saturating_sub(self.last_sent_us) >= keep_alive_us => {
+                self.send_packet(stack, PINGREQ, |_| Some(()))?;
+                self.sent = false;
+                self.last_sent_us = now_us;
+                self.ping_sent_us = Some(now_us);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Read what the broker has sent, and handle every whole packet in it.
+    fn receive(
+        &mut self,
+        stack: &mut S,
+        on_message: &mut dyn FnMut(&str, &[u8]),
+    ) -> nb::Result<(), Error<S::Error>> {
+        let socket = self.socket.as_mut().ok_or(Error::NotConnected)?;
+        if self.rx_len == N {
+            return Err(nb::Error::Other(Error::TooLong));
+        }
+        let count = stack
+            .receive(socket, &mut self.rx[self.rx_len..])
+            .map_err(|error| error.map(Error::Network))?;
+        self.rx_len += count;
+
+        while let Some((header_len, body_len)) = packet_len(&self.rx[..self.rx_len])? {
+            let end = header_len + body_len;
+            if end > N {
+                return Err(nb::Error::Other(Error::TooLong));
+            }
+            if end > self.rx_len {
+                break;
+            }
+            let kind = self.rx[0];
+            self.handle(kind, header_len, end, on_message)?;
+            self.rx.copy_within(end..self.rx_len, 0);
+            self.rx_len -= end;
+        }
+        Ok(())
+    }
+
+    /// Handle the packet of type `kind` whose body is `rx[start..end]`.
+    fn handle(
+        &mut self,
+        kind: u8,
+        start: usize,
+        end: usize,
+        on_message: &mut dyn FnMut(&str, &[u8]),
+    ) -> Result<(), Error<S::Error>> {
+        let body = &self.rx[start..end];
+        match kind & 0xF0 {
+            CONNACK => match body.get(1) {
+                Some(0) => {
+                    self.state = State::Connected;
+                    self.ping_sent_us = None;
+                }
+                Some(&code) => return Err(Error::Refused(code)),
+                None => return Err(Error::Protocol),
+            },
+            PUBLISH => {
+                let topic_len = usize::from(u16::from_be_bytes([
+                    *body.first().ok_or(Error::Protocol)?,
+                    *body.get(1).ok_or(Error::Protocol)?,
+                ]));
+                let topic = body.get(2..2 + topic_len).ok_or(Error::Protocol)?;
+                let topic = core::str::from_utf8(topic).map_err(|_| Error::Protocol)?;
+                // Messages above QoS 0 carry a packet identifier; the
+                // subscriptions ask for QoS 0, so brokers don't send them
+                let payload_start = match kind & 0x06 {
+                    0 => 2 + topic_len,
+                    _ => 4 + topic_len,
+                };
+                let payload = body.get(payload_start..).ok_or(Error::Protocol)?;
+                on_message(topic, payload);
+            }
+            SUBACK if body.get(2) == Some(&0x80) => {
+                return Err(Error::SubscribeFailed);
+            }
+            PINGRESP => self.ping_sent_us = None,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Build a packet of type `kind` with `body` and send it.
+    fn send_packet<F>(&mut self, stack: &mut S, kind: u8, body: F) -> Result<(), Error<S::Error>>
+    where
+        F: FnOnce(&mut Writer) -> Option<()>,
+    {
+        let socket = self.socket.as_mut().ok_or(Error::NotConnected)?;
+        let (header, rest) = self.tx.split_at_mut(HEADER_LEN);
+        let mut writer = Writer { buf: rest, len: 0 };
+        body(&mut writer).ok_or(Error::TooLong)?;
+        let body_len = writer.len;
+
+        // The length goes seven bits at a time, low first, right before the
+        // body
+        let mut length = [0; 4];
+        let mut length_len = 0;
+        let mut remaining = body_len;
+        loop {
+            let mut byte = (remaining % 128) as u8;
+            remaining /= 128;
+            if remaining > 0 {
+                byte |= 0x80;
+            }
+            length[length_len] = byte;
+            length_len += 1;
+            if remaining == 0 || length_len == length.len() {
+                break;
+            }
+        }
+        let start = header.len() - 1 - length_len;
+        header[start] = kind;
+        header[start + 1..].copy_from_slice(&length[..length_len]);
+
+        let mut packet = &self.tx[start..HEADER_LEN + body_len];
+        while !packet.is_empty() {
+            match stack.send(socket, packet) {
+                Ok(count) => packet = &packet[count..],
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(error)) => return Err(Error::Network(error)),
+            }
+        }
+        self.sent = true;
+        Ok(())
+    }
+
+    fn packet_id(&mut self) -> u16 {
+        let id = self.next_packet_id;
+        // Packet identifiers can't be 0
+        self.next_packet_id = self.next_packet_id.checked_add(1).unwrap_or(1);
+        id
+    }
+
+    /// Drop the connection and everything half received.
+    fn close(&mut self, stack: &mut S) {
+        if let Some(socket) = self.socket.take() {
+            let _ = stack.close(socket);
+        }
+        self.state = State::Disconnected;
+        self.ping_sent_us = None;
+        self.rx_len = 0;
+    }
+}
+
+/// The length of the fixed header and of the body of the packet starting
+/// `buf`, once enough of it has arrived to tell.
+fn packet_len<E>(buf: &[u8]) -> Result<Option<(usize, usize)>, Error<E>> {
+    let mut body_len = 0;
+    for (index, &byte) in buf.iter().enumerate().skip(1).take(4) {
+        body_len |= usize::from(byte & 0x7F) << (7 * (index - 1));
+        if byte & 0x80 == 0 {
+            return Ok(Some((index + 1, body_len)));
+        }
+    }
+    if buf.len() >= HEADER_LEN {
+        Err(Error::Protocol)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Writes a packet body into a buffer, giving `None` when it is full.
+struct Writer<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn bytes(&mut self, bytes: &[u8]) -> Option<()> {
+        self.buf
+            .get_mut(self.len..self.len + bytes.len())?
+            .copy_from_slice(bytes);
+        self.len += bytes.len();
+        Some(())
+    }
+
+    fn byte(&mut self, byte: u8) -> Option<()> {
+        self.bytes(&[byte])
+    }
+
+    fn u16(&mut self, value: u16) -> Option<()> {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    /// A string, after its length.
+    fn string(&mut self, string: &str) -> Option<()> {
+        let len = u16::try_from(string.len()).ok()?;
+        self.u16(len)?;
+        self.bytes(string.as_bytes())
+    }
+}
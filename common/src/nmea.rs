@@ -0,0 +1,394 @@
+//! Parsing the NMEA 0183 sentences sent by GPS receivers.
+//!
+//! Bytes from the receiver's serial port go to [`Parser::push`] one at a
+//! time. Each complete line with a good checksum comes back as a
+//! [`Sentence`]; only RMC (position, speed, date) and GGA (fix quality,
+//! satellites, altitude) are decoded, which is all most loggers need:
+//!
+//! ```ignore
+//! let mut parser = Parser::new();
+//! while let Ok(byte) = uart.read() {
+//!     if let Some(Sentence::Rmc(rmc)) = parser.push(byte) {
+//!         // rmc.latitude, rmc.longitude ...
+//!     }
+//! }
+//! ```
+//!
+//! Any talker is accepted, so `$GPRMC`, `$GNRMC` and `$GLRMC` all parse.
+//! Numbers are kept in fixed point, as the RP2040 has no FPU: coordinates in
+//! ten-millionths of a degree, which is about a centimetre.
+
+/// Longest sentence the standard allows, without the line ending.
+const MAX_LEN: usize = 82;
+
+/// UTC time of day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millisecond: u16,
+}
+
+/// UTC date.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// Recommended minimum data: where, how fast and when.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rmc {
+    pub time: Option<Time>,
+    /// Whether the receiver has a fix. Without one the other fields are
+    /// stale or missing.
+    pub valid: bool,
+    /// Ten-millionths of a degree, north positive.
+    pub latitude: Option<i32>,
+    /// Ten-millionths of a degree, east positive.
+    pub longitude: Option<i32>,
+    /// Speed over the ground in hundredths of a knot.
+    pub speed: Option<u32>,
+    /// Track over the ground in hundredths of a degree from true north.
+    pub course: Option<u32>,
+    pub date: Option<Date>,
+}
+
+/// Fix data: how good the position is, and the altitude.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gga {
+    pub time: Option<Time>,
+    pub latitude: Option<i32>,
+    pub longitude: Option<i32>,
+    /// 0 for no fix, 1 for GPS, 2 for differential GPS, and so on.
+    pub quality: u8,
+    /// Satellites used in the fix.
+    pub satellites: u8,
+    /// Horizontal dilution of precision, in tenths.
+    pub hdop: Option<u16>,
+    /// Altitude above mean sea level, in decimetres.
+    pub altitude: Option<i32>,
+}
+
+/// A decoded sentence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sentence {
+    Rmc(Rmc),
+    Gga(Gga),
+    /// A well-formed sentence of another type, such as GSV or VTG.
+    Other,
+}
+
+/// Collects bytes into sentences.
+pub struct Parser {
+    line: [u8; MAX_LEN],
+    len: usize,
+    /// Whether a `$` has been seen since the last sentence, and the line
+    /// hasn't outgrown the buffer.
+    active: bool,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    /// A parser waiting for the start of a sentence.
+    pub const fn new() -> Self {
+        Parser {
+            line: [0; MAX_LEN],
+            len: 0,
+            active: false,
+        }
+    }
+
+    /// Feed one byte, returning the sentence it completes, if any.
+    ///
+    /// Sentences with a bad checksum, or none, are dropped.
+    pub fn push(&mut self, byte: u8) -> Option<Sentence> {
+        match byte {
+            b'$' => {
+                self.active = true;
+                self.len = 0;
+                None
+            }
+            b'\r' | b'\n' => {
+                let complete = self.active;
+                self.active = false;
+                if complete {
+                    parse(&self.line[..self.len])
+                } else {
+                    None
+                }
+            }
+            _ if self.active => {
+                if self.len == MAX_LEN {
+                    self.active = false;
+                } else {
+                    self.line[self.len] = byte;
+                    self.len += 1;
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parse the text between `$` and the line ending.
+pub fn parse(line: &[u8]) -> Option<Sentence> {
+    let star = line.iter().rposition(|&byte| byte == b'*')?;
+    let (body, checksum) = (&line[..star], &line[star + 1..]);
+    let expected = u8::from_str_radix(core::str::from_utf8(checksum).ok()?, 16).ok()?;
+    if body.iter().fold(0, |sum, byte| sum ^ byte) != expected {
+        return None;
+    }
+    let body = core::str::from_utf8(body).ok()?;
+    let mut fields = body.split(',');
+    let kind = fields.next()?;
+    // Two letters of talker, then the type
+    match kind.get(2..)? {
+        "RMC" => parse_rmc(&mut fields).map(Sentence::Rmc),
+        "GGA" => parse_gga(&mut fields).map(Sentence::Gga),
+        _ => Some(Sentence::Other),
+    }
+}
+
+fn parse_rmc<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<Rmc> {
+    let time = parse_time(fields.next()?);
+    let valid = fields.next()? == "A";
+    let latitude = parse_coordinate(fields.next()?, fields.next()?, 2);
+    let longitude = parse_coordinate(fields.next()?, fields.next()?, 3);
+    let speed = decimal(fields.next()?, 2).map(|speed| speed as u32);
+    let course = decimal(fields.next()?, 2).map(|course| course as u32);
+    let date = parse_date(fields.next()?);
+    Some(Rmc {
+        time,
+        valid,
+        latitude,
+        longitude,
+        speed,
+        course,
+        date,
+    })
+}
+
+fn parse_gga<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<Gga> {
+    let time = parse_time(fields.next()?);
+    let latitude = parse_coordinate(fields.next()?, fields.next()?, 2);
+    let longitude = parse_coordinate(fields.next()?, fields.next()?, 3);
+    let quality = fields.next()?.parse().unwrap_or(0);
+    let satellites = fields.next()?.parse().unwrap_or(0);
+    let hdop = decimal(fields.next()?, 1).map(|hdop| hdop as u16);
+    let altitude = decimal(fields.next()?, 1);
+    Some(Gga {
+        time,
+        latitude,
+        longitude,
+        quality,
+        satellites,
+        hdop,
+        altitude,
+    })
+}
+
+/// `hhmmss.sss`
+fn parse_time(field: &str) -> Option<Time> {
+    let hour = field.get(0..2)?.parse().ok()?;
+    let minute = field.get(2..4)?.parse().ok()?;
+    let millis = decimal(field.get(4..)?, 3)?;
+    Some(Time {
+        hour,
+        minute,
+        second: (millis / 1000) as u8,
+        millisecond: (millis % 1000) as u16,
+    })
+}
+
+/// `ddmmyy`
+fn parse_date(field: &str) -> Option<Date> {
+    Some(Date {
+        day: field.get(0..2)?.parse().ok()?,
+        month: field.get(2..4)?.parse().ok()?,
+        year: 2000 + field.get(4..6)?.parse::<u16>().ok()?,
+    })
+}
+
+/// `ddmm.mmmm` or `dddmm.mmmm` and a hemisphere, to ten-millionths of a
+/// degree. Latitudes past 90 degrees, longitudes past 180 and minutes of 60
+/// or more are `None`.
+fn parse_coordinate(field: &str, hemisphere: &str, degree_digits: usize) -> Option<i32> {
+    let max_degrees = if degree_digits == 2 { 90 } else { 180 };
+    let degrees = field.get(..degree_digits)?;
+    if !degrees.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let degrees: i32 = degrees.parse().ok()?;
+    // In hundred-thousandths of a minute
+    let minutes = decimal(field.get(degree_digits..)?, 5)?;
+    if !(0..60 * 100_000).contains(&minutes) {
+        return None;
+    }
+    let value = degrees
+        .checked_mul(10_000_000)?
+        .checked_add(minutes.checked_mul(100)? / 60)?;
+    if value > max_degrees * 10_000_000 {
+        return None;
+    }
+    match hemisphere {
+        "N" | "E" => Some(value),
+        "S" | "W" => Some(-value),
+        _ => None,
+    }
+}
+
+/// A decimal number such as `-12.345`, times ten to the `places`, with any
+/// further digits dropped. Empty fields are `None`.
+fn decimal(field: &str, places: u32) -> Option<i32> {
+    let (negative, digits) = match field.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, field),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    let mut value: i32 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let mut fraction = fraction.bytes();
+    for _ in 0..places {
+        let digit = match fraction.next() {
+            Some(byte @ b'0'..=b'9') => i32::from(byte - b'0'),
+            Some(_) => return None,
+            None => 0,
+        };
+        value = value.checked_mul(10)?.checked_add(digit)?;
+    }
+    Some(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    /// `body` with its checksum appended.
+    fn sentence(body: &str) -> heapless::String<MAX_LEN> {
+        let checksum = body.bytes().fold(0, |sum, byte| sum ^ byte);
+        let mut line = heapless::String::new();
+        write!(line, "{}*{:02X}", body, checksum).unwrap();
+        line
+    }
+
+    #[test]
+    fn parses_rmc() {
+        let line = b"GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A";
+        let Some(Sentence::Rmc(rmc)) = parse(line) else {
+            panic!("not an RMC sentence");
+        };
+        assert_eq!(
+            rmc.time,
+            Some(Time {
+                hour: 12,
+                minute: 35,
+                second: 19,
+                millisecond: 0
+            })
+        );
+        assert!(rmc.valid);
+        assert_eq!(rmc.latitude, Some(481_173_000));
+        assert_eq!(rmc.longitude, Some(115_166_666));
+        assert_eq!(rmc.speed, Some(2240));
+        assert_eq!(rmc.course, Some(8440));
+        let date = rmc.date.unwrap();
+        assert_eq!((date.day, date.month), (23, 3));
+    }
+
+    #[test]
+    fn parses_gga() {
+        let line = b"GPGGA,123519,4807.038,N,01131.000,W,1,08,0.9,545.4,M,46.9,M,,*55";
+        let Some(Sentence::Gga(gga)) = parse(line) else {
+            panic!("not a GGA sentence");
+        };
+        assert_eq!(gga.latitude, Some(481_173_000));
+        assert_eq!(gga.longitude, Some(-115_166_666));
+        assert_eq!(gga.quality, 1);
+        assert_eq!(gga.satellites, 8);
+        assert_eq!(gga.hdop, Some(9));
+        assert_eq!(gga.altitude, Some(5454));
+    }
+
+    #[test]
+    fn other_sentences_are_passed_over() {
+        let line = sentence("GPGSV,1,1,00");
+        assert_eq!(parse(line.as_bytes()), Some(Sentence::Other));
+    }
+
+    #[test]
+    fn bad_or_missing_checksum_is_dropped() {
+        let line = b"GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6B";
+        assert_eq!(parse(line), None);
+        assert_eq!(parse(b"GPRMC,123519,A,4807.038,N"), None);
+        assert_eq!(parse(b"GPRMC,123519*ZZ"), None);
+    }
+
+    #[test]
+    fn empty_fields_are_none() {
+        let line = sentence("GNRMC,,V,,,,,,,,,,N");
+        let Some(Sentence::Rmc(rmc)) = parse(line.as_bytes()) else {
+            panic!("not an RMC sentence");
+        };
+        assert!(!rmc.valid);
+        assert_eq!(rmc.time, None);
+        assert_eq!(rmc.latitude, None);
+        assert_eq!(rmc.longitude, None);
+        assert_eq!(rmc.speed, None);
+        assert_eq!(rmc.date, None);
+    }
+
+    #[test]
+    fn out_of_range_coordinates_are_none() {
+        for (latitude, longitude) in [
+            ("9100.000", "01131.000"),
+            ("9000.001", "01131.000"),
+            ("4860.000", "01131.000"),
+            ("4807.038", "18100.000"),
+            ("4807.038", "01160.000"),
+            ("4807.038", "99999999999.000"),
+            ("-107.038", "01131.000"),
+            ("48-7.038", "01131.000"),
+        ] {
+            let mut body = heapless::String::<MAX_LEN>::new();
+            write!(
+                body,
+                "GPGGA,123519,{},N,{},E,1,08,0.9,545.4,M,46.9,M,,",
+                latitude, longitude
+            )
+            .unwrap();
+            let line = sentence(&body);
+            let Some(Sentence::Gga(gga)) = parse(line.as_bytes()) else {
+                panic!("not a GGA sentence");
+            };
+            assert!(
+                gga.latitude.is_none() || gga.longitude.is_none(),
+                "{} {}",
+                latitude,
+                longitude
+            );
+        }
+        // The poles and the antimeridian themselves are fine
+        let line = sentence("GPGGA,,9000.000,S,18000.000,W,1,08,,,M,,M,,");
+        let Some(Sentence::Gga(gga)) = parse(line.as_bytes()) else {
+            panic!("not a GGA sentence");
+        };
+        assert_eq!(gga.latitude, Some(-900_000_000));
+        assert_eq!(gga.longitude, Some(-1_800_000_000));
+    }
+}
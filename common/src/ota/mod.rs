@@ -0,0 +1,336 @@
+//! Firmware updates over a serial link, with an A/B pair of flash slots.
+//!
+//! Building with `OTA_SLOT_SIZE` set, in bytes, makes every BSP's build
+//! script split flash into two slots of that size, and two sectors for the
+//! swap, below the [`flash_storage`] region:
+//!
+//! ```text
+//! 0x10000000            slot A: boot2 and the running program
+//! + OTA_SLOT_SIZE       slot B: the next image, or the last one
+//! + 2 * OTA_SLOT_SIZE   scratch sector, then journal sector
+//! ...
+//! top - storage size    flash_storage region
+//! ```
+//!
+//! The link fails if the program is bigger than a slot. On a Pico, with
+//! 2 MiB of flash and the default 64 KiB of storage, slots can be up to
+//! 988 KiB:
+//!
+//! ```console
+//! $ OTA_SLOT_SIZE=1011712 cargo build --release --example pico_usb_update
+//! ```
+//!
+//! A [`Receiver`] takes an image a byte at a time, from a USB serial port or
+//! anything else, and writes it into slot B. The image is the whole of slot
+//! A, boot2 included, as made by `objcopy -O binary`, after a 12 byte header:
+//!
+//! ```text
+//! magic "RPUP" | length (u32) | CRC-32 of the image (u32)
+//! ```
+//!
+//! Once the CRC of what landed in flash matches, [`Image::apply`] swaps the
+//! two slots, a sector at a time, from RAM, and resets into the new
+//! firmware. Nothing else may run meanwhile: the other core must be stopped
+//! first. Each step of the swap is recorded in the journal sector, and if a
+//! reset or power loss cuts it short, the swap carries on from there at the
+//! next boot, before `main`. The code doing so is in the first sector of
+//! flash, which is swapped last; only a power loss in the few milliseconds
+//! that sector is being rewritten leaves the board in BOOTSEL mode, needing a
+//! reflash. To resume, the crate provides cortex-m-rt's `__pre_init`, so
+//! programs can't have a `#[pre_init]` function of their own.
+//!
+//! The old firmware stays in slot B, and the watchdog scratch registers
+//! carry the outcome across resets. Call [`check`] early in `main`. After an
+//! update it returns [`Status::Trial`], and the new firmware then has one
+//! boot to call [`confirm`]; if it resets again without doing so, say
+//! because the watchdog caught it hanging, [`check`] swaps the old firmware
+//! back and it starts with [`Status::RolledBack`]. A power cycle or the RUN
+//! pin clears the scratch registers, which keeps whichever firmware is in
+//! slot A.
+//!
+//! This uses watchdog scratch registers 0 and 1. The boot ROM uses 4 to 7.
+
+use crate::flash_storage::{self, PAGE_SIZE, SECTOR_SIZE, XIP_BASE};
+use crate::hal::pac;
+use crate::settings::crc32;
+
+mod swap;
+
+/// Starts every image sent to a [`Receiver`].
+pub const MAGIC: [u8; 4] = *b"RPUP";
+
+/// Magic, length and CRC.
+const HEADER_LEN: usize = 12;
+
+// Scratch register 0 through an update
+const TRIAL: u32 = 0x5550_0001;
+const UNCONFIRMED: u32 = 0x5550_0002;
+const ROLLED_BACK: u32 = 0x5550_0003;
+
+extern "C" {
+    // Set by `storage.x`: their addresses are the slot size and where the
+    // running program ends
+    static __ota_slot_size: u8;
+    static __ota_image_end: u8;
+}
+
+/// Errors from [`Receiver::feed`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The firmware was built without `OTA_SLOT_SIZE`.
+    NoSlot,
+    /// The header doesn't start with [`MAGIC`].
+    BadMagic,
+    /// The image is empty or bigger than a slot.
+    BadLength,
+    /// What was written to flash doesn't match the CRC in the header.
+    Crc,
+    /// The image doesn't start with boot2 and a vector table for slot A.
+    NotAnImage,
+}
+
+/// What [`check`] found at start-up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Nothing to report.
+    Normal,
+    /// Running new firmware for the first time; call [`confirm`] once it
+    /// works.
+    Trial,
+    /// The new firmware wasn't confirmed, and this, the firmware from before
+    /// the update, was put back.
+    RolledBack,
+}
+
+/// The size of each slot, or 0 if built without `OTA_SLOT_SIZE`.
+pub fn slot_size() -> u32 {
+    // Note (safety): only the symbol's address is taken
+    unsafe { &__ota_slot_size as *const u8 as u32 }
+}
+
+/// The size of the running program, from the start of flash.
+fn image_len() -> u32 {
+    // Note (safety): only the symbol's address is taken
+    unsafe { &__ota_image_end as *const u8 as u32 - XIP_BASE }
+}
+
+fn scratch(index: usize) -> u32 {
+    // Note (safety): a read of a register nothing else in this crate uses
+    let watchdog = unsafe { &*pac::WATCHDOG::ptr() };
+    match index {
+        0 => watchdog.scratch0.read().bits(),
+        _ => watchdog.scratch1.read().bits(),
+    }
+}
+
+fn set_scratch(index: usize, value: u32) {
+    // Note (safety): any value may be written to a scratch register
+    let watchdog = unsafe { &*pac::WATCHDOG::ptr() };
+    match index {
+        0 => watchdog.scratch0.write(|w| unsafe { w.bits(value) }),
+        _ => watchdog.scratch1.write(|w| unsafe { w.bits(value) }),
+    }
+}
+
+/// Stop the watchdog, which would otherwise reset the chip partway through a
+/// swap.
+fn stop_watchdog() {
+    // Note (safety): only the enable bit is changed
+    let watchdog = unsafe { &*pac::WATCHDOG::ptr() };
+    watchdog.ctrl.modify(|_, w| w.enable().clear_bit());
+}
+
+/// Follow up an update, rolling back if the new firmware didn't confirm
+/// itself. Call this early in `main`, before starting the other core.
+pub fn check() -> Status {
+    match scratch(0) {
+        TRIAL => {
+            set_scratch(0, UNCONFIRMED);
+            Status::Trial
+        }
+        UNCONFIRMED => {
+            // The same sectors as the update swapped, back again
+            swap_and_reset(scratch(1), ROLLED_BACK)
+        }
+        ROLLED_BACK => {
+            set_scratch(0, 0);
+            Status::RolledBack
+        }
+        _ => Status::Normal,
+    }
+}
+
+/// Swap the first `len` bytes of the slots, and reset with `status` in
+/// scratch register 0 and `len` in scratch register 1.
+fn swap_and_reset(len: u32, status: u32) -> ! {
+    stop_watchdog();
+    cortex_m::interrupt::disable();
+    swap::load();
+    // Note (safety): `load` has just put the swap in SRAM4, and the caller
+    // stopped the other core
+    unsafe { swap::swap(slot_size(), len, status) };
+    unreachable!("a swap ends in a reset")
+}
+
+/// Keep the firmware that [`check`] reported as [`Status::Trial`].
+pub fn confirm() {
+    if scratch(0) == UNCONFIRMED {
+        set_scratch(0, 0);
+    }
+}
+
+/// A verified image in slot B.
+#[derive(Debug)]
+pub struct Image {
+    len: u32,
+}
+
+impl Image {
+    /// The image's length, in bytes.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Whether the image is empty, which a verified one never is.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Swap the slots and reset into the new firmware.
+    ///
+    /// Only the sectors that either firmware uses are swapped, which takes
+    /// around 0.2 s per 4 KiB, with the watchdog stopped. The other core must
+    /// be stopped, and nothing may be left to do: this never returns.
+    pub fn apply(self) -> ! {
+        let len = self.len.max(image_len());
+        let len = len.div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+        swap_and_reset(len, TRIAL)
+    }
+}
+
+/// Writes an image into slot B as it arrives.
+pub struct Receiver {
+    header: [u8; HEADER_LEN],
+    /// Bytes of header, then of image, received.
+    received: usize,
+    len: u32,
+    crc: u32,
+    page: [u8; PAGE_SIZE],
+}
+
+impl Default for Receiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Receiver {
+    /// A receiver waiting for a header.
+    pub const fn new() -> Self {
+        Receiver {
+            header: [0; HEADER_LEN],
+            received: 0,
+            len: 0,
+            crc: 0,
+            page: [0xFF; PAGE_SIZE],
+        }
+    }
+
+    /// Start again, waiting for a header.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Whether a header has arrived and the image is being received.
+    pub fn is_receiving(&self) -> bool {
+        self.received >= HEADER_LEN
+    }
+
+    /// Take the next byte. Returns the image once it has all arrived and
+    /// checks out.
+    ///
+    /// After an error, or once the image is returned, the receiver waits for
+    /// a new header.
+    pub fn feed(&mut self, byte: u8) -> Result<Option<Image>, Error> {
+        let result = self.take(byte);
+        if !matches!(result, Ok(None)) {
+            self.reset();
+        }
+        result
+    }
+
+    fn take(&mut self, byte: u8) -> Result<Option<Image>, Error> {
+        if self.received < HEADER_LEN {
+            self.header[self.received] = byte;
+            self.received += 1;
+            if self.received == HEADER_LEN {
+                self.start()?;
+            }
+            return Ok(None);
+        }
+
+        let at = (self.received - HEADER_LEN) as u32;
+        self.page[at as usize % PAGE_SIZE] = byte;
+        self.received += 1;
+        let done = at + 1 == self.len;
+        if done || (at as usize + 1).is_multiple_of(PAGE_SIZE) {
+            let page_start = at - at % PAGE_SIZE as u32;
+            // Can't fail: `start` checked the image fits in the slot
+            let _ = flash_storage::program_slot_b(page_start, &self.page);
+            self.page = [0xFF; PAGE_SIZE];
+        }
+        if !done {
+            return Ok(None);
+        }
+
+        // Check what is in flash, not what was sent
+        // Note (safety): slot B is mapped at XIP_BASE, and the image fits
+        let written = unsafe {
+            core::slice::from_raw_parts((XIP_BASE + slot_size()) as *const u8, self.len as usize)
+        };
+        if crc32(written) != self.crc {
+            return Err(Error::Crc);
+        }
+        if !looks_like_image(written, slot_size()) {
+            return Err(Error::NotAnImage);
+        }
+        Ok(Some(Image { len: self.len }))
+    }
+
+    /// Check the header that has just arrived.
+    fn start(&mut self) -> Result<(), Error> {
+        if slot_size() == 0 {
+            return Err(Error::NoSlot);
+        }
+        let header = &self.header;
+        if header[0..4] != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        self.len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        self.crc = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        if self.len == 0 || self.len > slot_size() {
+            return Err(Error::BadLength);
+        }
+        Ok(())
+    }
+}
+
+/// Whether `image` has boot2 and, after it, a vector table whose stack
+/// pointer is in RAM and whose reset handler is in a slot of `slot_size`.
+fn looks_like_image(image: &[u8], slot_size: u32) -> bool {
+    const VECTORS: usize = 0x100;
+    const RAM: core::ops::Range<u32> = 0x2000_0000..0x2004_2001;
+
+    let word = |at: usize| {
+        image
+            .get(at..at + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    match (word(VECTORS), word(VECTORS + 4)) {
+        (Some(stack), Some(reset)) => {
+            RAM.contains(&stack)
+                && (XIP_BASE + VECTORS as u32..XIP_BASE + slot_size).contains(&reset)
+        }
+        _ => false,
+    }
+}
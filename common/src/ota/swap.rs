@@ -0,0 +1,405 @@
+//! The slot swap, which picks up where it left off after a reset or power
+//! loss.
+//!
+//! Two sectors after slot B take part: a scratch sector and a journal. Each
+//! sector of the image is swapped in three steps:
+//!
+//! 1. copy the slot A sector into the scratch sector;
+//! 2. copy the slot B sector over the slot A one;
+//! 3. copy the scratch sector over the slot B one.
+//!
+//! The source of each step stays intact until the step after it is done, so
+//! a step cut short can simply be done again. The journal's first page holds
+//! the length being swapped and the status to leave in scratch register 0
+//! afterwards, each followed by its complement; the pages after it hold a bit
+//! per step, cleared once the step is done.
+//!
+//! Swapping starts from the top of the image, so the first sector, with
+//! boot2, the vector table, the reset handler and `__pre_init` below, goes
+//! last. Until then every reset runs the old image's `__pre_init`, which
+//! loads [`swap`] into SRAM4 and lets it carry on from the journal before
+//! anything else runs. While the first sector itself is rewritten its pages
+//! go in backwards, so the boot ROM only finds a valid boot2 there once the
+//! rest of the sector is in place; a power loss in those few milliseconds
+//! leaves the board in BOOTSEL mode.
+//!
+//! Nothing here may call into flash: the code there is half one image and
+//! half the other partway through. [`swap`] runs from SRAM4, which cortex-m-rt
+//! leaves alone, buffers pages in SRAM5, and reads and writes memory with
+//! inline assembly, which, unlike `core::ptr::read_volatile` in a debug build,
+//! is never a function call.
+
+use crate::flash_storage::{PAGE_SIZE, SECTOR_SIZE, XIP_BASE};
+
+const MAGIC: u32 = u32::from_le_bytes(*b"RPSW");
+
+/// Pages are copied through here, the start of SRAM5.
+const BUFFER: u32 = 0x2004_1000;
+const PAGE_WORDS: u32 = PAGE_SIZE as u32 / 4;
+const SECTOR_WORDS: u32 = SECTOR_SIZE / 4;
+/// The journal holds 2048, 1 << 11, progress bits a page.
+const BITS_PER_PAGE_SHIFT: u32 = 11;
+
+const WATCHDOG_CTRL_CLR: u32 = 0x4005_8000 + 0x3000;
+const WATCHDOG_ENABLE: u32 = 1 << 30;
+const WATCHDOG_SCRATCH0: u32 = 0x4005_800c;
+const WATCHDOG_SCRATCH1: u32 = 0x4005_8010;
+const AIRCR: u32 = 0xE000_ED0C;
+const SYSRESETREQ: u32 = 0x05FA_0004;
+
+// Boot ROM function tags, see section 2.8.3 of the RP2040 datasheet
+const CONNECT_INTERNAL_FLASH: u32 = tag(b"IF");
+const FLASH_EXIT_XIP: u32 = tag(b"EX");
+const FLASH_RANGE_ERASE: u32 = tag(b"RE");
+const FLASH_RANGE_PROGRAM: u32 = tag(b"RP");
+const FLASH_FLUSH_CACHE: u32 = tag(b"FC");
+const FLASH_ENTER_CMD_XIP: u32 = tag(b"CX");
+
+const fn tag(code: &[u8; 2]) -> u32 {
+    code[0] as u32 | (code[1] as u32) << 8
+}
+
+// Called by cortex-m-rt's reset handler before RAM is set up. Loads `swap`
+// into SRAM4, and has it finish any swap the journal records, if built with
+// OTA slots.
+#[cfg(target_arch = "arm")]
+core::arch::global_asm!(
+    ".section .ota_boot, \"ax\"",
+    ".global __pre_init",
+    ".type __pre_init, %function",
+    ".thumb_func",
+    "__pre_init:",
+    "    ldr r0, =__ota_slot_size",
+    "    cmp r0, #0",
+    "    beq 2f",
+    "    push {{r4, lr}}",
+    "    ldr r1, =__ota_swap_load",
+    "    ldr r2, =__ota_swap_start",
+    "    ldr r3, =__ota_swap_end",
+    "0:  cmp r2, r3",
+    "    beq 1f",
+    "    ldm r1!, {{r4}}",
+    "    stm r2!, {{r4}}",
+    "    b 0b",
+    "1:  movs r1, #0",
+    "    movs r2, #0",
+    "    ldr r3, ={swap}",
+    "    blx r3",
+    "    pop {{r4, pc}}",
+    "2:  bx lr",
+    ".ltorg",
+    ".size __pre_init, . - __pre_init",
+    swap = sym swap,
+);
+
+/// Swap the first `len` bytes, a whole number of sectors, of the two
+/// `slot_size` slots, then reset with `status` in scratch register 0 and
+/// `len` in scratch register 1.
+///
+/// With `len` 0, carry on with the swap the journal records instead, and
+/// return if there is none.
+///
+/// # Safety
+///
+/// Must run from SRAM4, where `__pre_init` or [`load`] put it, with the
+/// other core stopped.
+#[inline(never)]
+#[link_section = ".ota_swap"]
+pub(super) unsafe extern "C" fn swap(slot_size: u32, len: u32, status: u32) {
+    disable_interrupts();
+    // A watchdog still running from before a reset would cut the swap short
+    write(WATCHDOG_CTRL_CLR, WATCHDOG_ENABLE);
+
+    let rom = Rom::lookup();
+    let scratch = slot_size.wrapping_mul(2);
+    let journal = scratch.wrapping_add(SECTOR_SIZE);
+
+    let mut resuming = len == 0;
+    let (len, status) = if resuming {
+        let header = XIP_BASE.wrapping_add(journal);
+        let len = read(header.wrapping_add(4));
+        let status = read(header.wrapping_add(12));
+        if read(header) != MAGIC
+            || read(header.wrapping_add(8)) != !len
+            || read(header.wrapping_add(16)) != !status
+            || len == 0
+            || len > slot_size
+            || len & (SECTOR_SIZE - 1) != 0
+        {
+            return;
+        }
+        (len, status)
+    } else {
+        erase(&rom, journal);
+        fill_buffer(0xFFFF_FFFF);
+        write(BUFFER, MAGIC);
+        write(BUFFER + 4, len);
+        write(BUFFER + 8, !len);
+        write(BUFFER + 12, status);
+        write(BUFFER + 16, !status);
+        program(&rom, journal);
+        (len, status)
+    };
+
+    let mut step = 0;
+    let mut sector = len;
+    while sector != 0 {
+        sector = sector.wrapping_sub(SECTOR_SIZE);
+        let a = sector;
+        let b = slot_size.wrapping_add(sector);
+        let mut phase = 0u32;
+        while phase < 3 {
+            if pending(journal, step) {
+                if phase == 0 {
+                    copy(&rom, a, scratch);
+                } else if phase == 2 {
+                    copy(&rom, scratch, b);
+                } else if resuming && sector == 0 {
+                    // This code is running from slot A's first sector, so
+                    // the copy into it either never started or finished, or
+                    // the board was reflashed in BOOTSEL mode meanwhile
+                    if same(a, scratch) {
+                        copy(&rom, b, a);
+                    } else if !same(a, b) {
+                        // Leave the new firmware be, and start it afresh
+                        erase(&rom, journal);
+                        reset();
+                    }
+                } else {
+                    copy(&rom, b, a);
+                }
+                mark(&rom, journal, step);
+                resuming = false;
+            }
+            step = step.wrapping_add(1);
+            phase = phase.wrapping_add(1);
+        }
+    }
+
+    erase(&rom, journal);
+    write(WATCHDOG_SCRATCH0, status);
+    write(WATCHDOG_SCRATCH1, len);
+    reset();
+}
+
+#[inline(always)]
+unsafe fn reset() -> ! {
+    write(AIRCR, SYSRESETREQ);
+    loop {
+        // Wait for the reset
+        disable_interrupts();
+    }
+}
+
+/// Copy [`swap`] into SRAM4, in case something has used it since the reset.
+pub(super) fn load() {
+    extern "C" {
+        // Set by `memory.x`
+        static __ota_swap_load: u32;
+        static mut __ota_swap_start: u32;
+        static __ota_swap_end: u32;
+    }
+    // Note (safety): the linker script puts `.ota_swap` at these addresses in
+    // flash and SRAM4, and nothing else uses SRAM4
+    unsafe {
+        let start = core::ptr::addr_of_mut!(__ota_swap_start);
+        let end = core::ptr::addr_of!(__ota_swap_end);
+        let words = (end as usize - start as usize) / 4;
+        core::ptr::copy_nonoverlapping(core::ptr::addr_of!(__ota_swap_load), start, words);
+    }
+}
+
+type Op = unsafe extern "C" fn();
+type Erase = unsafe extern "C" fn(u32, usize, u32, u8);
+type Program = unsafe extern "C" fn(u32, *const u8, usize);
+
+/// Boot ROM flash functions.
+struct Rom {
+    connect_internal_flash: Op,
+    flash_exit_xip: Op,
+    flash_range_erase: Erase,
+    flash_range_program: Program,
+    flash_flush_cache: Op,
+    flash_enter_cmd_xip: Op,
+}
+
+impl Rom {
+    #[inline(always)]
+    unsafe fn lookup() -> Self {
+        // The table and lookup function addresses are halfwords at 0x14 and
+        // 0x18
+        let lookup: unsafe extern "C" fn(u32, u32) -> u32 =
+            core::mem::transmute((read(0x18) & 0xFFFF) as usize);
+        let table = read(0x14) & 0xFFFF;
+        let connect_internal_flash = lookup(table, CONNECT_INTERNAL_FLASH) as usize;
+        let flash_exit_xip = lookup(table, FLASH_EXIT_XIP) as usize;
+        let flash_range_erase = lookup(table, FLASH_RANGE_ERASE) as usize;
+        let flash_range_program = lookup(table, FLASH_RANGE_PROGRAM) as usize;
+        let flash_flush_cache = lookup(table, FLASH_FLUSH_CACHE) as usize;
+        let flash_enter_cmd_xip = lookup(table, FLASH_ENTER_CMD_XIP) as usize;
+        Rom {
+            connect_internal_flash: core::mem::transmute::<usize, Op>(connect_internal_flash),
+            flash_exit_xip: core::mem::transmute::<usize, Op>(flash_exit_xip),
+            flash_range_erase: core::mem::transmute::<usize, Erase>(flash_range_erase),
+            flash_range_program: core::mem::transmute::<usize, Program>(flash_range_program),
+            flash_flush_cache: core::mem::transmute::<usize, Op>(flash_flush_cache),
+            flash_enter_cmd_xip: core::mem::transmute::<usize, Op>(flash_enter_cmd_xip),
+        }
+    }
+}
+
+/// Erase the sector at `offset` from the start of flash.
+#[inline(always)]
+unsafe fn erase(rom: &Rom, offset: u32) {
+    const BLOCK_SIZE: u32 = 65536;
+    const SECTOR_ERASE_CMD: u8 = 0x20;
+
+    (rom.connect_internal_flash)();
+    (rom.flash_exit_xip)();
+    (rom.flash_range_erase)(offset, SECTOR_SIZE as usize, BLOCK_SIZE, SECTOR_ERASE_CMD);
+    (rom.flash_flush_cache)();
+    (rom.flash_enter_cmd_xip)();
+}
+
+/// Program the page in [`BUFFER`] at `offset` from the start of flash.
+#[inline(always)]
+unsafe fn program(rom: &Rom, offset: u32) {
+    (rom.connect_internal_flash)();
+    (rom.flash_exit_xip)();
+    (rom.flash_range_program)(offset, BUFFER as *const u8, PAGE_SIZE);
+    (rom.flash_flush_cache)();
+    (rom.flash_enter_cmd_xip)();
+}
+
+/// Copy the sector at `from` to the one at `to`, last page first.
+#[inline(always)]
+unsafe fn copy(rom: &Rom, from: u32, to: u32) {
+    erase(rom, to);
+    let mut page = SECTOR_SIZE;
+    while page != 0 {
+        page = page.wrapping_sub(PAGE_SIZE as u32);
+        let source = XIP_BASE.wrapping_add(from).wrapping_add(page);
+        let mut word = 0;
+        while word < PAGE_WORDS {
+            let at = word.wrapping_mul(4);
+            write(BUFFER.wrapping_add(at), read(source.wrapping_add(at)));
+            word = word.wrapping_add(1);
+        }
+        program(rom, to.wrapping_add(page));
+    }
+}
+
+/// Whether the sectors at `a` and `b` hold the same data.
+#[inline(always)]
+unsafe fn same(a: u32, b: u32) -> bool {
+    let a = XIP_BASE.wrapping_add(a);
+    let b = XIP_BASE.wrapping_add(b);
+    let mut word = 0;
+    while word < SECTOR_WORDS {
+        let at = word.wrapping_mul(4);
+        if read(a.wrapping_add(at)) != read(b.wrapping_add(at)) {
+            return false;
+        }
+        word = word.wrapping_add(1);
+    }
+    true
+}
+
+/// Whether `step` has yet to be done.
+#[inline(always)]
+unsafe fn pending(journal: u32, step: u32) -> bool {
+    let word = XIP_BASE
+        .wrapping_add(journal)
+        .wrapping_add(PAGE_SIZE as u32)
+        .wrapping_add((step >> 5).wrapping_mul(4));
+    read(word) & bit(step) != 0
+}
+
+/// Record that `step` is done.
+#[inline(always)]
+unsafe fn mark(rom: &Rom, journal: u32, step: u32) {
+    // Programming leaves the bits set in the buffer as they were
+    fill_buffer(0xFFFF_FFFF);
+    let word = (step >> 5) & (PAGE_WORDS - 1);
+    write(BUFFER.wrapping_add(word.wrapping_mul(4)), !bit(step));
+    let page = (step >> BITS_PER_PAGE_SHIFT).wrapping_add(1);
+    program(
+        rom,
+        journal.wrapping_add(page.wrapping_mul(PAGE_SIZE as u32)),
+    );
+}
+
+#[inline(always)]
+unsafe fn fill_buffer(value: u32) {
+    let mut word = 0;
+    while word < PAGE_WORDS {
+        write(BUFFER.wrapping_add(word.wrapping_mul(4)), value);
+        word = word.wrapping_add(1);
+    }
+}
+
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+unsafe fn read(address: u32) -> u32 {
+    let value;
+    core::arch::asm!(
+        "ldr {value}, [{address}]",
+        value = out(reg) value,
+        address = in(reg) address,
+        options(nostack, readonly, preserves_flags),
+    );
+    value
+}
+
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+unsafe fn write(address: u32, value: u32) {
+    core::arch::asm!(
+        "str {value}, [{address}]",
+        value = in(reg) value,
+        address = in(reg) address,
+        options(nostack, preserves_flags),
+    );
+}
+
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+unsafe fn disable_interrupts() {
+    core::arch::asm!("cpsid i", options(nomem, nostack, preserves_flags));
+}
+
+/// `step`'s bit in its word of the journal.
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+fn bit(step: u32) -> u32 {
+    let mut bit = 1u32;
+    // Note (safety): only registers are touched
+    unsafe {
+        core::arch::asm!(
+            "lsls {bit}, {shift}",
+            bit = inout(reg) bit,
+            shift = in(reg) step & 31,
+            options(pure, nomem, nostack),
+        );
+    }
+    bit
+}
+
+// Host builds, for the tests, never run the swap
+#[cfg(not(target_arch = "arm"))]
+unsafe fn read(address: u32) -> u32 {
+    core::ptr::read_volatile(address as usize as *const u32)
+}
+
+#[cfg(not(target_arch = "arm"))]
+unsafe fn write(address: u32, value: u32) {
+    core::ptr::write_volatile(address as usize as *mut u32, value)
+}
+
+#[cfg(not(target_arch = "arm"))]
+unsafe fn disable_interrupts() {}
+
+#[cfg(not(target_arch = "arm"))]
+fn bit(step: u32) -> u32 {
+    1 << (step & 31)
+}
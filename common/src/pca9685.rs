@@ -0,0 +1,146 @@
+//! Driver for the NXP PCA9685 16-channel, 12-bit I²C PWM controller.
+//!
+//! The PCA9685 is the usual way to add servo outputs once the RP2040's own
+//! sixteen PWM channels run out. [`Pca9685::new`] sets it up for servos,
+//! running every channel at the [`servo::PERIOD_US`](crate::servo::PERIOD_US)
+//! refresh rate, and each [`Pca9685Channel`] implements
+//! [`PwmOutput`], so it can drive a [`Servo`](crate::servo::Servo). Every
+//! channel borrows the [`Pca9685`], which owns the bus, so all sixteen can be
+//! in use at once.
+
+use core::cell::RefCell;
+
+use embedded_hal::blocking::i2c::Write;
+
+use crate::servo::{PwmOutput, PERIOD_US};
+
+/// The address with A0 to A5 all tied low.
+pub const DEFAULT_ADDRESS: u8 = 0x40;
+
+/// Number of PWM channels.
+pub const CHANNEL_COUNT: u8 = 16;
+
+/// Frequency of the internal oscillator.
+const OSCILLATOR_HZ: u32 = 25_000_000;
+
+/// Counter steps per period.
+const STEPS: u32 = 4096;
+
+const MODE1: u8 = 0x00;
+const MODE2: u8 = 0x01;
+const LED0_ON_L: u8 = 0x06;
+const PRE_SCALE: u8 = 0xFE;
+
+const MODE1_RESTART: u8 = 1 << 7;
+const MODE1_AI: u8 = 1 << 5;
+const MODE1_SLEEP: u8 = 1 << 4;
+/// MODE2: totem-pole outputs, as servo signal lines want
+const MODE2_OUTDRV: u8 = 1 << 2;
+
+/// LEDn_OFF_H: output fully off
+const FULL_OFF: u8 = 1 << 4;
+
+struct Inner<I2C> {
+    i2c: I2C,
+    address: u8,
+}
+
+/// A PCA9685 on an I²C bus.
+pub struct Pca9685<I2C> {
+    inner: RefCell<Inner<I2C>>,
+    period_us: u32,
+}
+
+impl<I2C, E> Pca9685<I2C>
+where
+    I2C: Write<Error = E>,
+{
+    /// Set up the PCA9685 at `address` with every output off and a period of
+    /// [`PERIOD_US`].
+    ///
+    /// The oscillator needs 500 µs to start after waking; `delay_us` is called
+    /// to wait for it.
+    pub fn new(i2c: I2C, address: u8, mut delay_us: impl FnMut(u32)) -> Result<Self, E> {
+        // prescale = osc × period / 4096 - 1, rounded
+        let steps_per_us = u64::from(STEPS) * 1_000_000;
+        let scaled = u64::from(OSCILLATOR_HZ) * u64::from(PERIOD_US);
+        let prescale = (scaled + steps_per_us / 2) / steps_per_us - 1;
+        let mut inner = Inner { i2c, address };
+        // The prescaler can only be written while asleep
+        inner.write(&[MODE1, MODE1_SLEEP])?;
+        inner.write(&[PRE_SCALE, prescale as u8])?;
+        inner.write(&[MODE2, MODE2_OUTDRV])?;
+        inner.write(&[MODE1, MODE1_AI])?;
+        delay_us(500);
+        inner.write(&[MODE1, MODE1_AI | MODE1_RESTART])?;
+
+        let chip = Pca9685 {
+            inner: RefCell::new(inner),
+            period_us: ((prescale + 1) * steps_per_us / u64::from(OSCILLATOR_HZ)) as u32,
+        };
+        for channel in 0..CHANNEL_COUNT {
+            chip.set_pulse_width_us(channel, 0)?;
+        }
+        Ok(chip)
+    }
+
+    /// The actual PWM period, which the 8 bit prescaler can only get close
+    /// to [`PERIOD_US`].
+    pub fn period_us(&self) -> u32 {
+        self.period_us
+    }
+
+    /// Output `width_us` pulses on `channel`; `0` turns it off.
+    pub fn set_pulse_width_us(&self, channel: u8, width_us: u16) -> Result<(), E> {
+        assert!(channel < CHANNEL_COUNT, "the PCA9685 has channels 0 to 15");
+        let register = LED0_ON_L + 4 * channel;
+        let mut inner = self.inner.borrow_mut();
+        if width_us == 0 {
+            return inner.write(&[register, 0, 0, 0, FULL_OFF]);
+        }
+        let off = (u32::from(width_us) * STEPS / self.period_us).min(STEPS - 1) as u16;
+        let [off_l, off_h] = off.to_le_bytes();
+        // Every pulse starts at count 0 and ends at `off`
+        inner.write(&[register, 0, 0, off_l, off_h])
+    }
+
+    /// One channel, for use as a [`PwmOutput`].
+    pub fn channel(&self, channel: u8) -> Pca9685Channel<'_, I2C> {
+        assert!(channel < CHANNEL_COUNT, "the PCA9685 has channels 0 to 15");
+        Pca9685Channel {
+            chip: self,
+            channel,
+        }
+    }
+
+    /// Return the I²C bus.
+    pub fn free(self) -> I2C {
+        self.inner.into_inner().i2c
+    }
+}
+
+impl<I2C, E> Inner<I2C>
+where
+    I2C: Write<Error = E>,
+{
+    fn write(&mut self, bytes: &[u8]) -> Result<(), E> {
+        self.i2c.write(self.address, bytes)
+    }
+}
+
+/// One output of a [`Pca9685`].
+pub struct Pca9685Channel<'a, I2C> {
+    chip: &'a Pca9685<I2C>,
+    channel: u8,
+}
+
+impl<'a, I2C, E> PwmOutput for Pca9685Channel<'a, I2C>
+where
+    I2C: Write<Error = E>,
+{
+    type Error = E;
+
+    fn set_pulse_width_us(&mut self, width_us: u16) -> Result<(), E> {
+        self.chip.set_pulse_width_us(self.channel, width_us)
+    }
+}
@@ -0,0 +1,279 @@
+//! PDM microphone capture from a PIO state machine.
+//!
+//! A PDM microphone turns sound into a 1 bit stream at a few MHz, with the
+//! density of ones following the waveform. One state machine drives the
+//! microphone's clock and shifts the bits in, 32 to a word; a [`Cic`] filter
+//! then averages and decimates them down to 16 bit PCM.
+//!
+//! With a 2.048 MHz PDM clock and a decimation of 128 the output is 16 kHz,
+//! plenty for speech or a sound level meter. The filter runs on the CPU and
+//! costs around a dozen cycles per PDM bit, so 2 MHz takes a sixth of a
+//! 125 MHz core. Call [`PdmMic::read`] at least every 100 µs or so at that
+//! rate, before the joined 8-word FIFO overflows.
+//!
+//! [`PdmMic::read`] takes whatever is ready, for code with other things to
+//! do in between; [`PdmMic::read_frame`] waits for a whole frame.
+//!
+//! The PCM still carries the microphone's DC offset; [`rms`] and
+//! [`dbfs_tenths`] take it out before measuring the level.
+//!
+//! The pins must already be set to the PIO's function.
+
+use hal::pio::{
+    Buffers, PIOBuilder, PIOExt, PinDir, Running, Rx, ShiftDirection, StateMachine,
+    StateMachineIndex, UninitStateMachine, PIO,
+};
+
+use crate::hal;
+use crate::resources::{self, Resource};
+
+/// Order of the [`Cic`] filter.
+const ORDER: u32 = 3;
+
+/// A third order CIC (sinc³) decimator from PDM bits to PCM samples.
+pub struct Cic {
+    decimation: u32,
+    /// Bits until the next output sample.
+    countdown: u32,
+    integrators: [i32; ORDER as usize],
+    delays: [i32; ORDER as usize],
+    /// Total gain of the filter, as a power of two.
+    gain_bits: u32,
+}
+
+impl Cic {
+    /// A filter producing one sample per `decimation` PDM bits.
+    ///
+    /// `decimation` has to be a power of two from 8 to 256.
+    pub fn new(decimation: u32) -> Self {
+        assert!(
+            decimation.is_power_of_two() && (8..=256).contains(&decimation),
+            "decimation must be a power of two from 8 to 256"
+        );
+        Cic {
+            decimation,
+            countdown: decimation,
+            integrators: [0; ORDER as usize],
+            delays: [0; ORDER as usize],
+            gain_bits: ORDER * decimation.trailing_zeros(),
+        }
+    }
+
+    /// Number of PDM bits per output sample.
+    pub fn decimation(&self) -> u32 {
+        self.decimation
+    }
+
+    /// Filter 32 PDM bits, oldest in the MSB, calling `output` with each PCM
+    /// sample they complete.
+    pub fn push_word(&mut self, word: u32, mut output: impl FnMut(i16)) {
+        for bit in (0..32).rev() {
+            // The integrators wrap, and the combs undo the wrapping exactly
+            let x = if word & (1 << bit) != 0 { 1 } else { -1 };
+            self.integrators[0] = self.integrators[0].wrapping_add(x);
+            self.integrators[1] = self.integrators[1].wrapping_add(self.integrators[0]);
+            self.integrators[2] = self.integrators[2].wrapping_add(self.integrators[1]);
+
+            self.countdown -= 1;
+            if self.countdown == 0 {
+                self.countdown = self.decimation;
+                output(self.comb());
+            }
+        }
+    }
+
+    /// Run the comb stages on the last integrator, scaled to 16 bits.
+    fn comb(&mut self) -> i16 {
+        let mut y = self.integrators[2];
+        for delay in self.delays.iter_mut() {
+            let x = y;
+            y = x.wrapping_sub(*delay);
+            *delay = x;
+        }
+        // A full-scale input gives ±decimation³; bring that to ±32768
+        let y = if self.gain_bits >= 15 {
+            y >> (self.gain_bits - 15)
+        } else {
+            y << (15 - self.gain_bits)
+        };
+        y.clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16
+    }
+}
+
+/// A PDM microphone on a PIO state machine.
+pub struct PdmMic<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    cic: Cic,
+    sample_rate: u32,
+}
+
+impl<P, SM> PdmMic<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Start clocking the microphone at `pdm_clock_hz` on `clock_pin` and
+    /// reading its data from `data_pin`, decimating by `decimation`.
+    ///
+    /// The microphone's L/R select decides which clock edge it drives its
+    /// data on; this samples just before the rising edge, which suits a
+    /// microphone with L/R tied low.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        data_pin: u8,
+        clock_pin: u8,
+        pdm_clock_hz: u32,
+        system_clock_hz: u32,
+        decimation: u32,
+    ) -> Self {
+        // Two cycles per PDM clock period
+        let program = pio_proc::pio_asm!(
+            ".side_set 1",
+            ".wrap_target",
+            "    nop                side 0",
+            "    in pins, 1         side 1",
+            ".wrap",
+        );
+
+        let divisor = (u64::from(system_clock_hz) << 8) / (u64::from(pdm_clock_hz) * 2);
+        let (int, frac) = ((divisor >> 8) as u16, divisor as u8);
+
+        let installed = pio.install(&program.program).unwrap();
+        let (mut state, rx, _) = PIOBuilder::from_program(installed)
+            .in_pin_base(data_pin)
+            .side_set_pin_base(clock_pin)
+            .in_shift_direction(ShiftDirection::Left)
+            .autopush(true)
+            .push_threshold(32)
+            .buffers(Buffers::OnlyRx)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        let _ = resources::claim_all(
+            &[
+                Resource::pio_sm::<P, SM>(),
+                Resource::Gpio(data_pin),
+                Resource::Gpio(clock_pin),
+            ],
+            "pdm-mic",
+        );
+        state.set_pindirs([(clock_pin, PinDir::Output), (data_pin, PinDir::Input)]);
+
+        PdmMic {
+            _sm: state.start(),
+            rx,
+            cic: Cic::new(decimation),
+            sample_rate: pdm_clock_hz / decimation,
+        }
+    }
+
+    /// The PCM sample rate.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Filter whatever the state machine has captured into `samples`, and
+    /// return how many samples were written.
+    ///
+    /// Stops once `samples` cannot take another word's worth, leaving the rest
+    /// in the FIFO for the next call.
+    pub fn read(&mut self, samples: &mut [i16]) -> usize {
+        let per_word = (32 / self.cic.decimation() as usize).max(1);
+        let mut written = 0;
+        while samples.len() - written >= per_word {
+            let word = match self.rx.read() {
+                Some(word) => word,
+                None => break,
+            };
+            self.cic.push_word(word, |sample| {
+                samples[written] = sample;
+                written += 1;
+            });
+        }
+        written
+    }
+
+    /// Fill the whole of `frame`, waiting for the microphone as needed.
+    ///
+    /// `frame`'s length has to be a multiple of the samples in a word, i.e. of
+    /// `32 / decimation` when decimating by less than 32. A frame of 320
+    /// samples is 20 ms at 16 kHz.
+    pub fn read_frame(&mut self, frame: &mut [i16]) {
+        let per_word = (32 / self.cic.decimation() as usize).max(1);
+        assert!(
+            frame.len().is_multiple_of(per_word),
+            "frames must be a whole number of words"
+        );
+        let mut filled = 0;
+        while filled < frame.len() {
+            filled += self.read(&mut frame[filled..]);
+        }
+    }
+}
+
+/// The mean power of `samples` around their average, i.e. ignoring their
+/// DC offset.
+fn ac_power(samples: &[i16]) -> u64 {
+    let count = samples.len() as i64;
+    let mean = samples.iter().map(|&s| i64::from(s)).sum::<i64>() / count;
+    samples
+        .iter()
+        .map(|&s| {
+            let ac = i64::from(s) - mean;
+            (ac * ac) as u64
+        })
+        .sum::<u64>()
+        / count as u64
+}
+
+/// The RMS amplitude of `samples`, ignoring their DC offset, or 0 for none.
+///
+/// A full-scale sine gives about 23170.
+pub fn rms(samples: &[i16]) -> u16 {
+    if samples.is_empty() {
+        return 0;
+    }
+    let power = ac_power(samples);
+
+    // Integer square root, a bit at a time from the top
+    let mut root = 0u64;
+    let mut bit = 1u64 << 31;
+    while bit != 0 {
+        let trial = root | bit;
+        if trial * trial <= power {
+            root = trial;
+        }
+        bit >>= 1;
+    }
+    root as u16
+}
+
+/// The level of `samples` in tenths of a dB relative to a full-scale sine,
+/// ignoring their DC offset, or `None` for perfect silence.
+///
+/// The logarithm is approximated to within about 0.3 dB, which is fine for a
+/// level meter.
+pub fn dbfs_tenths(samples: &[i16]) -> Option<i16> {
+    if samples.is_empty() {
+        return None;
+    }
+    let power = ac_power(samples);
+    if power == 0 {
+        return None;
+    }
+
+    // log2(power) in 8.8 fixed point, interpolating linearly between powers
+    // of two
+    let whole = 63 - power.leading_zeros();
+    let fraction = if whole >= 8 {
+        (power >> (whole - 8)) & 0xFF
+    } else {
+        (power << (8 - whole)) & 0xFF
+    };
+    let log2 = (i64::from(whole) << 8) | fraction as i64;
+
+    // A full-scale sine has a power of 32768² / 2, or 2^29
+    let tenths = (log2 - (29 << 8)) * 30103 / (1000 << 8);
+    Some(tenths as i16)
+}
@@ -0,0 +1,193 @@
+//! Low-power waiting for a GPIO edge.
+//!
+//! [`sleep_until_edge`] gates every clock but the ones the GPIO block needs,
+//! and stops the processors until the edge; the clock tree carries on as it
+//! was. [`dormant_until_edge`] goes further and stops the oscillators too,
+//! the RP2040's lowest-power state, and puts the clock tree back afterwards:
+//!
+//! ```ignore
+//! let mut button = pins.gpio15.into_pull_up_input();
+//! power::dormant_until_edge(&mut button, Interrupt::EdgeLow);
+//! ```
+//!
+//! Dormant runs the system from the crystal while the PLLs and the ring
+//! oscillator are stopped, so `clk_ref` must be on the crystal beforehand, as
+//! `init_clocks_and_plls` leaves it. Whatever runs from the PLLs, USB and
+//! `clk_peri` included, stops until the wake-up, and the timer and the
+//! watchdog don't count meanwhile. See [`sleep`](crate::sleep) for waiting on
+//! the RTC instead.
+
+use cortex_m::peripheral::{NVIC, SCB};
+
+use crate::hal::gpio::{Interrupt, Pin, PinId, PinMode, ValidPinMode};
+use crate::hal::pac::{self, Interrupt::IO_IRQ_BANK0};
+
+/// Reset value of `SLEEP_EN0`: every clock runs in sleep.
+const SLEEP_EN0_ALL: u32 = 0xFFFF_FFFF;
+/// Reset value of `SLEEP_EN1`: every clock runs in sleep.
+const SLEEP_EN1_ALL: u32 = 0x0000_7FFF;
+
+/// Written to `XOSC.DORMANT` to stop the crystal oscillator.
+const XOSC_DORMANT: u32 = 0x636f_6d61;
+
+/// `clk_ref` source: the crystal oscillator.
+const CLK_REF_SRC_XOSC: u32 = 2;
+/// `clk_sys` source: `clk_ref`.
+const CLK_SYS_SRC_REF: u32 = 0;
+
+/// `PLL.PWR` with every part powered down.
+const PLL_PWR_DOWN: u32 = 0x2D;
+
+/// Sleep until `edge` on `pin`, with only the GPIO and pads clocked.
+///
+/// The interrupt wakes the core but its handler does not run: it is taken
+/// and cleared here, and masked again before returning. Other interrupts are
+/// ignored while sleeping, as their clocks are stopped.
+pub fn sleep_until_edge<I, M>(pin: &mut Pin<I, M>, edge: Interrupt, scb: &mut SCB)
+where
+    I: PinId,
+    M: PinMode + ValidPinMode<I>,
+{
+    // Note (safety): only SLEEP_EN0/1 are written, and put back below
+    let clocks = unsafe { &*pac::CLOCKS::ptr() };
+
+    pin.clear_interrupt(edge);
+    pin.set_interrupt_enabled(edge, true);
+
+    cortex_m::interrupt::free(|_| {
+        NVIC::unpend(IO_IRQ_BANK0);
+        // Note (safety): interrupts are disabled, so no handler runs
+        unsafe { NVIC::unmask(IO_IRQ_BANK0) };
+
+        clocks
+            .sleep_en0
+            .write(|w| w.clk_sys_io().set_bit().clk_sys_pads().set_bit());
+        clocks.sleep_en1.write(|w| unsafe { w.bits(0) });
+        scb.set_sleepdeep();
+
+        // A pending, unmasked interrupt ends WFI even with PRIMASK set
+        while !pin.interrupt_status(edge) {
+            cortex_m::asm::wfi();
+        }
+
+        scb.clear_sleepdeep();
+        clocks.sleep_en0.write(|w| unsafe { w.bits(SLEEP_EN0_ALL) });
+        clocks.sleep_en1.write(|w| unsafe { w.bits(SLEEP_EN1_ALL) });
+
+        pin.set_interrupt_enabled(edge, false);
+        pin.clear_interrupt(edge);
+        NVIC::mask(IO_IRQ_BANK0);
+        NVIC::unpend(IO_IRQ_BANK0);
+    });
+}
+
+/// Stop every oscillator until `edge` on `pin`, then restart them and put
+/// the clock tree back as it was.
+pub fn dormant_until_edge<I, M>(pin: &mut Pin<I, M>, edge: Interrupt)
+where
+    I: PinId,
+    M: PinMode + ValidPinMode<I>,
+{
+    // Note (safety): the clock registers are put back as they were found
+    // before returning, and interrupts are off in between
+    let clocks = unsafe { &*pac::CLOCKS::ptr() };
+    let xosc = unsafe { &*pac::XOSC::ptr() };
+    let rosc = unsafe { &*pac::ROSC::ptr() };
+    let pll_sys = unsafe { &*pac::PLL_SYS::ptr() };
+    let pll_usb = unsafe { &*pac::PLL_USB::ptr() };
+
+    pin.clear_interrupt(edge);
+    set_dormant_wake(I::DYN.num, edge, true);
+
+    cortex_m::interrupt::free(|_| {
+        let clk_ref_ctrl = clocks.clk_ref_ctrl.read().bits();
+        let clk_sys_ctrl = clocks.clk_sys_ctrl.read().bits();
+        let rosc_ctrl = rosc.ctrl.read().bits();
+        let sys = Pll::save(pll_sys);
+        let usb = Pll::save(pll_usb);
+
+        // Everything from the crystal, then the rest off
+        clocks
+            .clk_ref_ctrl
+            .write(|w| unsafe { w.bits(CLK_REF_SRC_XOSC) });
+        while clocks.clk_ref_selected.read().bits() != 1 << CLK_REF_SRC_XOSC {}
+        clocks
+            .clk_sys_ctrl
+            .write(|w| unsafe { w.bits((clk_sys_ctrl & !1) | CLK_SYS_SRC_REF) });
+        while clocks.clk_sys_selected.read().bits() != 1 << CLK_SYS_SRC_REF {}
+        pll_sys.pwr.write(|w| unsafe { w.bits(PLL_PWR_DOWN) });
+        pll_usb.pwr.write(|w| unsafe { w.bits(PLL_PWR_DOWN) });
+        rosc.ctrl.modify(|_, w| w.enable().disable());
+
+        // Execution stops here until the edge, and carries on once the
+        // crystal is running again
+        xosc.dormant.write(|w| unsafe { w.bits(XOSC_DORMANT) });
+        while xosc.status.read().stable().bit_is_clear() {}
+
+        rosc.ctrl.write(|w| unsafe { w.bits(rosc_ctrl) });
+        sys.restore(pll_sys);
+        usb.restore(pll_usb);
+        clocks
+            .clk_sys_ctrl
+            .write(|w| unsafe { w.bits(clk_sys_ctrl) });
+        while clocks.clk_sys_selected.read().bits() != 1 << (clk_sys_ctrl & 1) {}
+        clocks
+            .clk_ref_ctrl
+            .write(|w| unsafe { w.bits(clk_ref_ctrl) });
+        while clocks.clk_ref_selected.read().bits() != 1 << (clk_ref_ctrl & 3) {}
+    });
+
+    set_dormant_wake(I::DYN.num, edge, false);
+    pin.clear_interrupt(edge);
+}
+
+/// Enable or disable `edge` on GPIO `pin` as a way out of dormant. The HAL
+/// only sets the processors' interrupt enables, not these.
+fn set_dormant_wake(pin: u8, edge: Interrupt, enabled: bool) {
+    // Note (safety): only this pin's bit for `edge` changes, with interrupts
+    // off so the read-modify-write isn't interleaved with another
+    let io = unsafe { &*pac::IO_BANK0::ptr() };
+    let bit = 1 << (4 * u32::from(pin % 8) + edge as u32);
+    cortex_m::interrupt::free(|_| {
+        io.dormant_wake_inte[usize::from(pin / 8)].modify(|r, w| unsafe {
+            w.bits(if enabled {
+                r.bits() | bit
+            } else {
+                r.bits() & !bit
+            })
+        })
+    });
+}
+
+/// A PLL's settings, kept while it is powered down.
+struct Pll {
+    cs: u32,
+    pwr: u32,
+    fbdiv_int: u32,
+    prim: u32,
+}
+
+impl Pll {
+    fn save(pll: &pac::pll_sys::RegisterBlock) -> Self {
+        Pll {
+            cs: pll.cs.read().bits(),
+            pwr: pll.pwr.read().bits(),
+            fbdiv_int: pll.fbdiv_int.read().bits(),
+            prim: pll.prim.read().bits(),
+        }
+    }
+
+    /// Set the PLL up again, and wait for it to lock if it is in use.
+    fn restore(&self, pll: &pac::pll_sys::RegisterBlock) {
+        // Note (safety): these are the values the PLL had before
+        unsafe {
+            pll.cs.write(|w| w.bits(self.cs));
+            pll.fbdiv_int.write(|w| w.bits(self.fbdiv_int));
+            pll.prim.write(|w| w.bits(self.prim));
+            pll.pwr.write(|w| w.bits(self.pwr));
+        }
+        if self.pwr & 1 == 0 {
+            while pll.cs.read().lock().bit_is_clear() {}
+        }
+    }
+}
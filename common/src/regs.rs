@@ -0,0 +1,236 @@
+//! Decoded dumps of a few peripheral registers, for debugging over a serial
+//! terminal.
+//!
+//! When a pin doesn't toggle or the ADC reads nothing, the first question is
+//! usually how the hardware is actually set up. [`command`] is a
+//! [`shell`](crate::shell) command, `regs <block>`, that answers it without a
+//! debugger, printing a curated set of registers with their fields decoded:
+//!
+//! * `gpio`: for each pin, its function, output and output enable as driven
+//!   to the pad, the input level, and the pad's pulls and input enable
+//!   (`IO_BANK0` `GPIOx_STATUS` and `GPIOx_CTRL`, `PADS_BANK0` `GPIOx`),
+//! * `pwm`: for each slice, whether it runs, its mode, divider, `TOP`,
+//!   compare values and counter (`PWM` `CHx_CSR`, `DIV`, `TOP`, `CC`, `CTR`),
+//! * `adc`: the input selected, round-robin mask, ready and error flags, the
+//!   FIFO's state and the clock divider (`ADC` `CS`, `FCS`, `DIV`),
+//! * `usb`: VBUS, line state, connection, suspend and bus reset, the device
+//!   address, and whether the controller and the D+ pull-up are on
+//!   (`USBCTRL_REGS` `SIE_STATUS`, `SIE_CTRL`, `MAIN_CTRL`, `ADDR_ENDP`).
+//!
+//! Everything is only read, never written, so dumping registers doesn't
+//! disturb the drivers that own them. Bits that are cleared by reading, such
+//! as those in the FIFOs, are left alone. A block still held in reset is
+//! reported as such rather than read.
+
+use core::fmt::{Result as FmtResult, Write};
+
+use crate::hal::pac;
+
+/// Usage of [`command`], for its [`Command::help`](crate::shell::Command::help).
+pub const HELP: &str = "regs gpio|pwm|adc|usb";
+
+/// Bits of `RESETS.RESET_DONE` for the blocks dumped.
+const RESET_ADC: u32 = 1 << 0;
+const RESET_IO_BANK0: u32 = 1 << 5;
+const RESET_PADS_BANK0: u32 = 1 << 8;
+const RESET_PWM: u32 = 1 << 14;
+const RESET_USBCTRL: u32 = 1 << 24;
+
+/// Names of the `FUNCSEL` values of bank 0's pins.
+const FUNCTIONS: [&str; 10] = [
+    "f0", "spi", "uart", "i2c", "pwm", "sio", "pio0", "pio1", "clock", "usb",
+];
+
+/// The `regs` shell command: dump the block named by the first argument.
+pub fn command(args: &mut crate::shell::Args<'_>, out: &mut dyn Write) -> Result<(), &'static str> {
+    let (dump, needs): (fn(&mut dyn Write) -> FmtResult, u32) = match args.next() {
+        Some("gpio") => (gpio, RESET_IO_BANK0 | RESET_PADS_BANK0),
+        Some("pwm") => (pwm, RESET_PWM),
+        Some("adc") => (adc, RESET_ADC),
+        Some("usb") => (usb, RESET_USBCTRL),
+        _ => return Err("expected gpio, pwm, adc or usb"),
+    };
+
+    // Note (safety): RESET_DONE is read-only
+    let resets = unsafe { &*pac::RESETS::ptr() };
+    if resets.reset_done.read().bits() & needs != needs {
+        return out
+            .write_str("held in reset\r\n")
+            .map_err(|_| "output failed");
+    }
+    dump(out).map_err(|_| "output failed")
+}
+
+/// Bits `shift..shift + width` of `value`.
+fn field(value: u32, shift: u32, width: u32) -> u32 {
+    (value >> shift) & ((1 << width) - 1)
+}
+
+fn bit(value: u32, shift: u32) -> bool {
+    field(value, shift, 1) == 1
+}
+
+/// Print the state of each of bank 0's pins.
+fn gpio(out: &mut dyn Write) -> FmtResult {
+    // Note (safety): only read, and no register here clears on read
+    let (io, pads) = unsafe { (&*pac::IO_BANK0::ptr(), &*pac::PADS_BANK0::ptr()) };
+    out.write_str("pin func  out oe in pull     ie\r\n")?;
+    for pin in 0..30 {
+        let status = io.gpio[pin].gpio_status.read().bits();
+        let ctrl = io.gpio[pin].gpio_ctrl.read().bits();
+        let pad = pads.gpio[pin].read().bits();
+
+        // GPIO_CTRL: FUNCSEL in bits 0-4, 0x1f being none
+        let funcsel = field(ctrl, 0, 5) as usize;
+        let function = FUNCTIONS.get(funcsel).copied().unwrap_or("none");
+        // GPIO_STATUS: OUTTOPAD bit 9, OETOPAD bit 13, INFROMPAD bit 17
+        // PADS: PDE bit 2, PUE bit 3, IE bit 6
+        let pull = match (bit(pad, 3), bit(pad, 2)) {
+            (true, true) => "bus-keep",
+            (true, false) => "up",
+            (false, true) => "down",
+            (false, false) => "none",
+        };
+        write!(
+            out,
+            "{:3} {:5} {:3} {:2} {:2} {:8} {}\r\n",
+            pin,
+            function,
+            u8::from(bit(status, 9)),
+            u8::from(bit(status, 13)),
+            u8::from(bit(status, 17)),
+            pull,
+            u8::from(bit(pad, 6)),
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the set-up of each PWM slice.
+fn pwm(out: &mut dyn Write) -> FmtResult {
+    // Note (safety): only read, and no register here clears on read
+    let pwm = unsafe { &*pac::PWM::ptr() };
+    out.write_str("slice en mode       div      top  cc a  cc b   ctr\r\n")?;
+    for slice in 0..8 {
+        let ch = &pwm.ch[slice];
+        let csr = ch.csr.read().bits();
+        let div = ch.div.read().bits();
+        let cc = ch.cc.read().bits();
+
+        // CSR: EN bit 0, PH_CORRECT bit 1, DIVMODE bits 4-5
+        let mode = match (field(csr, 4, 2), bit(csr, 1)) {
+            (0, false) => "free",
+            (0, true) => "phase",
+            (1, _) => "level",
+            (2, _) => "rise",
+            _ => "fall",
+        };
+        // DIV: FRAC bits 0-3, INT bits 4-11, INT of 0 meaning 256
+        let int = match field(div, 4, 8) {
+            0 => 256,
+            int => int,
+        };
+        write!(
+            out,
+            "{:5} {:2} {:5} {:3}.{:04} {:5} {:5} {:5} {:5}\r\n",
+            slice,
+            u8::from(bit(csr, 0)),
+            mode,
+            int,
+            // Sixteenths, as ten-thousandths
+            field(div, 0, 4) * 625,
+            ch.top.read().bits() & 0xFFFF,
+            field(cc, 0, 16),
+            field(cc, 16, 16),
+            ch.ctr.read().bits() & 0xFFFF,
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the ADC's control, FIFO and divider.
+fn adc(out: &mut dyn Write) -> FmtResult {
+    // Note (safety): only read; RESULT and FIFO, which can pop, are left out
+    let adc = unsafe { &*pac::ADC::ptr() };
+    let cs = adc.cs.read().bits();
+    let fcs = adc.fcs.read().bits();
+    let div = adc.div.read().bits();
+
+    // CS: EN bit 0, TS_EN bit 1, START_MANY bit 3, READY bit 8, ERR bit 9,
+    // ERR_STICKY bit 10, AINSEL bits 12-14, RROBIN bits 16-20
+    write!(
+        out,
+        "enabled {}, temp sensor {}, free-running {}, ready {}\r\n",
+        u8::from(bit(cs, 0)),
+        u8::from(bit(cs, 1)),
+        u8::from(bit(cs, 3)),
+        u8::from(bit(cs, 8)),
+    )?;
+    write!(
+        out,
+        "input {}, round-robin mask {:05b}, error {}, sticky error {}\r\n",
+        field(cs, 12, 3),
+        field(cs, 16, 5),
+        u8::from(bit(cs, 9)),
+        u8::from(bit(cs, 10)),
+    )?;
+    // FCS: EN bit 0, SHIFT bit 1, ERR bit 2, DREQ_EN bit 3, EMPTY bit 8,
+    // FULL bit 9, UNDER bit 10, OVER bit 11, LEVEL bits 16-19, THRESH 24-27
+    write!(
+        out,
+        "fifo {}, dreq {}, level {}, threshold {}, empty {}, full {}, under {}, over {}\r\n",
+        u8::from(bit(fcs, 0)),
+        u8::from(bit(fcs, 3)),
+        field(fcs, 16, 4),
+        field(fcs, 24, 4),
+        u8::from(bit(fcs, 8)),
+        u8::from(bit(fcs, 9)),
+        u8::from(bit(fcs, 10)),
+        u8::from(bit(fcs, 11)),
+    )?;
+    // DIV: FRAC bits 0-7, INT bits 8-23; one sample every 1 + DIV clocks
+    write!(
+        out,
+        "divider {} + {}/256\r\n",
+        field(div, 8, 16),
+        field(div, 0, 8)
+    )
+}
+
+/// Print the USB controller's state.
+fn usb(out: &mut dyn Write) -> FmtResult {
+    // Note (safety): only read; SIE_STATUS bits clear on write, not read
+    let usb = unsafe { &*pac::USBCTRL_REGS::ptr() };
+    let status = usb.sie_status.read().bits();
+    let sie_ctrl = usb.sie_ctrl.read().bits();
+    let main_ctrl = usb.main_ctrl.read().bits();
+    let addr = usb.addr_endp.read().bits();
+
+    // MAIN_CTRL: CONTROLLER_EN bit 0, HOST_NDEVICE bit 1
+    // SIE_CTRL: PULLUP_EN bit 16
+    write!(
+        out,
+        "controller {}, {} mode, pull-up {}, address {}\r\n",
+        u8::from(bit(main_ctrl, 0)),
+        if bit(main_ctrl, 1) { "host" } else { "device" },
+        u8::from(bit(sie_ctrl, 16)),
+        field(addr, 0, 7),
+    )?;
+    // SIE_STATUS: VBUS_DETECTED bit 0, LINE_STATE bits 2-3, SUSPENDED bit 4,
+    // CONNECTED bit 16, BUS_RESET bit 19
+    let line = match field(status, 2, 2) {
+        0 => "SE0",
+        1 => "J",
+        2 => "K",
+        _ => "SE1",
+    };
+    write!(
+        out,
+        "vbus {}, line {}, connected {}, suspended {}, bus reset {}\r\n",
+        u8::from(bit(status, 0)),
+        line,
+        u8::from(bit(status, 16)),
+        u8::from(bit(status, 4)),
+        u8::from(bit(status, 19)),
+    )
+}
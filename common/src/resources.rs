@@ -0,0 +1,255 @@
+//! Runtime inventory of claimed peripherals.
+//!
+//! The type system already stops two drivers from owning the same pin, but a lot
+//! of the RP2040 is handed out by index: PIO state machines, DMA channels and PWM
+//! slices are easy to use twice by accident, and the second driver then simply
+//! does nothing. Drivers in this crate record what they use here: the channels
+//! [`dma::Allocator`](crate::dma::Allocator) hands out, the PWM slice of
+//! [`audio_pwm`](crate::audio_pwm), and the state machine and pins of the PIO
+//! drivers in [`servo`](crate::servo), [`i2s`](crate::i2s) and
+//! [`pio_pdm`](crate::pio_pdm). Drivers from other crates, such as
+//! `rp-hal-boards-pio`'s `Ws2812`, and firmware's own code should claim by
+//! hand, so that [`write_report`], or the [`shell`](crate::shell)'s
+//! `resources` command, can print the complete allocation table when
+//! something doesn't work.
+//!
+//! ```ignore
+//! use rp_hal_boards_common::resources::{self, Resource};
+//!
+//! resources::claim(Resource::PioSm { pio: 0, sm: 0 }, "ws2812").unwrap();
+//! resources::claim(Resource::Gpio(16), "ws2812").unwrap();
+//!
+//! // Fails, and tells you who got there first.
+//! let err = resources::claim(Resource::PioSm { pio: 0, sm: 0 }, "i2c").unwrap_err();
+//! assert_eq!(err, resources::ClaimError::InUse("ws2812"));
+//! ```
+
+use core::cell::RefCell;
+use core::fmt;
+
+use critical_section::Mutex;
+
+use crate::hal::gpio::PinId;
+use crate::hal::pio::{PIOExt, StateMachineIndex};
+
+/// Number of user GPIOs in bank 0.
+pub const GPIO_COUNT: usize = 30;
+/// Number of PIO blocks.
+pub const PIO_COUNT: usize = 2;
+/// Number of state machines in each PIO block.
+pub const SM_COUNT: usize = 4;
+/// Number of DMA channels.
+pub const DMA_CHANNEL_COUNT: usize = 12;
+/// Number of PWM slices.
+pub const PWM_SLICE_COUNT: usize = 8;
+
+/// Something that can be claimed in the registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resource {
+    /// A bank 0 GPIO, by number.
+    Gpio(u8),
+    /// A state machine of one of the two PIO blocks.
+    PioSm {
+        /// PIO block, 0 or 1.
+        pio: u8,
+        /// State machine in that block, 0 to 3.
+        sm: u8,
+    },
+    /// A DMA channel, 0 to 11.
+    DmaChannel(u8),
+    /// A PWM slice, 0 to 7.
+    PwmSlice(u8),
+}
+
+impl Resource {
+    /// The registry entry for the bank 0 pin `I`.
+    pub fn gpio<I: PinId>() -> Self {
+        Resource::Gpio(I::DYN.num)
+    }
+
+    /// The registry entry for state machine `SM` of PIO block `P`.
+    pub fn pio_sm<P: PIOExt, SM: StateMachineIndex>() -> Self {
+        Resource::PioSm {
+            pio: P::id() as u8,
+            sm: SM::id() as u8,
+        }
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Resource::Gpio(n) => write!(f, "GPIO{}", n),
+            Resource::PioSm { pio, sm } => write!(f, "PIO{} SM{}", pio, sm),
+            Resource::DmaChannel(n) => write!(f, "DMA CH{}", n),
+            Resource::PwmSlice(n) => write!(f, "PWM{}", n),
+        }
+    }
+}
+
+/// Why a claim was refused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClaimError {
+    /// The resource is already owned by the named driver.
+    InUse(&'static str),
+    /// The index does not exist on the RP2040.
+    OutOfRange,
+}
+
+struct Registry {
+    gpio: [Option<&'static str>; GPIO_COUNT],
+    pio: [[Option<&'static str>; SM_COUNT]; PIO_COUNT],
+    dma: [Option<&'static str>; DMA_CHANNEL_COUNT],
+    pwm: [Option<&'static str>; PWM_SLICE_COUNT],
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Registry {
+            gpio: [None; GPIO_COUNT],
+            pio: [[None; SM_COUNT]; PIO_COUNT],
+            dma: [None; DMA_CHANNEL_COUNT],
+            pwm: [None; PWM_SLICE_COUNT],
+        }
+    }
+
+    fn slot(&mut self, resource: Resource) -> Option<&mut Option<&'static str>> {
+        match resource {
+            Resource::Gpio(n) => self.gpio.get_mut(n as usize),
+            Resource::PioSm { pio, sm } => self
+                .pio
+                .get_mut(pio as usize)
+                .and_then(|block| block.get_mut(sm as usize)),
+            Resource::DmaChannel(n) => self.dma.get_mut(n as usize),
+            Resource::PwmSlice(n) => self.pwm.get_mut(n as usize),
+        }
+    }
+}
+
+static REGISTRY: Mutex<RefCell<Registry>> = Mutex::new(RefCell::new(Registry::new()));
+
+/// Record `owner` as the user of `resource`.
+///
+/// Claiming a resource twice for the same owner is allowed, so that a driver can
+/// be torn down and re-created without releasing first.
+pub fn claim(resource: Resource, owner: &'static str) -> Result<(), ClaimError> {
+    critical_section::with(|cs| {
+        let mut registry = REGISTRY.borrow_ref_mut(cs);
+        let slot = registry.slot(resource).ok_or(ClaimError::OutOfRange)?;
+        match *slot {
+            Some(current) if current != owner => Err(ClaimError::InUse(current)),
+            _ => {
+                *slot = Some(owner);
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Claim every resource in `resources`, or none of them.
+///
+/// On failure, returns the resource that was already taken along with the reason.
+pub fn claim_all(
+    resources: &[Resource],
+    owner: &'static str,
+) -> Result<(), (Resource, ClaimError)> {
+    critical_section::with(|cs| {
+        let mut registry = REGISTRY.borrow_ref_mut(cs);
+        for &resource in resources {
+            match registry.slot(resource) {
+                None => return Err((resource, ClaimError::OutOfRange)),
+                Some(Some(current)) if *current != owner => {
+                    return Err((resource, ClaimError::InUse(current)))
+                }
+                Some(_) => {}
+            }
+        }
+        for &resource in resources {
+            if let Some(slot) = registry.slot(resource) {
+                *slot = Some(owner);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Forget whoever owns `resource`.
+pub fn release(resource: Resource) {
+    critical_section::with(|cs| {
+        if let Some(slot) = REGISTRY.borrow_ref_mut(cs).slot(resource) {
+            *slot = None;
+        }
+    })
+}
+
+/// The current owner of `resource`, if any.
+pub fn owner(resource: Resource) -> Option<&'static str> {
+    critical_section::with(|cs| {
+        REGISTRY
+            .borrow_ref_mut(cs)
+            .slot(resource)
+            .and_then(|slot| *slot)
+    })
+}
+
+/// Write the allocation table to `w`, one claimed resource per line, followed by
+/// the number of free entries of each kind.
+///
+/// This is what the shell's [`resources`](crate::shell::resources) command
+/// prints.
+pub fn write_report<W: fmt::Write>(w: &mut W) -> fmt::Result {
+    // Copy the table out so that we don't hold the critical section while
+    // writing to a potentially slow sink.
+    let (gpio, pio, dma, pwm) = critical_section::with(|cs| {
+        let registry = REGISTRY.borrow_ref(cs);
+        (registry.gpio, registry.pio, registry.dma, registry.pwm)
+    });
+
+    let mut free = [0usize; 4];
+    for (n, owner) in gpio.iter().enumerate() {
+        report_line(w, Resource::Gpio(n as u8), *owner, &mut free[0])?;
+    }
+    for (p, block) in pio.iter().enumerate() {
+        for (sm, owner) in block.iter().enumerate() {
+            let resource = Resource::PioSm {
+                pio: p as u8,
+                sm: sm as u8,
+            };
+            report_line(w, resource, *owner, &mut free[1])?;
+        }
+    }
+    for (n, owner) in dma.iter().enumerate() {
+        report_line(w, Resource::DmaChannel(n as u8), *owner, &mut free[2])?;
+    }
+    for (n, owner) in pwm.iter().enumerate() {
+        report_line(w, Resource::PwmSlice(n as u8), *owner, &mut free[3])?;
+    }
+
+    write!(
+        w,
+        "free: {}/{} GPIO, {}/{} PIO SM, {}/{} DMA, {}/{} PWM\r\n",
+        free[0],
+        GPIO_COUNT,
+        free[1],
+        PIO_COUNT * SM_COUNT,
+        free[2],
+        DMA_CHANNEL_COUNT,
+        free[3],
+        PWM_SLICE_COUNT
+    )
+}
+
+fn report_line<W: fmt::Write>(
+    w: &mut W,
+    resource: Resource,
+    owner: Option<&'static str>,
+    free: &mut usize,
+) -> fmt::Result {
+    match owner {
+        Some(owner) => write!(w, "{}: {}\r\n", resource, owner),
+        None => {
+            *free += 1;
+            Ok(())
+        }
+    }
+}
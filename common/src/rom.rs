@@ -0,0 +1,139 @@
+//! Safe wrappers over the boot ROM's routines.
+//!
+//! The RP2040's boot ROM holds hand-optimised memory, bit and soft-float
+//! routines, which cost no flash. The HAL already routes the compiler's
+//! `f32` arithmetic and conversions to the ROM, unless its
+//! `disable-intrinsics` feature is on; this adds the rest behind safe
+//! interfaces: slice copies and fills, bit counts, the `f32` maths
+//! functions that `core` leaves out, such as [`sqrt`] and [`sin`], so there
+//! is no need for `libm` either, and [`reboot_to_usb_boot`], which every BSP
+//! re-exports.
+//!
+//! ```ignore
+//! rom::copy(&mut frame[..], &back_buffer[..]);
+//! let hypotenuse = rom::sqrt(x * x + y * y);
+//! ```
+//!
+//! The trigonometric functions are only accurate for arguments within
+//! ±128π on the first ROM version, as found on B0 and B1 chips.
+
+use crate::hal::rom_data::{self, float_funcs};
+use crate::resources::GPIO_COUNT;
+
+/// Copy `src` into `dst`, which must be the same length.
+///
+/// # Panics
+///
+/// If the lengths differ.
+pub fn copy(dst: &mut [u8], src: &[u8]) {
+    assert_eq!(dst.len(), src.len(), "rom::copy between different lengths");
+    // Note (safety): both slices are valid for their length, and `&mut`
+    // means they can't overlap
+    unsafe {
+        rom_data::memcpy(dst.as_mut_ptr(), src.as_ptr(), dst.len() as u32);
+    }
+}
+
+/// Copy `src` into `dst`, a word at a time, which must be the same length.
+///
+/// # Panics
+///
+/// If the lengths differ.
+pub fn copy_words(dst: &mut [u32], src: &[u32]) {
+    assert_eq!(
+        dst.len(),
+        src.len(),
+        "rom::copy_words between different lengths"
+    );
+    // Note (safety): as for `copy`, and both are word-aligned
+    unsafe {
+        rom_data::memcpy44(dst.as_mut_ptr(), src.as_ptr(), (dst.len() * 4) as u32);
+    }
+}
+
+/// Set every byte of `dst` to `value`.
+pub fn fill(dst: &mut [u8], value: u8) {
+    // Note (safety): the slice is valid for its length
+    unsafe {
+        rom_data::memset(dst.as_mut_ptr(), value, dst.len() as u32);
+    }
+}
+
+/// The number of set bits in `value`.
+pub fn popcount(value: u32) -> u32 {
+    rom_data::popcount32(value)
+}
+
+/// `value` with its bits in reverse order.
+pub fn reverse_bits(value: u32) -> u32 {
+    rom_data::reverse32(value)
+}
+
+/// The number of zero bits above the highest set bit, 32 for zero.
+pub fn leading_zeros(value: u32) -> u32 {
+    rom_data::clz32(value)
+}
+
+/// The number of zero bits below the lowest set bit, 32 for zero.
+pub fn trailing_zeros(value: u32) -> u32 {
+    rom_data::ctz32(value)
+}
+
+/// The square root of `x`, NaN if `x` is negative.
+pub fn sqrt(x: f32) -> f32 {
+    float_funcs::fsqrt(x)
+}
+
+/// The sine of `x` radians.
+pub fn sin(x: f32) -> f32 {
+    float_funcs::fsin(x)
+}
+
+/// The cosine of `x` radians.
+pub fn cos(x: f32) -> f32 {
+    float_funcs::fcos(x)
+}
+
+/// The tangent of `x` radians.
+pub fn tan(x: f32) -> f32 {
+    float_funcs::ftan(x)
+}
+
+/// The angle of the point (`x`, `y`) from the positive x axis, in radians
+/// from -π to π.
+pub fn atan2(y: f32, x: f32) -> f32 {
+    float_funcs::fatan2(y, x)
+}
+
+/// `e` raised to `x`.
+pub fn exp(x: f32) -> f32 {
+    float_funcs::fexp(x)
+}
+
+/// The natural logarithm of `x`.
+pub fn ln(x: f32) -> f32 {
+    float_funcs::fln(x)
+}
+
+/// Reboot into the USB bootloader, as if the board had been plugged in with
+/// BOOTSEL held down.
+///
+/// The bootloader flashes `gpio_activity_pin`, if given, while the host is
+/// talking to it; pins past GPIO29 don't exist and are ignored. With
+/// `disable_msd` set, it leaves out the mass storage drive and only offers the
+/// PICOBOOT interface used by `picotool`.
+pub fn reboot_to_usb_boot(gpio_activity_pin: Option<u8>, disable_msd: bool) -> ! {
+    let disable_interface_mask = u32::from(disable_msd);
+    rom_data::reset_to_usb_boot(activity_pin_mask(gpio_activity_pin), disable_interface_mask);
+    // The ROM resets the chip before it gets here
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+/// The bootloader's activity light mask for `pin`, empty for no pin or one
+/// past GPIO29.
+pub(crate) fn activity_pin_mask(pin: Option<u8>) -> u32 {
+    pin.filter(|&pin| usize::from(pin) < GPIO_COUNT)
+        .map_or(0, |pin| 1 << pin)
+}
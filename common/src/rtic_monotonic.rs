@@ -0,0 +1,152 @@
+//! An RTIC 2 monotonic on the RP2040's 64-bit microsecond timer.
+//!
+//! RTIC 2 software tasks are `async`, and wait for time to pass with
+//! `Timer::delay(...).await`. This module provides the timer behind that,
+//! using the TIMER peripheral's counter and its alarm 0:
+//!
+//! ```ignore
+//! #[init]
+//! fn init(c: init::Context) -> (Shared, Local) {
+//!     Timer::start(c.device.TIMER, &mut resets);
+//!     blink::spawn().ok();
+//!     // ...
+//! }
+//!
+//! // The timer queue needs the alarm's interrupt
+//! #[task(binds = TIMER_IRQ_0, priority = 3)]
+//! fn timer_irq(_: timer_irq::Context) {
+//!     unsafe { Timer::handle_interrupt() };
+//! }
+//!
+//! #[task]
+//! async fn blink(_: blink::Context) {
+//!     loop {
+//!         // ...
+//!         Timer::delay(Duration::millis(500)).await;
+//!     }
+//! }
+//! ```
+//!
+//! Alarm 0 only compares the low 32 bits of the counter, so waits longer
+//! than about 71 minutes take a few spurious wake-ups, which the timer queue
+//! sleeps through. The TIMER peripheral belongs to the monotonic once started,
+//! so `hal::Timer` can't be used alongside it; [`Timer::now`] reads the
+//! counter instead.
+//!
+//! Needs the `rtic-monotonic` feature.
+
+use core::future::Future;
+
+use rtic_time::{Monotonic, TimeoutError, TimerQueue};
+
+use crate::hal::pac;
+
+/// A point in time, in microseconds since [`Timer::start`].
+pub type Instant = fugit::TimerInstantU64<1_000_000>;
+
+/// A length of time, in microseconds.
+pub type Duration = fugit::TimerDurationU64<1_000_000>;
+
+static TIMER_QUEUE: TimerQueue<Timer> = TimerQueue::new();
+
+/// The monotonic, driven by the TIMER peripheral's alarm 0.
+pub struct Timer;
+
+impl Timer {
+    /// Take over the TIMER peripheral and start the timer queue.
+    ///
+    /// Call from `init`, before spawning anything that waits. The counter
+    /// restarts from zero.
+    pub fn start(timer: pac::TIMER, resets: &mut pac::RESETS) {
+        resets.reset.modify(|_, w| w.timer().set_bit());
+        resets.reset.modify(|_, w| w.timer().clear_bit());
+        while resets.reset_done.read().timer().bit_is_clear() {}
+
+        timer.inte.modify(|_, w| w.alarm_0().set_bit());
+        TIMER_QUEUE.initialize(Timer);
+
+        // Note (safety): the timer queue is ready for the interrupt
+        unsafe { pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_0) };
+    }
+
+    /// Serve the timer queue. Call from the `TIMER_IRQ_0` handler.
+    ///
+    /// # Safety
+    ///
+    /// Only call it from that interrupt, and only once [`Timer::start`] has
+    /// run.
+    pub unsafe fn handle_interrupt() {
+        TIMER_QUEUE.on_monotonic_interrupt();
+    }
+
+    /// The time now.
+    pub fn now() -> Instant {
+        <Self as Monotonic>::now()
+    }
+
+    /// Wait for `duration` to pass.
+    pub async fn delay(duration: Duration) {
+        TIMER_QUEUE.delay(duration).await;
+    }
+
+    /// Wait until `instant`, returning straight away if it has passed.
+    pub async fn delay_until(instant: Instant) {
+        TIMER_QUEUE.delay_until(instant).await;
+    }
+
+    /// Run `future`, giving up on it once `duration` has passed.
+    pub async fn timeout_after<F: Future>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, TimeoutError> {
+        TIMER_QUEUE.timeout_after(duration, future).await
+    }
+
+    fn timer() -> &'static pac::timer::RegisterBlock {
+        // Note (safety): the registers used are only written by the monotonic,
+        // which owns the peripheral
+        unsafe { &*pac::TIMER::ptr() }
+    }
+}
+
+impl Monotonic for Timer {
+    type Instant = Instant;
+    type Duration = Duration;
+
+    const ZERO: Instant = Instant::from_ticks(0);
+    const TICK_PERIOD: Duration = Duration::from_ticks(1);
+
+    fn now() -> Instant {
+        let timer = Self::timer();
+        // The raw registers don't latch, so make sure the high word didn't
+        // change while reading the low one
+        loop {
+            let high = timer.timerawh.read().bits();
+            let low = timer.timerawl.read().bits();
+            if timer.timerawh.read().bits() == high {
+                return Instant::from_ticks(u64::from(high) << 32 | u64::from(low));
+            }
+        }
+    }
+
+    fn set_compare(instant: Instant) {
+        let now = Self::now();
+        // Instants too far off for 32 bits wait for the low word to wrap, and
+        // check again then
+        let low = match instant.checked_duration_since(now) {
+            Some(wait) if wait.ticks() <= u64::from(u32::MAX) => {
+                instant.duration_since_epoch().ticks() as u32
+            }
+            _ => 0,
+        };
+        Self::timer().alarm0.write(|w| unsafe { w.bits(low) });
+    }
+
+    fn clear_compare_flag() {
+        Self::timer().intr.write(|w| w.alarm_0().set_bit());
+    }
+
+    fn pend_interrupt() {
+        pac::NVIC::pend(pac::Interrupt::TIMER_IRQ_0);
+    }
+}
@@ -0,0 +1,234 @@
+//! Running code at set times from a main loop, without an RTOS.
+//!
+//! Main loops that do several things at their own pace tend to fill up with
+//! `if timer.get_counter().ticks() >= next { next += ...; ... }`. A
+//! [`Scheduler`] keeps that bookkeeping in one place: register callbacks
+//! with [`periodic`](Scheduler::periodic) or [`after`](Scheduler::after),
+//! and call [`run_pending`](Scheduler::run_pending) from the loop.
+//!
+//! Callbacks are plain functions, or closures that capture nothing, and get
+//! a context value holding whatever they work on, as for the
+//! [`shell`](crate::shell):
+//!
+//! ```ignore
+//! let mut scheduler: Scheduler<App, 4> = Scheduler::new(Monotonic::new(timer));
+//! scheduler.periodic(Duration::millis(500), |app, _now| app.led.toggle().unwrap()).unwrap();
+//! scheduler.after(Duration::secs(5), |app, _now| app.say_hello()).unwrap();
+//! loop {
+//!     usb_dev.poll(&mut [&mut app.serial]);
+//!     scheduler.run_pending(&mut app);
+//! }
+//! ```
+//!
+//! When there is nothing else to do between callbacks,
+//! [`run`](Scheduler::run) sleeps in between on the timer's alarm 0.
+//!
+//! Periodic callbacks are due at whole multiples of their interval from when
+//! they were registered, so they don't drift. One that falls more than an
+//! interval behind skips the periods it missed rather than running for each.
+
+use cortex_m::peripheral::NVIC;
+
+use crate::hal::{
+    self,
+    pac::Interrupt::TIMER_IRQ_0,
+    timer::{Alarm, Alarm0},
+};
+
+/// A point in time, in microseconds of the timer's count.
+pub type Instant = fugit::TimerInstantU64<1_000_000>;
+
+/// A length of time, in microseconds.
+pub type Duration = fugit::TimerDurationU64<1_000_000>;
+
+/// The longest the alarm is set for in one go; waits longer than this wake
+/// up and set it again.
+const MAX_ALARM_US: u64 = 1 << 31;
+
+/// The RP2040's 64-bit microsecond timer, with alarm 0 for waiting.
+pub struct Monotonic {
+    timer: hal::Timer,
+    alarm: Alarm0,
+}
+
+impl Monotonic {
+    /// Use `timer`, which must still have its alarm 0.
+    pub fn new(mut timer: hal::Timer) -> Self {
+        let alarm = timer.alarm_0().expect("alarm 0 is already taken");
+        Monotonic { timer, alarm }
+    }
+
+    /// The time now.
+    pub fn now(&self) -> Instant {
+        self.timer.get_counter()
+    }
+
+    /// Sleep until `instant`, returning straight away if it has passed.
+    ///
+    /// The core sleeps with WFI until the alarm goes off. The alarm's
+    /// interrupt wakes it but its handler does not run. Any other interrupt
+    /// that is unmasked also ends the sleep, and its handler runs before this
+    /// goes back to sleep.
+    pub fn wait_until(&mut self, instant: Instant) {
+        loop {
+            let remaining = match instant.checked_duration_since(self.now()) {
+                Some(remaining) if remaining.ticks() > 0 => remaining.ticks(),
+                _ => return,
+            };
+            let countdown =
+                fugit::MicrosDurationU32::from_ticks(remaining.min(MAX_ALARM_US) as u32);
+            if self.alarm.schedule(countdown).is_err() {
+                return;
+            }
+            self.alarm.enable_interrupt();
+
+            cortex_m::interrupt::free(|_| {
+                NVIC::unpend(TIMER_IRQ_0);
+                // Note (safety): interrupts are disabled, so no handler runs
+                unsafe { NVIC::unmask(TIMER_IRQ_0) };
+
+                // A pending, unmasked interrupt ends WFI even with PRIMASK set
+                if !self.alarm.finished() {
+                    cortex_m::asm::wfi();
+                }
+
+                self.alarm.clear_interrupt();
+                self.alarm.disable_interrupt();
+                NVIC::mask(TIMER_IRQ_0);
+                NVIC::unpend(TIMER_IRQ_0);
+            });
+        }
+    }
+
+    /// Sleep for `duration`.
+    pub fn wait(&mut self, duration: Duration) {
+        let until = self.now() + duration;
+        self.wait_until(until);
+    }
+
+    /// Hand back the timer and its alarm.
+    pub fn free(self) -> (hal::Timer, Alarm0) {
+        (self.timer, self.alarm)
+    }
+}
+
+/// Identifies a callback, to [`cancel`](Scheduler::cancel) it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskId(usize);
+
+/// All of the scheduler's slots are in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Full;
+
+/// Runs a scheduled callback with the context and the time it was due.
+pub type Callback<C> = fn(&mut C, Instant);
+
+struct Task<C> {
+    due: Instant,
+    interval: Option<Duration>,
+    callback: Callback<C>,
+}
+
+/// Up to `N` callbacks, each run once or periodically, with a context of
+/// type `C`.
+pub struct Scheduler<C, const N: usize> {
+    monotonic: Monotonic,
+    tasks: [Option<Task<C>>; N],
+}
+
+impl<C, const N: usize> Scheduler<C, N> {
+    /// A scheduler with nothing scheduled yet.
+    pub fn new(monotonic: Monotonic) -> Self {
+        Scheduler {
+            monotonic,
+            tasks: core::array::from_fn(|_| None),
+        }
+    }
+
+    /// The time now.
+    pub fn now(&self) -> Instant {
+        self.monotonic.now()
+    }
+
+    /// The timer underneath.
+    pub fn monotonic(&mut self) -> &mut Monotonic {
+        &mut self.monotonic
+    }
+
+    /// Run `callback` every `interval`, starting one interval from now.
+    pub fn periodic(&mut self, interval: Duration, callback: Callback<C>) -> Result<TaskId, Full> {
+        assert!(interval.ticks() > 0, "the interval must not be zero");
+        let due = self.now() + interval;
+        self.add(Task {
+            due,
+            interval: Some(interval),
+            callback,
+        })
+    }
+
+    /// Run `callback` once, `delay` from now.
+    pub fn after(&mut self, delay: Duration, callback: Callback<C>) -> Result<TaskId, Full> {
+        let due = self.now() + delay;
+        self.at(due, callback)
+    }
+
+    /// Run `callback` once, at `instant`.
+    pub fn at(&mut self, instant: Instant, callback: Callback<C>) -> Result<TaskId, Full> {
+        self.add(Task {
+            due: instant,
+            interval: None,
+            callback,
+        })
+    }
+
+    /// Stop a callback from running again.
+    ///
+    /// A one-off callback's slot is reused once it has run, so only cancel
+    /// those that haven't run yet.
+    pub fn cancel(&mut self, id: TaskId) {
+        self.tasks[id.0] = None;
+    }
+
+    /// When the next callback is due, if any are scheduled.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.tasks.iter().flatten().map(|task| task.due).min()
+    }
+
+    /// Run every callback that is due, each at most once.
+    pub fn run_pending(&mut self, context: &mut C) {
+        for slot in self.tasks.iter_mut() {
+            let now = self.monotonic.now();
+            let task = match slot {
+                Some(task) if task.due <= now => task,
+                _ => continue,
+            };
+            let (due, callback) = (task.due, task.callback);
+            match task.interval {
+                Some(interval) => {
+                    while task.due <= now {
+                        task.due += interval;
+                    }
+                }
+                None => *slot = None,
+            }
+            callback(context, due);
+        }
+    }
+
+    /// Run callbacks as they come due, sleeping in between, forever.
+    pub fn run(&mut self, context: &mut C) -> ! {
+        loop {
+            self.run_pending(context);
+            match self.next_due() {
+                Some(due) => self.monotonic.wait_until(due),
+                None => cortex_m::asm::wfi(),
+            }
+        }
+    }
+
+    fn add(&mut self, task: Task<C>) -> Result<TaskId, Full> {
+        let index = self.tasks.iter().position(Option::is_none).ok_or(Full)?;
+        self.tasks[index] = Some(task);
+        Ok(TaskId(index))
+    }
+}
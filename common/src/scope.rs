@@ -0,0 +1,103 @@
+//! Framing for streaming captured samples to a host, oscilloscope style.
+//!
+//! Each burst of samples goes out as one frame, all multi-byte fields little
+//! endian:
+//!
+//! ```text
+//! offset  size  field
+//!      0     2  SYNC, 0x53 0x43 ("SC")
+//!      2     1  VERSION, 1
+//!      3     1  flags, bit 0 set if the samples carry straight on from the
+//!               previous frame's, with none missed in between
+//!      4     2  sequence number, counting frames, wrapping at 65536
+//!      6     4  sample rate in Hz
+//!     10     2  number of samples, n
+//!     12    2n  samples, u16 each, 12 bit ADC readings in the low bits
+//! 12 + 2n    2  CRC-16 of bytes 2 to 11 + 2n
+//! ```
+//!
+//! The CRC is CRC-16/CCITT-FALSE: polynomial 0x1021, starting from 0xFFFF,
+//! neither reflected nor inverted, so `b"123456789"` gives 0x29B1. It covers
+//! everything after `SYNC`.
+//!
+//! A host reads until it sees `SYNC`, reads the 10 header bytes after it,
+//! then the samples and the CRC. If the version is unknown, the length
+//! absurd or the CRC wrong, it drops the first byte of the `SYNC` and looks
+//! for the next one, so it can join the stream at any point and recover from
+//! anything else written to the port.
+
+/// First two bytes of every frame.
+pub const SYNC: [u8; 2] = *b"SC";
+
+/// Version of the frame layout.
+pub const VERSION: u8 = 1;
+
+/// Flag set in a frame whose samples carry straight on from the previous
+/// frame's.
+pub const FLAG_CONTIGUOUS: u8 = 1 << 0;
+
+/// Bytes before the samples.
+pub const HEADER_LEN: usize = 12;
+
+/// Bytes after the samples.
+pub const TRAILER_LEN: usize = 2;
+
+/// Length of a frame carrying `samples` samples.
+pub const fn frame_len(samples: usize) -> usize {
+    HEADER_LEN + 2 * samples + TRAILER_LEN
+}
+
+/// What a frame says about its samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// Counts frames, so the host can spot missing ones.
+    pub sequence: u16,
+    /// The rate the samples were taken at, in Hz.
+    pub sample_rate: u32,
+    /// Whether the samples carry straight on from the previous frame's, as
+    /// opposed to starting a new burst.
+    pub contiguous: bool,
+}
+
+/// `out` is too short for the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooShort;
+
+/// CRC-16/CCITT-FALSE of `data`.
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ u16::from(byte) << 8, |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Encode `samples` into a frame at the start of `out`, returning its length.
+///
+/// At most 65535 samples fit in a frame.
+pub fn encode(header: Header, samples: &[u16], out: &mut [u8]) -> Result<usize, TooShort> {
+    assert!(samples.len() <= usize::from(u16::MAX), "too many samples");
+    let len = frame_len(samples.len());
+    let frame = out.get_mut(..len).ok_or(TooShort)?;
+
+    frame[0..2].copy_from_slice(&SYNC);
+    frame[2] = VERSION;
+    frame[3] = if header.contiguous {
+        FLAG_CONTIGUOUS
+    } else {
+        0
+    };
+    frame[4..6].copy_from_slice(&header.sequence.to_le_bytes());
+    frame[6..10].copy_from_slice(&header.sample_rate.to_le_bytes());
+    frame[10..12].copy_from_slice(&(samples.len() as u16).to_le_bytes());
+    for (chunk, sample) in frame[HEADER_LEN..].chunks_exact_mut(2).zip(samples) {
+        chunk.copy_from_slice(&sample.to_le_bytes());
+    }
+    let crc = crc16(&frame[2..len - TRAILER_LEN]);
+    frame[len - TRAILER_LEN..].copy_from_slice(&crc.to_le_bytes());
+    Ok(len)
+}
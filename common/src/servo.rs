@@ -0,0 +1,348 @@
+//! Hobby servo control over any PWM output.
+//!
+//! A [`Servo`] turns angles into pulse widths, using a per-servo
+//! [`Calibration`], and sends them to anything that implements
+//! [`PwmOutput`]. That can be one of the RP2040's own PWM channels, wrapped
+//! in [`NativePwm`], a channel of a [`PioServos`] state machine driving up to
+//! [`PIO_CHANNELS`] pins, or a channel of a PWM expander such as the
+//! [`Pca9685`](crate::pca9685::Pca9685), so a robot with more servos than the
+//! chip has PWM channels can drive them all the same way.
+//!
+//! ```ignore
+//! pwm.set_div_int(native_div_int);
+//! pwm.set_div_frac(native_div_frac);
+//! pwm.set_top(NATIVE_TOP);
+//! pwm.channel_a.output_to(pins.servo1);
+//! let mut shoulder = Servo::new(NativePwm::new(pwm.channel_a, NATIVE_TOP));
+//! let mut elbow = Servo::new(pca9685.channel(0));
+//! shoulder.set_angle(30)?;
+//! elbow.set_angle(-45)?;
+//! ```
+
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+
+use embedded_hal::PwmPin;
+use hal::pio::{
+    Buffers, PIOBuilder, PIOExt, PinDir, PinState, Running, ShiftDirection, StateMachine,
+    StateMachineIndex, Tx, UninitStateMachine, PIO,
+};
+
+use crate::hal;
+use crate::resources::{self, Resource};
+
+/// Servo refresh period, 50 Hz.
+pub const PERIOD_US: u32 = 20_000;
+
+/// The PWM counter top used with [`native_clock_divider`], the full 16 bits.
+pub const NATIVE_TOP: u16 = u16::MAX;
+
+/// Something that can output a servo pulse every [`PERIOD_US`].
+pub trait PwmOutput {
+    type Error;
+
+    /// Output a pulse `width_us` long every period; `0` turns the output off.
+    fn set_pulse_width_us(&mut self, width_us: u16) -> Result<(), Self::Error>;
+}
+
+impl<T: PwmOutput + ?Sized> PwmOutput for &mut T {
+    type Error = T::Error;
+
+    fn set_pulse_width_us(&mut self, width_us: u16) -> Result<(), Self::Error> {
+        (**self).set_pulse_width_us(width_us)
+    }
+}
+
+/// Work out the clock divider that gives a slice counting to [`NATIVE_TOP`] a
+/// period of [`PERIOD_US`], as the integer and fractional parts for the
+/// HAL's `set_div_int` and `set_div_frac`.
+pub fn native_clock_divider(sys_clk_hz: u32) -> (u8, u8) {
+    let counts = (u64::from(NATIVE_TOP) + 1) * 1_000_000 / u64::from(PERIOD_US);
+    // Round up, so the period is never shorter than intended
+    let sixteenths = (u64::from(sys_clk_hz) * 16).div_ceil(counts);
+    let sixteenths = sixteenths.clamp(16, 255 * 16 + 15);
+    ((sixteenths >> 4) as u8, (sixteenths & 0xF) as u8)
+}
+
+/// One of the RP2040's own PWM channels, running at [`PERIOD_US`].
+pub struct NativePwm<P> {
+    pin: P,
+    top: u16,
+}
+
+impl<P: PwmPin<Duty = u16>> NativePwm<P> {
+    /// Wrap a channel whose slice counts to `top` and wraps every
+    /// [`PERIOD_US`], and enable it.
+    pub fn new(mut pin: P, top: u16) -> Self {
+        pin.set_duty(0);
+        pin.enable();
+        NativePwm { pin, top }
+    }
+
+    /// Give the channel back.
+    pub fn free(self) -> P {
+        self.pin
+    }
+}
+
+impl<P: PwmPin<Duty = u16>> PwmOutput for NativePwm<P> {
+    type Error = Infallible;
+
+    fn set_pulse_width_us(&mut self, width_us: u16) -> Result<(), Infallible> {
+        let duty = (u32::from(self.top) + 1) * u32::from(width_us) / PERIOD_US;
+        self.pin.set_duty(duty.min(u32::from(self.top)) as u16);
+        Ok(())
+    }
+}
+
+/// Channels one [`PioServos`] state machine drives, and the slots in its
+/// frame.
+pub const PIO_CHANNELS: usize = 8;
+
+/// Each channel's share of the frame, in microseconds. Pulses are cut to fit,
+/// less the program's four cycles of overhead.
+const PIO_SLOT_US: u32 = PERIOD_US / PIO_CHANNELS as u32;
+
+/// Up to eight servos on consecutive pins, from one PIO state machine.
+///
+/// The frame is split into a 2.5 ms slot per channel, and each pulse starts
+/// at the beginning of its channel's slot, so the pulses never overlap and
+/// the current drawn by the servos is spread out. The state machine runs at
+/// 1 MHz and takes a FIFO word per slot, so the eight-word FIFO holds one
+/// whole frame; [`PioServos::update`] tops it up, and has to be called at
+/// least every 15 ms or so to keep the pulses coming. Setting a width through
+/// a channel calls it too.
+pub struct PioServos<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    feed: RefCell<PioFeed<P, SM>>,
+    widths: [Cell<u16>; PIO_CHANNELS],
+    count: u8,
+}
+
+struct PioFeed<P: PIOExt, SM: StateMachineIndex> {
+    tx: Tx<(P, SM)>,
+    /// The slot the next word is for.
+    slot: usize,
+}
+
+impl<P, SM> PioServos<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Drive `count` servos, 1 to [`PIO_CHANNELS`], on the pins from
+    /// `base_pin` up. All channels start off.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        base_pin: u8,
+        count: u8,
+        system_clock_hz: u32,
+    ) -> Self {
+        assert!(
+            (1..=PIO_CHANNELS as u8).contains(&count),
+            "a PIO servo state machine drives 1 to 8 channels"
+        );
+        // Each word is a one-hot pin mask, then the cycles high and low
+        let program = pio_proc::pio_asm!(
+            ".wrap_target",
+            "    pull block",
+            "    out pins, 8",
+            "    out x, 12",
+            "high:",
+            "    jmp x-- high",
+            "    mov pins, null",
+            "    out x, 12",
+            "low:",
+            "    jmp x-- low",
+            ".wrap",
+        );
+
+        // One cycle per microsecond
+        let divisor = (u64::from(system_clock_hz) << 8) / 1_000_000;
+        let (int, frac) = ((divisor >> 8) as u16, divisor as u8);
+
+        let installed = pio.install(&program.program).unwrap();
+        let (mut state, _, tx) = PIOBuilder::from_program(installed)
+            .out_pins(base_pin, count)
+            .out_shift_direction(ShiftDirection::Right)
+            .buffers(Buffers::OnlyTx)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        let pins = base_pin..base_pin + count;
+        let _ = resources::claim(Resource::pio_sm::<P, SM>(), "pio-servo");
+        for pin in pins.clone() {
+            let _ = resources::claim(Resource::Gpio(pin), "pio-servo");
+        }
+        state.set_pins(pins.clone().map(|pin| (pin, PinState::Low)));
+        state.set_pindirs(pins.map(|pin| (pin, PinDir::Output)));
+
+        let servos = PioServos {
+            _sm: state.start(),
+            feed: RefCell::new(PioFeed { tx, slot: 0 }),
+            widths: Default::default(),
+            count,
+        };
+        servos.update();
+        servos
+    }
+
+    /// A handle on channel `channel`, the pin `base_pin + channel`, to give
+    /// to a [`Servo`].
+    pub fn channel(&self, channel: u8) -> PioServo<'_, P, SM> {
+        assert!(channel < self.count, "no such PIO servo channel");
+        PioServo {
+            servos: self,
+            channel,
+        }
+    }
+
+    /// Set the pulse on one channel; `0` turns it off. Takes effect from
+    /// the channel's next slot that isn't already queued.
+    pub fn set_pulse_width_us(&self, channel: u8, width_us: u16) {
+        self.widths[usize::from(channel)].set(width_us);
+        self.update();
+    }
+
+    /// Queue slots until the FIFO is full.
+    pub fn update(&self) {
+        let mut feed = self.feed.borrow_mut();
+        while !feed.tx.is_full() {
+            let word = self.slot_word(feed.slot);
+            feed.tx.write(word);
+            feed.slot = (feed.slot + 1) % PIO_CHANNELS;
+        }
+    }
+
+    /// The FIFO word for `slot`: the pin mask in bits 0 to 7, then the
+    /// loop counts for the high and low parts, each three or four cycles
+    /// short for the instructions around the loops.
+    fn slot_word(&self, slot: usize) -> u32 {
+        let width = u32::from(self.widths[slot].get());
+        if slot >= usize::from(self.count) || width == 0 {
+            return (PIO_SLOT_US - 7) << 20;
+        }
+        let width = width.clamp(3, PIO_SLOT_US - 4);
+        1 << slot | (width - 3) << 8 | (PIO_SLOT_US - width - 4) << 20
+    }
+}
+
+/// One channel of a [`PioServos`].
+pub struct PioServo<'a, P: PIOExt, SM: StateMachineIndex> {
+    servos: &'a PioServos<P, SM>,
+    channel: u8,
+}
+
+impl<'a, P, SM> PwmOutput for PioServo<'a, P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    type Error = Infallible;
+
+    fn set_pulse_width_us(&mut self, width_us: u16) -> Result<(), Infallible> {
+        self.servos.set_pulse_width_us(self.channel, width_us);
+        Ok(())
+    }
+}
+
+/// The pulse widths a particular servo needs for its end stops and center.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    /// Pulse width at -90°.
+    pub min_us: u16,
+    /// Pulse width at 0°.
+    pub mid_us: u16,
+    /// Pulse width at +90°.
+    pub max_us: u16,
+}
+
+impl Calibration {
+    /// The usual 1 to 2 ms range. Most servos turn further than ±45° with
+    /// it, but none are damaged by it.
+    pub const STANDARD: Calibration = Calibration {
+        min_us: 1000,
+        mid_us: 1500,
+        max_us: 2000,
+    };
+
+    /// The 0.5 to 2.5 ms range most micro servos use for 180° of travel.
+    pub const WIDE: Calibration = Calibration {
+        min_us: 500,
+        mid_us: 1500,
+        max_us: 2500,
+    };
+
+    /// The pulse width for `degrees`, clamped to ±90°.
+    pub fn pulse_width_us(&self, degrees: i16) -> u16 {
+        let degrees = i32::from(degrees.clamp(-90, 90));
+        let (from, to) = if degrees < 0 {
+            (self.mid_us, self.min_us)
+        } else {
+            (self.mid_us, self.max_us)
+        };
+        let span = i32::from(to) - i32::from(from);
+        (i32::from(from) + span * degrees.abs() / 90) as u16
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration::STANDARD
+    }
+}
+
+/// A servo on a [`PwmOutput`].
+pub struct Servo<O> {
+    output: O,
+    calibration: Calibration,
+    angle: Option<i16>,
+}
+
+impl<O: PwmOutput> Servo<O> {
+    /// A servo using [`Calibration::STANDARD`]. The output stays off until the
+    /// first [`Servo::set_angle`].
+    pub fn new(output: O) -> Self {
+        Servo::with_calibration(output, Calibration::STANDARD)
+    }
+
+    /// A servo with its own calibration.
+    pub fn with_calibration(output: O, calibration: Calibration) -> Self {
+        Servo {
+            output,
+            calibration,
+            angle: None,
+        }
+    }
+
+    /// Turn to `degrees`, between -90 and 90.
+    pub fn set_angle(&mut self, degrees: i16) -> Result<(), O::Error> {
+        let degrees = degrees.clamp(-90, 90);
+        self.output
+            .set_pulse_width_us(self.calibration.pulse_width_us(degrees))?;
+        self.angle = Some(degrees);
+        Ok(())
+    }
+
+    /// The last angle set, or `None` if the servo is off.
+    pub fn angle(&self) -> Option<i16> {
+        self.angle
+    }
+
+    /// Stop sending pulses, letting the servo go limp.
+    pub fn disable(&mut self) -> Result<(), O::Error> {
+        self.output.set_pulse_width_us(0)?;
+        self.angle = None;
+        Ok(())
+    }
+
+    /// Change the calibration. Takes effect on the next [`Servo::set_angle`].
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// Give the output back.
+    pub fn free(self) -> O {
+        self.output
+    }
+}
@@ -0,0 +1,522 @@
+//! A small settings record kept in two sectors of flash.
+//!
+//! The record survives resets and reflashing of the program, as long as the
+//! program does not grow into the sectors. It is stored as:
+//!
+//! ```text
+//! magic "RPST" | length (u16) | version (u16) | sequence (u32) | CRC-32 | data
+//! ```
+//!
+//! and fits in a single 256 byte flash page, leaving [`MAX_LEN`] bytes for
+//! data. The CRC covers the length, version, sequence and data. [`Store::load`]
+//! only returns data whose CRC matches, so sectors that were never written, or
+//! an interrupted [`Store::save`], read as "no settings" rather than garbage.
+//!
+//! Each save goes into the next erased page of the current sector, numbered
+//! one higher than the record before, and the highest numbered valid record
+//! wins. Once all sixteen pages of a sector are used, the next save erases
+//! the other sector and starts over there, so the previous record is never
+//! erased before a newer one has been written: a save cut short at any point
+//! leaves the last complete record in place.
+//!
+//! The version tags the layout of the data. When firmware changes its layout,
+//! it bumps the version and registers a [`Migration`] from the old one, and
+//! [`Store::load_migrated`] upgrades records written by older firmware instead
+//! of misreading them:
+//!
+//! ```ignore
+//! const MIGRATIONS: &[Migration] = &[
+//!     // Version 1 added a brightness byte after the two existing ones
+//!     Migration { from: 0, migrate: |data, len| { data[len] = 255; Some(len + 1) } },
+//! ];
+//! let len = store.load_migrated(1, MIGRATIONS, &mut buf)?;
+//! ```
+//!
+//! Writing flash stops execute-in-place, so [`Store::save`] runs from RAM with
+//! interrupts disabled. The other core must not be running from flash while
+//! it does.
+
+use crate::flash_storage;
+pub use crate::flash_storage::{PAGE_SIZE, SECTOR_SIZE};
+
+/// The last two sectors of the [`flash_storage`] region, whatever the size of
+/// the board's flash and of the region.
+pub fn default_offset() -> u32 {
+    flash_storage::capacity().saturating_sub(2 * SECTOR_SIZE)
+}
+
+/// Largest record a [`Store`] can hold.
+pub const MAX_LEN: usize = PAGE_SIZE - HEADER_LEN;
+
+const HEADER_LEN: usize = 16;
+const MAGIC: [u8; 4] = *b"RPST";
+
+/// Pages in a sector, each of which can hold one record.
+const PAGES: usize = SECTOR_SIZE as usize / PAGE_SIZE;
+
+/// Errors from [`Store::save`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The data is longer than [`MAX_LEN`].
+    TooLarge,
+    /// The store's sectors aren't inside the [`flash_storage`] region.
+    Flash(flash_storage::Error),
+}
+
+/// Errors from [`Store::load_migrated`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    /// There is no valid record, or it doesn't fit in the buffer.
+    Empty,
+    /// The record was written by newer firmware, with this version. It is
+    /// left alone, so going back to that firmware finds it intact.
+    TooNew(u16),
+    /// No [`Migration`] upgrades records from this version.
+    NoMigration(u16),
+    /// The [`Migration`] from this version rejected the record.
+    Failed(u16),
+}
+
+/// Upgrades a record from version `from` to version `from + 1`.
+#[derive(Clone, Copy)]
+pub struct Migration {
+    /// The version this migration reads.
+    pub from: u16,
+    /// Rewrite the first `len` bytes of the record in place. Returns the new
+    /// length, or `None` if the record can't be converted.
+    pub migrate: fn(data: &mut [u8; MAX_LEN], len: usize) -> Option<usize>,
+}
+
+/// A settings record in two sectors at a fixed offset in the
+/// [`flash_storage`] region.
+pub struct Store {
+    offset: u32,
+}
+
+impl Store {
+    /// A store in the two sectors `offset` bytes into the [`flash_storage`]
+    /// region, which must be a multiple of [`SECTOR_SIZE`]. Sectors outside
+    /// the region load as empty and fail to save.
+    pub const fn new(offset: u32) -> Self {
+        Store { offset }
+    }
+
+    /// Copy the stored record into `buf`, whatever its version. Returns its
+    /// length, or `None` if there is no valid record or it doesn't fit in
+    /// `buf`.
+    pub fn load(&self, buf: &mut [u8]) -> Option<usize> {
+        self.load_version(buf).map(|(_, len)| len)
+    }
+
+    /// Copy the stored record into `buf`. Returns its version and length, or
+    /// `None` if there is no valid record or it doesn't fit in `buf`.
+    pub fn load_version(&self, buf: &mut [u8]) -> Option<(u16, usize)> {
+        let (_, record) = latest(self.sectors().ok()?)?;
+        let data = record.data;
+        if data.len() > buf.len() {
+            return None;
+        }
+        buf[..data.len()].copy_from_slice(data);
+        Some((record.version, data.len()))
+    }
+
+    /// Load the record into `buf`, upgrading it to `version` first if it was
+    /// written with an older one. Returns its length.
+    ///
+    /// Migrations run one version at a time, in RAM. The upgraded record is
+    /// then saved, so they only run once.
+    pub fn load_migrated(
+        &mut self,
+        version: u16,
+        migrations: &[Migration],
+        buf: &mut [u8],
+    ) -> Result<usize, MigrationError> {
+        let mut data = [0u8; MAX_LEN];
+        let (stored, len) = self.load_version(&mut data).ok_or(MigrationError::Empty)?;
+        let len = migrate(&mut data, len, stored, version, migrations)?;
+        if len > buf.len() {
+            return Err(MigrationError::Empty);
+        }
+        if stored < version {
+            // `migrate` checks the length against MAX_LEN, and the sectors
+            // were just read. Should the save still fail, the migration
+            // simply runs again next time.
+            let _ = self.save_version(version, &data[..len]);
+        }
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    /// Replace the stored record with `data`, tagged as version 0.
+    pub fn save(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.save_version(0, data)
+    }
+
+    /// Replace the stored record with `data`, tagged as `version`.
+    pub fn save_version(&mut self, version: u16, data: &[u8]) -> Result<(), Error> {
+        let sectors = self.sectors().map_err(Error::Flash)?;
+        let sequence = latest(sectors).map_or(0, |(_, record)| record.sequence.wrapping_add(1));
+        let mut page = [0xFFu8; PAGE_SIZE];
+        encode(version, sequence, data, &mut page)?;
+
+        let next = next_page(sectors);
+        let sector = self.offset + next.sector as u32 * SECTOR_SIZE;
+        if next.erase {
+            flash_storage::erase_sector(sector).map_err(Error::Flash)?;
+        }
+        flash_storage::program_page(sector + (next.page * PAGE_SIZE) as u32, &page)
+            .map_err(Error::Flash)
+    }
+
+    /// Both sectors, read through XIP.
+    fn sectors(&self) -> Result<[&'static [u8]; 2], flash_storage::Error> {
+        if !self.offset.is_multiple_of(SECTOR_SIZE) {
+            return Err(flash_storage::Error::Unaligned);
+        }
+        let sector =
+            |index: u32| flash_storage::mapped(self.offset + index * SECTOR_SIZE, SECTOR_SIZE);
+        Ok([sector(0)?, sector(1)?])
+    }
+}
+
+/// A valid record, as read from a page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Record<'a> {
+    version: u16,
+    sequence: u32,
+    data: &'a [u8],
+}
+
+/// Where the next record goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct NextPage {
+    sector: usize,
+    page: usize,
+    /// Whether the sector has to be erased first.
+    erase: bool,
+}
+
+/// Fill `page` with a record of `data`.
+fn encode(
+    version: u16,
+    sequence: u32,
+    data: &[u8],
+    page: &mut [u8; PAGE_SIZE],
+) -> Result<(), Error> {
+    if data.len() > MAX_LEN {
+        return Err(Error::TooLarge);
+    }
+    page[0..4].copy_from_slice(&MAGIC);
+    page[4..6].copy_from_slice(&(data.len() as u16).to_le_bytes());
+    page[6..8].copy_from_slice(&version.to_le_bytes());
+    page[8..12].copy_from_slice(&sequence.to_le_bytes());
+    page[HEADER_LEN..HEADER_LEN + data.len()].copy_from_slice(data);
+    let crc = record_crc(&page[4..12], data);
+    page[12..16].copy_from_slice(&crc.to_le_bytes());
+    Ok(())
+}
+
+/// The record in `page`, if it holds a valid one.
+fn decode(page: &[u8]) -> Option<Record<'_>> {
+    if page.len() < PAGE_SIZE || page[0..4] != MAGIC {
+        return None;
+    }
+    let len = usize::from(u16::from_le_bytes([page[4], page[5]]));
+    if len > MAX_LEN {
+        return None;
+    }
+    let crc = u32::from_le_bytes([page[12], page[13], page[14], page[15]]);
+    let data = &page[HEADER_LEN..HEADER_LEN + len];
+    if record_crc(&page[4..12], data) != crc {
+        return None;
+    }
+    Some(Record {
+        version: u16::from_le_bytes([page[6], page[7]]),
+        sequence: u32::from_le_bytes([page[8], page[9], page[10], page[11]]),
+        data,
+    })
+}
+
+/// CRC-32 of a record's length, version and sequence, then its data.
+fn record_crc(header: &[u8], data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in header.iter().chain(data) {
+        crc = crc32_byte(crc, byte);
+    }
+    !crc
+}
+
+/// The sector holding the highest numbered valid record, and the record.
+fn latest<'a>(sectors: [&'a [u8]; 2]) -> Option<(usize, Record<'a>)> {
+    sectors
+        .iter()
+        .enumerate()
+        .flat_map(|(sector, data)| data.chunks(PAGE_SIZE).map(move |page| (sector, page)))
+        .filter_map(|(sector, page)| Some((sector, decode(page)?)))
+        .max_by_key(|(_, record)| record.sequence)
+}
+
+/// The first erased page after the last one written in the sector with the
+/// latest record, or the start of the other sector once that one is full.
+fn next_page(sectors: [&[u8]; 2]) -> NextPage {
+    let is_erased = |page: &[u8]| page.iter().all(|&byte| byte == 0xFF);
+    let current = latest(sectors).map_or(0, |(sector, _)| sector);
+    let next = sectors[current]
+        .chunks(PAGE_SIZE)
+        .rposition(|page| !is_erased(page))
+        .map_or(0, |last| last + 1);
+    if next < PAGES {
+        // Pages written by a save that was cut short are skipped, as
+        // programming can't set their bits back
+        let erase = latest(sectors).is_none() && !is_erased(sectors[current]);
+        return NextPage {
+            sector: current,
+            page: if erase { 0 } else { next },
+            erase,
+        };
+    }
+    // The current sector stays intact until the new record is written
+    let other = 1 - current;
+    NextPage {
+        sector: other,
+        page: 0,
+        erase: !is_erased(sectors[other]),
+    }
+}
+
+/// Upgrade the first `len` bytes of `data` from version `stored` to
+/// `version`, one [`Migration`] at a time. Returns the new length.
+fn migrate(
+    data: &mut [u8; MAX_LEN],
+    mut len: usize,
+    mut stored: u16,
+    version: u16,
+    migrations: &[Migration],
+) -> Result<usize, MigrationError> {
+    if stored > version {
+        return Err(MigrationError::TooNew(stored));
+    }
+    while stored < version {
+        let migration = migrations
+            .iter()
+            .find(|migration| migration.from == stored)
+            .ok_or(MigrationError::NoMigration(stored))?;
+        len = (migration.migrate)(data, len)
+            .filter(|&len| len <= MAX_LEN)
+            .ok_or(MigrationError::Failed(stored))?;
+        stored += 1;
+    }
+    Ok(len)
+}
+
+/// CRC-32 (IEEE 802.3), as used by zlib.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| crc32_byte(crc, byte))
+}
+
+fn crc32_byte(crc: u32, byte: u8) -> u32 {
+    (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+        if crc & 1 != 0 {
+            (crc >> 1) ^ 0xEDB8_8320
+        } else {
+            crc >> 1
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTOR: usize = SECTOR_SIZE as usize;
+
+    /// Two erased sectors.
+    fn erased() -> [[u8; SECTOR]; 2] {
+        [[0xFF; SECTOR]; 2]
+    }
+
+    /// Save `data` the way [`Store::save_version`] does, into RAM.
+    fn save(flash: &mut [[u8; SECTOR]; 2], version: u16, data: &[u8]) {
+        let sequence = latest([&flash[0], &flash[1]]).map_or(0, |(_, record)| record.sequence + 1);
+        let mut page = [0xFF; PAGE_SIZE];
+        encode(version, sequence, data, &mut page).unwrap();
+        let next = next_page([&flash[0], &flash[1]]);
+        let sector = &mut flash[next.sector];
+        if next.erase {
+            sector.fill(0xFF);
+        }
+        sector[next.page * PAGE_SIZE..][..PAGE_SIZE].copy_from_slice(&page);
+    }
+
+    fn load(flash: &[[u8; SECTOR]; 2]) -> Option<(u16, &[u8])> {
+        latest([&flash[0], &flash[1]]).map(|(_, record)| (record.version, record.data))
+    }
+
+    /// Version 1 stored a brightness and a volume; version 2 added a
+    /// contrast byte, defaulting to 128.
+    const V1_TO_V2: Migration = Migration {
+        from: 1,
+        migrate: |data, len| {
+            if len != 2 {
+                return None;
+            }
+            data[2] = 128;
+            Some(3)
+        },
+    };
+
+    #[test]
+    fn crc32_matches_zlib() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut flash = erased();
+        assert_eq!(load(&flash), None);
+        save(&mut flash, 3, b"hello");
+        assert_eq!(load(&flash), Some((3, &b"hello"[..])));
+    }
+
+    #[test]
+    fn migrates_v1_to_v2() {
+        let mut flash = erased();
+        save(&mut flash, 1, &[200, 7]);
+
+        let (stored, old) = load(&flash).unwrap();
+        let mut data = [0; MAX_LEN];
+        data[..old.len()].copy_from_slice(old);
+        let len = migrate(&mut data, old.len(), stored, 2, &[V1_TO_V2]).unwrap();
+        assert_eq!(&data[..len], &[200, 7, 128]);
+    }
+
+    #[test]
+    fn migration_rejecting_the_record_fails() {
+        let mut data = [0; MAX_LEN];
+        assert_eq!(
+            migrate(&mut data, 5, 1, 2, &[V1_TO_V2]),
+            Err(MigrationError::Failed(1))
+        );
+    }
+
+    #[test]
+    fn unknown_old_version_has_no_migration() {
+        let mut data = [0; MAX_LEN];
+        assert_eq!(
+            migrate(&mut data, 2, 0, 2, &[V1_TO_V2]),
+            Err(MigrationError::NoMigration(0))
+        );
+    }
+
+    #[test]
+    fn newer_version_is_left_alone() {
+        let mut data = [0; MAX_LEN];
+        assert_eq!(
+            migrate(&mut data, 3, 5, 2, &[V1_TO_V2]),
+            Err(MigrationError::TooNew(5))
+        );
+    }
+
+    #[test]
+    fn corrupt_record_fails_its_crc() {
+        let mut flash = erased();
+        save(&mut flash, 0, b"first");
+        save(&mut flash, 0, b"second");
+        // Flip a data bit of the second record
+        flash[0][PAGE_SIZE + HEADER_LEN] ^= 0x01;
+        assert_eq!(decode(&flash[0][PAGE_SIZE..]), None);
+        assert_eq!(load(&flash), Some((0, &b"first"[..])));
+    }
+
+    #[test]
+    fn corrupt_header_fails_its_crc() {
+        let mut flash = erased();
+        save(&mut flash, 1, b"data");
+        // A different version, as a torn write might leave
+        flash[0][6] ^= 0x02;
+        assert_eq!(load(&flash), None);
+    }
+
+    #[test]
+    fn interrupted_save_keeps_the_previous_record() {
+        let mut flash = erased();
+        save(&mut flash, 0, b"old");
+        // Half of the next page written, then the power went
+        flash[0][PAGE_SIZE..PAGE_SIZE + 10].fill(0x00);
+        assert_eq!(load(&flash), Some((0, &b"old"[..])));
+
+        save(&mut flash, 0, b"new");
+        assert_eq!(load(&flash), Some((0, &b"new"[..])));
+        assert_eq!(decode(&flash[0][2 * PAGE_SIZE..]).unwrap().data, b"new");
+    }
+
+    #[test]
+    fn full_sector_moves_to_the_other_without_erasing_itself() {
+        let mut flash = erased();
+        for count in 0..PAGES as u8 {
+            save(&mut flash, 0, &[count]);
+        }
+        let next = next_page([&flash[0], &flash[1]]);
+        assert_eq!(
+            next,
+            NextPage {
+                sector: 1,
+                page: 0,
+                erase: false
+            }
+        );
+
+        save(&mut flash, 0, &[100]);
+        assert_eq!(load(&flash), Some((0, &[100][..])));
+        // The full sector is still there, in case that save had been cut short
+        assert_eq!(
+            decode(&flash[0][(PAGES - 1) * PAGE_SIZE..]).unwrap().data,
+            &[15]
+        );
+    }
+
+    #[test]
+    fn wraps_back_to_the_first_sector() {
+        let mut flash = erased();
+        for count in 0..2 * PAGES as u8 + 1 {
+            save(&mut flash, 0, &[count]);
+        }
+        assert_eq!(load(&flash), Some((0, &[2 * PAGES as u8][..])));
+        // Sector 0 was erased for the new record, after sector 1 filled up
+        assert_eq!(
+            decode(&flash[0][..PAGE_SIZE]).unwrap().data,
+            &[2 * PAGES as u8]
+        );
+        assert!(flash[0][PAGE_SIZE..].iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn interrupted_erase_keeps_the_full_sector() {
+        let mut flash = erased();
+        for count in 0..2 * PAGES as u8 {
+            save(&mut flash, 0, &[count]);
+        }
+        // Erasing sector 0 for the next record stopped half way, leaving
+        // some of its old, lower numbered records
+        flash[0][..SECTOR / 2].fill(0xFF);
+        assert_eq!(load(&flash), Some((0, &[2 * PAGES as u8 - 1][..])));
+
+        save(&mut flash, 0, &[99]);
+        assert_eq!(load(&flash), Some((0, &[99][..])));
+    }
+
+    #[test]
+    fn garbage_without_a_record_is_erased() {
+        let mut flash = erased();
+        flash[0][..8].fill(0x00);
+        let next = next_page([&flash[0], &flash[1]]);
+        assert_eq!(
+            next,
+            NextPage {
+                sector: 0,
+                page: 0,
+                erase: true
+            }
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! A line-oriented command shell, for poking at firmware from a terminal.
+//!
+//! Bytes typed at the terminal are fed to [`Shell::feed`] one at a time. The
+//! shell echoes them, handles backspace, Ctrl-C and Ctrl-U, and once Enter is
+//! pressed splits the line into words and runs the [`Command`] named by the
+//! first one. `help` lists the commands.
+//!
+//! Handlers are plain functions, or closures that capture nothing. Whatever
+//! they share, such as pins and peripherals, goes in a context value of the
+//! application's choosing, which is handed to every handler:
+//!
+//! ```ignore
+//! let commands = [Command {
+//!     name: "led",
+//!     help: "led on|off",
+//!     run: |led: &mut LedPin, args, _out| match args.next() {
+//!         Some("on") => led.set_high().map_err(|_| "can't drive the LED"),
+//!         Some("off") => led.set_low().map_err(|_| "can't drive the LED"),
+//!         _ => Err("expected on or off"),
+//!     },
+//! }];
+//! let mut shell: Shell<_, 64> = Shell::new(&commands);
+//! // ...
+//! for &byte in &buf[..count] {
+//!     shell.feed(byte, &mut led, &mut serial).ok();
+//! }
+//! ```
+//!
+//! Output goes to any `core::fmt::Write`, such as a
+//! [`BufferedSerial`](crate::buffered_serial::BufferedSerial). Lines end in
+//! `\r\n`, as terminals expect.
+//!
+//! [`resources`] is a ready-made `resources` command, printing what the
+//! [`resources`](crate::resources) registry has recorded.
+
+use core::fmt::Write;
+
+/// Printed before each line.
+pub const PROMPT: &str = "> ";
+
+/// The words after the command name.
+pub type Args<'a> = core::str::SplitWhitespace<'a>;
+
+/// Runs a command with the context, its arguments and somewhere to print.
+/// An `Err` is printed as the command's error message.
+pub type Handler<C> = fn(&mut C, &mut Args<'_>, &mut dyn Write) -> Result<(), &'static str>;
+
+/// An entry in the shell's command table.
+pub struct Command<C> {
+    /// What to type to run it.
+    pub name: &'static str,
+    /// One line of usage, shown by `help`.
+    pub help: &'static str,
+    /// What it does.
+    pub run: Handler<C>,
+}
+
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7F;
+const CTRL_C: u8 = 0x03;
+const CTRL_U: u8 = 0x15;
+const ESCAPE: u8 = 0x1B;
+
+/// Where the shell is in a terminal escape sequence, which it skips.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    /// After ESC.
+    Start,
+    /// After `ESC [`, until the final byte.
+    Csi,
+}
+
+/// A shell with a line buffer of `N` bytes, running commands that take a
+/// context of type `C`.
+pub struct Shell<'a, C, const N: usize> {
+    commands: &'a [Command<C>],
+    line: [u8; N],
+    len: usize,
+    escape: Escape,
+    last: u8,
+}
+
+impl<'a, C, const N: usize> Shell<'a, C, N> {
+    /// A shell running `commands`.
+    pub fn new(commands: &'a [Command<C>]) -> Self {
+        Shell {
+            commands,
+            line: [0; N],
+            len: 0,
+            escape: Escape::None,
+            last: 0,
+        }
+    }
+
+    /// Print the prompt, e.g. when a terminal connects.
+    pub fn prompt(&self, out: &mut dyn Write) -> core::fmt::Result {
+        out.write_str(PROMPT)
+    }
+
+    /// Handle one byte from the terminal, echoing it and running the line
+    /// when it is complete.
+    ///
+    /// Characters typed once the line buffer is full are dropped, and only
+    /// printable ASCII makes it into the line.
+    pub fn feed(&mut self, byte: u8, context: &mut C, out: &mut dyn Write) -> core::fmt::Result {
+        let last = core::mem::replace(&mut self.last, byte);
+        match (self.escape, byte) {
+            (Escape::Start, b'[') => self.escape = Escape::Csi,
+            (Escape::Csi, 0x20..=0x3F) => {}
+            (Escape::Start, _) | (Escape::Csi, _) => self.escape = Escape::None,
+            (Escape::None, ESCAPE) => self.escape = Escape::Start,
+            // Terminals send CR, LF or both: only run the line once
+            (Escape::None, b'\n') if last == b'\r' => {}
+            (Escape::None, b'\r') | (Escape::None, b'\n') => {
+                out.write_str("\r\n")?;
+                let len = core::mem::replace(&mut self.len, 0);
+                // Only printable ASCII gets in, so this can't fail
+                let line = core::str::from_utf8(&self.line[..len]).unwrap_or("");
+                run(self.commands, line, context, out)?;
+                out.write_str(PROMPT)?;
+            }
+            (Escape::None, BACKSPACE) | (Escape::None, DELETE) => {
+                if self.len > 0 {
+                    self.len -= 1;
+                    out.write_str("\x08 \x08")?;
+                }
+            }
+            (Escape::None, CTRL_C) | (Escape::None, CTRL_U) => {
+                self.len = 0;
+                out.write_str("^C\r\n")?;
+                out.write_str(PROMPT)?;
+            }
+            (Escape::None, 0x20..=0x7E) => {
+                if self.len < N {
+                    self.line[self.len] = byte;
+                    self.len += 1;
+                    out.write_char(char::from(byte))?;
+                }
+            }
+            (Escape::None, _) => {}
+        }
+        Ok(())
+    }
+
+    /// Run a whole line, as if it had been typed.
+    pub fn run_line(&self, line: &str, context: &mut C, out: &mut dyn Write) -> core::fmt::Result {
+        run(self.commands, line, context, out)
+    }
+}
+
+/// Look up the command the line starts with and run it.
+fn run<C>(
+    commands: &[Command<C>],
+    line: &str,
+    context: &mut C,
+    out: &mut dyn Write,
+) -> core::fmt::Result {
+    let mut args = line.split_whitespace();
+    let name = match args.next() {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    if name == "help" {
+        for command in commands {
+            write!(out, "{}\r\n", command.help)?;
+        }
+        return out.write_str("help\r\n");
+    }
+
+    match commands.iter().find(|command| command.name == name) {
+        Some(command) => match (command.run)(context, &mut args, out) {
+            Ok(()) => Ok(()),
+            Err(message) => write!(out, "{}: {}\r\n", name, message),
+        },
+        None => write!(out, "{}: unknown command, try help\r\n", name),
+    }
+}
+
+/// The `resources` shell command: print the
+/// [`resources`](crate::resources) registry's allocation table.
+pub fn resources(_args: &mut Args<'_>, mut out: &mut dyn Write) -> Result<(), &'static str> {
+    crate::resources::write_report(&mut out).map_err(|_| "output failed")
+}
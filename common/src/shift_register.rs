@@ -0,0 +1,241 @@
+//! 74HC595 and 74HC165 shift registers as extra GPIOs.
+//!
+//! A chain of 74HC595s adds eight outputs per chip, a chain of 74HC165s eight
+//! inputs per chip, and either costs three GPIOs however long it gets: SPI
+//! clock, one SPI data line and a latch. The chains' pins implement the
+//! embedded-hal `OutputPin` and `InputPin` traits, so they drive LEDs and read
+//! keys like any other GPIO, or, read all at once, feed a
+//! [`keyboard::Debouncer`](crate::keyboard::Debouncer).
+//!
+//! Pins are numbered from the chip wired to the RP2040: 0 to 7 are its
+//! `Q0`-`Q7` (or `D0`-`D7`), 8 to 15 those of the next chip, and so on.
+//!
+//! Run the SPI bus in mode 0, MSB first, at up to about 20 MHz for short
+//! wires. The 595s only need MOSI and the 165s only MISO, so the two chains can
+//! use separate SPI blocks and each keep their own bus.
+
+use core::cell::RefCell;
+
+use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+/// Errors from the shift register chains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<S, P> {
+    /// The SPI bus failed.
+    Spi(S),
+    /// The latch or load pin failed.
+    Pin(P),
+}
+
+struct Outputs<SPI, LATCH, const N: usize> {
+    spi: SPI,
+    latch: LATCH,
+    levels: [u8; N],
+}
+
+impl<SPI, LATCH, SE, PE, const N: usize> Outputs<SPI, LATCH, N>
+where
+    SPI: Write<u8, Error = SE>,
+    LATCH: OutputPin<Error = PE>,
+{
+    fn update(&mut self) -> Result<(), Error<SE, PE>> {
+        // The last byte shifted in stays in the first chip
+        let mut bytes = self.levels;
+        bytes.reverse();
+        self.latch.set_low().map_err(Error::Pin)?;
+        self.spi.write(&bytes).map_err(Error::Spi)?;
+        // The rising edge copies the shift register to the outputs
+        self.latch.set_high().map_err(Error::Pin)
+    }
+}
+
+/// A chain of `N` 74HC595 output shift registers.
+///
+/// `SER` of the first chip goes to MOSI, `SRCLK` of every chip to SCK and
+/// `RCLK` of every chip to `latch`. Tie `OE` low and `SRCLR` high.
+pub struct Hc595<SPI, LATCH, const N: usize> {
+    inner: RefCell<Outputs<SPI, LATCH, N>>,
+}
+
+impl<SPI, LATCH, SE, PE, const N: usize> Hc595<SPI, LATCH, N>
+where
+    SPI: Write<u8, Error = SE>,
+    LATCH: OutputPin<Error = PE>,
+{
+    /// Take over the chain and set every output low.
+    pub fn new(spi: SPI, latch: LATCH) -> Result<Self, Error<SE, PE>> {
+        let mut inner = Outputs {
+            spi,
+            latch,
+            levels: [0; N],
+        };
+        inner.update()?;
+        Ok(Hc595 {
+            inner: RefCell::new(inner),
+        })
+    }
+
+    /// One output, for use as an `OutputPin`.
+    pub fn output(&self, pin: u8) -> ShiftOutput<'_, SPI, LATCH, N> {
+        let (byte, mask) = position(pin, N);
+        ShiftOutput {
+            chain: self,
+            byte,
+            mask,
+        }
+    }
+
+    /// Set every output at once, one byte per chip starting with the first.
+    pub fn write_all(&self, levels: [u8; N]) -> Result<(), Error<SE, PE>> {
+        let mut inner = self.inner.borrow_mut();
+        inner.levels = levels;
+        inner.update()
+    }
+
+    /// The levels last written, one byte per chip.
+    pub fn levels(&self) -> [u8; N] {
+        self.inner.borrow().levels
+    }
+
+    /// Return the SPI bus and the latch pin.
+    pub fn free(self) -> (SPI, LATCH) {
+        let inner = self.inner.into_inner();
+        (inner.spi, inner.latch)
+    }
+}
+
+/// One output of a [`Hc595`] chain.
+///
+/// Every change shifts the whole chain out again. To change many outputs at
+/// once, use [`Hc595::write_all`] instead.
+pub struct ShiftOutput<'a, SPI, LATCH, const N: usize> {
+    chain: &'a Hc595<SPI, LATCH, N>,
+    byte: usize,
+    mask: u8,
+}
+
+impl<'a, SPI, LATCH, SE, PE, const N: usize> OutputPin for ShiftOutput<'a, SPI, LATCH, N>
+where
+    SPI: Write<u8, Error = SE>,
+    LATCH: OutputPin<Error = PE>,
+{
+    type Error = Error<SE, PE>;
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut inner = self.chain.inner.borrow_mut();
+        inner.levels[self.byte] |= self.mask;
+        inner.update()
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let mut inner = self.chain.inner.borrow_mut();
+        inner.levels[self.byte] &= !self.mask;
+        inner.update()
+    }
+}
+
+struct Inputs<SPI, LOAD> {
+    spi: SPI,
+    load: LOAD,
+}
+
+/// A chain of `N` 74HC165 input shift registers.
+///
+/// `QH` of the first chip goes to MISO, `SER` of each chip to `QH` of the
+/// next, `CLK` of every chip to SCK and `SH/LD` of every chip to `load`. Tie
+/// `CLK INH` low.
+pub struct Hc165<SPI, LOAD, const N: usize> {
+    inner: RefCell<Inputs<SPI, LOAD>>,
+}
+
+impl<SPI, LOAD, SE, PE, const N: usize> Hc165<SPI, LOAD, N>
+where
+    SPI: Transfer<u8, Error = SE>,
+    LOAD: OutputPin<Error = PE>,
+{
+    /// Take over the chain.
+    pub fn new(spi: SPI, mut load: LOAD) -> Result<Self, Error<SE, PE>> {
+        load.set_high().map_err(Error::Pin)?;
+        Ok(Hc165 {
+            inner: RefCell::new(Inputs { spi, load }),
+        })
+    }
+
+    /// Latch and read every input, one byte per chip starting with the first.
+    pub fn read_all(&self) -> Result<[u8; N], Error<SE, PE>> {
+        let mut inner = self.inner.borrow_mut();
+        // Pulsing SH/LD low copies the inputs into the shift register
+        inner.load.set_low().map_err(Error::Pin)?;
+        inner.load.set_high().map_err(Error::Pin)?;
+        let mut bytes = [0; N];
+        inner.spi.transfer(&mut bytes).map_err(Error::Spi)?;
+        Ok(bytes)
+    }
+
+    /// Read every input as one row of keys for a
+    /// [`keyboard::Debouncer`](crate::keyboard::Debouncer), with `active_low`
+    /// for buttons that pull their input to ground.
+    ///
+    /// `COLS` must be at most `8 * N`.
+    pub fn scan<const COLS: usize>(
+        &self,
+        active_low: bool,
+    ) -> Result<[[bool; COLS]; 1], Error<SE, PE>> {
+        assert!(COLS <= 8 * N, "more columns than the chain has inputs");
+        let bytes = self.read_all()?;
+        let mut keys = [false; COLS];
+        for (pin, key) in keys.iter_mut().enumerate() {
+            *key = (bytes[pin / 8] & (1 << (pin % 8)) != 0) != active_low;
+        }
+        Ok([keys])
+    }
+
+    /// One input, for use as an `InputPin`.
+    pub fn input(&self, pin: u8) -> ShiftInput<'_, SPI, LOAD, N> {
+        let (byte, mask) = position(pin, N);
+        ShiftInput {
+            chain: self,
+            byte,
+            mask,
+        }
+    }
+
+    /// Return the SPI bus and the load pin.
+    pub fn free(self) -> (SPI, LOAD) {
+        let inner = self.inner.into_inner();
+        (inner.spi, inner.load)
+    }
+}
+
+/// One input of a [`Hc165`] chain.
+///
+/// Every read shifts the whole chain in. To read many inputs at once, use
+/// [`Hc165::read_all`] instead.
+pub struct ShiftInput<'a, SPI, LOAD, const N: usize> {
+    chain: &'a Hc165<SPI, LOAD, N>,
+    byte: usize,
+    mask: u8,
+}
+
+impl<'a, SPI, LOAD, SE, PE, const N: usize> InputPin for ShiftInput<'a, SPI, LOAD, N>
+where
+    SPI: Transfer<u8, Error = SE>,
+    LOAD: OutputPin<Error = PE>,
+{
+    type Error = Error<SE, PE>;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.chain.read_all()?[self.byte] & self.mask != 0)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+fn position(pin: u8, chips: usize) -> (usize, u8) {
+    let pin = usize::from(pin);
+    assert!(pin < 8 * chips, "pin number beyond the end of the chain");
+    (pin / 8, 1 << (pin % 8))
+}
@@ -0,0 +1,212 @@
+//! Safe wrappers over the SIO's hardware divider and interpolators.
+//!
+//! [`DivRem`] gives the HAL's [`HwDivider`] checked division, returning
+//! `None` for a zero divisor, and for `i32::MIN / -1`, rather than the
+//! hardware's made-up answer:
+//!
+//! ```ignore
+//! let (quotient, remainder) = sio.hwdivider.div_rem(1000, 7).unwrap();
+//! ```
+//!
+//! [`InterpLanes`] sets up an interpolator lane from a [`LaneConfig`], whose
+//! shift and mask are checked when it is built, at compile time in a
+//! `const`:
+//!
+//! ```ignore
+//! const INDEX: LaneConfig = LaneConfig::new().shift(16).mask(0, 7);
+//! sio.interp0.lane_config(LaneId::Lane0, INDEX);
+//! ```
+//!
+//! The divider is one per core, and the HAL's division intrinsics use it too,
+//! saving and restoring its state, so plain `/` on `u32` is already
+//! hardware-backed unless the HAL's `disable-intrinsics` feature is on.
+
+use crate::hal::sio::{HwDivider, Interp0, Interp1, Lane, LaneCtrl};
+
+/// Checked division on the hardware divider.
+pub trait DivRem {
+    /// `dividend / divisor` and `dividend % divisor`, or `None` if `divisor`
+    /// is zero.
+    fn div_rem(&self, dividend: u32, divisor: u32) -> Option<(u32, u32)>;
+
+    /// `dividend / divisor` and `dividend % divisor`, rounding towards zero,
+    /// or `None` if `divisor` is zero or the quotient overflows.
+    fn div_rem_signed(&self, dividend: i32, divisor: i32) -> Option<(i32, i32)>;
+}
+
+impl DivRem for HwDivider {
+    fn div_rem(&self, dividend: u32, divisor: u32) -> Option<(u32, u32)> {
+        if divisor == 0 {
+            return None;
+        }
+        let result = self.unsigned(dividend, divisor);
+        Some((result.quotient, result.remainder))
+    }
+
+    fn div_rem_signed(&self, dividend: i32, divisor: i32) -> Option<(i32, i32)> {
+        if divisor == 0 || (dividend == i32::MIN && divisor == -1) {
+            return None;
+        }
+        let result = self.signed(dividend, divisor);
+        Some((result.quotient, result.remainder))
+    }
+}
+
+/// One of an interpolator's two lanes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LaneId {
+    Lane0,
+    Lane1,
+}
+
+/// How an interpolator lane turns its accumulator into a result.
+///
+/// The lane shifts its input right by [`shift`](Self::shift), keeps the bits
+/// from [`mask`](Self::mask), optionally sign-extends them, and adds its base.
+/// [`new`](Self::new) passes the accumulator through unchanged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LaneConfig {
+    shift: u8,
+    mask_lsb: u8,
+    mask_msb: u8,
+    signed: bool,
+    cross_input: bool,
+    cross_result: bool,
+    add_raw: bool,
+    force_msb: u8,
+    blend: bool,
+    clamp: bool,
+}
+
+impl LaneConfig {
+    /// No shift, every bit kept, unsigned.
+    pub const fn new() -> Self {
+        LaneConfig {
+            shift: 0,
+            mask_lsb: 0,
+            mask_msb: 31,
+            signed: false,
+            cross_input: false,
+            cross_result: false,
+            add_raw: false,
+            force_msb: 0,
+            blend: false,
+            clamp: false,
+        }
+    }
+
+    /// Shift the input right by `bits`, up to 31.
+    pub const fn shift(mut self, bits: u8) -> Self {
+        assert!(bits <= 31, "interpolator shift above 31");
+        self.shift = bits;
+        self
+    }
+
+    /// Keep bits `lsb` to `msb` of the shifted input, both included.
+    pub const fn mask(mut self, lsb: u8, msb: u8) -> Self {
+        assert!(msb <= 31, "interpolator mask above bit 31");
+        assert!(lsb <= msb, "interpolator mask with lsb above msb");
+        self.mask_lsb = lsb;
+        self.mask_msb = msb;
+        self
+    }
+
+    /// Sign-extend the masked value from its top bit before adding the base.
+    pub const fn signed(mut self) -> Self {
+        self.signed = true;
+        self
+    }
+
+    /// Take the other lane's accumulator as input.
+    pub const fn cross_input(mut self) -> Self {
+        self.cross_input = true;
+        self
+    }
+
+    /// Write the other lane's result back to this accumulator on a pop.
+    pub const fn cross_result(mut self) -> Self {
+        self.cross_result = true;
+        self
+    }
+
+    /// Add the raw input, not the shifted and masked one, to the accumulator
+    /// on a pop.
+    pub const fn add_raw(mut self) -> Self {
+        self.add_raw = true;
+        self
+    }
+
+    /// OR `bits`, up to 3, into bits 28 and 29 of the result, to turn an
+    /// address into one of its bus aliases.
+    pub const fn force_msb(mut self, bits: u8) -> Self {
+        assert!(bits <= 3, "interpolator force_msb above 3");
+        self.force_msb = bits;
+        self
+    }
+
+    /// Blend between the two bases by lane 1's input. Only on lane 0 of
+    /// interpolator 0.
+    pub const fn blend(mut self) -> Self {
+        self.blend = true;
+        self
+    }
+
+    /// Clamp the result between the two bases. Only on lane 0 of
+    /// interpolator 1.
+    pub const fn clamp(mut self) -> Self {
+        self.clamp = true;
+        self
+    }
+
+    /// The lane's control register value.
+    pub fn encode(&self) -> u32 {
+        LaneCtrl {
+            clamp: self.clamp,
+            blend: self.blend,
+            force_msb: self.force_msb,
+            add_raw: self.add_raw,
+            cross_result: self.cross_result,
+            cross_input: self.cross_input,
+            signed: self.signed,
+            mask_msb: self.mask_msb,
+            mask_lsb: self.mask_lsb,
+            shift: self.shift,
+        }
+        .encode()
+    }
+}
+
+impl Default for LaneConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lane access and set-up for both interpolators.
+pub trait InterpLanes {
+    /// One of the interpolator's lanes.
+    fn lane(&mut self, lane: LaneId) -> &mut dyn Lane;
+
+    /// Set `lane` up from `config`.
+    fn lane_config(&mut self, lane: LaneId, config: LaneConfig) {
+        self.lane(lane).set_ctrl(config.encode());
+    }
+}
+
+impl InterpLanes for Interp0 {
+    fn lane(&mut self, lane: LaneId) -> &mut dyn Lane {
+        match lane {
+            LaneId::Lane0 => self.get_lane0(),
+            LaneId::Lane1 => self.get_lane1(),
+        }
+    }
+}
+
+impl InterpLanes for Interp1 {
+    fn lane(&mut self, lane: LaneId) -> &mut dyn Lane {
+        match lane {
+            LaneId::Lane0 => self.get_lane0(),
+            LaneId::Lane1 => self.get_lane1(),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Low-power waiting for the RTC alarm.
+//!
+//! The RP2040 has two low-power states:
+//!
+//! * *dormant* stops every oscillator, so only a GPIO edge (or an RTC clocked
+//!   from an external 32.768 kHz source on a GPIN pin) can wake it,
+//! * *sleep* gates every clock listed as off in `SLEEP_EN0`/`SLEEP_EN1` and
+//!   stops the processors until an interrupt.
+//!
+//! Boards like the Pico have no external RTC clock, so [`sleep_until`] uses
+//! sleep with only `clk_rtc` left running. For the lowest current, run the
+//! system from the crystal with the PLLs stopped (see [`run_from_xosc`])
+//! before going to sleep: the crystal has to keep running for the RTC anyway.
+//!
+//! [`sleep_until_counting`] also keeps the PWM slices and the GPIO clocked,
+//! so a slice in a counting mode, such as
+//! [`CountRisingEdge`](crate::hal::pwm::CountRisingEdge), keeps counting
+//! pulses on its B pin while the processors sleep.
+
+use cortex_m::peripheral::{NVIC, SCB};
+use fugit::RateExtU32;
+
+use crate::hal::{
+    clocks::{Clock, ClockSource, ClocksManager, InitError},
+    pac::{self, Interrupt::RTC_IRQ},
+    rtc::{DateTimeFilter, RealTimeClock},
+    xosc::setup_xosc_blocking,
+    Watchdog,
+};
+
+/// `clk_rtc` frequency set by [`run_from_xosc`], as expected by the RTC.
+pub const RTC_CLOCK_HZ: u32 = 46_875;
+
+/// Reset value of `SLEEP_EN0`: every clock runs in sleep.
+const SLEEP_EN0_ALL: u32 = 0xFFFF_FFFF;
+/// Reset value of `SLEEP_EN1`: every clock runs in sleep.
+const SLEEP_EN1_ALL: u32 = 0x0000_7FFF;
+
+/// Start the crystal and run every clock from it, leaving both PLLs in reset.
+///
+/// This replaces `init_clocks_and_plls` for firmware that doesn't need USB or
+/// a fast system clock. `clk_sys`, `clk_peri` and `clk_adc` run at the crystal
+/// frequency, and `clk_rtc` at [`RTC_CLOCK_HZ`].
+pub fn run_from_xosc(
+    xosc_crystal_freq: u32,
+    xosc_dev: pac::XOSC,
+    clocks_dev: pac::CLOCKS,
+    watchdog: &mut Watchdog,
+) -> Result<ClocksManager, InitError> {
+    let xosc = setup_xosc_blocking(xosc_dev, xosc_crystal_freq.Hz()).map_err(InitError::XoscErr)?;
+
+    // Configure watchdog tick generation to tick over every microsecond
+    watchdog.enable_tick_generation((xosc_crystal_freq / 1_000_000) as u8);
+
+    let mut clocks = ClocksManager::new(clocks_dev);
+    clocks
+        .reference_clock
+        .configure_clock(&xosc, xosc.get_freq())
+        .map_err(InitError::ClockError)?;
+    clocks
+        .system_clock
+        .configure_clock(&xosc, xosc.get_freq())
+        .map_err(InitError::ClockError)?;
+    clocks
+        .peripheral_clock
+        .configure_clock(&clocks.system_clock, clocks.system_clock.freq())
+        .map_err(InitError::ClockError)?;
+    clocks
+        .adc_clock
+        .configure_clock(&xosc, xosc.get_freq())
+        .map_err(InitError::ClockError)?;
+    clocks
+        .rtc_clock
+        .configure_clock(&xosc, RTC_CLOCK_HZ.Hz())
+        .map_err(InitError::ClockError)?;
+    Ok(clocks)
+}
+
+/// Sleep until the RTC matches `alarm`, with only the RTC clocked.
+///
+/// The alarm interrupt wakes the core but its handler does not run: the
+/// interrupt is taken and cleared here, and masked again before returning.
+/// Other interrupts are ignored while sleeping, as their clocks are stopped.
+pub fn sleep_until(rtc: &mut RealTimeClock, alarm: DateTimeFilter, scb: &mut SCB) {
+    sleep(rtc, alarm, scb, false);
+}
+
+/// Sleep like [`sleep_until`], with the PWM slices, GPIO and pads clocked as
+/// well, so PWM counters keep counting. This costs a little more, as
+/// `clk_sys` has to keep running.
+pub fn sleep_until_counting(rtc: &mut RealTimeClock, alarm: DateTimeFilter, scb: &mut SCB) {
+    sleep(rtc, alarm, scb, true);
+}
+
+fn sleep(rtc: &mut RealTimeClock, alarm: DateTimeFilter, scb: &mut SCB, counting: bool) {
+    rtc.schedule_alarm(alarm);
+    set_alarm_interrupt(true);
+
+    // Note (safety): only SLEEP_EN0/1 are written, and put back below
+    let clocks = unsafe { &*pac::CLOCKS::ptr() };
+
+    cortex_m::interrupt::free(|_| {
+        NVIC::unpend(RTC_IRQ);
+        // Note (safety): interrupts are disabled, so no handler runs
+        unsafe { NVIC::unmask(RTC_IRQ) };
+
+        clocks.sleep_en0.write(|w| {
+            let w = w.clk_rtc_rtc().set_bit();
+            if counting {
+                w.clk_sys_pwm()
+                    .set_bit()
+                    .clk_sys_io()
+                    .set_bit()
+                    .clk_sys_pads()
+                    .set_bit()
+            } else {
+                w
+            }
+        });
+        clocks.sleep_en1.write(|w| unsafe { w.bits(0) });
+        scb.set_sleepdeep();
+
+        // A pending, unmasked interrupt ends WFI even with PRIMASK set
+        cortex_m::asm::wfi();
+
+        scb.clear_sleepdeep();
+        clocks.sleep_en0.write(|w| unsafe { w.bits(SLEEP_EN0_ALL) });
+        clocks.sleep_en1.write(|w| unsafe { w.bits(SLEEP_EN1_ALL) });
+
+        rtc.disable_alarm();
+        rtc.clear_interrupt();
+        set_alarm_interrupt(false);
+        NVIC::mask(RTC_IRQ);
+        NVIC::unpend(RTC_IRQ);
+    });
+}
+
+/// Enable or disable the RTC's alarm interrupt, which the HAL leaves alone.
+fn set_alarm_interrupt(enabled: bool) {
+    // Note (safety): INTE only holds this one enable
+    let rtc = unsafe { &*pac::RTC::ptr() };
+    rtc.inte.write(|w| w.rtc().bit(enabled));
+}
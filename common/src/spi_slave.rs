@@ -0,0 +1,288 @@
+//! SPI peripheral (slave) mode, exchanging fixed-size frames by DMA.
+//!
+//! The HAL only drives SPI as the controller. [`SpiSlave`] puts an SPI block
+//! in peripheral mode, for a host MCU or single board computer that clocks
+//! frames of `N` bytes in and out of the board. Two DMA channels keep it
+//! going without the CPU: one writes every byte received into a ring of
+//! [`SLOTS`] frames, the other reads every byte sent from a second ring, and
+//! each starts over from [`dma::dispatch`](crate::dma::dispatch) when it
+//! reaches the end.
+//!
+//! ```ignore
+//! let mut slave = SpiSlave::new(pac.SPI0, &mut pac.RESETS, rx, tx, MODE_3, 17, buffers, Irq::Irq0);
+//! loop {
+//!     slave.receive(|request, reply| {
+//!         reply.copy_from_slice(request);
+//!     });
+//! }
+//! ```
+//!
+//! Each byte sent out is exchanged with a byte coming in, so the reply to a
+//! frame can't go out in the same frame: [`receive`](SpiSlave::receive)
+//! hands over the frame just received together with the slot for the frame
+//! after next, as the one in between may already be on its way out. Replies
+//! therefore lag requests by two frames, and the first two frames after
+//! start-up get zeros.
+//!
+//! The master has to keep chip select low for a whole frame, and use SPI
+//! mode 1 or 3: in modes 0 and 2 the block wants chip select to rise after
+//! every byte. The block samples the bus with `clk_peri`, which has to be at
+//! least 12 times as fast as the master's clock, so 10 MHz at most with the
+//! usual 125 MHz. The pins must already be set to the SPI function.
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_hal::spi::{Mode, Phase, Polarity};
+
+use crate::dma::{Channel, Irq};
+use crate::hal::pac;
+
+/// Frames in each ring.
+pub const SLOTS: usize = 4;
+
+/// An SPI block the DMA can serve in peripheral mode.
+pub trait SpiBlock {
+    /// DREQ number of the block's transmit FIFO.
+    const TX_DREQ: u8;
+    /// DREQ number of the block's receive FIFO.
+    const RX_DREQ: u8;
+    /// The block's bit in `RESETS.RESET`.
+    const RESET: u32;
+
+    /// The block's registers.
+    fn regs() -> &'static pac::spi0::RegisterBlock;
+}
+
+impl SpiBlock for pac::SPI0 {
+    const TX_DREQ: u8 = 16;
+    const RX_DREQ: u8 = 17;
+    const RESET: u32 = 1 << 16;
+
+    fn regs() -> &'static pac::spi0::RegisterBlock {
+        unsafe { &*pac::SPI0::ptr() }
+    }
+}
+
+impl SpiBlock for pac::SPI1 {
+    const TX_DREQ: u8 = 18;
+    const RX_DREQ: u8 = 19;
+    const RESET: u32 = 1 << 17;
+
+    fn regs() -> &'static pac::spi0::RegisterBlock {
+        unsafe { &*pac::SPI1::ptr() }
+    }
+}
+
+/// A ring of frames, aligned so the DMA's address wraps within it.
+#[repr(C, align(4096))]
+struct Ring<const N: usize>([[u8; N]; SLOTS]);
+
+/// The rings [`SpiSlave`] receives into and sends from.
+pub struct Buffers<const N: usize> {
+    rx: Ring<N>,
+    tx: Ring<N>,
+}
+
+impl<const N: usize> Buffers<N> {
+    /// Zeroed buffers, e.g. for `cortex_m::singleton!`.
+    pub const fn new() -> Self {
+        Buffers {
+            rx: Ring([[0; N]; SLOTS]),
+            tx: Ring([[0; N]; SLOTS]),
+        }
+    }
+}
+
+impl<const N: usize> Default for Buffers<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `CH_CTRL` for one direction: enabled, bytes, paced by `dreq`, with the
+/// ring side's address incrementing and wrapping every `1 << ring_bits`
+/// bytes.
+fn ring_ctrl(channel: &Channel, dreq: u8, ring_bits: u32, receive: bool) -> u32 {
+    const EN: u32 = 1 << 0;
+    const INCR_READ: u32 = 1 << 4;
+    const INCR_WRITE: u32 = 1 << 5;
+    const RING_SEL_WRITE: u32 = 1 << 10;
+    let ring = if receive {
+        INCR_WRITE | RING_SEL_WRITE
+    } else {
+        INCR_READ
+    };
+    // Chaining to itself disables chaining
+    EN | ring | ring_bits << 6 | u32::from(channel.id()) << 11 | u32::from(dreq) << 15
+}
+
+/// Start `id` over, from the [`dispatch`](crate::dma::dispatch) callback:
+/// its addresses have already wrapped back to the start of its ring.
+fn retrigger(id: u8) {
+    let dma = unsafe { &*pac::DMA::ptr() };
+    dma.multi_chan_trigger.write(|w| unsafe { w.bits(1 << id) });
+}
+
+/// An SPI block in peripheral mode, exchanging frames of `N` bytes.
+pub struct SpiSlave<D: SpiBlock, const N: usize> {
+    spi: D,
+    rx: Channel,
+    tx: Channel,
+    mode: Mode,
+    cs_pin: u8,
+    buffers: &'static mut Buffers<N>,
+    /// The slot [`receive`](Self::receive) hands out next.
+    next: usize,
+}
+
+impl<D: SpiBlock, const N: usize> SpiSlave<D, N> {
+    /// Take over `spi` as a peripheral in `mode`, with its chip select on
+    /// GPIO `cs_pin`, and start exchanging frames.
+    ///
+    /// `N` has to be a power of two from 16 to 1024. `rx` and `tx` are any
+    /// free DMA channels, e.g. from
+    /// [`Allocator::alloc`](crate::dma::Allocator::alloc); they restart from
+    /// `irq`, which needs [`dma::dispatch`](crate::dma::dispatch) called from
+    /// its handler and unmasking.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        spi: D,
+        resets: &mut pac::RESETS,
+        mut rx: Channel,
+        mut tx: Channel,
+        mode: Mode,
+        cs_pin: u8,
+        buffers: &'static mut Buffers<N>,
+        irq: Irq,
+    ) -> Self {
+        assert!(
+            N.is_power_of_two() && (16..=1024).contains(&N),
+            "frames must be a power of two from 16 to 1024 bytes"
+        );
+        rx.listen(irq, Some(retrigger));
+        tx.listen(irq, Some(retrigger));
+        let mut slave = SpiSlave {
+            spi,
+            rx,
+            tx,
+            mode,
+            cs_pin,
+            buffers,
+            next: 0,
+        };
+        slave.restart(resets);
+        slave
+    }
+
+    /// Hand the frame received next, if it is in, to `f`, along with the
+    /// slot for the reply that goes out two frames later, and return whether
+    /// there was one.
+    ///
+    /// Call it at least once a frame: the reply has to be in place before
+    /// the frame before it ends, when the DMA starts reading it into the
+    /// transmit FIFO, and frames the CPU doesn't get to before the DMA comes
+    /// round the ring again are lost.
+    pub fn receive(&mut self, f: impl FnOnce(&[u8; N], &mut [u8; N])) -> bool {
+        if self.position() / N == self.next {
+            return false;
+        }
+        compiler_fence(Ordering::SeqCst);
+        let reply = (self.next + 2) % SLOTS;
+        f(&self.buffers.rx.0[self.next], &mut self.buffers.tx.0[reply]);
+        compiler_fence(Ordering::SeqCst);
+        self.next = (self.next + 1) % SLOTS;
+        true
+    }
+
+    /// `true` while the master holds chip select low.
+    pub fn is_selected(&self) -> bool {
+        let sio = unsafe { &*pac::SIO::ptr() };
+        sio.gpio_in.read().bits() & 1 << self.cs_pin == 0
+    }
+
+    /// `true` when the master stopped part way through a frame, i.e. chip
+    /// select is high but the last frame is short. From then on frames are
+    /// received out of step, until [`restart`](Self::restart).
+    pub fn is_out_of_step(&self) -> bool {
+        !self.is_selected() && !self.position().is_multiple_of(N)
+    }
+
+    /// Reset the block and start over at the first slot, with the reply
+    /// slots cleared, e.g. after [`is_out_of_step`](Self::is_out_of_step).
+    ///
+    /// Resetting the block is the only way to drop the bytes already waiting
+    /// in its transmit FIFO.
+    pub fn restart(&mut self, resets: &mut pac::RESETS) {
+        self.rx.abort();
+        self.tx.abort();
+        resets
+            .reset
+            .modify(|r, w| unsafe { w.bits(r.bits() | D::RESET) });
+        resets
+            .reset
+            .modify(|r, w| unsafe { w.bits(r.bits() & !D::RESET) });
+        while resets.reset_done.read().bits() & D::RESET == 0 {}
+
+        for slot in self.buffers.tx.0.iter_mut() {
+            slot.fill(0);
+        }
+        self.next = 0;
+
+        let regs = D::regs();
+        regs.sspcr0.write(|w| unsafe {
+            w.dss()
+                .bits(7)
+                .frf()
+                .bits(0)
+                .spo()
+                .bit(self.mode.polarity == Polarity::IdleHigh)
+                .sph()
+                .bit(self.mode.phase == Phase::CaptureOnSecondTransition)
+        });
+        regs.sspcr1.write(|w| w.ms().set_bit());
+        regs.sspdmacr
+            .write(|w| w.rxdmae().set_bit().txdmae().set_bit());
+
+        let ring_bits = (N * SLOTS).trailing_zeros();
+        let data = regs.sspdr.as_ptr() as u32;
+        let rx = self.rx.regs();
+        rx.ch_read_addr.write(|w| unsafe { w.bits(data) });
+        rx.ch_write_addr
+            .write(|w| unsafe { w.bits(self.buffers.rx.0.as_ptr() as u32) });
+        rx.ch_trans_count
+            .write(|w| unsafe { w.bits((N * SLOTS) as u32) });
+        let tx = self.tx.regs();
+        tx.ch_read_addr
+            .write(|w| unsafe { w.bits(self.buffers.tx.0.as_ptr() as u32) });
+        tx.ch_write_addr.write(|w| unsafe { w.bits(data) });
+        tx.ch_trans_count
+            .write(|w| unsafe { w.bits((N * SLOTS) as u32) });
+        compiler_fence(Ordering::SeqCst);
+
+        // Fill the transmit FIFO before the block starts listening
+        rx.ch_ctrl_trig
+            .write(|w| unsafe { w.bits(ring_ctrl(&self.rx, D::RX_DREQ, ring_bits, true)) });
+        tx.ch_ctrl_trig
+            .write(|w| unsafe { w.bits(ring_ctrl(&self.tx, D::TX_DREQ, ring_bits, false)) });
+        regs.sspcr1.modify(|_, w| w.sse().set_bit());
+    }
+
+    /// Stop, handing back the SPI block, the DMA channels and the buffers.
+    ///
+    /// The block is left disabled, and the channels no longer interrupt.
+    pub fn free(mut self) -> (D, Channel, Channel, &'static mut Buffers<N>) {
+        D::regs().sspcr1.write(|w| unsafe { w.bits(0) });
+        self.rx.abort();
+        self.tx.abort();
+        self.rx.unlisten();
+        self.tx.unlisten();
+        (self.spi, self.rx, self.tx, self.buffers)
+    }
+
+    /// How far into the receive ring the next byte goes.
+    fn position(&self) -> usize {
+        let start = self.buffers.rx.0.as_ptr() as u32;
+        let write = self.rx.regs().ch_write_addr.read().bits();
+        write.wrapping_sub(start) as usize % (N * SLOTS)
+    }
+}
@@ -0,0 +1,151 @@
+//! System state shown on a single RGB LED, for boards without a display.
+//!
+//! Headless boards with an onboard NeoPixel can still say what they are
+//! doing. The application tells a [`StatusLed`] which [`State`] it is in and,
+//! every few milliseconds, shows the colour it returns:
+//!
+//! | State            | Pattern                                        |
+//! |------------------|------------------------------------------------|
+//! | `Booting`        | blue, blinking five times a second             |
+//! | `Ready`          | amber                                          |
+//! | `UsbConnected`   | green                                          |
+//! | `Logging`        | green, going dark for a moment once a second   |
+//! | `Error(n)`       | red, `n` short flashes, then a second's pause  |
+//! | `Panic`          | red, flickering ten times a second             |
+//!
+//! ```ignore
+//! let mut status = StatusLed::new();
+//! loop {
+//!     let now = (timer.get_counter().ticks() / 1000) as u32;
+//!     status.set(if configured { State::UsbConnected } else { State::Ready }, now);
+//!     led.write(once(status.colour(now)));
+//! }
+//! ```
+//!
+//! A panic handler can't count on a timer, so [`panic_loop`] shows
+//! [`State::Panic`] using [`delay`](crate::delay) alone.
+
+use crate::delay;
+
+/// A colour, as `(red, green, blue)`.
+pub type Rgb = (u8, u8, u8);
+
+const OFF: Rgb = (0, 0, 0);
+const BLUE: Rgb = (0, 0, 255);
+const AMBER: Rgb = (255, 120, 0);
+const GREEN: Rgb = (0, 255, 0);
+const RED: Rgb = (255, 0, 0);
+
+/// What the system is doing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Starting up.
+    Booting,
+    /// Running, with no USB host.
+    Ready,
+    /// Running, configured by a USB host.
+    UsbConnected,
+    /// Recording data.
+    Logging,
+    /// Stuck on error number `n`, counted out in flashes. 0 shows as 1.
+    Error(u8),
+    /// Panicked.
+    Panic,
+}
+
+/// The colour of `state`'s pattern `elapsed_ms` after it began, at full
+/// brightness.
+pub fn pattern(state: State, elapsed_ms: u32) -> Rgb {
+    let blink = |colour, period_ms, on_ms| {
+        if elapsed_ms % period_ms < on_ms {
+            colour
+        } else {
+            OFF
+        }
+    };
+    match state {
+        State::Booting => blink(BLUE, 200, 100),
+        State::Ready => AMBER,
+        State::UsbConnected => GREEN,
+        State::Logging => blink(GREEN, 1000, 900),
+        State::Error(code) => {
+            // 400 ms per flash, then 1 s dark
+            let flashes = u32::from(code.max(1));
+            let phase = elapsed_ms % (flashes * 400 + 1000);
+            if phase < flashes * 400 && phase % 400 < 200 {
+                RED
+            } else {
+                OFF
+            }
+        }
+        State::Panic => blink(RED, 100, 50),
+    }
+}
+
+/// Tracks the state shown, and when its pattern started.
+pub struct StatusLed {
+    state: State,
+    since_ms: u32,
+    brightness: u8,
+}
+
+impl StatusLed {
+    /// Showing [`State::Booting`], at a brightness of 32 out of 255, which is
+    /// plenty for an LED on the board.
+    pub const fn new() -> Self {
+        StatusLed {
+            state: State::Booting,
+            since_ms: 0,
+            brightness: 32,
+        }
+    }
+
+    /// Scale colours by `brightness` out of 255.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+    }
+
+    /// The state shown.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Show `state` from `now_ms` on. Setting the state already shown
+    /// carries on with its pattern rather than restarting it.
+    pub fn set(&mut self, state: State, now_ms: u32) {
+        if state != self.state {
+            self.state = state;
+            self.since_ms = now_ms;
+        }
+    }
+
+    /// The colour to show at `now_ms`.
+    pub fn colour(&self, now_ms: u32) -> Rgb {
+        let (r, g, b) = pattern(self.state, now_ms.wrapping_sub(self.since_ms));
+        let scale = |c: u8| (u16::from(c) * u16::from(self.brightness) / 255) as u8;
+        (scale(r), scale(g), scale(b))
+    }
+}
+
+impl Default for StatusLed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Show [`State::Panic`] forever, calling `show` with each colour, at
+/// `brightness` out of 255.
+///
+/// For panic handlers: it only needs [`delay`](crate::delay), not a timer or
+/// interrupts.
+pub fn panic_loop(brightness: u8, mut show: impl FnMut(Rgb)) -> ! {
+    let mut status = StatusLed::new();
+    status.set_brightness(brightness);
+    status.set(State::Panic, 0);
+    let mut now_ms = 0u32;
+    loop {
+        show(status.colour(now_ms));
+        delay::delay_us(10_000);
+        now_ms = now_ms.wrapping_add(10);
+    }
+}
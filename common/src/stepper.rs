@@ -0,0 +1,360 @@
+//! Stepper motors on step/direction drivers such as the A4988 and DRV8825,
+//! with trapezoidal acceleration.
+//!
+//! A [`Stepper`] ramps up to the speed set with [`Stepper::set_speed`] at the
+//! rate set with [`Stepper::set_acceleration`], cruises, and ramps down to
+//! stop exactly on the position given to [`Stepper::move_to`]. A new target
+//! can be given at any time, and the motor slows, turns round if it has to,
+//! and heads there without losing steps.
+//!
+//! Each step is timed by the one before: [`Stepper::step`] pulses the driver
+//! and returns how long to wait before calling it again. An [`AlarmStepper`]
+//! does that from one of the timer's alarms, so the steps keep coming while
+//! the main loop is busy:
+//!
+//! ```ignore
+//! static MOTOR: Mutex<RefCell<Option<AlarmStepper<StepPin, DirPin, Alarm0>>>> = ...;
+//!
+//! let mut stepper = Stepper::new(step_pin, dir_pin);
+//! stepper.set_speed(2000);
+//! stepper.set_acceleration(4000);
+//! let motor = AlarmStepper::new(stepper, timer.alarm_0().unwrap());
+//! // ... put it in MOTOR and unmask TIMER_IRQ_0, then:
+//! critical_section::with(|cs| MOTOR.borrow_ref_mut(cs).as_mut().unwrap().move_to(3200));
+//!
+//! #[interrupt]
+//! fn TIMER_IRQ_0() {
+//!     critical_section::with(|cs| MOTOR.borrow_ref_mut(cs).as_mut().unwrap().on_alarm());
+//! }
+//! ```
+//!
+//! The ramp follows D. Austin's "Generate stepper-motor speed profiles in
+//! real time" (2005), in fixed point: each step's interval comes from the
+//! last one with a division, so a step costs a few microseconds at most.
+//! The step pulses are timed with [`delay`](crate::delay), which needs
+//! [`delay::calibrate`](crate::delay::calibrate) to keep them short.
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::v2::OutputPin;
+use fugit::MicrosDurationU32;
+
+use crate::delay;
+use crate::hal::timer::Alarm;
+
+/// Length of each step pulse, in microseconds. The A4988 needs 1 µs and the
+/// DRV8825 1.9 µs.
+const STEP_PULSE_US: u32 = 2;
+
+/// Time between changing direction and the next step, in microseconds. Both
+/// drivers need well under 1 µs.
+const DIR_SETUP_US: u32 = 1;
+
+/// Fractional bits of the step intervals.
+const FRACTION_BITS: u32 = 8;
+
+/// One microsecond in the intervals' fixed point.
+const ONE_US: u64 = 1 << FRACTION_BITS;
+
+/// Soonest the alarm is set for, in microseconds, so the alarm isn't
+/// missed.
+const MIN_ALARM_US: u32 = 10;
+
+/// A motor on a step/direction driver.
+pub struct Stepper<STEP, DIR> {
+    step: STEP,
+    dir: DIR,
+    position: i32,
+    target: i32,
+    /// Steps per second per second.
+    acceleration: u32,
+    /// The first interval of a ramp from standstill, in 256ths of a µs.
+    c0: u64,
+    /// The interval at top speed.
+    c_min: u64,
+    /// The current interval, zero when stopped.
+    cn: u64,
+    /// Steps into the ramp: positive when speeding up or cruising,
+    /// negative when slowing down.
+    n: i32,
+    forward: bool,
+    /// The direction the pin was last set to.
+    dir_forward: Option<bool>,
+}
+
+impl<STEP, DIR> Stepper<STEP, DIR>
+where
+    STEP: OutputPin<Error = Infallible>,
+    DIR: OutputPin<Error = Infallible>,
+{
+    /// A stopped motor at position 0, with a speed of 1000 steps/s and an
+    /// acceleration of 1000 steps/s². The direction pin is high for forward,
+    /// towards higher positions.
+    pub fn new(mut step: STEP, dir: DIR) -> Self {
+        let _ = step.set_low();
+        let mut stepper = Stepper {
+            step,
+            dir,
+            position: 0,
+            target: 0,
+            acceleration: 0,
+            c0: 0,
+            c_min: 0,
+            cn: 0,
+            n: 0,
+            forward: true,
+            dir_forward: None,
+        };
+        stepper.set_speed(1000);
+        stepper.set_acceleration(1000);
+        stepper
+    }
+
+    /// Give back the pins.
+    pub fn free(self) -> (STEP, DIR) {
+        (self.step, self.dir)
+    }
+
+    /// Set the top speed, in steps per second. A motor already running faster
+    /// drops to it on the next step.
+    pub fn set_speed(&mut self, steps_per_second: u32) {
+        self.c_min = 1_000_000 * ONE_US / u64::from(steps_per_second.max(1));
+    }
+
+    /// Set the acceleration and deceleration, in steps per second per
+    /// second.
+    pub fn set_acceleration(&mut self, steps_per_second2: u32) {
+        let acceleration = steps_per_second2.max(1);
+        if self.acceleration != 0 && self.n != 0 {
+            // Keep the speed, on the new ramp
+            self.n =
+                (i64::from(self.n) * i64::from(self.acceleration) / i64::from(acceleration)) as i32;
+        }
+        self.acceleration = acceleration;
+        // c0 = 0.676 * sqrt(2 / a) seconds, the 0.676 correcting the error
+        // of the approximation in the first step
+        self.c0 = 956_008 * ONE_US * 16 / isqrt(u64::from(acceleration) * 256);
+    }
+
+    /// Head for `position`.
+    pub fn move_to(&mut self, position: i32) {
+        self.target = position;
+        if !self.is_running() {
+            self.plan();
+        }
+    }
+
+    /// Head for `steps` steps from the current target.
+    pub fn move_by(&mut self, steps: i32) {
+        self.move_to(self.target.saturating_add(steps));
+    }
+
+    /// Slow down to a stop as quickly as the acceleration allows.
+    pub fn stop(&mut self) {
+        if self.is_running() {
+            let stopping = self.steps_to_stop() as i32;
+            self.target = if self.forward {
+                self.position + stopping
+            } else {
+                self.position - stopping
+            };
+        }
+    }
+
+    /// Stop dead, for a limit switch or an emergency stop. The motor may
+    /// overshoot if it was moving fast.
+    pub fn halt(&mut self) {
+        self.target = self.position;
+        self.cn = 0;
+        self.n = 0;
+    }
+
+    /// Stop dead and call the current position `position`, after homing.
+    pub fn set_position(&mut self, position: i32) {
+        self.halt();
+        self.position = position;
+        self.target = position;
+    }
+
+    /// The current position, in steps.
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// The position being headed for.
+    pub fn target(&self) -> i32 {
+        self.target
+    }
+
+    /// Steps left to the target.
+    pub fn distance_to_go(&self) -> i32 {
+        self.target - self.position
+    }
+
+    /// The current speed in steps per second, negative going backwards.
+    pub fn speed(&self) -> i32 {
+        if self.cn == 0 {
+            return 0;
+        }
+        let speed = (1_000_000 * ONE_US / self.cn) as i32;
+        if self.forward {
+            speed
+        } else {
+            -speed
+        }
+    }
+
+    /// Whether the motor is moving, or about to.
+    pub fn is_running(&self) -> bool {
+        self.cn != 0
+    }
+
+    /// Take the step that is due, and return how long to wait before the
+    /// next one, in microseconds, or `None` once the motor has stopped.
+    pub fn step(&mut self) -> Option<u32> {
+        if !self.is_running() {
+            return None;
+        }
+        if self.dir_forward != Some(self.forward) {
+            let _ = self.dir.set_state(self.forward.into());
+            self.dir_forward = Some(self.forward);
+            delay::delay_us(DIR_SETUP_US);
+        }
+        let _ = self.step.set_high();
+        delay::delay_us(STEP_PULSE_US);
+        let _ = self.step.set_low();
+        self.position += if self.forward { 1 } else { -1 };
+
+        self.plan();
+        if self.is_running() {
+            Some((self.cn >> FRACTION_BITS) as u32)
+        } else {
+            None
+        }
+    }
+
+    /// Steps it takes to stop from the current speed.
+    fn steps_to_stop(&self) -> u32 {
+        if self.cn == 0 {
+            return 0;
+        }
+        // v² / 2a, with v = 1 / cn
+        let speed = 1_000_000 * ONE_US / self.cn;
+        (speed * speed / (2 * u64::from(self.acceleration))) as u32
+    }
+
+    /// Work out the interval to the next step, and its direction.
+    fn plan(&mut self) {
+        let distance = i64::from(self.target) - i64::from(self.position);
+        let stopping = i64::from(self.steps_to_stop());
+
+        if distance == 0 && stopping <= 1 {
+            self.cn = 0;
+            self.n = 0;
+            return;
+        }
+
+        let towards = distance > 0;
+        if self.n > 0 {
+            // Speeding up or cruising: slow down if the target is too close,
+            // or behind
+            if stopping >= distance.abs() || towards != self.forward {
+                self.n = -(stopping as i32);
+            }
+        } else if self.n < 0 && stopping < distance.abs() && towards == self.forward {
+            // Slowing down, but there's room to speed up again
+            self.n = -self.n;
+        }
+
+        if self.n == 0 {
+            // From standstill, or at the end of slowing down
+            self.cn = self.c0;
+            self.forward = towards;
+        } else {
+            let cn = self.cn as i64;
+            let cn = cn - 2 * cn / (4 * i64::from(self.n) + 1);
+            self.cn = (cn as u64).max(self.c_min);
+        }
+        self.n += 1;
+    }
+}
+
+/// A [`Stepper`] stepped from a timer alarm's interrupt.
+pub struct AlarmStepper<STEP, DIR, A> {
+    stepper: Stepper<STEP, DIR>,
+    alarm: A,
+}
+
+impl<STEP, DIR, A> AlarmStepper<STEP, DIR, A>
+where
+    STEP: OutputPin<Error = Infallible>,
+    DIR: OutputPin<Error = Infallible>,
+    A: Alarm,
+{
+    /// Step `stepper` from `alarm`, whose interrupt has to be unmasked in the
+    /// NVIC and call [`AlarmStepper::on_alarm`].
+    pub fn new(stepper: Stepper<STEP, DIR>, mut alarm: A) -> Self {
+        alarm.enable_interrupt();
+        AlarmStepper { stepper, alarm }
+    }
+
+    /// Give back the stepper and the alarm.
+    pub fn free(mut self) -> (Stepper<STEP, DIR>, A) {
+        self.alarm.disable_interrupt();
+        (self.stepper, self.alarm)
+    }
+
+    /// The motor, for settings and readings. A move started through it
+    /// only begins with [`AlarmStepper::start`].
+    pub fn stepper(&mut self) -> &mut Stepper<STEP, DIR> {
+        &mut self.stepper
+    }
+
+    /// Head for `position`, starting the alarm if the motor was stopped.
+    pub fn move_to(&mut self, position: i32) {
+        self.stepper.move_to(position);
+        self.start();
+    }
+
+    /// Head for `steps` steps from the current target.
+    pub fn move_by(&mut self, steps: i32) {
+        self.stepper.move_by(steps);
+        self.start();
+    }
+
+    /// Start the alarm, if the motor was stopped and has somewhere to go.
+    pub fn start(&mut self) {
+        if self.stepper.is_running() && self.alarm.finished() {
+            self.schedule(MIN_ALARM_US);
+        }
+    }
+
+    /// Take a step. Call this from the alarm's interrupt handler.
+    pub fn on_alarm(&mut self) {
+        self.alarm.clear_interrupt();
+        if let Some(interval) = self.stepper.step() {
+            self.schedule(interval);
+        }
+    }
+
+    fn schedule(&mut self, interval_us: u32) {
+        let interval = MicrosDurationU32::from_ticks(interval_us.max(MIN_ALARM_US));
+        // Only fails for waits over 2^32 µs, which no ramp reaches
+        let _ = self.alarm.schedule(interval);
+    }
+}
+
+/// The integer square root, rounded down.
+fn isqrt(value: u64) -> u64 {
+    if value < 2 {
+        return value;
+    }
+    // Newton's method from above
+    let mut x = value;
+    let mut y = x.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
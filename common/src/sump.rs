@@ -0,0 +1,139 @@
+//! The SUMP logic analyzer protocol, as spoken by sigrok's `ols` driver and
+//! PulseView.
+//!
+//! The host sends one-byte commands, below 0x80, and five-byte ones, a
+//! command byte followed by a 32 bit little-endian argument. A [`Parser`]
+//! turns the bytes into [`Command`]s. The device answers [`Command::Id`] with
+//! [`ID`] and [`Command::Metadata`] with the block from [`metadata`], and
+//! after [`Command::Run`] sends the samples, newest first.
+//!
+//! Rates are set as a divider of a notional 100 MHz clock, see
+//! [`sample_rate`]. Triggers are accepted and ignored, so captures start
+//! straight away.
+
+/// What the device answers [`Command::Id`] with.
+pub const ID: &[u8; 4] = b"1ALS";
+
+/// The clock the host's dividers divide.
+pub const CLOCK_HZ: u32 = 100_000_000;
+
+/// A command from the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// 0x00: stop whatever is going on. Sent five times in a row to get in
+    /// step with a five-byte command.
+    Reset,
+    /// 0x01: capture and send the samples.
+    Run,
+    /// 0x02: identify, answered with [`ID`].
+    Id,
+    /// 0x04: describe the device, answered with [`metadata`].
+    Metadata,
+    /// 0x80: sample at [`sample_rate`]`(divider)`.
+    Divider(u32),
+    /// 0x81: the number of samples to send, and to capture after the
+    /// trigger.
+    Counts { read: u32, delay: u32 },
+    /// 0x82: channel groups, demux, filter and other options.
+    Flags(u32),
+    /// Anything else, such as the trigger set-up: the command byte and its
+    /// argument.
+    Other(u8, u32),
+}
+
+/// The rate sampled at for a [`Command::Divider`].
+pub fn sample_rate(divider: u32) -> u32 {
+    CLOCK_HZ / (divider.min(CLOCK_HZ - 1) + 1)
+}
+
+/// Assembles [`Command`]s from the bytes the host sends.
+#[derive(Default)]
+pub struct Parser {
+    buffer: [u8; 5],
+    len: usize,
+}
+
+impl Parser {
+    /// A parser waiting for a command byte.
+    pub fn new() -> Self {
+        Parser::default()
+    }
+
+    /// Take the next byte from the host, returning the command it completes.
+    pub fn push(&mut self, byte: u8) -> Option<Command> {
+        self.buffer[self.len] = byte;
+        self.len += 1;
+        let code = self.buffer[0];
+        if code & 0x80 == 0 {
+            self.len = 0;
+            return match code {
+                0x00 => Some(Command::Reset),
+                0x01 => Some(Command::Run),
+                0x02 => Some(Command::Id),
+                0x04 => Some(Command::Metadata),
+                other => Some(Command::Other(other, 0)),
+            };
+        }
+        if self.len < self.buffer.len() {
+            return None;
+        }
+        self.len = 0;
+
+        let mut argument = [0; 4];
+        argument.copy_from_slice(&self.buffer[1..]);
+        let argument = u32::from_le_bytes(argument);
+        Some(match code {
+            0x80 => Command::Divider(argument & 0x00FF_FFFF),
+            // Both in units of four samples, less one
+            0x81 => Command::Counts {
+                read: ((argument & 0xFFFF) + 1) * 4,
+                delay: ((argument >> 16) + 1) * 4,
+            },
+            0x82 => Command::Flags(argument),
+            other => Command::Other(other, argument),
+        })
+    }
+}
+
+/// Metadata keys.
+const NAME: u8 = 0x01;
+const PROBES: u8 = 0x20;
+const SAMPLE_MEMORY: u8 = 0x21;
+const MAX_SAMPLE_RATE: u8 = 0x23;
+const PROTOCOL_VERSION: u8 = 0x24;
+const END: u8 = 0x00;
+
+/// Write the answer to [`Command::Metadata`] into `out`, returning its
+/// length, or `None` if `out` is too short.
+///
+/// `name` must not contain a NUL. `memory` is the most samples a capture can
+/// hold, and `max_rate` the fastest rate in Hz.
+pub fn metadata(
+    name: &str,
+    probes: u32,
+    memory: u32,
+    max_rate: u32,
+    out: &mut [u8],
+) -> Option<usize> {
+    let len = 1 + name.len() + 1 + 4 * 5 + 1;
+    let out = out.get_mut(..len)?;
+
+    out[0] = NAME;
+    out[1..=name.len()].copy_from_slice(name.as_bytes());
+    out[name.len() + 1] = 0;
+    let numbers = [
+        (PROBES, probes),
+        (SAMPLE_MEMORY, memory),
+        (MAX_SAMPLE_RATE, max_rate),
+        (PROTOCOL_VERSION, 2),
+    ];
+    for (chunk, &(key, value)) in out[name.len() + 2..]
+        .chunks_exact_mut(5)
+        .zip(numbers.iter())
+    {
+        chunk[0] = key;
+        chunk[1..].copy_from_slice(&value.to_be_bytes());
+    }
+    out[len - 1] = END;
+    Some(len)
+}
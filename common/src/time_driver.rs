@@ -0,0 +1,139 @@
+//! An `embassy-time` driver on the RP2040's 64-bit microsecond timer.
+//!
+//! Async drivers from the Embassy project, such as `cyw43` and
+//! `embassy-net`, wait with `embassy_time::Timer`, which needs a driver
+//! linked into the firmware to tell the time and wake tasks. This module is
+//! that driver, using the TIMER peripheral's counter and its alarm 0, for
+//! tasks run by `embassy-executor`:
+//!
+//! ```ignore
+//! #[entry]
+//! fn main() -> ! {
+//!     // ...
+//!     time_driver::start(pac.TIMER, &mut pac.RESETS);
+//!     executor.run(|spawner| spawner.must_spawn(blink()))
+//! }
+//!
+//! // Wakes the tasks whose time has come
+//! #[interrupt]
+//! fn TIMER_IRQ_0() {
+//!     unsafe { time_driver::handle_interrupt() };
+//! }
+//! ```
+//!
+//! Ticks are microseconds, so `embassy-time` needs no `tick-hz-*` feature of
+//! its own. Alarm 0 only compares the low 32 bits of the counter, so waits
+//! longer than about 71 minutes take a few spurious interrupts, which find
+//! nothing due and set the alarm again. The TIMER peripheral belongs to the
+//! driver once started, so neither `hal::Timer` nor
+//! [`rtic_monotonic`](crate::rtic_monotonic) can be used alongside it.
+//!
+//! Needs the `embassy-time` feature.
+
+use core::cell::{Cell, RefCell};
+use core::task::Waker;
+
+use critical_section::{CriticalSection, Mutex};
+use embassy_time_driver::Driver;
+use embassy_time_queue_utils::Queue;
+
+use crate::hal::pac;
+
+struct TimerDriver {
+    /// When alarm 0 is next due, or `u64::MAX` for never.
+    alarm: Mutex<Cell<u64>>,
+    queue: Mutex<RefCell<Queue>>,
+}
+
+embassy_time_driver::time_driver_impl!(static DRIVER: TimerDriver = TimerDriver {
+    alarm: Mutex::new(Cell::new(u64::MAX)),
+    queue: Mutex::new(RefCell::new(Queue::new())),
+});
+
+/// Take over the TIMER peripheral and start the driver.
+///
+/// Call before running the executor. The counter restarts from zero.
+pub fn start(timer: pac::TIMER, resets: &mut pac::RESETS) {
+    resets.reset.modify(|_, w| w.timer().set_bit());
+    resets.reset.modify(|_, w| w.timer().clear_bit());
+    while resets.reset_done.read().timer().bit_is_clear() {}
+
+    timer.inte.modify(|_, w| w.alarm_0().set_bit());
+
+    // Note (safety): the driver is ready for the interrupt
+    unsafe { pac::NVIC::unmask(pac::Interrupt::TIMER_IRQ_0) };
+}
+
+/// Wake the tasks that are due. Call from the `TIMER_IRQ_0` handler.
+///
+/// # Safety
+///
+/// Only call it from that interrupt, and only once [`start`] has run.
+pub unsafe fn handle_interrupt() {
+    critical_section::with(|cs| {
+        timer().intr.write(|w| w.alarm_0().set_bit());
+        let alarm = DRIVER.alarm.borrow(cs).get();
+        if alarm <= DRIVER.now() {
+            DRIVER.alarm.borrow(cs).set(u64::MAX);
+            DRIVER.wake_due(cs);
+        } else {
+            // Not due yet, as it was over 2^32 us off when set
+            timer().alarm0.write(|w| unsafe { w.bits(alarm as u32) });
+        }
+    });
+}
+
+fn timer() -> &'static pac::timer::RegisterBlock {
+    // Note (safety): the registers used are only written by the driver,
+    // which owns the peripheral
+    unsafe { &*pac::TIMER::ptr() }
+}
+
+impl TimerDriver {
+    /// Wake every task that is due, and set the alarm for the next one.
+    fn wake_due(&self, cs: CriticalSection) {
+        let mut queue = self.queue.borrow(cs).borrow_mut();
+        let mut next = queue.next_expiration(self.now());
+        while !self.set_alarm(cs, next) {
+            next = queue.next_expiration(self.now());
+        }
+    }
+
+    /// Set alarm 0 for `at`, returning false if that has already passed.
+    fn set_alarm(&self, cs: CriticalSection, at: u64) -> bool {
+        self.alarm.borrow(cs).set(at);
+        // Writing the alarm arms it; the interrupt checks the high word
+        timer().alarm0.write(|w| unsafe { w.bits(at as u32) });
+        if at > self.now() {
+            return true;
+        }
+        // Too late for the alarm to fire, so disarm it
+        timer().armed.write(|w| unsafe { w.bits(1) });
+        self.alarm.borrow(cs).set(u64::MAX);
+        false
+    }
+}
+
+impl Driver for TimerDriver {
+    fn now(&self) -> u64 {
+        let timer = timer();
+        // The raw registers don't latch, so make sure the high word didn't
+        // change while reading the low one
+        loop {
+            let high = timer.timerawh.read().bits();
+            let low = timer.timerawl.read().bits();
+            if timer.timerawh.read().bits() == high {
+                return u64::from(high) << 32 | u64::from(low);
+            }
+        }
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &Waker) {
+        critical_section::with(|cs| {
+            let earlier = self.queue.borrow(cs).borrow_mut().schedule_wake(at, waker);
+            if earlier {
+                self.wake_due(cs);
+            }
+        });
+    }
+}
@@ -0,0 +1,383 @@
+//! A common timebase for two boards linked by a UART.
+//!
+//! One board is the [`Leader`]: its timer is the common time. The other, the
+//! [`Follower`], now and then sends it a ping and gets a pong back, giving the
+//! four timestamps of a classic NTP exchange:
+//!
+//! ```text
+//! follower  t1 ──ping──▶ t2  leader
+//! follower  t4 ◀──pong── t3  leader
+//! ```
+//!
+//! The leader's clock is ahead of the follower's by
+//! `((t2 - t1) + (t3 - t4)) / 2`, give or take half of however much one
+//! direction took longer than the other. Both frames are the same length, so
+//! the time spent on the wire cancels out; what is left is how promptly each
+//! side polls its UART. The [`Estimator`] keeps the last few exchanges and
+//! trusts the one with the shortest round trip, as it had the least room for
+//! such delays, and tracks how fast the two crystals drift apart so
+//! [`Follower::to_common`] stays accurate between pings.
+//!
+//! Each frame carries a CRC, and a [`Decoder`] resynchronises on the next
+//! `SYNC` byte after an error, like the split keyboard link.
+//!
+//! Timestamps are microseconds, as read from `hal::Timer::get_counter`.
+
+use embedded_hal::serial::{Read, Write};
+
+use crate::keyboard::split::crc8;
+
+/// First byte of every frame.
+pub const SYNC: u8 = 0x5A;
+
+/// Length of an encoded frame.
+pub const FRAME_LEN: usize = 28;
+
+/// How many exchanges the [`Estimator`] picks the best one from.
+pub const WINDOW: usize = 8;
+
+/// The shortest time over which the drift between the clocks is measured, in
+/// microseconds.
+pub const DRIFT_INTERVAL_US: u64 = 1_000_000;
+
+const KIND_PING: u8 = 0;
+const KIND_PONG: u8 = 1;
+
+/// What travels over the link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// From the follower, sent at its local time `t1`.
+    Ping { seq: u8, t1: u64 },
+    /// The leader's answer: the ping's `t1`, and the leader's time when the
+    /// ping arrived (`t2`) and when this answer left (`t3`).
+    Pong { seq: u8, t1: u64, t2: u64, t3: u64 },
+}
+
+/// Encode `message` into a frame.
+pub fn encode(message: Message) -> [u8; FRAME_LEN] {
+    let (kind, seq, times) = match message {
+        Message::Ping { seq, t1 } => (KIND_PING, seq, [t1, 0, 0]),
+        Message::Pong { seq, t1, t2, t3 } => (KIND_PONG, seq, [t1, t2, t3]),
+    };
+    let mut frame = [0; FRAME_LEN];
+    frame[0] = SYNC;
+    frame[1] = kind;
+    frame[2] = seq;
+    for (chunk, time) in frame[3..27].chunks_exact_mut(8).zip(times.iter()) {
+        chunk.copy_from_slice(&time.to_le_bytes());
+    }
+    frame[27] = crc8(&frame[1..27]);
+    frame
+}
+
+/// Write `frame` to `serial` and wait until it has all gone.
+fn send<S: Write<u8>>(serial: &mut S, frame: &[u8]) -> Result<(), S::Error> {
+    for &byte in frame {
+        nb::block!(serial.write(byte))?;
+    }
+    nb::block!(serial.flush())
+}
+
+/// Reassembles frames from a byte stream, resynchronising after errors.
+pub struct Decoder {
+    buffer: [u8; FRAME_LEN],
+    len: usize,
+    errors: u32,
+}
+
+impl Decoder {
+    /// A decoder waiting for the start of a frame.
+    pub const fn new() -> Self {
+        Decoder {
+            buffer: [0; FRAME_LEN],
+            len: 0,
+            errors: 0,
+        }
+    }
+
+    /// Feed in one received byte. Returns the message when it completes a
+    /// valid frame.
+    pub fn push(&mut self, byte: u8) -> Option<Message> {
+        if self.len == 0 && byte != SYNC {
+            return None;
+        }
+        self.buffer[self.len] = byte;
+        self.len += 1;
+        if self.len < FRAME_LEN {
+            return None;
+        }
+        self.len = 0;
+
+        let decoded = if crc8(&self.buffer[1..27]) == self.buffer[27] {
+            Self::decode(&self.buffer)
+        } else {
+            None
+        };
+        if decoded.is_none() {
+            self.errors = self.errors.wrapping_add(1);
+            self.resync();
+        }
+        decoded
+    }
+
+    fn decode(frame: &[u8; FRAME_LEN]) -> Option<Message> {
+        let time = |n: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&frame[3 + 8 * n..11 + 8 * n]);
+            u64::from_le_bytes(bytes)
+        };
+        let seq = frame[2];
+        match frame[1] {
+            KIND_PING => Some(Message::Ping { seq, t1: time(0) }),
+            KIND_PONG => Some(Message::Pong {
+                seq,
+                t1: time(0),
+                t2: time(1),
+                t3: time(2),
+            }),
+            _ => None,
+        }
+    }
+
+    /// After a bad frame, restart from the next `SYNC` inside it, in case the
+    /// real frame started part-way through.
+    fn resync(&mut self) {
+        if let Some(start) = self.buffer[1..].iter().position(|&b| b == SYNC) {
+            let start = start + 1;
+            let rest = FRAME_LEN - start;
+            self.buffer.copy_within(start.., 0);
+            self.len = rest;
+        }
+    }
+
+    /// Number of frames dropped so far.
+    pub fn errors(&self) -> u32 {
+        self.errors
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One ping-pong exchange, from the follower's point of view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    /// The follower's local time when the pong arrived (`t4`).
+    pub local: u64,
+    /// How far the leader's clock was ahead of the follower's, in
+    /// microseconds.
+    pub offset: i64,
+    /// Time on the link and in the leader, `(t4 - t1) - (t3 - t2)`.
+    pub round_trip: u64,
+}
+
+impl Sample {
+    /// Work out the offset and round trip from the four timestamps.
+    pub fn new(t1: u64, t2: u64, t3: u64, t4: u64) -> Self {
+        let (t1, t2, t3, t4) = (t1 as i64, t2 as i64, t3 as i64, t4 as i64);
+        Sample {
+            local: t4 as u64,
+            offset: ((t2 - t1) + (t3 - t4)) / 2,
+            round_trip: ((t4 - t1) - (t3 - t2)).max(0) as u64,
+        }
+    }
+}
+
+/// Turns a stream of [`Sample`]s into an offset and drift between the clocks.
+pub struct Estimator {
+    window: [Option<Sample>; WINDOW],
+    next: usize,
+    current: Option<Sample>,
+    anchor: Option<Sample>,
+    drift_ppb: i64,
+}
+
+impl Estimator {
+    /// An estimator with no samples yet.
+    pub const fn new() -> Self {
+        Estimator {
+            window: [None; WINDOW],
+            next: 0,
+            current: None,
+            anchor: None,
+            drift_ppb: 0,
+        }
+    }
+
+    /// Add an exchange, returning the sample now trusted most.
+    pub fn add(&mut self, sample: Sample) -> Sample {
+        self.window[self.next] = Some(sample);
+        self.next = (self.next + 1) % WINDOW;
+
+        let best = self
+            .window
+            .iter()
+            .flatten()
+            .min_by_key(|sample| sample.round_trip)
+            .copied()
+            .unwrap_or(sample);
+
+        match self.anchor {
+            Some(anchor) => {
+                let elapsed = best.local.saturating_sub(anchor.local);
+                if elapsed >= DRIFT_INTERVAL_US {
+                    let measured = (best.offset - anchor.offset) * 1_000_000_000 / elapsed as i64;
+                    self.drift_ppb += (measured - self.drift_ppb) / 4;
+                    self.anchor = Some(best);
+                }
+            }
+            None => self.anchor = Some(best),
+        }
+        self.current = Some(best);
+        best
+    }
+
+    /// The sample the estimate is based on, if there is one yet.
+    pub fn current(&self) -> Option<Sample> {
+        self.current
+    }
+
+    /// How fast the leader's clock gains on the follower's, in parts per
+    /// billion.
+    pub fn drift_ppb(&self) -> i64 {
+        self.drift_ppb
+    }
+
+    /// Convert a local timestamp to the common timebase.
+    pub fn to_common(&self, local: u64) -> Option<u64> {
+        let current = self.current?;
+        let since = local as i64 - current.local as i64;
+        let drift = since * self.drift_ppb / 1_000_000_000;
+        Some((local as i64 + current.offset + drift) as u64)
+    }
+}
+
+impl Default for Estimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The board whose timer is the common time. It only answers pings.
+pub struct Leader<S> {
+    serial: S,
+    decoder: Decoder,
+}
+
+impl<S> Leader<S>
+where
+    S: Read<u8> + Write<u8>,
+{
+    /// Answer pings arriving on `serial`.
+    pub fn new(serial: S) -> Self {
+        Leader {
+            serial,
+            decoder: Decoder::new(),
+        }
+    }
+
+    /// Read whatever has arrived and answer any pings, reading the time from
+    /// `now`.
+    ///
+    /// Call this as often as possible: time spent between a ping arriving and
+    /// this noticing it counts against the accuracy.
+    pub fn poll(&mut self, mut now: impl FnMut() -> u64) -> Result<(), <S as Write<u8>>::Error> {
+        while let Ok(byte) = self.serial.read() {
+            if let Some(Message::Ping { seq, t1 }) = self.decoder.push(byte) {
+                let t2 = now();
+                let t3 = now();
+                let frame = encode(Message::Pong { seq, t1, t2, t3 });
+                send(&mut self.serial, &frame)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of frames dropped so far.
+    pub fn errors(&self) -> u32 {
+        self.decoder.errors()
+    }
+
+    /// Stop, handing back the serial port.
+    pub fn free(self) -> S {
+        self.serial
+    }
+}
+
+/// The board that follows the leader's clock.
+pub struct Follower<S> {
+    serial: S,
+    decoder: Decoder,
+    seq: u8,
+    estimator: Estimator,
+}
+
+impl<S> Follower<S>
+where
+    S: Read<u8> + Write<u8>,
+{
+    /// Follow the leader on the other end of `serial`.
+    pub fn new(serial: S) -> Self {
+        Follower {
+            serial,
+            decoder: Decoder::new(),
+            seq: 0,
+            estimator: Estimator::new(),
+        }
+    }
+
+    /// Send a ping, timestamped from `now`.
+    ///
+    /// Ten a second is plenty; between pings, the drift estimate keeps the
+    /// common time accurate.
+    pub fn ping(&mut self, mut now: impl FnMut() -> u64) -> Result<(), <S as Write<u8>>::Error> {
+        self.seq = self.seq.wrapping_add(1);
+        let frame = encode(Message::Ping {
+            seq: self.seq,
+            t1: now(),
+        });
+        send(&mut self.serial, &frame)
+    }
+
+    /// Read whatever has arrived. Returns the new best sample when a pong to
+    /// the latest ping completes an exchange.
+    ///
+    /// Call this as often as possible, for the same reason as
+    /// [`Leader::poll`]. Pongs to older pings are ignored.
+    pub fn poll(&mut self, mut now: impl FnMut() -> u64) -> Option<Sample> {
+        let mut best = None;
+        while let Ok(byte) = self.serial.read() {
+            if let Some(Message::Pong { seq, t1, t2, t3 }) = self.decoder.push(byte) {
+                let t4 = now();
+                if seq == self.seq {
+                    best = Some(self.estimator.add(Sample::new(t1, t2, t3, t4)));
+                }
+            }
+        }
+        best
+    }
+
+    /// Convert a local timestamp to the leader's timebase, once at least one
+    /// exchange has completed.
+    pub fn to_common(&self, local: u64) -> Option<u64> {
+        self.estimator.to_common(local)
+    }
+
+    /// The offset and drift estimate.
+    pub fn estimator(&self) -> &Estimator {
+        &self.estimator
+    }
+
+    /// Number of frames dropped so far.
+    pub fn errors(&self) -> u32 {
+        self.decoder.errors()
+    }
+
+    /// Stop, handing back the serial port.
+    pub fn free(self) -> S {
+        self.serial
+    }
+}
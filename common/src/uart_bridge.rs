@@ -0,0 +1,204 @@
+//! A USB to serial adapter: USB Serial bridged to a hardware UART.
+//!
+//! [`UartBridge`] passes what the host sends over USB Serial out of a UART,
+//! and what the UART receives back to the host, so any board can stand in for
+//! a USB-serial cable. It follows the line coding the host's terminal sets:
+//! the baud rate, data bits, parity and stop bits all carry over to the
+//! UART.
+//!
+//! ```ignore
+//! let uart = UartPeripheral::new(pac.UART0, uart_pins, &mut pac.RESETS);
+//! let mut bridge = UartBridge::new(uart, clocks.peripheral_clock.freq());
+//! loop {
+//!     usb_dev.poll(&mut [&mut serial]);
+//!     bridge.poll(&mut serial);
+//! }
+//! ```
+//!
+//! Bytes from the host are only taken from USB Serial while there is room
+//! for them, so a slow baud rate makes the host wait rather than losing data.
+//! Bytes from the UART are dropped if the USB queue is full, as nothing can
+//! hold the other end off. Mark and space parity, 1.5 stop bits and 16 data
+//! bits have no UART equivalent, and fall back to no parity, 2 stop bits and
+//! 8 data bits.
+
+use embedded_hal::serial::{Read, Write};
+use fugit::{HertzU32, RateExtU32};
+use heapless::Deque;
+use usb_device::class_prelude::UsbBus;
+use usbd_serial::{ParityType, StopBits as UsbStopBits};
+
+use crate::buffered_serial::BufferedSerial;
+use crate::hal::uart::{
+    DataBits, Disabled, Enabled, Parity, StopBits, UartConfig, UartDevice, UartPeripheral,
+    ValidUartPinout,
+};
+
+/// Bytes from the host waiting to go out of the UART.
+const PENDING_SIZE: usize = 256;
+
+/// The line coding until the host sets one: 115200 baud, 8N1.
+const DEFAULT_FORMAT: Format = Format {
+    baud: 115_200,
+    data_bits: 8,
+    parity: LineParity::None,
+    two_stop_bits: false,
+};
+
+/// The parity the host asked for, of those the UART has.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LineParity {
+    None,
+    Odd,
+    Even,
+}
+
+/// The parts of the host's line coding the UART can follow.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Format {
+    baud: u32,
+    data_bits: u8,
+    parity: LineParity,
+    two_stop_bits: bool,
+}
+
+impl Format {
+    /// The UART settings for this format, with the baud rate brought into
+    /// the range the UART's divider reaches from `peripheral_clock`.
+    fn config(&self, peripheral_clock: HertzU32) -> UartConfig {
+        let max = peripheral_clock.to_Hz() / 16;
+        let min = max / 0xFFFF + 1;
+        let data_bits = match self.data_bits {
+            5 => DataBits::Five,
+            6 => DataBits::Six,
+            7 => DataBits::Seven,
+            _ => DataBits::Eight,
+        };
+        let parity = match self.parity {
+            LineParity::None => None,
+            LineParity::Odd => Some(Parity::Odd),
+            LineParity::Even => Some(Parity::Even),
+        };
+        let stop_bits = if self.two_stop_bits {
+            StopBits::Two
+        } else {
+            StopBits::One
+        };
+        UartConfig::new(self.baud.clamp(min, max).Hz(), data_bits, parity, stop_bits)
+    }
+}
+
+/// A hardware UART bridged to USB Serial.
+pub struct UartBridge<D: UartDevice, P: ValidUartPinout<D>> {
+    /// Only `None` while the UART is being reconfigured.
+    uart: Option<UartPeripheral<Enabled, D, P>>,
+    peripheral_clock: HertzU32,
+    format: Format,
+    pending: Deque<u8, PENDING_SIZE>,
+}
+
+impl<D: UartDevice, P: ValidUartPinout<D>> UartBridge<D, P> {
+    /// Enable `uart` at 115200 baud, 8N1, until the host sets its own line
+    /// coding. `peripheral_clock` is `clk_peri`'s frequency.
+    pub fn new(uart: UartPeripheral<Disabled, D, P>, peripheral_clock: HertzU32) -> Self {
+        let uart = uart
+            .enable(DEFAULT_FORMAT.config(peripheral_clock), peripheral_clock)
+            .expect("the default line coding is always valid");
+        UartBridge {
+            uart: Some(uart),
+            peripheral_clock,
+            format: DEFAULT_FORMAT,
+            pending: Deque::new(),
+        }
+    }
+
+    /// The baud rate the UART is set to, as the host asked for it.
+    pub fn baud(&self) -> u32 {
+        self.format.baud
+    }
+
+    /// Follow any change of line coding, then move bytes both ways.
+    ///
+    /// Call it after each poll of the USB device.
+    pub fn poll<B: UsbBus, const N: usize>(&mut self, serial: &mut BufferedSerial<'_, B, N>) {
+        self.follow_line_coding(serial);
+        let uart = match self.uart.as_mut() {
+            Some(uart) => uart,
+            None => return,
+        };
+
+        // Host to UART, taking only what there is room for
+        let mut chunk = [0u8; 64];
+        let room = (self.pending.capacity() - self.pending.len()).min(chunk.len());
+        let count = serial.read(&mut chunk[..room]);
+        for &byte in &chunk[..count] {
+            // Can't fail, there is room for all of it
+            let _ = self.pending.push_back(byte);
+        }
+        while let Some(&byte) = self.pending.front() {
+            if uart.write(byte).is_err() {
+                break;
+            }
+            self.pending.pop_front();
+        }
+
+        // UART to host, while the USB queue has room
+        let room = (N - serial.pending()).min(chunk.len());
+        let mut count = 0;
+        while count < room {
+            match uart.read() {
+                Ok(byte) => chunk[count] = byte,
+                // A byte with a framing or parity error is dropped
+                Err(nb::Error::Other(_)) => continue,
+                Err(nb::Error::WouldBlock) => break,
+            }
+            count += 1;
+        }
+        // Can't fail, there is room for all of it
+        let _ = serial.write_all(&chunk[..count]);
+    }
+
+    /// Stop, handing back the UART disabled.
+    pub fn free(mut self) -> UartPeripheral<Disabled, D, P> {
+        match self.uart.take() {
+            Some(uart) => uart.disable(),
+            None => unreachable!("the UART is only taken while reconfiguring"),
+        }
+    }
+
+    /// Reconfigure the UART if the host has changed the line coding,
+    /// ignoring the rate of 0 some hosts report before setting one.
+    fn follow_line_coding<B: UsbBus, const N: usize>(
+        &mut self,
+        serial: &mut BufferedSerial<'_, B, N>,
+    ) {
+        let coding = serial.port().line_coding();
+        if coding.data_rate() == 0 {
+            return;
+        }
+        let format = Format {
+            baud: coding.data_rate(),
+            data_bits: coding.data_bits(),
+            parity: match coding.parity_type() {
+                ParityType::Odd => LineParity::Odd,
+                ParityType::Event => LineParity::Even,
+                _ => LineParity::None,
+            },
+            two_stop_bits: !matches!(coding.stop_bits(), UsbStopBits::One),
+        };
+        if format == self.format {
+            return;
+        }
+
+        // Let what has already been queued go out at the old settings
+        if let Some(mut uart) = self.uart.take() {
+            let _ = nb::block!(uart.flush());
+            let uart = uart
+                .disable()
+                .enable(format.config(self.peripheral_clock), self.peripheral_clock)
+                .expect("the baud rate is clamped to what the UART can do");
+            self.uart = Some(uart);
+        }
+        self.format = format;
+    }
+}
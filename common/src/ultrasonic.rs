@@ -0,0 +1,116 @@
+//! HC-SR04 and similar ultrasonic rangefinders.
+//!
+//! A 10 µs pulse on the trigger pin sends a burst of ultrasound, and the
+//! sensor then holds its echo pin high for as long as the sound took to come
+//! back. [`trigger`] sends the pulse, and an [`EchoTimer`] times the echo
+//! from the GPIO interrupts on both of its edges, with the timer's 64-bit
+//! microsecond count:
+//!
+//! ```ignore
+//! static ECHO: Mutex<RefCell<EchoTimer>> = Mutex::new(RefCell::new(EchoTimer::new()));
+//!
+//! critical_section::with(|cs| ECHO.borrow_ref_mut(cs).arm());
+//! ultrasonic::trigger(&mut trigger_pin);
+//! // ... and once the echo is over:
+//! let echo_us = critical_section::with(|cs| ECHO.borrow_ref(cs).echo_us());
+//!
+//! #[interrupt]
+//! fn IO_IRQ_BANK0() {
+//!     critical_section::with(|cs| {
+//!         let mut echo = ECHO.borrow_ref_mut(cs);
+//!         let now = timer.get_counter().ticks();
+//!         if echo_pin.interrupt_status(EdgeHigh) {
+//!             echo_pin.clear_interrupt(EdgeHigh);
+//!             echo.on_edge(true, now);
+//!         }
+//!         // ... and the same for EdgeLow, with false
+//!     });
+//! }
+//! ```
+//!
+//! [`distance_mm`] turns the echo into a distance. The HC-SR04's echo pin
+//! swings to 5 V, so it needs a divider, e.g. 1 kΩ and 2 kΩ, to feed a GPIO;
+//! the HC-SR04P runs from 3.3 V and doesn't.
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::v2::OutputPin;
+
+use crate::delay;
+
+/// Length of the trigger pulse, in microseconds.
+pub const TRIGGER_US: u32 = 10;
+
+/// The longest echo worth waiting for, in microseconds: about 4 m there and
+/// back, the sensor's rated range. With nothing in range, the HC-SR04 holds
+/// the echo high for about 38 ms.
+pub const MAX_ECHO_US: u32 = 25_000;
+
+/// The shortest time from one trigger to the next, in microseconds, so the
+/// last burst's echoes have died away.
+pub const CYCLE_US: u32 = 60_000;
+
+/// Send the trigger pulse, timed with [`delay`](crate::delay), which needs
+/// [`delay::calibrate`](crate::delay::calibrate).
+pub fn trigger<P: OutputPin<Error = Infallible>>(pin: &mut P) {
+    let _ = pin.set_high();
+    delay::delay_us(TRIGGER_US);
+    let _ = pin.set_low();
+}
+
+/// The distance in millimetres for an echo lasting `echo_us`, in air at
+/// `celsius` degrees.
+///
+/// Sound travels at about 331.3 m/s at 0 °C, and 0.606 m/s faster for each
+/// degree warmer: ignoring the temperature is out by about 1 % per 6 °C.
+pub fn distance_mm(echo_us: u32, celsius: i32) -> u32 {
+    // Millimetres per second
+    let speed = (331_300 + 606 * celsius).max(0) as u64;
+    // The echo covers the distance twice
+    (u64::from(echo_us) * speed / 2_000_000) as u32
+}
+
+/// Times the echo pulse from its edges.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EchoTimer {
+    /// When the echo went high.
+    rise_us: Option<u64>,
+    /// How long it stayed high, once it has gone low again.
+    echo_us: Option<u32>,
+}
+
+impl EchoTimer {
+    /// A timer with no measurement.
+    pub const fn new() -> Self {
+        EchoTimer {
+            rise_us: None,
+            echo_us: None,
+        }
+    }
+
+    /// Forget the last measurement, ready for the next trigger.
+    pub fn arm(&mut self) {
+        *self = EchoTimer::new();
+    }
+
+    /// Record an edge of the echo at `now_us`, rising if `high`. Call this
+    /// from the GPIO interrupt, rising edge first if both are pending.
+    pub fn on_edge(&mut self, high: bool, now_us: u64) {
+        if high {
+            self.rise_us = Some(now_us);
+        } else if let Some(rise_us) = self.rise_us.take() {
+            let width = now_us.saturating_sub(rise_us);
+            self.echo_us = Some(width.min(u64::from(u32::MAX)) as u32);
+        }
+    }
+
+    /// Whether the echo has started.
+    pub fn started(&self) -> bool {
+        self.rise_us.is_some() || self.echo_us.is_some()
+    }
+
+    /// How long the echo lasted, in microseconds, once it is over.
+    pub fn echo_us(&self) -> Option<u32> {
+        self.echo_us
+    }
+}
@@ -0,0 +1,262 @@
+//! Making the host notice that the device restarted.
+//!
+//! A watchdog or software reset only drops the D+ pull-up for a few
+//! microseconds before the new firmware turns it back on. Many hosts miss
+//! that blip and keep talking to the old device, and the CDC port never comes
+//! back until the cable is unplugged.
+//!
+//! [`force_reenumeration`] fixes this on start-up: it holds the bus in the
+//! single-ended-zero state a real unplug produces for long enough that the
+//! host's hub sees a disconnect. [`detach`], [`soft_reset`] and
+//! [`soft_reset_to_usb_boot`] do the same on the way down, for firmware that
+//! resets itself, such as after writing an update or to reboot into the
+//! bootloader.
+//!
+//! ```ignore
+//! usb::force_reenumeration(&pac.USBCTRL_REGS, &mut pac.RESETS, usb::DEFAULT_DETACH_MS);
+//! let usb_bus = UsbBusAllocator::new(hal::usb::UsbBus::new(pac.USBCTRL_REGS, /* ... */));
+//! ```
+//!
+//! The waits use [`delay`](crate::delay), so calibrate it first.
+//!
+//! [`ResetInterface`] is the other half of a firmware update: it is the
+//! vendor interface `picotool` looks for, so `picotool reboot -u` (or a
+//! `cargo run` runner calling it) can put the board in BOOTSEL mode without
+//! anyone pressing the button.
+
+use crate::delay;
+use crate::hal::pac;
+use crate::hal::rom_data;
+
+use usb_device::class_prelude::*;
+use usb_device::control::{Recipient, RequestType};
+
+/// How long to hold the bus disconnected by default, in milliseconds.
+///
+/// The USB specification gives hubs up to 100 ms to debounce a connect; this
+/// is comfortably longer than any host needs to register the disconnect.
+pub const DEFAULT_DETACH_MS: u32 = 100;
+
+/// Disconnect from the bus for `detach_ms`, then leave the USB controller in
+/// reset ready for `hal::usb::UsbBus::new`.
+///
+/// Call this before creating the USB bus, on every boot, so the host always
+/// enumerates the device afresh.
+pub fn force_reenumeration(regs: &pac::USBCTRL_REGS, resets: &mut pac::RESETS, detach_ms: u32) {
+    resets.reset.modify(|_, w| w.usbctrl().clear_bit());
+    while resets.reset_done.read().usbctrl().bit_is_clear() {}
+
+    detach(regs);
+    delay::delay_us(detach_ms.saturating_mul(1000));
+    release(regs);
+
+    resets.reset.modify(|_, w| w.usbctrl().set_bit());
+}
+
+/// Drop the D+ pull-up and drive both data lines low.
+///
+/// The controller has to be out of reset. The bus stays detached until
+/// [`release`] is called or the chip is reset.
+pub fn detach(regs: &pac::USBCTRL_REGS) {
+    // Connect the controller to the on-chip PHY, with the SIE switched off
+    regs.usb_muxing
+        .write(|w| w.to_phy().set_bit().softcon().set_bit());
+    regs.sie_ctrl.modify(|_, w| w.pullup_en().clear_bit());
+
+    // Drive SE0 directly through the PHY overrides, as a real unplug would
+    regs.usbphy_direct.write(|w| {
+        w.dp_pullup_en()
+            .clear_bit()
+            .tx_dp_oe()
+            .set_bit()
+            .tx_dm_oe()
+            .set_bit()
+            .tx_dp()
+            .clear_bit()
+            .tx_dm()
+            .clear_bit()
+    });
+    regs.usbphy_direct_override.write(|w| {
+        w.dp_pullup_en_override_en()
+            .set_bit()
+            .tx_dp_oe_override_en()
+            .set_bit()
+            .tx_dm_oe_override_en()
+            .set_bit()
+            .tx_dp_override_en()
+            .set_bit()
+            .tx_dm_override_en()
+            .set_bit()
+    });
+}
+
+/// Undo [`detach`], handing the data lines back to the controller.
+pub fn release(regs: &pac::USBCTRL_REGS) {
+    regs.usbphy_direct_override.write(|w| unsafe { w.bits(0) });
+    regs.usbphy_direct.write(|w| unsafe { w.bits(0) });
+}
+
+/// Detach from the bus for `detach_ms`, then reset the chip.
+///
+/// Use this instead of `SCB::sys_reset` when the firmware restarts itself, so
+/// the host has already seen the device go away when it comes back.
+///
+/// # Safety
+///
+/// The USB controller must be out of reset. Calling this while a
+/// `hal::usb::UsbBus` exists is fine, as the chip resets straight after.
+pub unsafe fn soft_reset(detach_ms: u32) -> ! {
+    detach_before_reset(detach_ms);
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// Detach from the bus for `detach_ms`, then reboot into the USB bootloader.
+///
+/// The masks are those of `rom_data::reset_to_usb_boot`: the GPIOs the
+/// bootloader uses as an activity light, and the bootloader interfaces to
+/// leave out.
+///
+/// # Safety
+///
+/// As for [`soft_reset`].
+pub unsafe fn soft_reset_to_usb_boot(
+    detach_ms: u32,
+    gpio_activity_pin_mask: u32,
+    disable_interface_mask: u32,
+) -> ! {
+    detach_before_reset(detach_ms);
+    rom_data::reset_to_usb_boot(gpio_activity_pin_mask, disable_interface_mask);
+    // The ROM resets the chip before it gets here
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+/// The shared start of [`soft_reset`] and [`soft_reset_to_usb_boot`].
+unsafe fn detach_before_reset(detach_ms: u32) {
+    let regs = pac::Peripherals::steal().USBCTRL_REGS;
+    cortex_m::interrupt::disable();
+    detach(&regs);
+    delay::delay_us(detach_ms.saturating_mul(1000));
+}
+
+/// Interface subclass of the `picotool` reset interface.
+const RESET_SUBCLASS: u8 = 0x00;
+
+/// Interface protocol of the `picotool` reset interface.
+const RESET_PROTOCOL: u8 = 0x01;
+
+/// Reboot into the USB bootloader. `wValue` bits 0-6 hold the bootloader
+/// interfaces to disable, and when bit 8 is set, bits 9-15 are the GPIO to use
+/// as an activity light.
+const REQUEST_BOOTSEL: u8 = 0x01;
+
+/// Reboot into the application in flash.
+const REQUEST_FLASH: u8 = 0x02;
+
+/// What the host asked for, carried out on the next poll.
+#[derive(Clone, Copy)]
+enum Reboot {
+    Bootsel { gpio_mask: u32, disable_mask: u32 },
+    Flash,
+}
+
+/// The reset interface of the Pico SDK's USB stdio, as a `usb-device` class.
+///
+/// It has no endpoints, only two control requests, so it can be added next to
+/// any other class:
+///
+/// ```ignore
+/// let mut serial = SerialPort::new(&usb_bus);
+/// let mut reset = usb::ResetInterface::new(&usb_bus);
+/// // ...
+/// usb_dev.poll(&mut [&mut serial, &mut reset]);
+/// ```
+///
+/// Releases of `picotool` before 2.0 only look for the interface on devices
+/// with Raspberry Pi's stdio VID:PID, `2e8a:000a`. Newer ones find it on any
+/// device.
+pub struct ResetInterface {
+    interface: InterfaceNumber,
+    name: StringIndex,
+    pending: Option<Reboot>,
+}
+
+impl ResetInterface {
+    /// Allocate the interface on `alloc`.
+    pub fn new<B: UsbBus>(alloc: &UsbBusAllocator<B>) -> Self {
+        ResetInterface {
+            interface: alloc.interface(),
+            name: alloc.string(),
+            pending: None,
+        }
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for ResetInterface {
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<(), UsbError> {
+        writer.interface_alt(
+            self.interface,
+            0,
+            0xFF,
+            RESET_SUBCLASS,
+            RESET_PROTOCOL,
+            Some(self.name),
+        )
+    }
+
+    fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&str> {
+        if index == self.name {
+            Some("Reset")
+        } else {
+            None
+        }
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        let request = xfer.request();
+        if request.request_type != RequestType::Class
+            || request.recipient != Recipient::Interface
+            || request.index != u16::from(u8::from(self.interface))
+        {
+            return;
+        }
+
+        let reboot = match request.request {
+            REQUEST_BOOTSEL => {
+                let gpio_mask = if request.value & 0x100 != 0 {
+                    1 << (request.value >> 9)
+                } else {
+                    0
+                };
+                Reboot::Bootsel {
+                    gpio_mask,
+                    disable_mask: u32::from(request.value & 0x7F),
+                }
+            }
+            REQUEST_FLASH => Reboot::Flash,
+            _ => {
+                xfer.reject().ok();
+                return;
+            }
+        };
+        self.pending = Some(reboot);
+        xfer.accept().ok();
+    }
+
+    /// Carry out a requested reboot, after the status stage has been queued.
+    ///
+    /// The host may not see the status stage complete before the device
+    /// disappears; `picotool` expects that and ignores the error.
+    fn poll(&mut self) {
+        match self.pending.take() {
+            // Safety: the chip resets straight away, taking the bus with it
+            Some(Reboot::Bootsel {
+                gpio_mask,
+                disable_mask,
+            }) => unsafe { soft_reset_to_usb_boot(DEFAULT_DETACH_MS, gpio_mask, disable_mask) },
+            Some(Reboot::Flash) => unsafe { soft_reset(DEFAULT_DETACH_MS) },
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! A USB microphone: USB Audio Class 1, 16 bit mono.
+//!
+//! [`Microphone`] shows up on the host as a sound card input, with no driver
+//! needed on Linux, macOS or Windows. The board hands it samples with
+//! [`write`](Microphone::write), from the ADC, a PDM microphone or anything
+//! else, and it sends them to the host a millisecond's worth at a time while
+//! something there is recording.
+//!
+//! ```ignore
+//! let mut mic = Microphone::new(&usb_bus, 16_000);
+//! loop {
+//!     usb_dev.poll(&mut [&mut mic]);
+//!     let len = pdm.read(&mut samples);
+//!     mic.write(&samples[..len]);
+//! }
+//! ```
+//!
+//! The samples come from the board's clock and the packets go at the host's
+//! pace, which can drift apart by a hundred samples a minute. The endpoint is
+//! asynchronous, so the class keeps a queue between the two and sends a
+//! sample more or less per packet when its average length strays.
+//! [`write`](Microphone::write) can hand over samples in blocks of up to
+//! [`MAX_BLOCK`] without the queue overflowing, at the cost of that much
+//! latency.
+
+use heapless::Deque;
+use usb_device::class_prelude::*;
+use usb_device::control::{Recipient, Request, RequestType};
+use usb_device::endpoint::EndpointType;
+
+/// The most samples [`Microphone::write`] should be given at once.
+pub const MAX_BLOCK: usize = 1024;
+
+/// The highest sample rate supported.
+pub const MAX_SAMPLE_RATE: u32 = 48_000;
+
+/// Samples the queue holds.
+const QUEUE_LEN: usize = 4 * MAX_BLOCK;
+
+/// Queue length to fill up to before sending, and to keep the average at.
+const TARGET_LEVEL: usize = 3 * QUEUE_LEN / 8;
+
+/// How far the average queue length strays before packets change length.
+const SLACK: usize = QUEUE_LEN / 16;
+
+/// Samples in the longest packet: a millisecond at the highest rate, a
+/// fraction left over and one to catch up.
+const MAX_PACKET_SAMPLES: usize = MAX_SAMPLE_RATE as usize / 1000 + 2;
+
+const USB_CLASS_AUDIO: u8 = 0x01;
+const AUDIO_SUBCLASS_CONTROL: u8 = 0x01;
+const AUDIO_SUBCLASS_STREAMING: u8 = 0x02;
+const CS_INTERFACE: u8 = 0x24;
+const CS_ENDPOINT: u8 = 0x25;
+const INTERFACE: u8 = 0x04;
+const ENDPOINT: u8 = 0x05;
+const AC_HEADER: u8 = 0x01;
+const AC_INPUT_TERMINAL: u8 = 0x02;
+const AC_OUTPUT_TERMINAL: u8 = 0x03;
+const AS_GENERAL: u8 = 0x01;
+const AS_FORMAT_TYPE: u8 = 0x02;
+const FORMAT_TYPE_I: u8 = 0x01;
+const EP_GENERAL: u8 = 0x01;
+const INPUT_TERMINAL_ID: u8 = 1;
+const OUTPUT_TERMINAL_ID: u8 = 2;
+
+/// Length of the audio control interface's class descriptors: its header,
+/// input terminal and output terminal.
+const AC_TOTAL_LEN: u16 = 9 + 12 + 9;
+
+/// A mono 16 bit USB microphone at a fixed sample rate.
+///
+/// Poll it with the `UsbDevice`, and keep it fed with
+/// [`write`](Microphone::write).
+pub struct Microphone<'a, B: UsbBus> {
+    control_if: InterfaceNumber,
+    stream_if: InterfaceNumber,
+    ep: EndpointIn<'a, B>,
+    sample_rate: u32,
+    /// Set while the host has the streaming interface's alternate setting 1
+    /// selected, i.e. is recording.
+    streaming: bool,
+    /// Set once the queue has filled to [`TARGET_LEVEL`], until it runs dry.
+    started: bool,
+    queue: Deque<i16, QUEUE_LEN>,
+    /// Thousandths of a sample carried over from the last packet.
+    remainder: u32,
+    /// The queue's length, averaged over the last quarter second or so, in
+    /// 1/256ths of a sample.
+    level: u32,
+}
+
+impl<'a, B: UsbBus> Microphone<'a, B> {
+    /// Allocate the interfaces and endpoint on `alloc`, for samples at
+    /// `sample_rate` Hz.
+    pub fn new(alloc: &'a UsbBusAllocator<B>, sample_rate: u32) -> Self {
+        assert!(
+            (1_000..=MAX_SAMPLE_RATE).contains(&sample_rate),
+            "the sample rate must be 1 kHz to 48 kHz"
+        );
+        let max_packet = (MAX_PACKET_SAMPLES * 2) as u16;
+        Microphone {
+            control_if: alloc.interface(),
+            stream_if: alloc.interface(),
+            ep: alloc
+                .alloc(None, EndpointType::Isochronous, max_packet, 1)
+                .expect("no isochronous endpoint left"),
+            sample_rate,
+            streaming: false,
+            started: false,
+            queue: Deque::new(),
+            remainder: 0,
+            level: (TARGET_LEVEL as u32) << 8,
+        }
+    }
+
+    /// The sample rate, in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// `true` while the host is recording.
+    pub fn is_streaming(&self) -> bool {
+        self.streaming
+    }
+
+    /// Queue `samples` for the host, returning how many fitted.
+    ///
+    /// While the host isn't recording they are all thrown away, so the host
+    /// never hears anything stale.
+    pub fn write(&mut self, samples: &[i16]) -> usize {
+        if !self.streaming {
+            return samples.len();
+        }
+        samples
+            .iter()
+            .take_while(|&&sample| self.queue.push_back(sample).is_ok())
+            .count()
+    }
+
+    /// Forget everything queued, and wait for the queue to fill again.
+    fn restart(&mut self) {
+        self.queue.clear();
+        self.started = false;
+        self.remainder = 0;
+        self.level = (TARGET_LEVEL as u32) << 8;
+    }
+
+    /// Write the next packet, if the endpoint is free: silence until the
+    /// queue has filled, then a millisecond of samples from it.
+    fn send_packet(&mut self) {
+        if !self.streaming {
+            return;
+        }
+        let queued = self.queue.len();
+        if !self.started && queued >= TARGET_LEVEL {
+            self.started = true;
+        }
+
+        let mut count = 0;
+        let mut remainder = self.remainder;
+        if self.started {
+            remainder += self.sample_rate;
+            count = (remainder / 1000) as usize;
+            remainder %= 1000;
+
+            // Steer the queue back towards the target, a sample at a time
+            let level = (self.level >> 8) as usize;
+            if level > TARGET_LEVEL + SLACK {
+                count += 1;
+            } else if level + SLACK < TARGET_LEVEL {
+                count -= 1;
+            }
+        }
+        let count = count.min(queued);
+
+        let mut packet = [0u8; MAX_PACKET_SAMPLES * 2];
+        for (bytes, sample) in packet
+            .chunks_exact_mut(2)
+            .zip(self.queue.iter())
+            .take(count)
+        {
+            bytes.copy_from_slice(&sample.to_le_bytes());
+        }
+        if self.ep.write(&packet[..count * 2]).is_err() {
+            // Still busy with the last one
+            return;
+        }
+
+        for _ in 0..count {
+            self.queue.pop_front();
+        }
+        if self.started {
+            self.remainder = remainder;
+            self.level = self.level - (self.level >> 8) + queued as u32;
+            if self.queue.is_empty() {
+                // Ran dry: fill up again rather than stutter
+                self.restart();
+            }
+        }
+    }
+
+    /// Whether `request` is the standard request `code` for the streaming
+    /// interface. usb-device 0.2 has no alternate settings, so the class
+    /// answers GET_INTERFACE and SET_INTERFACE itself.
+    fn is_stream_request(&self, request: &control::Request, code: u8) -> bool {
+        request.request_type == RequestType::Standard
+            && request.recipient == Recipient::Interface
+            && request.request == code
+            && request.index == u16::from(u8::from(self.stream_if))
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for Microphone<'_, B> {
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<(), UsbError> {
+        writer.interface(self.control_if, USB_CLASS_AUDIO, AUDIO_SUBCLASS_CONTROL, 0)?;
+        let [total_lo, total_hi] = AC_TOTAL_LEN.to_le_bytes();
+        // ADC 1.00, with one streaming interface
+        writer.write(
+            CS_INTERFACE,
+            &[
+                AC_HEADER,
+                0x00,
+                0x01,
+                total_lo,
+                total_hi,
+                1,
+                self.stream_if.into(),
+            ],
+        )?;
+        // A microphone with one channel...
+        writer.write(
+            CS_INTERFACE,
+            &[
+                AC_INPUT_TERMINAL,
+                INPUT_TERMINAL_ID,
+                0x01,
+                0x02,
+                0,
+                1,
+                0,
+                0,
+                0,
+                0,
+            ],
+        )?;
+        // ...feeding the USB stream
+        writer.write(
+            CS_INTERFACE,
+            &[
+                AC_OUTPUT_TERMINAL,
+                OUTPUT_TERMINAL_ID,
+                0x01,
+                0x01,
+                0,
+                INPUT_TERMINAL_ID,
+                0,
+            ],
+        )?;
+
+        // The host starts and stops recording by picking alternate setting 1
+        // or 0, which has no endpoints
+        writer.interface_alt(
+            self.stream_if,
+            0,
+            USB_CLASS_AUDIO,
+            AUDIO_SUBCLASS_STREAMING,
+            0,
+            None,
+        )?;
+        // Written by hand, as the endpoint descriptor that follows is the
+        // audio class's longer one, which `DescriptorWriter` can't count
+        writer.write(
+            INTERFACE,
+            &[
+                self.stream_if.into(),
+                1,
+                1,
+                USB_CLASS_AUDIO,
+                AUDIO_SUBCLASS_STREAMING,
+                0,
+                0,
+            ],
+        )?;
+        // PCM, one frame of delay
+        writer.write(
+            CS_INTERFACE,
+            &[AS_GENERAL, OUTPUT_TERMINAL_ID, 1, 0x01, 0x00],
+        )?;
+        let [rate_0, rate_1, rate_2, _] = self.sample_rate.to_le_bytes();
+        // Mono, 2 byte subframes of 16 bits, at one sample rate
+        writer.write(
+            CS_INTERFACE,
+            &[
+                AS_FORMAT_TYPE,
+                FORMAT_TYPE_I,
+                1,
+                2,
+                16,
+                1,
+                rate_0,
+                rate_1,
+                rate_2,
+            ],
+        )?;
+        let [size_lo, size_hi] = self.ep.max_packet_size().to_le_bytes();
+        // Isochronous and asynchronous, every frame, with no sync endpoint
+        writer.write(
+            ENDPOINT,
+            &[self.ep.address().into(), 0x05, size_lo, size_hi, 1, 0, 0],
+        )?;
+        // No sample rate or pitch controls
+        writer.write(CS_ENDPOINT, &[EP_GENERAL, 0, 0, 0, 0])
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        // Otherwise usb-device answers that every interface is on setting 0
+        if self.is_stream_request(xfer.request(), Request::GET_INTERFACE) {
+            xfer.accept_with(&[u8::from(self.streaming)]).ok();
+        }
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        if !self.is_stream_request(xfer.request(), Request::SET_INTERFACE) {
+            return;
+        }
+        let alternative = xfer.request().value;
+        if alternative > 1 {
+            xfer.reject().ok();
+            return;
+        }
+        self.streaming = alternative == 1;
+        self.restart();
+        xfer.accept().ok();
+    }
+
+    fn reset(&mut self) {
+        self.streaming = false;
+        self.restart();
+    }
+
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        if addr == self.ep.address() {
+            self.send_packet();
+        }
+    }
+
+    fn poll(&mut self) {
+        // Starts the stream off; after that each packet sent queues the next
+        self.send_packet();
+    }
+}
@@ -0,0 +1,152 @@
+//! A USB MIDI 1.0 device with one MIDI IN and one MIDI OUT jack.
+//!
+//! [`MidiClass`] shows up on the host as a MIDI port in each direction, with
+//! no driver needed on Linux, macOS or Windows. Messages travel as four byte
+//! USB MIDI event packets: a header with the cable number and the Code Index
+//! Number, which for channel messages is the status byte's top nibble, then
+//! up to three MIDI bytes.
+//!
+//! ```ignore
+//! let mut midi = MidiClass::new(&usb_bus);
+//! loop {
+//!     usb_dev.poll(&mut [&mut midi]);
+//!     // Note On, middle C, full velocity
+//!     let _ = midi.write(&[0x09, 0x90, 60, 127]);
+//! }
+//! ```
+
+use usb_device::class_prelude::*;
+use usb_device::Result;
+
+const USB_CLASS_AUDIO: u8 = 0x01;
+const AUDIO_SUBCLASS_CONTROL: u8 = 0x01;
+const AUDIO_SUBCLASS_MIDI_STREAMING: u8 = 0x03;
+const CS_INTERFACE: u8 = 0x24;
+const CS_ENDPOINT: u8 = 0x25;
+const AC_HEADER: u8 = 0x01;
+const MS_HEADER: u8 = 0x01;
+const MIDI_IN_JACK: u8 = 0x02;
+const MIDI_OUT_JACK: u8 = 0x03;
+const MS_GENERAL: u8 = 0x01;
+const JACK_EMBEDDED: u8 = 0x01;
+const JACK_EXTERNAL: u8 = 0x02;
+
+/// The jack the host sends to, and the one it stands for on our side.
+const EMBEDDED_IN_ID: u8 = 1;
+const EXTERNAL_OUT_ID: u8 = 2;
+
+/// The jack the host reads from, and the one it stands for on our side.
+const EXTERNAL_IN_ID: u8 = 3;
+const EMBEDDED_OUT_ID: u8 = 4;
+
+/// Length of the MIDI streaming interface's class descriptors: its header,
+/// the four jacks and both endpoints with their extra descriptors.
+const MS_TOTAL_LEN: u16 = 7 + 6 + 9 + 6 + 9 + 9 + 5 + 9 + 5;
+
+const MAX_PACKET: u16 = 64;
+
+/// A MIDI port on the host, to and from cable 0.
+pub struct MidiClass<'a, B: UsbBus> {
+    control_if: InterfaceNumber,
+    stream_if: InterfaceNumber,
+    ep_out: EndpointOut<'a, B>,
+    ep_in: EndpointIn<'a, B>,
+}
+
+impl<'a, B: UsbBus> MidiClass<'a, B> {
+    /// Allocate the interfaces and endpoints on `alloc`.
+    pub fn new(alloc: &'a UsbBusAllocator<B>) -> Self {
+        MidiClass {
+            control_if: alloc.interface(),
+            stream_if: alloc.interface(),
+            ep_out: alloc.bulk(MAX_PACKET),
+            ep_in: alloc.bulk(MAX_PACKET),
+        }
+    }
+
+    /// Send one event packet to the host.
+    ///
+    /// Fails with `WouldBlock` if the last one hasn't been collected yet.
+    pub fn write(&mut self, packet: &[u8; 4]) -> Result<usize> {
+        self.ep_in.write(packet)
+    }
+
+    /// Read what the host has sent into `data`, a whole number of event
+    /// packets, and return its length.
+    pub fn read(&mut self, data: &mut [u8]) -> Result<usize> {
+        self.ep_out.read(data)
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for MidiClass<'_, B> {
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<()> {
+        writer.interface(self.control_if, USB_CLASS_AUDIO, AUDIO_SUBCLASS_CONTROL, 0)?;
+        // Audio Class 1.0, with the one streaming interface
+        writer.write(
+            CS_INTERFACE,
+            &[AC_HEADER, 0x00, 0x01, 9, 0, 1, self.stream_if.into()],
+        )?;
+
+        writer.interface(
+            self.stream_if,
+            USB_CLASS_AUDIO,
+            AUDIO_SUBCLASS_MIDI_STREAMING,
+            0,
+        )?;
+        let [total_lo, total_hi] = MS_TOTAL_LEN.to_le_bytes();
+        writer.write(CS_INTERFACE, &[MS_HEADER, 0x00, 0x01, total_lo, total_hi])?;
+
+        // Host to us
+        writer.write(
+            CS_INTERFACE,
+            &[MIDI_IN_JACK, JACK_EMBEDDED, EMBEDDED_IN_ID, 0],
+        )?;
+        writer.write(
+            CS_INTERFACE,
+            &[
+                MIDI_OUT_JACK,
+                JACK_EXTERNAL,
+                EXTERNAL_OUT_ID,
+                1,
+                EMBEDDED_IN_ID,
+                1,
+                0,
+            ],
+        )?;
+
+        // Us to the host
+        writer.write(
+            CS_INTERFACE,
+            &[MIDI_IN_JACK, JACK_EXTERNAL, EXTERNAL_IN_ID, 0],
+        )?;
+        writer.write(
+            CS_INTERFACE,
+            &[
+                MIDI_OUT_JACK,
+                JACK_EMBEDDED,
+                EMBEDDED_OUT_ID,
+                1,
+                EXTERNAL_IN_ID,
+                1,
+                0,
+            ],
+        )?;
+
+        // Audio class endpoints have two extra bytes, unused for bulk
+        writer.endpoint_ex(&self.ep_out, |data| {
+            data[0] = 0;
+            data[1] = 0;
+            Ok(2)
+        })?;
+        writer.write(CS_ENDPOINT, &[MS_GENERAL, 1, EMBEDDED_IN_ID])?;
+
+        writer.endpoint_ex(&self.ep_in, |data| {
+            data[0] = 0;
+            data[1] = 0;
+            Ok(2)
+        })?;
+        writer.write(CS_ENDPOINT, &[MS_GENERAL, 1, EMBEDDED_OUT_ID])?;
+
+        Ok(())
+    }
+}
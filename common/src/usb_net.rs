@@ -0,0 +1,519 @@
+//! A USB network adapter: CDC-ECM, with a `smoltcp` device on top.
+//!
+//! [`Ecm`] is a USB class that shows up on the host as an Ethernet card
+//! wired straight to the board. Frames the host sends come out of its
+//! `smoltcp` [`Device`](smoltcp::phy::Device) implementation, and frames
+//! `smoltcp` sends go to the host, so a board can serve a small web page or
+//! take configuration over the cable it is powered from.
+//!
+//! ```ignore
+//! let mut ecm = Ecm::new(&usb_bus, HOST_MAC);
+//! let mut iface = Interface::new(Config::new(EthernetAddress(DEVICE_MAC).into()), &mut ecm, now);
+//! loop {
+//!     usb_dev.poll(&mut [&mut ecm]);
+//!     iface.poll(now, &mut ecm, &mut sockets);
+//! }
+//! ```
+//!
+//! The host needs an address on the link too: answering its DHCP requests
+//! with [`dhcp_reply`] gives it one without any set-up on its side. Linux
+//! and macOS drive CDC-ECM with their own drivers; Windows does not, as it
+//! only has built-in drivers for RNDIS and NCM.
+//!
+//! Needs the `usb-net` feature. `smoltcp` is re-exported, built for IPv4 over
+//! Ethernet with TCP and UDP sockets.
+
+pub use smoltcp;
+
+use smoltcp::phy::{self, DeviceCapabilities, Medium};
+use smoltcp::time::Instant;
+use usb_device::class_prelude::*;
+use usb_device::control::{Recipient, Request, RequestType};
+
+/// The largest Ethernet frame, without its checksum.
+pub const MAX_FRAME: usize = 1514;
+
+/// Bulk packet size, which is also the most a full-speed bulk endpoint takes.
+const PACKET: usize = 64;
+
+const USB_CLASS_CDC: u8 = 0x02;
+const USB_CLASS_CDC_DATA: u8 = 0x0A;
+const CDC_SUBCLASS_ECM: u8 = 0x06;
+const CS_INTERFACE: u8 = 0x24;
+const CDC_TYPE_HEADER: u8 = 0x00;
+const CDC_TYPE_UNION: u8 = 0x06;
+const CDC_TYPE_ETHERNET: u8 = 0x0F;
+const SET_ETHERNET_PACKET_FILTER: u8 = 0x43;
+const NETWORK_CONNECTION: u8 = 0x00;
+const CONNECTION_SPEED_CHANGE: u8 = 0x2A;
+
+/// The link speed told to the host, in bits per second: about what a
+/// full-speed bulk endpoint manages.
+const LINK_SPEED: u32 = 12_000_000;
+
+/// The notifications still to send once the host enables the data
+/// interface, which Linux waits for before bringing the link up.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Notify {
+    Connection,
+    Speed,
+    Done,
+}
+
+/// A frame on its way in from the host.
+struct Rx {
+    buf: [u8; MAX_FRAME],
+    len: usize,
+    /// Set when the frame ended with more than fits in `buf`.
+    overflow: bool,
+    /// Set when `buf[..len]` is a whole frame, not yet taken.
+    ready: bool,
+}
+
+/// A frame on its way out to the host.
+struct Tx {
+    buf: [u8; MAX_FRAME],
+    len: usize,
+    /// How much of the frame has been written to the endpoint.
+    sent: usize,
+    /// Set from when the frame is written into `buf` until its last packet
+    /// is sent.
+    busy: bool,
+}
+
+impl Tx {
+    /// Write the frame's next packet, if the endpoint is free. A packet
+    /// shorter than [`PACKET`], empty if need be, ends the frame.
+    fn send_next<B: UsbBus>(&mut self, ep: &EndpointIn<B>) {
+        while self.busy {
+            let end = (self.sent + PACKET).min(self.len);
+            match ep.write(&self.buf[self.sent..end]) {
+                Ok(written) => {
+                    self.sent += written;
+                    if written < PACKET {
+                        self.busy = false;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// A CDC-ECM network interface: one Ethernet link to the host.
+///
+/// Poll it with the `UsbDevice`, and use it as the `smoltcp` device of an
+/// `Interface` with a MAC address other than the host's.
+pub struct Ecm<'a, B: UsbBus> {
+    comm_if: InterfaceNumber,
+    comm_ep: EndpointIn<'a, B>,
+    data_if: InterfaceNumber,
+    read_ep: EndpointOut<'a, B>,
+    write_ep: EndpointIn<'a, B>,
+    mac_string: StringIndex,
+    /// The host's MAC address, as twelve hex digits.
+    mac_hex: heapless::String<12>,
+    /// The data interface's alternate setting: 1 when the host has the link
+    /// up.
+    data_alt: u8,
+    notify: Notify,
+    rx: Rx,
+    tx: Tx,
+}
+
+impl<'a, B: UsbBus> Ecm<'a, B> {
+    /// Allocate the interfaces and endpoints on `alloc`. The host's end of
+    /// the link gets `host_mac`, which should be locally administered, i.e.
+    /// have bit 1 of its first byte set.
+    pub fn new(alloc: &'a UsbBusAllocator<B>, host_mac: [u8; 6]) -> Self {
+        let mut mac_hex = heapless::String::new();
+        for byte in host_mac {
+            for nibble in [byte >> 4, byte & 0xF] {
+                let digit = char::from_digit(u32::from(nibble), 16).unwrap_or('0');
+                let _ = mac_hex.push(digit.to_ascii_uppercase());
+            }
+        }
+        Ecm {
+            comm_if: alloc.interface(),
+            comm_ep: alloc.interrupt(16, 32),
+            data_if: alloc.interface(),
+            read_ep: alloc.bulk(PACKET as u16),
+            write_ep: alloc.bulk(PACKET as u16),
+            mac_string: alloc.string(),
+            mac_hex,
+            data_alt: 0,
+            notify: Notify::Done,
+            rx: Rx {
+                buf: [0; MAX_FRAME],
+                len: 0,
+                overflow: false,
+                ready: false,
+            },
+            tx: Tx {
+                buf: [0; MAX_FRAME],
+                len: 0,
+                sent: 0,
+                busy: false,
+            },
+        }
+    }
+
+    /// `true` once the host has brought the link up.
+    pub fn is_connected(&self) -> bool {
+        self.data_alt == 1
+    }
+
+    /// Read packets until a frame is complete or the endpoint is empty.
+    fn read_packets(&mut self) {
+        let rx = &mut self.rx;
+        while !rx.ready {
+            let mut packet = [0; PACKET];
+            let len = match self.read_ep.read(&mut packet) {
+                Ok(len) => len,
+                Err(_) => break,
+            };
+            match rx.buf.get_mut(rx.len..rx.len + len) {
+                Some(space) => {
+                    space.copy_from_slice(&packet[..len]);
+                    rx.len += len;
+                }
+                None => rx.overflow = true,
+            }
+            if len < PACKET {
+                // The end of a frame: keep it if it is whole, or start over
+                if rx.overflow || rx.len == 0 {
+                    rx.len = 0;
+                    rx.overflow = false;
+                } else {
+                    rx.ready = true;
+                }
+            }
+        }
+    }
+
+    /// Send the next notification, if there is one and the endpoint is free.
+    fn send_notification(&mut self) {
+        let interface = u8::from(self.comm_if);
+        let sent = match self.notify {
+            Notify::Connection => {
+                self.comm_ep
+                    .write(&[0xA1, NETWORK_CONNECTION, 1, 0, interface, 0, 0, 0])
+            }
+            Notify::Speed => {
+                let mut notification = [0; 16];
+                notification[..8].copy_from_slice(&[
+                    0xA1,
+                    CONNECTION_SPEED_CHANGE,
+                    0,
+                    0,
+                    interface,
+                    0,
+                    8,
+                    0,
+                ]);
+                notification[8..12].copy_from_slice(&LINK_SPEED.to_le_bytes());
+                notification[12..16].copy_from_slice(&LINK_SPEED.to_le_bytes());
+                self.comm_ep.write(&notification)
+            }
+            Notify::Done => return,
+        };
+        if sent.is_ok() {
+            self.notify = match self.notify {
+                Notify::Connection => Notify::Speed,
+                _ => Notify::Done,
+            };
+        }
+    }
+
+    /// Whether `request` is the standard request `code` for the data
+    /// interface.
+    fn is_data_request(&self, request: &control::Request, code: u8) -> bool {
+        request.request_type == RequestType::Standard
+            && request.recipient == Recipient::Interface
+            && request.request == code
+            && request.index == u16::from(u8::from(self.data_if))
+    }
+}
+
+impl<B: UsbBus> UsbClass<B> for Ecm<'_, B> {
+    fn get_configuration_descriptors(&self, writer: &mut DescriptorWriter) -> Result<(), UsbError> {
+        writer.iad(self.comm_if, 2, USB_CLASS_CDC, CDC_SUBCLASS_ECM, 0)?;
+        writer.interface(self.comm_if, USB_CLASS_CDC, CDC_SUBCLASS_ECM, 0)?;
+        // CDC 1.10
+        writer.write(CS_INTERFACE, &[CDC_TYPE_HEADER, 0x10, 0x01])?;
+        writer.write(
+            CS_INTERFACE,
+            &[CDC_TYPE_UNION, self.comm_if.into(), self.data_if.into()],
+        )?;
+        let [size_lo, size_hi] = (MAX_FRAME as u16).to_le_bytes();
+        writer.write(
+            CS_INTERFACE,
+            &[
+                CDC_TYPE_ETHERNET,
+                self.mac_string.into(),
+                // No statistics, no multicast or power filters
+                0,
+                0,
+                0,
+                0,
+                size_lo,
+                size_hi,
+                0,
+                0,
+                0,
+            ],
+        )?;
+        writer.endpoint(&self.comm_ep)?;
+        // The host turns the link on and off by picking alternate setting 1
+        // or 0, which has no endpoints
+        writer.interface_alt(self.data_if, 0, USB_CLASS_CDC_DATA, 0, 0, None)?;
+        writer.interface_alt(self.data_if, 1, USB_CLASS_CDC_DATA, 0, 0, None)?;
+        writer.endpoint(&self.write_ep)?;
+        writer.endpoint(&self.read_ep)
+    }
+
+    fn get_string(&self, index: StringIndex, _lang_id: u16) -> Option<&str> {
+        if index == self.mac_string {
+            Some(self.mac_hex.as_str())
+        } else {
+            None
+        }
+    }
+
+    fn reset(&mut self) {
+        self.data_alt = 0;
+        self.notify = Notify::Done;
+        self.rx.len = 0;
+        self.rx.overflow = false;
+        self.rx.ready = false;
+        self.tx.busy = false;
+    }
+
+    fn control_in(&mut self, xfer: ControlIn<B>) {
+        // Otherwise usb-device answers that every interface is on setting 0
+        if self.is_data_request(xfer.request(), Request::GET_INTERFACE) {
+            xfer.accept_with(&[self.data_alt]).ok();
+        }
+    }
+
+    fn control_out(&mut self, xfer: ControlOut<B>) {
+        if self.is_data_request(xfer.request(), Request::SET_INTERFACE) {
+            let alternative = xfer.request().value;
+            if alternative > 1 {
+                xfer.reject().ok();
+                return;
+            }
+            self.data_alt = alternative as u8;
+            self.notify = if alternative == 1 {
+                Notify::Connection
+            } else {
+                Notify::Done
+            };
+            xfer.accept().ok();
+            return;
+        }
+
+        let request = xfer.request();
+        if request.request_type != RequestType::Class
+            || request.recipient != Recipient::Interface
+            || request.index != u16::from(u8::from(self.comm_if))
+        {
+            return;
+        }
+        if request.request == SET_ETHERNET_PACKET_FILTER {
+            // Everything for us arrives anyway: there is nobody else on
+            // the link
+            xfer.accept().ok();
+        } else {
+            xfer.reject().ok();
+        }
+    }
+
+    fn endpoint_out(&mut self, addr: EndpointAddress) {
+        if addr == self.read_ep.address() {
+            self.read_packets();
+        }
+    }
+
+    fn endpoint_in_complete(&mut self, addr: EndpointAddress) {
+        if addr == self.write_ep.address() {
+            self.tx.send_next(&self.write_ep);
+        } else if addr == self.comm_ep.address() {
+            self.send_notification();
+        }
+    }
+
+    fn poll(&mut self) {
+        self.send_notification();
+        self.read_packets();
+        self.tx.send_next(&self.write_ep);
+    }
+}
+
+/// Hands `smoltcp` the frame [`Ecm`] has received.
+pub struct EcmRxToken<'t> {
+    rx: &'t mut Rx,
+}
+
+impl phy::RxToken for EcmRxToken<'_> {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let result = f(&mut self.rx.buf[..self.rx.len]);
+        self.rx.len = 0;
+        self.rx.ready = false;
+        result
+    }
+}
+
+/// Takes a frame from `smoltcp` for [`Ecm`] to send.
+pub struct EcmTxToken<'t, 'a, B: UsbBus> {
+    tx: &'t mut Tx,
+    ep: &'t EndpointIn<'a, B>,
+}
+
+impl<B: UsbBus> phy::TxToken for EcmTxToken<'_, '_, B> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let len = len.min(MAX_FRAME);
+        let result = f(&mut self.tx.buf[..len]);
+        self.tx.len = len;
+        self.tx.sent = 0;
+        self.tx.busy = true;
+        self.tx.send_next(self.ep);
+        result
+    }
+}
+
+impl<'a, B: UsbBus> phy::Device for Ecm<'a, B> {
+    type RxToken<'t>
+        = EcmRxToken<'t>
+    where
+        Self: 't;
+    type TxToken<'t>
+        = EcmTxToken<'t, 'a, B>
+    where
+        Self: 't;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        // A reply may go straight out, so only hand over a frame when there
+        // is room for one
+        if !self.rx.ready || self.tx.busy {
+            return None;
+        }
+        Some((
+            EcmRxToken { rx: &mut self.rx },
+            EcmTxToken {
+                tx: &mut self.tx,
+                ep: &self.write_ep,
+            },
+        ))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        if !self.is_connected() || self.tx.busy {
+            return None;
+        }
+        Some(EcmTxToken {
+            tx: &mut self.tx,
+            ep: &self.write_ep,
+        })
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut capabilities = DeviceCapabilities::default();
+        capabilities.medium = Medium::Ethernet;
+        capabilities.max_transmission_unit = MAX_FRAME;
+        capabilities.max_burst_size = Some(1);
+        capabilities
+    }
+}
+
+/// The smallest BOOTP message, which DHCP replies are padded to.
+pub const DHCP_REPLY_LEN: usize = 300;
+
+/// The lease [`dhcp_reply`] hands out, in seconds: a day, though as the
+/// only address on offer it is renewed the same every time.
+const DHCP_LEASE_S: u32 = 86_400;
+
+const DHCP_MAGIC: [u8; 4] = [0x63, 0x82, 0x53, 0x63];
+const DHCP_DISCOVER: u8 = 1;
+const DHCP_OFFER: u8 = 2;
+const DHCP_REQUEST: u8 = 3;
+const DHCP_ACK: u8 = 5;
+
+/// Answer the DHCP `request` a host sent to UDP port 67, offering or
+/// acknowledging `client` as its address on a /24 with `server`, this board,
+/// and no default route.
+///
+/// Writes the reply, to be broadcast to UDP port 68, into `reply`, which has
+/// to hold [`DHCP_REPLY_LEN`] bytes, and returns its length. Returns `None`
+/// for anything but a DISCOVER or REQUEST.
+pub fn dhcp_reply(
+    request: &[u8],
+    server: [u8; 4],
+    client: [u8; 4],
+    reply: &mut [u8],
+) -> Option<usize> {
+    // A BOOTREQUEST for Ethernet, with the DHCP magic cookie
+    if request.len() < 240
+        || request[0] != 1
+        || request[1] != 1
+        || request[2] != 6
+        || request[236..240] != DHCP_MAGIC
+    {
+        return None;
+    }
+    let mut options = &request[240..];
+    let mut message_type = None;
+    while let [code, rest @ ..] = options {
+        match code {
+            0 => options = rest,
+            255 => break,
+            _ => {
+                let (&len, rest) = rest.split_first()?;
+                let value = rest.get(..usize::from(len))?;
+                if *code == 53 && len == 1 {
+                    message_type = Some(value[0]);
+                }
+                options = &rest[usize::from(len)..];
+            }
+        }
+    }
+    let reply_type = match message_type? {
+        DHCP_DISCOVER => DHCP_OFFER,
+        DHCP_REQUEST => DHCP_ACK,
+        _ => return None,
+    };
+
+    let reply = reply.get_mut(..DHCP_REPLY_LEN)?;
+    reply.fill(0);
+    // BOOTREPLY, Ethernet, 6-byte addresses
+    reply[..3].copy_from_slice(&[2, 1, 6]);
+    // The transaction ID and flags
+    reply[4..8].copy_from_slice(&request[4..8]);
+    reply[10..12].copy_from_slice(&request[10..12]);
+    reply[16..20].copy_from_slice(&client);
+    reply[20..24].copy_from_slice(&server);
+    // The client's hardware address
+    reply[28..44].copy_from_slice(&request[28..44]);
+    reply[236..240].copy_from_slice(&DHCP_MAGIC);
+
+    let lease = DHCP_LEASE_S.to_be_bytes();
+    let options: [&[u8]; 5] = [
+        &[53, 1, reply_type],
+        &[54, 4, server[0], server[1], server[2], server[3]],
+        &[51, 4, lease[0], lease[1], lease[2], lease[3]],
+        &[1, 4, 255, 255, 255, 0],
+        &[255],
+    ];
+    let mut at = 240;
+    for option in options {
+        reply[at..at + option.len()].copy_from_slice(option);
+        at += option.len();
+    }
+    Some(DHCP_REPLY_LEN)
+}
@@ -0,0 +1,663 @@
+//! VGA output from PIO and DMA, in an 80x30 text mode.
+//!
+//! Three state machines of one PIO block make a 640x480 signal: one drives
+//! HSYNC and flags the start of each line, one counts lines to drive VSYNC
+//! and flags the visible ones, and the third shifts out a line of 8-bit
+//! RGB332 pixels from the DMA each time it is flagged. The pixel clock is
+//! 25 MHz rather than 25.175 MHz, which monitors take as 640x480 at 59.5 Hz,
+//! so the system clock has to be a multiple of 125 MHz, e.g. the default.
+//!
+//! The picture is a [`Screen`] of 80x30 character cells, each with a
+//! foreground and background colour from a 16 colour palette, drawn with a
+//! [`Font`] of 8x16 glyphs built from an `embedded-graphics` font. Two DMA
+//! channels take turns sending a line while the interrupt renders the one
+//! after next.
+//!
+//! ```ignore
+//! let screen = cortex_m::singleton!(: Screen = Screen::new()).unwrap();
+//! let font = cortex_m::singleton!(: Font = Font::new(&FONT_8X13)).unwrap();
+//! let lines = cortex_m::singleton!(: Lines = Lines::new()).unwrap();
+//! let vga = Vga::new(&mut pio, sm0, sm1, sm2, channels, DEFAULT_PINOUT, sys_hz, lines, screen, font, Irq::Irq0);
+//! writeln!(Console::new(screen, 0, attr(15, 1)), "Hello").unwrap();
+//!
+//! #[interrupt]
+//! fn DMA_IRQ_0() {
+//!     // Once per line: renders the line after next
+//!     vga.on_interrupt();
+//! }
+//! ```
+//!
+//! # Wiring
+//!
+//! The colour pins make the 0 to 0.7 V analogue levels through a resistor
+//! ladder per colour, summed at the VGA connector into its 75 Ω load. With
+//! [`DEFAULT_PINOUT`]:
+//!
+//! | GPIO | Signal  | Resistor | VGA pin |
+//! |------|---------|----------|---------|
+//! | 0    | Blue 0  | 820 Ω    | 3       |
+//! | 1    | Blue 1  | 390 Ω    | 3       |
+//! | 2    | Green 0 | 2 kΩ     | 2       |
+//! | 3    | Green 1 | 1 kΩ     | 2       |
+//! | 4    | Green 2 | 510 Ω    | 2       |
+//! | 5    | Red 0   | 2 kΩ     | 1       |
+//! | 6    | Red 1   | 1 kΩ     | 1       |
+//! | 7    | Red 2   | 510 Ω    | 1       |
+//! | 16   | HSYNC   | 100 Ω    | 13      |
+//! | 17   | VSYNC   | 100 Ω    | 14      |
+//! | GND  |         |          | 5 to 8, 10 |
+//!
+//! Needs the `vga` feature.
+
+use core::sync::atomic::{AtomicU16, Ordering};
+
+use embedded_graphics::{
+    mono_font::{MonoFont, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    prelude::*,
+    text::{Baseline, Text},
+};
+
+use crate::dma::{Channel, Irq};
+use crate::hal::{
+    pac,
+    pio::{
+        Buffers as PioBuffers, PIOBuilder, PIOExt, PinDir, Running, ShiftDirection, StateMachine,
+        Tx, UninitStateMachine, PIO, SM0, SM1, SM2,
+    },
+};
+
+/// Visible pixels per line.
+pub const WIDTH: usize = 640;
+
+/// Visible lines per frame.
+pub const HEIGHT: usize = 480;
+
+/// Character cells per row.
+pub const COLS: usize = 80;
+
+/// Rows of character cells.
+pub const ROWS: usize = 30;
+
+/// Lines per character cell.
+pub const CELL_HEIGHT: usize = HEIGHT / ROWS;
+
+/// Four RGB332 pixels per word.
+const LINE_WORDS: usize = WIDTH / 4;
+
+/// The first character a [`Font`] holds, a space.
+const FIRST_CHAR: u8 = 0x20;
+
+/// How many characters a [`Font`] holds: printable ASCII.
+const CHARS: usize = 0x7F - FIRST_CHAR as usize;
+
+/// Light grey on black, like a PC at boot.
+pub const DEFAULT_ATTR: u8 = attr(7, 0);
+
+/// Where the colour and sync signals come out.
+#[derive(Clone, Copy, Debug)]
+pub struct Pinout {
+    /// The first of eight consecutive pins carrying RGB332: blue from this
+    /// pin, then green, then red, each least significant bit first.
+    pub colour_base: u8,
+    /// The HSYNC pin.
+    pub hsync: u8,
+    /// The VSYNC pin.
+    pub vsync: u8,
+}
+
+/// Colour on GPIO 0 to 7, HSYNC on GPIO 16 and VSYNC on GPIO 17, as in the
+/// table above.
+pub const DEFAULT_PINOUT: Pinout = Pinout {
+    colour_base: 0,
+    hsync: 16,
+    vsync: 17,
+};
+
+/// A cell attribute: palette colour `fg` on palette colour `bg`.
+pub const fn attr(fg: u8, bg: u8) -> u8 {
+    (fg & 0xF) | (bg & 0xF) << 4
+}
+
+/// The RGB332 pixel nearest `red`, `green` and `blue`.
+pub const fn rgb332(red: u8, green: u8, blue: u8) -> u8 {
+    (red & 0xE0) | (green & 0xE0) >> 3 | blue >> 6
+}
+
+/// The text on show: 80x30 cells, shared by the code writing it and the
+/// [`Vga`] interrupt showing it.
+///
+/// Writing while it is shown can tear, but never blocks the interrupt.
+pub struct Screen {
+    /// Each cell's character in the low byte and attribute in the high.
+    cells: [AtomicU16; COLS * ROWS],
+}
+
+impl Screen {
+    /// A screen of spaces in [`DEFAULT_ATTR`].
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const BLANK: AtomicU16 = AtomicU16::new((DEFAULT_ATTR as u16) << 8 | b' ' as u16);
+        Screen {
+            cells: [BLANK; COLS * ROWS],
+        }
+    }
+
+    /// Put `ch` in the cell at `col`, `row`, in `attr`, if it is on screen.
+    pub fn put(&self, col: usize, row: usize, ch: u8, attr: u8) {
+        if col < COLS && row < ROWS {
+            self.cells[row * COLS + col]
+                .store(u16::from(attr) << 8 | u16::from(ch), Ordering::Relaxed);
+        }
+    }
+
+    /// The character and attribute in the cell at `col`, `row`.
+    pub fn get(&self, col: usize, row: usize) -> (u8, u8) {
+        let cell = self.cells[row * COLS + col].load(Ordering::Relaxed);
+        (cell as u8, (cell >> 8) as u8)
+    }
+
+    /// Write `text` from `col`, `row` onwards in `attr`, cutting it off at
+    /// the end of the row, and return the column after it.
+    pub fn write_str(&self, col: usize, row: usize, text: &str, attr: u8) -> usize {
+        let mut col = col;
+        for ch in text.bytes().take(COLS.saturating_sub(col)) {
+            self.put(col, row, ch, attr);
+            col += 1;
+        }
+        col
+    }
+
+    /// Fill rows `rows` with spaces in `attr`.
+    pub fn clear_rows(&self, rows: core::ops::Range<usize>, attr: u8) {
+        let blank = u16::from(attr) << 8 | u16::from(b' ');
+        let cells = rows.start.min(ROWS) * COLS..rows.end.min(ROWS) * COLS;
+        for cell in &self.cells[cells] {
+            cell.store(blank, Ordering::Relaxed);
+        }
+    }
+
+    /// Fill the screen with spaces in `attr`.
+    pub fn clear(&self, attr: u8) {
+        self.clear_rows(0..ROWS, attr);
+    }
+
+    /// Move rows `top + 1` onwards up by one, and blank the last in `attr`.
+    pub fn scroll_up(&self, top: usize, attr: u8) {
+        for cell in top * COLS..(ROWS - 1) * COLS {
+            let below = self.cells[cell + COLS].load(Ordering::Relaxed);
+            self.cells[cell].store(below, Ordering::Relaxed);
+        }
+        self.clear_rows(ROWS - 1..ROWS, attr);
+    }
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A scrolling terminal over the rows of a [`Screen`] from one onwards,
+/// written to with `write!`.
+///
+/// `\n` starts a new line, `\r` goes back to the start of this one, and
+/// text wraps at the end of each row.
+pub struct Console<'a> {
+    screen: &'a Screen,
+    top: usize,
+    col: usize,
+    row: usize,
+    attr: u8,
+}
+
+impl<'a> Console<'a> {
+    /// A console over rows `top` onwards of `screen`, writing in `attr`
+    /// from the start of row `top`.
+    pub fn new(screen: &'a Screen, top: usize, attr: u8) -> Self {
+        Console {
+            screen,
+            top,
+            col: 0,
+            row: top,
+            attr,
+        }
+    }
+
+    /// Write in `attr` from now on.
+    pub fn set_attr(&mut self, attr: u8) {
+        self.attr = attr;
+    }
+
+    /// Blank the console's rows and go back to their start.
+    pub fn clear(&mut self) {
+        self.screen.clear_rows(self.top..ROWS, self.attr);
+        self.col = 0;
+        self.row = self.top;
+    }
+
+    fn new_line(&mut self) {
+        self.col = 0;
+        if self.row + 1 < ROWS {
+            self.row += 1;
+        } else {
+            self.screen.scroll_up(self.top, self.attr);
+        }
+    }
+}
+
+impl core::fmt::Write for Console<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for ch in s.bytes() {
+            match ch {
+                b'\n' => self.new_line(),
+                b'\r' => self.col = 0,
+                _ => {
+                    if self.col == COLS {
+                        self.new_line();
+                    }
+                    self.screen.put(self.col, self.row, ch, self.attr);
+                    self.col += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 8x16 glyphs for printable ASCII, the leftmost pixel of each row in the
+/// top bit. Other characters show as blanks.
+pub struct Font {
+    glyphs: [[u8; CELL_HEIGHT]; CHARS],
+}
+
+impl Font {
+    /// Render every printable ASCII character of `font`, which has to be at
+    /// most 8 pixels wide and 16 high, e.g. `FONT_8X13`, centred vertically
+    /// in its cell.
+    pub fn new(font: &MonoFont) -> Self {
+        let size = font.character_size;
+        assert!(
+            size.width <= 8 && size.height as usize <= CELL_HEIGHT,
+            "VGA glyphs are at most 8x16"
+        );
+        let top = (CELL_HEIGHT as i32 - size.height as i32) / 2;
+        let style = MonoTextStyle::new(font, BinaryColor::On);
+
+        let mut glyphs = [[0; CELL_HEIGHT]; CHARS];
+        for (ch, glyph) in (FIRST_CHAR..).zip(glyphs.iter_mut()) {
+            let mut utf8 = [0; 4];
+            let text = char::from(ch).encode_utf8(&mut utf8);
+            let mut target = Glyph(glyph);
+            let _ = Text::with_baseline(text, Point::new(0, top), style, Baseline::Top)
+                .draw(&mut target);
+        }
+        Font { glyphs }
+    }
+
+    /// The pixels of row `y` of `ch`'s glyph.
+    fn row(&self, ch: u8, y: usize) -> u8 {
+        match self.glyphs.get(usize::from(ch.wrapping_sub(FIRST_CHAR))) {
+            Some(glyph) => glyph[y],
+            None => 0,
+        }
+    }
+}
+
+/// A draw target over one glyph's rows, for [`Font::new`].
+struct Glyph<'a>(&'a mut [u8; CELL_HEIGHT]);
+
+impl OriginDimensions for Glyph<'_> {
+    fn size(&self) -> Size {
+        Size::new(8, CELL_HEIGHT as u32)
+    }
+}
+
+impl DrawTarget for Glyph<'_> {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, colour) in pixels {
+            let on_glyph = (0..8).contains(&point.x) && (0..CELL_HEIGHT as i32).contains(&point.y);
+            if colour.is_on() && on_glyph {
+                self.0[point.y as usize] |= 0x80 >> point.x;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// CGA's 16 colours.
+const DEFAULT_PALETTE: [u8; 16] = [
+    rgb332(0, 0, 0),
+    rgb332(0, 0, 170),
+    rgb332(0, 170, 0),
+    rgb332(0, 170, 170),
+    rgb332(170, 0, 0),
+    rgb332(170, 0, 170),
+    rgb332(170, 85, 0),
+    rgb332(170, 170, 170),
+    rgb332(85, 85, 85),
+    rgb332(85, 85, 255),
+    rgb332(85, 255, 85),
+    rgb332(85, 255, 255),
+    rgb332(255, 85, 85),
+    rgb332(255, 85, 255),
+    rgb332(255, 255, 85),
+    rgb332(255, 255, 255),
+];
+
+/// Byte masks for four pixels from a nibble of glyph row, the leftmost,
+/// from the nibble's top bit, in the lowest byte.
+const EXPAND: [u32; 16] = {
+    let mut table = [0; 16];
+    let mut nibble = 0;
+    while nibble < 16 {
+        let mut pixel = 0;
+        while pixel < 4 {
+            if nibble & (8 >> pixel) != 0 {
+                table[nibble] |= 0xFF << (8 * pixel);
+            }
+            pixel += 1;
+        }
+        nibble += 1;
+    }
+    table
+};
+
+/// The DMA's two line buffers, 2.5 kB, e.g. for `cortex_m::singleton!`.
+pub struct Lines {
+    words: [[u32; LINE_WORDS]; 2],
+}
+
+impl Lines {
+    /// Empty line buffers.
+    pub const fn new() -> Self {
+        Lines {
+            words: [[0; LINE_WORDS]; 2],
+        }
+    }
+}
+
+impl Default for Lines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `CH_CTRL` for a line channel: enabled, high priority, words,
+/// incrementing the read address, paced by `dreq`, and chaining to the other
+/// line channel.
+fn line_ctrl(other: u8, dreq: u8) -> u32 {
+    const EN: u32 = 1 << 0;
+    const HIGH_PRIORITY: u32 = 1 << 1;
+    const SIZE_WORD: u32 = 2 << 2;
+    const INCR_READ: u32 = 1 << 4;
+    EN | HIGH_PRIORITY | SIZE_WORD | INCR_READ | u32::from(other) << 11 | u32::from(dreq) << 15
+}
+
+/// Render line `y` of `screen` into `line`.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+fn render_line(
+    screen: &Screen,
+    font: &Font,
+    palette: &[u32; 16],
+    line: &mut [u32; LINE_WORDS],
+    y: usize,
+) {
+    let (row, glyph_row) = (y / CELL_HEIGHT, y % CELL_HEIGHT);
+    let cells = &screen.cells[row * COLS..(row + 1) * COLS];
+    for (cell, words) in cells.iter().zip(line.chunks_exact_mut(2)) {
+        let cell = cell.load(Ordering::Relaxed);
+        let fg = palette[usize::from(cell >> 8) & 0xF];
+        let bg = palette[usize::from(cell >> 12)];
+        let pixels = font.row(cell as u8, glyph_row);
+        let (left, right) = (
+            EXPAND[usize::from(pixels >> 4)],
+            EXPAND[usize::from(pixels & 0xF)],
+        );
+        words[0] = (fg & left) | (bg & !left);
+        words[1] = (fg & right) | (bg & !right);
+    }
+}
+
+/// The three state machines, left running.
+type Machines<P> = (
+    StateMachine<(P, SM0), Running>,
+    StateMachine<(P, SM1), Running>,
+    StateMachine<(P, SM2), Running>,
+);
+
+/// VGA output running from a PIO block and two DMA channels.
+pub struct Vga<P: PIOExt> {
+    _sms: Machines<P>,
+    _tx: Tx<(P, SM2)>,
+    channels: [Channel; 2],
+    lines: &'static mut Lines,
+    screen: &'static Screen,
+    font: &'static Font,
+    /// Each palette colour's pixel, four times over.
+    palette: [u32; 16],
+    /// The next line to finish going out, from channel `line % 2`.
+    line: usize,
+}
+
+impl<P: PIOExt> Vga<P> {
+    /// Start showing `screen` in `font` on `pinout`.
+    ///
+    /// The pins must already be set to the PIO's function, and
+    /// `system_clock_hz` must be a multiple of 125 MHz. `channels` are any
+    /// two free DMA channels; completion interrupts come in on `irq`, which
+    /// has to be unmasked straight away, with
+    /// [`on_interrupt`](Self::on_interrupt) called from its handler.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm0: UninitStateMachine<(P, SM0)>,
+        sm1: UninitStateMachine<(P, SM1)>,
+        sm2: UninitStateMachine<(P, SM2)>,
+        mut channels: [Channel; 2],
+        pinout: Pinout,
+        system_clock_hz: u32,
+        lines: &'static mut Lines,
+        screen: &'static Screen,
+        font: &'static Font,
+        irq: Irq,
+    ) -> Self {
+        assert!(
+            system_clock_hz.is_multiple_of(125_000_000) && system_clock_hz > 0,
+            "VGA needs a multiple of 125 MHz"
+        );
+        let div = (system_clock_hz / 125_000_000) as u16;
+
+        let mut palette = [0; 16];
+        for (word, &colour) in palette.iter_mut().zip(DEFAULT_PALETTE.iter()) {
+            *word = u32::from(colour) * 0x0101_0101;
+        }
+        render_line(screen, font, &palette, &mut lines.words[0], 0);
+        render_line(screen, font, &palette, &mut lines.words[1], 1);
+
+        // At 25 MHz: 656 pixels of active line and front porch, 96 of sync
+        // and 48 of back porch, flagging the start of the next active line
+        let hsync = pio_proc::pio_asm!(
+            "    set pins, 1",
+            "    pull block",
+            ".wrap_target",
+            "    mov x, osr",
+            "active:",
+            "    jmp x-- active",
+            "    set pins, 0 [31]",
+            "    set pins, 0 [31]",
+            "    set pins, 0 [31]",
+            "    set pins, 1 [31]",
+            "    set pins, 1 [13]",
+            "    irq 0       [1]",
+            ".wrap",
+        );
+        // 480 active lines, flagged on to the pixels, then 10 of front
+        // porch, 2 of sync and 33 of back porch
+        let vsync = pio_proc::pio_asm!(
+            "    set pins, 1",
+            "    pull block",
+            ".wrap_target",
+            "    mov x, osr",
+            "active:",
+            "    wait 1 irq 0",
+            "    irq 1",
+            "    jmp x-- active",
+            "    set y, 9",
+            "front:",
+            "    wait 1 irq 0",
+            "    jmp y-- front",
+            "    set pins, 0",
+            "    wait 1 irq 0",
+            "    wait 1 irq 0",
+            "    set pins, 1",
+            "    set y, 31",
+            "back:",
+            "    wait 1 irq 0",
+            "    jmp y-- back",
+            "    wait 1 irq 0",
+            ".wrap",
+        );
+        // At 125 MHz: 640 pixels of five cycles each per flagged line, and
+        // black in between
+        let pixels = pio_proc::pio_asm!(
+            "    pull block",
+            "    mov y, osr",
+            "    out null, 32",
+            ".wrap_target",
+            "    mov pins, null",
+            "    wait 1 irq 1",
+            "    mov x, y",
+            "colour:",
+            "    out pins, 8 [3]",
+            "    jmp x-- colour",
+            ".wrap",
+        );
+        let mut install = |program: &pio::Program<{ pio::RP2040_MAX_PROGRAM_SIZE }>| {
+            pio.install(program)
+                .expect("VGA needs 37 instructions of the PIO's memory")
+        };
+        let (hsync, vsync, pixels) = (
+            install(&hsync.program),
+            install(&vsync.program),
+            install(&pixels.program),
+        );
+
+        let (mut sm0, _, mut tx0) = PIOBuilder::from_program(hsync)
+            .set_pins(pinout.hsync, 1)
+            .clock_divisor_fixed_point(div * 5, 0)
+            .build(sm0);
+        let (mut sm1, _, mut tx1) = PIOBuilder::from_program(vsync)
+            .set_pins(pinout.vsync, 1)
+            .clock_divisor_fixed_point(div * 5, 0)
+            .build(sm1);
+        let (mut sm2, _, mut tx2) = PIOBuilder::from_program(pixels)
+            .out_pins(pinout.colour_base, 8)
+            .out_shift_direction(ShiftDirection::Right)
+            .autopull(true)
+            .pull_threshold(32)
+            .buffers(PioBuffers::OnlyTx)
+            .clock_divisor_fixed_point(div, 0)
+            .build(sm2);
+        sm0.set_pindirs([(pinout.hsync, PinDir::Output)]);
+        sm1.set_pindirs([(pinout.vsync, PinDir::Output)]);
+        sm2.set_pindirs(
+            (pinout.colour_base..pinout.colour_base + 8).map(|pin| (pin, PinDir::Output)),
+        );
+
+        // Each loop counter, one less than its count: `mov x, osr` is the
+        // first of HSYNC's 656 cycles
+        tx0.write(655 - 1);
+        tx1.write(HEIGHT as u32 - 1);
+        tx2.write(WIDTH as u32 - 1);
+
+        let fifo = tx2.fifo_address() as u32;
+        let dreq = (P::id() * 8 + 2) as u8;
+        let ids = [channels[0].id(), channels[1].id()];
+        for (i, channel) in channels.iter_mut().enumerate() {
+            let regs = channel.regs();
+            regs.ch_read_addr
+                .write(|w| unsafe { w.bits(lines.words[i].as_ptr() as u32) });
+            regs.ch_write_addr.write(|w| unsafe { w.bits(fifo) });
+            regs.ch_trans_count
+                .write(|w| unsafe { w.bits(LINE_WORDS as u32) });
+            channel.listen(irq, None);
+        }
+        // Channel 1 waits to be chained to; channel 0 starts filling the FIFO
+        channels[1]
+            .regs()
+            .ch_al1_ctrl
+            .write(|w| unsafe { w.bits(line_ctrl(ids[0], dreq)) });
+        channels[0]
+            .regs()
+            .ch_ctrl_trig
+            .write(|w| unsafe { w.bits(line_ctrl(ids[1], dreq)) });
+
+        // Each waits on the one after it
+        let sm2 = sm2.start();
+        let sm1 = sm1.start();
+        let sm0 = sm0.start();
+
+        Vga {
+            _sms: (sm0, sm1, sm2),
+            _tx: tx2,
+            channels,
+            lines,
+            screen,
+            font,
+            palette,
+            line: 0,
+        }
+    }
+
+    /// Set palette colour `index` to the nearest of `red`, `green` and
+    /// `blue` in RGB332.
+    pub fn set_palette(&mut self, index: u8, red: u8, green: u8, blue: u8) {
+        self.palette[usize::from(index & 0xF)] = u32::from(rgb332(red, green, blue)) * 0x0101_0101;
+    }
+
+    /// The screen being shown.
+    pub fn screen(&self) -> &'static Screen {
+        self.screen
+    }
+
+    /// Keep the output going. Call this from the DMA interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        let dma = unsafe { &*pac::DMA::ptr() };
+        let mask = self
+            .channels
+            .iter()
+            .fold(0u32, |mask, channel| mask | 1 << channel.id());
+        let mut pending = (dma.ints0.read().bits() | dma.ints1.read().bits()) & mask;
+        dma.ints0.write(|w| unsafe { w.bits(pending) });
+        dma.ints1.write(|w| unsafe { w.bits(pending) });
+
+        // Channels finish in turn; the other one is already sending the
+        // next line, so this one's buffer is free for the line after
+        while pending != 0 {
+            let buffer = self.line % 2;
+            let channel = &self.channels[buffer];
+            let bit = 1 << channel.id();
+            if pending & bit == 0 {
+                break;
+            }
+            pending &= !bit;
+
+            let words = &mut self.lines.words[buffer];
+            channel
+                .regs()
+                .ch_read_addr
+                .write(|w| unsafe { w.bits(words.as_ptr() as u32) });
+            let y = (self.line + 2) % HEIGHT;
+            render_line(self.screen, self.font, &self.palette, words, y);
+            self.line = (self.line + 1) % HEIGHT;
+        }
+    }
+}
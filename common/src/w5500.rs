@@ -0,0 +1,657 @@
+//! Driver for the WIZnet W5500 wired Ethernet controller, over SPI.
+//!
+//! The W5500 runs TCP, UDP, IPv4 and ARP itself, on up to eight sockets at
+//! once, so all the RP2040 does is move payloads in and out of the chip's
+//! buffers. [`W5500`] implements the `embedded-nal` stack traits, TCP client
+//! and server and UDP, so that network code written against them runs on it:
+//!
+//! ```ignore
+//! let mut w5500 = W5500::new(spi, cs)?;
+//! w5500.set_network(&NetworkConfig {
+//!     mac: [0x02, 0, 0, 0, 0, 1],
+//!     ip: Ipv4Addr::new(192, 168, 1, 100),
+//!     subnet: Ipv4Addr::new(255, 255, 255, 0),
+//!     gateway: Ipv4Addr::new(192, 168, 1, 1),
+//! })?;
+//! let mut server = TcpClientStack::socket(&mut w5500)?;
+//! w5500.bind(&mut server, 7)?;
+//! w5500.listen(&mut server)?;
+//! let (mut client, remote) = nb::block!(w5500.accept(&mut server))?;
+//! ```
+//!
+//! A listening socket takes a connection by becoming it, so
+//! [`TcpFullStack::accept`] moves the listener onto another free socket; with
+//! all eight in use it waits for one to close. Every socket has the 2 KiB of
+//! send and receive buffer the chip starts with.
+//!
+//! The bus must be SPI mode 0. The chip manages 33 MHz, but most modules and
+//! their wiring want 20 MHz or less.
+
+use core::convert::Infallible;
+use core::fmt::Debug;
+
+use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::digital::v2::OutputPin;
+use embedded_nal::{
+    IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, TcpClientStack, TcpFullStack, UdpClientStack,
+    UdpFullStack,
+};
+
+// Common registers
+const MR: u16 = 0x0000;
+const GAR: u16 = 0x0001;
+const SUBR: u16 = 0x0005;
+const SHAR: u16 = 0x0009;
+const SIPR: u16 = 0x000F;
+const PHYCFGR: u16 = 0x002E;
+const VERSIONR: u16 = 0x0039;
+
+// Socket registers
+const SN_MR: u16 = 0x0000;
+const SN_CR: u16 = 0x0001;
+const SN_IR: u16 = 0x0002;
+const SN_SR: u16 = 0x0003;
+const SN_PORT: u16 = 0x0004;
+const SN_DIPR: u16 = 0x000C;
+const SN_DPORT: u16 = 0x0010;
+const SN_TX_FSR: u16 = 0x0020;
+const SN_TX_WR: u16 = 0x0024;
+const SN_RX_RSR: u16 = 0x0026;
+const SN_RX_RD: u16 = 0x0028;
+
+// Socket modes
+const MODE_TCP: u8 = 0x01;
+const MODE_UDP: u8 = 0x02;
+
+// Socket commands
+const OPEN: u8 = 0x01;
+const LISTEN: u8 = 0x02;
+const CONNECT: u8 = 0x04;
+const DISCON: u8 = 0x08;
+const CLOSE: u8 = 0x10;
+const SEND: u8 = 0x20;
+const RECV: u8 = 0x40;
+
+// Socket states
+const SOCK_CLOSED: u8 = 0x00;
+const SOCK_INIT: u8 = 0x13;
+const SOCK_LISTEN: u8 = 0x14;
+const SOCK_ESTABLISHED: u8 = 0x17;
+const SOCK_CLOSE_WAIT: u8 = 0x1C;
+const SOCK_UDP: u8 = 0x22;
+
+// Socket interrupts
+const IR_SEND_OK: u8 = 0x10;
+const IR_TIMEOUT: u8 = 0x08;
+
+/// What `VERSIONR` always reads on a W5500.
+const VERSION: u8 = 0x04;
+
+/// Sockets on the chip.
+pub const SOCKETS: u8 = 8;
+
+/// Each socket's send buffer, in bytes.
+const TX_BUFFER_SIZE: usize = 2048;
+
+/// The length of the header the chip puts before each UDP datagram it
+/// receives: the sender's address, port, and the datagram's length.
+const UDP_HEADER_LEN: u16 = 8;
+
+/// The first of the ports given to sockets that don't bind their own.
+const FIRST_EPHEMERAL_PORT: u16 = 49152;
+
+/// Register polls before giving up on the chip. The chip's own retransmission
+/// timeout ends every wait well before this runs out at 10 MHz.
+const POLLS: u32 = 100_000;
+
+/// Errors from the chip and the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The SPI bus failed.
+    Spi(E),
+    /// No W5500 answered, or it didn't come out of reset.
+    NotFound,
+    /// All eight sockets are in use.
+    NoFreeSocket,
+    /// The socket isn't connected, or the other end has closed it.
+    NotConnected,
+    /// The other end, or the next hop to it, didn't answer in time.
+    Timeout,
+    /// A datagram bigger than the socket's send buffer.
+    TooLong,
+    /// An IPv6 address, which the W5500 doesn't do.
+    Unsupported,
+}
+
+/// The chip's Ethernet address and IPv4 set-up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetworkConfig {
+    /// A locally administered address, with bit 1 of the first byte set,
+    /// is safe for boards without one of their own.
+    pub mac: [u8; 6],
+    pub ip: Ipv4Addr,
+    pub subnet: Ipv4Addr,
+    pub gateway: Ipv4Addr,
+}
+
+/// A TCP socket, connected, listening or neither.
+#[derive(Debug)]
+pub struct TcpSocket {
+    index: u8,
+    port: u16,
+    /// Whether a `connect` has been started and not yet finished.
+    connecting: bool,
+}
+
+/// A UDP socket.
+#[derive(Debug)]
+pub struct UdpSocket {
+    index: u8,
+    /// Where `send` sends to.
+    remote: Option<SocketAddrV4>,
+    /// Whether the chip's socket is open.
+    open: bool,
+}
+
+/// A W5500 on an SPI bus, with its own chip select.
+pub struct W5500<SPI, CS> {
+    spi: SPI,
+    cs: CS,
+    /// Sockets handed out, one bit each.
+    used: u8,
+    next_port: u16,
+}
+
+impl<SPI, CS, E> W5500<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin<Error = Infallible>,
+{
+    /// Reset the chip and check that it is a W5500.
+    ///
+    /// Boards that wire up its reset pin should pulse that low first; the
+    /// chip needs 500 µs, and the PHY up to 2 s more to find a link.
+    pub fn new(spi: SPI, mut cs: CS) -> Result<Self, Error<E>> {
+        let _ = cs.set_high();
+        let mut chip = W5500 {
+            spi,
+            cs,
+            used: 0,
+            next_port: FIRST_EPHEMERAL_PORT,
+        };
+        chip.write(0, MR, &[0x80])?;
+        let mut polls = 0;
+        while chip.read_u8(0, MR)? & 0x80 != 0 {
+            polls += 1;
+            if polls == POLLS {
+                return Err(Error::NotFound);
+            }
+        }
+        if chip.read_u8(0, VERSIONR)? != VERSION {
+            return Err(Error::NotFound);
+        }
+        Ok(chip)
+    }
+
+    /// Set the Ethernet address and the IPv4 address, subnet mask and
+    /// gateway.
+    pub fn set_network(&mut self, config: &NetworkConfig) -> Result<(), Error<E>> {
+        self.write(0, SHAR, &config.mac)?;
+        self.write(0, GAR, &config.gateway.octets())?;
+        self.write(0, SUBR, &config.subnet.octets())?;
+        self.write(0, SIPR, &config.ip.octets())
+    }
+
+    /// Whether the PHY has a link to a switch or another computer.
+    pub fn link_up(&mut self) -> Result<bool, Error<E>> {
+        Ok(self.read_u8(0, PHYCFGR)? & 0x01 != 0)
+    }
+
+    /// Give back the bus and the chip select.
+    pub fn free(self) -> (SPI, CS) {
+        (self.spi, self.cs)
+    }
+
+    /// Claim a free socket.
+    fn allocate(&mut self) -> Result<u8, Error<E>> {
+        let index = (0..SOCKETS)
+            .find(|index| self.used & (1 << index) == 0)
+            .ok_or(Error::NoFreeSocket)?;
+        self.used |= 1 << index;
+        Ok(index)
+    }
+
+    /// Close a socket and give it back.
+    fn release(&mut self, index: u8) -> Result<(), Error<E>> {
+        self.used &= !(1 << index);
+        self.command(index, CLOSE)
+    }
+
+    /// A port for a socket that doesn't bind its own.
+    fn ephemeral_port(&mut self) -> u16 {
+        let port = self.next_port;
+        self.next_port = self
+            .next_port
+            .checked_add(1)
+            .unwrap_or(FIRST_EPHEMERAL_PORT);
+        port
+    }
+
+    /// Open socket `index` with `mode` on `port`, checking that it reaches
+    /// `state`.
+    fn open(&mut self, index: u8, mode: u8, port: u16, state: u8) -> Result<(), Error<E>> {
+        self.command(index, CLOSE)?;
+        self.write(socket_block(index), SN_MR, &[mode])?;
+        self.write(socket_block(index), SN_PORT, &port.to_be_bytes())?;
+        self.command(index, OPEN)?;
+        if self.status(index)? == state {
+            Ok(())
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    fn status(&mut self, index: u8) -> Result<u8, Error<E>> {
+        self.read_u8(socket_block(index), SN_SR)
+    }
+
+    /// Give socket `index` a command and wait for the chip to take it.
+    fn command(&mut self, index: u8, command: u8) -> Result<(), Error<E>> {
+        self.write(socket_block(index), SN_CR, &[command])?;
+        let mut polls = 0;
+        while self.read_u8(socket_block(index), SN_CR)? != 0 {
+            polls += 1;
+            if polls == POLLS {
+                return Err(Error::NotFound);
+            }
+        }
+        Ok(())
+    }
+
+    /// Set where socket `index` sends to.
+    fn set_remote(&mut self, index: u8, remote: SocketAddrV4) -> Result<(), Error<E>> {
+        self.write(socket_block(index), SN_DIPR, &remote.ip().octets())?;
+        self.write(socket_block(index), SN_DPORT, &remote.port().to_be_bytes())
+    }
+
+    /// Where socket `index` is connected to.
+    fn remote(&mut self, index: u8) -> Result<SocketAddr, Error<E>> {
+        let mut ip = [0; 4];
+        let mut port = [0; 2];
+        self.read(socket_block(index), SN_DIPR, &mut ip)?;
+        self.read(socket_block(index), SN_DPORT, &mut port)?;
+        Ok(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::from(ip)),
+            u16::from_be_bytes(port),
+        ))
+    }
+
+    /// Put `data` in socket `index`'s send buffer and send it, waiting until
+    /// it has gone. The caller checks that it fits.
+    fn transmit(&mut self, index: u8, data: &[u8]) -> Result<(), Error<E>> {
+        let pointer = self.read_u16(socket_block(index), SN_TX_WR)?;
+        // The chip wraps the pointer round the buffer itself
+        self.write(tx_block(index), pointer, data)?;
+        let pointer = pointer.wrapping_add(data.len() as u16);
+        self.write(socket_block(index), SN_TX_WR, &pointer.to_be_bytes())?;
+        self.command(index, SEND)?;
+        let mut polls = 0;
+        loop {
+            let interrupts = self.read_u8(socket_block(index), SN_IR)?;
+            if interrupts & IR_SEND_OK != 0 {
+                self.write(socket_block(index), SN_IR, &[IR_SEND_OK])?;
+                return Ok(());
+            }
+            if interrupts & IR_TIMEOUT != 0 {
+                self.write(socket_block(index), SN_IR, &[IR_TIMEOUT])?;
+                return Err(Error::Timeout);
+            }
+            polls += 1;
+            if polls == POLLS {
+                return Err(Error::Timeout);
+            }
+        }
+    }
+
+    /// Room in socket `index`'s send buffer.
+    fn tx_free(&mut self, index: u8) -> Result<u16, Error<E>> {
+        self.read_u16_settled(socket_block(index), SN_TX_FSR)
+    }
+
+    /// Bytes waiting in socket `index`'s receive buffer.
+    fn rx_waiting(&mut self, index: u8) -> Result<u16, Error<E>> {
+        self.read_u16_settled(socket_block(index), SN_RX_RSR)
+    }
+
+    /// Copy out `buf.len()` bytes from `offset` into socket `index`'s
+    /// receive buffer, without taking them.
+    fn peek(&mut self, index: u8, offset: u16, buf: &mut [u8]) -> Result<(), Error<E>> {
+        let pointer = self.read_u16(socket_block(index), SN_RX_RD)?;
+        self.read(rx_block(index), pointer.wrapping_add(offset), buf)
+    }
+
+    /// Take `len` bytes from socket `index`'s receive buffer.
+    fn consume(&mut self, index: u8, len: u16) -> Result<(), Error<E>> {
+        let pointer = self.read_u16(socket_block(index), SN_RX_RD)?;
+        let pointer = pointer.wrapping_add(len);
+        self.write(socket_block(index), SN_RX_RD, &pointer.to_be_bytes())?;
+        self.command(index, RECV)
+    }
+
+    fn read_u8(&mut self, block: u8, address: u16) -> Result<u8, Error<E>> {
+        let mut value = [0];
+        self.read(block, address, &mut value)?;
+        Ok(value[0])
+    }
+
+    fn read_u16(&mut self, block: u8, address: u16) -> Result<u16, Error<E>> {
+        let mut value = [0; 2];
+        self.read(block, address, &mut value)?;
+        Ok(u16::from_be_bytes(value))
+    }
+
+    /// Read a 16-bit register the chip may be updating, until two reads
+    /// agree, as the datasheet asks for the free and received sizes.
+    fn read_u16_settled(&mut self, block: u8, address: u16) -> Result<u16, Error<E>> {
+        let mut value = self.read_u16(block, address)?;
+        loop {
+            let again = self.read_u16(block, address)?;
+            if again == value {
+                return Ok(value);
+            }
+            value = again;
+        }
+    }
+
+    fn read(&mut self, block: u8, address: u16, buf: &mut [u8]) -> Result<(), Error<E>> {
+        let [high, low] = address.to_be_bytes();
+        buf.iter_mut().for_each(|byte| *byte = 0);
+        let spi = &mut self.spi;
+        let _ = self.cs.set_low();
+        let result = spi
+            .write(&[high, low, block << 3])
+            .and_then(|_| spi.transfer(buf).map(|_| ()))
+            .map_err(Error::Spi);
+        let _ = self.cs.set_high();
+        result
+    }
+
+    fn write(&mut self, block: u8, address: u16, data: &[u8]) -> Result<(), Error<E>> {
+        let [high, low] = address.to_be_bytes();
+        let spi = &mut self.spi;
+        let _ = self.cs.set_low();
+        let result = spi
+            .write(&[high, low, block << 3 | 0b100])
+            .and_then(|_| spi.write(data))
+            .map_err(Error::Spi);
+        let _ = self.cs.set_high();
+        result
+    }
+}
+
+/// The block select for socket `index`'s registers.
+fn socket_block(index: u8) -> u8 {
+    index << 2 | 1
+}
+
+/// The block select for socket `index`'s send buffer.
+fn tx_block(index: u8) -> u8 {
+    index << 2 | 2
+}
+
+/// The block select for socket `index`'s receive buffer.
+fn rx_block(index: u8) -> u8 {
+    index << 2 | 3
+}
+
+/// The IPv4 address in `address`, which is all the W5500 does.
+fn ipv4<E>(address: SocketAddr) -> Result<SocketAddrV4, Error<E>> {
+    match address {
+        SocketAddr::V4(address) => Ok(address),
+        SocketAddr::V6(_) => Err(Error::Unsupported),
+    }
+}
+
+impl<SPI, CS, E> TcpClientStack for W5500<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin<Error = Infallible>,
+    E: Debug,
+{
+    type TcpSocket = TcpSocket;
+    type Error = Error<E>;
+
+    fn socket(&mut self) -> Result<TcpSocket, Error<E>> {
+        let index = self.allocate()?;
+        Ok(TcpSocket {
+            index,
+            port: 0,
+            connecting: false,
+        })
+    }
+
+    fn connect(&mut self, socket: &mut TcpSocket, remote: SocketAddr) -> nb::Result<(), Error<E>> {
+        match self.status(socket.index)? {
+            SOCK_ESTABLISHED => {
+                socket.connecting = false;
+                Ok(())
+            }
+            SOCK_CLOSED if socket.connecting => {
+                socket.connecting = false;
+                Err(nb::Error::Other(Error::Timeout))
+            }
+            SOCK_CLOSED | SOCK_INIT => {
+                let remote = ipv4(remote)?;
+                if socket.port == 0 {
+                    socket.port = self.ephemeral_port();
+                }
+                self.open(socket.index, MODE_TCP, socket.port, SOCK_INIT)?;
+                self.set_remote(socket.index, remote)?;
+                self.command(socket.index, CONNECT)?;
+                socket.connecting = true;
+                Err(nb::Error::WouldBlock)
+            }
+            _ => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    fn is_connected(&mut self, socket: &TcpSocket) -> Result<bool, Error<E>> {
+        Ok(self.status(socket.index)? == SOCK_ESTABLISHED)
+    }
+
+    fn send(&mut self, socket: &mut TcpSocket, buffer: &[u8]) -> nb::Result<usize, Error<E>> {
+        if !matches!(
+            self.status(socket.index)?,
+            SOCK_ESTABLISHED | SOCK_CLOSE_WAIT
+        ) {
+            return Err(nb::Error::Other(Error::NotConnected));
+        }
+        let len = buffer.len().min(usize::from(self.tx_free(socket.index)?));
+        if len == 0 && !buffer.is_empty() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.transmit(socket.index, &buffer[..len])?;
+        Ok(len)
+    }
+
+    fn receive(
+        &mut self,
+        socket: &mut TcpSocket,
+        buffer: &mut [u8],
+    ) -> nb::Result<usize, Error<E>> {
+        let waiting = self.rx_waiting(socket.index)?;
+        if waiting == 0 {
+            return match self.status(socket.index)? {
+                SOCK_ESTABLISHED => Err(nb::Error::WouldBlock),
+                _ => Err(nb::Error::Other(Error::NotConnected)),
+            };
+        }
+        let len = buffer.len().min(usize::from(waiting));
+        self.peek(socket.index, 0, &mut buffer[..len])?;
+        self.consume(socket.index, len as u16)?;
+        Ok(len)
+    }
+
+    fn close(&mut self, socket: TcpSocket) -> Result<(), Error<E>> {
+        if matches!(
+            self.status(socket.index)?,
+            SOCK_ESTABLISHED | SOCK_CLOSE_WAIT
+        ) {
+            // Say goodbye properly, giving the other end a moment to agree
+            self.command(socket.index, DISCON)?;
+            let mut polls = 0;
+            while self.status(socket.index)? != SOCK_CLOSED && polls < POLLS {
+                polls += 1;
+            }
+        }
+        self.release(socket.index)
+    }
+}
+
+impl<SPI, CS, E> TcpFullStack for W5500<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin<Error = Infallible>,
+    E: Debug,
+{
+    fn bind(&mut self, socket: &mut TcpSocket, local_port: u16) -> Result<(), Error<E>> {
+        socket.port = local_port;
+        Ok(())
+    }
+
+    fn listen(&mut self, socket: &mut TcpSocket) -> Result<(), Error<E>> {
+        if socket.port == 0 {
+            socket.port = self.ephemeral_port();
+        }
+        self.open(socket.index, MODE_TCP, socket.port, SOCK_INIT)?;
+        self.command(socket.index, LISTEN)?;
+        if self.status(socket.index)? == SOCK_LISTEN {
+            Ok(())
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    fn accept(&mut self, socket: &mut TcpSocket) -> nb::Result<(TcpSocket, SocketAddr), Error<E>> {
+        match self.status(socket.index)? {
+            SOCK_ESTABLISHED | SOCK_CLOSE_WAIT => {}
+            SOCK_CLOSED => {
+                // The connection came and went before it was taken
+                self.listen(socket)?;
+                return Err(nb::Error::WouldBlock);
+            }
+            _ => return Err(nb::Error::WouldBlock),
+        }
+        // The listener has become the connection; listen again on another
+        // socket, or leave the connection waiting until one is free
+        let index = match self.allocate() {
+            Ok(index) => index,
+            Err(Error::NoFreeSocket) => return Err(nb::Error::WouldBlock),
+            Err(error) => return Err(nb::Error::Other(error)),
+        };
+        let remote = self.remote(socket.index)?;
+        let connection = TcpSocket {
+            index: socket.index,
+            port: socket.port,
+            connecting: false,
+        };
+        socket.index = index;
+        self.listen(socket)?;
+        Ok((connection, remote))
+    }
+}
+
+impl<SPI, CS, E> UdpClientStack for W5500<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin<Error = Infallible>,
+    E: Debug,
+{
+    type UdpSocket = UdpSocket;
+    type Error = Error<E>;
+
+    fn socket(&mut self) -> Result<UdpSocket, Error<E>> {
+        let index = self.allocate()?;
+        Ok(UdpSocket {
+            index,
+            remote: None,
+            open: false,
+        })
+    }
+
+    fn connect(&mut self, socket: &mut UdpSocket, remote: SocketAddr) -> Result<(), Error<E>> {
+        socket.remote = Some(ipv4(remote)?);
+        if !socket.open {
+            let port = self.ephemeral_port();
+            self.open(socket.index, MODE_UDP, port, SOCK_UDP)?;
+            socket.open = true;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, socket: &mut UdpSocket, buffer: &[u8]) -> nb::Result<(), Error<E>> {
+        let remote = socket.remote.ok_or(Error::NotConnected)?;
+        if !socket.open {
+            return Err(nb::Error::Other(Error::NotConnected));
+        }
+        if buffer.len() > TX_BUFFER_SIZE {
+            return Err(nb::Error::Other(Error::TooLong));
+        }
+        if usize::from(self.tx_free(socket.index)?) < buffer.len() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.set_remote(socket.index, remote)?;
+        self.transmit(socket.index, buffer)?;
+        Ok(())
+    }
+
+    fn receive(
+        &mut self,
+        socket: &mut UdpSocket,
+        buffer: &mut [u8],
+    ) -> nb::Result<(usize, SocketAddr), Error<E>> {
+        if !socket.open {
+            return Err(nb::Error::Other(Error::NotConnected));
+        }
+        if self.rx_waiting(socket.index)? < UDP_HEADER_LEN {
+            return Err(nb::Error::WouldBlock);
+        }
+        let mut header = [0; UDP_HEADER_LEN as usize];
+        self.peek(socket.index, 0, &mut header)?;
+        let ip = Ipv4Addr::new(header[0], header[1], header[2], header[3]);
+        let port = u16::from_be_bytes([header[4], header[5]]);
+        let datagram_len = u16::from_be_bytes([header[6], header[7]]);
+        // Whatever doesn't fit in `buffer` is dropped with the rest
+        let len = buffer.len().min(usize::from(datagram_len));
+        self.peek(socket.index, UDP_HEADER_LEN, &mut buffer[..len])?;
+        self.consume(socket.index, UDP_HEADER_LEN + datagram_len)?;
+        Ok((len, SocketAddr::new(IpAddr::V4(ip), port)))
+    }
+
+    fn close(&mut self, socket: UdpSocket) -> Result<(), Error<E>> {
+        self.release(socket.index)
+    }
+}
+
+impl<SPI, CS, E> UdpFullStack for W5500<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin<Error = Infallible>,
+    E: Debug,
+{
+    fn bind(&mut self, socket: &mut UdpSocket, local_port: u16) -> Result<(), Error<E>> {
+        self.open(socket.index, MODE_UDP, local_port, SOCK_UDP)?;
+        socket.open = true;
+        Ok(())
+    }
+
+    fn send_to(
+        &mut self,
+        socket: &mut UdpSocket,
+        remote: SocketAddr,
+        buffer: &[u8],
+    ) -> nb::Result<(), Error<E>> {
+        socket.remote = Some(ipv4(remote)?);
+        UdpClientStack::send(self, socket, buffer)
+    }
+}
@@ -2,8 +2,14 @@ MEMORY {
     BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
     FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
     RAM   : ORIGIN = 0x20000000, LENGTH = 256K
+    /* Left out of RAM, for the OTA swap to run from */
+    SRAM4 : ORIGIN = 0x20040000, LENGTH = 4K
 }
 
+/* Each BSP's build script writes storage.x, which keeps the top of flash
+ * for data and defines __flash_storage_start and __flash_storage_end. */
+INCLUDE storage.x
+
 EXTERN(BOOT2_FIRMWARE)
 
 SECTIONS {
@@ -13,3 +19,27 @@ SECTIONS {
         KEEP(*(.boot2));
     } > BOOT2
 } INSERT BEFORE .text;
+
+/* The OTA slot swap (see rp_hal_boards_common::ota) resumes from
+ * __pre_init. That, the code it loads into SRAM4 and cortex-m-rt's reset
+ * handler, which calls it, have to be in the first sector of flash, which
+ * the swap rewrites last. */
+SECTIONS {
+    .ota_boot :
+    {
+        KEEP(*(.ota_boot));
+    } > FLASH
+
+    .ota_swap : ALIGN(4)
+    {
+        __ota_swap_start = .;
+        KEEP(*(.ota_swap .ota_swap.*));
+        . = ALIGN(4);
+        __ota_swap_end = .;
+    } > SRAM4 AT > FLASH
+    __ota_swap_load = LOADADDR(.ota_swap);
+} INSERT AFTER .vector_table;
+
+_stext = ALIGN(__ota_swap_load + SIZEOF(.ota_swap), 4);
+ASSERT(_stext + 0x100 <= ORIGIN(BOOT2) + 0x1000,
+    "The OTA swap code and the reset handler don't fit in the first sector");
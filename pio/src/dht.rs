@@ -0,0 +1,239 @@
+//! DHT11 and DHT22 temperature and humidity sensors.
+//!
+//! The state machine runs at 1 MHz, so a cycle is a microsecond. It holds
+//! the data line low for the start pulse, releases it, waits out the
+//! sensor's answer, then times the high part of each of the 40 bits: 26 to
+//! 28 µs for a 0, 70 µs for a 1, sampled 42 µs in.
+//!
+//! With no sensor, or a sensor that stops half way, the state machine waits
+//! for ever; [`Dht::start`] abandons any read still going, so time reads out
+//! and start again. The line needs a pull-up, which the sensor boards have.
+//!
+//! [`Dht::read`] does all of that, waiting for the result:
+//!
+//! ```ignore
+//! let mut dht = Dht::new(&mut pio, sm0, 2, sys_hz);
+//! let reading = dht.read(Model::Dht22, &mut delay)?;
+//! // reading.temperature and reading.humidity are in tenths
+//! ```
+
+use embedded_hal::blocking::delay::DelayUs;
+use hal::pio::{
+    PIOBuilder, PIOExt, PinDir, PinState, Running, Rx, ShiftDirection, StateMachine,
+    StateMachineIndex, Tx, UninitStateMachine, PIO,
+};
+
+use crate::{divisor, hal, registers, Program, MOV_ISR_NULL};
+
+/// State machine clock, one cycle per microsecond.
+const CLOCK_HZ: u32 = 1_000_000;
+
+/// Bits sent by the sensor.
+const BITS: u32 = 40;
+
+/// `set pindirs, 0`: releases the line.
+const RELEASE: u32 = 0xE080;
+
+/// Start pulse for a DHT22, in microseconds.
+pub const DHT22_START_US: u32 = 1_100;
+
+/// Start pulse for a DHT11, in microseconds.
+pub const DHT11_START_US: u32 = 18_000;
+
+/// How long a read may take, in microseconds: the DHT11's start pulse and
+/// 40 bits of up to 120 µs, with room to spare.
+const READ_TIMEOUT_US: u32 = 30_000;
+
+/// How often [`Dht::read`] checks for the result, in microseconds.
+const POLL_US: u32 = 500;
+
+/// The two kinds of sensor, which differ in start pulse and data format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    /// DHT11: whole degrees and percent, 0 to 50 °C.
+    Dht11,
+    /// DHT22 or AM2302: tenths, -40 to 80 °C.
+    Dht22,
+}
+
+impl Model {
+    /// The start pulse this model needs, in microseconds.
+    pub fn start_us(self) -> u32 {
+        match self {
+            Model::Dht11 => DHT11_START_US,
+            Model::Dht22 => DHT22_START_US,
+        }
+    }
+}
+
+/// Errors from [`Dht::read`] and [`decode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The sensor didn't answer, or stopped half way.
+    Timeout,
+    /// The bytes read don't match their checksum.
+    Checksum,
+}
+
+/// One reading from the sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reading {
+    /// Temperature in tenths of a degree Celsius.
+    pub temperature: i16,
+    /// Relative humidity in tenths of a percent.
+    pub humidity: u16,
+}
+
+/// Check and convert the five bytes from [`Dht::read_raw`].
+pub fn decode(model: Model, raw: [u8; 5]) -> Result<Reading, Error> {
+    let sum = raw[..4]
+        .iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    if sum != raw[4] {
+        return Err(Error::Checksum);
+    }
+    // Both put the sign in the top bit of the temperature
+    let negative = match model {
+        Model::Dht11 => raw[3] & 0x80 != 0,
+        Model::Dht22 => raw[2] & 0x80 != 0,
+    };
+    let (humidity, temperature) = match model {
+        // Integer and decimal parts
+        Model::Dht11 => (
+            u16::from(raw[0]) * 10 + u16::from(raw[1]),
+            i16::from(raw[2]) * 10 + i16::from(raw[3] & 0x7F),
+        ),
+        Model::Dht22 => (
+            u16::from_be_bytes([raw[0], raw[1]]),
+            i16::from_be_bytes([raw[2] & 0x7F, raw[3]]),
+        ),
+    };
+    Ok(Reading {
+        temperature: if negative { -temperature } else { temperature },
+        humidity,
+    })
+}
+
+/// The program. Takes the start pulse length in µs, then the number of bits
+/// less one, and pushes the bits MSB first, 32 to a word, the last few in
+/// the low bits of a final word.
+pub fn program() -> Program {
+    pio_proc::pio_asm!(
+        ".wrap_target",
+        "    pull block",
+        "    mov x, osr",
+        "    pull block",
+        "    set pindirs, 1             ; drive the line low",
+        "hold:",
+        "    jmp x-- hold",
+        "    set pindirs, 0             ; and release it",
+        "    mov x, osr",
+        "    wait 1 pin 0",
+        "    wait 0 pin 0               ; the sensor answers low...",
+        "    wait 1 pin 0               ; ...then high",
+        "bit:",
+        "    wait 0 pin 0               ; every bit starts low",
+        "    wait 1 pin 0",
+        "    nop               [31]",
+        "    nop               [8]",
+        "    in pins, 1",
+        "    jmp x-- bit",
+        "    push",
+        ".wrap",
+    )
+    .program
+}
+
+/// A DHT11 or DHT22 on a state machine.
+pub struct Dht<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    tx: Tx<(P, SM)>,
+    /// The first word of the read in progress.
+    first: Option<u32>,
+}
+
+impl<P, SM> Dht<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Talk to the sensor on `pin`.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        pin: u8,
+        system_clock_hz: u32,
+    ) -> Self {
+        let (int, frac) = divisor(system_clock_hz, CLOCK_HZ);
+        let installed = pio.install(&program()).unwrap();
+        let (mut state, rx, tx) = PIOBuilder::from_program(installed)
+            .set_pins(pin, 1)
+            .in_pin_base(pin)
+            .in_shift_direction(ShiftDirection::Left)
+            .autopush(true)
+            .push_threshold(32)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        // Released, and low whenever it's driven
+        state.set_pins([(pin, PinState::Low)]);
+        state.set_pindirs([(pin, PinDir::Input)]);
+
+        Dht {
+            _sm: state.start(),
+            rx,
+            tx,
+            first: None,
+        }
+    }
+
+    /// Start a read with a start pulse of `start_us`, [`DHT22_START_US`] or
+    /// [`DHT11_START_US`], abandoning any read still going.
+    ///
+    /// The sensors need a second or two between reads.
+    pub fn start(&mut self, start_us: u32) {
+        let regs = registers::<P>();
+        let sm = &regs.sm[SM::id()];
+        // Back to the `pull`, with the line released and nothing half read
+        sm.sm_instr.write(|w| unsafe { w.bits(RELEASE) });
+        sm.sm_instr.write(|w| unsafe { w.bits(MOV_ISR_NULL) });
+        let wrap_bottom = sm.sm_execctrl.read().wrap_bottom().bits();
+        sm.sm_instr
+            .write(|w| unsafe { w.bits(u32::from(wrap_bottom)) });
+        while self.rx.read().is_some() {}
+        self.first = None;
+
+        self.tx.write(start_us);
+        self.tx.write(BITS - 1);
+    }
+
+    /// The five bytes read, once they have all arrived.
+    pub fn read_raw(&mut self) -> Option<[u8; 5]> {
+        while let Some(word) = self.rx.read() {
+            match self.first.take() {
+                None => self.first = Some(word),
+                Some(first) => {
+                    let [a, b, c, d] = first.to_be_bytes();
+                    return Some([a, b, c, d, word as u8]);
+                }
+            }
+        }
+        None
+    }
+
+    /// Read a `model` sensor, waiting up to 30 ms for it with `delay`.
+    ///
+    /// The sensors need a second or two between reads, and a DHT22 returns
+    /// the reading it took at the previous read, so throw the first one away
+    /// after a long pause.
+    pub fn read<D: DelayUs<u32>>(&mut self, model: Model, delay: &mut D) -> Result<Reading, Error> {
+        self.start(model.start_us());
+        for _ in 0..READ_TIMEOUT_US / POLL_US {
+            delay.delay_us(POLL_US);
+            if let Some(raw) = self.read_raw() {
+                return decode(model, raw);
+            }
+        }
+        Err(Error::Timeout)
+    }
+}
@@ -0,0 +1,44 @@
+//! Clocking data and rows into HUB75 RGB LED matrix panels.
+//!
+//! A panel takes two state machines. The data program shifts one byte per
+//! pixel pair out on `R0 G0 B0 R1 G1 B1`, toggling `CLK` by side-set for
+//! each. The row program takes one word per row and bit plane: it sets the
+//! row address on `A` to `E`, pulses `LAT` and holds `OE` low for the plane's
+//! display time.
+//!
+//! Sequencing rows and planes is up to the panel driver, such as the
+//! Interstate 75 BSP's `hub75` module.
+
+use crate::Program;
+
+/// The data program: the low six bits of each word on six consecutive pins,
+/// shifted right, with `CLK` side-set.
+pub fn data_program() -> Program {
+    pio_proc::pio_asm!(
+        ".side_set 1",
+        ".wrap_target",
+        "    pull          side 0",
+        "    out pins, 6   side 0",
+        "    nop           side 1",
+        ".wrap",
+    )
+    .program
+}
+
+/// The row program: the row address in bits 0-4 of each word and the
+/// display time, in cycles, in the rest, shifted right with autopull. Side-set
+/// bit 0 is `LAT`, bit 1 is `OE`, active low.
+pub fn row_program() -> Program {
+    pio_proc::pio_asm!(
+        ".side_set 2",
+        ".wrap_target",
+        "    out pins, 5   side 0b10",
+        "    out x, 27     side 0b11",
+        "    nop           side 0b10",
+        "on:",
+        "    jmp x-- on    side 0b00",
+        "    nop           side 0b10",
+        ".wrap",
+    )
+    .program
+}
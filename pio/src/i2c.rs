@@ -0,0 +1,316 @@
+//! I2C controller, with clock stretching.
+//!
+//! This is the Raspberry Pi Pico SDK's `pio_i2c` design. Each FIFO word is
+//! either a byte to shift, or a count of instructions that follow for the
+//! state machine to execute, which is how the start, repeated start and stop
+//! conditions are made. A byte takes 32 cycles per bit.
+//!
+//! SCL has to be the GPIO after SDA. The pins' output enables are inverted,
+//! so the state machine only ever pulls the lines low and the pull-ups, which
+//! this turns on, bring them high. The internal ones only suit short buses at
+//! 100 kHz; use external 4.7 kΩ resistors otherwise.
+//!
+//! An unexpected NAK stops the state machine on an IRQ flag, which the
+//! driver notices, clears and reports as [`Error::Nack`] after sending a
+//! stop.
+
+use hal::pio::{
+    PIOBuilder, PIOExt, PinDir, PinState, Running, Rx, ShiftDirection, StateMachine,
+    StateMachineIndex, Tx, UninitStateMachine, PIO,
+};
+
+use crate::{divisor, hal, registers, Program, MOV_ISR_NULL};
+
+/// State machine cycles per SCL period.
+const CYCLES_PER_BIT: u32 = 32;
+
+/// Fields of a FIFO word, in its top 16 bits.
+const ICOUNT_LSB: u16 = 10;
+const FINAL_LSB: u16 = 9;
+const DATA_LSB: u16 = 1;
+const NAK_LSB: u16 = 0;
+
+/// `set pindirs, <sda> side <scl> [7]`, for the start and stop conditions.
+/// With the output enables inverted, a pindir of 1 releases the line.
+const SC0_SD0: u16 = 0xF780;
+const SC0_SD1: u16 = 0xF781;
+const SC1_SD0: u16 = 0xFF80;
+const SC1_SD1: u16 = 0xFF81;
+
+/// Both starts also empty the ISR, so the bytes read after a write line up.
+const START: [u16; 3] = [MOV_ISR_NULL as u16, SC1_SD0, SC0_SD0];
+const REPEATED_START: [u16; 5] = [MOV_ISR_NULL as u16, SC0_SD1, SC1_SD1, SC1_SD0, SC0_SD0];
+const STOP: [u16; 3] = [SC0_SD0, SC1_SD0, SC1_SD1];
+
+/// `out null, 32`, to drop a word from the TX FIFO.
+const OUT_NULL_32: u32 = 0x6060;
+
+/// The program. Starts at `entry_point`, the wrap target.
+pub fn program() -> Program {
+    pio_proc::pio_asm!(
+        ".side_set 1 opt pindirs",
+        ".wrap_target",
+        "entry_point:",
+        "    out x, 6                   ; instruction count",
+        "    out y, 1                   ; a NAK is expected",
+        "    jmp !x do_byte",
+        "    out null, 32               ; the rest of the word is unused",
+        "do_exec:",
+        "    out exec, 16               ; one instruction per word",
+        "    jmp x-- do_exec",
+        ".wrap",
+        "do_byte:",
+        "    set x, 7",
+        "bitloop:",
+        "    out pindirs, 1         [7] ; all ones when reading",
+        "    nop             side 1 [2] ; SCL rising edge",
+        "    wait 1 pin 1           [4] ; the target may stretch the clock",
+        "    in pins, 1             [7] ; sample in the middle of SCL high",
+        "    jmp x-- bitloop side 0 [7] ; SCL falling edge",
+        "    out pindirs, 1         [7] ; our ACK, when reading",
+        "    nop             side 1 [7]",
+        "    wait 1 pin 1           [7]",
+        "    jmp pin do_nack side 0 [2]",
+        "    jmp entry_point",
+        "do_nack:",
+        "    jmp y-- entry_point",
+        "    irq wait 0 rel",
+        "    jmp entry_point",
+    )
+    .program
+}
+
+/// Errors from an [`I2c`] transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The target didn't acknowledge its address or a byte written to it.
+    Nack,
+}
+
+/// An I2C controller on a state machine.
+pub struct I2c<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    tx: Tx<(P, SM)>,
+}
+
+impl<P, SM> I2c<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Run the bus on `sda` and `sda + 1` at `frequency` Hz.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        sda: u8,
+        frequency: u32,
+        system_clock_hz: u32,
+    ) -> Self {
+        let scl = sda + 1;
+        let (int, frac) = divisor(system_clock_hz, frequency * CYCLES_PER_BIT);
+        let installed = pio.install(&program()).unwrap();
+        let (mut state, rx, tx) = PIOBuilder::from_program(installed)
+            .out_pins(sda, 1)
+            .set_pins(sda, 1)
+            .in_pin_base(sda)
+            .side_set_pin_base(scl)
+            .jmp_pin(sda)
+            .out_shift_direction(ShiftDirection::Left)
+            .autopull(true)
+            .pull_threshold(16)
+            .in_shift_direction(ShiftDirection::Left)
+            .push_threshold(8)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+
+        // Drive both lines high before inverting the output enables, which
+        // releases them, so the bus doesn't see a glitch
+        let pads = unsafe { &*hal::pac::PADS_BANK0::ptr() };
+        let io = unsafe { &*hal::pac::IO_BANK0::ptr() };
+        state.set_pins([(sda, PinState::High), (scl, PinState::High)]);
+        state.set_pindirs([(sda, PinDir::Output), (scl, PinDir::Output)]);
+        for pin in [sda, scl] {
+            pads.gpio[usize::from(pin)].modify(|_, w| w.pue().set_bit().pde().clear_bit());
+            io.gpio[usize::from(pin)]
+                .gpio_ctrl
+                .modify(|_, w| w.oeover().invert());
+        }
+        state.set_pins([(sda, PinState::Low), (scl, PinState::Low)]);
+
+        I2c {
+            _sm: state.start(),
+            rx,
+            tx,
+        }
+    }
+
+    /// Write `bytes` to the target at 7 bit `address`.
+    pub fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.put_instructions(&START);
+        self.enable_rx(false);
+        self.put_address(address, false);
+        self.put_bytes(bytes, true);
+        self.finish()
+    }
+
+    /// Read `buffer` full from the target at 7 bit `address`.
+    pub fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.put_instructions(&START);
+        self.get_bytes(address, buffer);
+        self.finish()
+    }
+
+    /// Write `bytes` to the target at 7 bit `address`, then read `buffer`
+    /// full after a repeated start.
+    pub fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        self.put_instructions(&START);
+        self.enable_rx(false);
+        self.put_address(address, false);
+        self.put_bytes(bytes, false);
+        self.put_instructions(&REPEATED_START);
+        self.get_bytes(address, buffer);
+        self.finish()
+    }
+
+    /// Send the stop condition and wait for the bus to go idle, recovering
+    /// from a NAK on the way.
+    fn finish(&mut self) -> Result<(), Error> {
+        self.put_instructions(&STOP);
+        self.wait_idle();
+        if !self.nacked() {
+            return Ok(());
+        }
+
+        // Throw away what's left of the transfer, and carry on from the
+        // wrap target, which is where the program starts
+        let regs = registers::<P>();
+        let sm = &regs.sm[SM::id()];
+        while !self.tx.is_empty() {
+            sm.sm_instr.write(|w| unsafe { w.bits(OUT_NULL_32) });
+        }
+        let wrap_bottom = sm.sm_execctrl.read().wrap_bottom().bits();
+        // An unconditional jump encodes as just its address
+        sm.sm_instr
+            .write(|w| unsafe { w.bits(u32::from(wrap_bottom)) });
+        regs.irq.write(|w| unsafe { w.bits(1 << SM::id()) });
+
+        self.put_instructions(&STOP);
+        self.wait_idle();
+        Err(Error::Nack)
+    }
+
+    /// Whether the state machine stopped on an unexpected NAK.
+    fn nacked(&self) -> bool {
+        registers::<P>().irq.read().bits() & (1 << SM::id()) != 0
+    }
+
+    /// Wait until the state machine has run out of words, or stopped on a
+    /// NAK.
+    fn wait_idle(&self) {
+        let regs = registers::<P>();
+        let stall = 1 << SM::id();
+        regs.fdebug.write(|w| unsafe { w.txstall().bits(stall) });
+        while regs.fdebug.read().txstall().bits() & stall == 0 && !self.nacked() {}
+    }
+
+    /// Push received bytes into the RX FIFO, or not.
+    fn enable_rx(&self, enable: bool) {
+        registers::<P>().sm[SM::id()]
+            .sm_shiftctrl
+            .modify(|_, w| w.autopush().bit(enable));
+    }
+
+    /// Queue one word, unless the transfer has already failed.
+    fn put(&mut self, word: u16) {
+        while !self.nacked() {
+            if self.tx.write(u32::from(word) << 16) {
+                return;
+            }
+        }
+    }
+
+    fn put_instructions(&mut self, instructions: &[u16]) {
+        self.put((instructions.len() as u16 - 1) << ICOUNT_LSB);
+        for &instruction in instructions {
+            self.put(instruction);
+        }
+    }
+
+    /// Queue the address byte. The target has to ACK it.
+    fn put_address(&mut self, address: u8, read: bool) {
+        let byte = u16::from(address) << 1 | u16::from(read);
+        self.put(byte << DATA_LSB | 1 << NAK_LSB);
+    }
+
+    /// Queue `bytes` to write. A NAK on the last one is fine if it ends
+    /// the transfer.
+    fn put_bytes(&mut self, bytes: &[u8], ends_transfer: bool) {
+        for (i, &byte) in bytes.iter().enumerate() {
+            let last = ends_transfer && i + 1 == bytes.len();
+            self.put(u16::from(byte) << DATA_LSB | u16::from(last) << FINAL_LSB | 1 << NAK_LSB);
+        }
+    }
+
+    /// Address the target for reading and read `buffer` full, ACKing every
+    /// byte but the last.
+    fn get_bytes(&mut self, address: u8, buffer: &mut [u8]) {
+        self.enable_rx(true);
+        while self.rx.read().is_some() {}
+        self.put_address(address, true);
+
+        // Shifting ones out releases SDA for the target to drive. The address
+        // byte comes back too, and is skipped.
+        let mut to_send = buffer.len();
+        let mut received = 0;
+        let mut address_skipped = false;
+        while (to_send > 0 || received < buffer.len()) && !self.nacked() {
+            if to_send > 0 && !self.tx.is_full() {
+                to_send -= 1;
+                let last = if to_send == 0 {
+                    1 << FINAL_LSB | 1 << NAK_LSB
+                } else {
+                    0
+                };
+                self.put(0xFF << DATA_LSB | last);
+            }
+            if let Some(word) = self.rx.read() {
+                if address_skipped {
+                    buffer[received] = word as u8;
+                    received += 1;
+                } else {
+                    address_skipped = true;
+                }
+            }
+        }
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_hal::blocking::i2c::Write for I2c<P, SM> {
+    type Error = Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        I2c::write(self, address, bytes)
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_hal::blocking::i2c::Read for I2c<P, SM> {
+    type Error = Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        I2c::read(self, address, buffer)
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_hal::blocking::i2c::WriteRead for I2c<P, SM> {
+    type Error = Error;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        I2c::write_read(self, address, bytes, buffer)
+    }
+}
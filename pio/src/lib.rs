@@ -0,0 +1,62 @@
+#![no_std]
+
+//! PIO programs shared by the rp-hal-boards Board Support Packages.
+//!
+//! Each module holds one program, or a few that work together, as a function
+//! returning the assembled [`Program`], and a thin wrapper that installs it
+//! on a state machine, configures the pins and clock, and talks to the FIFOs:
+//!
+//! ```ignore
+//! let (mut pio, sm0, _, _, _) = pac.PIO0.split(&mut pac.RESETS);
+//! let _led: Pin<_, FunctionPio0> = pins.gpio16.into_mode();
+//! let mut ws = Ws2812::new(&mut pio, sm0, 16, clocks.system_clock.freq().to_Hz());
+//! ws.write([(255, 0, 0), (0, 255, 0)]);
+//! ```
+//!
+//! Code that needs more than the wrappers offer can still use the programs with
+//! its own `PIOBuilder` set-up. As everywhere in the HAL, the pins must
+//! already be set to the PIO's function; the wrappers take GPIO numbers.
+
+pub extern crate rp2040_hal as hal;
+
+pub mod dht;
+pub mod hub75;
+pub mod i2c;
+pub mod ppm;
+pub mod quadrature;
+pub mod spi;
+pub mod uart;
+pub mod ultrasonic;
+pub mod ws2812;
+
+use hal::pac;
+use hal::pio::PIOExt;
+
+/// An assembled program, of at most the 32 instructions a PIO block holds.
+pub type Program = pio::Program<{ pio::RP2040_MAX_PROGRAM_SIZE }>;
+
+/// The clock divisor, as an integer and 256ths, running a state machine at
+/// `hz` from a system clock at `system_clock_hz`.
+///
+/// Clamped to what the divider can do, 1 to just under 65536.
+fn divisor(system_clock_hz: u32, hz: u32) -> (u16, u8) {
+    let divisor = (u64::from(system_clock_hz) << 8) / u64::from(hz.max(1));
+    let divisor = divisor.clamp(1 << 8, (1 << 24) - 1);
+    ((divisor >> 8) as u16, divisor as u8)
+}
+
+/// The registers of the PIO block `P`, for what the HAL doesn't offer on a
+/// running state machine.
+fn registers<P: PIOExt>() -> &'static pac::pio0::RegisterBlock {
+    // Both blocks share PIO0's register layout
+    let block = if P::id() == 0 {
+        pac::PIO0::ptr()
+    } else {
+        pac::PIO1::ptr()
+    };
+    unsafe { &*block }
+}
+
+/// `mov isr, null`, encoded with no side-set or delay: empties the ISR and
+/// resets its shift count.
+const MOV_ISR_NULL: u32 = 0xA0C3;
@@ -0,0 +1,102 @@
+//! RC PPM output, the single-wire stream of servo channels that radio
+//! transmitters take from a trainer port and many receivers send out.
+//!
+//! Every channel starts with a 300 µs pulse, and the time from one pulse to
+//! the next is the channel's value, 1000 to 2000 µs. A longer gap marks the
+//! end of the frame. The state machine runs at 1 MHz and times each gap from
+//! one FIFO word, so the FIFO holds a whole frame of up to seven channels.
+
+use hal::pio::{
+    Buffers, PIOBuilder, PIOExt, PinDir, PinState, Running, StateMachine, StateMachineIndex, Tx,
+    UninitStateMachine, PIO,
+};
+
+use crate::{divisor, hal, Program};
+
+/// State machine clock, one cycle per microsecond.
+const CLOCK_HZ: u32 = 1_000_000;
+
+/// Length of the pulse starting every channel, in microseconds.
+pub const PULSE_US: u32 = 300;
+
+/// The usual frame length, in microseconds.
+pub const FRAME_US: u32 = 22_500;
+
+/// Cycles of each gap not counted by the word written: the `pull`, the
+/// `out` and the last loop iteration.
+const OVERHEAD: u32 = 3;
+
+/// The program: for each word, a 300 µs pulse, then a gap of the word's
+/// value plus three microseconds. The line idles low.
+pub fn program() -> Program {
+    pio_proc::pio_asm!(
+        ".side_set 1",
+        ".wrap_target",
+        "    pull block         side 0",
+        "    set x, 28          side 1 [9]",
+        "pulse:",
+        "    jmp x-- pulse      side 1 [9]",
+        "    out x, 32          side 0",
+        "gap:",
+        "    jmp x-- gap        side 0",
+        ".wrap",
+    )
+    .program
+}
+
+/// A PPM output on a state machine.
+pub struct Ppm<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    tx: Tx<(P, SM)>,
+}
+
+impl<P, SM> Ppm<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Send the stream on `pin`, with pulses high.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        pin: u8,
+        system_clock_hz: u32,
+    ) -> Self {
+        let (int, frac) = divisor(system_clock_hz, CLOCK_HZ);
+        let installed = pio.install(&program()).unwrap();
+        let (mut state, _, tx) = PIOBuilder::from_program(installed)
+            .side_set_pin_base(pin)
+            .buffers(Buffers::OnlyTx)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        state.set_pins([(pin, PinState::Low)]);
+        state.set_pindirs([(pin, PinDir::Output)]);
+
+        Ppm {
+            _sm: state.start(),
+            tx,
+        }
+    }
+
+    /// Queue one frame of `channels`, each in microseconds, padded to
+    /// `frame_us` such as [`FRAME_US`], waiting for FIFO space as needed.
+    ///
+    /// Call this again before the frame has gone out, for an unbroken
+    /// stream. Values are clamped to 800 to 2200 µs.
+    pub fn write_frame(&mut self, channels: &[u16], frame_us: u32) {
+        let mut used = 0;
+        for &channel in channels {
+            let interval = u32::from(channel).clamp(800, 2200);
+            self.push(interval);
+            used += interval;
+        }
+        // The sync gap, at least 4 ms so receivers can tell it apart
+        self.push(frame_us.saturating_sub(used).max(4000));
+    }
+
+    /// Queue a pulse and gap lasting `interval_us` in all.
+    fn push(&mut self, interval_us: u32) {
+        let gap = interval_us - PULSE_US - OVERHEAD;
+        while !self.tx.write(gap) {}
+    }
+}
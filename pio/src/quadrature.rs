@@ -0,0 +1,93 @@
+//! Quadrature encoders, such as motor encoders and rotary knobs.
+//!
+//! The state machine samples the A and B inputs in a five-cycle loop and
+//! pushes their state whenever it changes; [`Quadrature::count`] drains the
+//! FIFO and counts each step forwards or back. The FIFO holds eight changes,
+//! so call it at least eight times per shortest step.
+//!
+//! Mechanical contacts bounce. Each bounce undoes itself in the count, but
+//! it does use the FIFO, so knobs want a sample rate of a few tens of kHz.
+
+use hal::pio::{
+    Buffers, PIOBuilder, PIOExt, PinDir, Running, Rx, ShiftDirection, StateMachine,
+    StateMachineIndex, UninitStateMachine, PIO,
+};
+
+use crate::{divisor, hal, Program};
+
+/// State machine cycles per sample.
+const CYCLES_PER_SAMPLE: u32 = 5;
+
+/// The change in count for each `previous << 2 | current` state, with B in
+/// bit 1 and A in bit 0. Going forwards, A leads: 00, 01, 11, 10.
+const STEPS: [i8; 16] = [0, 1, -1, 0, -1, 0, 0, 1, 1, 0, 0, -1, 0, -1, 1, 0];
+
+/// The program: pushes `B << 1 | A` each time it changes.
+pub fn program() -> Program {
+    pio_proc::pio_asm!(
+        ".wrap_target",
+        "sample:",
+        "    mov isr, null",
+        "    in pins, 2",
+        "    mov x, isr",
+        "    jmp x!=y changed",
+        "    jmp sample",
+        "changed:",
+        "    push noblock",
+        "    mov y, x",
+        ".wrap",
+    )
+    .program
+}
+
+/// A quadrature encoder on a state machine.
+pub struct Quadrature<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    state: u8,
+    count: i32,
+}
+
+impl<P, SM> Quadrature<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Read A from `pin_a` and B from `pin_a + 1`, `sample_rate` times a
+    /// second.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        pin_a: u8,
+        sample_rate: u32,
+        system_clock_hz: u32,
+    ) -> Self {
+        let (int, frac) = divisor(system_clock_hz, sample_rate * CYCLES_PER_SAMPLE);
+        let installed = pio.install(&program()).unwrap();
+        let (mut state, rx, _) = PIOBuilder::from_program(installed)
+            .in_pin_base(pin_a)
+            .in_shift_direction(ShiftDirection::Left)
+            .buffers(Buffers::OnlyRx)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        state.set_pindirs([(pin_a, PinDir::Input), (pin_a + 1, PinDir::Input)]);
+
+        Quadrature {
+            _sm: state.start(),
+            rx,
+            state: 0,
+            count: 0,
+        }
+    }
+
+    /// Steps counted since start-up, four per encoder cycle, positive going
+    /// forwards.
+    pub fn count(&mut self) -> i32 {
+        while let Some(word) = self.rx.read() {
+            let state = word as u8 & 0b11;
+            self.count += i32::from(STEPS[usize::from(self.state << 2 | state)]);
+            self.state = state;
+        }
+        self.count
+    }
+}
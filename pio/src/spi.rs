@@ -0,0 +1,122 @@
+//! SPI controller, mode 0, on any pins.
+//!
+//! Data changes while SCK is low and is sampled on the rising edge, four
+//! cycles per bit, MSB first. Every byte written clocks one in, so
+//! [`Spi::transfer`] and [`Spi::write`] send and receive in step.
+
+use core::convert::Infallible;
+
+use hal::pio::{
+    PIOBuilder, PIOExt, PinDir, PinState, Running, Rx, ShiftDirection, StateMachine,
+    StateMachineIndex, Tx, UninitStateMachine, PIO,
+};
+
+use crate::{divisor, hal, Program};
+
+/// State machine cycles per SCK period.
+const CYCLES_PER_BIT: u32 = 4;
+
+/// The program: one bit out on the falling edge of SCK, one in on the
+/// rising edge. SCK is side-set.
+pub fn program() -> Program {
+    pio_proc::pio_asm!(
+        ".side_set 1",
+        ".wrap_target",
+        "    out pins, 1        side 0 [1]",
+        "    in pins, 1         side 1 [1]",
+        ".wrap",
+    )
+    .program
+}
+
+/// An SPI controller on a state machine.
+pub struct Spi<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    tx: Tx<(P, SM)>,
+}
+
+impl<P, SM> Spi<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Clock the bus at `frequency` Hz.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        sck: u8,
+        mosi: u8,
+        miso: u8,
+        frequency: u32,
+        system_clock_hz: u32,
+    ) -> Self {
+        let (int, frac) = divisor(system_clock_hz, frequency * CYCLES_PER_BIT);
+        let installed = pio.install(&program()).unwrap();
+        let (mut state, rx, tx) = PIOBuilder::from_program(installed)
+            .out_pins(mosi, 1)
+            .in_pin_base(miso)
+            .side_set_pin_base(sck)
+            .out_shift_direction(ShiftDirection::Left)
+            .autopull(true)
+            .pull_threshold(8)
+            .in_shift_direction(ShiftDirection::Left)
+            .autopush(true)
+            .push_threshold(8)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        state.set_pins([(sck, PinState::Low), (mosi, PinState::Low)]);
+        state.set_pindirs([
+            (sck, PinDir::Output),
+            (mosi, PinDir::Output),
+            (miso, PinDir::Input),
+        ]);
+
+        Spi {
+            _sm: state.start(),
+            rx,
+            tx,
+        }
+    }
+
+    /// Send `words`, replacing each with the byte received meanwhile.
+    pub fn transfer<'w>(&mut self, words: &'w mut [u8]) -> &'w [u8] {
+        for word in words.iter_mut() {
+            *word = self.exchange(*word);
+        }
+        words
+    }
+
+    /// Send `words`, dropping what comes back.
+    pub fn write(&mut self, words: &[u8]) {
+        for &word in words {
+            self.exchange(word);
+        }
+    }
+
+    fn exchange(&mut self, word: u8) -> u8 {
+        while !self.tx.write(u32::from(word) << 24) {}
+        loop {
+            if let Some(received) = self.rx.read() {
+                return received as u8;
+            }
+        }
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_hal::blocking::spi::Transfer<u8> for Spi<P, SM> {
+    type Error = Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Infallible> {
+        Ok(Spi::transfer(self, words))
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_hal::blocking::spi::Write<u8> for Spi<P, SM> {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+        Spi::write(self, words);
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+//! UART transmitter and receiver, 8N1, on any pins.
+//!
+//! Both run eight cycles per bit. The transmitter sends one byte per FIFO
+//! word. The receiver waits for a start bit, samples each bit in its middle,
+//! and drops bytes whose stop bit isn't high, such as a break or a baud rate
+//! mismatch.
+//!
+//! A [`UartTx`] and a [`UartRx`] on two state machines make a full duplex
+//! port. They implement `embedded-hal`'s serial traits, so drivers written
+//! for the hardware UARTs work on them too:
+//!
+//! ```ignore
+//! let (mut pio, sm0, sm1, _, _) = pac.PIO0.split(&mut pac.RESETS);
+//! let mut tx = UartTx::new(&mut pio, sm0, 4, 115_200, sys_hz);
+//! let mut rx = UartRx::new(&mut pio, sm1, 5, 115_200, sys_hz);
+//! tx.bwrite_all(b"hello\r\n")?;
+//! let byte = nb::block!(rx.read())?;
+//! ```
+
+use core::convert::Infallible;
+
+use hal::pio::{
+    Buffers, PIOBuilder, PIOExt, PinDir, PinState, Running, Rx, ShiftDirection, StateMachine,
+    StateMachineIndex, Tx, UninitStateMachine, PIO,
+};
+
+use crate::{divisor, hal, registers, Program};
+
+/// State machine cycles per bit.
+const CYCLES_PER_BIT: u32 = 8;
+
+/// Set state machine `SM` of `P` to run at `baud`.
+fn set_clock<P: PIOExt, SM: StateMachineIndex>(baud: u32, system_clock_hz: u32) {
+    let (int, frac) = divisor(system_clock_hz, baud * CYCLES_PER_BIT);
+    registers::<P>().sm[SM::id()]
+        .sm_clkdiv
+        .write(|w| unsafe { w.int().bits(int).frac().bits(frac) });
+}
+
+/// The transmitter: a byte from the low bits of each word, LSB first.
+pub fn tx_program() -> Program {
+    pio_proc::pio_asm!(
+        ".side_set 1 opt",
+        "    pull               side 1 [7]",
+        "    set x, 7           side 0 [7]",
+        "bitloop:",
+        "    out pins, 1",
+        "    jmp x-- bitloop           [6]",
+    )
+    .program
+}
+
+/// The receiver: pushes each byte in the top bits of a word.
+pub fn rx_program() -> Program {
+    pio_proc::pio_asm!(
+        "start:",
+        "    wait 0 pin 0",
+        "    set x, 7                  [10]",
+        "bitloop:",
+        "    in pins, 1",
+        "    jmp x-- bitloop           [6]",
+        "    jmp pin good_stop",
+        // A framing error: drop the byte and wait for the line to go idle
+        "    mov isr, null",
+        "    wait 1 pin 0",
+        "    jmp start",
+        "good_stop:",
+        "    push",
+    )
+    .program
+}
+
+/// A UART transmitter on a state machine.
+pub struct UartTx<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    tx: Tx<(P, SM)>,
+}
+
+impl<P, SM> UartTx<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Transmit on `pin` at `baud`.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        pin: u8,
+        baud: u32,
+        system_clock_hz: u32,
+    ) -> Self {
+        let (int, frac) = divisor(system_clock_hz, baud * CYCLES_PER_BIT);
+        let installed = pio.install(&tx_program()).unwrap();
+        let (mut state, _, tx) = PIOBuilder::from_program(installed)
+            .out_pins(pin, 1)
+            .side_set_pin_base(pin)
+            .out_shift_direction(ShiftDirection::Right)
+            .buffers(Buffers::OnlyTx)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        // Idle high
+        state.set_pins([(pin, PinState::High)]);
+        state.set_pindirs([(pin, PinDir::Output)]);
+
+        UartTx {
+            _sm: state.start(),
+            tx,
+        }
+    }
+
+    /// Change to `baud`, taking effect from the next bit.
+    pub fn set_baud(&mut self, baud: u32, system_clock_hz: u32) {
+        set_clock::<P, SM>(baud, system_clock_hz);
+    }
+
+    /// Queue `byte` for sending, returning `false` if the FIFO is full.
+    pub fn write(&mut self, byte: u8) -> bool {
+        if !self.tx.write(u32::from(byte)) {
+            return false;
+        }
+        // The state machine stalls again once this byte is out
+        let stall = 1 << SM::id();
+        registers::<P>()
+            .fdebug
+            .write(|w| unsafe { w.txstall().bits(stall) });
+        true
+    }
+
+    /// Whether everything queued has been sent, up to the last stop bit,
+    /// which is still going out.
+    pub fn is_idle(&self) -> bool {
+        let stall = 1 << SM::id();
+        self.tx.is_empty() && registers::<P>().fdebug.read().txstall().bits() & stall != 0
+    }
+
+    /// Queue all of `bytes`, waiting for FIFO space as needed.
+    pub fn write_all(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            while !self.write(byte) {}
+        }
+    }
+}
+
+/// A UART receiver on a state machine.
+pub struct UartRx<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+}
+
+impl<P, SM> UartRx<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Receive on `pin` at `baud`, with the pin's pull-up on so an
+    /// unconnected line idles high.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        pin: u8,
+        baud: u32,
+        system_clock_hz: u32,
+    ) -> Self {
+        let pads = unsafe { &*hal::pac::PADS_BANK0::ptr() };
+        pads.gpio[usize::from(pin)].modify(|_, w| w.pue().set_bit().pde().clear_bit());
+
+        let (int, frac) = divisor(system_clock_hz, baud * CYCLES_PER_BIT);
+        let installed = pio.install(&rx_program()).unwrap();
+        let (mut state, rx, _) = PIOBuilder::from_program(installed)
+            .in_pin_base(pin)
+            .jmp_pin(pin)
+            .in_shift_direction(ShiftDirection::Right)
+            .buffers(Buffers::OnlyRx)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        state.set_pindirs([(pin, PinDir::Input)]);
+
+        UartRx {
+            _sm: state.start(),
+            rx,
+        }
+    }
+
+    /// Change to `baud`, taking effect from the next bit.
+    pub fn set_baud(&mut self, baud: u32, system_clock_hz: u32) {
+        set_clock::<P, SM>(baud, system_clock_hz);
+    }
+
+    /// The next byte received, if any.
+    pub fn read(&mut self) -> Option<u8> {
+        self.rx.read().map(|word| (word >> 24) as u8)
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_hal::serial::Write<u8> for UartTx<P, SM> {
+    type Error = Infallible;
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        if UartTx::write(self, byte) {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        if self.is_idle() {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_hal::blocking::serial::write::Default<u8>
+    for UartTx<P, SM>
+{
+}
+
+impl<P: PIOExt, SM: StateMachineIndex> embedded_hal::serial::Read<u8> for UartRx<P, SM> {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Infallible> {
+        UartRx::read(self).ok_or(nb::Error::WouldBlock)
+    }
+}
@@ -0,0 +1,162 @@
+//! HC-SR04 and similar ultrasonic rangefinders.
+//!
+//! The state machine runs at 2 MHz. For each measurement it sends the 10 µs
+//! trigger pulse, waits for the echo to go high, and counts down once a
+//! microsecond for as long as it stays high, so the echo is timed to the
+//! microsecond however busy the CPU is. The count stops at the limit it was
+//! given, for sensors that hold the echo high long after anything in range
+//! would have answered.
+//!
+//! With no sensor, the state machine waits for ever for the echo;
+//! [`Ultrasonic::start`] abandons any measurement still going, so time
+//! measurements out and start again. [`Ultrasonic::measure`] does all of
+//! that, waiting for the result:
+//!
+//! ```ignore
+//! let mut sensor = Ultrasonic::new(&mut pio, sm0, 2, 3, sys_hz);
+//! let echo_us = sensor.measure(MAX_ECHO_US, &mut delay)?;
+//! ```
+//!
+//! The echo, which swings to 5 V on an HC-SR04, needs a divider to feed a
+//! GPIO.
+
+use embedded_hal::blocking::delay::DelayUs;
+use hal::pio::{
+    PIOBuilder, PIOExt, PinDir, PinState, Running, Rx, StateMachine, StateMachineIndex, Tx,
+    UninitStateMachine, PIO,
+};
+
+use crate::{divisor, hal, registers, Program};
+
+/// State machine clock, two cycles per microsecond.
+const CLOCK_HZ: u32 = 2_000_000;
+
+/// How often [`Ultrasonic::measure`] checks for the result, in
+/// microseconds.
+const POLL_US: u32 = 500;
+
+/// Time allowed for the echo to start after the trigger, in microseconds.
+/// The sensor sends its burst first, which takes about 200 µs.
+const START_US: u32 = 2_000;
+
+/// `set pins, 0`: ends the trigger pulse.
+const TRIGGER_LOW: u32 = 0xE000;
+
+/// Errors from [`Ultrasonic::measure`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The echo never started: no sensor, or it's wired wrong.
+    NoEcho,
+    /// The echo lasted longer than the limit, so nothing is in range.
+    OutOfRange,
+}
+
+/// The program. Takes the longest echo to time, in µs; triggers the sensor
+/// and pushes the µs left of that when the echo ended, or all ones if it
+/// hadn't.
+pub fn program() -> Program {
+    pio_proc::pio_asm!(
+        ".wrap_target",
+        "    pull block",
+        "    set pins, 1       [19] ; the 10 µs trigger pulse",
+        "    set pins, 0",
+        "    mov x, osr",
+        "    wait 1 pin 0",
+        "high:",
+        "    jmp pin count              ; two cycles a loop",
+        "    jmp done",
+        "count:",
+        "    jmp x-- high",
+        "done:",
+        "    mov isr, x",
+        "    push",
+        ".wrap",
+    )
+    .program
+}
+
+/// A rangefinder on a state machine.
+pub struct Ultrasonic<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    rx: Rx<(P, SM)>,
+    tx: Tx<(P, SM)>,
+    /// The limit given to the measurement in progress.
+    limit_us: u32,
+}
+
+impl<P, SM> Ultrasonic<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Talk to the sensor with its trigger on `trigger` and its echo on
+    /// `echo`.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        trigger: u8,
+        echo: u8,
+        system_clock_hz: u32,
+    ) -> Self {
+        let (int, frac) = divisor(system_clock_hz, CLOCK_HZ);
+        let installed = pio.install(&program()).unwrap();
+        let (mut state, rx, tx) = PIOBuilder::from_program(installed)
+            .set_pins(trigger, 1)
+            .in_pin_base(echo)
+            .jmp_pin(echo)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        state.set_pins([(trigger, PinState::Low)]);
+        state.set_pindirs([(trigger, PinDir::Output), (echo, PinDir::Input)]);
+
+        Ultrasonic {
+            _sm: state.start(),
+            rx,
+            tx,
+            limit_us: 0,
+        }
+    }
+
+    /// Trigger a measurement timing echoes up to `limit_us`, abandoning any
+    /// measurement still going.
+    ///
+    /// The sensor needs about 60 ms between measurements.
+    pub fn start(&mut self, limit_us: u32) {
+        let regs = registers::<P>();
+        let sm = &regs.sm[SM::id()];
+        // Back to the `pull`, with the trigger low
+        sm.sm_instr.write(|w| unsafe { w.bits(TRIGGER_LOW) });
+        let wrap_bottom = sm.sm_execctrl.read().wrap_bottom().bits();
+        sm.sm_instr
+            .write(|w| unsafe { w.bits(u32::from(wrap_bottom)) });
+        while self.rx.read().is_some() {}
+
+        self.limit_us = limit_us.max(1);
+        self.tx.write(self.limit_us);
+    }
+
+    /// The echo's length in microseconds, once it has ended, or
+    /// [`Error::OutOfRange`] once it has passed the limit.
+    pub fn read(&mut self) -> Option<Result<u32, Error>> {
+        let left = self.rx.read()?;
+        Some(if left > self.limit_us {
+            Err(Error::OutOfRange)
+        } else {
+            Ok(self.limit_us - left)
+        })
+    }
+
+    /// Measure an echo of up to `limit_us`, such as
+    /// `rp_hal_boards_common::ultrasonic::MAX_ECHO_US`, waiting for it with
+    /// `delay`.
+    pub fn measure<D: DelayUs<u32>>(&mut self, limit_us: u32, delay: &mut D) -> Result<u32, Error> {
+        self.start(limit_us);
+        for _ in 0..(START_US + self.limit_us) / POLL_US + 1 {
+            delay.delay_us(POLL_US);
+            if let Some(result) = self.read() {
+                return result;
+            }
+        }
+        Err(Error::NoEcho)
+    }
+}
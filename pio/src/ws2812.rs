@@ -0,0 +1,85 @@
+//! WS2812 / NeoPixel LEDs.
+//!
+//! Every bit takes ten cycles at 8 MHz, 1.25 µs: high for the first two or
+//! seven cycles for a 0 or a 1, low for the rest. Colours are queued in the
+//! FIFO, so [`Ws2812::write`] returns as soon as the last one fits.
+//!
+//! The LEDs latch once the line has been low for 300 µs, so leave at least
+//! that long between frames.
+
+use hal::pio::{
+    Buffers, PIOBuilder, PIOExt, PinDir, PinState, Running, ShiftDirection, StateMachine,
+    StateMachineIndex, Tx, UninitStateMachine, PIO,
+};
+
+use crate::{divisor, hal, Program};
+
+/// State machine clock: ten cycles per 800 kHz bit.
+const CLOCK_HZ: u32 = 8_000_000;
+
+/// The program: 24 bit colours, MSB first, left-aligned in each word.
+pub fn program() -> Program {
+    pio_proc::pio_asm!(
+        ".side_set 1",
+        ".wrap_target",
+        "bitloop:",
+        "    out x, 1           side 0 [2]",
+        "    jmp !x do_zero     side 1 [1]",
+        "do_one:",
+        "    jmp bitloop        side 1 [4]",
+        "do_zero:",
+        "    nop                side 0 [4]",
+        ".wrap",
+    )
+    .program
+}
+
+/// A chain of WS2812 LEDs on one pin.
+pub struct Ws2812<P: PIOExt, SM: StateMachineIndex> {
+    _sm: StateMachine<(P, SM), Running>,
+    tx: Tx<(P, SM)>,
+}
+
+impl<P, SM> Ws2812<P, SM>
+where
+    P: PIOExt,
+    SM: StateMachineIndex,
+{
+    /// Drive the LEDs' data input from `pin`.
+    pub fn new(
+        pio: &mut PIO<P>,
+        sm: UninitStateMachine<(P, SM)>,
+        pin: u8,
+        system_clock_hz: u32,
+    ) -> Self {
+        let (int, frac) = divisor(system_clock_hz, CLOCK_HZ);
+        let installed = pio.install(&program()).unwrap();
+        let (mut state, _, tx) = PIOBuilder::from_program(installed)
+            .side_set_pin_base(pin)
+            .out_shift_direction(ShiftDirection::Left)
+            .autopull(true)
+            .pull_threshold(24)
+            .buffers(Buffers::OnlyTx)
+            .clock_divisor_fixed_point(int, frac)
+            .build(sm);
+        state.set_pins([(pin, PinState::Low)]);
+        state.set_pindirs([(pin, PinDir::Output)]);
+
+        Ws2812 {
+            _sm: state.start(),
+            tx,
+        }
+    }
+
+    /// Send one frame of `(red, green, blue)` colours, waiting for FIFO space
+    /// as needed.
+    pub fn write<I>(&mut self, colours: I)
+    where
+        I: IntoIterator<Item = (u8, u8, u8)>,
+    {
+        for (r, g, b) in colours {
+            let grb = u32::from(g) << 24 | u32::from(r) << 16 | u32::from(b) << 8;
+            while !self.tx.write(grb) {}
+        }
+    }
+}